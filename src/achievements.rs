@@ -0,0 +1,386 @@
+//! Achievements: milestones unlocked once and kept across every run
+//!
+//! Each achievement is checked against the game after every turn and when
+//! a run ends; the first time one holds it's unlocked for good, with the
+//! day it happened. Unlocks belong to the player rather than to any one
+//! save, so the desktop front-ends keep them as JSON in the data directory
+//! and the web version keeps them in `localStorage`. Unreadable unlocks
+//! start over empty, like the hall of fame.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::character::ClassType;
+use crate::game::{Game, GameState};
+
+/// What a locked achievement shows in place of its name
+pub const LOCKED_NAME: &str = "???";
+
+/// A milestone and the test of whether a game has reached it
+pub struct Achievement {
+    /// Key the unlock is stored under; never changes once released
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    earned: fn(&Game) -> bool,
+}
+
+impl Achievement {
+    pub fn is_earned(&self, game: &Game) -> bool {
+        (self.earned)(game)
+    }
+}
+
+fn won(game: &Game) -> bool {
+    matches!(game.game_state, GameState::Victory)
+}
+
+fn won_as(game: &Game, class: ClassType) -> bool {
+    won(game) && game.player.class.class_type == class
+}
+
+/// Every achievement, in the order the Achievements screen lists them
+pub const ACHIEVEMENTS: [Achievement; 20] = [
+    Achievement {
+        id: "first_blood",
+        name: "First Blood",
+        description: "Slay your first enemy.",
+        earned: |game| game.stats.total_kills() >= 1,
+    },
+    Achievement {
+        id: "slayer",
+        name: "Slayer",
+        description: "Slay 25 enemies in one run.",
+        earned: |game| game.stats.total_kills() >= 25,
+    },
+    Achievement {
+        id: "centurion",
+        name: "Centurion",
+        description: "Slay 100 enemies in one run.",
+        earned: |game| game.stats.total_kills() >= 100,
+    },
+    Achievement {
+        id: "heavy_hitter",
+        name: "Heavy Hitter",
+        description: "Deal 1000 damage in one run.",
+        earned: |game| game.stats.damage_dealt >= 1000,
+    },
+    Achievement {
+        id: "delver",
+        name: "Delver",
+        description: "Reach dungeon level 5.",
+        earned: |game| game.stats.deepest_level >= 5,
+    },
+    Achievement {
+        id: "into_the_abyss",
+        name: "Into the Abyss",
+        description: "Reach dungeon level 10.",
+        earned: |game| game.stats.deepest_level >= 10,
+    },
+    Achievement {
+        id: "seasoned",
+        name: "Seasoned",
+        description: "Reach character level 5.",
+        earned: |game| game.player.level >= 5,
+    },
+    Achievement {
+        id: "legend",
+        name: "Legend",
+        description: "Reach character level 10.",
+        earned: |game| game.player.level >= 10,
+    },
+    Achievement {
+        id: "pocket_change",
+        name: "Pocket Change",
+        description: "Carry 100 gold.",
+        earned: |game| game.player.gold >= 100,
+    },
+    Achievement {
+        id: "hoarder",
+        name: "Dragon's Hoard",
+        description: "Carry 1000 gold.",
+        earned: |game| game.player.gold >= 1000,
+    },
+    Achievement {
+        id: "treasure_hunter",
+        name: "Treasure Hunter",
+        description: "Loot 50 items in one run.",
+        earned: |game| game.stats.items_looted >= 50,
+    },
+    Achievement {
+        id: "marathon",
+        name: "Marathon",
+        description: "Last 1000 turns in one run.",
+        earned: |game| game.stats.turns >= 1000,
+    },
+    Achievement {
+        id: "thick_skinned",
+        name: "Thick Skinned",
+        description: "Take 500 damage in one run and live.",
+        earned: |game| {
+            game.stats.damage_taken >= 500 && !matches!(game.game_state, GameState::GameOver)
+        },
+    },
+    Achievement {
+        id: "short_story",
+        name: "A Short Story",
+        description: "Die on the very first level.",
+        earned: |game| {
+            matches!(game.game_state, GameState::GameOver)
+                && game.current_dungeon_index == 0
+                && game.stats.deepest_level == 1
+        },
+    },
+    Achievement {
+        id: "teetotaller",
+        name: "Teetotaller",
+        description: "Win a run without drinking a single potion.",
+        earned: |game| won(game) && game.stats.potions_drunk == 0,
+    },
+    Achievement {
+        id: "warrior_victory",
+        name: "Warlord",
+        description: "Win as a Warrior.",
+        earned: |game| won_as(game, ClassType::Warrior),
+    },
+    Achievement {
+        id: "mage_victory",
+        name: "Archmage",
+        description: "Win as a Mage.",
+        earned: |game| won_as(game, ClassType::Mage),
+    },
+    Achievement {
+        id: "ranger_victory",
+        name: "Pathfinder",
+        description: "Win as a Ranger.",
+        earned: |game| won_as(game, ClassType::Ranger),
+    },
+    Achievement {
+        id: "cleric_victory",
+        name: "Saint",
+        description: "Win as a Cleric.",
+        earned: |game| won_as(game, ClassType::Cleric),
+    },
+    Achievement {
+        id: "once_more",
+        name: "Once More, With Feeling",
+        description: "Carry a character on into New Game+.",
+        earned: |game| game.ng_plus_counter >= 1,
+    },
+];
+
+/// The achievements unlocked so far, with the day each one was
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AchievementProfile {
+    /// Day of each unlock, as YYYY-MM-DD, by achievement id
+    unlocked: BTreeMap<String, String>,
+}
+
+impl AchievementProfile {
+    /// Parses stored unlocks; anything unreadable starts a fresh profile
+    pub fn from_json(json: &str) -> Self {
+        serde_json::from_str(json).unwrap_or_default()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// The day `achievement` was unlocked, if it has been
+    pub fn unlocked_on(&self, achievement: &Achievement) -> Option<&str> {
+        self.unlocked.get(achievement.id).map(String::as_str)
+    }
+
+    /// Unlocks, as of `date`, every achievement `game` has earned that
+    /// wasn't already, and returns them
+    pub fn unlock_earned(&mut self, game: &Game, date: &str) -> Vec<&'static Achievement> {
+        let mut unlocked = Vec::new();
+        for achievement in &ACHIEVEMENTS {
+            if self.unlocked_on(achievement).is_none() && achievement.is_earned(game) {
+                self.unlocked
+                    .insert(achievement.id.to_string(), date.to_string());
+                unlocked.push(achievement);
+            }
+        }
+        unlocked
+    }
+
+    /// "7 of 20 unlocked"
+    pub fn summary(&self) -> String {
+        let count = ACHIEVEMENTS
+            .iter()
+            .filter(|achievement| self.unlocked_on(achievement).is_some())
+            .count();
+        format!("{count} of {} unlocked", ACHIEVEMENTS.len())
+    }
+
+    /// Text rows for the Achievements screen, each with whether it's
+    /// unlocked; a locked one keeps its name hidden but gives its hint
+    pub fn rows(&self) -> Vec<(String, bool)> {
+        ACHIEVEMENTS
+            .iter()
+            .map(|achievement| match self.unlocked_on(achievement) {
+                Some(date) => (
+                    format!(
+                        "{:<24} {} ({date})",
+                        achievement.name, achievement.description
+                    ),
+                    true,
+                ),
+                None => (
+                    format!("{LOCKED_NAME:<24} {}", achievement.description),
+                    false,
+                ),
+            })
+            .collect()
+    }
+}
+
+/// The achievements file kept next to the save
+#[cfg(not(target_arch = "wasm32"))]
+pub struct AchievementsFile {
+    path: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AchievementsFile {
+    const FILE_NAME: &'static str = "achievements.json";
+
+    /// Uses the achievements file inside `dir`
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> Self {
+        AchievementsFile {
+            path: dir.into().join(Self::FILE_NAME),
+        }
+    }
+
+    /// Reads the unlocks, or none if the file is missing or corrupt
+    pub fn load(&self) -> AchievementProfile {
+        std::fs::read_to_string(&self.path)
+            .map(|json| AchievementProfile::from_json(&json))
+            .unwrap_or_default()
+    }
+
+    /// Writes `profile` to the file
+    pub fn save(&self, profile: &AchievementProfile) -> anyhow::Result<()> {
+        crate::save::write_atomically(&self.path, profile.to_json())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl crate::save::DataFile for AchievementsFile {
+    fn in_dir(dir: std::path::PathBuf) -> Self {
+        Self::new(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::Player;
+    use crate::inventory::InventoryManager;
+    use crate::item::{Consumable, Item};
+    #[cfg(not(target_arch = "wasm32"))]
+    use crate::save::TempDir;
+    use std::collections::HashSet;
+
+    fn test_game(class: ClassType) -> Game {
+        let mut game = Game::new(Player::new("Tester".to_string(), class));
+        game.game_state = GameState::Playing;
+        game
+    }
+
+    fn ids(unlocked: &[&Achievement]) -> Vec<&'static str> {
+        unlocked.iter().map(|achievement| achievement.id).collect()
+    }
+
+    #[test]
+    fn test_achievement_ids_are_unique() {
+        let ids: HashSet<&str> = ACHIEVEMENTS.iter().map(|a| a.id).collect();
+        assert_eq!(ids.len(), ACHIEVEMENTS.len());
+    }
+
+    #[test]
+    fn test_a_run_ending_unlocks_what_it_earned() {
+        let mut profile = AchievementProfile::default();
+        let mut game = test_game(ClassType::Mage);
+        assert!(profile.unlock_earned(&game, "2024-06-01").is_empty());
+
+        // Winning is only checked once the run is over
+        game.stats.potions_drunk = 1;
+        game.game_state = GameState::Victory;
+        assert_eq!(
+            ids(&profile.unlock_earned(&game, "2024-06-01")),
+            ["mage_victory"]
+        );
+
+        // Dying on the first level of a fresh run
+        let mut game = test_game(ClassType::Warrior);
+        game.game_state = GameState::GameOver;
+        assert_eq!(
+            ids(&profile.unlock_earned(&game, "2024-06-02")),
+            ["short_story"]
+        );
+        assert_eq!(profile.summary(), "2 of 20 unlocked");
+    }
+
+    #[test]
+    fn test_lighting_torches_and_reading_scrolls_is_still_teetotal() {
+        let mut game = test_game(ClassType::Cleric);
+        for item in [Consumable::torch(), Consumable::health_potion(20)] {
+            InventoryManager::add_item(&mut game.player, Item::Consumable(item));
+        }
+        let torch = InventoryManager::get_item_count(&game.player) - 2;
+        assert!(game.use_item(torch).consumed);
+        game.game_state = GameState::Victory;
+        let mut profile = AchievementProfile::default();
+        assert!(ids(&profile.unlock_earned(&game, "2024-06-01")).contains(&"teetotaller"));
+
+        // The potion puts it out of reach
+        let potion = InventoryManager::get_item_count(&game.player) - 1;
+        assert!(game.use_item(potion).consumed);
+        let mut profile = AchievementProfile::default();
+        assert!(!ids(&profile.unlock_earned(&game, "2024-06-01")).contains(&"teetotaller"));
+    }
+
+    #[test]
+    fn test_rows_hide_the_names_of_locked_achievements() {
+        let mut profile = AchievementProfile::default();
+        let mut game = test_game(ClassType::Warrior);
+        game.player.gold = 150;
+        profile.unlock_earned(&game, "2024-06-01");
+
+        let rows = profile.rows();
+        assert_eq!(rows.len(), ACHIEVEMENTS.len());
+        let (pocket_change, unlocked) = &rows[8];
+        assert!(unlocked);
+        assert!(pocket_change.starts_with("Pocket Change"));
+        assert!(pocket_change.ends_with("(2024-06-01)"));
+        let (first_blood, unlocked) = &rows[0];
+        assert!(!unlocked);
+        assert!(first_blood.starts_with(LOCKED_NAME));
+        assert!(first_blood.ends_with("Slay your first enemy."));
+    }
+
+    #[test]
+    fn test_corrupt_json_starts_fresh() {
+        assert_eq!(
+            AchievementProfile::from_json("{broken"),
+            AchievementProfile::default()
+        );
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_file_round_trip() {
+        let dir = TempDir::new("achievements");
+        let file = AchievementsFile::new(dir.path());
+        assert_eq!(file.load(), AchievementProfile::default());
+
+        let mut profile = AchievementProfile::default();
+        let mut game = test_game(ClassType::Ranger);
+        game.player.gold = 100;
+        profile.unlock_earned(&game, "2024-06-01");
+        file.save(&profile).unwrap();
+        assert_eq!(file.load(), profile);
+    }
+}
@@ -0,0 +1,237 @@
+//! Sound effects for key game events.
+//!
+//! The game queues a `SoundEvent` whenever something worth hearing happens
+//! and the desktop front-ends hand them to an `AudioManager`. Sounds are
+//! short synthesized tones, so there are no asset files to ship. Without the
+//! `audio` feature, or when no output device can be opened (headless CI, a
+//! machine without speakers), the manager quietly does nothing.
+
+/// Something that happened in the game and has a sound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundEvent {
+    /// The player's attack landed
+    Hit,
+    /// An enemy's attack landed
+    Hurt,
+    LevelUp,
+    ItemPickup,
+    ChestOpen,
+    Stairs,
+    /// The player walked into a wall
+    Bump,
+    Death,
+    Victory,
+}
+
+#[cfg(feature = "audio")]
+impl SoundEvent {
+    /// The tones that make up this sound, as (frequency in Hz, milliseconds)
+    fn notes(&self) -> &'static [(f32, u64)] {
+        match self {
+            SoundEvent::Hit => &[(220.0, 60)],
+            SoundEvent::Hurt => &[(110.0, 90)],
+            SoundEvent::LevelUp => &[(523.3, 90), (659.3, 90), (784.0, 160)],
+            SoundEvent::ItemPickup => &[(880.0, 50), (1174.7, 70)],
+            SoundEvent::ChestOpen => &[(392.0, 70), (587.3, 70), (784.0, 110)],
+            SoundEvent::Stairs => &[(330.0, 80), (247.0, 80), (196.0, 120)],
+            SoundEvent::Bump => &[(82.4, 40)],
+            SoundEvent::Death => &[(196.0, 200), (164.8, 200), (130.8, 400)],
+            SoundEvent::Victory => &[(523.3, 120), (659.3, 120), (784.0, 120), (1046.5, 360)],
+        }
+    }
+}
+
+/// Sound effect volume, cycled at runtime with the 'v' key and kept in the
+/// settings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Volume {
+    Muted,
+    Low,
+    #[default]
+    High,
+}
+
+impl Volume {
+    pub const ALL: [Volume; 3] = [Volume::Muted, Volume::Low, Volume::High];
+
+    /// Name stored in the settings file
+    pub fn name(self) -> &'static str {
+        match self {
+            Volume::Muted => "muted",
+            Volume::Low => "low",
+            Volume::High => "high",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|volume| volume.name().eq_ignore_ascii_case(name))
+    }
+}
+
+#[cfg(feature = "audio")]
+impl Volume {
+    /// The next setting in the mute/low/high cycle
+    pub fn next(&self) -> Self {
+        match self {
+            Volume::Muted => Volume::Low,
+            Volume::Low => Volume::High,
+            Volume::High => Volume::Muted,
+        }
+    }
+
+    /// Gain applied to every sound
+    fn gain(&self) -> f32 {
+        match self {
+            Volume::Muted => 0.0,
+            Volume::Low => 0.15,
+            Volume::High => 0.4,
+        }
+    }
+}
+
+#[cfg(feature = "audio")]
+impl std::fmt::Display for Volume {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Volume::Muted => write!(f, "Muted"),
+            Volume::Low => write!(f, "Low"),
+            Volume::High => write!(f, "High"),
+        }
+    }
+}
+
+/// An open output device. The stream must stay alive for sounds to play.
+#[cfg(feature = "audio")]
+struct Output {
+    _stream: rodio::OutputStream,
+    handle: rodio::OutputStreamHandle,
+}
+
+/// Plays sound effects, or does nothing when sound is unavailable
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+pub struct AudioManager {
+    #[cfg(feature = "audio")]
+    output: Option<Output>,
+    #[cfg(feature = "audio")]
+    volume: Volume,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AudioManager {
+    /// Opens the default output device, if there is one
+    pub fn new() -> Self {
+        #[cfg(feature = "audio")]
+        {
+            let output = rodio::OutputStream::try_default()
+                .ok()
+                .map(|(_stream, handle)| Output { _stream, handle });
+            Self {
+                output,
+                volume: Volume::default(),
+            }
+        }
+
+        #[cfg(not(feature = "audio"))]
+        Self::default()
+    }
+
+    /// Starts playing the sound for `event` without waiting for it to finish
+    #[cfg_attr(not(feature = "audio"), allow(unused_variables))]
+    pub fn play(&self, event: SoundEvent) {
+        #[cfg(feature = "audio")]
+        {
+            use rodio::Source;
+            use std::time::Duration;
+
+            if self.volume == Volume::Muted {
+                return;
+            }
+            let Some(output) = &self.output else {
+                return;
+            };
+            let Ok(sink) = rodio::Sink::try_new(&output.handle) else {
+                return;
+            };
+
+            sink.set_volume(self.volume.gain());
+            for &(frequency, millis) in event.notes() {
+                sink.append(
+                    rodio::source::SineWave::new(frequency)
+                        .take_duration(Duration::from_millis(millis)),
+                );
+            }
+            sink.detach();
+        }
+    }
+
+    /// Plays every sound in order, as queued by the game
+    pub fn play_all(&self, events: impl IntoIterator<Item = SoundEvent>) {
+        for event in events {
+            self.play(event);
+        }
+    }
+
+    /// Plays from now on at `volume`, as the settings keep it
+    #[cfg_attr(not(feature = "audio"), allow(unused_variables))]
+    pub fn set_volume(&mut self, volume: Volume) {
+        #[cfg(feature = "audio")]
+        {
+            self.volume = volume;
+        }
+    }
+
+    /// Moves to the next volume setting and returns it
+    #[cfg(feature = "audio")]
+    pub fn cycle_volume(&mut self) -> Volume {
+        self.volume = self.volume.next();
+        self.volume
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playing_never_panics_without_a_device() {
+        let mut audio = AudioManager::default();
+        audio.play(SoundEvent::Hit);
+        audio.play_all([SoundEvent::LevelUp, SoundEvent::Death]);
+
+        // Opening the real device either works or falls back to silence
+        audio = AudioManager::new();
+        audio.play(SoundEvent::Victory);
+    }
+
+    #[cfg(feature = "audio")]
+    #[test]
+    fn test_volume_cycles_through_mute_low_high() {
+        let mut audio = AudioManager::default();
+        assert_eq!(audio.cycle_volume(), Volume::Muted);
+        assert_eq!(audio.cycle_volume(), Volume::Low);
+        assert_eq!(audio.cycle_volume(), Volume::High);
+        assert!(Volume::Muted.gain() < Volume::Low.gain());
+        assert!(Volume::Low.gain() < Volume::High.gain());
+    }
+
+    #[cfg(feature = "audio")]
+    #[test]
+    fn test_every_event_has_a_sound() {
+        let events = [
+            SoundEvent::Hit,
+            SoundEvent::Hurt,
+            SoundEvent::LevelUp,
+            SoundEvent::ItemPickup,
+            SoundEvent::ChestOpen,
+            SoundEvent::Stairs,
+            SoundEvent::Death,
+            SoundEvent::Victory,
+        ];
+        for event in events {
+            assert!(!event.notes().is_empty(), "{event:?} is silent");
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Base64 for build codes and the terminal clipboard
+//!
+//! Build codes use the URL-safe alphabet without padding, so they survive
+//! being pasted into chats and links. Terminals expect the standard,
+//! padded alphabet for clipboard escape sequences.
+
+const STANDARD: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const URL_SAFE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+fn encode(bytes: &[u8], alphabet: &[u8; 64], pad: bool) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | u32::from(byte) << (16 - 8 * i)
+        });
+        // Three bytes make four characters, and a shorter last chunk one
+        // character more than it has bytes
+        for i in 0..=chunk.len() {
+            encoded.push(alphabet[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+        if pad {
+            for _ in chunk.len()..3 {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Standard padded base64, as terminals read it
+pub fn encode_standard(bytes: &[u8]) -> String {
+    encode(bytes, STANDARD, true)
+}
+
+/// URL-safe base64 without padding
+pub fn encode_url_safe(bytes: &[u8]) -> String {
+    encode(bytes, URL_SAFE, false)
+}
+
+/// Reads URL-safe base64 without padding. Fails with the first character
+/// that isn't part of the alphabet, or None when the text ends part way
+/// through a byte.
+pub fn decode_url_safe(text: &str) -> Result<Vec<u8>, Option<char>> {
+    let mut values = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        let value = URL_SAFE
+            .iter()
+            .position(|&symbol| symbol as char == c)
+            .ok_or(Some(c))?;
+        values.push(value as u32);
+    }
+    if values.len() % 4 == 1 {
+        return Err(None);
+    }
+
+    let mut bytes = Vec::with_capacity(values.len() * 3 / 4);
+    for chunk in values.chunks(4) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (i, &value)| group | value << (18 - 6 * i));
+        for i in 0..chunk.len() - 1 {
+            bytes.push((group >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encodings_match_the_standard_vectors() {
+        let cases = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (plain, encoded) in cases {
+            assert_eq!(encode_standard(plain.as_bytes()), encoded);
+            let url_safe = encode_url_safe(plain.as_bytes());
+            assert_eq!(url_safe, encoded.trim_end_matches('='));
+            assert_eq!(decode_url_safe(&url_safe).unwrap(), plain.as_bytes());
+        }
+        assert_eq!(encode_url_safe(&[0xfb, 0xff]), "-_8");
+    }
+
+    #[test]
+    fn test_decoding_rejects_foreign_characters_and_stray_endings() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(decode_url_safe(&encode_url_safe(&bytes)).unwrap(), bytes);
+
+        assert_eq!(decode_url_safe("Zm9v+g"), Err(Some('+')));
+        assert_eq!(decode_url_safe("Zm9vY"), Err(None));
+    }
+}
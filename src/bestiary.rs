@@ -0,0 +1,205 @@
+//! What the player has learned about the enemies they've fought
+//!
+//! Enemies are recorded by type, so every goblin shares an entry whatever
+//! its name. Their attack and defense stay hidden until enough of them have
+//! been slain to know them. Pack leaders count towards their type, but the
+//! attack and defense shown are those of the rank and file.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::combat::{CombatResult, Combatant};
+use crate::world::Enemy;
+
+/// Kills of an enemy type before its attack and defense are shown
+pub const IDENTIFY_KILLS: u32 = 3;
+
+/// Everything known about one type of enemy
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BestiaryEntry {
+    /// Fights it has been in, counting each enemy of the type
+    pub encountered: u32,
+    pub killed: u32,
+    /// Most damage one of them has done the player in a single blow
+    pub max_damage_taken: i32,
+    /// Attack of the last one met
+    pub attack: i32,
+    /// Defense of the last one met
+    pub defense: i32,
+    /// How far they shoot, for those that fight from a distance
+    #[serde(default)]
+    pub range: Option<i32>,
+    /// Pack leaders of the type slain
+    #[serde(default)]
+    pub leaders_killed: u32,
+}
+
+impl BestiaryEntry {
+    /// Whether enough have been slain to show the exact stats
+    pub fn is_identified(&self) -> bool {
+        self.killed >= IDENTIFY_KILLS
+    }
+
+    /// "ATK 9  DEF 4", or question marks until identified; the range of
+    /// an enemy that shoots shows from the first meeting, as it's plain to see
+    pub fn stats_text(&self) -> String {
+        let stats = if self.is_identified() {
+            format!("ATK {}  DEF {}", self.attack, self.defense)
+        } else {
+            "ATK ???  DEF ???".to_string()
+        };
+        let stats = match self.range {
+            Some(range) => format!("{stats}  RNG {range}"),
+            None => stats,
+        };
+        match self.leaders_killed {
+            0 => stats,
+            1 => format!("{stats}  (1 leader slain)"),
+            leaders => format!("{stats}  ({leaders} leaders slain)"),
+        }
+    }
+}
+
+/// The enemies met so far, by type name
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bestiary {
+    entries: HashMap<String, BestiaryEntry>,
+}
+
+impl Bestiary {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn entry(&mut self, enemy: &Enemy) -> &mut BestiaryEntry {
+        let entry = self
+            .entries
+            .entry(enemy.enemy_type.to_string())
+            .or_default();
+        if let Some(ranged) = enemy.ranged {
+            entry.range = Some(ranged.range);
+        }
+        entry
+    }
+
+    /// Notes that a fight with `enemy` started; a leader's stats, raised
+    /// above the rest, and the attack it lends them are left out
+    pub fn record_encounter(&mut self, enemy: &Enemy) {
+        let entry = self.entry(enemy);
+        entry.encountered += 1;
+        if !enemy.leader {
+            entry.attack = enemy.attack_damage() - enemy.rally_bonus();
+            entry.defense = enemy.defense();
+        }
+    }
+
+    pub fn record_kill(&mut self, enemy: &Enemy) {
+        let entry = self.entry(enemy);
+        entry.killed += 1;
+        if enemy.leader {
+            entry.leaders_killed += 1;
+        }
+    }
+
+    /// Notes a blow of `damage` the player took from `enemy`
+    pub fn record_hit(&mut self, enemy: &Enemy, damage: i32) {
+        let entry = self.entry(enemy);
+        entry.max_damage_taken = entry.max_damage_taken.max(damage);
+    }
+
+    /// Records the kills and blows of a combat turn against `enemies`
+    pub fn record_turn(&mut self, result: &CombatResult, enemies: &[Enemy]) {
+        for enemy in result.slain.iter().filter_map(|&i| enemies.get(i)) {
+            self.record_kill(enemy);
+        }
+        for &(index, damage) in &result.damage_taken_from {
+            if let Some(enemy) = enemies.get(index) {
+                self.record_hit(enemy, damage);
+            }
+        }
+    }
+
+    /// Every enemy type met, in alphabetical order
+    pub fn entries(&self) -> Vec<(&str, &BestiaryEntry)> {
+        let mut entries: Vec<(&str, &BestiaryEntry)> = self
+            .entries
+            .iter()
+            .map(|(name, entry)| (name.as_str(), entry))
+            .collect();
+        entries.sort_by_key(|&(name, _)| name);
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::enemy::EnemyType;
+
+    fn lookup<'a>(bestiary: &'a Bestiary, name: &str) -> &'a BestiaryEntry {
+        &bestiary.entries[name]
+    }
+
+    fn goblin() -> Enemy {
+        Enemy::new("Sneaky Goblin".to_string(), EnemyType::Goblin, 2)
+    }
+
+    #[test]
+    fn test_stats_are_identified_after_enough_kills() {
+        let mut bestiary = Bestiary::default();
+        let goblin = goblin();
+        for kills in 1..=IDENTIFY_KILLS {
+            bestiary.record_encounter(&goblin);
+            bestiary.record_kill(&goblin);
+            let entry = lookup(&bestiary, "Goblin");
+            assert_eq!(entry.killed, kills);
+            assert_eq!(entry.is_identified(), kills == IDENTIFY_KILLS);
+        }
+
+        assert_eq!(
+            lookup(&bestiary, "Goblin").stats_text(),
+            format!("ATK {}  DEF {}", goblin.attack_damage(), goblin.defense())
+        );
+        let other = Enemy::new("Brutal Orc".to_string(), EnemyType::Orc, 3);
+        bestiary.record_encounter(&other);
+        assert_eq!(lookup(&bestiary, "Orc").stats_text(), "ATK ???  DEF ???");
+
+        // How far an archer shoots is plain from the first arrow
+        let archer = Enemy::new("Keen Goblin Archer".to_string(), EnemyType::GoblinArcher, 2);
+        bestiary.record_hit(&archer, 4);
+        assert_eq!(
+            lookup(&bestiary, "Goblin Archer").stats_text(),
+            "ATK ???  DEF ???  RNG 6"
+        );
+
+        // A leader's kill is counted, but its raised stats aren't shown
+        let mut leader = goblin.clone();
+        leader.promote_to_leader();
+        bestiary.record_encounter(&leader);
+        bestiary.record_kill(&leader);
+        assert_eq!(
+            lookup(&bestiary, "Goblin").stats_text(),
+            format!(
+                "ATK {}  DEF {}  (1 leader slain)",
+                goblin.attack_damage(),
+                goblin.defense()
+            )
+        );
+    }
+
+    #[test]
+    fn test_worst_hit_is_kept_and_the_bestiary_round_trips() {
+        let mut bestiary = Bestiary::default();
+        let goblin = goblin();
+        bestiary.record_encounter(&goblin);
+        bestiary.record_hit(&goblin, 7);
+        bestiary.record_hit(&goblin, 3);
+        assert_eq!(lookup(&bestiary, "Goblin").max_damage_taken, 7);
+
+        let json = serde_json::to_string(&bestiary).unwrap();
+        let loaded: Bestiary = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, bestiary);
+        assert_eq!(lookup(&loaded, "Goblin").encountered, 1);
+    }
+}
@@ -0,0 +1,308 @@
+//! Bones: what's left of characters who died
+//!
+//! When a character dies, a short record of them is kept: who they were,
+//! where they fell and the best of the gear they wore. Later runs may come
+//! across their grave on a level about as deep as the one they died on,
+//! guarded by their ghost and with one of their items in a chest beside it.
+//! The desktop front-ends keep the records as JSON next to the save file and
+//! the web version keeps them in `localStorage`. Only the most recent
+//! `MAX_BONES` deaths are kept, and a run never meets its own ghost. If the
+//! records can't be read or written the graves just don't turn up.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+
+use crate::character::ClassType;
+use crate::game::Game;
+use crate::item::Item;
+use crate::world::enemy::EnemyType;
+use crate::world::{DungeonType, Enemy};
+
+/// How many deaths are remembered; the oldest is forgotten first
+pub const MAX_BONES: usize = 20;
+
+/// How many of their best items a dead character leaves behind
+pub const BONES_ITEMS: usize = 2;
+
+/// Chance of a level getting a grave when there are bones of a fitting depth
+pub const GRAVE_CHANCE: f64 = 0.25;
+
+/// A character who died, as remembered by later runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BonesRecord {
+    pub name: String,
+    pub class: ClassType,
+    pub level: u32,
+    /// Dungeon level they died on, counting from 1
+    pub depth: u32,
+    pub dungeon_type: DungeonType,
+    /// What killed them, if anything did
+    pub killed_by: Option<String>,
+    /// Their best equipped gear, best first
+    pub items: Vec<Item>,
+    /// The run they died in, so it never meets its own ghost
+    pub run_id: u64,
+}
+
+impl BonesRecord {
+    /// Remembers the character of a game that has just been lost
+    pub fn from_game(game: &Game) -> Self {
+        let inventory = &game.player.inventory;
+        let mut gear: Vec<_> = inventory
+            .equipped
+            .values()
+            .flatten()
+            .filter_map(|&index| match inventory.items.get(index) {
+                Some(Item::Equipment(equipment)) => Some(equipment),
+                _ => None,
+            })
+            .collect();
+        // Slots come out of a HashMap, so break ties by name
+        gear.sort_by_key(|equipment| {
+            (
+                Reverse(equipment.rarity),
+                Reverse(equipment.power),
+                equipment.name.clone(),
+            )
+        });
+
+        let dungeon = game.current_dungeon();
+        BonesRecord {
+            name: game.player.name.clone(),
+            class: game.player.class.class_type,
+            level: game.player.level,
+            depth: dungeon.current_level as u32 + 1,
+            dungeon_type: dungeon.dungeon_type,
+            killed_by: game.stats.killed_by.clone(),
+            items: gear
+                .into_iter()
+                .take(BONES_ITEMS)
+                .map(|equipment| Item::Equipment(equipment.clone()))
+                .collect(),
+            run_id: game.run_id,
+        }
+    }
+
+    /// "Ghost of Aldric, Level 6 Warrior"
+    pub fn ghost_name(&self) -> String {
+        format!(
+            "Ghost of {}, Level {} {}",
+            self.name, self.level, self.class
+        )
+    }
+
+    /// What the gravestone says
+    pub fn epitaph(&self) -> String {
+        let cause = match &self.killed_by {
+            Some(enemy) => format!("slain by {enemy}"),
+            None => "fallen".to_string(),
+        };
+        format!(
+            "Here lies {}, a level {} {}, {cause} on level {} of the {}.",
+            self.name,
+            self.level,
+            self.class,
+            self.depth,
+            format!("{:?}", self.dungeon_type).to_lowercase()
+        )
+    }
+
+    /// The ghost guarding the grave: as strong as the character was, and
+    /// strongest where their class was
+    pub fn ghost(&self) -> Enemy {
+        let mut ghost = Enemy::new(self.ghost_name(), EnemyType::Ghost, self.level);
+        let bonus = self.level as i32 / 2;
+        match self.class {
+            ClassType::Warrior => ghost.stats.increase_strength(bonus),
+            ClassType::Mage => ghost.stats.increase_intelligence(bonus),
+            ClassType::Ranger => ghost.stats.increase_dexterity(bonus),
+            ClassType::Cleric => ghost.stats.increase_wisdom(bonus),
+        }
+        // What they carried is in the chest by the grave
+        ghost.item_drop_chance = 0.0;
+        ghost
+    }
+}
+
+/// Every remembered death, oldest first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BonesPool {
+    records: Vec<BonesRecord>,
+}
+
+impl BonesPool {
+    /// Parses stored bones; anything unreadable starts a fresh pool
+    pub fn from_json(json: &str) -> Self {
+        serde_json::from_str(json).unwrap_or_default()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Remembers a death, forgetting the oldest past `MAX_BONES`
+    pub fn add(&mut self, record: BonesRecord) {
+        self.records.push(record);
+        if self.records.len() > MAX_BONES {
+            self.records.remove(0);
+        }
+    }
+
+    pub fn records(&self) -> &[BonesRecord] {
+        &self.records
+    }
+
+    /// Picks the bones of a character from another run who died within a
+    /// level of `depth`
+    pub fn choose_for<R: Rng + ?Sized>(
+        &self,
+        depth: u32,
+        run_id: u64,
+        rng: &mut R,
+    ) -> Option<&BonesRecord> {
+        let fitting: Vec<&BonesRecord> = self
+            .records
+            .iter()
+            .filter(|record| record.run_id != run_id && record.depth.abs_diff(depth) <= 1)
+            .collect();
+        if fitting.is_empty() {
+            return None;
+        }
+        Some(fitting[rng.gen_range(0..fitting.len())])
+    }
+}
+
+/// The bones file kept next to the save
+#[cfg(not(target_arch = "wasm32"))]
+pub struct BonesFile {
+    path: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl BonesFile {
+    const FILE_NAME: &'static str = "bones.json";
+
+    /// Uses the bones file inside `dir`
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> Self {
+        BonesFile {
+            path: dir.into().join(Self::FILE_NAME),
+        }
+    }
+
+    /// Reads the bones, or none if the file is missing or corrupt
+    pub fn load(&self) -> BonesPool {
+        std::fs::read_to_string(&self.path)
+            .map(|json| BonesPool::from_json(&json))
+            .unwrap_or_default()
+    }
+
+    /// Adds a death to the file
+    pub fn record(&self, record: BonesRecord) -> anyhow::Result<()> {
+        let mut pool = self.load();
+        pool.add(record);
+        crate::save::write_atomically(&self.path, pool.to_json())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl crate::save::DataFile for BonesFile {
+    fn in_dir(dir: std::path::PathBuf) -> Self {
+        Self::new(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::Equipment;
+    #[cfg(not(target_arch = "wasm32"))]
+    use crate::save::TempDir;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn record(name: &str, level: u32, depth: u32, run_id: u64) -> BonesRecord {
+        BonesRecord {
+            name: name.to_string(),
+            class: ClassType::Warrior,
+            level,
+            depth,
+            dungeon_type: DungeonType::Cavern,
+            killed_by: Some("Orc".to_string()),
+            items: vec![Item::Equipment(Equipment::weapon("Old Sword", 6))],
+            run_id,
+        }
+    }
+
+    #[test]
+    fn test_record_remembers_the_character() {
+        let mut game = Game::new(crate::character::Player::new(
+            "Aldric".to_string(),
+            ClassType::Warrior,
+        ));
+        game.stats.killed_by = Some("Goblin".to_string());
+
+        let bones = BonesRecord::from_game(&game);
+        assert_eq!(bones.name, "Aldric");
+        assert_eq!(bones.depth, 1);
+        assert_eq!(bones.run_id, game.run_id);
+        assert!(!bones.items.is_empty() && bones.items.len() <= BONES_ITEMS);
+        assert!(bones.epitaph().contains("slain by Goblin on level 1"));
+    }
+
+    #[test]
+    fn test_ghost_is_named_and_scaled_from_the_record() {
+        let weak = record("Aldric", 2, 1, 1).ghost();
+        let strong = record("Aldric", 6, 1, 1).ghost();
+
+        assert_eq!(strong.name, "Ghost of Aldric, Level 6 Warrior");
+        assert_eq!(strong.enemy_type, EnemyType::Ghost);
+        assert_eq!(strong.level, 6);
+        assert!(strong.stats.strength > weak.stats.strength);
+        assert!(strong.max_health >= weak.max_health);
+        assert_eq!(strong.item_drop_chance, 0.0);
+    }
+
+    #[test]
+    fn test_pool_keeps_the_latest_deaths() {
+        let mut pool = BonesPool::default();
+        for i in 0..(MAX_BONES as u64 + 5) {
+            pool.add(record(&format!("Hero{i}"), 1, 1, i));
+        }
+        assert_eq!(pool.records().len(), MAX_BONES);
+        assert_eq!(pool.records()[0].name, "Hero5");
+
+        let restored = BonesPool::from_json(&pool.to_json());
+        assert_eq!(restored.to_json(), pool.to_json());
+        assert!(BonesPool::from_json("{broken").records().is_empty());
+    }
+
+    #[test]
+    fn test_bones_fit_the_depth_and_skip_the_current_run() {
+        let mut pool = BonesPool::default();
+        pool.add(record("Deep", 5, 6, 1));
+        pool.add(record("Mine", 3, 2, 7));
+        let mut rng = StdRng::seed_from_u64(3);
+
+        assert!(pool.choose_for(2, 7, &mut rng).is_none());
+        assert_eq!(pool.choose_for(5, 7, &mut rng).unwrap().name, "Deep");
+        assert_eq!(pool.choose_for(2, 8, &mut rng).unwrap().name, "Mine");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_file_round_trip() {
+        let dir = TempDir::new("bones");
+        let file = BonesFile::new(dir.path());
+
+        // A missing file holds no bones
+        assert!(file.load().records().is_empty());
+
+        file.record(record("First", 2, 1, 1)).unwrap();
+        file.record(record("Second", 4, 3, 2)).unwrap();
+        let loaded = file.load();
+        assert_eq!(loaded.records().len(), 2);
+        assert_eq!(loaded.records()[1].name, "Second");
+        assert_eq!(loaded.records()[1].depth, 3);
+    }
+}
@@ -0,0 +1,190 @@
+//! Point-buy stats for a new character
+//!
+//! Customizing starts every stat a few points below the class's own and
+//! hands out a small pool to spend, with no stat raised too far past the
+//! class's value. Each front-end drives the same allocation, so none of
+//! them can make a character the rules don't allow.
+
+use crate::character::{Class, ClassType, Player, StatType, Stats};
+use crate::combat::Combatant;
+
+/// Points to spend when customizing
+pub const STAT_POINT_POOL: i32 = 8;
+
+/// How far below the class's value each stat starts when customizing
+pub const STAT_DISCOUNT: i32 = 3;
+
+/// How far above the class's value a stat can be raised
+pub const STAT_CAP_ABOVE_BASE: i32 = 4;
+
+/// What a character would start with, shown as points are assigned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatPreview {
+    pub max_health: i32,
+    pub max_mana: i32,
+    pub attack: i32,
+    pub defense: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatAllocation {
+    class_type: ClassType,
+    base: Stats,
+    stats: Stats,
+}
+
+impl StatAllocation {
+    pub fn new(class_type: ClassType) -> Self {
+        let base = Class::new(class_type).base_stats();
+        let mut stats = base.clone();
+        for stat in StatType::iter() {
+            stats.modify_stat(stat, -STAT_DISCOUNT);
+        }
+        StatAllocation {
+            class_type,
+            base,
+            stats,
+        }
+    }
+
+    pub fn class_type(&self) -> ClassType {
+        self.class_type
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Lowest `stat` can go: where it started
+    pub fn floor(&self, stat: StatType) -> i32 {
+        self.base.get(stat) - STAT_DISCOUNT
+    }
+
+    /// Highest `stat` can go
+    pub fn cap(&self, stat: StatType) -> i32 {
+        self.base.get(stat) + STAT_CAP_ABOVE_BASE
+    }
+
+    pub fn points_left(&self) -> i32 {
+        let spent: i32 = StatType::iter()
+            .map(|stat| self.stats.get(stat) - self.floor(stat))
+            .sum();
+        STAT_POINT_POOL - spent
+    }
+
+    /// Whether every point has been spent, as it must be to confirm
+    pub fn is_complete(&self) -> bool {
+        self.points_left() == 0
+    }
+
+    /// Spends a point on `stat`. Returns false, changing nothing, with no
+    /// points left or the stat at its cap.
+    pub fn raise(&mut self, stat: StatType) -> bool {
+        if self.points_left() == 0 || self.stats.get(stat) >= self.cap(stat) {
+            return false;
+        }
+        self.stats.modify_stat(stat, 1);
+        true
+    }
+
+    /// Takes back a point spent on `stat`. Returns false, changing nothing,
+    /// if none was.
+    pub fn lower(&mut self, stat: StatType) -> bool {
+        if self.stats.get(stat) <= self.floor(stat) {
+            return false;
+        }
+        self.stats.modify_stat(stat, -1);
+        true
+    }
+
+    /// Raises `stat` if `up`, lowers it otherwise
+    pub fn adjust(&mut self, stat: StatType, up: bool) -> bool {
+        if up {
+            self.raise(stat)
+        } else {
+            self.lower(stat)
+        }
+    }
+
+    /// Health, mana, attack and defense the character would start with,
+    /// starter kit and all
+    pub fn preview(&self) -> StatPreview {
+        let player = Player::with_stats(String::new(), self.class_type, self.stats.clone());
+        StatPreview {
+            max_health: player.max_health,
+            max_mana: player.max_mana,
+            attack: player.attack_damage(),
+            defense: player.defense(),
+        }
+    }
+
+    /// The new character, or how many points are still to spend
+    pub fn finish(&self, name: String) -> Result<Player, i32> {
+        if !self.is_complete() {
+            return Err(self.points_left());
+        }
+        Ok(Player::with_stats(
+            name,
+            self.class_type,
+            self.stats.clone(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_points_come_from_the_pool_up_to_each_cap() {
+        let mut allocation = StatAllocation::new(ClassType::Warrior);
+        assert_eq!(allocation.stats().strength, 8 - STAT_DISCOUNT);
+        assert_eq!(allocation.points_left(), STAT_POINT_POOL);
+        assert!(!allocation.lower(StatType::Strength));
+
+        // Strength tops out at the class value plus the cap
+        let raised = (0..STAT_POINT_POOL)
+            .filter(|_| allocation.raise(StatType::Strength))
+            .count() as i32;
+        assert_eq!(raised, STAT_DISCOUNT + STAT_CAP_ABOVE_BASE);
+        assert_eq!(allocation.stats().strength, 8 + STAT_CAP_ABOVE_BASE);
+        assert_eq!(allocation.points_left(), STAT_POINT_POOL - raised);
+        assert_eq!(allocation.finish("Aria".to_string()).err(), Some(1));
+
+        // The last point goes elsewhere, and then the pool is empty
+        assert!(allocation.raise(StatType::Wisdom));
+        assert!(!allocation.raise(StatType::Dexterity));
+        assert!(allocation.is_complete());
+
+        // Taking a point back returns it to the pool
+        assert!(allocation.lower(StatType::Strength));
+        assert_eq!(allocation.points_left(), 1);
+        assert!(allocation.adjust(StatType::Dexterity, true));
+        let player = allocation.finish("Aria".to_string()).unwrap();
+        assert_eq!(player.stats.strength, 11);
+        assert_eq!(player.stats.dexterity, 5 - STAT_DISCOUNT + 1);
+    }
+
+    #[test]
+    fn test_the_preview_matches_the_character_made() {
+        let mut allocation = StatAllocation::new(ClassType::Ranger);
+        for stat in [StatType::Constitution, StatType::Dexterity] {
+            for _ in 0..STAT_POINT_POOL / 2 {
+                allocation.raise(stat);
+            }
+        }
+        let preview = allocation.preview();
+        let player = allocation.finish("Robin".to_string()).unwrap();
+        assert_eq!(
+            preview,
+            StatPreview {
+                max_health: player.max_health,
+                max_mana: player.max_mana,
+                attack: player.attack_damage(),
+                defense: player.defense(),
+            }
+        );
+        assert_eq!(player.health, player.max_health);
+        assert_eq!(player.max_health, player.stats.max_health());
+    }
+}
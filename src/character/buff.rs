@@ -0,0 +1,108 @@
+//! Short-lived modifiers that abilities put on the player in a fight.
+//!
+//! A buff lasts a number of combat turns and counts down with the ability
+//! cooldowns, before the ability used that turn starts its own. One raised
+//! this turn is therefore still up for the enemies' counterattacks. Every
+//! buff drops when the fight ends.
+
+use serde::{Deserialize, Serialize};
+
+/// Combat turns Guard Stance lasts: the enemies' next round of attacks
+pub const GUARD_TURNS: u32 = 1;
+
+/// Share of each blow a guarding player deals back to the attacker
+pub const GUARD_REFLECT_SHARE: f32 = 0.25;
+
+/// Points Blessing adds to every stat
+pub const BLESSING_BONUS: i32 = 2;
+
+/// Combat turns Blessing lasts
+pub const BLESSING_TURNS: u32 = 5;
+
+/// What a buff does while it lasts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BuffEffect {
+    /// Incoming blows are halved and a share of each is dealt back
+    Guard,
+    /// Every stat is raised by the amount
+    Blessing(i32),
+}
+
+impl BuffEffect {
+    pub fn name(self) -> &'static str {
+        match self {
+            BuffEffect::Guard => "Guard Stance",
+            BuffEffect::Blessing(_) => "Blessing",
+        }
+    }
+
+    /// Points the effect adds to every stat
+    pub fn stat_bonus(self) -> i32 {
+        match self {
+            BuffEffect::Blessing(bonus) => bonus,
+            BuffEffect::Guard => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Buff {
+    pub effect: BuffEffect,
+    /// Combat turns left, counting the current one
+    pub turns: u32,
+}
+
+impl Buff {
+    pub fn new(effect: BuffEffect, turns: u32) -> Self {
+        Buff { effect, turns }
+    }
+
+    /// "Blessing +2 (3 turns)", shown on the combat screens
+    pub fn label(&self) -> String {
+        let turns = match self.turns {
+            1 => "1 turn".to_string(),
+            turns => format!("{turns} turns"),
+        };
+        match self.effect {
+            BuffEffect::Guard => format!("{} ({turns})", self.effect.name()),
+            BuffEffect::Blessing(bonus) => format!("{} +{bonus} ({turns})", self.effect.name()),
+        }
+    }
+}
+
+/// Splits a blow of `damage` against a guarding player into the damage they
+/// take, half of it rounded up, and the damage dealt back to the attacker
+pub fn guard(damage: i32) -> (i32, i32) {
+    if damage <= 0 {
+        return (0, 0);
+    }
+    let reflected = (damage as f32 * GUARD_REFLECT_SHARE).round() as i32;
+    (damage - damage / 2, reflected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guard_halves_the_blow_and_reflects_a_quarter() {
+        assert_eq!(guard(20), (10, 5));
+        assert_eq!(guard(10), (5, 3));
+        assert_eq!(guard(7), (4, 2));
+        // A blow that lands still hurts, even if too weak to bounce back
+        assert_eq!(guard(1), (1, 0));
+        assert_eq!(guard(0), (0, 0));
+    }
+
+    #[test]
+    fn test_labels_show_the_turns_left() {
+        assert_eq!(
+            Buff::new(BuffEffect::Guard, 1).label(),
+            "Guard Stance (1 turn)"
+        );
+        assert_eq!(
+            Buff::new(BuffEffect::Blessing(2), 3).label(),
+            "Blessing +2 (3 turns)"
+        );
+    }
+}
@@ -0,0 +1,596 @@
+//! Character builds shared as codes.
+//!
+//! A build code holds a character's name, class, level, stats and the
+//! equipment they wear, but nothing else they carry. It's the build as JSON
+//! behind a version byte and its length, followed by an FNV-1a checksum,
+//! all written out in URL-safe base64 so it survives being pasted into a
+//! chat. Importing checks the checksum and every number in the build, so a
+//! code that was edited by hand or cut short is refused instead of giving
+//! someone 999 strength.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::base64;
+use crate::character::player::{self, MAX_NAME_LENGTH};
+use crate::character::{ClassType, Player, StatType, Stats};
+use crate::combat::DamageType;
+use crate::item::equipment::{EquipmentType, Rarity};
+use crate::item::{Equipment, EquipmentSlot, Item};
+
+/// Format of the codes this version writes; the first byte of every code
+const BUILD_CODE_VERSION: u8 = 1;
+
+/// Bytes before the build itself: the version and its length
+const HEADER_LEN: usize = 5;
+
+/// Bytes of the checksum closing every code
+const CHECKSUM_LEN: usize = 4;
+
+/// Highest level a build can claim
+pub const MAX_BUILD_LEVEL: u32 = 50;
+
+/// Highest value a build can give any stat
+pub const MAX_BUILD_STAT: i32 = 100;
+
+/// Highest power a build's equipment can have
+pub const MAX_BUILD_POWER: i32 = 150;
+
+/// Largest bonus, or penalty, a build's equipment can give a stat
+pub const MAX_BUILD_STAT_BONUS: i32 = 30;
+
+/// Heaviest a piece of a build's equipment can be
+pub const MAX_BUILD_WEIGHT: u32 = 20;
+
+/// Most times a piece of a build's equipment can have been enchanted; the
+/// gold doubling each time puts anything past this out of reach
+pub const MAX_BUILD_ENCHANT: u32 = 20;
+
+/// Longest equipment name a build can have
+const MAX_ITEM_NAME_LENGTH: usize = 40;
+
+/// Why a build code was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+    /// The code has a character that base64 doesn't use
+    InvalidCharacter(char),
+    /// The code ends before the build it holds does
+    Truncated,
+    /// The checksum doesn't match, so the code was changed after it was made
+    Tampered,
+    /// The code was made by a version of the game that writes them
+    /// differently
+    UnsupportedVersion(u8),
+    /// The checksum matches but the build in the code can't be read
+    Malformed,
+    /// A name character creation wouldn't accept
+    InvalidName(String),
+    /// A number the game never gives out
+    OutOfRange { field: String, value: i64 },
+    /// More than one piece of equipment for the same slot
+    DuplicateSlot(EquipmentSlot),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::InvalidCharacter(c) => {
+                write!(f, "'{c}' can't appear in a build code")
+            }
+            ImportError::Truncated => write!(f, "the build code is cut short"),
+            ImportError::Tampered => write!(f, "the build code has been changed"),
+            ImportError::UnsupportedVersion(version) => {
+                write!(f, "build codes of version {version} aren't supported")
+            }
+            ImportError::Malformed => write!(f, "the build in the code can't be read"),
+            ImportError::InvalidName(name) => write!(f, "\"{name}\" isn't a valid name"),
+            ImportError::OutOfRange { field, value } => {
+                write!(f, "{value} is out of range for {field}")
+            }
+            ImportError::DuplicateSlot(slot) => {
+                write!(f, "the build wears more than one {slot} item")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// What goes into a code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Build {
+    name: String,
+    class: ClassType,
+    level: u32,
+    stats: Stats,
+    equipment: Vec<ItemDefinition>,
+}
+
+/// What it takes to make a piece of equipment again. How worn it is,
+/// whether it's identified and whether it's cursed are left out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ItemDefinition {
+    name: String,
+    slot: EquipmentSlot,
+    power: i32,
+    rarity: Rarity,
+    damage_type: DamageType,
+    stat_bonuses: Vec<(StatType, i32)>,
+    level_requirement: u32,
+    weight: Option<u32>,
+    /// Codes from before enchanting have none
+    #[serde(default)]
+    enchant_level: u32,
+}
+
+impl From<&Equipment> for ItemDefinition {
+    fn from(equipment: &Equipment) -> Self {
+        // In a fixed order, so the same build always makes the same code
+        let stat_bonuses = StatType::iter()
+            .filter_map(|stat| Some((stat, *equipment.stat_bonuses.get(&stat)?)))
+            .collect();
+        ItemDefinition {
+            name: equipment.name.clone(),
+            slot: equipment.slot,
+            power: equipment.power,
+            rarity: equipment.rarity,
+            damage_type: equipment.damage_type,
+            stat_bonuses,
+            level_requirement: equipment.level_requirement,
+            weight: equipment.weight,
+            enchant_level: equipment.enchant_level,
+        }
+    }
+}
+
+impl ItemDefinition {
+    fn to_equipment(&self) -> Equipment {
+        let mut equipment = if self.slot == EquipmentSlot::Weapon {
+            Equipment::weapon(&self.name, self.power)
+        } else {
+            Equipment::armor(&self.name, self.slot, self.power)
+        };
+        equipment.rarity = self.rarity;
+        equipment.damage_type = self.damage_type;
+        equipment.stat_bonuses = self.stat_bonuses.iter().copied().collect();
+        equipment.level_requirement = self.level_requirement;
+        equipment.weight = self.weight;
+        equipment.enchant_level = self.enchant_level;
+        equipment.description = description(&equipment);
+        equipment
+    }
+}
+
+/// The description a generated item with these numbers would have
+fn description(equipment: &Equipment) -> String {
+    match equipment.equipment_type {
+        EquipmentType::Weapon => format!(
+            "A weapon that deals {} {} damage. Required level: {}",
+            equipment.power,
+            equipment.damage_type.to_string().to_lowercase(),
+            equipment.level_requirement
+        ),
+        EquipmentType::Armor => format!(
+            "A piece of armor that provides {} protection. Required level: {}",
+            equipment.power, equipment.level_requirement
+        ),
+    }
+}
+
+fn check_range(
+    field: impl Into<String>,
+    value: i64,
+    min: i64,
+    max: i64,
+) -> Result<(), ImportError> {
+    if (min..=max).contains(&value) {
+        Ok(())
+    } else {
+        Err(ImportError::OutOfRange {
+            field: field.into(),
+            value,
+        })
+    }
+}
+
+impl Build {
+    /// Refuses anything the game itself could never have made
+    fn validate(&self) -> Result<(), ImportError> {
+        let name_length = self.name.chars().count();
+        if name_length == 0
+            || name_length > MAX_NAME_LENGTH
+            || !self.name.chars().all(player::is_name_character)
+        {
+            return Err(ImportError::InvalidName(self.name.clone()));
+        }
+        check_range("level", self.level.into(), 1, MAX_BUILD_LEVEL.into())?;
+        for stat in StatType::iter() {
+            check_range(
+                stat.to_string().to_lowercase(),
+                self.stats.get(stat).into(),
+                1,
+                MAX_BUILD_STAT.into(),
+            )?;
+        }
+
+        let mut slots = HashSet::new();
+        for item in &self.equipment {
+            if !slots.insert(item.slot) {
+                return Err(ImportError::DuplicateSlot(item.slot));
+            }
+            check_range(
+                "an item name's length",
+                item.name.chars().count() as i64,
+                1,
+                MAX_ITEM_NAME_LENGTH as i64,
+            )?;
+            check_range(
+                format!("{}'s power", item.name),
+                item.power.into(),
+                0,
+                MAX_BUILD_POWER.into(),
+            )?;
+            check_range(
+                format!("{}'s level requirement", item.name),
+                item.level_requirement.into(),
+                1,
+                self.level.into(),
+            )?;
+            check_range(
+                format!("{}'s enchantment", item.name),
+                item.enchant_level.into(),
+                0,
+                MAX_BUILD_ENCHANT.into(),
+            )?;
+            if let Some(weight) = item.weight {
+                check_range(
+                    format!("{}'s weight", item.name),
+                    weight.into(),
+                    1,
+                    MAX_BUILD_WEIGHT.into(),
+                )?;
+            }
+            for &(stat, bonus) in &item.stat_bonuses {
+                check_range(
+                    format!("{}'s {stat} bonus", item.name),
+                    bonus.into(),
+                    (-MAX_BUILD_STAT_BONUS).into(),
+                    MAX_BUILD_STAT_BONUS.into(),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        let mut bytes = Vec::with_capacity(HEADER_LEN + json.len() + CHECKSUM_LEN);
+        bytes.push(BUILD_CODE_VERSION);
+        bytes.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&json);
+        bytes.extend_from_slice(&checksum(&bytes).to_le_bytes());
+        base64::encode_url_safe(&bytes)
+    }
+
+    fn decode(code: &str) -> Result<Self, ImportError> {
+        let bytes = base64::decode_url_safe(code.trim()).map_err(|error| match error {
+            Some(c) => ImportError::InvalidCharacter(c),
+            None => ImportError::Truncated,
+        })?;
+        if bytes.len() < HEADER_LEN + CHECKSUM_LEN {
+            return Err(ImportError::Truncated);
+        }
+        let json_len = u32::from_le_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]) as usize;
+        let expected_len = HEADER_LEN
+            .checked_add(json_len)
+            .and_then(|len| len.checked_add(CHECKSUM_LEN));
+        match expected_len {
+            Some(len) if bytes.len() < len => return Err(ImportError::Truncated),
+            Some(len) if bytes.len() == len => {}
+            // Longer than it says, or claiming more than could ever fit
+            _ => return Err(ImportError::Tampered),
+        }
+
+        let (body, sum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+        if checksum(body).to_le_bytes() != sum {
+            return Err(ImportError::Tampered);
+        }
+        if body[0] != BUILD_CODE_VERSION {
+            return Err(ImportError::UnsupportedVersion(body[0]));
+        }
+        serde_json::from_slice(&body[HEADER_LEN..]).map_err(|_| ImportError::Malformed)
+    }
+}
+
+/// 32-bit FNV-1a, the same on every build and platform
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, &byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+impl Player {
+    /// A code others can import to play this character's build
+    pub fn export_code(&self) -> String {
+        let equipment = EquipmentSlot::iter()
+            .filter_map(|slot| self.inventory.equipped_in(slot))
+            .map(ItemDefinition::from)
+            .collect();
+        Build {
+            name: self.name.clone(),
+            class: self.class.class_type,
+            level: self.level,
+            stats: self.stats_without_buffs(),
+            equipment,
+        }
+        .encode()
+    }
+
+    /// Makes the character in a build code: their class's starting pack,
+    /// with the level, stats and equipment of the build
+    pub fn import_code(code: &str) -> Result<Player, ImportError> {
+        let build = Build::decode(code)?;
+        build.validate()?;
+
+        let mut player = Player::new(build.name, build.class);
+        player.level = build.level;
+        player.stats = build.stats;
+        for item in &build.equipment {
+            player.wear_in_place(item.to_equipment());
+        }
+        player.recalculate_derived_stats();
+        player.health = player.max_health;
+        player.mana = player.max_mana;
+        Ok(player)
+    }
+
+    /// A new level 1 character on this one's template, for starting a game
+    /// from a build. It keeps the shape of the stats, with what they gained
+    /// over the class's starting stats spread over the levels it took, and
+    /// wears the same equipment weakened to what a new character could find.
+    pub fn at_first_level(&self) -> Player {
+        let mut fresh = Player::new(self.name.clone(), self.class.class_type);
+        let levels = self.level.max(1) as i32;
+        let base = fresh.stats.clone();
+        for stat in StatType::iter() {
+            let gained = (self.stats.get(stat) - base.get(stat)).max(0);
+            fresh.stats.modify_stat(stat, gained / levels);
+        }
+
+        for slot in EquipmentSlot::iter() {
+            if let Some(equipment) = self.inventory.equipped_in(slot) {
+                fresh.wear_in_place(at_first_level(equipment));
+            }
+        }
+        fresh.recalculate_derived_stats();
+        fresh.health = fresh.max_health;
+        fresh.mana = fresh.max_mana;
+        fresh
+    }
+
+    /// Wears `equipment`, putting it in place of whatever was worn in its
+    /// slot
+    fn wear_in_place(&mut self, equipment: Equipment) {
+        match self
+            .inventory
+            .equipped
+            .get(&equipment.slot)
+            .copied()
+            .flatten()
+        {
+            Some(index) => self.inventory.items[index] = Item::Equipment(equipment),
+            None => {
+                self.inventory.add_item(Item::Equipment(equipment));
+                let _ = self.inventory.equip_item(self.inventory.items.len() - 1);
+            }
+        }
+    }
+}
+
+/// `equipment` as a level 1 character could find it: power scaled down to
+/// a level 1 item's, and stat bonuses of at most 1
+fn at_first_level(equipment: &Equipment) -> Equipment {
+    let mut item = ItemDefinition::from(equipment);
+    if item.level_requirement > 1 {
+        // Generated power grows with the item's level, which is about two
+        // more than the level it needs
+        item.power = (item.power * 5 / (item.level_requirement as i32 + 4)).max(1);
+        item.level_requirement = 1;
+    }
+    for (_, bonus) in &mut item.stat_bonuses {
+        *bonus = (*bonus).min(1);
+    }
+    item.to_equipment()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::Buff;
+    use crate::character::BuffEffect;
+
+    fn veteran() -> Player {
+        let mut player = Player::new("Aria".to_string(), ClassType::Warrior);
+        player.level = 12;
+        player.stats.strength = 40;
+        player.stats.dexterity = 20;
+        let mut sword = Equipment::weapon("Flaming Sword", 24);
+        sword.rarity = Rarity::Epic;
+        sword.damage_type = DamageType::Fire;
+        sword.level_requirement = 10;
+        sword.stat_bonuses.insert(StatType::Strength, 5);
+        sword.weight = Some(6);
+        player.wear_in_place(sword);
+        player
+            .inventory
+            .add_item(Item::Equipment(Equipment::weapon("Spare Dagger", 3)));
+        player
+    }
+
+    /// A code for `build` with a correct checksum, as an edited game
+    /// would make it
+    fn code_for(build: impl FnOnce(&mut Build)) -> String {
+        let player = veteran();
+        let mut decoded = Build::decode(&player.export_code()).unwrap();
+        build(&mut decoded);
+        decoded.encode()
+    }
+
+    #[test]
+    fn test_a_build_survives_the_round_trip() {
+        let player = veteran();
+        let code = player.export_code();
+        assert!(code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(code, player.export_code(), "the same build, the same code");
+
+        let imported = Player::import_code(&code).unwrap();
+        assert_eq!(imported.name, "Aria");
+        assert_eq!(imported.class.class_type, ClassType::Warrior);
+        assert_eq!(imported.level, 12);
+        assert_eq!(imported.stats.strength, 40);
+        assert_eq!(imported.health, imported.max_health);
+
+        let sword = imported.inventory.get_equipped_weapon().unwrap();
+        assert_eq!(sword.name, "Flaming Sword");
+        assert_eq!((sword.power, sword.rarity), (24, Rarity::Epic));
+        assert_eq!(sword.damage_type, DamageType::Fire);
+        assert_eq!(sword.stat_bonuses.get(&StatType::Strength), Some(&5));
+        assert!(sword.description.contains("24 fire damage"));
+        for slot in EquipmentSlot::iter() {
+            assert_eq!(
+                imported.inventory.equipped_in(slot).map(|item| &item.name),
+                player.inventory.equipped_in(slot).map(|item| &item.name)
+            );
+        }
+        // Only what's worn travels with the build
+        assert!(!imported
+            .inventory
+            .items
+            .iter()
+            .any(|item| item.name() == "Spare Dagger"));
+        assert_eq!(imported.export_code(), code);
+    }
+
+    #[test]
+    fn test_buffs_stay_out_of_the_code() {
+        let mut player = Player::new("Sister".to_string(), ClassType::Cleric);
+        player.level = 3;
+        let code = player.export_code();
+        player.start_buff(2);
+        assert!(player
+            .buffs
+            .contains(&Buff::new(BuffEffect::Blessing(2), 5)));
+        assert_eq!(player.export_code(), code);
+    }
+
+    #[test]
+    fn test_damaged_codes_are_refused() {
+        let code = veteran().export_code();
+
+        assert_eq!(
+            Player::import_code(&code[..code.len() / 2]).unwrap_err(),
+            ImportError::Truncated
+        );
+        assert_eq!(
+            Player::import_code("AQ").unwrap_err(),
+            ImportError::Truncated
+        );
+        assert_eq!(
+            Player::import_code(&format!("{code}!")).unwrap_err(),
+            ImportError::InvalidCharacter('!')
+        );
+
+        // Changing a character of the build breaks the checksum
+        let middle = code.len() / 2;
+        let swapped = if &code[middle..=middle] == "A" {
+            "B"
+        } else {
+            "A"
+        };
+        let edited = format!("{}{swapped}{}", &code[..middle], &code[middle + 1..]);
+        assert_eq!(
+            Player::import_code(&edited).unwrap_err(),
+            ImportError::Tampered
+        );
+
+        // So does changing the JSON and keeping the old checksum
+        let mut bytes = base64::decode_url_safe(&code).unwrap();
+        let json_at = bytes.windows(2).position(|w| w == b"40").unwrap();
+        bytes[json_at] = b'9';
+        bytes[json_at + 1] = b'9';
+        assert_eq!(
+            Player::import_code(&base64::encode_url_safe(&bytes)).unwrap_err(),
+            ImportError::Tampered
+        );
+    }
+
+    #[test]
+    fn test_impossible_builds_are_refused() {
+        let refused = |edit: fn(&mut Build)| Player::import_code(&code_for(edit)).unwrap_err();
+
+        assert_eq!(
+            refused(|build| build.stats.strength = 999),
+            ImportError::OutOfRange {
+                field: "strength".to_string(),
+                value: 999
+            }
+        );
+        assert!(matches!(
+            refused(|build| build.level = 0),
+            ImportError::OutOfRange { value: 0, .. }
+        ));
+        assert!(matches!(
+            refused(|build| build.equipment[0].power = 9000),
+            ImportError::OutOfRange { value: 9000, .. }
+        ));
+        assert!(matches!(
+            refused(|build| build.equipment[0].level_requirement = 13),
+            ImportError::OutOfRange { value: 13, .. }
+        ));
+        assert!(matches!(
+            refused(|build| build.equipment[0].enchant_level = 4_000_000_000),
+            ImportError::OutOfRange {
+                value: 4_000_000_000,
+                ..
+            }
+        ));
+        assert_eq!(
+            refused(|build| build.name = "<script>".to_string()),
+            ImportError::InvalidName("<script>".to_string())
+        );
+        assert!(matches!(
+            refused(|build| {
+                let copy = build.equipment[0].clone();
+                build.equipment.push(copy);
+            }),
+            ImportError::DuplicateSlot(_)
+        ));
+    }
+
+    #[test]
+    fn test_a_new_game_scales_the_build_down_to_level_one() {
+        let fresh = veteran().at_first_level();
+        let base = Player::new("Aria".to_string(), ClassType::Warrior);
+
+        assert_eq!((fresh.level, fresh.experience), (1, 0));
+        // 40 strength at level 12 is what the class starts with plus a
+        // twelfth of what was gained
+        let gained = 40 - base.stats.strength;
+        assert_eq!(fresh.stats.strength, base.stats.strength + gained / 12);
+        assert_eq!(fresh.stats.wisdom, base.stats.wisdom);
+        assert_eq!(fresh.health, fresh.max_health);
+
+        let sword = fresh.inventory.get_equipped_weapon().unwrap();
+        assert_eq!(sword.name, "Flaming Sword");
+        assert_eq!(sword.level_requirement, 1);
+        assert_eq!(sword.power, 24 * 5 / 14);
+        assert_eq!(sword.stat_bonuses.get(&StatType::Strength), Some(&1));
+
+        // A level 1 build needs no scaling
+        assert_eq!(base.at_first_level().export_code(), base.export_code());
+    }
+}
@@ -1,4 +1,6 @@
-use crate::character::Stats;
+use crate::character::buff::{BLESSING_BONUS, BLESSING_TURNS, GUARD_TURNS};
+use crate::character::{Buff, BuffEffect, Stats};
+use crate::item::{Consumable, Equipment, EquipmentSlot, Item};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 
@@ -10,6 +12,46 @@ pub enum ClassType {
     Cleric,
 }
 
+impl ClassType {
+    /// Every class, in the order character creation lists them
+    pub const ALL: [ClassType; 4] = [
+        ClassType::Warrior,
+        ClassType::Mage,
+        ClassType::Ranger,
+        ClassType::Cleric,
+    ];
+
+    /// One line shown next to the class during character creation
+    pub fn description(self) -> &'static str {
+        match self {
+            ClassType::Warrior => "A powerful melee fighter with high health",
+            ClassType::Mage => "A spellcaster with powerful magical abilities",
+            ClassType::Ranger => "A skilled archer with balanced stats",
+            ClassType::Cleric => "A healer with supportive abilities",
+        }
+    }
+
+    /// The class's knack outside a fight, for the character sheet: its
+    /// name and what it does
+    pub fn passive(self) -> (&'static str, &'static str) {
+        match self {
+            ClassType::Warrior => (
+                "Brute Force",
+                "Smashes trapped chests open by strength, loudly",
+            ),
+            ClassType::Mage => ("Arcane Sense", "Press E once a level to sense the way down"),
+            ClassType::Ranger => (
+                "Keen Eyes",
+                "Spots foes and loot 2 tiles past sight; checks every chest",
+            ),
+            ClassType::Cleric => (
+                "Hallowed Hands",
+                "Healing potions heal 25% more; your slain stay dead",
+            ),
+        }
+    }
+}
+
 impl std::fmt::Display for ClassType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -24,21 +66,119 @@ impl std::fmt::Display for ClassType {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Class {
     pub class_type: ClassType,
-    pub abilities: Vec<String>,
 }
 
 impl Class {
     pub fn new(class_type: ClassType) -> Self {
-        let abilities = match class_type {
-            ClassType::Warrior => vec!["Slash".to_string(), "Shield Block".to_string()],
-            ClassType::Mage => vec!["Fireball".to_string(), "Magic Shield".to_string()],
-            ClassType::Ranger => vec!["Aimed Shot".to_string(), "Evasion".to_string()],
-            ClassType::Cleric => vec!["Heal".to_string(), "Divine Protection".to_string()],
-        };
-
-        Class {
-            class_type,
-            abilities,
+        Class { class_type }
+    }
+
+    /// Every ability of the class with the level that unlocks it, in the
+    /// order they are learned
+    pub fn abilities(&self) -> &'static [(u32, &'static str)] {
+        match self.class_type {
+            ClassType::Warrior => &[
+                (1, "Slash"),
+                (2, "Guard Stance"),
+                (3, "Shield Block"),
+                (5, "Execute"),
+            ],
+            ClassType::Mage => &[(1, "Fireball"), (3, "Magic Shield"), (5, "Meteor")],
+            ClassType::Ranger => &[
+                (1, "Aimed Shot"),
+                (3, "Evasion"),
+                (5, "Haste"),
+                (7, "Rain of Arrows"),
+            ],
+            ClassType::Cleric => &[
+                (1, "Heal"),
+                (2, "Smite"),
+                (3, "Blessing"),
+                (4, "Divine Protection"),
+                (5, "Consecrate"),
+                (6, "Greater Heal"),
+            ],
+        }
+    }
+
+    /// Abilities a character of the given level can use
+    pub fn abilities_at(&self, level: u32) -> Vec<&'static str> {
+        self.abilities()
+            .iter()
+            .filter(|(unlock_level, _)| *unlock_level <= level)
+            .map(|(_, ability)| *ability)
+            .collect()
+    }
+
+    /// Mana spent to use an ability; weapon skills and Evasion are free
+    pub fn mana_cost(ability: &str) -> i32 {
+        match ability {
+            "Meteor" => 15,
+            "Greater Heal" => 12,
+            "Fireball" | "Consecrate" => 8,
+            "Smite" | "Blessing" => 6,
+            "Heal" | "Haste" => 5,
+            "Guard Stance" => 3,
+            "Shield Block" | "Magic Shield" | "Divine Protection" => 4,
+            _ => 0,
+        }
+    }
+
+    /// Combat turns an ability needs to recover after use; the basic
+    /// attacks can be used every turn
+    pub fn cooldown(ability: &str) -> u32 {
+        match ability {
+            "Greater Heal" | "Haste" => 8,
+            "Meteor" | "Blessing" => 6,
+            "Execute" | "Rain of Arrows" => 5,
+            "Divine Protection" => 4,
+            "Shield Block" | "Magic Shield" | "Evasion" | "Consecrate" => 3,
+            "Heal" | "Guard Stance" => 2,
+            "Fireball" | "Smite" => 1,
+            _ => 0,
+        }
+    }
+
+    /// The buff an ability puts on whoever uses it, if it's one that does
+    pub fn buff(ability: &str) -> Option<Buff> {
+        match ability {
+            "Guard Stance" => Some(Buff::new(BuffEffect::Guard, GUARD_TURNS)),
+            "Blessing" => Some(Buff::new(
+                BuffEffect::Blessing(BLESSING_BONUS),
+                BLESSING_TURNS,
+            )),
+            _ => None,
+        }
+    }
+
+    /// Gear a new character of this class starts with; equipment in it is
+    /// worn from the start
+    pub fn starter_kit(&self) -> Vec<Item> {
+        let weapon = |name, power| Item::Equipment(Equipment::weapon(name, power));
+        let armor = |name, slot, power| Item::Equipment(Equipment::armor(name, slot, power));
+
+        match self.class_type {
+            ClassType::Warrior => vec![
+                weapon("Iron Sword", 4),
+                armor("Leather Armor", EquipmentSlot::Chest, 2),
+                Item::Consumable(Consumable::health_potion(30)),
+                Item::Consumable(Consumable::health_potion(30)),
+            ],
+            ClassType::Mage => vec![
+                weapon("Oak Staff", 3),
+                armor("Apprentice Robe", EquipmentSlot::Chest, 1),
+                Item::Consumable(Consumable::mana_potion(30)),
+                Item::Consumable(Consumable::mana_potion(30)),
+            ],
+            ClassType::Ranger => vec![
+                weapon("Hunting Bow", 4),
+                armor("Leather Boots", EquipmentSlot::Feet, 1),
+            ],
+            ClassType::Cleric => vec![
+                weapon("Iron Mace", 3),
+                armor("Wooden Shield", EquipmentSlot::Shield, 2),
+                Item::Consumable(Consumable::healing_scroll(40)),
+            ],
         }
     }
 
@@ -79,9 +219,7 @@ impl Class {
         stats
     }
 
-    pub fn level_up_stats(&self, stats: &mut Stats) {
-        let mut rng = rand::thread_rng();
-
+    pub fn level_up_stats<R: Rng + ?Sized>(&self, stats: &mut Stats, rng: &mut R) {
         match self.class_type {
             ClassType::Warrior => {
                 stats.increase_strength(1 + rng.gen_range(0..=1));
@@ -139,10 +277,4 @@ impl Class {
             }
         }
     }
-
-    pub fn use_ability(&self, ability_index: usize) -> Option<&str> {
-        self.abilities
-            .get(ability_index)
-            .map(std::string::String::as_str)
-    }
 }
@@ -0,0 +1,165 @@
+//! A creature that follows the player and fights at their side
+//!
+//! Rangers start with a wolf pup; anyone else has to tame a weakened beast
+//! with a Taming Charm. A companion is never killed: at 0 HP it's knocked
+//! out and gets back up after a rest, or sooner with Smelling Salts.
+
+use serde::{Deserialize, Serialize};
+
+use crate::combat::Combatant;
+use crate::world::{Enemy, EnemyKind};
+
+/// Turns a knocked out companion rests before it gets back up
+pub const KNOCKOUT_TURNS: u32 = 50;
+
+/// Chance that an enemy attacks the companion instead of the player
+pub const COMPANION_TARGET_CHANCE: f64 = 0.3;
+
+/// Share of its health a beast must be below before it can be tamed
+pub const TAME_HEALTH_SHARE: f32 = 0.25;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Companion {
+    pub name: String,
+    /// What sort of creature it is, e.g. "Wolf Pup"
+    pub kind: String,
+    pub health: i32,
+    pub max_health: i32,
+    /// Damage of its attack; it always lands, but enemy defense applies
+    pub attack: i32,
+    /// Turns left until a knocked out companion gets back up
+    #[serde(default)]
+    pub knocked_out_turns: u32,
+}
+
+impl Companion {
+    /// The companion every Ranger starts with
+    pub fn wolf_pup() -> Self {
+        Companion {
+            name: "Fang".to_string(),
+            kind: "Wolf Pup".to_string(),
+            health: 20,
+            max_health: 20,
+            attack: 4,
+            knocked_out_turns: 0,
+        }
+    }
+
+    /// A beast won over with a Taming Charm. It comes along fully healed but
+    /// fights at half its old strength.
+    pub fn tamed(enemy: &Enemy) -> Self {
+        Companion {
+            name: enemy.name.clone(),
+            kind: enemy.enemy_type.to_string(),
+            health: enemy.max_health,
+            max_health: enemy.max_health,
+            attack: (enemy.attack_damage() / 2).max(1),
+            knocked_out_turns: 0,
+        }
+    }
+
+    /// Whether `enemy` is a beast hurt badly enough to be tamed
+    pub fn can_tame(enemy: &Enemy) -> bool {
+        enemy.kind == EnemyKind::Beast
+            && enemy.is_alive()
+            && (enemy.health as f32) < enemy.max_health as f32 * TAME_HEALTH_SHARE
+    }
+
+    pub fn is_knocked_out(&self) -> bool {
+        self.health <= 0
+    }
+
+    /// Takes `damage`, returning whether it knocked the companion out
+    pub fn take_damage(&mut self, damage: i32) -> bool {
+        if self.is_knocked_out() {
+            return false;
+        }
+        self.health -= damage;
+        if self.is_knocked_out() {
+            self.health = 0;
+            self.knocked_out_turns = KNOCKOUT_TURNS;
+            return true;
+        }
+        false
+    }
+
+    /// Counts down one turn of a knockout, returning whether the companion
+    /// got back up this turn
+    pub fn rest(&mut self) -> bool {
+        if !self.is_knocked_out() {
+            return false;
+        }
+        self.knocked_out_turns = self.knocked_out_turns.saturating_sub(1);
+        if self.knocked_out_turns == 0 {
+            self.revive();
+            return true;
+        }
+        false
+    }
+
+    /// Brings a knocked out companion back at half health
+    pub fn revive(&mut self) {
+        self.health = (self.max_health / 2).max(1);
+        self.knocked_out_turns = 0;
+    }
+
+    /// "Fang the Wolf Pup"
+    pub fn title(&self) -> String {
+        format!("{} the {}", self.name, self.kind)
+    }
+
+    /// Its health, or how long it stays down, for the side panel and the
+    /// character screen
+    pub fn status_text(&self) -> String {
+        if self.is_knocked_out() {
+            format!(
+                "{}: knocked out ({} turns)",
+                self.name, self.knocked_out_turns
+            )
+        } else {
+            format!("{}: {}/{} HP", self.name, self.health, self.max_health)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::enemy::EnemyType;
+
+    #[test]
+    fn test_knocked_out_companions_get_back_up_after_a_rest() {
+        let mut pup = Companion::wolf_pup();
+        assert!(!pup.take_damage(5));
+        assert_eq!(pup.status_text(), "Fang: 15/20 HP");
+
+        assert!(pup.take_damage(30));
+        assert_eq!(pup.health, 0);
+        assert!(pup.is_knocked_out());
+        assert!(!pup.take_damage(5));
+        assert_eq!(pup.status_text(), "Fang: knocked out (50 turns)");
+
+        for _ in 1..KNOCKOUT_TURNS {
+            assert!(!pup.rest());
+        }
+        assert!(pup.rest());
+        assert_eq!(pup.health, 10);
+        assert!(!pup.rest());
+    }
+
+    #[test]
+    fn test_only_badly_hurt_beasts_can_be_tamed() {
+        let mut slime = Enemy::new("Oozing Slime".to_string(), EnemyType::Slime, 2);
+        assert!(!Companion::can_tame(&slime));
+        slime.health = slime.max_health / 5;
+        assert!(Companion::can_tame(&slime));
+
+        let tamed = Companion::tamed(&slime);
+        assert_eq!(tamed.title(), "Oozing Slime the Slime");
+        assert_eq!(tamed.health, slime.max_health);
+
+        let mut goblin = Enemy::new("Sneaky Goblin".to_string(), EnemyType::Goblin, 2);
+        goblin.health = 1;
+        assert!(!Companion::can_tame(&goblin));
+    }
+}
@@ -1,8 +1,15 @@
+pub mod allocation;
+pub mod buff;
+pub mod build;
 pub mod class;
+pub mod companion;
 pub mod player;
 pub mod stats;
 
+pub use allocation::StatAllocation;
+pub use buff::{Buff, BuffEffect};
 pub use class::{Class, ClassType};
+pub use companion::Companion;
 pub use player::Player;
 pub use stats::StatType;
 pub use stats::Stats;
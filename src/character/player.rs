@@ -1,7 +1,43 @@
-use crate::character::{Class, ClassType, Stats};
+use crate::character::buff;
+use crate::character::{Buff, BuffEffect, Class, ClassType, Companion, StatType, Stats};
+use crate::combat::{damage_after_defense, Combatant, DamageType};
+use crate::game::energy;
 use crate::inventory::manager::Inventory;
+use crate::item::consumable::{HASTE_TURNS, WHETSTONE_BONUS};
+use crate::item::Item;
 
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Free stat points awarded on each level up
+pub const STAT_POINTS_PER_LEVEL: u32 = 2;
+
+/// Longest name character creation accepts
+pub const MAX_NAME_LENGTH: usize = 20;
+
+/// Name given to a character created without one
+pub const DEFAULT_NAME: &str = "Hero";
+
+/// Carry capacity of a character with no strength at all
+pub const BASE_CARRY_CAPACITY: u32 = 30;
+
+/// Carry capacity each point of strength adds
+pub const CARRY_CAPACITY_PER_STRENGTH: u32 = 5;
+
+/// Share of the carry capacity above which the player is encumbered
+pub const ENCUMBERED_SHARE: f32 = 0.9;
+
+/// How much more a Cleric's healing potions heal, in percent
+pub const CLERIC_POTION_BONUS_PERCENT: i32 = 25;
+
+/// Points of dexterity above 5 for each point of speed they add
+const DEXTERITY_PER_SPEED: u32 = 4;
+
+/// Whether `c` may be typed into a character name
+pub fn is_name_character(c: char) -> bool {
+    c.is_alphanumeric() || c == ' '
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Player {
@@ -16,14 +52,65 @@ pub struct Player {
     pub max_mana: i32,
     pub inventory: Inventory,
     pub gold: u32,
+    /// Stat points earned from leveling that haven't been allocated yet
+    #[serde(default)]
+    pub unspent_stat_points: u32,
+    /// Turns the lit torch has left; it burns only on dark levels
+    #[serde(default)]
+    pub torch_turns: u32,
+    /// The creature fighting at the player's side, if they have one
+    #[serde(default)]
+    pub companion: Option<Companion>,
+    /// Energy stored towards the next action while exploring
+    #[serde(default)]
+    pub energy: u32,
+    /// Turns the player stays hasted
+    #[serde(default)]
+    pub haste_turns: u32,
+    /// Turns the player's weapon keeps a whetstone's edge
+    #[serde(default)]
+    pub honed_turns: u32,
+    /// Turns the player stays poisoned, losing a little health each one
+    #[serde(default)]
+    pub poisoned_turns: u32,
+    /// Wading has cost the player their footing, so the next attack on them
+    /// can't be dodged
+    #[serde(default)]
+    pub off_balance: bool,
+    /// Combat turns each ability, by index, still needs before it's ready
+    #[serde(default)]
+    pub cooldowns: HashMap<usize, u32>,
+    /// Buffs from abilities still up in the current fight. Blessings are
+    /// already added to `stats`.
+    #[serde(default)]
+    pub buffs: Vec<Buff>,
 }
 
 impl Player {
     pub fn new(name: String, class_type: ClassType) -> Self {
+        let stats = Class::new(class_type).base_stats();
+        Self::with_stats(name, class_type, stats)
+    }
+
+    /// A new character with `stats` in place of the class's own, as chosen
+    /// through a `StatAllocation`
+    pub fn with_stats(name: String, class_type: ClassType, stats: Stats) -> Self {
         let class = Class::new(class_type);
-        let stats = class.base_stats();
-        let max_health = 10 + (stats.constitution * 5);
-        let max_mana = 5 + (stats.wisdom * 3);
+        let max_health = stats.max_health();
+        let max_mana = stats.max_mana();
+
+        // Start with the class starter kit packed and its gear worn
+        let mut inventory = Inventory::new();
+        for item in class.starter_kit() {
+            let is_equipment = matches!(item, Item::Equipment(_));
+            inventory.add_item(item);
+            if is_equipment {
+                let _ = inventory.equip_item(inventory.items.len() - 1);
+            }
+        }
+
+        // Rangers set out with a wolf pup at their heels
+        let companion = (class_type == ClassType::Ranger).then(Companion::wolf_pup);
 
         Player {
             name,
@@ -35,86 +122,278 @@ impl Player {
             max_health,
             mana: max_mana,
             max_mana,
-            inventory: Inventory::new(20), // Start with 20 slots
+            inventory,
             gold: 50,
+            unspent_stat_points: 0,
+            torch_turns: 0,
+            companion,
+            energy: 0,
+            haste_turns: 0,
+            honed_turns: 0,
+            poisoned_turns: 0,
+            off_balance: false,
+            cooldowns: HashMap::new(),
+            buffs: Vec::new(),
+        }
+    }
+
+    /// Abilities unlocked at the player's current level
+    pub fn available_abilities(&self) -> Vec<&'static str> {
+        self.class.abilities_at(self.level)
+    }
+
+    /// Abilities learned since the player was at `previous_level`, for
+    /// level up messages
+    pub fn abilities_unlocked_since(&self, previous_level: u32) -> Vec<&'static str> {
+        self.class
+            .abilities()
+            .iter()
+            .filter(|(unlock_level, _)| (previous_level + 1..=self.level).contains(unlock_level))
+            .map(|(_, ability)| *ability)
+            .collect()
+    }
+
+    /// Combat turns before the ability at `index` can be used again
+    pub fn cooldown_left(&self, index: usize) -> u32 {
+        self.cooldowns.get(&index).copied().unwrap_or(0)
+    }
+
+    /// "(ready)" or "(2 turns)", shown beside the ability in combat menus
+    pub fn cooldown_label(&self, index: usize) -> String {
+        match self.cooldown_left(index) {
+            0 => "(ready)".to_string(),
+            1 => "(1 turn)".to_string(),
+            turns => format!("({turns} turns)"),
+        }
+    }
+
+    /// Puts the ability at `index` on cooldown after it has been used
+    pub fn start_cooldown(&mut self, index: usize) {
+        let turns = self
+            .available_abilities()
+            .get(index)
+            .map_or(0, |ability| Class::cooldown(ability));
+        if turns > 0 {
+            self.cooldowns.insert(index, turns);
         }
     }
 
-    pub fn gain_experience(&mut self, exp: u32) -> bool {
+    /// Counts down every cooldown by one combat turn
+    pub fn tick_cooldowns(&mut self) {
+        self.cooldowns.retain(|_, turns| {
+            *turns = turns.saturating_sub(1);
+            *turns > 0
+        });
+    }
+
+    /// Raises the buff of the ability at `index`, if it has one, after it
+    /// has been used. Using it again while it's up starts it afresh.
+    pub fn start_buff(&mut self, index: usize) {
+        let Some(buff) = self
+            .available_abilities()
+            .get(index)
+            .and_then(|ability| Class::buff(ability))
+        else {
+            return;
+        };
+        if let Some(old) = self.buffs.iter().position(|old| old.effect == buff.effect) {
+            let old = self.buffs.remove(old);
+            self.apply_stat_bonus(-old.effect.stat_bonus());
+        }
+        self.apply_stat_bonus(buff.effect.stat_bonus());
+        self.buffs.push(buff);
+    }
+
+    /// Counts down every buff by one combat turn, returning a message for
+    /// each one that wears off
+    pub fn tick_buffs(&mut self) -> Vec<String> {
+        let mut messages = Vec::new();
+        for buff in std::mem::take(&mut self.buffs) {
+            if buff.turns > 1 {
+                self.buffs.push(Buff::new(buff.effect, buff.turns - 1));
+            } else {
+                self.apply_stat_bonus(-buff.effect.stat_bonus());
+                messages.push(format!("Your {} wears off.", buff.effect.name()));
+            }
+        }
+        messages
+    }
+
+    /// Drops every buff once a fight is over
+    pub fn clear_buffs(&mut self) {
+        for buff in std::mem::take(&mut self.buffs) {
+            self.apply_stat_bonus(-buff.effect.stat_bonus());
+        }
+    }
+
+    /// Whether Guard Stance is up
+    pub fn is_guarding(&self) -> bool {
+        self.buffs
+            .iter()
+            .any(|buff| buff.effect == BuffEffect::Guard)
+    }
+
+    /// Damage a guarding player deals back to an attacker whose blow of
+    /// `amount` raw damage lands; 0 when not guarding
+    pub fn guard_reflection(&self, amount: i32) -> i32 {
+        if !self.is_guarding() {
+            return 0;
+        }
+        buff::guard(damage_after_defense(amount, self.defense())).1
+    }
+
+    fn apply_stat_bonus(&mut self, bonus: i32) {
+        if bonus != 0 {
+            for stat in StatType::iter() {
+                self.stats.modify_stat(stat, bonus);
+            }
+        }
+    }
+
+    /// Every ability is ready again once a fight is over
+    pub fn reset_cooldowns(&mut self) {
+        self.cooldowns.clear();
+    }
+
+    /// Total experience needed to reach the next level
+    pub fn xp_for_next_level(&self) -> u32 {
+        (100.0 * f64::from(self.level).powf(1.5)).round() as u32
+    }
+
+    pub fn gain_experience<R: Rng + ?Sized>(&mut self, exp: u32, rng: &mut R) -> bool {
         self.experience += exp;
-        let level_up_threshold = self.level * 100;
+        let mut leveled_up = false;
 
-        if self.experience >= level_up_threshold {
-            self.level_up();
-            return true;
+        while self.experience >= self.xp_for_next_level() {
+            self.level_up(rng);
+            leveled_up = true;
         }
 
-        false
+        leveled_up
     }
 
-    pub fn level_up(&mut self) {
+    pub fn level_up<R: Rng + ?Sized>(&mut self, rng: &mut R) {
         self.level += 1;
-        self.class.level_up_stats(&mut self.stats);
+        self.class.level_up_stats(&mut self.stats, rng);
+        self.unspent_stat_points += STAT_POINTS_PER_LEVEL;
 
-        // Recalculate max health and mana
-        self.max_health = 10 + (self.stats.constitution * 5);
-        self.max_mana = 5 + (self.stats.wisdom * 3);
+        self.recalculate_derived_stats();
 
         // Restore health and mana on level up
         self.health = self.max_health;
         self.mana = self.max_mana;
     }
 
-    pub fn heal(&mut self, amount: i32) {
-        self.health = (self.health + amount).min(self.max_health);
+    /// Spends one unspent stat point on the given stat
+    pub fn allocate_stat_point(&mut self, stat: StatType) -> Result<(), String> {
+        if self.unspent_stat_points == 0 {
+            return Err("No stat points to spend".to_string());
+        }
+
+        self.unspent_stat_points -= 1;
+        self.stats.modify_stat(stat, 1);
+
+        // Keep current health/mana in step with any increase to their maximums
+        let (old_max_health, old_max_mana) = (self.max_health, self.max_mana);
+        self.recalculate_derived_stats();
+        self.health += self.max_health - old_max_health;
+        self.mana += self.max_mana - old_max_mana;
+
+        Ok(())
     }
 
-    pub fn is_alive(&self) -> bool {
-        self.health > 0
+    /// Recalculates max health and mana from the current stats, leaving out
+    /// any blessing that only lasts the fight
+    pub fn recalculate_derived_stats(&mut self) {
+        let stats = self.stats_without_buffs();
+        self.max_health = stats.max_health();
+        self.max_mana = stats.max_mana();
     }
 
-    pub fn attack_damage(&self) -> i32 {
-        let base_damage = match self.class.class_type {
-            ClassType::Warrior => self.stats.strength,
-            ClassType::Mage => self.stats.intelligence / 2,
-            ClassType::Ranger => self.stats.dexterity,
-            ClassType::Cleric => self.stats.wisdom / 2,
-        };
+    /// The player's stats without what their buffs add for the fight
+    pub fn stats_without_buffs(&self) -> Stats {
+        let bonus: i32 = self.buffs.iter().map(|buff| buff.effect.stat_bonus()).sum();
+        let mut stats = self.stats.clone();
+        for stat in StatType::iter() {
+            stats.modify_stat(stat, -bonus);
+        }
+        stats
+    }
 
-        // Add weapon damage if equipped
-        let weapon_damage = if let Some(weapon) = self.inventory.get_equipped_weapon() {
-            weapon.power
-        } else {
-            1 // Base damage without weapon
-        };
+    /// Total weight the player can carry, which grows with strength
+    pub fn carry_capacity(&self) -> u32 {
+        BASE_CARRY_CAPACITY + self.stats.strength.max(0) as u32 * CARRY_CAPACITY_PER_STRENGTH
+    }
 
-        base_damage + weapon_damage
+    /// Total weight of everything in the inventory, worn or not
+    pub fn carried_weight(&self) -> u32 {
+        self.inventory.total_weight()
     }
 
-    pub fn defense(&self) -> i32 {
-        let base_defense = self.stats.constitution / 2;
+    /// Whether the pack is heavy enough to slow the player down. Enemies get
+    /// a parting blow on an encumbered player who flees.
+    pub fn is_encumbered(&self) -> bool {
+        self.carried_weight() as f32 >= self.carry_capacity() as f32 * ENCUMBERED_SHARE
+    }
 
-        // Add armor defense
-        let armor_defense = self.inventory.get_total_armor_defense();
+    /// How fast the player acts while exploring: a little faster for every
+    /// few points of dexterity above 5, slower when encumbered and faster
+    /// while hasted
+    pub fn speed(&self) -> u32 {
+        let nimbleness = (self.stats.dexterity - 5).max(0) as u32 / DEXTERITY_PER_SPEED;
+        let mut speed = energy::NORMAL_SPEED + nimbleness;
+        if self.is_encumbered() {
+            speed = speed * 3 / 4;
+        }
+        if self.haste_turns > 0 {
+            speed = energy::hasted(speed);
+        }
+        speed
+    }
 
-        base_defense + armor_defense
+    pub fn heal(&mut self, amount: i32) {
+        self.health = (self.health + amount).min(self.max_health);
     }
 
-    pub fn take_damage(&mut self, amount: i32) -> i32 {
-        let defense = self.defense();
-        let damage_taken = (amount - defense).max(1); // Always take at least 1 damage
-        self.health -= damage_taken;
-        damage_taken
+    /// What a healing potion of `potency` restores for this player;
+    /// Clerics get `CLERIC_POTION_BONUS_PERCENT` more out of it
+    pub fn potion_healing(&self, potency: i32) -> i32 {
+        if self.class.class_type == ClassType::Cleric {
+            potency + potency * CLERIC_POTION_BONUS_PERCENT / 100
+        } else {
+            potency
+        }
+    }
+
+    /// Attack a whetstone's edge adds to the equipped weapon while it lasts
+    pub fn honing_bonus(&self) -> i32 {
+        if self.honed_turns > 0 {
+            WHETSTONE_BONUS
+        } else {
+            0
+        }
+    }
+
+    /// Damage type of the equipped weapon; unarmed attacks are physical
+    pub fn weapon_damage_type(&self) -> DamageType {
+        self.inventory
+            .get_equipped_weapon()
+            .map_or(DamageType::Physical, |weapon| weapon.damage_type)
     }
 
     pub fn use_ability(&mut self, ability_index: usize) -> Result<String, String> {
-        let _rng = rand::thread_rng();
+        if let Some(ability_name) = self.available_abilities().get(ability_index).copied() {
+            if self.cooldown_left(ability_index) > 0 {
+                return Err(format!(
+                    "{ability_name} isn't ready yet {}",
+                    self.cooldown_label(ability_index)
+                ));
+            }
 
-        if let Some(ability_name) = self.class.use_ability(ability_index) {
             match ability_name {
                 "Heal" => {
                     let heal_amount = self.stats.wisdom * 2;
-                    let mana_cost = 5;
+                    let mana_cost = Class::mana_cost(ability_name);
 
                     if self.mana >= mana_cost {
                         self.mana -= mana_cost;
@@ -126,7 +405,7 @@ impl Player {
                 }
                 "Fireball" => {
                     let damage = self.stats.intelligence * 3;
-                    let mana_cost = 8;
+                    let mana_cost = Class::mana_cost(ability_name);
 
                     if self.mana >= mana_cost {
                         self.mana -= mana_cost;
@@ -135,8 +414,55 @@ impl Player {
                         Err("Not enough mana to cast Fireball".to_string())
                     }
                 }
+                "Greater Heal" => {
+                    let mana_cost = Class::mana_cost(ability_name);
+
+                    if self.mana >= mana_cost {
+                        self.mana -= mana_cost;
+                        let heal_amount = self.max_health - self.health;
+                        self.heal(heal_amount);
+                        Ok(format!(
+                            "You cast Greater Heal and restored {heal_amount} health"
+                        ))
+                    } else {
+                        Err("Not enough mana to cast Greater Heal".to_string())
+                    }
+                }
+                "Meteor" => {
+                    let damage = self.stats.intelligence * 6;
+                    let mana_cost = Class::mana_cost(ability_name);
+
+                    if self.mana >= mana_cost {
+                        self.mana -= mana_cost;
+                        Ok(format!("You call down a Meteor for {damage} damage"))
+                    } else {
+                        Err("Not enough mana to cast Meteor".to_string())
+                    }
+                }
+                "Smite" => {
+                    let damage = self.stats.wisdom * 2;
+                    let mana_cost = Class::mana_cost(ability_name);
+
+                    if self.mana >= mana_cost {
+                        self.mana -= mana_cost;
+                        Ok(format!("You cast Smite for {damage} damage"))
+                    } else {
+                        Err("Not enough mana to cast Smite".to_string())
+                    }
+                }
+                "Consecrate" => {
+                    let damage = self.stats.wisdom * 2;
+                    let mana_cost = Class::mana_cost(ability_name);
+
+                    if self.mana >= mana_cost {
+                        self.mana -= mana_cost;
+                        Ok(format!("You cast Consecrate for {damage} damage"))
+                    } else {
+                        Err("Not enough mana to cast Consecrate".to_string())
+                    }
+                }
                 "Shield Block" | "Magic Shield" | "Divine Protection" => {
-                    let mana_cost = 4;
+                    let mana_cost = Class::mana_cost(ability_name);
 
                     if self.mana >= mana_cost {
                         self.mana -= mana_cost;
@@ -149,7 +475,48 @@ impl Player {
                     let damage = self.attack_damage() * 2;
                     Ok(format!("You use {ability_name} for {damage} damage"))
                 }
+                "Execute" | "Rain of Arrows" => {
+                    let damage = self.attack_damage() * 4;
+                    Ok(format!("You use {ability_name} for {damage} damage"))
+                }
                 "Evasion" => Ok("You use Evasion, increasing your chance to dodge".to_string()),
+                "Guard Stance" => {
+                    let mana_cost = Class::mana_cost(ability_name);
+
+                    if self.mana >= mana_cost {
+                        self.mana -= mana_cost;
+                        Ok("You take up a Guard Stance, ready to turn blows back".to_string())
+                    } else {
+                        Err("Not enough mana for Guard Stance".to_string())
+                    }
+                }
+                "Blessing" => {
+                    let mana_cost = Class::mana_cost(ability_name);
+
+                    if self.mana >= mana_cost {
+                        self.mana -= mana_cost;
+                        Ok(format!(
+                            "You cast Blessing, raising every stat by {} for {} turns",
+                            buff::BLESSING_BONUS,
+                            buff::BLESSING_TURNS
+                        ))
+                    } else {
+                        Err("Not enough mana to cast Blessing".to_string())
+                    }
+                }
+                "Haste" => {
+                    let mana_cost = Class::mana_cost(ability_name);
+
+                    if self.mana >= mana_cost {
+                        self.mana -= mana_cost;
+                        self.haste_turns = self.haste_turns.max(HASTE_TURNS);
+                        Ok(format!(
+                            "You cast Haste and will move faster for {HASTE_TURNS} turns"
+                        ))
+                    } else {
+                        Err("Not enough mana to cast Haste".to_string())
+                    }
+                }
                 _ => Ok(format!("You use {ability_name}")),
             }
         } else {
@@ -157,3 +524,281 @@ impl Player {
         }
     }
 }
+
+impl Combatant for Player {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    fn health(&self) -> i32 {
+        self.health
+    }
+
+    fn attack_damage(&self) -> i32 {
+        let base_damage = match self.class.class_type {
+            ClassType::Warrior => self.stats.strength,
+            ClassType::Mage => self.stats.intelligence / 2,
+            ClassType::Ranger => self.stats.dexterity,
+            ClassType::Cleric => self.stats.wisdom / 2,
+        };
+
+        // Add weapon damage if equipped
+        let weapon_damage = if let Some(weapon) = self.inventory.get_equipped_weapon() {
+            weapon.effective_power() + self.honing_bonus()
+        } else {
+            1 // Base damage without weapon
+        };
+
+        base_damage + weapon_damage
+    }
+
+    /// Fighting bare-handed adds its 1 to the base instead
+    fn weapon_damage(&self) -> i32 {
+        self.inventory
+            .get_equipped_weapon()
+            .map_or(0, |weapon| weapon.effective_power() + self.honing_bonus())
+    }
+
+    fn defense(&self) -> i32 {
+        let base_defense = self.stats.base_defense();
+
+        // Add armor defense
+        let armor_defense = self.inventory.get_total_armor_defense();
+
+        base_defense + armor_defense
+    }
+
+    /// Players have no resistances, so every damage type hits the same.
+    /// Guard Stance halves whatever gets through their defense.
+    fn take_hit(&mut self, amount: i32, _damage_type: DamageType) -> i32 {
+        let mut damage_taken = damage_after_defense(amount, self.defense());
+        if self.is_guarding() {
+            damage_taken = buff::guard(damage_taken).0;
+        }
+        self.health -= damage_taken;
+        damage_taken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::{Consumable, Equipment, EquipmentSlot};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_xp_curve() {
+        let mut player = Player::new("Test".to_string(), ClassType::Warrior);
+        assert_eq!(player.xp_for_next_level(), 100);
+
+        player.level = 2;
+        assert_eq!(player.xp_for_next_level(), 283);
+
+        player.level = 4;
+        assert_eq!(player.xp_for_next_level(), 800);
+    }
+
+    #[test]
+    fn test_level_up_awards_stat_points() {
+        let mut player = Player::new("Test".to_string(), ClassType::Mage);
+        let mut rng = StdRng::seed_from_u64(1);
+        assert!(!player.gain_experience(99, &mut rng));
+        assert!(player.gain_experience(1, &mut rng));
+        assert_eq!(player.level, 2);
+        assert_eq!(player.unspent_stat_points, STAT_POINTS_PER_LEVEL);
+    }
+
+    #[test]
+    fn test_allocate_stat_point_updates_derived_stats() {
+        let mut player = Player::new("Test".to_string(), ClassType::Cleric);
+        player.unspent_stat_points = 1;
+        let (con, max_health, health) =
+            (player.stats.constitution, player.max_health, player.health);
+
+        assert!(player.allocate_stat_point(StatType::Constitution).is_ok());
+        assert_eq!(player.stats.constitution, con + 1);
+        assert_eq!(player.max_health, max_health + 5);
+        assert_eq!(player.health, health + 5);
+        assert_eq!(player.unspent_stat_points, 0);
+
+        assert!(player.allocate_stat_point(StatType::Strength).is_err());
+    }
+
+    #[test]
+    fn test_each_class_starts_with_its_kit_equipped() {
+        let warrior = Player::new("Test".to_string(), ClassType::Warrior);
+        let names: Vec<&str> = warrior.inventory.items.iter().map(Item::name).collect();
+        assert_eq!(
+            names,
+            [
+                "Iron Sword",
+                "Leather Armor",
+                "Minor Health Potion",
+                "Minor Health Potion"
+            ]
+        );
+        assert_eq!(
+            warrior
+                .inventory
+                .get_equipped_weapon()
+                .map(|w| w.name.as_str()),
+            Some("Iron Sword")
+        );
+        assert_eq!(warrior.inventory.get_total_armor_defense(), 2);
+
+        let mage = Player::new("Test".to_string(), ClassType::Mage);
+        assert!(mage
+            .inventory
+            .items
+            .iter()
+            .any(|item| item.name() == "Minor Mana Potion"));
+
+        let ranger = Player::new("Test".to_string(), ClassType::Ranger);
+        assert_eq!(ranger.inventory.items.len(), 2);
+        assert_eq!(ranger.companion, Some(Companion::wolf_pup()));
+        assert!(warrior.companion.is_none());
+        assert_eq!(ranger.inventory.get_total_armor_defense(), 1);
+
+        let cleric = Player::new("Test".to_string(), ClassType::Cleric);
+        assert!(cleric
+            .inventory
+            .items
+            .iter()
+            .any(|item| item.name() == "Scroll of Healing"));
+        assert_eq!(
+            cleric
+                .inventory
+                .get_equipped_weapon()
+                .map(|w| w.name.as_str()),
+            Some("Iron Mace")
+        );
+    }
+
+    #[test]
+    fn test_abilities_unlock_with_level() {
+        let mut cleric = Player::new("Test".to_string(), ClassType::Cleric);
+        assert_eq!(cleric.available_abilities(), ["Heal"]);
+        assert!(cleric.use_ability(1).is_err());
+
+        cleric.level = 4;
+        assert_eq!(
+            cleric.available_abilities(),
+            ["Heal", "Smite", "Blessing", "Divine Protection"]
+        );
+        assert_eq!(
+            cleric.abilities_unlocked_since(1),
+            ["Smite", "Blessing", "Divine Protection"]
+        );
+        assert!(cleric.abilities_unlocked_since(4).is_empty());
+        cleric.level = 6;
+        assert_eq!(
+            cleric.abilities_unlocked_since(4),
+            ["Consecrate", "Greater Heal"]
+        );
+
+        let mut warrior = Player::new("Test".to_string(), ClassType::Warrior);
+        assert_eq!(warrior.available_abilities(), ["Slash"]);
+        warrior.level = 3;
+        assert_eq!(warrior.abilities_unlocked_since(2), ["Shield Block"]);
+    }
+
+    #[test]
+    fn test_cooldowns_tick_down_and_reset() {
+        let mut mage = Player::new("Test".to_string(), ClassType::Mage);
+        mage.level = 5;
+        assert_eq!(mage.cooldown_label(2), "(ready)");
+
+        mage.start_cooldown(2);
+        assert_eq!(mage.cooldown_left(2), Class::cooldown("Meteor"));
+        assert!(mage.use_ability(2).is_err());
+
+        mage.start_cooldown(0);
+        mage.tick_cooldowns();
+        assert_eq!(mage.cooldown_left(0), 0, "Fireball recovers in a turn");
+        assert_eq!(mage.cooldown_label(2), "(5 turns)");
+        assert_eq!(mage.cooldowns.len(), 1);
+
+        mage.reset_cooldowns();
+        assert_eq!(mage.cooldown_label(2), "(ready)");
+        assert!(mage.use_ability(2).unwrap().contains("Meteor for"));
+    }
+
+    #[test]
+    fn test_unspent_points_and_companion_default_when_missing_from_save() {
+        let player = Player::new("Test".to_string(), ClassType::Ranger);
+        let mut json = serde_json::to_value(&player).unwrap();
+        json.as_object_mut().unwrap().remove("unspent_stat_points");
+        json.as_object_mut().unwrap().remove("companion");
+
+        let loaded: Player = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.unspent_stat_points, 0);
+        assert!(loaded.companion.is_none());
+    }
+
+    #[test]
+    fn test_carry_capacity_grows_with_strength() {
+        let mut mage = Player::new("Test".to_string(), ClassType::Mage);
+        assert_eq!(mage.carry_capacity(), 45);
+        // Oak Staff, Apprentice Robe and two mana potions
+        assert_eq!(mage.carried_weight(), 6 + 12 + 1 + 1);
+        assert!(!mage.is_encumbered());
+
+        mage.unspent_stat_points = 1;
+        mage.allocate_stat_point(StatType::Strength).unwrap();
+        assert_eq!(mage.carry_capacity(), 50);
+
+        // 45 of 50 is 90%, which is encumbered
+        mage.inventory.add_item(Item::Equipment(Equipment::armor(
+            "Tower Shield",
+            EquipmentSlot::Shield,
+            3,
+        )));
+        mage.inventory
+            .items
+            .extend(std::iter::repeat_with(|| Item::Consumable(Consumable::torch())).take(8));
+        assert_eq!(mage.carried_weight(), 44);
+        assert!(!mage.is_encumbered());
+        mage.inventory
+            .add_item(Item::Consumable(Consumable::health_potion(30)));
+        assert!(mage.is_encumbered());
+        // Carrying that much slows them down
+        assert_eq!(mage.speed(), 7);
+    }
+
+    #[test]
+    fn test_speed_comes_from_dexterity_and_haste() {
+        let mut ranger = Player::new("Test".to_string(), ClassType::Ranger);
+        assert_eq!(ranger.speed(), 11);
+        ranger.stats.dexterity = 4;
+        assert_eq!(ranger.speed(), 10);
+
+        ranger.level = 5;
+        ranger.use_ability(2).unwrap();
+        assert_eq!(ranger.haste_turns, HASTE_TURNS);
+        assert_eq!(ranger.speed(), 15);
+    }
+
+    #[test]
+    fn test_equipment_saved_without_a_weight_weighs_its_slot_default() {
+        let player = Player::new("Test".to_string(), ClassType::Warrior);
+        let mut json = serde_json::to_value(&player).unwrap();
+        for item in json["inventory"]["items"].as_array_mut().unwrap() {
+            if let Some(equipment) = item.get_mut("Equipment") {
+                equipment.as_object_mut().unwrap().remove("weight");
+            }
+        }
+        json["inventory"]
+            .as_object_mut()
+            .unwrap()
+            .insert("max_size".to_string(), 20.into());
+
+        let loaded: Player = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.carried_weight(), player.carried_weight());
+        assert_eq!(loaded.inventory.items[1].weight(), 12);
+    }
+}
@@ -9,7 +9,43 @@ pub enum StatType {
     Wisdom,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl StatType {
+    pub fn iter() -> impl Iterator<Item = StatType> {
+        [
+            StatType::Strength,
+            StatType::Intelligence,
+            StatType::Dexterity,
+            StatType::Constitution,
+            StatType::Wisdom,
+        ]
+        .into_iter()
+    }
+
+    /// Three-letter form for short item summaries
+    pub fn abbreviation(self) -> &'static str {
+        match self {
+            StatType::Strength => "STR",
+            StatType::Intelligence => "INT",
+            StatType::Dexterity => "DEX",
+            StatType::Constitution => "CON",
+            StatType::Wisdom => "WIS",
+        }
+    }
+}
+
+impl std::fmt::Display for StatType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatType::Strength => write!(f, "Strength"),
+            StatType::Intelligence => write!(f, "Intelligence"),
+            StatType::Dexterity => write!(f, "Dexterity"),
+            StatType::Constitution => write!(f, "Constitution"),
+            StatType::Wisdom => write!(f, "Wisdom"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Stats {
     pub strength: i32,
     pub intelligence: i32,
@@ -29,6 +65,16 @@ impl Stats {
         }
     }
 
+    pub fn get(&self, stat_type: StatType) -> i32 {
+        match stat_type {
+            StatType::Strength => self.strength,
+            StatType::Intelligence => self.intelligence,
+            StatType::Dexterity => self.dexterity,
+            StatType::Constitution => self.constitution,
+            StatType::Wisdom => self.wisdom,
+        }
+    }
+
     pub fn modify_stat(&mut self, stat_type: StatType, amount: i32) {
         match stat_type {
             StatType::Strength => self.strength += amount,
@@ -82,6 +128,21 @@ impl Stats {
     }
 
     // Utility methods for common operations
+
+    /// Maximum health these stats give, for players and enemies alike
+    pub fn max_health(&self) -> i32 {
+        10 + self.constitution * 5
+    }
+
+    /// Maximum mana these stats give
+    pub fn max_mana(&self) -> i32 {
+        5 + self.wisdom * 3
+    }
+
+    /// Defense from constitution, before armor or level bonuses
+    pub fn base_defense(&self) -> i32 {
+        self.constitution / 2
+    }
 }
 
 impl Default for Stats {
@@ -133,4 +194,18 @@ mod tests {
         stats.increase_strength(5);
         assert_eq!(stats.strength, 15);
     }
+
+    #[test]
+    fn test_derived_stats() {
+        let mut stats = Stats::new();
+        assert_eq!(stats.max_health(), 35);
+        assert_eq!(stats.max_mana(), 20);
+        assert_eq!(stats.base_defense(), 2);
+
+        stats.modify_stat(StatType::Constitution, 1);
+        stats.modify_stat(StatType::Wisdom, 2);
+        assert_eq!(stats.max_health(), 40);
+        assert_eq!(stats.max_mana(), 26);
+        assert_eq!(stats.base_defense(), 3);
+    }
 }
@@ -0,0 +1,249 @@
+//! Abilities that hit every enemy in an area.
+//!
+//! In a fight an area ability catches everyone taking part. Out of one it
+//! lands on the map, Fireball on a tile the player aims at and Consecrate
+//! around the player, and hurts every enemy within reach of where it lands.
+//! Enemies it kills there leave their loot lying on the floor.
+
+use rand::Rng;
+
+use super::{
+    add_interaction_message, handle_enemy_defeat, loot_message, reward_defeat, CombatResult,
+};
+use super::{Combatant, DamageType};
+use crate::character::Player;
+use crate::world::{Enemy, Level, Position};
+
+/// How far away a Fireball can be aimed, in tiles
+pub const FIREBALL_RANGE: i32 = 6;
+
+/// How far from where it lands an area ability reaches, in tiles
+pub const AREA_RADIUS: i32 = 1;
+
+/// Where an area ability lands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AreaTarget {
+    /// A tile the player can see, no further than `range` away
+    Tile { range: i32 },
+    /// The player's own tile
+    Caster,
+}
+
+impl AreaTarget {
+    /// Where `ability` lands, if it covers an area
+    pub fn of_ability(ability: &str) -> Option<Self> {
+        match ability {
+            "Fireball" => Some(AreaTarget::Tile {
+                range: FIREBALL_RANGE,
+            }),
+            "Consecrate" => Some(AreaTarget::Caster),
+            _ => None,
+        }
+    }
+}
+
+/// One use of an area ability
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AreaEffect {
+    /// What the messages call it: "The fireball engulfs 3 enemies!"
+    pub source: &'static str,
+    /// Damage dealt to each enemy caught, before defense
+    pub damage: i32,
+    pub damage_type: DamageType,
+}
+
+impl AreaEffect {
+    /// `ability` dealing `damage` to each enemy caught, if it covers an area
+    pub fn for_ability(ability: &str, damage: i32) -> Option<Self> {
+        let source = match ability {
+            "Fireball" => "fireball",
+            "Consecrate" => "holy fire",
+            _ => return None,
+        };
+        Some(AreaEffect {
+            source,
+            damage,
+            damage_type: DamageType::for_ability(ability, DamageType::Physical),
+        })
+    }
+
+    /// Announces how many enemies the effect caught
+    pub fn summary(&self, caught: usize) -> String {
+        match caught {
+            0 => format!("The {} catches no one.", self.source),
+            1 => format!("The {} engulfs 1 enemy!", self.source),
+            caught => format!("The {} engulfs {caught} enemies!", self.source),
+        }
+    }
+}
+
+/// Whether `pos` is within `radius` tiles of `center`, diagonals included
+pub fn in_area(center: Position, radius: i32, pos: Position) -> bool {
+    (pos.x - center.x).abs().max((pos.y - center.y).abs()) <= radius
+}
+
+/// Hits every enemy on `level` within `radius` of `center` with `effect`,
+/// top row first. Each one caught gets its own result; one it kills is
+/// taken off the map, rewarded as a kill with its index 0 in `slain`, and
+/// leaves its loot on the floor where it stood.
+pub fn apply_area_effect_with_rng<R: Rng + ?Sized>(
+    player: &mut Player,
+    level: &mut Level,
+    center: Position,
+    radius: i32,
+    effect: &AreaEffect,
+    rng: &mut R,
+) -> Vec<(Position, CombatResult)> {
+    let mut caught: Vec<Position> = level
+        .enemies
+        .iter()
+        .filter(|(&pos, enemy)| enemy.is_alive() && in_area(center, radius, pos))
+        .map(|(&pos, _)| pos)
+        .collect();
+    caught.sort_by_key(|pos| (pos.y, pos.x));
+
+    let mut hits = Vec::with_capacity(caught.len());
+    for pos in caught {
+        let mut result = CombatResult::new();
+        let Some(enemy) = level.get_enemy_at_mut(&pos) else {
+            continue;
+        };
+        blast(enemy, effect, &mut result);
+        if !enemy.is_alive() {
+            if let Some(enemy) = level.remove_enemy_at(&pos) {
+                let (experience, gold, items) = enemy.get_drops_with_rng(rng);
+                reward_defeat(player, &enemy, experience, &mut result, rng);
+                if let Some(message) = loot_message(&enemy.name, gold, &items) {
+                    result.add_message(message);
+                }
+                level.drop_loot(pos, gold, items);
+                result.slain.push(0);
+                result.enemy_defeated = true;
+            }
+        }
+        hits.push((pos, result));
+    }
+    hits
+}
+
+/// Hits every enemy still standing in a fight with `effect`, rewarding the
+/// kills as the player's
+pub(super) fn engulf<R: Rng + ?Sized>(
+    player: &mut Player,
+    enemies: &mut [Enemy],
+    effect: &AreaEffect,
+    result: &mut CombatResult,
+    rng: &mut R,
+) {
+    let caught: Vec<usize> = (0..enemies.len())
+        .filter(|&index| enemies[index].is_alive())
+        .collect();
+    result.add_message(effect.summary(caught.len()));
+    for index in caught {
+        blast(&mut enemies[index], effect, result);
+        if !enemies[index].is_alive() {
+            handle_enemy_defeat(player, &enemies[index], index, result, rng);
+        }
+    }
+}
+
+/// Hits one enemy caught by `effect`
+fn blast(enemy: &mut Enemy, effect: &AreaEffect, result: &mut CombatResult) {
+    let damage = enemy.take_hit(effect.damage, effect.damage_type);
+    result.player_damage_dealt += damage;
+    result.add_message(format!(
+        "The {} hits the {} for {damage} damage!",
+        effect.source, enemy.name
+    ));
+    add_interaction_message(enemy, effect.damage_type, result);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::ClassType;
+    use crate::world::enemy::EnemyType;
+    use crate::world::{Tile, TileType};
+    use rand::rngs::mock::StepRng;
+
+    fn open_level() -> Level {
+        let mut level = Level::new(20, 20);
+        for row in &mut level.tiles {
+            for tile in row.iter_mut() {
+                *tile = Tile::new(TileType::Floor);
+            }
+        }
+        level
+    }
+
+    fn goblin(health: i32) -> Enemy {
+        let mut enemy = Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1);
+        enemy.health = health;
+        enemy
+    }
+
+    #[test]
+    fn test_only_abilities_that_cover_an_area_get_one() {
+        assert_eq!(
+            AreaTarget::of_ability("Fireball"),
+            Some(AreaTarget::Tile {
+                range: FIREBALL_RANGE
+            })
+        );
+        assert_eq!(
+            AreaTarget::of_ability("Consecrate"),
+            Some(AreaTarget::Caster)
+        );
+        assert_eq!(AreaTarget::of_ability("Smite"), None);
+
+        let consecrate = AreaEffect::for_ability("Consecrate", 10).unwrap();
+        assert_eq!(consecrate.damage_type, DamageType::Holy);
+        assert!(AreaEffect::for_ability("Meteor", 10).is_none());
+        assert_eq!(consecrate.summary(3), "The holy fire engulfs 3 enemies!");
+    }
+
+    #[test]
+    fn test_a_blast_hits_the_area_and_leaves_the_dead_and_their_loot() {
+        let mut player = Player::new("Hero".to_string(), ClassType::Mage);
+        let mut level = open_level();
+        let center = Position::new(10, 10);
+        let mut doomed = goblin(1);
+        doomed.item_drop_chance = 1.0;
+        level.enemies.insert(Position::new(11, 9), doomed);
+        level.enemies.insert(Position::new(9, 11), goblin(500));
+        level.enemies.insert(Position::new(12, 10), goblin(500));
+
+        let effect = AreaEffect::for_ability("Fireball", 20).unwrap();
+        // Rolls of 0 drop everything a goblin can carry
+        let hits = apply_area_effect_with_rng(
+            &mut player,
+            &mut level,
+            center,
+            AREA_RADIUS,
+            &effect,
+            &mut StepRng::new(0, 0),
+        );
+
+        let positions: Vec<Position> = hits.iter().map(|(pos, _)| *pos).collect();
+        assert_eq!(positions, [Position::new(11, 9), Position::new(9, 11)]);
+
+        let (_, killed) = &hits[0];
+        assert_eq!(killed.slain, [0]);
+        assert!(killed.experience_gained > 0);
+        assert!(!level.enemies.contains_key(&Position::new(11, 9)));
+        assert!(level.items.contains_key(&Position::new(11, 9)));
+        assert!(killed
+            .messages
+            .iter()
+            .any(|m| m.starts_with("The Goblin drops")));
+
+        let (_, survived) = &hits[1];
+        assert!(survived.slain.is_empty());
+        assert_eq!(
+            level.enemies[&Position::new(9, 11)].health,
+            500 - survived.player_damage_dealt
+        );
+        // Out of reach, untouched
+        assert_eq!(level.enemies[&Position::new(12, 10)].health, 500);
+    }
+}
@@ -0,0 +1,169 @@
+//! How a blow's damage came about.
+//!
+//! Every blow that lands in a turn is kept in `CombatResult::hits` with the
+//! numbers that went into it, and its line in the combat log is written
+//! from them by `Hit::describe`, so the terminal, GUI and web all show the
+//! same breakdown: "You hit the Ogre for 12 (8 base +4 weapon, crit x1.5 =
+//! 18, -6 armor)".
+
+use serde::{Deserialize, Serialize};
+
+use super::context::scale;
+
+/// What a critical hit's multiplier is put down to in a breakdown
+pub const CRITICAL_SOURCE: &str = "crit";
+
+/// Which side struck a blow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Striker {
+    Player,
+    Enemy,
+}
+
+/// Something that changed a blow before it reached the defender
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DamageModifier {
+    /// A flat amount on top of the base: "+4 weapon"
+    Bonus { source: String, amount: i32 },
+    /// The blow so far scaled: "crit x1.5"
+    Multiplier { source: String, factor: f32 },
+}
+
+/// One blow that landed, from its base damage to what the defender took
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hit {
+    pub attacker: Striker,
+    /// The enemy on the other end of the blow
+    pub enemy: String,
+    pub base: i32,
+    /// In the order they were applied
+    pub modifiers: Vec<DamageModifier>,
+    /// Damage the defender took once their armor and resistances had their say
+    pub damage: i32,
+}
+
+impl Hit {
+    /// A blow by `attacker` against or from `enemy`, starting at `base`
+    pub fn new(attacker: Striker, enemy: &str, base: i32) -> Self {
+        Hit {
+            attacker,
+            enemy: enemy.to_string(),
+            base,
+            modifiers: Vec::new(),
+            damage: 0,
+        }
+    }
+
+    /// Adds `amount` from `source`, if there's anything to add
+    pub fn bonus(mut self, source: &str, amount: i32) -> Self {
+        if amount != 0 {
+            self.modifiers.push(DamageModifier::Bonus {
+                source: source.to_string(),
+                amount,
+            });
+        }
+        self
+    }
+
+    /// Scales the blow by `factor` for `source`
+    pub fn multiplier(mut self, source: &str, factor: f32) -> Self {
+        self.modifiers.push(DamageModifier::Multiplier {
+            source: source.to_string(),
+            factor,
+        });
+        self
+    }
+
+    /// Whether the player struck this blow and it was a critical hit
+    pub fn is_player_critical(&self) -> bool {
+        self.attacker == Striker::Player
+            && self.modifiers.iter().any(|modifier| {
+                matches!(modifier, DamageModifier::Multiplier { source, .. } if source == CRITICAL_SOURCE)
+            })
+    }
+
+    /// The blow as it reached the defender, before their armor
+    pub fn raw(&self) -> i32 {
+        self.modifiers
+            .iter()
+            .fold(self.base, |running, modifier| match modifier {
+                DamageModifier::Bonus { amount, .. } => running + amount,
+                DamageModifier::Multiplier { factor, .. } => scale(running, *factor),
+            })
+    }
+
+    /// The combat log's line for the blow: "The Ogre hits you for 7 (9 base,
+    /// -2 armor)", or just "... for 7 damage!" when nothing changed it
+    pub fn describe(&self) -> String {
+        let lead = match self.attacker {
+            Striker::Player => format!("You hit the {} for {}", self.enemy, self.damage),
+            Striker::Enemy => format!("The {} hits you for {}", self.enemy, self.damage),
+        };
+        let soaked = self.raw() - self.damage;
+        if self.modifiers.is_empty() && soaked == 0 {
+            return format!("{lead} damage!");
+        }
+
+        let mut breakdown = format!("{} base", self.base);
+        let mut running = self.base;
+        for modifier in &self.modifiers {
+            match modifier {
+                DamageModifier::Bonus { source, amount } => {
+                    running += amount;
+                    breakdown.push_str(&format!(" {amount:+} {source}"));
+                }
+                DamageModifier::Multiplier { source, factor } => {
+                    running = scale(running, *factor);
+                    breakdown
+                        .push_str(&format!(", {source} x{} = {running}", factor_text(*factor)));
+                }
+            }
+        }
+        if soaked > 0 {
+            breakdown.push_str(&format!(", -{soaked} armor"));
+        } else if soaked < 0 {
+            breakdown.push_str(&format!(", +{} weakness", -soaked));
+        }
+        format!("{lead} ({breakdown})")
+    }
+}
+
+/// A multiplier as the log shows it: 1.5, 1.25, 2
+fn factor_text(factor: f32) -> String {
+    let text = format!("{factor:.2}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hits_are_broken_down_step_by_step() {
+        let mut hit = Hit::new(Striker::Player, "Ogre", 8)
+            .bonus("weapon", 4)
+            .multiplier("crit", 1.5);
+        hit.damage = 12;
+        assert_eq!(hit.raw(), 18);
+        assert_eq!(
+            hit.describe(),
+            "You hit the Ogre for 12 (8 base +4 weapon, crit x1.5 = 18, -6 armor)"
+        );
+
+        let mut plain = Hit::new(Striker::Enemy, "Goblin", 5).bonus("weapon", 0);
+        plain.damage = 5;
+        assert_eq!(plain.describe(), "The Goblin hits you for 5 damage!");
+        plain.damage = 3;
+        assert_eq!(
+            plain.describe(),
+            "The Goblin hits you for 3 (5 base, -2 armor)"
+        );
+
+        let mut weak = Hit::new(Striker::Player, "Frost Wraith", 10).multiplier("flanked", 1.25);
+        weak.damage = 20;
+        assert_eq!(
+            weak.describe(),
+            "You hit the Frost Wraith for 20 (10 base, flanked x1.25 = 13, +7 weakness)"
+        );
+    }
+}
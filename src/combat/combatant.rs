@@ -0,0 +1,122 @@
+//! What players and enemies have in common in a fight.
+//!
+//! Both sides roll their attacks and soak hits the same way, so combat code
+//! works on the `Combatant` trait instead of on either type. The per-type
+//! differences stay in the implementations: players add their weapon and
+//! armor, enemies their level bonuses and resistances.
+
+use rand::Rng;
+
+use super::{resolve_attack, AttackOutcome, DamageType};
+use crate::character::Stats;
+
+/// Anything that can trade blows in a fight
+pub trait Combatant {
+    fn name(&self) -> &str;
+    fn stats(&self) -> &Stats;
+    fn health(&self) -> i32;
+    /// Raw damage of a basic attack, before the defender's defense
+    fn attack_damage(&self) -> i32;
+    /// Part of `attack_damage` that comes from a wielded weapon
+    fn weapon_damage(&self) -> i32 {
+        0
+    }
+    /// Part of `attack_damage` lent by a pack leader nearby
+    fn rally_bonus(&self) -> i32 {
+        0
+    }
+    fn defense(&self) -> i32;
+    /// Applies a hit, returning the damage actually taken
+    fn take_hit(&mut self, amount: i32, damage_type: DamageType) -> i32;
+
+    fn is_alive(&self) -> bool {
+        self.health() > 0
+    }
+}
+
+/// Damage a hit does once defense is taken off; a hit that lands always
+/// does at least 1
+pub fn damage_after_defense(amount: i32, defense: i32) -> i32 {
+    (amount - defense).max(1)
+}
+
+/// Rolls one blow of `base_damage` from `attacker` against `defender` and
+/// applies it. Returns the roll and the damage dealt, 0 when dodged.
+pub fn strike<A, D, R>(
+    attacker: &A,
+    defender: &mut D,
+    base_damage: i32,
+    damage_type: DamageType,
+    rng: &mut R,
+) -> (AttackOutcome, i32)
+where
+    A: Combatant + ?Sized,
+    D: Combatant + ?Sized,
+    R: Rng + ?Sized,
+{
+    let outcome = resolve_attack(attacker.stats(), defender.stats(), base_damage, rng);
+    let damage = match outcome {
+        AttackOutcome::Dodged => 0,
+        _ => defender.take_hit(outcome.damage(), damage_type),
+    };
+    (outcome, damage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::{ClassType, Player};
+    use crate::world::enemy::EnemyType;
+    use crate::world::Enemy;
+    use rand::rngs::mock::StepRng;
+
+    #[test]
+    fn test_defense_never_blocks_a_hit_entirely() {
+        assert_eq!(damage_after_defense(10, 3), 7);
+        assert_eq!(damage_after_defense(2, 9), 1);
+    }
+
+    #[test]
+    fn test_players_and_enemies_share_stat_math() {
+        let player = Player::new("Hero".to_string(), ClassType::Warrior);
+        let enemy = Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1);
+
+        for combatant in [&player as &dyn Combatant, &enemy] {
+            assert_eq!(combatant.health(), combatant.stats().max_health());
+            assert!(combatant.defense() >= combatant.stats().base_defense());
+            assert!(combatant.is_alive());
+        }
+    }
+
+    #[test]
+    fn test_strike_works_in_both_directions() {
+        // Never dodge, never crit
+        let mut rng = StepRng::new(u64::MAX, 0);
+        let mut player = Player::new("Hero".to_string(), ClassType::Warrior);
+        let mut enemy = Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1);
+
+        let (outcome, dealt) = strike(
+            &player,
+            &mut enemy,
+            player.attack_damage(),
+            DamageType::Physical,
+            &mut rng,
+        );
+        assert_eq!(outcome, AttackOutcome::Hit(player.attack_damage()));
+        assert_eq!(
+            dealt,
+            damage_after_defense(player.attack_damage(), enemy.defense())
+        );
+        assert_eq!(enemy.health, enemy.max_health - dealt);
+
+        let health = player.health;
+        let (_, taken) = strike(
+            &enemy,
+            &mut player,
+            enemy.attack_damage(),
+            DamageType::Physical,
+            &mut rng,
+        );
+        assert_eq!(player.health, health - taken);
+    }
+}
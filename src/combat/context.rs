@@ -0,0 +1,250 @@
+//! Where a fight happens, as far as it changes the blows.
+//!
+//! Combat doesn't move anyone around, but where the player stands still
+//! counts. Enemies on more than one side hit harder, a player who opened
+//! the fight by catching an enemy unawares or striking it from range hits
+//! harder on the first turn, and one fighting knee-deep in water or on
+//! loose rubble hits a little softer, and one with nowhere to step back to
+//! can't flee at all. The context is read off the level before each turn
+//! and handed to the turn, which stays free of the map.
+
+use super::breakdown::Hit;
+use super::Combatant;
+use crate::world::{Level, TileType};
+
+/// Extra damage enemies do to a flanked player, as a share of the blow
+pub const FLANKED_DAMAGE_BONUS: f32 = 0.2;
+
+/// Extra damage the player does on the first turn of a fight they opened,
+/// as a share of the blow
+pub const OPENING_STRIKE_BONUS: f32 = 0.25;
+
+/// Damage the player loses fighting on unsteady ground, as a share of the
+/// blow
+pub const UNSTEADY_FOOTING_PENALTY: f32 = 0.1;
+
+/// How where the player stands changes this turn's blows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CombatContext {
+    /// More than one enemy stands next to the player
+    pub flanked: bool,
+    /// The player opened the fight with a sneak attack or from range, and
+    /// this is its first turn
+    pub opening_strike: bool,
+    /// What the player stands in, when it's water or rubble
+    pub footing: Option<TileType>,
+    /// There's nowhere to flee to
+    pub cornered: bool,
+}
+
+impl CombatContext {
+    /// Reads the context off `level` around the player; `opening_strike`
+    /// says whether this is the first turn of a fight the player opened.
+    /// Whether they're cornered is left for the game to work out.
+    pub fn from_level(level: &Level, opening_strike: bool) -> Self {
+        let player = level.player_position;
+        let neighbours = level
+            .enemies
+            .iter()
+            .filter(|(pos, enemy)| {
+                enemy.is_alive() && (pos.x - player.x).abs().max((pos.y - player.y).abs()) == 1
+            })
+            .count();
+        let footing = level
+            .get_tile(player.x, player.y)
+            .map(|tile| tile.tile_type)
+            .filter(|tile_type| matches!(tile_type, TileType::ShallowWater | TileType::Rubble));
+        CombatContext {
+            flanked: neighbours > 1,
+            opening_strike,
+            footing,
+            cornered: false,
+        }
+    }
+
+    /// Share of a blow from the player that lands, and what changed it
+    fn player_share(&self) -> (f32, Vec<&'static str>) {
+        let mut share = 1.0;
+        let mut sources = Vec::new();
+        if self.opening_strike {
+            share += OPENING_STRIKE_BONUS;
+            sources.push("opening strike");
+        }
+        if self.footing.is_some() {
+            share -= UNSTEADY_FOOTING_PENALTY;
+            sources.push("footing");
+        }
+        (share, sources)
+    }
+
+    /// What a blow of `damage` from the player comes to
+    pub fn player_damage(&self, damage: i32) -> i32 {
+        scale(damage, self.player_share().0)
+    }
+
+    /// What a blow of `damage` from an enemy comes to
+    pub fn enemy_damage(&self, damage: i32) -> i32 {
+        if self.flanked {
+            scale(damage, 1.0 + FLANKED_DAMAGE_BONUS)
+        } else {
+            damage
+        }
+    }
+
+    /// `hit`, a blow from the player, with what this context does to it
+    pub fn player_hit(&self, hit: Hit) -> Hit {
+        match self.player_share() {
+            (_, sources) if sources.is_empty() => hit,
+            (share, sources) => hit.multiplier(&sources.join(" and "), share),
+        }
+    }
+
+    /// `hit`, a blow from an enemy, with what this context does to it
+    pub fn enemy_hit(&self, hit: Hit) -> Hit {
+        if self.flanked {
+            hit.multiplier("flanked", 1.0 + FLANKED_DAMAGE_BONUS)
+        } else {
+            hit
+        }
+    }
+
+    /// One line per modifier in play, for the combat screen:
+    /// "Flanked: enemies deal +20% damage"
+    pub fn labels(&self) -> Vec<String> {
+        let percent = |share: f32| (share * 100.0).round() as i32;
+        let mut labels = Vec::new();
+        if self.flanked {
+            labels.push(format!(
+                "Flanked: enemies deal +{}% damage",
+                percent(FLANKED_DAMAGE_BONUS)
+            ));
+        }
+        if self.opening_strike {
+            labels.push(format!(
+                "Opening strike: you deal +{}% damage this turn",
+                percent(OPENING_STRIKE_BONUS)
+            ));
+        }
+        if let Some(footing) = self.footing {
+            labels.push(format!(
+                "Unsteady footing ({}): you deal -{}% damage",
+                footing.name().to_lowercase(),
+                percent(UNSTEADY_FOOTING_PENALTY)
+            ));
+        }
+        if self.cornered {
+            labels.push("Cornered: there's nowhere to flee".to_string());
+        }
+        labels
+    }
+}
+
+/// `damage` times `share`, rounded, never turning a blow that lands into
+/// one that doesn't
+pub(super) fn scale(damage: i32, share: f32) -> i32 {
+    if damage <= 0 {
+        return damage;
+    }
+    ((damage as f32 * share).round() as i32).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::enemy::EnemyType;
+    use crate::world::{Enemy, Position, Tile};
+
+    fn open_level() -> Level {
+        let mut level = Level::new(7, 7);
+        for row in level.tiles.iter_mut() {
+            for tile in row.iter_mut() {
+                *tile = Tile::floor();
+            }
+        }
+        level.player_position = Position::new(3, 3);
+        level
+    }
+
+    fn add_goblin(level: &mut Level, x: i32, y: i32) {
+        level.enemies.insert(
+            Position::new(x, y),
+            Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1),
+        );
+    }
+
+    #[test]
+    fn test_context_is_read_off_the_level() {
+        let mut level = open_level();
+        add_goblin(&mut level, 4, 3);
+        assert_eq!(
+            CombatContext::from_level(&level, false),
+            CombatContext::default()
+        );
+
+        // One more beside the player, diagonally, flanks them; one two
+        // tiles off doesn't
+        add_goblin(&mut level, 1, 3);
+        assert!(!CombatContext::from_level(&level, false).flanked);
+        add_goblin(&mut level, 2, 2);
+        assert!(CombatContext::from_level(&level, false).flanked);
+        level.get_enemy_at_mut(&Position::new(2, 2)).unwrap().health = 0;
+        assert!(!CombatContext::from_level(&level, false).flanked);
+
+        level.tiles[3][3] = Tile::shallow_water();
+        let context = CombatContext::from_level(&level, true);
+        assert_eq!(context.footing, Some(TileType::ShallowWater));
+        assert!(context.opening_strike);
+        level.tiles[3][3] = Tile::rubble();
+        assert_eq!(
+            CombatContext::from_level(&level, false).footing,
+            Some(TileType::Rubble)
+        );
+    }
+
+    #[test]
+    fn test_modifiers_scale_the_blows_and_are_listed() {
+        let none = CombatContext::default();
+        assert_eq!(none.player_damage(20), 20);
+        assert_eq!(none.enemy_damage(20), 20);
+        assert!(none.labels().is_empty());
+
+        let flanked = CombatContext {
+            flanked: true,
+            ..none
+        };
+        assert_eq!(flanked.enemy_damage(20), 24);
+        assert_eq!(flanked.player_damage(20), 20);
+
+        let opening = CombatContext {
+            opening_strike: true,
+            ..none
+        };
+        assert_eq!(opening.player_damage(20), 25);
+
+        let wading = CombatContext {
+            footing: Some(TileType::ShallowWater),
+            ..none
+        };
+        assert_eq!(wading.player_damage(20), 18);
+        // A blow that lands still does at least 1
+        assert_eq!(wading.player_damage(1), 1);
+        assert_eq!(wading.player_damage(0), 0);
+
+        let all = CombatContext {
+            flanked: true,
+            opening_strike: true,
+            footing: Some(TileType::Rubble),
+            cornered: true,
+        };
+        assert_eq!(all.player_damage(20), 23);
+        assert_eq!(
+            all.labels(),
+            [
+                "Flanked: enemies deal +20% damage",
+                "Opening strike: you deal +25% damage this turn",
+                "Unsteady footing (rubble): you deal -10% damage",
+                "Cornered: there's nowhere to flee",
+            ]
+        );
+    }
+}
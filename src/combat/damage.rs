@@ -0,0 +1,198 @@
+//! Damage types and how enemies react to them.
+//!
+//! Weapons and damaging abilities deal a `DamageType`. Each enemy has an
+//! `Affinity` toward every type, taken from its kind and theme, and the
+//! affinity's multiplier is applied after defense when a hit resolves.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// What a hit is made of, used to apply enemy resistances
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DamageType {
+    #[default]
+    Physical,
+    Fire,
+    Ice,
+    Lightning,
+    /// Divine power from Cleric abilities and blessed weapons
+    Holy,
+    Poison,
+}
+
+impl DamageType {
+    /// Types a weapon can be enchanted with
+    pub const ELEMENTS: [DamageType; 4] = [
+        DamageType::Fire,
+        DamageType::Ice,
+        DamageType::Lightning,
+        DamageType::Holy,
+    ];
+
+    /// Picks one of the weapon enchantments at random
+    pub fn random_element<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self::ELEMENTS[rng.gen_range(0..Self::ELEMENTS.len())]
+    }
+
+    /// Damage type of a damaging ability. Weapon skills deal the type of the
+    /// equipped weapon.
+    pub fn for_ability(ability_name: &str, weapon: DamageType) -> Self {
+        match ability_name {
+            "Fireball" | "Meteor" => DamageType::Fire,
+            "Smite" | "Consecrate" => DamageType::Holy,
+            "Slash" | "Aimed Shot" | "Execute" | "Rain of Arrows" => weapon,
+            _ => DamageType::Physical,
+        }
+    }
+
+    /// Word added to the name of a weapon dealing this type
+    pub fn weapon_prefix(&self) -> Option<&'static str> {
+        match self {
+            DamageType::Fire => Some("Flaming"),
+            DamageType::Ice => Some("Frozen"),
+            DamageType::Lightning => Some("Shocking"),
+            DamageType::Holy => Some("Blessed"),
+            DamageType::Physical | DamageType::Poison => None,
+        }
+    }
+
+    /// How the damage is described in combat messages
+    fn noun(&self) -> &'static str {
+        match self {
+            DamageType::Physical => "blow",
+            DamageType::Fire => "flames",
+            DamageType::Ice => "frost",
+            DamageType::Lightning => "lightning",
+            DamageType::Holy => "holy light",
+            DamageType::Poison => "poison",
+        }
+    }
+
+    fn verb(&self) -> &'static str {
+        match self {
+            DamageType::Physical => "staggers",
+            DamageType::Fire => "sear",
+            DamageType::Ice => "bites into",
+            DamageType::Lightning => "courses through",
+            DamageType::Holy => "burns",
+            DamageType::Poison => "ravages",
+        }
+    }
+}
+
+impl fmt::Display for DamageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DamageType::Physical => write!(f, "Physical"),
+            DamageType::Fire => write!(f, "Fire"),
+            DamageType::Ice => write!(f, "Ice"),
+            DamageType::Lightning => write!(f, "Lightning"),
+            DamageType::Holy => write!(f, "Holy"),
+            DamageType::Poison => write!(f, "Poison"),
+        }
+    }
+}
+
+/// How strongly an enemy reacts to one damage type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Affinity {
+    Immune,
+    Resistant,
+    #[default]
+    Neutral,
+    Weak,
+}
+
+impl Affinity {
+    /// Multiplier applied to damage after defense
+    pub fn multiplier(&self) -> f32 {
+        match self {
+            Affinity::Immune => 0.0,
+            Affinity::Resistant => 0.5,
+            Affinity::Neutral => 1.0,
+            Affinity::Weak => 2.0,
+        }
+    }
+}
+
+/// Message describing a notable interaction, such as
+/// "The flames sear the Frost Wraith for double damage!"
+pub fn interaction_message(
+    damage_type: DamageType,
+    affinity: Affinity,
+    enemy_name: &str,
+) -> Option<String> {
+    let noun = damage_type.noun();
+    match affinity {
+        Affinity::Immune => Some(format!("The {enemy_name} is unaffected by the {noun}!")),
+        Affinity::Resistant => Some(format!(
+            "The {enemy_name} resists the {noun}, taking half damage."
+        )),
+        Affinity::Neutral => None,
+        Affinity::Weak => {
+            let lead = if damage_type == DamageType::Holy {
+                "Holy light".to_string()
+            } else {
+                format!("The {noun}")
+            };
+            Some(format!(
+                "{lead} {} the {enemy_name} for double damage!",
+                damage_type.verb()
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_affinity_multipliers() {
+        assert_eq!(Affinity::Immune.multiplier(), 0.0);
+        assert_eq!(Affinity::Resistant.multiplier(), 0.5);
+        assert_eq!(Affinity::Neutral.multiplier(), 1.0);
+        assert_eq!(Affinity::Weak.multiplier(), 2.0);
+    }
+
+    #[test]
+    fn test_interaction_messages() {
+        assert_eq!(
+            interaction_message(DamageType::Fire, Affinity::Weak, "Frost Wraith").as_deref(),
+            Some("The flames sear the Frost Wraith for double damage!")
+        );
+        assert_eq!(
+            interaction_message(DamageType::Holy, Affinity::Weak, "Skeleton").as_deref(),
+            Some("Holy light burns the Skeleton for double damage!")
+        );
+        assert_eq!(
+            interaction_message(DamageType::Fire, Affinity::Resistant, "Fire Elemental").as_deref(),
+            Some("The Fire Elemental resists the flames, taking half damage.")
+        );
+        assert_eq!(
+            interaction_message(DamageType::Poison, Affinity::Immune, "Golem").as_deref(),
+            Some("The Golem is unaffected by the poison!")
+        );
+        assert_eq!(
+            interaction_message(DamageType::Ice, Affinity::Neutral, "Goblin"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_weapon_skills_use_the_weapon_type() {
+        assert_eq!(
+            DamageType::for_ability("Slash", DamageType::Ice),
+            DamageType::Ice
+        );
+        assert_eq!(
+            DamageType::for_ability("Fireball", DamageType::Ice),
+            DamageType::Fire
+        );
+        assert_eq!(
+            DamageType::for_ability("Heal", DamageType::Holy),
+            DamageType::Physical
+        );
+    }
+}
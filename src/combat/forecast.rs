@@ -0,0 +1,283 @@
+//! What the next exchange of blows is likely to do.
+//!
+//! The combat screens show a forecast before the player picks an action: the
+//! damage their attack does to the target, the target's counterattack and
+//! the chance of getting away. It's worked out with the same functions that
+//! resolve a turn, so the preview can't drift from what actually happens.
+
+use std::fmt;
+
+use super::{
+    critical_chance, critical_damage, damage_after_defense, dodge_chance, flee_chance,
+    sneak_attack_bonus, Affinity, Combatant, DamageType, MAX_CRITICAL_MULTIPLIER,
+    MIN_CRITICAL_MULTIPLIER,
+};
+use crate::character::Player;
+use crate::world::{Alertness, Enemy};
+
+/// Damage a blow does if it lands, from a plain hit to the hardest critical
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DamageRange {
+    pub min: i32,
+    pub max: i32,
+}
+
+impl DamageRange {
+    pub fn contains(&self, damage: i32) -> bool {
+        (self.min..=self.max).contains(&damage)
+    }
+}
+
+impl fmt::Display for DamageRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.min == self.max {
+            write!(f, "{}", self.min)
+        } else {
+            write!(f, "{}-{}", self.min, self.max)
+        }
+    }
+}
+
+/// The odds of the player's next attack, the target's counterattack and
+/// fleeing
+#[derive(Debug, Clone, PartialEq)]
+pub struct CombatForecast {
+    pub enemy: String,
+    /// Damage the player's attack does to the target when it lands
+    pub attack: DamageRange,
+    pub hit_chance: f64,
+    pub critical_chance: f64,
+    /// What the player's weapon deals and how the target takes it
+    pub damage_type: DamageType,
+    pub affinity: Affinity,
+    /// The target hasn't noticed the player, so the attack sneaks in
+    pub sneak_attack: bool,
+    /// Damage the target's counterattack does to the player when it lands
+    pub counter: DamageRange,
+    pub counter_hit_chance: f64,
+    pub counter_critical_chance: f64,
+    pub flee_chance: f64,
+}
+
+/// Forecasts the player attacking `enemy` and it striking back. A
+/// `cornered` player can't flee at all.
+pub fn forecast(player: &Player, enemy: &Enemy, cornered: bool) -> CombatForecast {
+    let damage_type = player.weapon_damage_type();
+    let sneak_attack = enemy.alertness == Alertness::Unaware;
+    let attack_landed = |raw: i32| {
+        let damage = enemy.damage_from(raw, damage_type);
+        if sneak_attack {
+            damage + sneak_attack_bonus(damage)
+        } else {
+            damage
+        }
+    };
+    let counter_landed = |raw: i32| damage_after_defense(raw, player.defense());
+
+    CombatForecast {
+        enemy: enemy.name.clone(),
+        attack: landed_range(player.attack_damage(), attack_landed),
+        hit_chance: 1.0 - dodge_chance(&enemy.stats),
+        critical_chance: critical_chance(&player.stats),
+        damage_type,
+        affinity: enemy.affinity(damage_type),
+        sneak_attack,
+        counter: landed_range(enemy.attack_damage(), counter_landed),
+        // A player who lost their footing can't dodge it
+        counter_hit_chance: if player.off_balance {
+            1.0
+        } else {
+            1.0 - dodge_chance(&player.stats)
+        },
+        counter_critical_chance: critical_chance(&enemy.stats),
+        flee_chance: flee_chance(&player.stats, cornered),
+    }
+}
+
+/// Damage from a plain hit of `base_damage` to the hardest critical, after
+/// `landed` applies the defender's defense and the like
+fn landed_range(base_damage: i32, landed: impl Fn(i32) -> i32) -> DamageRange {
+    let damages = [
+        landed(base_damage),
+        landed(critical_damage(base_damage, MIN_CRITICAL_MULTIPLIER)),
+        landed(critical_damage(base_damage, MAX_CRITICAL_MULTIPLIER)),
+    ];
+    DamageRange {
+        min: damages.into_iter().min().unwrap_or(0),
+        max: damages.into_iter().max().unwrap_or(0),
+    }
+}
+
+fn percent(chance: f64) -> u32 {
+    (chance * 100.0).round() as u32
+}
+
+impl CombatForecast {
+    /// "Attack: 6-12 damage, 93% to hit, 10% critical (weak to Fire)"
+    pub fn attack_line(&self) -> String {
+        let mut notes = Vec::new();
+        match self.affinity {
+            Affinity::Weak => notes.push(format!("weak to {}", self.damage_type)),
+            Affinity::Resistant => notes.push(format!("resists {}", self.damage_type)),
+            Affinity::Immune => notes.push(format!("immune to {}", self.damage_type)),
+            Affinity::Neutral => {}
+        }
+        if self.sneak_attack {
+            notes.push("sneak attack".to_string());
+        }
+        let notes = if notes.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", notes.join(", "))
+        };
+        format!(
+            "Attack: {} damage, {}% to hit, {}% critical{notes}",
+            self.attack,
+            percent(self.hit_chance),
+            percent(self.critical_chance)
+        )
+    }
+
+    /// "Goblin hits back: 2-5 damage, 92% to hit | Flee: 45%"
+    pub fn counter_line(&self) -> String {
+        format!(
+            "{} hits back: {} damage, {}% to hit | Flee: {}%",
+            self.enemy,
+            self.counter,
+            percent(self.counter_hit_chance),
+            percent(self.flee_chance)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::ClassType;
+    use crate::combat::{process_group_combat_turn_with_rng, CombatAction, CombatContext};
+    use crate::item::{Equipment, Item};
+    use crate::world::enemy::EnemyType;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn hardy(mut enemy: Enemy) -> Enemy {
+        enemy.max_health = 100_000;
+        enemy.health = enemy.max_health;
+        enemy
+    }
+
+    #[test]
+    fn test_resolved_turns_stay_within_the_forecast() {
+        let mut rng = StdRng::seed_from_u64(11);
+        for (class, enemy_type, unaware) in [
+            (ClassType::Warrior, EnemyType::Orc, false),
+            (ClassType::Mage, EnemyType::Skeleton, true),
+            (ClassType::Ranger, EnemyType::Goblin, false),
+            (ClassType::Cleric, EnemyType::Elemental, false),
+        ] {
+            let mut player = Player::new("Hero".to_string(), class);
+            player.companion = None;
+            player.max_health = 100_000;
+            player.health = player.max_health;
+            let mut enemy = hardy(Enemy::new("Foe".to_string(), enemy_type, 4));
+            if !unaware {
+                enemy.alert(crate::world::Position::new(0, 0));
+            }
+
+            let (mut hits, mut counters, turns) = (0, 0, 2000);
+            let mut expected = forecast(&player, &enemy, false);
+            for _ in 0..turns {
+                // Gear wears down, so forecast every turn afresh
+                expected = forecast(&player, &enemy, false);
+                let mut enemies = vec![enemy.clone()];
+                let result = process_group_combat_turn_with_rng(
+                    &mut player,
+                    &mut enemies,
+                    0,
+                    CombatAction::Attack,
+                    &CombatContext::default(),
+                    &mut rng,
+                    &mut StdRng::seed_from_u64(0),
+                );
+                player.health = player.max_health;
+                if result.player_damage_dealt > 0 {
+                    hits += 1;
+                    assert!(
+                        expected.attack.contains(result.player_damage_dealt),
+                        "{class:?} dealt {} outside {}",
+                        result.player_damage_dealt,
+                        expected.attack
+                    );
+                }
+                if result.enemy_damage_dealt > 0 {
+                    counters += 1;
+                    assert!(expected.counter.contains(result.enemy_damage_dealt));
+                }
+            }
+
+            let hit_rate = f64::from(hits) / f64::from(turns);
+            let counter_rate = f64::from(counters) / f64::from(turns);
+            assert!((hit_rate - expected.hit_chance).abs() < 0.05, "{hit_rate}");
+            assert!(
+                (counter_rate - expected.counter_hit_chance).abs() < 0.05,
+                "{counter_rate}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_flee_forecast_matches_escapes() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let player = Player::new("Hero".to_string(), ClassType::Warrior);
+        let enemy = hardy(Enemy::new("Foe".to_string(), EnemyType::Goblin, 1));
+        let expected = forecast(&player, &enemy, false).flee_chance;
+
+        let turns = 2000;
+        let escapes = (0..turns)
+            .filter(|_| {
+                let mut player = player.clone();
+                player.max_health = 100_000;
+                player.health = player.max_health;
+                process_group_combat_turn_with_rng(
+                    &mut player,
+                    &mut [enemy.clone()],
+                    0,
+                    CombatAction::Flee,
+                    &CombatContext::default(),
+                    &mut rng,
+                    &mut StdRng::seed_from_u64(0),
+                )
+                .player_fled
+            })
+            .count();
+        assert!((escapes as f64 / f64::from(turns) - expected).abs() < 0.05);
+
+        assert_eq!(forecast(&player, &enemy, true).flee_chance, 0.0);
+        assert!(forecast(&player, &enemy, true)
+            .counter_line()
+            .ends_with("Flee: 0%"));
+    }
+
+    #[test]
+    fn test_lines_note_affinities_and_off_balance() {
+        let mut player = Player::new("Hero".to_string(), ClassType::Cleric);
+        let mut skeleton = hardy(Enemy::new("Skeleton".to_string(), EnemyType::Skeleton, 2));
+        skeleton.alert(crate::world::Position::new(0, 0));
+        let mut mace = Equipment::weapon("Blessed Mace", 4);
+        mace.damage_type = DamageType::Holy;
+        player.inventory.add_item(Item::Equipment(mace));
+        player
+            .inventory
+            .equip_item(player.inventory.items.len() - 1)
+            .unwrap();
+
+        let holy = forecast(&player, &skeleton, false);
+        assert!(holy.attack_line().ends_with("(weak to Holy)"));
+        assert!(holy.counter_line().starts_with("Skeleton hits back"));
+        assert!(holy.counter_hit_chance < 1.0);
+
+        player.off_balance = true;
+        assert_eq!(forecast(&player, &skeleton, false).counter_hit_chance, 1.0);
+        assert_eq!(DamageRange { min: 3, max: 3 }.to_string(), "3");
+    }
+}
@@ -1,10 +1,38 @@
+pub mod area;
+pub mod breakdown;
+pub mod combatant;
+pub mod context;
+pub mod damage;
+pub mod forecast;
+pub mod playback;
+
+pub use area::{apply_area_effect_with_rng, AreaEffect, AreaTarget};
+pub use breakdown::{Hit, Striker};
+pub use combatant::{damage_after_defense, strike, Combatant};
+pub use context::CombatContext;
+pub use damage::{Affinity, DamageType};
+pub use forecast::forecast;
+pub use playback::CombatEnding;
+#[cfg(not(target_arch = "wasm32"))]
+pub use playback::CombatPlayback;
+
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 
-use crate::character::Player;
+use crate::character::companion::COMPANION_TARGET_CHANCE;
+use crate::character::{Companion, Player, Stats};
 use crate::inventory::InventoryManager;
+use crate::item::consumable::ConsumableType;
 use crate::item::Item;
-use crate::world::Enemy;
+use crate::world::{Alertness, Enemy};
+
+/// Logged when picking something up leaves the player encumbered
+pub const ENCUMBERED_MESSAGE: &str =
+    "Your pack is heavy: you are encumbered, and fleeing gives enemies a free attack.";
+
+/// Extra damage a hit does to an enemy that hadn't noticed the player, as a
+/// share of the hit
+pub const SNEAK_ATTACK_BONUS: f32 = 0.5;
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum CombatAction {
@@ -14,17 +42,38 @@ pub enum CombatAction {
     Flee,
 }
 
+/// The gold and items a slain enemy drops where it fell; `enemy` is its
+/// index among the fight's enemies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Loot {
+    pub enemy: usize,
+    pub gold: u32,
+    pub items: Vec<Item>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CombatResult {
     pub player_damage_dealt: i32,
     pub enemy_damage_dealt: i32,
     pub experience_gained: u32,
-    pub gold_gained: u32,
-    pub items_gained: Vec<Item>,
+    /// What the enemies slain this turn left behind
+    pub loot: Vec<Loot>,
     pub player_level_up: bool,
+    /// Every enemy in the fight is down
     pub enemy_defeated: bool,
+    /// Indices of the enemies slain this turn
+    pub slain: Vec<usize>,
+    /// Index of each enemy that hit the player this turn and the damage it did
+    pub damage_taken_from: Vec<(usize, i32)>,
+    /// Name of the enemy that killed the player, if one did
+    pub killed_by: Option<String>,
     pub player_fled: bool,
+    /// Whether the player drank a potion this round
+    pub potion_drunk: bool,
     pub messages: Vec<String>,
+    /// Every blow that landed this turn and what went into it; each also
+    /// has its line in `messages`
+    pub hits: Vec<Hit>,
 }
 
 impl CombatResult {
@@ -33,172 +82,1557 @@ impl CombatResult {
             player_damage_dealt: 0,
             enemy_damage_dealt: 0,
             experience_gained: 0,
-            gold_gained: 0,
-            items_gained: Vec::new(),
+            loot: Vec::new(),
             player_level_up: false,
             enemy_defeated: false,
+            slain: Vec::new(),
+            damage_taken_from: Vec::new(),
+            killed_by: None,
             player_fled: false,
+            potion_drunk: false,
             messages: Vec::new(),
+            hits: Vec::new(),
         }
     }
 
+    /// Records a blow that landed, with its breakdown as its message
+    pub fn add_hit(&mut self, hit: Hit) {
+        self.messages.push(hit.describe());
+        self.hits.push(hit);
+    }
+
     pub fn add_message(&mut self, message: impl Into<String>) {
         self.messages.push(message.into());
     }
+
+    /// How the fight ended this turn, if it did
+    pub fn ending(&self, player: &Player) -> Option<CombatEnding> {
+        if self.enemy_defeated {
+            Some(CombatEnding::Victory)
+        } else if self.player_fled {
+            Some(CombatEnding::Fled)
+        } else if !player.is_alive() {
+            Some(CombatEnding::Defeat)
+        } else {
+            None
+        }
+    }
+}
+
+/// Result of a single attack roll
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttackOutcome {
+    /// A normal hit dealing the given raw damage
+    Hit(i32),
+    /// A critical hit; `damage` already includes the multiplier
+    Critical { damage: i32, multiplier: f32 },
+    /// The defender dodged and no damage is dealt
+    Dodged,
+}
+
+impl AttackOutcome {
+    /// Raw damage to apply to the defender, before defense
+    pub fn damage(&self) -> i32 {
+        match self {
+            AttackOutcome::Hit(damage) | AttackOutcome::Critical { damage, .. } => *damage,
+            AttackOutcome::Dodged => 0,
+        }
+    }
 }
 
-pub fn process_combat_turn(
+/// Chance for an attacker to land a critical hit: 5% plus 1% per dexterity
+pub fn critical_chance(attacker_stats: &Stats) -> f64 {
+    (0.05 + f64::from(attacker_stats.dexterity) * 0.01).clamp(0.0, 0.5)
+}
+
+/// Chance for a defender to dodge an attack: 1% per dexterity, capped at 30%
+pub fn dodge_chance(defender_stats: &Stats) -> f64 {
+    (f64::from(defender_stats.dexterity) * 0.01).clamp(0.0, 0.3)
+}
+
+/// Smallest multiplier a critical hit rolls
+pub const MIN_CRITICAL_MULTIPLIER: f32 = 1.5;
+
+/// Largest multiplier a critical hit rolls
+pub const MAX_CRITICAL_MULTIPLIER: f32 = 2.0;
+
+/// Raw damage of a critical hit rolling `multiplier` on `base_damage`
+pub fn critical_damage(base_damage: i32, multiplier: f32) -> i32 {
+    (base_damage as f32 * multiplier).round() as i32
+}
+
+/// Chance for a player with `stats` to get away: 30% plus 3% per dexterity.
+/// A cornered player never does.
+pub fn flee_chance(stats: &Stats, cornered: bool) -> f64 {
+    if cornered {
+        return 0.0;
+    }
+    (0.3 + f64::from(stats.dexterity) * 0.03).clamp(0.0, 1.0)
+}
+
+/// Extra damage a hit of `damage` does to an enemy that hadn't noticed the
+/// player
+pub fn sneak_attack_bonus(damage: i32) -> i32 {
+    if damage <= 0 {
+        return 0;
+    }
+    ((damage as f32 * SNEAK_ATTACK_BONUS).round() as i32).max(1)
+}
+
+/// Rolls dodge and critical hit chances for one attack
+pub fn resolve_attack<R: Rng + ?Sized>(
+    attacker_stats: &Stats,
+    defender_stats: &Stats,
+    base_damage: i32,
+    rng: &mut R,
+) -> AttackOutcome {
+    if rng.gen_bool(dodge_chance(defender_stats)) {
+        return AttackOutcome::Dodged;
+    }
+
+    if rng.gen_bool(critical_chance(attacker_stats)) {
+        let multiplier = rng.gen_range(MIN_CRITICAL_MULTIPLIER..=MAX_CRITICAL_MULTIPLIER);
+        let damage = critical_damage(base_damage, multiplier);
+        return AttackOutcome::Critical { damage, multiplier };
+    }
+
+    AttackOutcome::Hit(base_damage)
+}
+
+/// Message for the player's attack against an enemy
+pub fn player_attack_message(outcome: &AttackOutcome, enemy_name: &str, damage: i32) -> String {
+    match outcome {
+        AttackOutcome::Hit(_) => format!("You attack the {enemy_name} for {damage} damage!"),
+        AttackOutcome::Critical { .. } => {
+            format!("Critical hit! You attack the {enemy_name} for {damage} damage!")
+        }
+        AttackOutcome::Dodged => format!("The {enemy_name} dodges your attack!"),
+    }
+}
+
+/// Message for an enemy's attack against the player
+pub fn enemy_attack_message(outcome: &AttackOutcome, enemy_name: &str, damage: i32) -> String {
+    match outcome {
+        AttackOutcome::Hit(_) => format!("The {enemy_name} hits you for {damage} damage!"),
+        AttackOutcome::Critical { .. } => {
+            format!("Critical hit! The {enemy_name} hits you for {damage} damage!")
+        }
+        AttackOutcome::Dodged => format!("You dodge the {enemy_name}'s attack!"),
+    }
+}
+
+/// Resolves one turn against a group of enemies. The player's action hits
+/// `enemies[target]` (or the first living enemy if that one is down), then
+/// every enemy still standing counterattacks. Where the player stands
+/// changes the blows as `context` says, and a cornered player has nowhere
+/// to flee to, so trying always fails. The blows are rolled with `rng`
+/// and what the slain drop with `loot_rng`.
+pub fn process_group_combat_turn_with_rng<R: Rng + ?Sized, L: Rng + ?Sized>(
     player: &mut Player,
-    enemy: &mut Enemy,
+    enemies: &mut [Enemy],
+    target: usize,
     action: CombatAction,
+    context: &CombatContext,
+    rng: &mut R,
+    loot_rng: &mut L,
 ) -> CombatResult {
     let mut result = CombatResult::new();
-    let mut rng = rand::thread_rng();
+    let Some(target) = living_target(enemies, target) else {
+        result.enemy_defeated = true;
+        return result;
+    };
+    let unaware = enemies[target].alertness == Alertness::Unaware;
+    let mut tamed = false;
+    let mut used_ability = None;
+    let mut area_used = false;
 
     match action {
         CombatAction::Attack => {
             // Player attacks first
-            let player_damage = player.attack_damage();
-            let damage_dealt = enemy.take_damage(player_damage);
+            let enemy = &mut enemies[target];
+            let damage_type = player.weapon_damage_type();
+            let damage = context.player_damage(player.attack_damage());
+            let (outcome, damage_dealt) = strike(player, enemy, damage, damage_type, rng);
             result.player_damage_dealt = damage_dealt;
-            result.add_message(format!(
-                "You attack the {} for {} damage!",
-                enemy.name, damage_dealt
-            ));
-
-            if !enemy.is_alive() {
-                handle_enemy_defeat(player, enemy, &mut result);
-                return result;
+            if outcome == AttackOutcome::Dodged {
+                result.add_message(player_attack_message(&outcome, &enemy.name, damage_dealt));
+            } else {
+                let hit = attack_hit(Striker::Player, player, &enemy.name, context);
+                result.add_hit(landed(hit, &outcome, damage_dealt));
+                add_interaction_message(enemy, damage_type, &mut result);
             }
-
-            // Enemy counterattack
-            let enemy_damage = enemy.attack_damage();
-            let damage_taken = player.take_damage(enemy_damage);
-            result.enemy_damage_dealt = damage_taken;
-            result.add_message(format!(
-                "The {} hits you for {} damage!",
-                enemy.name, damage_taken
-            ));
+            result.messages.extend(player.inventory.wear_weapon(rng));
         }
         CombatAction::UseAbility(ability_index) => {
             // Player uses ability
             match player.use_ability(ability_index) {
                 Ok(message) => {
-                    // Some abilities might do damage to the enemy
-                    let message_clone = message.clone();
-                    result.add_message(message_clone);
-
-                    if message.contains("damage") {
-                        // Extract the damage value from the message
-                        if let Some(damage_str) = message.split("damage").next() {
-                            if let Some(damage_value) = damage_str
-                                .split_whitespace()
-                                .last()
-                                .and_then(|s| s.parse::<i32>().ok())
-                            {
-                                let damage_dealt = enemy.take_damage(damage_value);
-                                result.player_damage_dealt = damage_dealt;
+                    used_ability = Some(ability_index);
+                    result.add_message(message.clone());
 
-                                if !enemy.is_alive() {
-                                    handle_enemy_defeat(player, enemy, &mut result);
-                                    return result;
-                                }
+                    // Some abilities might do damage to the enemy, or to
+                    // every enemy in the fight
+                    let ability = player.available_abilities().get(ability_index).copied();
+                    let base = ability_damage(&message);
+                    let damage = base.map(|damage| context.player_damage(damage));
+                    let area = ability
+                        .zip(damage)
+                        .and_then(|(name, damage)| AreaEffect::for_ability(name, damage));
+                    if let Some(effect) = area {
+                        area::engulf(player, enemies, &effect, &mut result, loot_rng);
+                        area_used = true;
+                    } else if let Some(damage_value) = damage {
+                        let enemy = &mut enemies[target];
+                        let damage_type = ability.map_or(DamageType::Physical, |name| {
+                            DamageType::for_ability(name, player.weapon_damage_type())
+                        });
+                        let (outcome, damage_dealt) =
+                            strike(&*player, enemy, damage_value, damage_type, rng);
+
+                        match outcome {
+                            AttackOutcome::Dodged => {
+                                result
+                                    .add_message(format!("The {} dodges your attack!", enemy.name));
+                            }
+                            _ => {
+                                let hit = Hit::new(
+                                    Striker::Player,
+                                    &enemy.name,
+                                    base.unwrap_or(damage_value),
+                                );
+                                let hit = context.player_hit(hit);
+                                result.add_hit(landed(hit, &outcome, damage_dealt));
+                                result.player_damage_dealt = damage_dealt;
+                                add_interaction_message(enemy, damage_type, &mut result);
                             }
                         }
                     }
-
-                    // Enemy counterattack
-                    let enemy_damage = enemy.attack_damage();
-                    let damage_taken = player.take_damage(enemy_damage);
-                    result.enemy_damage_dealt = damage_taken;
-                    result.add_message(format!(
-                        "The {} hits you for {} damage!",
-                        enemy.name, damage_taken
-                    ));
                 }
                 Err(err) => {
+                    // A failed ability costs nothing, not even the turn
                     result.add_message(err);
+                    return result;
                 }
             }
         }
+        CombatAction::UseItem(item_index) if is_thrown_item(player, item_index) => {
+            throw_item(player, item_index, &mut enemies[target], &mut result);
+        }
+        CombatAction::UseItem(item_index) if is_taming_item(player, item_index) => {
+            match tame_beast(player, item_index, &enemies[target]) {
+                Ok(message) => {
+                    result.add_message(message);
+                    // The beast leaves the fight without being slain
+                    enemies[target].health = 0;
+                    tamed = true;
+                }
+                Err(message) => {
+                    // Like a failed ability, this costs nothing
+                    result.add_message(message);
+                    return result;
+                }
+            }
+        }
+        CombatAction::UseItem(item_index) if is_return_scroll(player, item_index) => {
+            // The scroll won't work with enemies about; trying costs nothing
+            result.add_message("The Scroll of Return won't work in the middle of a fight!");
+            return result;
+        }
         CombatAction::UseItem(item_index) => {
             // Player uses an item - get a clone of the item first
             let item_message = if item_index < InventoryManager::get_item_count(player) {
-                let result = InventoryManager::use_item(player, item_index);
-                result.message
+                let potion = is_potion(player, item_index);
+                let use_result = InventoryManager::use_item(player, item_index);
+                result.potion_drunk = potion && use_result.consumed;
+                use_result.message
             } else {
                 "Invalid item or item cannot be used.".to_string()
             };
 
             // Add message about item use
             result.add_message(item_message);
-
-            // Enemy counterattack
-            let enemy_damage = enemy.attack_damage();
-            let damage_taken = player.take_damage(enemy_damage);
-            result.enemy_damage_dealt = damage_taken;
-            result.add_message(format!(
-                "The {} hits you for {} damage!",
-                enemy.name, damage_taken
-            ));
+        }
+        CombatAction::Flee if context.cornered => {
+            result.add_message("There's nowhere to run!");
         }
         CombatAction::Flee => {
-            // Player attempts to flee
-            let flee_chance = 0.3 + (player.stats.dexterity as f32 * 0.03);
-
-            if rng.gen_bool(f64::from(flee_chance)) {
+            // Player attempts to flee; a clean escape gets away from everyone
+            if rng.gen_bool(flee_chance(&player.stats, context.cornered)) {
                 result.player_fled = true;
                 result.add_message("You successfully fled from combat!".to_string());
-            } else {
-                result.add_message("You failed to escape!".to_string());
-
-                // Enemy gets a free attack
-                let enemy_damage = enemy.attack_damage();
-                let damage_taken = player.take_damage(enemy_damage);
-                result.enemy_damage_dealt = damage_taken;
-                result.add_message(format!(
-                    "The {} hits you for {} damage as you try to escape!",
-                    enemy.name, damage_taken
-                ));
+                // Turning to run drops any guard, and the fight is over
+                player.clear_buffs();
+                if player.is_encumbered() {
+                    parting_blows(player, enemies, context, &mut result, rng);
+                }
+                return result;
             }
+            result.add_message("You failed to escape!".to_string());
         }
     }
 
+    // The turn has been spent, so cooldowns run down and the ability just
+    // used starts recovering
+    player.tick_cooldowns();
+    result.messages.extend(player.tick_buffs());
+    if let Some(ability_index) = used_ability {
+        player.start_cooldown(ability_index);
+        player.start_buff(ability_index);
+    }
+
+    // An area ability has already dealt with everyone it caught
+    if unaware && !area_used {
+        sneak_attack(&mut enemies[target], &mut result);
+    }
+
+    if !tamed && !area_used && !enemies[target].is_alive() {
+        handle_enemy_defeat(player, &enemies[target], target, &mut result, loot_rng);
+    }
+    companion_attack(player, enemies, target, &mut result, loot_rng);
+    if enemies.iter().all(|enemy| !enemy.is_alive()) {
+        result.enemy_defeated = true;
+        return result;
+    }
+    // A pack whose leader has fallen loses heart before it strikes back
+    if enemies
+        .iter()
+        .any(|enemy| enemy.leader && !enemy.is_alive())
+    {
+        for enemy in enemies.iter_mut() {
+            enemy.rallied = false;
+        }
+    }
+
+    // Every enemy still standing counterattacks, unless it's stunned. Some
+    // go for the player's companion instead.
+    if context.flanked {
+        result.add_message("You are flanked!");
+    }
+    for (index, enemy) in enemies
+        .iter_mut()
+        .enumerate()
+        .filter(|(_, enemy)| enemy.is_alive())
+    {
+        if !player.is_alive() {
+            break;
+        }
+        if enemy.lose_turn_to_stun() {
+            result.add_message(format!("The {} is stunned and can't attack!", enemy.name));
+            continue;
+        }
+        if let Some(companion) = player
+            .companion
+            .as_mut()
+            .filter(|companion| !companion.is_knocked_out())
+        {
+            if rng.gen_bool(COMPANION_TARGET_CHANCE) {
+                attack_companion(companion, enemy, &mut result);
+                continue;
+            }
+        }
+        let damage_taken = enemy_attack(player, enemy, context, &mut result, rng);
+        if damage_taken > 0 {
+            result.damage_taken_from.push((index, damage_taken));
+        }
+        // A guarding player can kill with the blow turned back
+        if !enemy.is_alive() {
+            handle_enemy_defeat(player, enemy, index, &mut result, loot_rng);
+        }
+    }
+    if enemies.iter().all(|enemy| !enemy.is_alive()) {
+        result.enemy_defeated = true;
+    }
+
     result
 }
 
-fn handle_enemy_defeat(player: &mut Player, enemy: &Enemy, result: &mut CombatResult) {
-    // Get enemy drops
-    let (exp, gold, possible_item) = enemy.get_drops();
-
-    // Add experience and check for level up
-    result.experience_gained = exp;
-    result.gold_gained = gold;
-    let leveled_up = player.gain_experience(exp);
+/// Every enemy still standing gets a free attack on an encumbered player
+/// lumbering away from the fight
+fn parting_blows<R: Rng + ?Sized>(
+    player: &mut Player,
+    enemies: &mut [Enemy],
+    context: &CombatContext,
+    result: &mut CombatResult,
+    rng: &mut R,
+) {
+    result.add_message("Your heavy pack slows you down as you flee!");
+    for (index, enemy) in enemies.iter_mut().enumerate() {
+        if !player.is_alive() {
+            break;
+        }
+        if !enemy.is_alive() {
+            continue;
+        }
+        let damage_taken = enemy_attack(player, enemy, context, result, rng);
+        if damage_taken > 0 {
+            result.damage_taken_from.push((index, damage_taken));
+        }
+    }
+}
 
-    // Add rewards to player
-    player.gold += gold;
+/// The makings of a basic attack by `striker`, the `attacker` side, against
+/// or from `enemy`: its base, its weapon and what the context does to it
+fn attack_hit(
+    attacker: Striker,
+    striker: &impl Combatant,
+    enemy: &str,
+    context: &CombatContext,
+) -> Hit {
+    let (weapon, rally) = (striker.weapon_damage(), striker.rally_bonus());
+    let hit = Hit::new(attacker, enemy, striker.attack_damage() - weapon - rally)
+        .bonus("weapon", weapon)
+        .bonus("leader", rally);
+    match attacker {
+        Striker::Player => context.player_hit(hit),
+        Striker::Enemy => context.enemy_hit(hit),
+    }
+}
 
-    if let Some(item) = possible_item {
-        // Try to add item to inventory
-        let add_result = InventoryManager::add_item(player, item.clone());
-        if add_result.success {
-            let item_name = item.name().to_string();
-            result.items_gained.push(item.clone());
-            result.add_message(format!("You found: {item_name}"));
-        } else {
-            result.add_message("You found an item but your inventory is full!".to_string());
+/// `hit` as it landed: a critical one scaled up, and `damage` taken
+fn landed(hit: Hit, outcome: &AttackOutcome, damage: i32) -> Hit {
+    let mut hit = match outcome {
+        AttackOutcome::Critical { multiplier, .. } => {
+            hit.multiplier(breakdown::CRITICAL_SOURCE, *multiplier)
         }
+        _ => hit,
+    };
+    hit.damage = damage;
+    hit
+}
+
+/// Resolves the blow an enemy lands as it closes in and starts a fight,
+/// before the player's first turn in it
+pub fn first_strike_with_rng<R: Rng + ?Sized>(
+    player: &mut Player,
+    enemy: &mut Enemy,
+    context: &CombatContext,
+    rng: &mut R,
+) -> CombatResult {
+    let mut result = CombatResult::new();
+    let damage_taken = enemy_attack(player, enemy, context, &mut result, rng);
+    if damage_taken > 0 {
+        result.damage_taken_from.push((0, damage_taken));
+    }
+    result
+}
+
+/// `target` if that enemy is still standing, otherwise the first one that is
+pub fn living_target(enemies: &[Enemy], target: usize) -> Option<usize> {
+    if enemies.get(target).is_some_and(Enemy::is_alive) {
+        Some(target)
+    } else {
+        enemies.iter().position(Enemy::is_alive)
     }
+}
+
+/// The next living enemy after `target`, wrapping around
+pub fn next_target(enemies: &[Enemy], target: usize) -> usize {
+    (1..=enemies.len())
+        .map(|step| (target + step) % enemies.len())
+        .find(|&index| enemies[index].is_alive())
+        .unwrap_or(target)
+}
+
+/// Resolves one enemy's attack against the player and records the outcome,
+/// returning the damage the player took. A guarding player turns part of
+/// the blow back on the enemy.
+fn enemy_attack<R: Rng + ?Sized>(
+    player: &mut Player,
+    enemy: &mut Enemy,
+    context: &CombatContext,
+    result: &mut CombatResult,
+    rng: &mut R,
+) -> i32 {
+    let off_balance = std::mem::take(&mut player.off_balance);
+    let damage = context.enemy_damage(enemy.attack_damage());
+    let outcome = match resolve_attack(enemy.stats(), player.stats(), damage, rng) {
+        // Caught without their footing, the player can't dodge
+        AttackOutcome::Dodged if off_balance => AttackOutcome::Hit(damage),
+        outcome => outcome,
+    };
+    let (damage_taken, reflected) = match outcome {
+        AttackOutcome::Dodged => (0, 0),
+        _ => (
+            player.take_hit(outcome.damage(), DamageType::Physical),
+            player.guard_reflection(outcome.damage()),
+        ),
+    };
+    result.enemy_damage_dealt += damage_taken;
+    if outcome == AttackOutcome::Dodged {
+        result.add_message(enemy_attack_message(&outcome, &enemy.name, damage_taken));
+    } else {
+        let hit = attack_hit(Striker::Enemy, enemy, &enemy.name, context);
+        result.add_hit(landed(hit, &outcome, damage_taken));
+    }
+    if damage_taken > 0 {
+        result.messages.extend(player.inventory.wear_armor(rng));
+    }
+    if reflected > 0 {
+        enemy.health = (enemy.health - reflected).max(0);
+        result.add_message(format!(
+            "Your guard turns {reflected} damage back on the {}!",
+            enemy.name
+        ));
+    }
+    if !player.is_alive() {
+        result.killed_by = Some(enemy.name.clone());
+    }
+    damage_taken
+}
+
+/// The companion's attack after the player's action, at the player's
+/// target or the next enemy standing. A kill is rewarded as if the player
+/// had made it.
+fn companion_attack<R: Rng + ?Sized>(
+    player: &mut Player,
+    enemies: &mut [Enemy],
+    target: usize,
+    result: &mut CombatResult,
+    rng: &mut R,
+) {
+    let Some((name, attack)) = player
+        .companion
+        .as_ref()
+        .filter(|companion| !companion.is_knocked_out())
+        .map(|companion| (companion.name.clone(), companion.attack))
+    else {
+        return;
+    };
+    let Some(index) = living_target(enemies, target) else {
+        return;
+    };
+    let enemy = &mut enemies[index];
+    let damage = enemy.take_hit(attack, DamageType::Physical);
+    result.add_message(format!(
+        "{name} attacks the {} for {damage} damage!",
+        enemy.name
+    ));
+    if !enemy.is_alive() {
+        handle_enemy_defeat(player, &enemies[index], index, result, rng);
+    }
+}
+
+/// Resolves one enemy's attack against the player's companion. It never
+/// dodges and has no armor to soak the blow.
+fn attack_companion(companion: &mut Companion, enemy: &Enemy, result: &mut CombatResult) {
+    let damage = damage_after_defense(enemy.attack_damage(), 0);
+    result.add_message(format!(
+        "The {} hits {} for {damage} damage!",
+        enemy.name, companion.name
+    ));
+    if companion.take_damage(damage) {
+        result.add_message(format!("{} is knocked out!", companion.name));
+    }
+}
 
-    // Record results
-    result.enemy_defeated = true;
-    result.player_level_up = leveled_up;
+/// Tells the player when an enemy's affinity changed how much a hit did
+/// Throws a consumable at an enemy outside of a fight: there is no
+/// counterattack, but a kill is rewarded as in combat, its drops rolled
+/// with `rng`
+pub fn resolve_throw<R: Rng + ?Sized>(
+    player: &mut Player,
+    enemy: &mut Enemy,
+    item_index: usize,
+    rng: &mut R,
+) -> CombatResult {
+    let mut result = CombatResult::new();
+    let unaware = enemy.alertness == Alertness::Unaware;
+    throw_item(player, item_index, enemy, &mut result);
+    if unaware {
+        sneak_attack(enemy, &mut result);
+    }
+    if !enemy.is_alive() {
+        handle_enemy_defeat(player, enemy, 0, &mut result, rng);
+        result.enemy_defeated = true;
+    }
+    result
+}
+
+/// Whether inventory slot `index` holds a consumable meant for throwing
+pub fn is_thrown_item(player: &Player, index: usize) -> bool {
+    matches!(
+        InventoryManager::get_item(player, index),
+        Some(Item::Consumable(consumable)) if consumable.is_thrown()
+    )
+}
+
+/// Whether inventory slot `index` holds a Taming Charm
+pub fn is_taming_item(player: &Player, index: usize) -> bool {
+    matches!(
+        InventoryManager::get_item(player, index),
+        Some(Item::Consumable(consumable))
+            if consumable.consumable_type == ConsumableType::TamingCharm
+    )
+}
+
+/// Whether inventory slot `index` holds a Scroll of Return
+pub fn is_return_scroll(player: &Player, index: usize) -> bool {
+    matches!(
+        InventoryManager::get_item(player, index),
+        Some(Item::Consumable(consumable))
+            if consumable.consumable_type == ConsumableType::ReturnScroll
+    )
+}
+
+/// Whether inventory slot `index` holds a potion
+pub fn is_potion(player: &Player, index: usize) -> bool {
+    matches!(
+        InventoryManager::get_item(player, index),
+        Some(Item::Consumable(consumable)) if consumable.consumable_type.is_potion()
+    )
+}
+
+/// Uses the Taming Charm at `item_index` on `enemy`, making it the player's
+/// companion. Fails without using the charm if the player already has a
+/// companion or `enemy` isn't a badly hurt beast.
+pub fn tame_beast(player: &mut Player, item_index: usize, enemy: &Enemy) -> Result<String, String> {
+    if let Some(companion) = &player.companion {
+        return Err(format!("{} is already at your side.", companion.title()));
+    }
+    if !Companion::can_tame(enemy) {
+        return Err(format!(
+            "The {} won't be tamed. Only badly hurt beasts can be.",
+            enemy.name
+        ));
+    }
+    InventoryManager::remove_item(player, item_index);
+    player.companion = Some(Companion::tamed(enemy));
+    Ok(format!(
+        "The {} stops fighting and becomes your companion!",
+        enemy.name
+    ))
+}
+
+/// Takes the thrown consumable at `item_index` out of the inventory and
+/// throws it at `enemy`
+fn throw_item(
+    player: &mut Player,
+    item_index: usize,
+    enemy: &mut Enemy,
+    result: &mut CombatResult,
+) {
+    let Some(Item::Consumable(consumable)) = InventoryManager::remove_item(player, item_index)
+    else {
+        return;
+    };
+    let (damage, message) = consumable.throw_at(enemy);
+    result.player_damage_dealt += damage;
+    result.add_message(message);
+    if let Some(damage_type) = consumable.damage_type() {
+        add_interaction_message(enemy, damage_type, result);
+    }
+}
+
+/// Adds the sneak attack bonus to a blow that landed on an enemy that
+/// hadn't noticed the player
+fn sneak_attack(enemy: &mut Enemy, result: &mut CombatResult) {
+    let bonus = sneak_attack_bonus(result.player_damage_dealt);
+    if bonus == 0 {
+        return;
+    }
+    enemy.health -= bonus;
+    result.player_damage_dealt += bonus;
+    result.add_message(format!(
+        "Sneak attack! The {} never saw you coming (+{bonus} damage).",
+        enemy.name
+    ));
+}
+
+fn add_interaction_message(enemy: &Enemy, damage_type: DamageType, result: &mut CombatResult) {
+    let affinity = enemy.affinity(damage_type);
+    if let Some(message) = damage::interaction_message(damage_type, affinity, &enemy.name) {
+        result.add_message(message);
+    }
+}
+
+/// Damage an ability's message says it deals: "You cast Smite for 14
+/// damage" deals 14
+pub fn ability_damage(message: &str) -> Option<i32> {
+    let (before, _) = message.split_once("damage")?;
+    before.split_whitespace().last()?.parse().ok()
+}
+
+/// Rewards the player for slaying enemy `index` of the fight and leaves
+/// its gold and items where it fell, for the game to place on the map
+fn handle_enemy_defeat<R: Rng + ?Sized>(
+    player: &mut Player,
+    enemy: &Enemy,
+    index: usize,
+    result: &mut CombatResult,
+    rng: &mut R,
+) {
+    let (exp, gold, items) = enemy.get_drops_with_rng(rng);
+    reward_defeat(player, enemy, exp, result, rng);
+    if let Some(message) = loot_message(&enemy.name, gold, &items) {
+        result.add_message(message);
+    }
+    if gold > 0 || !items.is_empty() {
+        result.loot.push(Loot {
+            enemy: index,
+            gold,
+            items,
+        });
+    }
+    result.slain.push(index);
+}
+
+/// Rewards the player for slaying `enemy` with `exp` experience
+fn reward_defeat<R: Rng + ?Sized>(
+    player: &mut Player,
+    enemy: &Enemy,
+    exp: u32,
+    result: &mut CombatResult,
+    rng: &mut R,
+) {
+    result.experience_gained += exp;
+    let previous_level = player.level;
+    let leveled_up = player.gain_experience(exp, rng);
+    result.player_level_up |= leveled_up;
 
     result.add_message(format!("You defeated the {}!", enemy.name));
-    result.add_message(format!("You gained {exp} experience and {gold} gold."));
+    result.add_message(format!("You gained {exp} experience."));
 
     if leveled_up {
         result.add_message(format!("You leveled up to level {}!", player.level));
+        for ability in player.abilities_unlocked_since(previous_level) {
+            result.add_message(format!("You learned {ability}!"));
+        }
+        result.add_message(format!(
+            "You have {} stat points to spend.",
+            player.unspent_stat_points
+        ));
+    }
+}
+
+/// "The Goblin drops 12 gold and a Health Potion.", or None if it dropped
+/// nothing
+fn loot_message(enemy_name: &str, gold: u32, items: &[Item]) -> Option<String> {
+    let mut things: Vec<String> = items.iter().map(|item| item.name().to_string()).collect();
+    if gold > 0 {
+        things.insert(0, format!("{gold} gold"));
+    }
+    let list = match things.split_last()? {
+        (last, []) => last.clone(),
+        (last, rest) => format!("{} and {last}", rest.join(", ")),
+    };
+    Some(format!("The {enemy_name} drops {list}."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::consumable::{Consumable, ConsumableType};
+    use crate::item::EquipmentSlot;
+    use rand::rngs::mock::StepRng;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn stats_with_dex(dexterity: i32) -> Stats {
+        let mut stats = Stats::new();
+        stats.set_dexterity(dexterity);
+        stats
+    }
+
+    // StepRng yielding 0 makes every gen_bool(p > 0) succeed, u64::MAX makes
+    // every one fail
+    fn always_rng() -> StepRng {
+        StepRng::new(0, 0)
+    }
+
+    fn never_rng() -> StepRng {
+        StepRng::new(u64::MAX, 0)
+    }
+
+    /// Rolls what the slain drop, which a stepping rng can't
+    fn loot_rng() -> StdRng {
+        StdRng::seed_from_u64(0)
+    }
+
+    #[test]
+    fn test_chance_formulas() {
+        assert!((critical_chance(&stats_with_dex(0)) - 0.05).abs() < 1e-9);
+        assert!((critical_chance(&stats_with_dex(10)) - 0.15).abs() < 1e-9);
+        assert!((critical_chance(&stats_with_dex(100)) - 0.5).abs() < 1e-9);
+
+        assert!((dodge_chance(&stats_with_dex(7)) - 0.07).abs() < 1e-9);
+        assert!((dodge_chance(&stats_with_dex(100)) - 0.3).abs() < 1e-9);
+        assert_eq!(dodge_chance(&stats_with_dex(-5)), 0.0);
+    }
+
+    #[test]
+    fn test_dodge_negates_attack() {
+        let outcome = resolve_attack(
+            &stats_with_dex(5),
+            &stats_with_dex(5),
+            10,
+            &mut always_rng(),
+        );
+        assert_eq!(outcome, AttackOutcome::Dodged);
+        assert_eq!(outcome.damage(), 0);
+    }
+
+    #[test]
+    fn test_plain_hit() {
+        let outcome = resolve_attack(&stats_with_dex(5), &stats_with_dex(5), 10, &mut never_rng());
+        assert_eq!(outcome, AttackOutcome::Hit(10));
+    }
+
+    #[test]
+    fn test_critical_hit_multiplier() {
+        // First roll (dodge) fails, then the step wraps to 0 so the crit roll succeeds
+        let mut rng = StepRng::new(u64::MAX, 1);
+        let outcome = resolve_attack(&stats_with_dex(5), &stats_with_dex(5), 10, &mut rng);
+
+        match outcome {
+            AttackOutcome::Critical { damage, multiplier } => {
+                assert!((1.5..=2.0).contains(&multiplier));
+                assert_eq!(damage, (10.0 * multiplier).round() as i32);
+            }
+            other => panic!("expected a critical hit, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_critical_rate_matches_dexterity() {
+        let mut rng = StdRng::seed_from_u64(1234);
+        let attacker = stats_with_dex(15); // 20% crit chance
+        let defender = stats_with_dex(0); // never dodges
+        let trials = 10_000;
+
+        let crits = (0..trials)
+            .filter(|_| {
+                matches!(
+                    resolve_attack(&attacker, &defender, 10, &mut rng),
+                    AttackOutcome::Critical { .. }
+                )
+            })
+            .count();
+
+        let rate = crits as f64 / trials as f64;
+        assert!((rate - 0.20).abs() < 0.02, "crit rate was {rate}");
+    }
+
+    #[test]
+    fn test_attack_messages() {
+        let crit = AttackOutcome::Critical {
+            damage: 18,
+            multiplier: 1.8,
+        };
+
+        assert_eq!(
+            player_attack_message(&AttackOutcome::Hit(9), "Goblin", 7),
+            "You attack the Goblin for 7 damage!"
+        );
+        assert_eq!(
+            player_attack_message(&crit, "Goblin", 15),
+            "Critical hit! You attack the Goblin for 15 damage!"
+        );
+        assert_eq!(
+            player_attack_message(&AttackOutcome::Dodged, "goblin", 0),
+            "The goblin dodges your attack!"
+        );
+        assert_eq!(
+            enemy_attack_message(&AttackOutcome::Dodged, "Orc", 0),
+            "You dodge the Orc's attack!"
+        );
+        assert_eq!(
+            enemy_attack_message(&crit, "Orc", 12),
+            "Critical hit! The Orc hits you for 12 damage!"
+        );
+    }
+
+    #[test]
+    fn test_combat_ending() {
+        let mut player = Player::new("Hero".to_string(), crate::character::ClassType::Warrior);
+        let mut result = CombatResult::new();
+        assert_eq!(result.ending(&player), None);
+
+        result.player_fled = true;
+        assert_eq!(result.ending(&player), Some(CombatEnding::Fled));
+
+        result.enemy_defeated = true;
+        assert_eq!(result.ending(&player), Some(CombatEnding::Victory));
+
+        player.health = 0;
+        assert_eq!(
+            CombatResult::new().ending(&player),
+            Some(CombatEnding::Defeat)
+        );
+    }
+
+    fn goblins(count: usize) -> Vec<Enemy> {
+        (0..count)
+            .map(|i| {
+                Enemy::new(
+                    format!("Goblin {i}"),
+                    crate::world::enemy::EnemyType::Goblin,
+                    1,
+                )
+            })
+            .collect()
+    }
+
+    fn sturdy_player() -> Player {
+        let mut player = Player::new("Hero".to_string(), crate::character::ClassType::Warrior);
+        player.max_health = 1000;
+        player.health = 1000;
+        player
+    }
+
+    #[test]
+    fn test_unaware_enemies_take_a_sneak_attack() {
+        let mut player = sturdy_player();
+        let mut attack = |enemy: Enemy| {
+            let mut enemies = vec![enemy];
+            enemies[0].health = 500;
+            let result = process_group_combat_turn_with_rng(
+                &mut player,
+                &mut enemies,
+                0,
+                CombatAction::Attack,
+                &CombatContext::default(),
+                &mut never_rng(),
+                &mut loot_rng(),
+            );
+            (result.player_damage_dealt, result.messages)
+        };
+
+        let mut alerted = goblins(1).remove(0);
+        alerted.alert(crate::world::Position::new(0, 0));
+        let (plain, messages) = attack(alerted);
+        assert!(!messages.iter().any(|m| m.starts_with("Sneak attack")));
+
+        let (sneaky, messages) = attack(goblins(1).remove(0));
+        let bonus = ((plain as f32 * SNEAK_ATTACK_BONUS).round() as i32).max(1);
+        assert_eq!(sneaky, plain + bonus);
+        assert!(messages.iter().any(|m| m.starts_with("Sneak attack!")));
+    }
+
+    #[test]
+    fn test_the_context_shifts_the_blows_both_ways() {
+        let turn = |context: CombatContext| {
+            let mut player = sturdy_player();
+            let mut enemies = goblins(2);
+            for enemy in &mut enemies {
+                enemy.health = 500;
+                enemy.stats.dexterity = 30;
+                enemy.alert(crate::world::Position::new(0, 0));
+            }
+            process_group_combat_turn_with_rng(
+                &mut player,
+                &mut enemies,
+                0,
+                CombatAction::Attack,
+                &context,
+                &mut never_rng(),
+                &mut loot_rng(),
+            )
+        };
+
+        let plain = turn(CombatContext::default());
+        assert!(!plain.messages.contains(&"You are flanked!".to_string()));
+        let flanked = turn(CombatContext {
+            flanked: true,
+            ..CombatContext::default()
+        });
+        assert!(flanked.messages.contains(&"You are flanked!".to_string()));
+        assert!(flanked.enemy_damage_dealt > plain.enemy_damage_dealt);
+        assert_eq!(flanked.player_damage_dealt, plain.player_damage_dealt);
+
+        let opening = turn(CombatContext {
+            opening_strike: true,
+            ..CombatContext::default()
+        });
+        assert!(opening.player_damage_dealt > plain.player_damage_dealt);
+        let wading = turn(CombatContext {
+            footing: Some(crate::world::TileType::ShallowWater),
+            ..CombatContext::default()
+        });
+        assert!(wading.player_damage_dealt < plain.player_damage_dealt);
+        assert_eq!(wading.enemy_damage_dealt, plain.enemy_damage_dealt);
+    }
+
+    #[test]
+    fn test_blows_that_land_carry_their_breakdown() {
+        let mut player = sturdy_player();
+        let mut enemies = goblins(1);
+        enemies[0].health = 500;
+        enemies[0].alert(crate::world::Position::new(0, 0));
+        let context = CombatContext {
+            flanked: true,
+            opening_strike: true,
+            ..CombatContext::default()
+        };
+        let attack = player.attack_damage();
+        let result = process_group_combat_turn_with_rng(
+            &mut player,
+            &mut enemies,
+            0,
+            CombatAction::Attack,
+            &context,
+            &mut never_rng(),
+            &mut loot_rng(),
+        );
+
+        let [blow, counter] = &result.hits[..] else {
+            panic!("{:?}", result.hits);
+        };
+        assert_eq!(blow.attacker, Striker::Player);
+        assert_eq!(blow.damage, result.player_damage_dealt);
+        assert_eq!(blow.raw(), context.player_damage(attack));
+        assert!(blow.describe().contains("opening strike x1.25"));
+        assert_eq!(counter.attacker, Striker::Enemy);
+        assert_eq!(counter.damage, result.enemy_damage_dealt);
+        assert!(counter.describe().contains("flanked x1.2 = "));
+        for hit in &result.hits {
+            assert!(result.messages.contains(&hit.describe()));
+        }
+    }
+
+    #[test]
+    fn test_every_living_enemy_counterattacks() {
+        let mut player = sturdy_player();
+        let mut enemies = goblins(3);
+        for enemy in &mut enemies {
+            enemy.health = 500;
+        }
+        enemies[2].health = 0;
+
+        let result = process_group_combat_turn_with_rng(
+            &mut player,
+            &mut enemies,
+            1,
+            CombatAction::Attack,
+            &CombatContext::default(),
+            &mut never_rng(),
+            &mut loot_rng(),
+        );
+
+        assert!(enemies[1].health < 500, "the target takes the hit");
+        assert_eq!(enemies[0].health, 500);
+        let counterattacks = result
+            .messages
+            .iter()
+            .filter(|m| m.contains("hits you"))
+            .count();
+        assert_eq!(counterattacks, 2, "the slain goblin stays down");
+        assert_eq!(player.health, 1000 - result.enemy_damage_dealt);
+        assert!(!result.enemy_defeated);
+    }
+
+    #[test]
+    fn test_abilities_cool_down_between_uses() {
+        let mut player = sturdy_player();
+        let mut enemies = goblins(1);
+        enemies[0].health = 500;
+        let mut turn = |player: &mut Player, action| {
+            process_group_combat_turn_with_rng(
+                player,
+                &mut enemies,
+                0,
+                action,
+                &CombatContext::default(),
+                &mut never_rng(),
+                &mut loot_rng(),
+            )
+        };
+
+        // Shield Block needs three turns to recover
+        player.level = 3;
+        turn(&mut player, CombatAction::UseAbility(2));
+        assert_eq!(player.cooldown_left(2), 3);
+
+        // Trying again is refused and costs no turn
+        let mana = player.mana;
+        let refused = turn(&mut player, CombatAction::UseAbility(2));
+        assert!(refused.messages[0].contains("isn't ready"));
+        assert_eq!(player.mana, mana);
+        assert_eq!(player.cooldown_left(2), 3);
+
+        for left in [2, 1, 0] {
+            turn(&mut player, CombatAction::Attack);
+            assert_eq!(player.cooldown_left(2), left);
+        }
+        assert!(turn(&mut player, CombatAction::UseAbility(2)).messages[0]
+            .contains("increase your defense"));
+    }
+
+    #[test]
+    fn test_guard_stance_halves_and_reflects_until_the_next_turn() {
+        let mut player = sturdy_player();
+        player.level = 2;
+        let mut enemies = goblins(1);
+        enemies[0].health = 500;
+        enemies[0].stats.dexterity = 30;
+        let blow = damage_after_defense(enemies[0].attack_damage(), player.defense());
+        let mut turn = |player: &mut Player, action| {
+            process_group_combat_turn_with_rng(
+                player,
+                &mut enemies,
+                0,
+                action,
+                &CombatContext::default(),
+                &mut never_rng(),
+                &mut loot_rng(),
+            )
+        };
+
+        let mana = player.mana;
+        let guarded = turn(&mut player, CombatAction::UseAbility(1));
+        assert_eq!(
+            player.mana,
+            mana - crate::character::Class::mana_cost("Guard Stance")
+        );
+        assert_eq!(guarded.enemy_damage_dealt, blow - blow / 2);
+        assert!(guarded.messages.iter().any(|m| m.contains(&format!(
+            "turns {} damage back",
+            (blow as f32 * 0.25).round() as i32
+        ))));
+
+        // The guard is down by the player's next turn
+        let open = turn(&mut player, CombatAction::Attack);
+        assert!(!player.is_guarding());
+        assert_eq!(open.enemy_damage_dealt, blow);
+        assert!(open
+            .messages
+            .iter()
+            .any(|m| m == "Your Guard Stance wears off."));
+        assert!(!open.messages.iter().any(|m| m.contains("damage back")));
+    }
+
+    #[test]
+    fn test_a_reflected_blow_can_win_the_fight() {
+        let mut player = sturdy_player();
+        player.level = 2;
+        let mut enemies = goblins(1);
+        enemies[0].stats.dexterity = 30;
+        enemies[0].health = 500;
+        let blow = damage_after_defense(enemies[0].attack_damage(), player.defense());
+        let reflected = crate::character::buff::guard(blow).1;
+        // Guard Stance does no damage itself, so only the reflection can
+        // finish the goblin
+        enemies[0].health = reflected;
+
+        let result = process_group_combat_turn_with_rng(
+            &mut player,
+            &mut enemies,
+            0,
+            CombatAction::UseAbility(1),
+            &CombatContext::default(),
+            &mut never_rng(),
+            &mut loot_rng(),
+        );
+        assert!(result.enemy_defeated);
+        assert_eq!(result.slain, [0]);
+        assert!(result.experience_gained > 0);
+    }
+
+    #[test]
+    fn test_blessing_raises_stats_until_it_expires() {
+        let mut player = sturdy_player();
+        player.class = crate::character::Class::new(crate::character::ClassType::Cleric);
+        player.level = 3;
+        player.mana = 100;
+        let base_attack = player.attack_damage();
+        let strength = player.stats.strength;
+        let mut enemies = goblins(1);
+        enemies[0].health = 5000;
+        let mut turn = |player: &mut Player, action| {
+            process_group_combat_turn_with_rng(
+                player,
+                &mut enemies,
+                0,
+                action,
+                &CombatContext::default(),
+                &mut never_rng(),
+                &mut loot_rng(),
+            )
+        };
+
+        turn(&mut player, CombatAction::UseAbility(2));
+        assert_eq!(player.stats.strength, strength + 2);
+        // A Cleric hits with half their wisdom
+        assert_eq!(player.attack_damage(), base_attack + 1);
+
+        for left in [4, 3, 2, 1] {
+            let result = turn(&mut player, CombatAction::Attack);
+            assert_eq!(player.buffs[0].turns, left);
+            assert!(result.player_damage_dealt > 0);
+        }
+        let expired = turn(&mut player, CombatAction::Attack);
+        assert!(expired
+            .messages
+            .iter()
+            .any(|m| m == "Your Blessing wears off."));
+        assert!(player.buffs.is_empty());
+        assert_eq!(player.stats.strength, strength);
+        assert_eq!(player.attack_damage(), base_attack);
+    }
+
+    #[test]
+    fn test_off_balance_players_cannot_dodge_the_next_attack() {
+        let mut player = sturdy_player();
+        player.off_balance = true;
+        let mut enemies = goblins(1);
+        enemies[0].health = 500;
+        let mut turn = |player: &mut Player| {
+            process_group_combat_turn_with_rng(
+                player,
+                &mut enemies,
+                0,
+                CombatAction::Attack,
+                &CombatContext::default(),
+                &mut always_rng(),
+                &mut loot_rng(),
+            )
+        };
+
+        assert!(turn(&mut player).enemy_damage_dealt > 0);
+        assert!(!player.off_balance);
+        assert_eq!(turn(&mut player).enemy_damage_dealt, 0);
+    }
+
+    #[test]
+    fn test_victory_needs_every_enemy_and_pools_rewards() {
+        let mut player = sturdy_player();
+        let mut enemies = goblins(2);
+        for enemy in &mut enemies {
+            enemy.health = 1;
+        }
+        let gold_before = player.gold;
+
+        let first = process_group_combat_turn_with_rng(
+            &mut player,
+            &mut enemies,
+            0,
+            CombatAction::Attack,
+            &CombatContext::default(),
+            &mut never_rng(),
+            &mut loot_rng(),
+        );
+        assert_eq!(first.slain, [0]);
+        assert!(!first.enemy_defeated);
+        assert_eq!(first.ending(&player), None);
+        assert!(
+            first.enemy_damage_dealt > 0,
+            "the survivor still strikes back"
+        );
+
+        // Aiming at the fallen goblin hits the one left standing
+        let second = process_group_combat_turn_with_rng(
+            &mut player,
+            &mut enemies,
+            0,
+            CombatAction::Attack,
+            &CombatContext::default(),
+            &mut never_rng(),
+            &mut loot_rng(),
+        );
+        assert_eq!(second.slain, [1]);
+        assert!(second.enemy_defeated);
+        assert_eq!(second.ending(&player), Some(CombatEnding::Victory));
+        // Their gold is left where they fell, for the game to put down
+        assert_eq!(player.gold, gold_before);
+        let dropped: Vec<usize> = first
+            .loot
+            .iter()
+            .chain(&second.loot)
+            .map(|loot| loot.enemy)
+            .collect();
+        assert_eq!(dropped, [0, 1], "both goblins paid out");
+    }
+
+    #[test]
+    fn test_area_abilities_hit_everyone_in_the_fight() {
+        let mut player = Player::new("Hero".to_string(), crate::character::ClassType::Mage);
+        player.max_health = 1000;
+        player.health = 1000;
+        let mut enemies = goblins(3);
+        enemies[0].health = 500;
+        enemies[1].health = 1;
+        enemies[2].health = 500;
+
+        let result = process_group_combat_turn_with_rng(
+            &mut player,
+            &mut enemies,
+            0,
+            CombatAction::UseAbility(0),
+            &CombatContext::default(),
+            &mut never_rng(),
+            &mut loot_rng(),
+        );
+        assert!(result
+            .messages
+            .contains(&"The fireball engulfs 3 enemies!".to_string()));
+        assert_eq!(result.slain, [1]);
+        assert_eq!(enemies[0].health, enemies[2].health);
+        assert!(enemies[0].health < 500);
+        assert_eq!(result.player_damage_dealt, 3 * (500 - enemies[0].health));
+        // No sneak attack on top, and the goblin's drops are paid out once
+        assert!(!result
+            .messages
+            .iter()
+            .any(|m| m.starts_with("Sneak attack")));
+        assert_eq!(
+            result
+                .messages
+                .iter()
+                .filter(|m| m.starts_with("You defeated"))
+                .count(),
+            1
+        );
+        assert_eq!(ability_damage("You cast Smite for 14 damage"), Some(14));
+        assert_eq!(ability_damage("You cast Heal and restored 9 health"), None);
+    }
+
+    #[test]
+    fn test_fleeing_escapes_every_enemy() {
+        let mut player = sturdy_player();
+        let mut enemies = goblins(3);
+
+        let result = process_group_combat_turn_with_rng(
+            &mut player,
+            &mut enemies,
+            0,
+            CombatAction::Flee,
+            &CombatContext::default(),
+            &mut always_rng(),
+            &mut loot_rng(),
+        );
+        assert!(result.player_fled);
+        assert_eq!(result.enemy_damage_dealt, 0);
+        assert_eq!(player.health, 1000);
+
+        // Cornered, even a sure escape fails
+        let cornered = process_group_combat_turn_with_rng(
+            &mut player,
+            &mut enemies,
+            0,
+            CombatAction::Flee,
+            &CombatContext {
+                cornered: true,
+                ..CombatContext::default()
+            },
+            &mut always_rng(),
+            &mut loot_rng(),
+        );
+        assert!(!cornered.player_fled);
+        assert_eq!(cornered.messages[0], "There's nowhere to run!");
+    }
+
+    #[test]
+    fn test_encumbered_players_take_parting_blows_when_fleeing() {
+        let mut player = sturdy_player();
+        // Nimble enough that every escape succeeds, even with `never_rng`
+        player.stats.dexterity = 30;
+        let flee = |player: &mut Player| {
+            process_group_combat_turn_with_rng(
+                player,
+                &mut goblins(3),
+                0,
+                CombatAction::Flee,
+                &CombatContext::default(),
+                &mut never_rng(),
+                &mut loot_rng(),
+            )
+        };
+
+        let light = flee(&mut player);
+        assert!(light.player_fled);
+        assert!(light.damage_taken_from.is_empty());
+
+        while !player.is_encumbered() {
+            player
+                .inventory
+                .add_item(Item::Consumable(Consumable::torch()));
+        }
+        let heavy = flee(&mut player);
+        assert!(heavy.player_fled);
+        assert_eq!(
+            heavy.messages[1],
+            "Your heavy pack slows you down as you flee!"
+        );
+        assert_eq!(heavy.damage_taken_from.len(), 3);
+        assert_eq!(player.health, 1000 - heavy.enemy_damage_dealt);
+    }
+
+    /// A sturdy player carrying only the given throwables
+    fn thrower(items: &[(ConsumableType, i32)]) -> Player {
+        let mut player = sturdy_player();
+        player.inventory.items.clear();
+        for slot in EquipmentSlot::iter() {
+            player.inventory.equipped.insert(slot, None);
+        }
+        for (consumable_type, potency) in items {
+            let item = Item::Consumable(Consumable {
+                name: format!("{consumable_type:?}"),
+                description: String::new(),
+                consumable_type: consumable_type.clone(),
+                potency: *potency,
+                value: 1,
+            });
+            player.inventory.add_item(item);
+        }
+        player
+    }
+
+    #[test]
+    fn test_thrown_items_hit_the_target_not_the_player() {
+        let mut player = thrower(&[
+            (ConsumableType::FireBomb, 30),
+            (ConsumableType::FlashPowder, 2),
+        ]);
+        let mut enemies = goblins(2);
+        for enemy in &mut enemies {
+            enemy.health = 500;
+        }
+
+        let bomb = process_group_combat_turn_with_rng(
+            &mut player,
+            &mut enemies,
+            1,
+            CombatAction::UseItem(0),
+            &CombatContext::default(),
+            &mut never_rng(),
+            &mut loot_rng(),
+        );
+        assert!(bomb.player_damage_dealt > 0);
+        assert_eq!(enemies[1].health, 500 - bomb.player_damage_dealt);
+        assert_eq!(enemies[0].health, 500);
+        assert!(!bomb.potion_drunk);
+        assert_eq!(InventoryManager::get_item_count(&player), 1);
+
+        // The stunned goblin skips its counterattack; the other still strikes
+        let flash = process_group_combat_turn_with_rng(
+            &mut player,
+            &mut enemies,
+            0,
+            CombatAction::UseItem(0),
+            &CombatContext::default(),
+            &mut never_rng(),
+            &mut loot_rng(),
+        );
+        assert_eq!(enemies[0].stunned_turns, 1);
+        assert!(flash
+            .messages
+            .iter()
+            .any(|m| m.contains("stunned and can't attack")));
+        let counterattacks = flash
+            .messages
+            .iter()
+            .filter(|m| m.contains("hits you"))
+            .count();
+        assert_eq!(counterattacks, 1);
+        assert!(InventoryManager::is_empty(&player));
+    }
+
+    #[test]
+    fn test_throw_outside_combat_rewards_a_kill() {
+        let mut player = thrower(&[(ConsumableType::ThrowingKnife, 50)]);
+        let mut enemy = goblins(1).remove(0);
+        enemy.health = 1;
+        let experience = player.experience;
+
+        let result = resolve_throw(&mut player, &mut enemy, 0, &mut StdRng::seed_from_u64(3));
+        assert!(result.enemy_defeated);
+        assert_eq!(result.slain, [0]);
+        assert_eq!(result.enemy_damage_dealt, 0, "no counterattack");
+        assert!(player.experience > experience || player.level > 1);
+        // The knife is gone; the goblin may have dropped loot in its place
+        assert!(!player
+            .inventory
+            .items
+            .iter()
+            .any(|item| item.name() == "ThrowingKnife"));
+    }
+
+    #[test]
+    fn test_companions_join_in_and_draw_some_blows() {
+        let mut player = sturdy_player();
+        player.companion = Some(Companion::wolf_pup());
+        let mut enemies = goblins(1);
+        enemies[0].health = 500;
+        let bite = damage_after_defense(4, enemies[0].defense());
+
+        // Every roll succeeds: the goblin dodges the player and goes for Fang
+        let result = process_group_combat_turn_with_rng(
+            &mut player,
+            &mut enemies,
+            0,
+            CombatAction::Attack,
+            &CombatContext::default(),
+            &mut always_rng(),
+            &mut loot_rng(),
+        );
+        assert_eq!(enemies[0].health, 500 - bite);
+        assert!(result
+            .messages
+            .contains(&format!("Fang attacks the Goblin 0 for {bite} damage!")));
+        assert_eq!(player.health, 1000);
+        let pup = player.companion.as_ref().unwrap();
+        assert_eq!(pup.health, 20 - enemies[0].attack_damage());
+
+        // A knocked out companion sits the fight out
+        player.companion.as_mut().unwrap().take_damage(100);
+        let result = process_group_combat_turn_with_rng(
+            &mut player,
+            &mut enemies,
+            0,
+            CombatAction::Attack,
+            &CombatContext::default(),
+            &mut never_rng(),
+            &mut loot_rng(),
+        );
+        assert!(!result.messages.iter().any(|m| m.starts_with("Fang")));
+        assert!(player.health < 1000);
+    }
+
+    #[test]
+    fn test_taming_charms_win_over_badly_hurt_beasts() {
+        let mut player = thrower(&[(ConsumableType::TamingCharm, 1)]);
+        let mut enemies = vec![Enemy::new(
+            "Oozing Slime".to_string(),
+            crate::world::enemy::EnemyType::Slime,
+            1,
+        )];
+        let tame = |player: &mut Player, enemies: &mut [Enemy]| {
+            process_group_combat_turn_with_rng(
+                player,
+                enemies,
+                0,
+                CombatAction::UseItem(0),
+                &CombatContext::default(),
+                &mut never_rng(),
+                &mut loot_rng(),
+            )
+        };
+
+        // A healthy slime shrugs the charm off, and the charm is kept
+        let result = tame(&mut player, &mut enemies);
+        assert!(result.messages[0].starts_with("The Oozing Slime won't be tamed"));
+        assert_eq!(player.inventory.items.len(), 1);
+
+        enemies[0].health = 1;
+        let result = tame(&mut player, &mut enemies);
+        assert!(result.enemy_defeated);
+        assert!(result.slain.is_empty(), "a tamed beast isn't a kill");
+        assert_eq!(result.experience_gained, 0);
+        assert_eq!(player.companion.as_ref().unwrap().name, "Oozing Slime");
+        assert!(player.inventory.items.is_empty());
+    }
+
+    #[test]
+    fn test_target_selection_skips_the_fallen() {
+        let mut enemies = goblins(3);
+        assert_eq!(next_target(&enemies, 0), 1);
+        assert_eq!(next_target(&enemies, 2), 0);
+
+        enemies[1].health = 0;
+        assert_eq!(next_target(&enemies, 0), 2);
+        assert_eq!(living_target(&enemies, 1), Some(0));
+
+        enemies[0].health = 0;
+        enemies[2].health = 0;
+        assert_eq!(living_target(&enemies, 0), None);
     }
 }
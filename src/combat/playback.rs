@@ -0,0 +1,189 @@
+//! Paced presentation of combat turns.
+//!
+//! A turn resolves instantly, but showing all of its messages at once makes
+//! fights hard to follow. `CombatPlayback` hands the messages out one at a
+//! time, eases the enemy's health toward its new value, and holds back the
+//! end of the fight until everything has been shown. Times are in seconds
+//! from any clock the front-end likes.
+
+use std::collections::VecDeque;
+
+/// Seconds between two combat messages
+pub const MESSAGE_DELAY: f64 = 0.4;
+
+/// How a fight ended, applied once its last messages have been shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CombatEnding {
+    Victory,
+    Fled,
+    Defeat,
+}
+
+#[derive(Debug, Clone)]
+pub struct CombatPlayback {
+    delay: f64,
+    pending: VecDeque<String>,
+    next_reveal: f64,
+    health_from: i32,
+    health_to: i32,
+    started: f64,
+    duration: f64,
+    ending: Option<CombatEnding>,
+}
+
+impl Default for CombatPlayback {
+    fn default() -> Self {
+        Self::new(MESSAGE_DELAY)
+    }
+}
+
+impl CombatPlayback {
+    pub fn new(delay: f64) -> Self {
+        Self {
+            delay,
+            pending: VecDeque::new(),
+            next_reveal: 0.0,
+            health_from: 0,
+            health_to: 0,
+            started: 0.0,
+            duration: 0.0,
+            ending: None,
+        }
+    }
+
+    /// Queues a turn's messages, the first of which is due right away.
+    /// `enemy_health` is the enemy's health before and after the turn.
+    pub fn start_turn(
+        &mut self,
+        now: f64,
+        messages: impl IntoIterator<Item = String>,
+        enemy_health: (i32, i32),
+        ending: Option<CombatEnding>,
+    ) {
+        self.pending.extend(messages);
+        self.next_reveal = now;
+        (self.health_from, self.health_to) = enemy_health;
+        self.started = now;
+        // The health bar settles as the last message appears
+        self.duration = self.delay * self.pending.len().saturating_sub(1) as f64;
+        self.ending = ending;
+    }
+
+    /// Takes the messages that are due by `now`, oldest first
+    pub fn tick(&mut self, now: f64) -> Vec<String> {
+        let mut due = Vec::new();
+        while now >= self.next_reveal {
+            let Some(message) = self.pending.pop_front() else {
+                break;
+            };
+            due.push(message);
+            self.next_reveal += self.delay;
+        }
+        due
+    }
+
+    /// Takes every remaining message at once
+    pub fn skip(&mut self) -> Vec<String> {
+        self.duration = 0.0;
+        self.pending.drain(..).collect()
+    }
+
+    /// Whether messages are still waiting to be shown
+    pub fn is_playing(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// The fight's ending, once every message has been shown
+    pub fn take_ending(&mut self) -> Option<CombatEnding> {
+        if self.is_playing() {
+            None
+        } else {
+            self.ending.take()
+        }
+    }
+
+    /// Enemy health to show at `now`, easing from its old value to its new one
+    pub fn enemy_health(&self, now: f64) -> i32 {
+        if self.duration <= 0.0 {
+            return self.health_to;
+        }
+
+        let progress = ((now - self.started) / self.duration).clamp(0.0, 1.0);
+        let change = (self.health_to - self.health_from) as f64 * progress;
+        self.health_from + change.round() as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|text| text.to_string()).collect()
+    }
+
+    #[test]
+    fn test_messages_are_revealed_in_order_one_delay_apart() {
+        let mut playback = CombatPlayback::new(0.5);
+        playback.start_turn(10.0, messages(&["hit", "miss", "win"]), (8, 0), None);
+
+        assert_eq!(playback.tick(10.0), messages(&["hit"]));
+        assert!(playback.tick(10.4).is_empty());
+        assert_eq!(playback.tick(10.5), messages(&["miss"]));
+        assert_eq!(playback.tick(11.0), messages(&["win"]));
+        assert!(!playback.is_playing());
+    }
+
+    #[test]
+    fn test_late_tick_catches_up_in_order() {
+        let mut playback = CombatPlayback::new(0.5);
+        playback.start_turn(0.0, messages(&["a", "b", "c"]), (5, 5), None);
+
+        assert_eq!(playback.tick(3.0), messages(&["a", "b", "c"]));
+    }
+
+    #[test]
+    fn test_ending_waits_for_the_queue_to_drain() {
+        let mut playback = CombatPlayback::new(0.5);
+        playback.start_turn(
+            0.0,
+            messages(&["hit", "defeated"]),
+            (4, 0),
+            Some(CombatEnding::Victory),
+        );
+
+        playback.tick(0.0);
+        assert_eq!(playback.take_ending(), None);
+
+        playback.tick(0.5);
+        assert_eq!(playback.take_ending(), Some(CombatEnding::Victory));
+        assert_eq!(playback.take_ending(), None);
+    }
+
+    #[test]
+    fn test_skip_flushes_the_queue_and_releases_the_ending() {
+        let mut playback = CombatPlayback::new(0.5);
+        playback.start_turn(
+            0.0,
+            messages(&["a", "b", "c"]),
+            (10, 0),
+            Some(CombatEnding::Defeat),
+        );
+        playback.tick(0.0);
+
+        assert_eq!(playback.skip(), messages(&["b", "c"]));
+        assert_eq!(playback.enemy_health(0.0), 0);
+        assert_eq!(playback.take_ending(), Some(CombatEnding::Defeat));
+    }
+
+    #[test]
+    fn test_enemy_health_eases_toward_its_new_value() {
+        let mut playback = CombatPlayback::new(0.5);
+        playback.start_turn(0.0, messages(&["a", "b", "c"]), (10, 2), None);
+
+        assert_eq!(playback.enemy_health(0.0), 10);
+        assert_eq!(playback.enemy_health(0.5), 6);
+        assert_eq!(playback.enemy_health(1.0), 2);
+        assert_eq!(playback.enemy_health(5.0), 2);
+    }
+}
@@ -0,0 +1,212 @@
+//! Keeping a run alive through a crash
+//!
+//! The terminal game runs each game inside `guard`, which writes the game
+//! to a recovery file if it panics before passing the panic on. The next
+//! launch offers to restore it. A panic hook writes the panic and its
+//! backtrace to a log file, since printing them would garble the alternate
+//! screen the game draws on. Other reports worth attaching to a bug report,
+//! such as a player getting stuck, go to the same file.
+
+use anyhow::{Context, Result};
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::game::Game;
+use crate::save::{data_dir, write_atomically, SaveSummary};
+
+const RECOVERY_FILE_NAME: &str = "crash_recovery.save";
+const CRASH_LOG_NAME: &str = "crash.log";
+
+/// Panic payload passed on once the game that crashed has been saved
+#[derive(Debug)]
+pub struct RecoveredCrash {
+    /// What the original panic said
+    pub message: String,
+    pub saved_to: PathBuf,
+}
+
+/// The file a crashed game is written to
+pub struct CrashRecovery {
+    path: PathBuf,
+}
+
+impl CrashRecovery {
+    /// Uses the recovery file inside `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        CrashRecovery {
+            path: dir.into().join(RECOVERY_FILE_NAME),
+        }
+    }
+
+    /// Uses the recovery file in the game's data directory
+    pub fn in_default_location() -> Self {
+        Self::new(data_dir())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Describes the game waiting to be restored, if there is a readable one
+    pub fn summary(&self) -> Option<SaveSummary> {
+        self.read().ok().map(|game| SaveSummary::from_game(&game))
+    }
+
+    fn read(&self) -> Result<Game> {
+        let json = fs::read_to_string(&self.path)
+            .with_context(|| format!("Could not read {}", self.path.display()))?;
+        serde_json::from_str(&json).context("The crash recovery file is corrupted")
+    }
+
+    pub fn save(&self, game: &Game) -> Result<()> {
+        let json = serde_json::to_string(game)?;
+        write_atomically(&self.path, json)
+    }
+
+    /// Loads the crashed game and deletes the file, so it's restored once
+    pub fn restore(&self) -> Result<Game> {
+        let mut game = self.read()?;
+        self.discard()?;
+        game.update_visibility();
+        Ok(game)
+    }
+
+    pub fn discard(&self) -> Result<()> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)
+                .with_context(|| format!("Could not delete {}", self.path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs `play` on `game`. If it panics, the game is written to `recovery`
+/// and the panic carries on as a `RecoveredCrash`; should the save fail too,
+/// the original panic carries on instead.
+pub fn guard<T>(game: &mut Game, recovery: &CrashRecovery, play: impl FnOnce(&mut Game) -> T) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(|| play(game))) {
+        Ok(value) => value,
+        Err(payload) => {
+            if recovery.save(game).is_err() {
+                panic::resume_unwind(payload);
+            }
+            panic::resume_unwind(Box::new(RecoveredCrash {
+                message: panic_message(payload.as_ref()),
+                saved_to: recovery.path().to_path_buf(),
+            }))
+        }
+    }
+}
+
+/// The text of a panic, for the payloads `panic!` makes
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(crash) = payload.downcast_ref::<RecoveredCrash>() {
+        crash.message.clone()
+    } else if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown error".to_string()
+    }
+}
+
+/// Where the panic hook writes crash details
+pub fn log_path() -> PathBuf {
+    data_dir().join(CRASH_LOG_NAME)
+}
+
+/// Sends panics and their backtraces to the crash log instead of the
+/// terminal, falling back to the default hook if the log can't be written
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let written = append_to_log(&log_path(), &info.to_string());
+        if written.is_err() {
+            default_hook(info);
+        }
+    }));
+}
+
+fn append_to_log(path: &Path, panic: &str) -> Result<()> {
+    append_entry(
+        path,
+        "Crash",
+        &[panic.to_string(), Backtrace::force_capture().to_string()],
+    )
+}
+
+/// Writes `lines` to the crash log under a `heading` like "Player stuck",
+/// for bug reports
+pub fn log_report(heading: &str, lines: &[String]) -> Result<()> {
+    append_entry(&log_path(), heading, lines)
+}
+
+fn append_entry(path: &Path, heading: &str, lines: &[String]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    let mut log = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(
+        log,
+        "=== {heading} at {seconds} (seconds since the Unix epoch) ==="
+    )?;
+    for line in lines {
+        writeln!(log, "{line}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::{ClassType, Player};
+    use crate::save::TempDir;
+
+    #[test]
+    fn test_a_panicking_game_is_saved_for_recovery() {
+        let dir = TempDir::new("crash_recovery");
+        let recovery = CrashRecovery::new(dir.path());
+        let mut game = Game::new(Player::new("Tester".to_string(), ClassType::Warrior));
+        assert!(recovery.summary().is_none());
+
+        let crashed = panic::catch_unwind(AssertUnwindSafe(|| {
+            guard(&mut game, &recovery, |game| {
+                game.player.gold = 77;
+                panic!("the dungeon collapsed");
+            })
+        }));
+
+        let payload = crashed.unwrap_err();
+        let crash = payload.downcast_ref::<RecoveredCrash>().unwrap();
+        assert_eq!(crash.message, "the dungeon collapsed");
+        assert_eq!(crash.saved_to, recovery.path());
+        assert_eq!(recovery.summary().unwrap().name, "Tester");
+
+        let restored = recovery.restore().unwrap();
+        assert_eq!(restored.player.gold, 77);
+        // A recovered game is only offered once
+        assert!(!recovery.path().exists());
+    }
+
+    #[test]
+    fn test_a_game_that_ends_normally_leaves_nothing_to_recover() {
+        let dir = TempDir::new("crash_no_recovery");
+        let recovery = CrashRecovery::new(dir.path());
+        let mut game = Game::new(Player::new("Tester".to_string(), ClassType::Mage));
+
+        let level = guard(&mut game, &recovery, |game| game.player.level);
+        assert_eq!(level, 1);
+        assert!(recovery.summary().is_none());
+        assert!(recovery.restore().is_err());
+    }
+}
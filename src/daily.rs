@@ -0,0 +1,166 @@
+//! The daily challenge: one run a day, the same for everyone who plays it
+//!
+//! Everything about the run comes from the date (in UTC): the world seed,
+//! and the class, which rotates through the four day by day. The difficulty
+//! is always Normal and death is final. Nothing is fetched from anywhere, so
+//! two players on the same day get the same dungeons without going online.
+//! Each day's result goes into its own section of the hall of fame, and a
+//! day's challenge can only be taken once.
+
+use serde::{Deserialize, Serialize};
+
+use crate::character::{ClassType, Player};
+use crate::game::{Difficulty, Game, PendingGame};
+use crate::hall_of_fame::{self, RunRecord};
+use crate::startup::StartupOptions;
+
+/// The challenge for one day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyChallenge {
+    /// Day of the challenge, as YYYY-MM-DD
+    pub date: String,
+}
+
+impl DailyChallenge {
+    /// Today's challenge
+    pub fn today() -> Self {
+        Self::for_date(hall_of_fame::today())
+    }
+
+    pub fn for_date(date: impl Into<String>) -> Self {
+        DailyChallenge { date: date.into() }
+    }
+
+    /// World seed for the day: an FNV-1a hash of the date, which unlike the
+    /// standard library's hasher is the same on every build and platform
+    pub fn seed(&self) -> u64 {
+        self.date.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+
+    /// The class everyone plays that day, moving on to the next each day
+    pub fn class(&self) -> ClassType {
+        ClassType::ALL[day_of_year(&self.date) as usize % ClassType::ALL.len()]
+    }
+
+    /// "Daily Challenge — 2024-06-01"
+    pub fn label(&self) -> String {
+        format!("Daily Challenge — {}", self.date)
+    }
+
+    /// Starts the day's run for a character called `name`
+    pub fn start(&self, name: String) -> Game {
+        self.begin(name).finish()
+    }
+
+    /// Sets up the day's run for a character called `name`, its dungeon
+    /// still to be generated
+    pub fn begin(&self, name: String) -> PendingGame {
+        let options = StartupOptions {
+            seed: Some(self.seed()),
+            difficulty: Difficulty::Normal,
+            ..StartupOptions::default()
+        };
+        let mut pending = Game::start(Player::new(name, self.class()), &options);
+        let game = pending.game_mut();
+        game.permadeath = true;
+        game.daily = Some(self.clone());
+        pending
+    }
+
+    /// Why the challenge can't be taken again once `result` is recorded
+    pub fn already_taken(&self, result: &RunRecord) -> String {
+        format!(
+            "You've already taken the {}: {} the {}, score {} ({}).",
+            self.label(),
+            result.name,
+            result.class,
+            result.score(),
+            result.outcome
+        )
+    }
+}
+
+/// Day of the year, counting from 1, of a YYYY-MM-DD date; 0 if it can't be
+/// read
+fn day_of_year(date: &str) -> u32 {
+    let mut parts = date.split('-').map(|part| part.parse::<u32>().ok());
+    let (Some(Some(year)), Some(Some(month)), Some(Some(day))) =
+        (parts.next(), parts.next(), parts.next())
+    else {
+        return 0;
+    };
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days_in_months = [
+        31,
+        if leap { 29 } else { 28 },
+        31,
+        30,
+        31,
+        30,
+        31,
+        31,
+        30,
+        31,
+        30,
+    ];
+    days_in_months
+        .iter()
+        .take(month.saturating_sub(1) as usize)
+        .sum::<u32>()
+        + day
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameState;
+    use crate::world::TileType;
+
+    #[test]
+    fn test_same_day_same_dungeons() {
+        let first = DailyChallenge::for_date("2024-06-01").start("Ann".to_string());
+        let second = DailyChallenge::for_date("2024-06-01").start("Bob".to_string());
+        let next_day = DailyChallenge::for_date("2024-06-02").start("Ann".to_string());
+
+        let layout = |game: &Game| -> Vec<Vec<Vec<TileType>>> {
+            game.current_dungeon()
+                .levels
+                .iter()
+                .map(|level| {
+                    level
+                        .tiles
+                        .iter()
+                        .map(|row| row.iter().map(|tile| tile.tile_type).collect())
+                        .collect()
+                })
+                .collect()
+        };
+        assert_eq!(layout(&first), layout(&second));
+        assert_ne!(layout(&first), layout(&next_day));
+        assert_eq!(first.player_position(), second.player_position());
+    }
+
+    #[test]
+    fn test_rules_are_fixed_by_the_date() {
+        let daily = DailyChallenge::for_date("2024-06-01");
+        let game = daily.start("Ann".to_string());
+
+        assert!(game.permadeath);
+        assert_eq!(game.difficulty, Difficulty::Normal);
+        assert_eq!(game.daily, Some(daily.clone()));
+        assert!(matches!(game.game_state, GameState::MainMenu));
+        assert_eq!(daily.label(), "Daily Challenge — 2024-06-01");
+
+        // June 1st 2024 is the 153rd day of a leap year
+        assert_eq!(day_of_year("2024-06-01"), 153);
+        assert_eq!(day_of_year("2023-01-01"), 1);
+        assert_eq!(day_of_year("garbage"), 0);
+        assert_eq!(game.player.class.class_type, ClassType::ALL[153 % 4]);
+        assert_ne!(
+            DailyChallenge::for_date("2024-06-02").class(),
+            daily.class()
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! Flashes and shakes that make blows felt without sound.
+//!
+//! The game queues a `ScreenEffect` whenever the player is hurt, lands a
+//! critical hit or levels up, and each front-end takes them and shows them
+//! with its own timing in an `ActiveEffects`. The GUI and web flash the
+//! bars and shake the map for a few frames; the terminal, which can't
+//! shake, shows the line in reverse video for a moment instead. The Screen
+//! effects option turns them off for players bothered by flashing.
+
+use crate::combat::CombatResult;
+use crate::world::fog_of_war::FogColor;
+
+/// How long the map shakes after the player is hurt, in milliseconds:
+/// three frames at sixty a second
+pub const SHAKE_MS: f64 = 50.0;
+/// Farthest the map is pushed from its place while it shakes, in pixels
+pub const SHAKE_PIXELS: f32 = 2.0;
+/// Length of one frame at sixty a second, in milliseconds
+const FRAME_MS: f64 = 1000.0 / 60.0;
+
+/// Something that happened to the player worth a flash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenEffect {
+    /// The player took damage: their health flashes red and the map shakes
+    Hurt,
+    /// The player landed a critical hit: the enemy's health flashes
+    CriticalHit,
+    /// The player went up a level: the stat panel flashes gold
+    LevelUp,
+}
+
+impl ScreenEffect {
+    /// How long the flash lasts, in milliseconds
+    pub fn duration_ms(self) -> f64 {
+        match self {
+            ScreenEffect::Hurt | ScreenEffect::CriticalHit => 250.0,
+            ScreenEffect::LevelUp => 600.0,
+        }
+    }
+
+    /// Color of the flash
+    pub fn color(self) -> FogColor {
+        match self {
+            ScreenEffect::Hurt => FogColor::rgb(220, 40, 40),
+            ScreenEffect::CriticalHit => FogColor::rgb(255, 255, 255),
+            ScreenEffect::LevelUp => FogColor::rgb(255, 200, 40),
+        }
+    }
+}
+
+/// Effects the game has queued and no front-end has taken yet
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EffectQueue {
+    pending: Vec<ScreenEffect>,
+}
+
+impl EffectQueue {
+    pub fn push(&mut self, effect: ScreenEffect) {
+        self.pending.push(effect);
+    }
+
+    /// Queues what a combat turn calls for: a flash for every way the
+    /// player was hurt, struck a critical blow or went up a level
+    pub fn record_combat(&mut self, result: &CombatResult) {
+        if result.enemy_damage_dealt > 0 {
+            self.push(ScreenEffect::Hurt);
+        }
+        if result.hits.iter().any(|hit| hit.is_player_critical()) {
+            self.push(ScreenEffect::CriticalHit);
+        }
+        if result.player_level_up {
+            self.push(ScreenEffect::LevelUp);
+        }
+    }
+
+    /// Returns and clears the effects queued since the last call
+    pub fn take(&mut self) -> Vec<ScreenEffect> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// The effects a front-end is showing and when each started, in whatever
+/// milliseconds its clock counts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActiveEffects {
+    started: Vec<(ScreenEffect, f64)>,
+}
+
+impl ActiveEffects {
+    /// Starts showing `effects` at `now`; one already showing starts over
+    pub fn start(&mut self, effects: impl IntoIterator<Item = ScreenEffect>, now: f64) {
+        for effect in effects {
+            self.started.retain(|(showing, _)| *showing != effect);
+            self.started.push((effect, now));
+        }
+    }
+
+    /// Whether `effect` is still showing at `now`
+    pub fn is_showing(&self, effect: ScreenEffect, now: f64) -> bool {
+        self.started
+            .iter()
+            .any(|&(showing, at)| showing == effect && now - at < effect.duration_ms())
+    }
+
+    /// How far to push the map at `now`, in pixels: a different way each
+    /// frame for the first few after the player is hurt, then not at all
+    pub fn shake_offset(&self, now: f64) -> (f32, f32) {
+        let Some(elapsed) = self
+            .started
+            .iter()
+            .find(|(effect, _)| *effect == ScreenEffect::Hurt)
+            .map(|&(_, at)| now - at)
+            .filter(|&elapsed| (0.0..SHAKE_MS).contains(&elapsed))
+        else {
+            return (0.0, 0.0);
+        };
+        let half = SHAKE_PIXELS / 2.0;
+        match (elapsed / FRAME_MS) as u32 % 3 {
+            0 => (SHAKE_PIXELS, -half),
+            1 => (-SHAKE_PIXELS, half),
+            _ => (half, SHAKE_PIXELS),
+        }
+    }
+
+    /// Drops the effects that have run their course by `now`. Returns
+    /// whether any were showing, so the front-end keeps redrawing until a
+    /// frame after the last one ends.
+    pub fn expire(&mut self, now: f64) -> bool {
+        let showing = !self.started.is_empty();
+        self.started
+            .retain(|&(effect, at)| now - at < effect.duration_ms());
+        showing
+    }
+
+    pub fn clear(&mut self) {
+        self.started.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::{Hit, Striker};
+
+    #[test]
+    fn test_a_combat_turn_queues_a_flash_for_each_thing_that_happened() {
+        let mut queue = EffectQueue::default();
+        let mut result = CombatResult::new();
+        result.player_damage_dealt = 6;
+        result
+            .hits
+            .push(Hit::new(Striker::Player, "Goblin", 6).bonus("weapon", 2));
+        queue.record_combat(&result);
+        assert!(queue.take().is_empty(), "a plain hit shows nothing");
+
+        result
+            .hits
+            .push(Hit::new(Striker::Enemy, "Goblin", 4).multiplier("crit", 1.5));
+        result.enemy_damage_dealt = 6;
+        queue.record_combat(&result);
+        assert_eq!(queue.take(), [ScreenEffect::Hurt], "the enemy's crit");
+
+        result
+            .hits
+            .push(Hit::new(Striker::Player, "Goblin", 8).multiplier("crit", 2.0));
+        result.player_level_up = true;
+        queue.record_combat(&result);
+        assert_eq!(
+            queue.take(),
+            [
+                ScreenEffect::Hurt,
+                ScreenEffect::CriticalHit,
+                ScreenEffect::LevelUp
+            ]
+        );
+        assert!(queue.take().is_empty());
+    }
+
+    #[test]
+    fn test_effects_run_their_course_and_start_over_when_repeated() {
+        let mut active = ActiveEffects::default();
+        active.start([ScreenEffect::Hurt, ScreenEffect::LevelUp], 1000.0);
+        assert!(active.is_showing(ScreenEffect::Hurt, 1000.0));
+        assert!(!active.is_showing(ScreenEffect::CriticalHit, 1000.0));
+        assert_ne!(active.shake_offset(1010.0), (0.0, 0.0));
+        assert_ne!(active.shake_offset(1010.0), active.shake_offset(1030.0));
+
+        // The shake is over long before the flash
+        assert_eq!(active.shake_offset(1000.0 + SHAKE_MS), (0.0, 0.0));
+        assert!(active.is_showing(ScreenEffect::Hurt, 1200.0));
+        assert!(active.expire(1300.0));
+        assert!(!active.is_showing(ScreenEffect::Hurt, 1300.0));
+        assert!(active.is_showing(ScreenEffect::LevelUp, 1300.0));
+
+        active.start([ScreenEffect::LevelUp], 1500.0);
+        assert!(active.expire(1700.0), "started over at 1500");
+        assert!(active.is_showing(ScreenEffect::LevelUp, 1700.0));
+        // One more frame to draw it gone, then nothing
+        assert!(active.expire(2100.0));
+        assert!(!active.is_showing(ScreenEffect::LevelUp, 2100.0));
+        assert!(!active.expire(2120.0));
+
+        active.start([ScreenEffect::CriticalHit], 3000.0);
+        active.clear();
+        assert!(!active.is_showing(ScreenEffect::CriticalHit, 3000.0));
+    }
+}
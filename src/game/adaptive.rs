@@ -0,0 +1,224 @@
+//! Difficulty that follows how the player is getting on
+//!
+//! With adaptive difficulty on, the game keeps a short history of the
+//! player's fights and scores how comfortably they came through them. A
+//! hidden multiplier drifts towards what that score calls for, and every
+//! enemy spawned from then on has its stats and the loot it carries scaled
+//! by it. Enemies already about are never changed, and the multiplier only
+//! moves a little each time the player reaches a new level, so the game
+//! never lurches from hard to easy.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+/// Lowest the multiplier goes, for a player who keeps nearly dying
+pub const MIN_MULTIPLIER: f32 = 0.85;
+
+/// Highest the multiplier goes, for a player who's never touched
+pub const MAX_MULTIPLIER: f32 = 1.15;
+
+/// Most the multiplier moves on reaching a level
+pub const MAX_STEP: f32 = 0.05;
+
+/// Fights the score is worked out from, the newest kept
+pub const HISTORY_LENGTH: usize = 10;
+
+/// Share of max health below which a fight counts as a close call
+pub const NEAR_DEATH_SHARE: f32 = 0.2;
+
+/// How one fight went for the player
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FightRecord {
+    /// Damage taken over the fight, as a share of max health
+    pub damage_share: f32,
+    pub potions_used: u32,
+    /// Whether their health fell below `NEAR_DEATH_SHARE` of its maximum
+    pub near_death: bool,
+    pub fled: bool,
+}
+
+/// How well a fight went, from -1 (barely got out alive) to 1 (untouched)
+pub fn fight_score(fight: &FightRecord) -> f32 {
+    let mut score = 1.0 - 2.0 * fight.damage_share.clamp(0.0, 1.0);
+    score -= 0.25 * fight.potions_used.min(4) as f32;
+    if fight.near_death {
+        score -= 0.5;
+    }
+    if fight.fled {
+        score -= 0.5;
+    }
+    score.clamp(-1.0, 1.0)
+}
+
+/// How well the player is doing over `history`: the mean of its fight
+/// scores, or 0 before there are any
+pub fn performance_score(history: &[FightRecord]) -> f32 {
+    if history.is_empty() {
+        return 0.0;
+    }
+    history.iter().map(fight_score).sum::<f32>() / history.len() as f32
+}
+
+/// The multiplier a performance score calls for, from `MIN_MULTIPLIER` for
+/// a score of -1 to `MAX_MULTIPLIER` for 1
+pub fn target_multiplier(score: f32) -> f32 {
+    let spread = (MAX_MULTIPLIER - MIN_MULTIPLIER) / 2.0;
+    (1.0 + score.clamp(-1.0, 1.0) * spread).clamp(MIN_MULTIPLIER, MAX_MULTIPLIER)
+}
+
+/// `current` moved towards `target` by no more than `MAX_STEP`, kept
+/// within bounds
+pub fn next_multiplier(current: f32, target: f32) -> f32 {
+    let step = (target - current).clamp(-MAX_STEP, MAX_STEP);
+    (current + step).clamp(MIN_MULTIPLIER, MAX_MULTIPLIER)
+}
+
+/// The fights remembered and the multiplier they've led to, saved with
+/// the game
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptiveDifficulty {
+    /// What enemies spawned from now on have their stats and loot scaled by
+    pub multiplier: f32,
+    /// The newest fights, oldest first
+    history: VecDeque<FightRecord>,
+    /// The fight under way, if there is one
+    current: Option<FightRecord>,
+}
+
+impl Default for AdaptiveDifficulty {
+    fn default() -> Self {
+        AdaptiveDifficulty {
+            multiplier: 1.0,
+            history: VecDeque::new(),
+            current: None,
+        }
+    }
+}
+
+impl AdaptiveDifficulty {
+    /// Notes a turn of the fight under way: `damage` taken by a player
+    /// left with `health` of `max_health`, and whether a potion was drunk
+    pub fn record_turn(&mut self, damage: i32, health: i32, max_health: i32, potion: bool) {
+        let fight = self.current.get_or_insert_with(FightRecord::default);
+        fight.damage_share += damage.max(0) as f32 / max_health.max(1) as f32;
+        fight.potions_used += u32::from(potion);
+        fight.near_death |= (health as f32) < max_health as f32 * NEAR_DEATH_SHARE;
+    }
+
+    /// Files away the fight under way, noting whether the player fled it
+    pub fn finish_fight(&mut self, fled: bool) {
+        let Some(mut fight) = self.current.take() else {
+            return;
+        };
+        fight.fled = fled;
+        self.history.push_back(fight);
+        while self.history.len() > HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+    }
+
+    /// Moves the multiplier a step towards what the fights so far call for,
+    /// as the player reaches a level
+    pub fn level_reached(&mut self) {
+        let target = target_multiplier(performance_score(self.history.make_contiguous()));
+        self.multiplier = next_multiplier(self.multiplier, target);
+    }
+
+    /// "Adaptive difficulty: x1.05", for debug builds to show
+    pub fn label(&self) -> String {
+        format!("Adaptive difficulty: x{:.2}", self.multiplier)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flawless() -> FightRecord {
+        FightRecord::default()
+    }
+
+    fn close_call() -> FightRecord {
+        FightRecord {
+            damage_share: 0.9,
+            potions_used: 2,
+            near_death: true,
+            fled: false,
+        }
+    }
+
+    #[test]
+    fn test_fights_are_scored_from_untouched_to_barely_alive() {
+        assert_eq!(fight_score(&flawless()), 1.0);
+        assert_eq!(fight_score(&close_call()), -1.0);
+        let half = FightRecord {
+            damage_share: 0.5,
+            ..flawless()
+        };
+        assert_eq!(fight_score(&half), 0.0);
+        let fled = FightRecord {
+            damage_share: 0.25,
+            fled: true,
+            ..flawless()
+        };
+        assert_eq!(fight_score(&fled), 0.0);
+
+        assert_eq!(performance_score(&[]), 0.0);
+        assert_eq!(performance_score(&[flawless(), close_call()]), 0.0);
+        assert_eq!(performance_score(&[flawless(); 3]), 1.0);
+    }
+
+    #[test]
+    fn test_the_multiplier_is_clamped_and_moves_a_step_at_a_time() {
+        assert_eq!(target_multiplier(1.0), MAX_MULTIPLIER);
+        assert_eq!(target_multiplier(-5.0), MIN_MULTIPLIER);
+        assert_eq!(target_multiplier(0.0), 1.0);
+
+        assert_eq!(next_multiplier(1.0, MAX_MULTIPLIER), 1.0 + MAX_STEP);
+        assert_eq!(next_multiplier(1.0, 0.98), 0.98);
+        assert_eq!(next_multiplier(1.14, 2.0), MAX_MULTIPLIER);
+        assert_eq!(next_multiplier(0.5, 0.5), MIN_MULTIPLIER);
+    }
+
+    #[test]
+    fn test_a_run_of_easy_fights_raises_the_multiplier_level_by_level() {
+        let mut adaptive = AdaptiveDifficulty::default();
+        adaptive.level_reached();
+        assert_eq!(adaptive.multiplier, 1.0);
+
+        for _ in 0..HISTORY_LENGTH + 5 {
+            adaptive.record_turn(0, 50, 50, false);
+            adaptive.finish_fight(false);
+        }
+        assert_eq!(adaptive.history.len(), HISTORY_LENGTH);
+        let mut previous = adaptive.multiplier;
+        for _ in 0..5 {
+            adaptive.level_reached();
+            assert!(adaptive.multiplier - previous <= MAX_STEP + f32::EPSILON);
+            previous = adaptive.multiplier;
+        }
+        assert!((adaptive.multiplier - MAX_MULTIPLIER).abs() < 1e-6);
+
+        // A string of close calls brings it back down just as gently
+        for _ in 0..HISTORY_LENGTH {
+            adaptive.record_turn(30, 5, 50, true);
+            adaptive.record_turn(15, 5, 50, true);
+            adaptive.finish_fight(false);
+        }
+        adaptive.level_reached();
+        assert!((adaptive.multiplier - (MAX_MULTIPLIER - MAX_STEP)).abs() < 1e-6);
+
+        let json = serde_json::to_string(&adaptive).unwrap();
+        assert_eq!(
+            serde_json::from_str::<AdaptiveDifficulty>(&json).unwrap(),
+            adaptive
+        );
+        // Saves from before adaptive difficulty start it at 1
+        assert_eq!(
+            serde_json::from_str::<AdaptiveDifficulty>("{}").unwrap(),
+            AdaptiveDifficulty::default()
+        );
+    }
+}
@@ -0,0 +1,55 @@
+//! How hard the dungeons are made for a run
+//!
+//! The difficulty is picked when the game starts and stays with the save. It
+//! scales the difficulty new dungeons are generated at, which in turn sets
+//! how many enemies and how much loot each level gets.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    /// Name used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|difficulty| difficulty.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Dungeon difficulty for a run at this setting, from what it would be
+    /// on Normal: Easy takes a quarter off, Hard adds half again
+    pub fn scale(self, dungeon_difficulty: u32) -> u32 {
+        let scaled = match self {
+            Difficulty::Easy => dungeon_difficulty * 3 / 4,
+            Difficulty::Normal => dungeon_difficulty,
+            Difficulty::Hard => dungeon_difficulty + dungeon_difficulty.div_ceil(2),
+        };
+        scaled.max(1)
+    }
+}
+
+impl std::fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "Easy"),
+            Difficulty::Normal => write!(f, "Normal"),
+            Difficulty::Hard => write!(f, "Hard"),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Who gets to act, and how often, while exploring
+//!
+//! Every creature fills up with energy at its speed, one tick at a time, and
+//! acts each time it has `ACTION_COST` stored. After the player acts, time
+//! runs on until they have enough energy to act again; the enemies gain
+//! energy over those same ticks, so a beast twice as fast as the player
+//! moves twice for every step they take and a burdened player lets the
+//! enemies close in. Leftover energy is kept with the creature, so the
+//! schedule carries over between turns and through a save.
+//!
+//! Combat keeps its own strict turn order and doesn't use energy.
+
+/// Energy spent by one action
+pub const ACTION_COST: u32 = 100;
+
+/// Speed of an ordinary creature: one action every ten ticks
+pub const NORMAL_SPEED: u32 = 10;
+
+/// Ticks that pass before a creature holding `energy` can act again at
+/// `speed`
+pub fn ticks_until_ready(energy: u32, speed: u32) -> u32 {
+    ACTION_COST.saturating_sub(energy).div_ceil(speed.max(1))
+}
+
+/// Adds `ticks` worth of energy at `speed`, returning how many actions it
+/// pays for; whatever is left over stays in `energy`
+pub fn gain(energy: &mut u32, speed: u32, ticks: u32) -> u32 {
+    *energy += speed * ticks;
+    let actions = *energy / ACTION_COST;
+    *energy %= ACTION_COST;
+    actions
+}
+
+/// Speed while hasted: half as fast again
+pub fn hasted(speed: u32) -> u32 {
+    speed + speed / 2
+}
+
+/// Speed while slowed: half speed
+pub fn slowed(speed: u32) -> u32 {
+    (speed / 2).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Actions a creature at `speed` takes over `ticks` ticks, one at a time
+    fn actions_over(speed: u32, ticks: u32) -> u32 {
+        let mut energy = 0;
+        (0..ticks).map(|_| gain(&mut energy, speed, 1)).sum()
+    }
+
+    #[test]
+    fn test_actions_follow_speed_over_100_ticks() {
+        assert_eq!(actions_over(NORMAL_SPEED, 100), 10);
+        assert_eq!(actions_over(2 * NORMAL_SPEED, 100), 20);
+        assert_eq!(actions_over(hasted(NORMAL_SPEED), 100), 15);
+        assert_eq!(actions_over(slowed(NORMAL_SPEED), 100), 5);
+        assert_eq!(actions_over(7, 100), 7);
+    }
+
+    #[test]
+    fn test_enemies_act_per_player_action_by_relative_speed() {
+        // Counts the enemy's actions over ten of the player's
+        let run = |player_speed: u32, enemy_speed: u32| {
+            let (mut player, mut enemy, mut actions) = (0, 0, 0);
+            for _ in 0..10 {
+                let ticks = ticks_until_ready(player, player_speed);
+                assert_eq!(gain(&mut player, player_speed, ticks), 1);
+                actions += gain(&mut enemy, enemy_speed, ticks);
+            }
+            actions
+        };
+        assert_eq!(run(NORMAL_SPEED, NORMAL_SPEED), 10);
+        assert_eq!(run(NORMAL_SPEED, 2 * NORMAL_SPEED), 20);
+        assert_eq!(run(2 * NORMAL_SPEED, NORMAL_SPEED), 5);
+        // A burdened player at 3/4 speed lets enemies act twice now and then
+        let burdened = run(NORMAL_SPEED * 3 / 4, NORMAL_SPEED);
+        assert!((13..=15).contains(&burdened), "{burdened}");
+    }
+
+    #[test]
+    fn test_leftover_energy_carries_over() {
+        let mut energy = 0;
+        assert_eq!(gain(&mut energy, 15, 10), 1);
+        assert_eq!(energy, 50);
+        assert_eq!(ticks_until_ready(energy, 15), 4);
+        assert_eq!(gain(&mut energy, 15, 4), 1);
+        assert_eq!(energy, 10);
+    }
+}
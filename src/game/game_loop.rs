@@ -0,0 +1,566 @@
+//! The rules for what each input does in each game state
+//!
+//! Every front-end turns its own keys, clicks and taps into an [`Input`]
+//! and hands it to [`GameLoop::handle`], which passes it to the handler for
+//! the state the game is in. Anything left for the front-end to do, such as
+//! playing a combat turn back or writing a save, comes back as an
+//! [`Effect`], so the terminal loop, the GUI and the web build each do their
+//! own drawing and I/O around the same transitions.
+
+use serde::{Deserialize, Serialize};
+
+use super::replay::{self, ReplayAction};
+use super::{Game, GameState, Interaction, PauseOption};
+use crate::character::StatType;
+use crate::combat::{self, CombatAction, CombatContext, CombatEnding, CombatResult};
+use crate::inventory::{InventoryManager, RECIPES};
+use crate::item::Item;
+use crate::log::LogCategory;
+use crate::world::reachability::unstick_cost;
+use crate::world::{Enemy, Position};
+
+/// Something the player asked for, in terms of the game rather than keys
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Input {
+    // Exploring
+    Move {
+        dx: i32,
+        dy: i32,
+    },
+    /// Take the stairs the player stands on
+    Stairs {
+        down: bool,
+    },
+    /// Do what `Game::current_interaction` offers: pick up what's
+    /// underfoot, open a chest next to the player and the like
+    GetItem,
+    /// Use what's on this 0-based hotbar slot
+    Hotbar(usize),
+    /// Cast the area ability at this ability index, centred on `aim`
+    CastArea {
+        index: usize,
+        aim: Position,
+    },
+    OpenInventory,
+    OpenCharacter,
+    Save,
+    Pause,
+    /// Open the pause menu straight at the quit question
+    Quit,
+    /// Pull a stuck player free; asks first, and a second press does it
+    Unstick,
+    /// Sense which way the stairs down lie, as a Mage can once a level
+    Sense,
+    /// Write the explored map of the level out to share
+    ExportMap,
+
+    // Inventory
+    UseItem(usize),
+    DropItem(usize),
+    /// Enchant the item at this index, at a shrine
+    Enchant(usize),
+    /// Make what the recipe at this index of `RECIPES` makes
+    Craft(usize),
+    AssignHotbar {
+        index: usize,
+        slot: usize,
+    },
+
+    // Talking
+    /// Take up the offer of the NPC being talked to
+    Accept,
+
+    /// Leave the inventory, character screen, pause menu or a talk for the
+    /// map
+    Back,
+
+    // Combat
+    /// Play a turn against the enemy at `GameLoop::combat_target`
+    Combat(CombatAction),
+    /// The front-end has finished showing the last combat turn
+    CombatShown,
+
+    // Pause menu
+    Choose(PauseOption),
+
+    /// Spend one of the player's unspent stat points, from whichever screen
+    /// offered it
+    AllocateStat(StatType),
+}
+
+/// Something left for the front-end to do after an input
+#[derive(Debug, Clone)]
+pub enum Effect {
+    /// A combat turn was fought; its messages are already in the log. The
+    /// front-end shows it, then sends `Input::CombatShown` so a finished
+    /// fight ends.
+    CombatTurn {
+        result: CombatResult,
+        target: usize,
+        health_before: i32,
+    },
+    /// The player gained a level and has stat points to spend
+    LevelUp,
+    /// Save the game, as the player asked to
+    Save,
+    ShowOptions,
+    ShowHelp,
+    /// Write the current level's explored map out, as the player asked to
+    ExportMap,
+    /// Ask the quit question; on a yes the front-end saves if need be and
+    /// goes back to the title screen
+    AskToQuit,
+}
+
+/// What an input did
+#[derive(Debug, Clone, Default)]
+pub struct Transition {
+    /// The state the game moved into, if it changed
+    pub entered: Option<GameState>,
+    /// What the front-end has left to do, in order
+    pub effects: Vec<Effect>,
+}
+
+/// What a front-end needs to show before asking for a combat action
+#[derive(Debug, Clone)]
+pub struct CombatRound {
+    pub enemies: Vec<Enemy>,
+    /// The line announcing the fight, on its first round only
+    pub opening: Option<String>,
+    /// How where the player stands changes the coming turn's blows, and
+    /// whether they can flee
+    pub context: CombatContext,
+}
+
+/// The game's state machine, kept by a front-end for as long as a game runs
+#[derive(Debug, Clone, Default)]
+pub struct GameLoop {
+    /// Index into the current fight's enemies of the one being attacked
+    pub combat_target: usize,
+    /// How the last combat turn ended the fight, held until it's been shown
+    pending_ending: Option<CombatEnding>,
+    /// The player was asked whether to pull themselves free, and the next
+    /// `Input::Unstick` does it
+    unstick_asked: bool,
+}
+
+impl GameLoop {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies `input` to the game through the handler for its current state.
+    /// Inputs that mean nothing in that state are ignored.
+    pub fn handle(&mut self, game: &mut Game, input: Input) -> Transition {
+        if game.replay.is_some() && replay::accepts(game, &input) {
+            let turn = game.stats.turns;
+            if let Some(recording) = &mut game.replay {
+                recording.record(turn, ReplayAction::Input(input.clone()));
+            }
+        }
+        if let Input::AllocateStat(stat) = input {
+            // The screen offering the points shows why one couldn't be spent
+            let _ = game.player.allocate_stat_point(stat);
+            return Transition::default();
+        }
+
+        let before = game.game_state.clone();
+        let effects = match before {
+            GameState::Playing => self.playing(game, input),
+            GameState::Combat(_) => self.combat(game, input),
+            GameState::Inventory => self.inventory(game, input),
+            GameState::Character => self.character(game, input),
+            GameState::Paused => self.paused(game, input),
+            GameState::Dialogue(pos) => self.dialogue(game, pos, input),
+            GameState::MainMenu | GameState::GameOver | GameState::Victory => Vec::new(),
+        };
+        game.warn_of_low_health();
+        Transition {
+            entered: (game.game_state != before).then(|| game.game_state.clone()),
+            effects,
+        }
+    }
+
+    /// Gets a fight ready for the next action: announces a fight that just
+    /// started and moves the aim off fallen enemies. Returns `None`, back
+    /// on the map, if the enemies are gone.
+    pub fn combat_round(&mut self, game: &mut Game) -> Option<CombatRound> {
+        if !matches!(game.game_state, GameState::Combat(_)) {
+            return None;
+        }
+        let enemies = game.combat_enemies();
+        if enemies.is_empty() {
+            game.game_state = GameState::Playing;
+            return None;
+        }
+        let opening = if game.combat_started {
+            game.combat_started = false;
+            self.combat_target = 0;
+            self.pending_ending = None;
+            Some(game.combat_start_message())
+        } else {
+            None
+        };
+        self.combat_target = combat::living_target(&enemies, self.combat_target).unwrap_or(0);
+        Some(CombatRound {
+            opening,
+            context: game.combat_context(),
+            enemies,
+        })
+    }
+
+    fn playing(&mut self, game: &mut Game, input: Input) -> Vec<Effect> {
+        // Anything else pressed in between takes the question back
+        let unstick_asked = std::mem::take(&mut self.unstick_asked);
+        match input {
+            // A fight that starts is fought from the next input on
+            Input::Move { dx, dy } if game.move_player(dx, dy).passes_turn() => {
+                game.process_turn();
+            }
+            Input::Stairs { down } if game.take_stairs(down).passes_turn() => {
+                game.process_turn();
+            }
+            Input::GetItem => match game.current_interaction() {
+                // Stairs taken with G cost a turn like any others
+                Some(Interaction::TakeStairs { down }) => {
+                    return self.playing(game, Input::Stairs { down })
+                }
+                _ => game.try_get_item(),
+            },
+            Input::Hotbar(slot) => {
+                let result = game.use_hotbar(slot);
+                game.log.push(LogCategory::System, result.message);
+                if result.success {
+                    game.process_turn();
+                }
+            }
+            Input::CastArea { index, aim } => {
+                let result = game.cast_area_ability(index, aim);
+                game.log.push(LogCategory::Combat, result.message);
+                if result.success {
+                    game.process_turn();
+                }
+            }
+            Input::OpenInventory => game.game_state = GameState::Inventory,
+            Input::OpenCharacter => game.game_state = GameState::Character,
+            Input::Save => return vec![Effect::Save],
+            Input::ExportMap => return vec![Effect::ExportMap],
+            Input::Pause => {
+                game.pause();
+            }
+            Input::Quit if game.pause() => return vec![Effect::AskToQuit],
+            Input::Unstick if unstick_asked => {
+                let result = game.unstick();
+                game.log.push(LogCategory::System, result.message);
+                if result.success {
+                    game.process_turn();
+                }
+            }
+            Input::Sense => {
+                let result = game.sense_way_down();
+                game.log.push(LogCategory::System, result.message);
+                if result.success {
+                    game.process_turn();
+                }
+            }
+            Input::Unstick => {
+                if game.is_stuck() {
+                    game.log.push(
+                        LogCategory::System,
+                        format!(
+                            "Stuck? Press Ctrl+U again to force your way free, at a cost of {} HP.",
+                            unstick_cost(game.player.health)
+                        ),
+                    );
+                    self.unstick_asked = true;
+                } else {
+                    let result = game.unstick();
+                    game.log.push(LogCategory::System, result.message);
+                }
+            }
+            _ => {}
+        }
+        Vec::new()
+    }
+
+    fn inventory(&mut self, game: &mut Game, input: Input) -> Vec<Effect> {
+        match input {
+            Input::UseItem(index) => match InventoryManager::get_item(&game.player, index) {
+                Some(Item::Equipment(_) | Item::Consumable(_)) => {
+                    let result = game.use_item(index);
+                    game.log.push(LogCategory::System, result.message);
+                }
+                Some(Item::Quest { .. }) => {
+                    game.log
+                        .push(LogCategory::System, "This item cannot be used");
+                }
+                None => {}
+            },
+            Input::DropItem(index) => {
+                let result = game.drop_item(index);
+                game.log.push(LogCategory::System, result.message);
+            }
+            Input::Enchant(index) => {
+                let result = game.enchant_item(index);
+                game.log.push(LogCategory::System, result.message);
+            }
+            Input::Craft(recipe) => {
+                if let Some(recipe) = RECIPES.get(recipe) {
+                    let result = InventoryManager::craft(&mut game.player, recipe);
+                    game.log.push(LogCategory::System, result.message);
+                }
+            }
+            Input::AssignHotbar { index, slot } => {
+                let result = InventoryManager::assign_hotbar(&mut game.player, index, slot);
+                game.log.push(LogCategory::System, result.message);
+            }
+            Input::Back => game.game_state = GameState::Playing,
+            _ => {}
+        }
+        Vec::new()
+    }
+
+    fn character(&mut self, game: &mut Game, input: Input) -> Vec<Effect> {
+        if input == Input::Back {
+            game.game_state = GameState::Playing;
+        }
+        Vec::new()
+    }
+
+    fn dialogue(&mut self, game: &mut Game, pos: Position, input: Input) -> Vec<Effect> {
+        match input {
+            Input::Accept => {
+                let result = game.accept_offer(pos);
+                game.log.push(LogCategory::System, result.message);
+                game.game_state = GameState::Playing;
+            }
+            Input::Back => game.game_state = GameState::Playing,
+            _ => {}
+        }
+        Vec::new()
+    }
+
+    fn combat(&mut self, game: &mut Game, input: Input) -> Vec<Effect> {
+        match input {
+            Input::Combat(action) => {
+                // A turn already played has to be shown before the next one
+                if self.pending_ending.is_some() {
+                    return Vec::new();
+                }
+                let enemies = game.combat_enemies();
+                let Some(target) = combat::living_target(&enemies, self.combat_target) else {
+                    // Every foe is already down
+                    game.end_combat(CombatEnding::Victory);
+                    return Vec::new();
+                };
+                self.combat_target = target;
+                let health_before = enemies[target].health;
+
+                let result = game.resolve_combat_turn(action, target);
+                self.pending_ending = result.ending(&game.player);
+                let mut effects = Vec::new();
+                let level_up = result.player_level_up;
+                effects.push(Effect::CombatTurn {
+                    result,
+                    target,
+                    health_before,
+                });
+                if level_up {
+                    effects.push(Effect::LevelUp);
+                }
+                effects
+            }
+            Input::CombatShown => {
+                match self.pending_ending.take() {
+                    Some(ending) => {
+                        game.end_combat(ending);
+                        self.combat_target = 0;
+                    }
+                    None => {
+                        // Move the aim off an enemy that just fell
+                        let enemies = game.combat_enemies();
+                        self.combat_target =
+                            combat::living_target(&enemies, self.combat_target).unwrap_or(0);
+                    }
+                }
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn paused(&mut self, game: &mut Game, input: Input) -> Vec<Effect> {
+        match input {
+            Input::Choose(PauseOption::Resume) | Input::Back => game.resume(),
+            Input::Choose(PauseOption::Save) => {
+                game.resume();
+                return vec![Effect::Save];
+            }
+            Input::Choose(PauseOption::Options) => return vec![Effect::ShowOptions],
+            Input::Choose(PauseOption::Help) => return vec![Effect::ShowHelp],
+            Input::Choose(PauseOption::ResetHints) => {
+                game.reset_hints();
+                game.resume();
+            }
+            Input::Choose(PauseOption::ExportMap) => {
+                game.resume();
+                return vec![Effect::ExportMap];
+            }
+            Input::Choose(PauseOption::QuitToMenu) => return vec![Effect::AskToQuit],
+            _ => {}
+        }
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::{ClassType, Player};
+    use crate::item::Consumable;
+    use crate::world::enemy::EnemyType;
+    use crate::world::{Level, Tile};
+
+    /// A game on an open floor with the player at (2, 2) and no one else
+    fn scripted_game() -> Game {
+        let player = Player::new("Tester".to_string(), ClassType::Warrior);
+        let mut game = Game::new(player);
+        game.game_state = GameState::Playing;
+        game.ambient_events = false;
+
+        let mut level = Level::new(12, 7);
+        for y in 1..6 {
+            for x in 1..11 {
+                let mut tile = Tile::floor();
+                tile.visible = true;
+                level.tiles[y][x] = tile;
+            }
+        }
+        level.player_position = Position::new(2, 2);
+        let dungeon = game.current_dungeon_mut();
+        dungeon.levels[dungeon.current_level] = level;
+        game
+    }
+
+    /// Feeds `inputs` to the state machine, returning every effect in order
+    fn play(game_loop: &mut GameLoop, game: &mut Game, inputs: &[Input]) -> Vec<Effect> {
+        inputs
+            .iter()
+            .flat_map(|input| game_loop.handle(game, input.clone()).effects)
+            .collect()
+    }
+
+    #[test]
+    fn test_walking_and_the_inventory() {
+        let mut game = scripted_game();
+        let mut game_loop = GameLoop::new();
+        InventoryManager::add_item(
+            &mut game.player,
+            Item::Consumable(Consumable::health_potion(20)),
+        );
+        let potion = InventoryManager::get_item_count(&game.player) - 1;
+        game.player.health -= 10;
+        let health = game.player.health;
+
+        let effects = play(
+            &mut game_loop,
+            &mut game,
+            &[
+                Input::Move { dx: 1, dy: 0 },
+                Input::Move { dx: 0, dy: 1 },
+                // Ignored while exploring
+                Input::UseItem(potion),
+                Input::OpenInventory,
+                // Ignored in the inventory
+                Input::Move { dx: 1, dy: 0 },
+                Input::UseItem(potion),
+                Input::Back,
+            ],
+        );
+        assert!(effects.is_empty());
+        assert_eq!(game.player_position(), Position::new(3, 3));
+        assert!(game.player.health > health);
+        assert_eq!(InventoryManager::get_item_count(&game.player), potion);
+        assert_eq!(game.stats.turns, 2);
+        assert!(matches!(game.game_state, GameState::Playing));
+    }
+
+    #[test]
+    fn test_pausing_saving_and_quitting() {
+        let mut game = scripted_game();
+        let mut game_loop = GameLoop::new();
+
+        let transition = game_loop.handle(&mut game, Input::Pause);
+        assert_eq!(transition.entered, Some(GameState::Paused));
+        let effects = play(
+            &mut game_loop,
+            &mut game,
+            &[Input::Choose(PauseOption::Save)],
+        );
+        assert!(matches!(effects[..], [Effect::Save]));
+        assert!(matches!(game.game_state, GameState::Playing));
+
+        let effects = play(&mut game_loop, &mut game, &[Input::Quit]);
+        assert!(matches!(effects[..], [Effect::AskToQuit]));
+        assert!(matches!(game.game_state, GameState::Paused));
+        let effects = play(
+            &mut game_loop,
+            &mut game,
+            &[Input::Choose(PauseOption::QuitToMenu)],
+        );
+        assert!(matches!(effects[..], [Effect::AskToQuit]));
+
+        let transition = game_loop.handle(&mut game, Input::Back);
+        assert_eq!(transition.entered, Some(GameState::Playing));
+        assert!(transition.effects.is_empty());
+
+        // The map can be exported from the map or the pause menu
+        let effects = play(&mut game_loop, &mut game, &[Input::ExportMap]);
+        assert!(matches!(effects[..], [Effect::ExportMap]));
+        game_loop.handle(&mut game, Input::Pause);
+        let effects = play(
+            &mut game_loop,
+            &mut game,
+            &[Input::Choose(PauseOption::ExportMap)],
+        );
+        assert!(matches!(effects[..], [Effect::ExportMap]));
+        assert!(matches!(game.game_state, GameState::Playing));
+    }
+
+    #[test]
+    fn test_a_fight_ends_once_its_last_turn_is_shown() {
+        let mut game = scripted_game();
+        let mut game_loop = GameLoop::new();
+        let goblin = Position::new(3, 2);
+        let mut enemy = Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1);
+        enemy.health = 1;
+        game.current_level_mut().enemies.insert(goblin, enemy);
+
+        let transition = game_loop.handle(&mut game, Input::Move { dx: 1, dy: 0 });
+        assert_eq!(transition.entered, Some(GameState::Combat(vec![goblin])));
+        let round = game_loop.combat_round(&mut game).unwrap();
+        assert_eq!(
+            round.opening.as_deref(),
+            Some("Combat started with Goblin!")
+        );
+        assert!(game_loop.combat_round(&mut game).unwrap().opening.is_none());
+
+        // Swing until the goblin falls; it stays on the map until then
+        let mut turns = 0;
+        while matches!(game.game_state, GameState::Combat(_)) {
+            turns += 1;
+            assert!(turns < 50, "the goblin never fell");
+            let effects = play(
+                &mut game_loop,
+                &mut game,
+                &[Input::Combat(CombatAction::Attack)],
+            );
+            assert!(matches!(effects[0], Effect::CombatTurn { target: 0, .. }));
+            assert!(game.current_level().enemies.contains_key(&goblin));
+            game_loop.handle(&mut game, Input::CombatShown);
+        }
+        assert!(matches!(game.game_state, GameState::Playing));
+        assert!(!game.current_level().enemies.contains_key(&goblin));
+        assert_eq!(game.player_position(), Position::new(2, 2));
+    }
+}
@@ -0,0 +1,46 @@
+//! Tips given the first time something happens in a run
+//!
+//! Each hint is shown once per run, the first time the player meets what
+//! it's about, and the run remembers which have been shown so a loaded game
+//! doesn't repeat them. The options screen can turn them off altogether.
+
+use serde::{Deserialize, Serialize};
+
+/// Share of their maximum health below which the player is told how to heal
+pub const LOW_HEALTH_FRACTION: f32 = 0.3;
+
+/// Something the player is told about the first time it comes up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum HintId {
+    EnemySighted,
+    ChestSighted,
+    StairsSighted,
+    LowHealth,
+    FullInventory,
+    LevelUp,
+}
+
+impl HintId {
+    pub fn text(self) -> &'static str {
+        match self {
+            HintId::EnemySighted => {
+                "Hint: Walk into an enemy to attack it. Once you've learned a spell or volley that reaches across the map, press f to use it from afar."
+            }
+            HintId::ChestSighted => {
+                "Hint: Stand next to a chest and press g to check it for traps and open it; walking into it skips the check."
+            }
+            HintId::StairsSighted => {
+                "Hint: Walk onto stairs to take them: > leads deeper into the dungeon, < back up."
+            }
+            HintId::LowHealth => {
+                "Hint: Your health is low! Drink a health potion from your inventory (i) or hotbar (1-5)."
+            }
+            HintId::FullInventory => {
+                "Hint: You can't carry any more. Use or drop something from your inventory (i) to make room."
+            }
+            HintId::LevelUp => {
+                "Hint: Leveling up restores your health and mana and gives you stat points to spend; some levels also teach a new ability."
+            }
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! What the player can do right where they stand
+//!
+//! `Game::current_interaction` picks the one thing G would do now, and the
+//! G key does exactly that, so the prompt the front-ends show under the map
+//! can never promise something else.
+
+use crate::world::Position;
+
+/// The one thing the player can do on or beside their tile, in the order
+/// `Game::current_interaction` looks for them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Interaction {
+    /// Pick up what lies at `pos`: "Steel Dagger", "12 gold" or both
+    PickUp { pos: Position, what: String },
+    /// Look over the chest at `pos`, then open it if nothing's found
+    OpenChest(Position),
+    /// Try to disarm the trap found on the chest at `pos`
+    DisarmTrap(Position),
+    /// Smash open the chest at `pos`, trap and all, as a Warrior does
+    SmashChest(Position),
+    /// Talk with the NPC at `pos`, a "Hermit" or the like
+    Talk { pos: Position, name: String },
+    /// Take the stairs the player stands on
+    TakeStairs { down: bool },
+    /// Read the gravestone at `pos`
+    ReadGrave(Position),
+    /// Pray at the altar at `pos`, which hasn't answered yet
+    Pray(Position),
+    /// Search the unsearched corpse at `pos`
+    Search { pos: Position, name: String },
+}
+
+impl Interaction {
+    /// The prompt line: "Press G to loot the chest"
+    pub fn prompt(&self) -> String {
+        match self {
+            Interaction::PickUp { what, .. } => format!("Press G to pick up {what}"),
+            Interaction::OpenChest(_) => "Press G to loot the chest".to_string(),
+            Interaction::DisarmTrap(_) => "Press G to disarm the chest's trap".to_string(),
+            Interaction::SmashChest(_) => "Press G to smash the chest open".to_string(),
+            Interaction::Talk { name, .. } => format!("Press G to talk to the {name}"),
+            Interaction::TakeStairs { down: true } => "Press > to descend the stairs".to_string(),
+            Interaction::TakeStairs { down: false } => "Press < to climb the stairs".to_string(),
+            Interaction::ReadGrave(_) => "Press G to read the gravestone".to_string(),
+            Interaction::Pray(_) => "Press G to pray at the altar".to_string(),
+            Interaction::Search { name, .. } => format!("Press G to search the {name} corpse"),
+        }
+    }
+}
@@ -1,42 +1,163 @@
 #[cfg(not(target_arch = "wasm32"))]
-use crossterm::event::KeyCode;
-use rand::Rng;
+use crossterm::event::{KeyCode, KeyModifiers};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use serde::{Deserialize, Serialize};
-#[cfg(all(windows, not(all(feature = "gui", target_os = "windows"))))]
-use std::time::Instant;
-
-use crate::character::Player;
-#[cfg(all(
-    not(all(feature = "gui", target_os = "windows")),
-    not(target_arch = "wasm32")
-))]
-use crate::combat::process_combat_turn;
-use crate::inventory::InventoryManager;
-#[cfg(all(
-    not(all(feature = "gui", target_os = "windows")),
-    not(target_arch = "wasm32")
-))]
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::atomic::AtomicUsize;
+
+use crate::achievements::AchievementProfile;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::achievements::AchievementsFile;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::audio::AudioManager;
+use crate::audio::SoundEvent;
+use crate::bestiary::Bestiary;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::bones::{BonesFile, BonesRecord};
+use crate::bones::{BonesPool, GRAVE_CHANCE};
+use crate::character::{ClassType, Companion, Player, StatType};
+use crate::combat::area::{in_area, AREA_RADIUS};
+use crate::combat::{
+    self, process_group_combat_turn_with_rng, AreaEffect, AreaTarget, CombatAction, CombatContext,
+    CombatEnding, CombatResult, Combatant, DamageType, Loot, ENCUMBERED_MESSAGE,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::crash::{self, CrashRecovery};
+use crate::daily::DailyChallenge;
+use crate::effects::{EffectQueue, ScreenEffect};
+use crate::hall_of_fame;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::hall_of_fame::{LeaderboardFile, Placement};
+use crate::inventory::{ActionResult, InventoryManager};
+use crate::item::consumable::ConsumableType;
+use crate::item::enchant::{self, EnchantOffer};
 use crate::item::Item;
-#[cfg(all(windows, not(all(feature = "gui", target_os = "windows"))))]
-use crate::platform;
-#[cfg(all(
-    not(all(feature = "gui", target_os = "windows")),
-    not(target_arch = "wasm32")
-))]
-use crate::ui::UI;
-use crate::world::{Dungeon, Level, Position, Tile, TileType};
+use crate::log::{GameLog, LogCategory};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::map_export::MapExporter;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::menu::{MenuInput, MenuSelector};
+use crate::quest::{Quest, QuestStatus};
+use crate::render::BarLevel;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::save::{DataFile, SaveManager};
+use crate::settings::Settings;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::settings::SettingsFile;
+use crate::startup::StartupOptions;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::ui::{TitleOption, UI};
+use crate::world::chest::{self, POISON_TURNS};
+use crate::world::corpse::{SearchFind, RAISE_CHANCE};
+use crate::world::enemy::{EnemyType, KITE_DISTANCE, PANIC_TURNS};
+use crate::world::reachability::unstick_cost;
+use crate::world::special_room::{self, Prayer};
+use crate::world::weather::{self, AMBIENT_EVENT_CHANCE, TREMOR_WAKE_RADIUS, WHISPERS};
+use crate::world::{
+    Alertness, AmbientEvent, AmbientLight, Chest, ChestKind, ChestTrap, Corpse, Dialogue, Dungeon,
+    DungeonBuilder, Enemy, Level, Noise, Npc, NpcOffer, Position, ReachabilityReport, RoomKind,
+    TileType,
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+mod adaptive;
+mod difficulty;
+pub mod energy;
+pub mod game_loop;
+mod hints;
+mod interaction;
+mod movement;
+mod pause;
+pub mod replay;
+mod rng;
+mod snapshot;
+mod stats;
+mod visibility;
+
+pub use adaptive::AdaptiveDifficulty;
+pub use difficulty::Difficulty;
+#[cfg(not(target_arch = "wasm32"))]
+pub use game_loop::{Effect, Transition};
+pub use game_loop::{GameLoop, Input};
+pub use hints::HintId;
+pub use interaction::Interaction;
+pub use movement::{BlockReason, MoveOutcome};
+pub use pause::{quit_question, PauseOption};
+pub use replay::Replay;
+use replay::ReplayAction;
+#[cfg(not(target_arch = "wasm32"))]
+use replay::ReplayFile;
+#[cfg(not(target_arch = "wasm32"))]
+pub use replay::ReplayPlayer;
+pub use rng::RngStreams;
+#[cfg(target_arch = "wasm32")]
+pub use snapshot::{export_game, import_game, EventWatch, GameEvent, GameSnapshot};
+pub use stats::GameStats;
+pub use visibility::ExplorationPass;
+
+/// How far the player sees unless the settings say otherwise, in tiles
+pub const DEFAULT_VIEW_RADIUS: i32 = 10;
+
+/// How far a consumable can be thrown outside of combat, in tiles
+pub const THROW_RANGE: i32 = 5;
+
+/// Turns the enemies of a fight the player fled from stay winded
+pub const FLEE_WINDED_TURNS: u32 = 3;
+
+/// How far a companion can fall behind before it catches up at once, in
+/// tiles
+const COMPANION_LEASH: i32 = 3;
+
+/// Damage lava does to the player each turn they stand in it
+pub const LAVA_DAMAGE: i32 = 6;
+
+/// Damage poison does to the player each turn it lasts
+pub const POISON_DAMAGE: i32 = 1;
+
+fn default_view_radius() -> i32 {
+    DEFAULT_VIEW_RADIUS
+}
+
+fn new_run_id() -> u64 {
+    rand::random()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum GameState {
     MainMenu,
     Playing,
-    Combat(Position),
+    /// Fighting the enemies at these positions, the one the player bumped
+    /// into first
+    Combat(Vec<Position>),
     Inventory,
     Character,
+    /// The pause menu is open over the map
+    Paused,
+    /// Talking with the NPC standing at this position
+    Dialogue(Position),
     GameOver,
     Victory,
 }
 
+/// Something worth attaching to a bug report, for the front-end to write to
+/// the crash log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BugReport {
+    /// "Player stuck"
+    pub heading: &'static str,
+    pub lines: Vec<String>,
+}
+
+/// Where a Scroll of Return was read, for the next one to go back to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReturnPoint {
+    pub dungeon_index: usize,
+    pub level: usize,
+    pub position: Position,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Game {
     pub player: Player,
@@ -44,661 +165,6560 @@ pub struct Game {
     pub current_dungeon_index: usize,
     pub game_state: GameState,
     pub combat_started: bool,
+    /// The player opened the current fight with a sneak attack or from
+    /// range, and its first turn is still to come
+    #[serde(default)]
+    pub opening_strike: bool,
+    /// How the enemy that started the current fight by closing in on the
+    /// player opened it: "The Goblin attacks you! The Goblin hits you for
+    /// 4 damage!"
+    #[serde(default)]
+    pub ambush: Option<String>,
+    /// Turn count and other statistics for this run
+    #[serde(default)]
+    pub stats: GameStats,
+    /// Fetch quests accepted this run, one per dungeon
+    #[serde(default)]
+    pub quests: Vec<Quest>,
+    /// One life only: the game autosaves and the save is deleted on death
+    #[serde(default)]
+    pub permadeath: bool,
+    /// How many times this character has gone on to New Game+
+    #[serde(default)]
+    pub ng_plus_counter: u32,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    /// Seed the dungeons are generated from, if one was given at launch;
+    /// each New Game+ moves on to the next seed
+    #[serde(default)]
+    pub world_seed: Option<u64>,
+    /// How far the player sees, in tiles; a preference, so not saved
+    #[serde(skip, default = "default_view_radius")]
+    pub view_radius: i32,
+    /// Whether enemies move back into levels left alone for a while; a
+    /// preference, so not saved
+    #[serde(skip)]
+    pub enemy_respawn: bool,
+    /// Whether enemies spawned from now on are scaled to how the player is
+    /// getting on; a preference, so not saved
     #[serde(skip)]
-    #[cfg(all(windows, not(all(feature = "gui", target_os = "windows"))))]
-    pub last_render_time: Option<Instant>,
+    pub adaptive_difficulty: bool,
+    /// The fights adaptive difficulty has seen and the multiplier they led to
+    #[serde(default)]
+    pub adaptive: AdaptiveDifficulty,
+    /// Whether walking onto stairs waits for '>' or '<' before taking them;
+    /// a preference, so not saved
+    #[serde(skip)]
+    pub confirm_stairs: bool,
+    /// Whether weather and the like happen on their own; a preference, so
+    /// not saved
+    #[serde(skip)]
+    pub ambient_events: bool,
+    /// Whether tips are given the first time things come up; a preference,
+    /// so not saved
+    #[serde(skip)]
+    pub hints: bool,
+    /// The tips already given this run
+    #[serde(default)]
+    pub seen_hints: BTreeSet<HintId>,
+    /// Everything the game has told the player, tagged by turn
+    #[serde(default)]
+    pub log: GameLog,
+    /// How low the player's health was when last looked at, so each drop
+    /// past a threshold is warned about once
+    #[serde(default)]
+    pub health_warning: BarLevel,
+    /// The enemy types met and how many of each were slain
+    #[serde(default)]
+    pub bestiary: Bestiary,
+    /// Tells this run apart from others, so it never finds its own grave
+    #[serde(default = "new_run_id")]
+    pub run_id: u64,
+    /// What everything rolled during play comes from, saved so that a
+    /// reload rolls the same again
+    #[serde(default)]
+    pub rng: RngStreams,
+    /// Characters from earlier runs whose graves may turn up, given by the
+    /// front-end through `haunt`
+    #[serde(skip)]
+    pub bones: BonesPool,
+    /// Achievements unlocked over every run, given by the front-end through
+    /// `track_achievements`; without them nothing is unlocked
+    #[serde(skip)]
+    achievements: Option<AchievementProfile>,
+    /// Whether anything was unlocked since the front-end last saved them
+    #[serde(skip)]
+    achievements_changed: bool,
+    /// The day's challenge, if that's what this run is
+    #[serde(default)]
+    pub daily: Option<DailyChallenge>,
+    /// The run as it's being recorded, if replays are turned on
+    #[serde(default)]
+    pub replay: Option<Replay>,
+    /// Where the last Scroll of Return was read, until another takes the
+    /// player back there
+    #[serde(default)]
+    pub return_point: Option<ReturnPoint>,
+    /// Turns the player's last move took on top of the usual one, as
+    /// climbing over rubble does; the enemies get them when the turn passes
+    #[serde(skip)]
+    extra_turns: u32,
+    /// Whether marking the area around the player as explored is left to
+    /// the front-end, which takes each pass with `take_exploration`
+    #[serde(skip)]
+    pub defer_exploration: bool,
+    #[serde(skip)]
+    exploration: Option<ExplorationPass>,
+    /// Sounds for the front-end to play, drained by the front-end
+    #[serde(skip)]
+    pending_sounds: Vec<SoundEvent>,
+    /// Bug reports for the front-end to write, drained by the front-end
+    #[serde(skip)]
+    pending_bug_reports: Vec<BugReport>,
+    /// Flashes and shakes for the front-end to show, drained by the
+    /// front-end
+    #[serde(skip)]
+    pending_effects: EffectQueue,
 }
 
-impl Game {
-    pub fn new(player: Player) -> Self {
-        // Create initial dungeon
-        let first_dungeon = Dungeon::generate_random(player.level);
-
-        let mut game = Game {
-            player,
-            dungeons: vec![first_dungeon],
-            current_dungeon_index: 0,
-            game_state: GameState::MainMenu,
-            combat_started: false,
-            #[cfg(all(windows, not(all(feature = "gui", target_os = "windows"))))]
-            last_render_time: None,
-        };
+/// A new game whose dungeon is still being generated. Natively `finish`
+/// builds the levels on threads; the web build calls `step` once a frame
+/// so the page keeps drawing.
+#[derive(Debug)]
+pub struct PendingGame {
+    game: Game,
+    dungeon: DungeonBuilder,
+    rng: StdRng,
+}
 
-        // Initialize visibility for the starting level
-        game.update_visibility();
+impl PendingGame {
+    /// Levels the new dungeon will have
+    pub fn levels_total(&self) -> usize {
+        self.dungeon.levels_total()
+    }
 
-        game
+    /// Levels generated so far
+    pub fn levels_built(&self) -> usize {
+        self.dungeon.levels_built()
     }
 
-    pub fn current_dungeon(&self) -> &Dungeon {
-        &self.dungeons[self.current_dungeon_index]
+    /// The game being set up, for anything set before it starts that
+    /// doesn't touch the dungeon
+    pub fn game_mut(&mut self) -> &mut Game {
+        &mut self.game
     }
 
-    pub fn current_dungeon_mut(&mut self) -> &mut Dungeon {
-        &mut self.dungeons[self.current_dungeon_index]
+    /// Generates one more level, returning true once there are none left
+    pub fn step(&mut self) -> bool {
+        self.dungeon.step();
+        self.dungeon.is_finished()
     }
 
-    pub fn current_level(&self) -> &Level {
-        self.current_dungeon().current_level()
+    /// Generates whatever levels are left and starts the game
+    pub fn finish(self) -> Game {
+        self.finish_counting(&AtomicUsize::new(0))
     }
 
-    pub fn current_level_mut(&mut self) -> &mut Level {
-        self.current_dungeon_mut().current_level_mut()
+    /// Like `finish`, counting each level in `progress` as it's generated
+    pub fn finish_counting(self, progress: &AtomicUsize) -> Game {
+        let PendingGame {
+            mut game,
+            dungeon,
+            rng,
+        } = self;
+        game.settle_into(dungeon.build_counting(progress), rng);
+        game
     }
+}
 
-    pub fn player_position(&self) -> Position {
-        self.current_level().player_position
+impl Game {
+    pub fn new(player: Player) -> Self {
+        Self::with_options(player, &StartupOptions::default())
     }
 
-    pub fn move_player(&mut self, dx: i32, dy: i32) -> bool {
-        // Get the current player position
-        let current_pos = self.current_level().player_position;
-        let new_pos = Position::new(current_pos.x + dx, current_pos.y + dy);
+    /// A new game for `player` at the difficulty and world seed asked for
+    /// at launch
+    pub fn with_options(player: Player, options: &StartupOptions) -> Self {
+        Self::start(player, options).finish()
+    }
 
-        // Check if the position is valid (tiles only, not enemies)
-        let tile_walkable = self.current_level().is_position_valid(new_pos.x, new_pos.y)
-            && self.current_level().tiles[new_pos.y as usize][new_pos.x as usize]
-                .tile_type
-                .is_walkable();
+    /// Like `with_options`, but hands back the game before its dungeon is
+    /// generated, for a front-end that shows how generation is going
+    pub fn start(player: Player, options: &StartupOptions) -> PendingGame {
+        let game = Game {
+            player,
+            dungeons: Vec::new(),
+            current_dungeon_index: 0,
+            game_state: GameState::MainMenu,
+            combat_started: false,
+            opening_strike: false,
+            ambush: None,
+            stats: GameStats::new(),
+            quests: Vec::new(),
+            permadeath: false,
+            ng_plus_counter: 0,
+            difficulty: options.difficulty,
+            world_seed: options.seed,
+            view_radius: DEFAULT_VIEW_RADIUS,
+            enemy_respawn: false,
+            adaptive_difficulty: false,
+            adaptive: AdaptiveDifficulty::default(),
+            confirm_stairs: false,
+            ambient_events: true,
+            hints: true,
+            seen_hints: BTreeSet::new(),
+            log: GameLog::new(),
+            health_warning: BarLevel::default(),
+            bestiary: Bestiary::default(),
+            run_id: new_run_id(),
+            rng: options
+                .seed
+                .map_or_else(RngStreams::from_entropy, RngStreams::seeded),
+            bones: BonesPool::default(),
+            achievements: None,
+            achievements_changed: false,
+            daily: None,
+            replay: None,
+            return_point: None,
+            extra_turns: 0,
+            defer_exploration: false,
+            exploration: None,
+            pending_sounds: Vec::new(),
+            pending_effects: EffectQueue::default(),
+            pending_bug_reports: Vec::new(),
+        };
+        let (dungeon, rng) = game.plan_new_dungeon();
+        PendingGame { game, dungeon, rng }
+    }
 
-        if !tile_walkable {
-            return false;
+    /// Opens the pause menu; only exploring can be paused
+    pub fn pause(&mut self) -> bool {
+        if matches!(self.game_state, GameState::Playing) {
+            self.game_state = GameState::Paused;
+            true
+        } else {
+            false
         }
+    }
 
-        // Check for enemies
-        if self.current_level().enemies.contains_key(&new_pos) {
-            // Start combat - don't move the player into the enemy's position
-            self.game_state = GameState::Combat(new_pos);
-
-            // Mark that we're starting a new combat
-            self.combat_started = true;
-
-            return true;
+    /// Closes the pause menu and carries on exploring
+    pub fn resume(&mut self) {
+        if matches!(self.game_state, GameState::Paused) {
+            self.game_state = GameState::Playing;
         }
+    }
 
-        // Check for items on the ground
-        if self.current_level().items.contains_key(&new_pos) {
-            let item = self.current_level_mut().remove_item_at(&new_pos).unwrap();
-            // Try to add to inventory
-            let add_result = InventoryManager::add_item(&mut self.player, item.clone());
-            if !add_result.success {
-                // Put the item back if inventory is full
-                self.current_level_mut().items.insert(new_pos, item);
-                return false;
-            }
+    /// Takes on the player's preferences that affect play
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        let turn = self.stats.turns;
+        if let Some(recording) = &mut self.replay {
+            recording.record(turn, ReplayAction::Settings(settings.clone()));
         }
-
-        // Check for special tiles
-        if let Some(tile) = self.current_level().get_tile(new_pos.x, new_pos.y) {
-            match tile.tile_type {
-                TileType::StairsDown => {
-                    if self.current_dungeon_mut().go_to_next_level().is_err() {
-                        // Can't go further down
-                        return false;
-                    }
-                    // Move player to the starting position of the new level
-                    let new_level_start = self.current_level().player_position;
-                    self.current_level_mut().player_position = new_level_start;
-                    return true;
-                }
-                TileType::StairsUp => {
-                    if self.current_dungeon_mut().go_to_previous_level().is_err() {
-                        // Can't go further up
-                        return false;
-                    }
-                    // Move player to the starting position of the previous level
-                    let new_level_start = self.current_level().player_position;
-                    self.current_level_mut().player_position = new_level_start;
-                    return true;
-                }
-                TileType::Exit => {
-                    if self.current_dungeon().is_final_level() {
-                        // Victory condition - player reached the exit of the final level
-                        self.game_state = GameState::Victory;
-                    }
-                    // Allow player to move to the exit position
-                    self.current_level_mut().player_position = new_pos;
-                    return true;
-                }
-                TileType::Chest => {
-                    // Generate loot from chest
-                    if let Some(item) = self.current_level().get_item_at(&new_pos) {
-                        let item_clone = item.clone();
-                        #[cfg_attr(not(debug_assertions), allow(unused_variables))]
-                        let item_name = item_clone.name().to_string();
-                        let add_result = InventoryManager::add_item(&mut self.player, item_clone);
-                        if !add_result.success {
-                            // Inventory full, can't loot the chest
-                            return false;
-                        }
-                        // Remove the item and replace the chest with a floor tile
-                        self.current_level_mut().remove_item_at(&new_pos);
-                        if let Some(tile) =
-                            self.current_level_mut().get_tile_mut(new_pos.x, new_pos.y)
-                        {
-                            *tile = Tile::floor();
-                        }
-
-                        // This is auto-looting by walking into a chest
-                        // We don't directly add a message here because the move_player method
-                        // doesn't return messages, but we'll add a hook for it
-                        #[cfg(debug_assertions)]
-                        println!("DEBUG: Auto-looted chest at {new_pos:?}, found {item_name}");
-                    }
-                    return true;
+        self.view_radius = settings.view_radius as i32;
+        self.enemy_respawn = settings.enemy_respawn;
+        self.adaptive_difficulty = settings.adaptive_difficulty;
+        self.confirm_stairs = settings.confirm_stairs;
+        self.ambient_events = settings.ambient_events;
+        self.hints = settings.hints;
+        if !self.ambient_events {
+            // Turned off, whatever was going on stops at once
+            for dungeon in &mut self.dungeons {
+                for level in &mut dungeon.levels {
+                    level.ambient_effects.clear();
                 }
-                _ => {}
             }
         }
+        self.log.set_capacity(settings.message_log_size as usize);
+    }
 
-        // Move the player
-        self.current_level_mut().player_position = new_pos;
-        true
+    /// Difficulty of newly generated dungeons: the player's level, raised by
+    /// half of it again for every New Game+, then scaled by the run's
+    /// difficulty setting
+    pub fn dungeon_difficulty(&self) -> u32 {
+        let level = self.player.level.max(1);
+        self.difficulty
+            .scale(level + self.ng_plus_counter * (level / 2).max(1))
     }
 
-    pub fn process_turn(&mut self) {
-        // Update game state, process enemy movements, etc.
-        if let GameState::Playing = self.game_state {
-            // Process enemy turns
-            // This is a simple implementation - more complex AI would be better
-            let mut rng = rand::thread_rng();
+    /// Starts New Game+ after a victory. The character keeps their level,
+    /// stats, gold and equipment but leaves any quest items behind, and
+    /// starts over in a freshly generated, harder dungeon.
+    pub fn start_new_game_plus(&mut self) {
+        while let Some(index) = self
+            .player
+            .inventory
+            .items
+            .iter()
+            .position(|item| matches!(item, Item::Quest { .. }))
+        {
+            InventoryManager::remove_item(&mut self.player, index);
+        }
 
-            // Clone enemy positions to avoid borrowing issues
-            let enemy_positions: Vec<Position> =
-                self.current_level().enemies.keys().copied().collect();
+        self.ng_plus_counter += 1;
+        self.log.push(
+            LogCategory::System,
+            format!(
+                "New Game+ {}: the dungeons grow darker.",
+                self.ng_plus_counter
+            ),
+        );
+        self.enter_new_dungeon();
+        self.combat_started = false;
+        self.game_state = GameState::Playing;
+    }
 
-            for pos in enemy_positions {
-                // 50% chance enemy moves randomly
-                if rng.gen_bool(0.5) {
-                    let dx = rng.gen_range(-1..=1);
-                    let dy = rng.gen_range(-1..=1);
+    /// Replaces the dungeons with a fresh one at the current difficulty,
+    /// along with its fetch quest
+    fn enter_new_dungeon(&mut self) {
+        let (dungeon, rng) = self.plan_new_dungeon();
+        self.settle_into(dungeon.build(), rng);
+    }
 
-                    let new_pos = Position::new(pos.x + dx, pos.y + dy);
+    /// The next dungeon at the current difficulty, ready to generate, and
+    /// the RNG that goes on to give it its quest and graves
+    fn plan_new_dungeon(&self) -> (DungeonBuilder, StdRng) {
+        let mut rng = match self.world_seed {
+            Some(seed) => StdRng::seed_from_u64(seed.wrapping_add(self.ng_plus_counter.into())),
+            None => StdRng::from_entropy(),
+        };
+        let dungeon = DungeonBuilder::random(self.dungeon_difficulty(), &mut rng);
+        (dungeon, rng)
+    }
 
-                    // Only move if position is valid and not occupied
-                    if self.current_level().is_tile_walkable(new_pos)
-                        && !self.current_level().enemies.contains_key(&new_pos)
-                        && new_pos != self.player_position()
-                    {
-                        if let Some(enemy) = self.current_level_mut().remove_enemy_at(&pos) {
-                            self.current_level_mut().enemies.insert(new_pos, enemy);
-                        }
-                    }
+    /// Replaces the dungeons with `dungeon`, freshly generated, and gives
+    /// it its fetch quest
+    fn settle_into(&mut self, dungeon: Dungeon, mut rng: StdRng) {
+        self.dungeons = vec![dungeon];
+        self.current_dungeon_index = 0;
+        if self.adaptive_difficulty {
+            self.adaptive.level_reached();
+            let factor = self.adaptive.multiplier;
+            for level in &mut self.dungeons[0].levels {
+                for enemy in level.enemies.values_mut() {
+                    enemy.scale_strength(factor);
                 }
             }
         }
-    }
-
-    pub fn update_visibility(&mut self) {
-        // Get the current level and player position
-        let level = self.current_level_mut();
-        let player_pos = level.player_position;
+        self.return_point = None;
+        self.quests.clear();
 
-        // Set all tiles to not visible
-        for row in &mut level.visible_tiles {
-            for tile in row {
-                *tile = false;
-            }
+        // Every dungeon comes with a fetch quest
+        if let Some(quest) = Quest::generate_for_dungeon(&mut self.dungeons[0], 0, &mut rng) {
+            self.log
+                .push(LogCategory::System, format!("New quest: {}", quest.title));
+            self.quests.push(quest);
+        }
+        self.place_graves(&mut rng);
+        for warning in std::mem::take(&mut self.dungeons[0].generation_warnings) {
+            self.log.push(LogCategory::System, warning.clone());
+            self.file_bug_report("Level regenerated", vec![warning]);
         }
 
-        // Reveal a circular area around the player
-        let view_radius = 10; // Increased view radius to match UI display
+        // Initialize visibility for the starting level
+        self.update_visibility();
+        self.place_companion();
+        self.warn_if_dark();
+    }
 
-        for dy in -view_radius..=view_radius {
-            for dx in -view_radius..=view_radius {
-                let x = player_pos.x + dx;
-                let y = player_pos.y + dy;
+    /// Gives the run the bones of characters who died in earlier runs.
+    /// Levels of the current dungeon the player hasn't explored yet may get
+    /// a grave now, unless the dungeon has graves already, and so may every
+    /// dungeon generated later.
+    pub fn haunt(&mut self, bones: BonesPool) {
+        self.bones = bones;
+        let haunted = self
+            .current_dungeon()
+            .levels
+            .iter()
+            .any(|level| !level.graves.is_empty());
+        if !haunted {
+            self.with_world_rng(|game, rng| game.place_graves(rng));
+        }
+    }
 
-                // Check if within bounds
-                if x >= 0 && x < level.width as i32 && y >= 0 && y < level.height as i32 {
-                    // Check if within view radius (circular area)
-                    if dx * dx + dy * dy <= view_radius * view_radius {
-                        level.visible_tiles[y as usize][x as usize] = true;
-                        level.revealed_tiles[y as usize][x as usize] = true;
+    /// Maybe digs a grave on each unexplored level of the current dungeon
+    /// for a character who died about as deep, burying each at most once
+    fn place_graves<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        if self.bones.records().is_empty() {
+            return;
+        }
+        // Who has been buried in this dungeon, by run and name
+        let mut buried: Vec<(u64, String)> = Vec::new();
+        for index in 0..self.current_dungeon().levels.len() {
+            let explored = self.current_dungeon().levels[index]
+                .tiles
+                .iter()
+                .flatten()
+                .any(|tile| tile.explored);
+            if explored || !rng.gen_bool(GRAVE_CHANCE) {
+                continue;
+            }
+            let Some(record) = self
+                .bones
+                .choose_for(index as u32 + 1, self.run_id, rng)
+                .filter(|record| !buried.contains(&(record.run_id, record.name.clone())))
+                .cloned()
+            else {
+                continue;
+            };
 
-                        // Update tile to be explored
-                        if let Some(tile) = level.get_tile_mut(x, y) {
-                            tile.explored = true;
-                            tile.visible = true;
-                        }
-                    }
-                }
+            // The ghost guards one of the things they left behind
+            let items = record.items.choose(rng).cloned().into_iter().collect();
+            let level = &mut self.current_dungeon_mut().levels[index];
+            if level
+                .place_grave(record.epitaph(), record.ghost(), items, rng)
+                .is_some()
+            {
+                buried.push((record.run_id, record.name));
             }
         }
+    }
+
+    /// Checks the run against the achievements in `profile` from now on
+    pub fn track_achievements(&mut self, profile: AchievementProfile) {
+        self.achievements = Some(profile);
+        self.check_achievements();
+    }
 
-        // Add more tile visibility for the screen around the player
-        // This ensures all tiles shown on screen are visible, even beyond the circular radius
-        let screen_width = 30; // Half the screen width
-        let screen_height = 10; // Half the screen height
+    /// Unlocks every achievement the run has earned that wasn't yet,
+    /// announcing each one. Runs after every turn; the front-ends also call
+    /// it when the run ends.
+    pub fn check_achievements(&mut self) {
+        let Some(mut profile) = self.achievements.take() else {
+            return;
+        };
+        for achievement in profile.unlock_earned(self, &hall_of_fame::today()) {
+            self.log.push(
+                LogCategory::Achievement,
+                format!(
+                    "Achievement unlocked: {} - {}",
+                    achievement.name, achievement.description
+                ),
+            );
+            self.achievements_changed = true;
+        }
+        self.achievements = Some(profile);
+    }
 
-        for dy in -screen_height..=screen_height {
-            for dx in -screen_width..=screen_width {
-                let x = player_pos.x + dx;
-                let y = player_pos.y + dy;
+    /// Gives the tip about `hint` unless it was given already this run or
+    /// tips are turned off. Returns whether it was given.
+    pub fn give_hint(&mut self, hint: HintId) -> bool {
+        if !self.hints || !self.seen_hints.insert(hint) {
+            return false;
+        }
+        self.log.push(LogCategory::Hint, hint.text());
+        true
+    }
 
-                // Check if within bounds and not already visible
-                if x >= 0 && x < level.width as i32 && y >= 0 && y < level.height as i32 {
-                    level.revealed_tiles[y as usize][x as usize] = true;
+    /// Lets every tip be given again
+    pub fn reset_hints(&mut self) {
+        self.seen_hints.clear();
+        self.log
+            .push(LogCategory::System, "Tutorial hints will be shown again.");
+    }
 
-                    // Only mark as explored, not necessarily visible (for fog of war effect)
-                    if let Some(tile) = level.get_tile_mut(x, y) {
-                        tile.explored = true;
-                    }
+    /// Tips about what the player can see: enemies, chests and stairs
+    fn hint_at_surroundings(&mut self) {
+        let level = self.current_level();
+        let in_sight = |pos: &Position| {
+            level
+                .visible_tiles
+                .get(pos.y as usize)
+                .and_then(|row| row.get(pos.x as usize))
+                .copied()
+                .unwrap_or(false)
+        };
+        let enemy = level.enemies.keys().any(in_sight);
+        let mut chest = false;
+        let mut stairs = false;
+        for (y, row) in level.tiles.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                if !level.visible_tiles[y][x] {
+                    continue;
+                }
+                match tile.tile_type {
+                    TileType::Chest => chest = true,
+                    TileType::StairsDown | TileType::StairsUp => stairs = true,
+                    _ => {}
                 }
             }
         }
+        for (seen, hint) in [
+            (enemy, HintId::EnemySighted),
+            (chest, HintId::ChestSighted),
+            (stairs, HintId::StairsSighted),
+        ] {
+            if seen {
+                self.give_hint(hint);
+            }
+        }
     }
 
-    /// Attempts to pick up an item at the player's position or loot a chest in an adjacent tile.
-    /// Returns a message describing the result of the action.
-    pub fn try_get_item(&mut self) -> Option<String> {
-        let player_pos = self.current_level().player_position;
+    /// Tips about the player's health and level
+    fn hint_at_condition(&mut self) {
+        let max_health = self.player.max_health.max(1) as f32;
+        if self.player.is_alive()
+            && (self.player.health as f32) < max_health * hints::LOW_HEALTH_FRACTION
+        {
+            self.give_hint(HintId::LowHealth);
+        }
+        if self.player.unspent_stat_points > 0 {
+            self.give_hint(HintId::LevelUp);
+        }
+    }
 
-        // First check if there's an item at the current position
-        if let Some(item) = self.current_level().get_item_at(&player_pos) {
-            let item_clone = item.clone();
-            let add_result = InventoryManager::add_item(&mut self.player, item_clone);
-            if add_result.success {
-                self.current_level_mut().remove_item_at(&player_pos);
-                return Some("You picked up an item.".to_string());
-            }
-            return Some(add_result.message);
+    /// The unlocks, if anything was unlocked since the last call, for the
+    /// front-end to save
+    pub fn take_unlocked_achievements(&mut self) -> Option<&AchievementProfile> {
+        if std::mem::take(&mut self.achievements_changed) {
+            self.achievements.as_ref()
+        } else {
+            None
         }
+    }
 
-        // Check adjacent positions for chests or items
-        let directions = [(0, -1), (0, 1), (-1, 0), (1, 0)]; // up, down, left, right
+    /// Returns and clears the sounds queued since the last call
+    pub fn take_sounds(&mut self) -> Vec<SoundEvent> {
+        std::mem::take(&mut self.pending_sounds)
+    }
 
-        for (dx, dy) in &directions {
-            let adj_pos = Position::new(player_pos.x + dx, player_pos.y + dy);
+    /// Returns and clears the screen effects queued since the last call
+    pub fn take_effects(&mut self) -> Vec<ScreenEffect> {
+        self.pending_effects.take()
+    }
 
-            // Check if position is valid
-            if !self.current_level().is_position_valid(adj_pos.x, adj_pos.y) {
-                continue;
-            }
+    /// Returns and clears the bug reports filed since the last call
+    pub fn take_bug_reports(&mut self) -> Vec<BugReport> {
+        std::mem::take(&mut self.pending_bug_reports)
+    }
 
-            // Check if there's a chest at this position
-            if let Some(tile) = self.current_level().get_tile(adj_pos.x, adj_pos.y) {
-                if tile.tile_type == TileType::Chest {
-                    // Try to loot the chest
-                    if let Some(item) = self.current_level().get_item_at(&adj_pos) {
-                        let item_clone = item.clone();
-                        // Get the item name before potentially moving item_clone
-                        let item_name = item_clone.name().to_string();
-                        // Also save the name for potential error message
-                        let item_name_for_err = item_clone.name().to_string();
-                        let add_result = InventoryManager::add_item(&mut self.player, item_clone);
-                        if add_result.success {
-                            // Item name is already saved
-                            self.current_level_mut().remove_item_at(&adj_pos);
-                            // Replace chest with floor
-                            if let Some(tile_mut) =
-                                self.current_level_mut().get_tile_mut(adj_pos.x, adj_pos.y)
-                            {
-                                *tile_mut = Tile::floor();
-                            }
-                            return Some(format!("You looted the chest and found {item_name}!"));
-                        }
-                        return Some(format!(
-                            "Chest contains {}, but {}.",
-                            item_name_for_err,
-                            add_result.message.to_lowercase()
-                        ));
-                    }
-                    // This could indicate an issue with chest item generation
-                    // Add more detailed debug information
-                    #[cfg(debug_assertions)]
-                    println!("DEBUG: Found empty chest at position {adj_pos:?}");
-
-                    // Replace chest with floor since it's empty
-                    if let Some(tile_mut) =
-                        self.current_level_mut().get_tile_mut(adj_pos.x, adj_pos.y)
-                    {
-                        *tile_mut = Tile::floor();
-                    }
+    /// Files a bug report about the current level, naming where it is and
+    /// the seed it was generated from
+    fn file_bug_report(&mut self, heading: &'static str, details: Vec<String>) {
+        let seed = match self.world_seed {
+            Some(seed) => format!("World seed: {seed} (New Game+ {})", self.ng_plus_counter),
+            None => "World seed: none, the world was random".to_string(),
+        };
+        let mut lines = vec![
+            format!(
+                "{} - Level {}",
+                self.current_dungeon().name,
+                self.current_dungeon().current_level + 1
+            ),
+            seed,
+        ];
+        lines.extend(details);
+        self.pending_bug_reports.push(BugReport { heading, lines });
+    }
 
-                    return Some("The chest is empty.".to_string());
-                }
-            }
+    /// Updates the run statistics and queues the sounds for a combat turn
+    /// against `enemies`; call after the player's changes are applied
+    pub fn record_combat_turn(&mut self, result: &CombatResult, enemies: &[Enemy]) {
+        self.stats.record_combat(
+            result,
+            result
+                .slain
+                .iter()
+                .filter_map(|&i| enemies.get(i))
+                .map(|e| e.kind),
+        );
+        if result.killed_by.is_some() {
+            self.stats.killed_by = result.killed_by.clone();
+        }
+        self.bestiary.record_turn(result, enemies);
 
-            // Check if there's an item at this adjacent position
-            if let Some(item) = self.current_level().get_item_at(&adj_pos) {
-                let item_clone = item.clone();
-                let add_result = InventoryManager::add_item(&mut self.player, item_clone);
-                if add_result.success {
-                    self.current_level_mut().remove_item_at(&adj_pos);
-                    return Some("You picked up an item.".to_string());
-                }
-                return Some(add_result.message);
-            }
+        if result.player_damage_dealt > 0 {
+            self.pending_sounds.push(SoundEvent::Hit);
+        }
+        if result.enemy_damage_dealt > 0 {
+            self.pending_sounds.push(SoundEvent::Hurt);
+        }
+        if result.player_level_up {
+            self.pending_sounds.push(SoundEvent::LevelUp);
+        }
+        self.pending_effects.record_combat(result);
+        if !self.player.is_alive() {
+            self.pending_sounds.push(SoundEvent::Death);
         }
+    }
+
+    /// Enemies drawn into a fight started by bumping into `bumped`: that one
+    /// first, then any others next to the player or to it
+    fn combat_participants(&self, bumped: Position) -> Vec<Position> {
+        let player = self.player_position();
+        let adjacent = |a: Position, b: Position| (a.x - b.x).abs() <= 1 && (a.y - b.y).abs() <= 1;
 
-        Some("There's nothing here to pick up.".to_string())
+        let mut others: Vec<Position> = self
+            .current_level()
+            .enemies
+            .iter()
+            .filter(|(&pos, enemy)| {
+                pos != bumped
+                    && enemy.is_alive()
+                    && (adjacent(pos, player) || adjacent(pos, bumped))
+            })
+            .map(|(&pos, _)| pos)
+            .collect();
+        others.sort_by_key(|pos| (pos.y, pos.x));
+
+        let mut participants = vec![bumped];
+        participants.extend(others);
+        participants
     }
-}
 
-#[cfg(all(
-    not(all(feature = "gui", target_os = "windows")),
-    not(target_arch = "wasm32")
-))]
-pub fn run() {
-    // Initialize UI
-    let mut ui = UI::new();
-    if let Err(e) = ui.initialize() {
-        eprintln!("Error initializing UI: {e}");
-        return;
+    /// The enemies in the current fight, in the order of `GameState::Combat`.
+    /// Enemies slain this fight stay in the list until it ends.
+    pub fn combat_enemies(&self) -> Vec<Enemy> {
+        let GameState::Combat(positions) = &self.game_state else {
+            return Vec::new();
+        };
+        positions
+            .iter()
+            .filter_map(|pos| self.current_level().get_enemy_at(pos).cloned())
+            .collect()
     }
 
-    // Show title screen
-    if let Err(e) = ui.draw_title_screen() {
-        eprintln!("Error drawing title screen: {e}");
-        return;
+    /// Where the enemies still on the map in the current fight stand
+    fn combat_positions(&self) -> Vec<Position> {
+        let GameState::Combat(positions) = &self.game_state else {
+            return Vec::new();
+        };
+        positions
+            .iter()
+            .copied()
+            .filter(|pos| self.current_level().enemies.contains_key(pos))
+            .collect()
     }
 
-    // Main menu loop
-    loop {
-        match ui.wait_for_key() {
-            Ok(key_event) => match key_event.code {
-                KeyCode::Char('1') => {
-                    // Start new game
-                    break;
-                }
-                KeyCode::Char('2') => {
-                    // Exit
-                    if let Err(e) = ui.cleanup() {
-                        eprintln!("Error cleaning up UI: {e}");
-                    }
-                    return;
-                }
-                _ => {}
-            },
-            Err(e) => {
-                eprintln!("Error reading key: {e}");
-                if let Err(e) = ui.cleanup() {
-                    eprintln!("Error cleaning up UI: {e}");
-                }
-                return;
-            }
+    /// Whether the player has nowhere to flee to from the current fight
+    pub fn is_cornered(&self) -> bool {
+        matches!(self.game_state, GameState::Combat(_))
+            && self.retreat_position(&self.combat_positions()).is_none()
+    }
+
+    /// Announces a new fight, naming everyone in it, or telling how the
+    /// enemy that started it struck first
+    pub fn combat_start_message(&self) -> String {
+        if let Some(ambush) = &self.ambush {
+            return ambush.clone();
         }
+        let names: Vec<String> = self
+            .combat_enemies()
+            .into_iter()
+            .map(|enemy| enemy.described_name())
+            .collect();
+        let foes = match names.split_last() {
+            None => "Unknown Enemy".to_string(),
+            Some((last, [])) => last.clone(),
+            Some((last, rest)) => format!("{} and {last}", rest.join(", ")),
+        };
+        format!("Combat started with {foes}!")
     }
 
-    // Character creation
-    let player = match ui.character_creation() {
-        Ok(p) => p,
-        Err(e) => {
-            eprintln!("Error during character creation: {e}");
-            if let Err(e) = ui.cleanup() {
-                eprintln!("Error cleaning up UI: {e}");
-            }
-            return;
+    /// How where the player stands changes the blows of the current fight's
+    /// next turn, and whether they can flee
+    pub fn combat_context(&self) -> CombatContext {
+        CombatContext {
+            cornered: self.is_cornered(),
+            ..CombatContext::from_level(self.current_level(), self.opening_strike)
         }
-    };
+    }
 
-    // Create new game
-    let mut game = Game::new(player);
+    /// Plays one combat turn with the player's action aimed at the enemy at
+    /// `target` in `combat_enemies()`, and records its statistics and sounds
+    pub fn resolve_combat_turn(&mut self, action: CombatAction, target: usize) -> CombatResult {
+        if !matches!(self.game_state, GameState::Combat(_)) {
+            return CombatResult::new();
+        }
+        self.current_level_mut().rally_packs();
+        let positions = self.combat_positions();
+        let mut enemies = self.combat_enemies();
+        let context = self.combat_context();
+        self.opening_strike = false;
 
-    // Show combat tutorial
-    if let Err(e) = ui.show_combat_tutorial() {
-        eprintln!("Error showing combat tutorial: {e}");
-        if let Err(e) = ui.cleanup() {
-            eprintln!("Error cleaning up UI: {e}");
+        let mut result = process_group_combat_turn_with_rng(
+            &mut self.player,
+            &mut enemies,
+            target,
+            action,
+            &context,
+            &mut self.rng.combat,
+            &mut self.rng.world,
+        );
+        if self.adaptive_difficulty {
+            self.adaptive.record_turn(
+                result.enemy_damage_dealt,
+                self.player.health,
+                self.player.max_health,
+                result.potion_drunk,
+            );
         }
-        return;
-    }
 
-    game.game_state = GameState::Playing;
+        // No one in a fight stays unaware of the player, and the din carries
+        let player = self.player_position();
+        for (pos, enemy) in positions.iter().zip(&enemies) {
+            if let Some(enemy_ref) = self.current_level_mut().get_enemy_at_mut(pos) {
+                *enemy_ref = enemy.clone();
+                enemy_ref.alert(player);
+            }
+        }
+        self.make_noise(Noise::Fight);
+        self.record_combat_turn(&result, &enemies);
+        self.leave_loot(&positions, &result.loot);
+        self.leave_corpses(&positions, &enemies, &result.slain);
+        let routs = self.rout_packs(&positions, &enemies, &result.slain);
+        result.messages.extend(routs);
+        if !self.companion_is_up() {
+            self.current_level_mut().companion_position = None;
+        }
+        for message in &result.messages {
+            self.log.push(LogCategory::Combat, message.clone());
+        }
+        self.hint_at_condition();
+        result
+    }
 
-    // Game loop
-    while !matches!(game.game_state, GameState::GameOver | GameState::Victory) {
-        // Windows-specific frame rate limiting for better performance
-        #[cfg(all(windows, not(all(feature = "gui", target_os = "windows"))))]
-        {
-            if platform::is_command_prompt() {
-                platform::cmd_frame_limit();
-            } else {
-                platform::windows_frame_limit();
+    /// Leaves combat once its last messages have been shown, clearing away
+    /// every enemy that fell in the fight. A player who fled steps back out
+    /// of reach.
+    pub fn end_combat(&mut self, ending: CombatEnding) {
+        if let GameState::Combat(positions) = self.game_state.clone() {
+            let level = self.current_level_mut();
+            for pos in &positions {
+                if level
+                    .get_enemy_at(pos)
+                    .is_some_and(|enemy| !enemy.is_alive())
+                {
+                    level.remove_enemy_at(pos);
+                }
+            }
+            if ending == CombatEnding::Fled {
+                self.escape_from(&positions);
             }
         }
 
-        // Update visibility
-        game.update_visibility();
+        match ending {
+            CombatEnding::Victory => self.log.push(LogCategory::Combat, "You were victorious!"),
+            CombatEnding::Fled => self.log.push(LogCategory::Combat, "You fled from combat!"),
+            CombatEnding::Defeat => {}
+        }
+        self.game_state = match ending {
+            CombatEnding::Victory | CombatEnding::Fled => GameState::Playing,
+            CombatEnding::Defeat => GameState::GameOver,
+        };
+        if self.adaptive_difficulty {
+            self.adaptive.finish_fight(ending == CombatEnding::Fled);
+        }
+        self.combat_started = false;
+        self.opening_strike = false;
+        self.ambush = None;
+        self.player.reset_cooldowns();
+        self.player.clear_buffs();
+    }
 
-        // Windows-specific screen update optimization
-        #[cfg(windows)]
-        let should_redraw = {
-            let now = std::time::Instant::now();
-            let should_draw = game.last_render_time.map_or(true, |last| {
-                now.duration_since(last).as_millis() > 16 // ~60 FPS max
-            });
-            if should_draw {
-                game.last_render_time = Some(now);
+    /// Moves the player to their retreat from the enemies at `foes` and
+    /// leaves those enemies winded, so the next step doesn't walk straight
+    /// back into the fight
+    fn escape_from(&mut self, foes: &[Position]) {
+        if let Some(retreat) = self.retreat_position(foes) {
+            self.current_level_mut().player_position = retreat;
+            self.update_visibility();
+        }
+        for pos in foes {
+            if let Some(enemy) = self.current_level_mut().get_enemy_at_mut(pos) {
+                enemy.wind(FLEE_WINDED_TURNS);
             }
-            should_draw
+        }
+    }
+
+    /// Where the player can flee to from the enemies at `foes`: the nearest
+    /// open tile one or two steps away, not passing any enemy, that puts
+    /// more distance between them and the closest foe. None when cornered.
+    fn retreat_position(&self, foes: &[Position]) -> Option<Position> {
+        let level = self.current_level();
+        let player = self.player_position();
+        let distance = |pos: Position| {
+            foes.iter()
+                .map(|foe| (foe.x - pos.x).abs().max((foe.y - pos.y).abs()))
+                .min()
+                .unwrap_or(i32::MAX)
+        };
+        let open = |pos: Position| {
+            level.get_tile(pos.x, pos.y).is_some_and(|tile| {
+                matches!(
+                    tile.tile_type,
+                    TileType::Floor | TileType::Door | TileType::ShallowWater | TileType::Rubble
+                )
+            }) && !level.enemies.contains_key(&pos)
+                && !level.npcs.contains_key(&pos)
+        };
+        let neighbours = |pos: Position| {
+            (-1..=1)
+                .flat_map(move |dy| (-1..=1).map(move |dx| Position::new(pos.x + dx, pos.y + dy)))
+                .filter(move |&next| next != pos)
         };
 
-        #[cfg(not(windows))]
-        let should_redraw = true;
+        let one_step: Vec<Position> = neighbours(player).filter(|&pos| open(pos)).collect();
+        let two_steps: Vec<Position> = one_step
+            .iter()
+            .flat_map(|&pos| neighbours(pos))
+            .filter(|&pos| pos != player && open(pos))
+            .collect();
 
-        // Draw game screen only when needed
-        if should_redraw {
-            if let Err(e) =
-                ui.draw_game_screen(&game.player, game.current_level(), game.current_dungeon())
-            {
-                eprintln!("Error drawing game screen: {e}");
-                break;
+        // The fewest steps that get away, then the farthest from the foes;
+        // ties go to the top-left tile so the choice is always the same
+        let current = distance(player);
+        [one_step, two_steps].into_iter().find_map(|steps| {
+            steps
+                .into_iter()
+                .filter(|&pos| distance(pos) > current)
+                .max_by_key(|&pos| (distance(pos), -pos.y, -pos.x))
+        })
+    }
+
+    /// Updates stats and quest progress after an item lands in the player's
+    /// inventory
+    fn on_item_acquired(&mut self, item: &Item) {
+        self.stats.items_looted += 1;
+
+        for quest in &mut self.quests {
+            if quest.status == QuestStatus::Active && quest.matches_item(item) {
+                quest.status = QuestStatus::ItemFound;
+                self.log.push(
+                    LogCategory::Loot,
+                    format!(
+                        "You recovered the {}! Bring it to the dungeon exit.",
+                        quest.item_name
+                    ),
+                );
             }
         }
+    }
 
-        // Handle input based on game state
-        match game.game_state {
-            GameState::Playing => match ui.wait_for_key() {
-                Ok(key_event) => match key_event.code {
-                    KeyCode::Up => {
-                        if game.move_player(0, -1) {
-                            match game.game_state {
-                                GameState::Combat(_) => {
-                                    // Combat will be handled in the next loop iteration
-                                }
-                                _ => game.process_turn(),
-                            }
-                        }
-                    }
-                    KeyCode::Down => {
-                        if game.move_player(0, 1) {
-                            match game.game_state {
-                                GameState::Combat(_) => {
-                                    // Combat will be handled in the next loop iteration
-                                }
-                                _ => game.process_turn(),
-                            }
-                        }
-                    }
-                    KeyCode::Left => {
-                        if game.move_player(-1, 0) {
-                            match game.game_state {
-                                GameState::Combat(_) => {
-                                    // Combat will be handled in the next loop iteration
-                                }
-                                _ => game.process_turn(),
-                            }
-                        }
-                    }
-                    KeyCode::Right => {
-                        if game.move_player(1, 0) {
-                            match game.game_state {
-                                GameState::Combat(_) => {
-                                    // Combat will be handled in the next loop iteration
-                                }
-                                _ => game.process_turn(),
-                            }
-                        }
-                    }
-                    KeyCode::Char('i') => {
-                        game.game_state = GameState::Inventory;
-                    }
-                    KeyCode::Char('c') => {
-                        game.game_state = GameState::Character;
-                    }
-                    KeyCode::Char('g') => {
-                        // Try to get item at current position or adjacent chest
-                        if let Some(result) = game.try_get_item() {
-                            ui.add_message(result);
-                        }
-                    }
-                    KeyCode::Char('q') => {
-                        break;
-                    }
-                    _ => {}
-                },
-                Err(e) => {
-                    eprintln!("Error reading key: {e}");
-                    break;
+    /// Turns in every quest for the current dungeon whose item the player
+    /// carries, granting the rewards
+    fn turn_in_quests(&mut self) {
+        for quest in &mut self.quests {
+            if quest.dungeon_index != self.current_dungeon_index
+                || quest.status != QuestStatus::ItemFound
+            {
+                continue;
+            }
+
+            let Some(index) = self
+                .player
+                .inventory
+                .items
+                .iter()
+                .position(|item| quest.matches_item(item))
+            else {
+                continue;
+            };
+
+            InventoryManager::remove_item(&mut self.player, index);
+            quest.status = QuestStatus::Completed;
+            self.player.gold += quest.reward_gold;
+            self.stats.gold_earned += quest.reward_gold;
+            let previous_level = self.player.level;
+            let leveled_up = self
+                .player
+                .gain_experience(quest.reward_experience, &mut self.rng.world);
+
+            self.log.push(
+                LogCategory::System,
+                format!(
+                    "Quest complete: {}! You earn {} XP and {} gold.",
+                    quest.title, quest.reward_experience, quest.reward_gold
+                ),
+            );
+            if leveled_up {
+                self.pending_effects.push(ScreenEffect::LevelUp);
+                self.log.push(
+                    LogCategory::System,
+                    format!("You leveled up to level {}!", self.player.level),
+                );
+                for ability in self.player.abilities_unlocked_since(previous_level) {
+                    self.log
+                        .push(LogCategory::System, format!("You learned {ability}!"));
                 }
-            },
-            GameState::Combat(enemy_pos) => {
-                // Make sure the enemy still exists at this position
-                if game.current_level().enemies.contains_key(&enemy_pos) {
-                    // Check if we need to clear messages for a new combat
-                    if game.combat_started {
-                        // Clone the enemy name before clearing the UI to avoid borrowing issues
-                        let enemy_name = game
-                            .current_level()
-                            .get_enemy_at(&enemy_pos)
-                            .unwrap()
-                            .name
-                            .clone();
-                        ui.clear_messages();
-                        ui.add_message(format!("Combat started with {enemy_name}!"));
-                        game.combat_started = false;
-                    }
+            }
+        }
+    }
 
-                    // Get the enemy reference after clearing messages
-                    let enemy = game.current_level().get_enemy_at(&enemy_pos).unwrap();
+    /// Uses or equips an inventory item, counting any potion drunk.
+    /// Throwables are thrown at the nearest enemy in sight.
+    pub fn use_item(&mut self, index: usize) -> ActionResult {
+        if combat::is_thrown_item(&self.player, index) {
+            return self.throw_item(index);
+        }
+        if combat::is_taming_item(&self.player, index) {
+            return self.tame_adjacent_beast(index);
+        }
+        if combat::is_return_scroll(&self.player, index) {
+            return self.read_return_scroll(index);
+        }
+        let potion = combat::is_potion(&self.player, index);
+        let result = InventoryManager::use_item(&mut self.player, index);
+        if potion && result.consumed {
+            self.stats.potions_drunk += 1;
+        }
+        result
+    }
 
-                    // Draw the combat screen
-                    if let Err(e) = ui.draw_combat_screen(&game.player, enemy) {
-                        eprintln!("Error drawing combat screen: {e}");
-                        break;
-                    }
+    /// Uses the consumable on 0-based hotbar `slot`, emptying the slot once
+    /// the last one is gone
+    pub fn use_hotbar(&mut self, slot: usize) -> ActionResult {
+        let Some(name) = self.player.inventory.hotbar.get(slot).map(str::to_string) else {
+            return ActionResult::failure(format!("Hotbar slot {} is empty.", slot + 1));
+        };
+        let Some(index) = InventoryManager::hotbar_item(&self.player, slot) else {
+            self.player.inventory.hotbar.clear(slot);
+            return ActionResult::failure(format!(
+                "You have no {name} left; hotbar slot {} is now empty.",
+                slot + 1
+            ));
+        };
+        let mut result = self.use_item(index);
+        if result.success && InventoryManager::hotbar_item(&self.player, slot).is_none() {
+            self.player.inventory.hotbar.clear(slot);
+            result.message = format!(
+                "{} That was your last {name}; hotbar slot {} is now empty.",
+                result.message,
+                slot + 1
+            );
+        }
+        result
+    }
 
-                    // Get the combat action from the user
-                    let action = match ui.handle_combat_action(&game.player) {
-                        Ok(a) => a,
-                        Err(e) => {
-                            eprintln!("Error handling combat action: {e}");
-                            break;
-                        }
-                    };
+    /// Throws the consumable at inventory `index` at the nearest visible
+    /// enemy within `THROW_RANGE` tiles
+    pub fn throw_item(&mut self, index: usize) -> ActionResult {
+        let Some(name) =
+            InventoryManager::get_item(&self.player, index).map(|item| item.name().to_string())
+        else {
+            return ActionResult::failure("Invalid item index");
+        };
+        let Some(pos) = self.nearest_visible_enemy(THROW_RANGE) else {
+            return ActionResult::failure(format!(
+                "There's no enemy in range to throw the {name} at."
+            ));
+        };
+        let Some(mut enemy) = self.current_level_mut().remove_enemy_at(&pos) else {
+            return ActionResult::failure("Invalid target");
+        };
 
-                    // Apply the chosen action
-                    let mut enemy_clone = enemy.clone();
-                    let mut player_clone = game.player.clone();
-                    let result = process_combat_turn(&mut player_clone, &mut enemy_clone, action);
-
-                    // Update game state
-                    game.player = player_clone;
-                    if !result.enemy_defeated && !result.player_fled {
-                        if let Some(enemy_ref) =
-                            game.current_level_mut().get_enemy_at_mut(&enemy_pos)
-                        {
-                            *enemy_ref = enemy_clone;
-                        }
-                    }
+        self.bestiary.record_encounter(&enemy);
+        let mut result =
+            combat::resolve_throw(&mut self.player, &mut enemy, index, &mut self.rng.world);
+        self.record_combat_turn(&result, std::slice::from_ref(&enemy));
+        self.leave_loot(&[pos], &result.loot);
+        self.leave_corpses(&[pos], std::slice::from_ref(&enemy), &result.slain);
+        let routs = self.rout_packs(&[pos], std::slice::from_ref(&enemy), &result.slain);
+        result.messages.extend(routs);
+        if enemy.is_alive() {
+            enemy.struck_from_range = true;
+            enemy.alert(self.player_position());
+            self.current_level_mut().enemies.insert(pos, enemy);
+        }
+        self.make_noise(Noise::Fight);
+        ActionResult::success(result.messages.join(" "))
+    }
 
-                    // Add combat messages to UI
-                    ui.add_messages_from_combat(&result);
+    /// Uses the Taming Charm at inventory `index` on an enemy next to the
+    /// player, one that can be tamed if there is one
+    fn tame_adjacent_beast(&mut self, index: usize) -> ActionResult {
+        let player = self.player_position();
+        let Some((pos, enemy)) = self
+            .current_level()
+            .enemies
+            .iter()
+            .filter(|(pos, _)| (pos.x - player.x).abs().max((pos.y - player.y).abs()) == 1)
+            .min_by_key(|(pos, enemy)| (!Companion::can_tame(enemy), pos.y, pos.x))
+            .map(|(&pos, enemy)| (pos, enemy.clone()))
+        else {
+            return ActionResult::failure("There's no beast next to you to tame.");
+        };
 
-                    // Check if combat is over
-                    if result.enemy_defeated {
-                        game.current_level_mut().remove_enemy_at(&enemy_pos);
-                        game.game_state = GameState::Playing;
-                        // Reset combat state and add victory message
-                        game.combat_started = false;
-                        ui.add_message("You were victorious!".to_string());
-                    } else if result.player_fled {
-                        game.game_state = GameState::Playing;
-                        // Reset combat state and add fled message
-                        game.combat_started = false;
-                        ui.add_message("You fled from combat!".to_string());
-                    } else if !game.player.is_alive() {
-                        game.game_state = GameState::GameOver;
-                    }
+        match combat::tame_beast(&mut self.player, index, &enemy) {
+            Ok(message) => {
+                // The beast stays where it stood, now at the player's side
+                let level = self.current_level_mut();
+                level.remove_enemy_at(&pos);
+                level.companion_position = Some(pos);
+                ActionResult::success(message)
+            }
+            Err(message) => ActionResult::failure(message),
+        }
+    }
+
+    /// Reads the Scroll of Return at inventory `index`: the first takes the
+    /// player to where they started the dungeon and remembers where they
+    /// read it, the next takes them back there
+    fn read_return_scroll(&mut self, index: usize) -> ActionResult {
+        let dungeon_index = self.current_dungeon_index;
+        let back = self
+            .return_point
+            .filter(|point| point.dungeon_index == dungeon_index);
+        let (level, position) = match back {
+            Some(point) => (point.level, point.position),
+            None => {
+                let entrance = &self.current_dungeon().levels[0];
+                let start = entrance
+                    .rooms
+                    .first()
+                    .map_or(entrance.player_position, |room| room.center());
+                (0, start)
+            }
+        };
+        if self.current_dungeon().levels[level]
+            .enemies
+            .contains_key(&position)
+        {
+            return ActionResult::failure(
+                "The scroll's magic falters: something stands where it would take you.",
+            );
+        }
+
+        InventoryManager::remove_item(&mut self.player, index);
+        self.return_point = match back {
+            Some(_) => None,
+            None => Some(ReturnPoint {
+                dungeon_index,
+                level: self.current_dungeon().current_level,
+                position: self.player_position(),
+            }),
+        };
+        let turn = self.stats.turns;
+        let dungeon = self.current_dungeon_mut();
+        dungeon.leave_level(turn);
+        dungeon.current_level = level;
+        self.arrive_on_level(Some(position), "teleport");
+
+        ActionResult::success(match back {
+            Some(_) => "The scroll carries you back to where you read the last one.",
+            None => "The scroll whisks you back to the dungeon's entrance. Read another to return.",
+        })
+    }
+
+    /// The area ability the player knows, if any, with its index among
+    /// their abilities and where it lands
+    pub fn area_ability(&self) -> Option<(usize, &'static str, AreaTarget)> {
+        self.player
+            .available_abilities()
+            .into_iter()
+            .enumerate()
+            .find_map(|(index, name)| {
+                AreaTarget::of_ability(name).map(|target| (index, name, target))
+            })
+    }
+
+    /// Casts the area ability at `index` outside of a fight, at `aim` if
+    /// it's one the player aims. Every enemy caught is hurt and the rest
+    /// are alerted; one that can't be cast costs nothing.
+    pub fn cast_area_ability(&mut self, index: usize, aim: Position) -> ActionResult {
+        let Some(name) = self.player.available_abilities().get(index).copied() else {
+            return ActionResult::failure("Invalid ability index");
+        };
+        let center = match AreaTarget::of_ability(name) {
+            None => return ActionResult::failure(format!("{name} doesn't reach across the map.")),
+            Some(AreaTarget::Caster) => self.player_position(),
+            Some(AreaTarget::Tile { range }) if self.can_see_within(aim, range) => aim,
+            Some(AreaTarget::Tile { range }) => {
+                return ActionResult::failure(format!(
+                    "You can only aim {name} at a tile you can see within {range} tiles."
+                ))
+            }
+        };
+        let message = match self.player.use_ability(index) {
+            Ok(message) => message,
+            Err(message) => return ActionResult::failure(message),
+        };
+        let Some(effect) = combat::ability_damage(&message)
+            .and_then(|damage| AreaEffect::for_ability(name, damage))
+        else {
+            return ActionResult::success(message);
+        };
+
+        // Kills leave the map, so keep who was caught for the statistics
+        let caught: HashMap<Position, Enemy> = self
+            .current_level()
+            .enemies
+            .iter()
+            .filter(|(&pos, _)| in_area(center, AREA_RADIUS, pos))
+            .map(|(&pos, enemy)| (pos, enemy.clone()))
+            .collect();
+        for enemy in caught.values() {
+            self.bestiary.record_encounter(enemy);
+        }
+        let hits = combat::apply_area_effect_with_rng(
+            &mut self.player,
+            self.dungeons[self.current_dungeon_index].current_level_mut(),
+            center,
+            AREA_RADIUS,
+            &effect,
+            &mut self.rng.world,
+        );
+
+        let player = self.player_position();
+        let mut messages = vec![message, effect.summary(hits.len())];
+        for (pos, result) in &hits {
+            if let Some(enemy) = caught.get(pos) {
+                self.record_combat_turn(result, std::slice::from_ref(enemy));
+                self.leave_corpses(&[*pos], std::slice::from_ref(enemy), &result.slain);
+                let routs = self.rout_packs(&[*pos], std::slice::from_ref(enemy), &result.slain);
+                messages.extend(routs);
+            }
+            if let Some(enemy) = self.current_level_mut().get_enemy_at_mut(pos) {
+                enemy.alert(player);
+            }
+            messages.extend(result.messages.iter().cloned());
+        }
+        self.make_noise(Noise::Fight);
+        ActionResult::success(messages.join(" "))
+    }
+
+    /// Whether the player can see `pos` and it's no more than `range` tiles
+    /// away
+    fn can_see_within(&self, pos: Position, range: i32) -> bool {
+        let level = self.current_level();
+        let player_pos = level.player_position;
+        let (dx, dy) = (pos.x - player_pos.x, pos.y - player_pos.y);
+        dx * dx + dy * dy <= range * range
+            && level
+                .visible_tiles
+                .get(pos.y as usize)
+                .and_then(|row| row.get(pos.x as usize))
+                .copied()
+                .unwrap_or(false)
+    }
+
+    /// Position of the closest enemy the player can see within `range`
+    /// tiles, ties going to the one nearest the top left
+    fn nearest_visible_enemy(&self, range: i32) -> Option<Position> {
+        let level = self.current_level();
+        let player_pos = level.player_position;
+        level
+            .enemies
+            .keys()
+            .filter(|&&pos| self.can_see_within(pos, range))
+            .min_by_key(|pos| {
+                let (dx, dy) = (pos.x - player_pos.x, pos.y - player_pos.y);
+                (dx * dx + dy * dy, pos.y, pos.x)
+            })
+            .copied()
+    }
+
+    /// Drops an inventory item onto the player's tile, unequipping it first
+    pub fn drop_item(&mut self, index: usize) -> ActionResult {
+        let Some(item) = InventoryManager::get_item(&self.player, index) else {
+            return ActionResult::failure("Invalid item index");
+        };
+        let name = item.name().to_string();
+
+        if let Item::Quest { .. } = item {
+            return ActionResult::failure(format!(
+                "You can't abandon the {name}, it's needed for a quest."
+            ));
+        }
+
+        if self.player.inventory.is_stuck(index) {
+            return ActionResult::failure(format!("The {name} is cursed and won't come off."));
+        }
+
+        let pos = self.player_position();
+        if self.current_level().items.contains_key(&pos) {
+            return ActionResult::failure(
+                "There's already an item here. Move to an empty spot first.",
+            );
+        }
+
+        let was_equipped = self
+            .player
+            .inventory
+            .equipped
+            .values()
+            .any(|slot| *slot == Some(index));
+
+        if let Some(item) = InventoryManager::remove_item(&mut self.player, index) {
+            self.current_level_mut().items.insert(pos, item);
+        }
+
+        if was_equipped {
+            ActionResult::success(format!("You unequip and drop the {name}."))
+        } else {
+            ActionResult::success(format!("You drop the {name}."))
+        }
+    }
+
+    /// Whether the player stands in a shrine, where gear can be enchanted
+    pub fn can_enchant(&self) -> bool {
+        let level = self.current_level();
+        level.special_room_at(level.player_position) == Some(RoomKind::Shrine)
+    }
+
+    /// What enchanting the item at `index` would take, if it can be
+    /// enchanted at all
+    pub fn enchant_offer(&self, index: usize) -> Option<EnchantOffer> {
+        EnchantOffer::for_item(&self.player, index)
+    }
+
+    /// Offers gold and Arcane Dust at a shrine to enchant the item at `index`
+    pub fn enchant_item(&mut self, index: usize) -> ActionResult {
+        self.with_world_rng(|game, rng| game.enchant_item_with_rng(index, rng))
+    }
+
+    fn enchant_item_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        index: usize,
+        rng: &mut R,
+    ) -> ActionResult {
+        if !self.can_enchant() {
+            return ActionResult::failure("Gear can only be enchanted at a shrine.");
+        }
+        let Some(offer) = self.enchant_offer(index) else {
+            return ActionResult::failure("Only identified equipment can be enchanted.");
+        };
+        if !offer.affordable() {
+            return ActionResult::failure(format!(
+                "Enchanting the {} takes {} gold and {} Arcane Dust.",
+                offer.name, offer.cost.gold, offer.cost.dust
+            ));
+        }
+
+        self.player.gold -= offer.cost.gold;
+        let took = rng.gen_bool(offer.chance);
+        let stat = (took && rng.gen_bool(enchant::STAT_BONUS_CHANCE))
+            .then(|| StatType::iter().collect::<Vec<_>>())
+            .and_then(|stats| stats.choose(rng).copied());
+        let Some(Item::Equipment(equipment)) = self.player.inventory.items.get_mut(index) else {
+            return ActionResult::failure("Invalid item index");
+        };
+        let message = if took {
+            let gained = equipment.enchant(stat);
+            format!(
+                "The altar flares and the {} becomes the {} ({gained}).",
+                offer.name, equipment.name
+            )
+        } else {
+            equipment.record_failed_enchant();
+            format!(
+                "The altar flickers and goes dark. The enchantment on the {} doesn't take.",
+                offer.name
+            )
+        };
+
+        // Dust goes from the back so the indices before it stay put
+        let mut spent = 0;
+        for dust in (0..self.player.inventory.items.len()).rev() {
+            if spent == offer.cost.dust {
+                break;
+            }
+            if matches!(&self.player.inventory.items[dust], Item::Consumable(consumable)
+                if consumable.consumable_type == ConsumableType::ArcaneDust)
+            {
+                InventoryManager::remove_item(&mut self.player, dust);
+                spent += 1;
+            }
+        }
+
+        if took {
+            ActionResult::success(message)
+        } else {
+            ActionResult::failure(message)
+        }
+    }
+
+    pub fn current_dungeon(&self) -> &Dungeon {
+        &self.dungeons[self.current_dungeon_index]
+    }
+
+    pub fn current_dungeon_mut(&mut self) -> &mut Dungeon {
+        &mut self.dungeons[self.current_dungeon_index]
+    }
+
+    /// Lends the world stream to `roll` along with the game, for rolls made
+    /// by methods that need all of `self`. Calls mustn't nest, or the inner
+    /// one's rolls would be made again.
+    fn with_world_rng<T>(&mut self, roll: impl FnOnce(&mut Self, &mut ChaCha8Rng) -> T) -> T {
+        let mut rng = self.rng.world.clone();
+        let result = roll(self, &mut rng);
+        self.rng.world = rng;
+        result
+    }
+
+    pub fn current_level(&self) -> &Level {
+        self.current_dungeon().current_level()
+    }
+
+    pub fn current_level_mut(&mut self) -> &mut Level {
+        self.current_dungeon_mut().current_level_mut()
+    }
+
+    /// What the screens call a special run: "Daily Challenge — 2024-06-01"
+    pub fn run_label(&self) -> Option<String> {
+        self.daily.as_ref().map(DailyChallenge::label)
+    }
+
+    /// The adaptive difficulty multiplier for the character screen, shown
+    /// in debug builds only, and only while adaptive difficulty is on
+    pub fn difficulty_note(&self) -> Option<String> {
+        (cfg!(debug_assertions) && self.adaptive_difficulty).then(|| self.adaptive.label())
+    }
+
+    pub fn player_position(&self) -> Position {
+        self.current_level().player_position
+    }
+
+    /// Tries to step the player by (`dx`, `dy`). Walls and other obstacles
+    /// are reported in the log; enemies, chests, stairs and the exit are
+    /// interacted with by walking into them.
+    pub fn move_player(&mut self, dx: i32, dy: i32) -> MoveOutcome {
+        // Get the current player position
+        let current_pos = self.current_level().player_position;
+        let new_pos = Position::new(current_pos.x + dx, current_pos.y + dy);
+
+        // Check if the position is valid (tiles only, not enemies)
+        let Some(tile_type) = self
+            .current_level()
+            .get_tile(new_pos.x, new_pos.y)
+            .map(|tile| tile.tile_type)
+        else {
+            return self.blocked(BlockReason::Obstacle);
+        };
+        if tile_type == TileType::Grave {
+            return self.blocked(BlockReason::Grave);
+        }
+        if tile_type == TileType::Altar {
+            return self.blocked(BlockReason::Altar);
+        }
+        if !tile_type.is_walkable() {
+            return self.blocked(BlockReason::Wall);
+        }
+
+        // Check for enemies
+        if self.current_level().enemies.contains_key(&new_pos) {
+            // Start combat - don't move the player into the enemy's position.
+            // Catching it unawares or after hitting it from range opens the
+            // fight with a stronger first turn.
+            if let Some(bumped) = self.current_level_mut().get_enemy_at_mut(&new_pos) {
+                let opened = bumped.alertness == Alertness::Unaware || bumped.struck_from_range;
+                bumped.struck_from_range = false;
+                self.opening_strike = opened;
+            }
+            self.game_state = GameState::Combat(self.combat_participants(new_pos));
+            for enemy in self.combat_enemies() {
+                self.bestiary.record_encounter(&enemy);
+            }
+            self.log
+                .push(LogCategory::Combat, self.combat_start_message());
+
+            // Mark that we're starting a new combat
+            self.combat_started = true;
+
+            return MoveOutcome::StartedCombat;
+        }
+
+        // Friendly folk are talked to rather than pushed past
+        if self.current_level().npcs.contains_key(&new_pos) {
+            self.start_talking(new_pos);
+            return MoveOutcome::StartedTalking;
+        }
+
+        // Check for items on the ground; anything on a chest's tile is
+        // looted with the chest. One too heavy to carry stays where it is,
+        // and the player steps over it.
+        if tile_type != TileType::Chest && self.current_level().items.contains_key(&new_pos) {
+            self.pick_up_at(new_pos);
+        }
+
+        // Trade places with the companion
+        if self.current_level().companion_position == Some(new_pos) {
+            self.current_level_mut().companion_position = Some(current_pos);
+        }
+
+        // Check for special tiles
+        match tile_type {
+            TileType::StairsDown | TileType::StairsUp if self.confirm_stairs => {
+                // Step onto the stairs and wait for '>' or '<'
+                self.current_level_mut().player_position = new_pos;
+                self.update_visibility();
+                let prompt = if tile_type == TileType::StairsDown {
+                    "Press > to descend."
                 } else {
-                    // Enemy no longer exists at this position, return to playing
-                    game.game_state = GameState::Playing;
-                }
+                    "Press < to ascend."
+                };
+                self.log.push(LogCategory::Movement, prompt);
+                return MoveOutcome::AtStairs;
             }
-            GameState::Inventory => {
-                if let Err(e) = ui.draw_inventory_screen(&game.player) {
-                    eprintln!("Error drawing inventory screen: {e}");
-                    break;
+            TileType::StairsDown => return self.descend(),
+            TileType::StairsUp => return self.ascend(),
+            TileType::Exit if !self.current_dungeon().is_final_level() => {
+                // Only the final level is generated with an exit; one anywhere
+                // else leads on down like stairs rather than doing nothing
+                self.log.push(
+                    LogCategory::Movement,
+                    "This is no way out yet: the dungeon's exit lies on its deepest level. The passage leads on down.",
+                );
+                return self.descend();
+            }
+            TileType::Exit => {
+                // Victory condition - player reached the exit of the final level
+                self.turn_in_quests();
+                self.game_state = GameState::Victory;
+                self.pending_sounds.push(SoundEvent::Victory);
+                self.current_level_mut().player_position = new_pos;
+                return MoveOutcome::ReachedExit;
+            }
+            TileType::Chest => {
+                // Walking into a chest opens it without moving, and without
+                // looking it over first; a Ranger's eye still catches what
+                // G would, unless they've already looked
+                let unchecked = self
+                    .current_level()
+                    .chest_contents
+                    .get(&new_pos)
+                    .is_some_and(|chest| !chest.found);
+                if unchecked && self.player.class.class_type == ClassType::Ranger {
+                    self.with_world_rng(|game, rng| game.check_chest(new_pos, rng));
+                } else {
+                    self.open_chest(new_pos);
                 }
-
-                match ui.wait_for_key() {
-                    Ok(key_event) => match key_event.code {
-                        KeyCode::Char(c) if ('1'..='9').contains(&c) => {
-                            let index = c.to_digit(10).unwrap() as usize - 1;
-                            if index < InventoryManager::get_item_count(&game.player) {
-                                if let Some(item) = InventoryManager::get_item(&game.player, index)
-                                {
-                                    match item {
-                                        Item::Equipment(_) | Item::Consumable(_) => {
-                                            let result =
-                                                InventoryManager::use_item(&mut game.player, index);
-                                            ui.add_message(result.message);
-                                        }
-                                        Item::Quest { .. } => {
-                                            ui.add_message("This item cannot be used".to_string());
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        KeyCode::Char('e') | KeyCode::Esc => {
-                            game.game_state = GameState::Playing;
-                        }
-                        _ => {}
-                    },
-                    Err(e) => {
-                        eprintln!("Error reading key: {e}");
-                        break;
-                    }
+                if matches!(self.game_state, GameState::Combat(_)) {
+                    return MoveOutcome::StartedCombat;
                 }
+                return MoveOutcome::OpenedChest;
             }
-            GameState::Character => {
-                if let Err(e) = ui.draw_character_screen(&game.player) {
-                    eprintln!("Error drawing character screen: {e}");
-                    break;
-                }
+            _ => {}
+        }
 
-                // Any key returns to game
-                if let Err(e) = ui.wait_for_key() {
-                    eprintln!("Error reading key: {e}");
-                    break;
-                }
+        // Move the player
+        let noise = if tile_type == TileType::Door {
+            Noise::Door
+        } else {
+            Noise::Footsteps
+        };
+        let from = self
+            .current_level()
+            .get_tile(current_pos.x, current_pos.y)
+            .map(|tile| tile.tile_type);
+        self.current_level_mut().player_position = new_pos;
+        self.collect_gold_at(new_pos);
+        self.update_visibility();
+        self.announce_nearby_items();
+        self.announce_special_room();
+        self.make_noise(noise);
+        self.enter_terrain(from, tile_type);
+        self.hint_at_surroundings();
+        MoveOutcome::Moved
+    }
+
+    /// What stepping from a `from` tile onto `tile_type` does to the player
+    fn enter_terrain(&mut self, from: Option<TileType>, tile_type: TileType) {
+        self.extra_turns = tile_type.move_cost() - 1;
+        self.player.off_balance = tile_type == TileType::ShallowWater;
+        if from == Some(tile_type) && tile_type != TileType::Lava {
+            return;
+        }
+        let message = match tile_type {
+            TileType::ShallowWater => "You wade into the shallow water, quietly but unsteadily.",
+            TileType::Lava => "You step into the lava! It will burn you every turn you stay.",
+            TileType::Rubble => "You clamber over the rubble; it slows you down.",
+            _ => return,
+        };
+        self.log.push(LogCategory::Movement, message);
+    }
+
+    /// Takes the stairs the player is standing on, down for the '>' key or
+    /// up for '<'
+    pub fn take_stairs(&mut self, down: bool) -> MoveOutcome {
+        let pos = self.player_position();
+        let tile_type = self
+            .current_level()
+            .get_tile(pos.x, pos.y)
+            .map(|tile| tile.tile_type);
+        match (down, tile_type) {
+            (true, Some(TileType::StairsDown)) => self.descend(),
+            (false, Some(TileType::StairsUp)) => self.ascend(),
+            (true, _) => self.blocked(BlockReason::NoStairsDown),
+            (false, _) => self.blocked(BlockReason::NoStairsUp),
+        }
+    }
+
+    fn descend(&mut self) -> MoveOutcome {
+        let turn = self.stats.turns;
+        self.current_dungeon_mut().leave_level(turn);
+        if self.current_dungeon_mut().go_to_next_level().is_err() {
+            // Can't go further down
+            return self.blocked(BlockReason::Obstacle);
+        }
+        let depth = self.current_dungeon().current_level as u32 + 1;
+        self.stats.record_depth(depth);
+        if self.adaptive_difficulty {
+            self.adaptive.level_reached();
+        }
+        // Arrive on the stairs leading back up
+        let arrival = self.current_level().stairs_up_position;
+        self.arrive_on_level(arrival, "descend");
+        MoveOutcome::UsedStairs
+    }
+
+    fn ascend(&mut self) -> MoveOutcome {
+        let turn = self.stats.turns;
+        self.current_dungeon_mut().leave_level(turn);
+        if self.current_dungeon_mut().go_to_previous_level().is_err() {
+            // Can't go further up
+            return self.blocked(BlockReason::Obstacle);
+        }
+        // Arrive on the stairs leading back down
+        let arrival = self.current_level().stairs_down_position;
+        self.arrive_on_level(arrival, "ascend");
+        MoveOutcome::UsedStairs
+    }
+
+    /// Reports why the player couldn't move
+    fn blocked(&mut self, reason: BlockReason) -> MoveOutcome {
+        self.log.push(LogCategory::Movement, reason.message());
+        if matches!(reason, BlockReason::Wall | BlockReason::Obstacle) {
+            self.pending_sounds.push(SoundEvent::Bump);
+        }
+        MoveOutcome::Blocked(reason)
+    }
 
-                game.game_state = GameState::Playing;
+    /// Lets every enemy within earshot of the player hear `noise`; the
+    /// player's dexterity decides how far it carries, and standing in water
+    /// or rain halves that
+    pub fn make_noise(&mut self, noise: Noise) {
+        let mut radius = noise.radius(self.player.stats.dexterity);
+        let origin = self.player_position();
+        // The water muffles whatever the player does in it
+        if self
+            .current_level()
+            .get_tile(origin.x, origin.y)
+            .is_some_and(|tile| tile.tile_type == TileType::ShallowWater)
+        {
+            radius /= 2;
+        }
+        if weather::has_effect(self.current_level(), AmbientEvent::Rain) {
+            radius /= 2;
+        }
+        for (pos, enemy) in &mut self.current_level_mut().enemies {
+            if (pos.x - origin.x).abs().max((pos.y - origin.y).abs()) <= radius {
+                enemy.hear_noise(origin);
             }
-            _ => {}
         }
     }
 
-    // Handle game end
-    match game.game_state {
-        GameState::GameOver => {
-            if let Err(e) = ui.draw_game_over(&game.player) {
-                eprintln!("Error drawing game over screen: {e}");
+    /// Logs each item lying in view next to the player, once per item
+    fn announce_nearby_items(&mut self) {
+        let names: Vec<String> = self
+            .current_level_mut()
+            .announce_adjacent_items()
+            .into_iter()
+            .map(|item| item.name().to_string())
+            .collect();
+        for name in names {
+            let article = if name.starts_with(['A', 'E', 'I', 'O', 'U']) {
+                "an"
+            } else {
+                "a"
+            };
+            self.log
+                .push(LogCategory::Loot, format!("You see {article} {name} here."));
+        }
+    }
+
+    /// Describes the special room the player just walked into, the first
+    /// time they do
+    fn announce_special_room(&mut self) {
+        if let Some(kind) = self.current_level_mut().enter_special_room() {
+            self.log.push(LogCategory::Movement, kind.flavor());
+        }
+    }
+
+    /// Puts the player on `arrival` after taking the stairs, falling back to
+    /// where they last stood on this level, and reports the new depth
+    fn arrive_on_level(&mut self, arrival: Option<Position>, verb: &str) {
+        if let Some(pos) = arrival {
+            self.current_level_mut().player_position = pos;
+        }
+        self.place_companion();
+        self.update_visibility();
+        self.announce_nearby_items();
+        self.pending_sounds.push(SoundEvent::Stairs);
+        let depth = self.current_dungeon().current_level + 1;
+        self.log.push(
+            LogCategory::Movement,
+            format!("You {verb} to level {depth}."),
+        );
+        self.warn_if_dark();
+
+        if self.enemy_respawn {
+            let (turn, view_radius) = (self.stats.turns, self.view_radius);
+            let already: HashSet<Position> = self.current_level().enemies.keys().copied().collect();
+            let spawned = self.dungeons[self.current_dungeon_index].repopulate_current_level(
+                turn,
+                view_radius,
+                &mut self.rng.world,
+            );
+            if self.adaptive_difficulty {
+                let factor = self.adaptive.multiplier;
+                for (pos, enemy) in self.current_level_mut().enemies.iter_mut() {
+                    if !already.contains(pos) {
+                        enemy.scale_strength(factor);
+                    }
+                }
+            }
+            if spawned > 0 {
+                self.log.push(
+                    LogCategory::Movement,
+                    "Something has moved in while you were away.",
+                );
             }
         }
-        GameState::Victory => {
-            if let Err(e) = ui.draw_victory_screen(&game.player) {
-                eprintln!("Error drawing victory screen: {e}");
+        self.hint_at_surroundings();
+    }
+
+    /// Warns the player when their health has dropped below half or below
+    /// a quarter since last time, once for each drop; healing back up lets
+    /// the warning come again
+    pub fn warn_of_low_health(&mut self) {
+        let level = BarLevel::of(self.player.health, self.player.max_health);
+        // A fallen player has more to hear about than their health
+        if level > self.health_warning && self.player.health > 0 {
+            if let Some(warning) = level.warning() {
+                self.log.push(LogCategory::Combat, warning);
             }
         }
-        _ => {}
+        self.health_warning = level;
     }
 
-    // Clean up
-    if let Err(e) = ui.cleanup() {
-        eprintln!("Error cleaning up UI: {e}");
+    pub fn process_turn(&mut self) {
+        // Update game state, process enemy movements, etc.
+        if let GameState::Playing = self.game_state {
+            self.stats.turns += 1;
+            self.log.set_turn(self.stats.turns);
+            let turn = self.stats.turns;
+            self.current_level_mut().rot_corpses(turn);
+            if self.burn_in_lava() || self.suffer_poison() {
+                return;
+            }
+            self.burn_torch();
+            self.tend_companion();
+            self.with_world_rng(|game, rng| game.pass_ambient_time(rng));
+            for message in self.player.inventory.attune_equipped() {
+                self.log.push(LogCategory::Loot, message);
+            }
+
+            // Time runs on until the player is ready to act again, and the
+            // enemies act as often as their speed allows in that time
+            let ticks = self.pass_player_time();
+
+            // Process enemy turns: unaware enemies wander, suspicious ones
+            // go to look at what they heard and alerted ones chase the player
+            let player = self.player_position();
+
+            // Leaders rally whoever is near them now
+            self.current_level_mut().rally_packs();
+
+            // Clone enemy positions to avoid borrowing issues
+            let mut enemy_positions: Vec<Position> =
+                self.current_level().enemies.keys().copied().collect();
+            // In a fixed order, so a seeded run plays out the same every time
+            enemy_positions.sort_by_key(|pos| (pos.y, pos.x));
+
+            for start_pos in enemy_positions {
+                let Some(enemy) = self.current_level_mut().get_enemy_at_mut(&start_pos) else {
+                    continue;
+                };
+                let speed = enemy.speed();
+                let actions = energy::gain(&mut enemy.energy, speed, ticks);
+                enemy.slowed_turns = enemy.slowed_turns.saturating_sub(1);
+                enemy.reload_turns = enemy.reload_turns.saturating_sub(1);
+
+                // Stunned and winded enemies stay where they are
+                if enemy.lose_turn_to_stun() || enemy.catch_breath() {
+                    continue;
+                }
+
+                // A pack that saw its leader fall runs from the player
+                if enemy.flee_in_panic() {
+                    let mut pos = start_pos;
+                    for _ in 0..actions {
+                        let Some(away) = self.step_away(pos, player) else {
+                            break;
+                        };
+                        if let Some(enemy) = self.current_level_mut().remove_enemy_at(&pos) {
+                            self.current_level_mut().enemies.insert(away, enemy);
+                            pos = away;
+                        }
+                    }
+                    continue;
+                }
+
+                let sees_player = self.current_level().enemy_sees_player(start_pos);
+                let Some(enemy) = self.current_level_mut().get_enemy_at_mut(&start_pos) else {
+                    continue;
+                };
+                enemy.look_for_player(sees_player, player);
+                let destination = enemy.destination(player);
+
+                // Archers and the like keep their distance and shoot rather
+                // than close in; their shots can end the game outside a fight
+                if actions > 0 && self.take_ranged_turn(start_pos) {
+                    if !self.player.is_alive() {
+                        self.game_state = GameState::GameOver;
+                        return;
+                    }
+                    continue;
+                }
+
+                let mut pos = start_pos;
+
+                // Faster enemies (beasts) get more steps, slowed ones fewer
+                for _ in 0..actions {
+                    let new_pos = match destination {
+                        Some(goal) => match self.step_toward(pos, goal) {
+                            Some(step) => step,
+                            None => break,
+                        },
+                        None => {
+                            // 50% chance an unaware enemy moves randomly
+                            if !self.rng.world.gen_bool(0.5) {
+                                continue;
+                            }
+                            let dx = self.rng.world.gen_range(-1..=1);
+                            let dy = self.rng.world.gen_range(-1..=1);
+                            Position::new(pos.x + dx, pos.y + dy)
+                        }
+                    };
+
+                    // Only move if position is valid and not occupied
+                    if self.is_free_for_enemy(new_pos) {
+                        if let Some(enemy) = self.current_level_mut().remove_enemy_at(&pos) {
+                            self.current_level_mut().enemies.insert(new_pos, enemy);
+                            pos = new_pos;
+                        }
+                    }
+                }
+
+                // An enemy hunting the player that ends up beside them
+                // attacks, and the fight starts with its blow
+                let beside = (pos.x - player.x).abs().max((pos.y - player.y).abs()) == 1;
+                if actions > 0
+                    && beside
+                    && self
+                        .current_level()
+                        .get_enemy_at(&pos)
+                        .is_some_and(|enemy| enemy.alertness == Alertness::Alerted)
+                {
+                    self.ambush(pos);
+                    break;
+                }
+
+                // A search that can't get any closer finds nothing and ends
+                if let Some(enemy) = self.current_level_mut().get_enemy_at_mut(&pos) {
+                    if enemy.alertness == Alertness::Suspicious
+                        && (Some(pos) == destination || pos == start_pos)
+                    {
+                        enemy.calm_down();
+                    }
+                }
+            }
+            self.with_world_rng(|game, rng| game.raise_corpses(rng));
+            self.check_achievements();
+            self.hint_at_condition();
+        }
+    }
+
+    /// Starts a fight with the enemy at `attacker`, which has closed in on
+    /// the player and strikes before their first turn
+    fn ambush(&mut self, attacker: Position) {
+        self.current_level_mut().rally_packs();
+        self.game_state = GameState::Combat(self.combat_participants(attacker));
+        for enemy in self.combat_enemies() {
+            self.bestiary.record_encounter(&enemy);
+        }
+        let context = self.combat_context();
+        let Some(mut enemy) = self.current_level().get_enemy_at(&attacker).cloned() else {
+            return;
+        };
+        let result = combat::first_strike_with_rng(
+            &mut self.player,
+            &mut enemy,
+            &context,
+            &mut self.rng.combat,
+        );
+        let mut lines = vec![format!("The {} attacks you!", enemy.name)];
+        lines.extend(result.messages.iter().cloned());
+        if let Some(enemy_ref) = self.current_level_mut().get_enemy_at_mut(&attacker) {
+            *enemy_ref = enemy.clone();
+        }
+        self.record_combat_turn(&result, &[enemy]);
+        if self.adaptive_difficulty {
+            let player = &self.player;
+            self.adaptive.record_turn(
+                result.enemy_damage_dealt,
+                player.health,
+                player.max_health,
+                false,
+            );
+        }
+        for line in &lines {
+            self.log.push(LogCategory::Combat, line.clone());
+        }
+        if !self.player.is_alive() {
+            self.game_state = GameState::GameOver;
+            return;
+        }
+        self.ambush = Some(lines.join(" "));
+        self.combat_started = true;
+    }
+
+    /// Gives each dark mage a chance to raise an unsearched corpse it can
+    /// see as a weak skeleton
+    fn raise_corpses<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        let mages: Vec<(Position, String)> = self
+            .current_level()
+            .enemies
+            .iter()
+            .filter(|(_, enemy)| enemy.enemy_type == EnemyType::DarkMage)
+            .map(|(&pos, enemy)| (pos, enemy.name.clone()))
+            .collect();
+        for (mage, name) in mages {
+            let level = self.current_level_mut();
+            let Some(pos) = level.corpse_to_raise(mage) else {
+                continue;
+            };
+            if !rng.gen_bool(RAISE_CHANCE) {
+                continue;
+            }
+            let Some(corpse) = level.corpses.remove(&pos) else {
+                continue;
+            };
+            let risen = corpse.raise();
+            let seen = level
+                .get_tile(pos.x, pos.y)
+                .is_some_and(|tile| tile.visible);
+            let message = format!(
+                "The {name} raises the {} corpse as a {}!",
+                corpse.name, risen.name
+            );
+            level.enemies.insert(pos, risen);
+            if seen {
+                self.log.push(LogCategory::Combat, message);
+            }
+        }
+    }
+
+    /// Burns the player if they're standing in lava, ending the game if it
+    /// kills them. Returns whether it did.
+    fn burn_in_lava(&mut self) -> bool {
+        let pos = self.player_position();
+        if !self
+            .current_level()
+            .get_tile(pos.x, pos.y)
+            .is_some_and(|tile| tile.tile_type.is_hazardous())
+        {
+            return false;
+        }
+        self.player.health -= LAVA_DAMAGE;
+        self.feel_hurt();
+        self.log.push(
+            LogCategory::Combat,
+            format!("The lava burns you for {LAVA_DAMAGE} damage!"),
+        );
+        if self.player.is_alive() {
+            return false;
+        }
+        self.stats.killed_by = Some("lava".to_string());
+        self.game_state = GameState::GameOver;
+        true
+    }
+
+    /// Queues the sound and the flash of the player being hurt outside a
+    /// fight
+    fn feel_hurt(&mut self) {
+        self.pending_sounds.push(SoundEvent::Hurt);
+        self.pending_effects.push(ScreenEffect::Hurt);
+    }
+
+    /// Hurts a poisoned player and counts the poison down, ending the game
+    /// if it kills them. Returns whether it did.
+    fn suffer_poison(&mut self) -> bool {
+        if self.player.poisoned_turns == 0 {
+            return false;
+        }
+        self.player.poisoned_turns -= 1;
+        self.player.health -= POISON_DAMAGE;
+        self.feel_hurt();
+        let message = if self.player.poisoned_turns == 0 {
+            "The poison runs its course."
+        } else {
+            "The poison burns in your veins."
+        };
+        self.log.push(LogCategory::Combat, message);
+        if self.player.is_alive() {
+            return false;
+        }
+        self.stats.killed_by = Some("poison".to_string());
+        self.game_state = GameState::GameOver;
+        true
+    }
+
+    /// Lets time pass until the player has the energy for their next
+    /// action, and for any extra turns their last move took, counting down
+    /// their haste and their weapon's edge. Returns the ticks that passed.
+    fn pass_player_time(&mut self) -> u32 {
+        let speed = self.player.speed();
+        let mut ticks = 0;
+        for _ in 0..=std::mem::take(&mut self.extra_turns) {
+            let wait = energy::ticks_until_ready(self.player.energy, speed);
+            energy::gain(&mut self.player.energy, speed, wait);
+            ticks += wait;
+        }
+
+        if self.player.haste_turns > 0 {
+            self.player.haste_turns -= 1;
+            if self.player.haste_turns == 0 {
+                self.log
+                    .push(LogCategory::System, "The haste wears off; you slow down.");
+            }
+        }
+        if self.player.honed_turns > 0 {
+            self.player.honed_turns -= 1;
+            if self.player.honed_turns == 0 {
+                self.log
+                    .push(LogCategory::System, "Your weapon's honed edge has dulled.");
+            }
+        }
+        ticks
+    }
+
+    /// Whether an enemy may step onto `pos`; they keep out of lava and go
+    /// around anyone friendly rather than through them
+    fn is_free_for_enemy(&self, pos: Position) -> bool {
+        self.current_level().is_tile_walkable(pos)
+            && !self
+                .current_level()
+                .get_tile(pos.x, pos.y)
+                .is_some_and(|tile| tile.tile_type.is_hazardous())
+            && !self.current_level().enemies.contains_key(&pos)
+            && !self.current_level().npcs.contains_key(&pos)
+            && pos != self.player_position()
+            && Some(pos) != self.current_level().companion_position
+    }
+
+    fn companion_is_up(&self) -> bool {
+        self.player
+            .companion
+            .as_ref()
+            .is_some_and(|companion| !companion.is_knocked_out())
+    }
+
+    /// Puts a companion that's up on a free tile next to the player, or
+    /// takes it off the map while it's knocked out
+    fn place_companion(&mut self) {
+        let player = self.player_position();
+        let spot = if self.companion_is_up() {
+            (-1..=1)
+                .flat_map(|dy| (-1..=1).map(move |dx| Position::new(player.x + dx, player.y + dy)))
+                .find(|&pos| self.is_free_for_enemy(pos))
+        } else {
+            None
+        };
+        self.current_level_mut().companion_position = spot;
+    }
+
+    /// Counts down a knocked out companion's rest and brings one that's up
+    /// along behind the player, one step a turn
+    fn tend_companion(&mut self) {
+        let Some(companion) = self.player.companion.as_mut() else {
+            return;
+        };
+        if companion.rest() {
+            let message = format!("{} gets back on its feet.", companion.name);
+            self.log.push(LogCategory::System, message);
+        }
+
+        let player = self.player_position();
+        let follow = match self.current_level().companion_position {
+            Some(pos) if self.companion_is_up() && pos != player => {
+                let distance = (pos.x - player.x).abs().max((pos.y - player.y).abs());
+                if distance <= 1 {
+                    Some(pos)
+                } else if distance <= COMPANION_LEASH {
+                    Some(self.step_toward(pos, player).unwrap_or(pos))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        match follow {
+            Some(pos) => self.current_level_mut().companion_position = Some(pos),
+            None => self.place_companion(),
+        }
+    }
+
+    /// Takes the turn of a ranged enemy at `pos` that's after the player:
+    /// backing off when the player comes close, otherwise taking aim and
+    /// then shooting while the player stays in its line of fire. Returns
+    /// false, leaving the enemy to move as usual, when it has no shot.
+    fn take_ranged_turn(&mut self, pos: Position) -> bool {
+        let player = self.player_position();
+        let level = self.current_level();
+        let Some(enemy) = level.get_enemy_at(&pos) else {
+            return false;
+        };
+        let Some(ranged) = enemy.ranged else {
+            return false;
+        };
+        if enemy.alertness != Alertness::Alerted || !level.in_line_of_fire(pos, ranged.range) {
+            if let Some(enemy) = self.current_level_mut().get_enemy_at_mut(&pos) {
+                enemy.taking_aim = false;
+            }
+            return false;
+        }
+
+        // Too close for comfort: step back while there's room to
+        let distance = (pos.x - player.x).abs().max((pos.y - player.y).abs());
+        if distance < KITE_DISTANCE {
+            if let Some(away) = self.step_away(pos, player) {
+                if let Some(mut enemy) = self.current_level_mut().remove_enemy_at(&pos) {
+                    enemy.taking_aim = false;
+                    self.current_level_mut().enemies.insert(away, enemy);
+                }
+                return true;
+            }
+        }
+
+        let Some(enemy) = self.current_level_mut().get_enemy_at_mut(&pos) else {
+            return false;
+        };
+        // Readying the next shot, and in no hurry to come closer
+        if enemy.reload_turns > 0 {
+            return true;
+        }
+        // The shot is telegraphed a turn ahead, for the player to duck out of
+        if !enemy.taking_aim {
+            enemy.taking_aim = true;
+            let message = format!("The {} {}!", enemy.name, enemy.enemy_type.takes_aim());
+            self.log.push(LogCategory::Combat, message);
+            return true;
+        }
+        enemy.taking_aim = false;
+        enemy.reload_turns = ranged.cooldown;
+        let shooter = enemy.clone();
+
+        let damage = self.player.take_hit(ranged.damage, DamageType::Physical);
+        self.log.push(
+            LogCategory::Combat,
+            format!(
+                "{} hits you for {damage} damage!",
+                shooter.enemy_type.projectile()
+            ),
+        );
+        self.stats.damage_taken += damage.max(0) as u32;
+        self.bestiary.record_hit(&shooter, damage);
+        if damage > 0 {
+            self.feel_hurt();
+        }
+        if !self.player.is_alive() {
+            self.stats.killed_by = Some(shooter.name);
+        }
+        true
+    }
+
+    /// The free tile next to `from` farthest from `threat`, if any is
+    /// farther from it than `from` is
+    fn step_away(&self, from: Position, threat: Position) -> Option<Position> {
+        let distance = |pos: Position| (pos.x - threat.x).abs().max((pos.y - threat.y).abs());
+        (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| Position::new(from.x + dx, from.y + dy)))
+            .filter(|&pos| distance(pos) > distance(from) && self.is_free_for_enemy(pos))
+            .max_by_key(|&pos| distance(pos))
+    }
+
+    /// The free tile next to `from` that brings an enemy closest to `goal`,
+    /// if one brings it any closer. A step around rubble is taken over one
+    /// through it.
+    fn step_toward(&self, from: Position, goal: Position) -> Option<Position> {
+        let (dx, dy) = ((goal.x - from.x).signum(), (goal.y - from.y).signum());
+        [(dx, dy), (dx, 0), (0, dy)]
+            .into_iter()
+            .filter(|&step| step != (0, 0))
+            .map(|(dx, dy)| Position::new(from.x + dx, from.y + dy))
+            .filter(|&pos| self.is_free_for_enemy(pos))
+            .min_by_key(|pos| {
+                self.current_level()
+                    .get_tile(pos.x, pos.y)
+                    .map_or(1, |tile| tile.tile_type.move_cost())
+            })
+    }
+
+    /// Tells the player when they stand in the dark without a torch
+    fn warn_if_dark(&mut self) {
+        if self.current_level().ambient_light == AmbientLight::Dark && self.player.torch_turns == 0
+        {
+            self.log.push(
+                LogCategory::System,
+                "It is very dark here. A torch would help.",
+            );
+        }
+    }
+
+    /// How far the player sees right now: the radius from the settings,
+    /// shortened on dark levels unless a torch is burning, and in the rain
+    pub fn current_view_radius(&self) -> i32 {
+        let level = self.current_level();
+        let radius = level
+            .ambient_light
+            .view_radius(self.view_radius, self.player.torch_turns > 0);
+        (radius - weather::sight_penalty(level)).max(1)
+    }
+
+    /// Counts down the weather on the player's level and, now and then,
+    /// starts something new there
+    fn pass_ambient_time<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        let ended = weather::tick_effects(self.current_level_mut());
+        if !ended.is_empty() {
+            for message in ended {
+                self.log.push(LogCategory::System, message);
+            }
+            self.update_visibility();
+        }
+        if self.ambient_events && rng.gen_bool(AMBIENT_EVENT_CHANCE) {
+            let event = AmbientEvent::for_dungeon(self.current_dungeon().dungeon_type);
+            self.start_ambient_event(event, rng);
+        }
+    }
+
+    /// Makes `event` happen on the player's level
+    fn start_ambient_event<R: Rng + ?Sized>(&mut self, event: AmbientEvent, rng: &mut R) {
+        weather::start_effect(self.current_level_mut(), event);
+        let player = self.player_position();
+        let message = match event {
+            AmbientEvent::Rain => {
+                self.update_visibility();
+                "It starts to rain. You can't see as far, or be heard as far.".to_string()
+            }
+            AmbientEvent::Rockslide => match weather::rockslide(self.current_level_mut(), rng) {
+                Some(_) => "Rocks come crashing down somewhere in the distance.".to_string(),
+                None => return,
+            },
+            AmbientEvent::Tremor => {
+                for (pos, enemy) in &mut self.current_level_mut().enemies {
+                    if in_area(player, TREMOR_WAKE_RADIUS, *pos) {
+                        enemy.hear_noise(player);
+                    }
+                }
+                "The ground shakes! Something nearby stirs.".to_string()
+            }
+            AmbientEvent::Whispers => WHISPERS.choose(rng).unwrap_or(&WHISPERS[0]).to_string(),
+        };
+        self.log.push(LogCategory::System, message);
+    }
+
+    /// Burns a turn of the player's torch while they're somewhere dark,
+    /// saying so when it goes out
+    fn burn_torch(&mut self) {
+        if self.current_level().ambient_light != AmbientLight::Dark || self.player.torch_turns == 0
+        {
+            return;
+        }
+        self.player.torch_turns -= 1;
+        if self.player.torch_turns == 0 {
+            self.log.push(
+                LogCategory::System,
+                "Your torch burns out. Darkness closes in.",
+            );
+        }
+    }
+
+    pub fn update_visibility(&mut self) {
+        let view_radius = self.current_view_radius();
+        let (dungeon_index, level_index) = (
+            self.current_dungeon_index,
+            self.current_dungeon().current_level,
+        );
+        let glimpse = if self.player.class.class_type == ClassType::Ranger {
+            visibility::RANGER_GLIMPSE_RANGE
+        } else {
+            0
+        };
+        let level = self.current_level_mut();
+        visibility::update_sight(level, view_radius);
+        visibility::update_glimpse(level, view_radius, glimpse);
+
+        let pass = ExplorationPass::new(dungeon_index, level_index, level.player_position);
+        if self.defer_exploration {
+            // The front-end finishes the last one before starting this one
+            self.exploration = Some(pass);
+        } else {
+            self.explore(pass, usize::MAX);
+        }
+    }
+
+    /// The exploration pass left for the front-end by the last visibility
+    /// update, if it hasn't been taken yet
+    pub fn take_exploration(&mut self) -> Option<ExplorationPass> {
+        self.exploration.take()
+    }
+
+    /// Marks up to `rows` more rows of `pass`, returning it if it isn't
+    /// done yet. A pass for a level that's gone is dropped.
+    pub fn explore(&mut self, mut pass: ExplorationPass, rows: usize) -> Option<ExplorationPass> {
+        let level = self
+            .dungeons
+            .get_mut(pass.dungeon_index)?
+            .levels
+            .get_mut(pass.level_index)?;
+        (!pass.advance(level, rows)).then_some(pass)
+    }
+
+    /// Opens the chest at `pos`, spilling what it holds onto the floor
+    /// around it for the player to pick up like any other loot. A trap
+    /// still on it springs first, and a mimic wakes and attacks.
+    fn open_chest(&mut self, pos: Position) {
+        let kind = self
+            .current_level()
+            .chest_contents
+            .get(&pos)
+            .map(|chest| chest.kind);
+        if kind == Some(ChestKind::Mimic) {
+            return self.wake_mimic(pos, true);
+        }
+        if let Some(ChestKind::Trapped(trap)) = kind {
+            if self.spring_trap(trap) {
+                return;
+            }
+        }
+        let Some(chest) = self.current_level_mut().take_chest(pos) else {
+            return;
+        };
+        self.update_visibility();
+        self.pending_sounds.push(SoundEvent::ChestOpen);
+        self.make_noise(Noise::Chest);
+
+        if chest.is_empty() {
+            self.log.push(LogCategory::Loot, "The chest is empty.");
+            return;
+        }
+
+        self.log.push(
+            LogCategory::Loot,
+            format!(
+                "You open the chest: {}. Its contents spill onto the floor.",
+                chest.summary()
+            ),
+        );
+        self.current_level_mut()
+            .drop_loot(pos, chest.gold, chest.items);
+    }
+
+    /// Looks the chest at `pos` over before opening it, as G does. A trap
+    /// found is left for the player to disarm or spring, and a mimic found
+    /// wakes without catching them; anything missed is opened regardless.
+    fn check_chest<R: Rng + ?Sized>(&mut self, pos: Position, rng: &mut R) {
+        let stats = &self.player.stats;
+        let (dexterity, wisdom) = (stats.dexterity, stats.wisdom);
+        let Some(chest) = self.current_level_mut().chest_contents.get_mut(&pos) else {
+            return self.open_chest(pos);
+        };
+        if !chest.inspect(dexterity, wisdom, rng) {
+            return self.open_chest(pos);
+        }
+        match chest.kind {
+            ChestKind::Mimic => self.wake_mimic(pos, false),
+            ChestKind::Trapped(trap) => {
+                let how = if self.player.class.class_type == ClassType::Warrior {
+                    "smash it open"
+                } else {
+                    "try disarming it"
+                };
+                self.log.push(
+                    LogCategory::System,
+                    format!(
+                        "You find a {} on the chest. Press G to {how}, or walk into the chest to open it anyway.",
+                        trap.name()
+                    ),
+                )
+            }
+            ChestKind::Plain => self.open_chest(pos),
+        }
+    }
+
+    /// Tries to disarm the trap found on the chest at `pos`, opening the
+    /// chest either way: safely if it worked, springing the trap if not
+    fn disarm_chest<R: Rng + ?Sized>(&mut self, pos: Position, rng: &mut R) {
+        let dexterity = self.player.stats.dexterity;
+        let Some(chest) = self.current_level_mut().chest_contents.get_mut(&pos) else {
+            return;
+        };
+        let Some(trap) = chest.found_trap() else {
+            return self.open_chest(pos);
+        };
+        let message = if rng.gen_bool(chest::disarm_chance(dexterity)) {
+            chest.kind = ChestKind::Plain;
+            format!("You disarm the {}.", trap.name())
+        } else {
+            format!("Your hand slips as you work at the {}!", trap.name())
+        };
+        self.log.push(LogCategory::System, message);
+        self.open_chest(pos);
+    }
+
+    /// Smashes open the chest at `pos`, whose trap has been found, by
+    /// strength rather than a careful hand: the trap is crushed if it goes
+    /// well and springs if not, and either way it's heard far off
+    fn smash_chest<R: Rng + ?Sized>(&mut self, pos: Position, rng: &mut R) {
+        let strength = self.player.stats.strength;
+        let Some(chest) = self.current_level_mut().chest_contents.get_mut(&pos) else {
+            return;
+        };
+        let Some(trap) = chest.found_trap() else {
+            return self.open_chest(pos);
+        };
+        let message = if rng.gen_bool(chest::smash_chance(strength)) {
+            chest.kind = ChestKind::Plain;
+            format!("You smash the chest open, crushing the {}.", trap.name())
+        } else {
+            format!(
+                "You bring your fist down, but the {} goes off!",
+                trap.name()
+            )
+        };
+        self.log.push(LogCategory::System, message);
+        self.make_noise(Noise::Smash);
+        self.open_chest(pos);
+    }
+
+    /// Springs `trap` on the player as they open a chest, ending the game
+    /// if it kills them. Returns whether it did.
+    fn spring_trap(&mut self, trap: ChestTrap) -> bool {
+        let damage = trap.damage(self.current_level().level_num);
+        self.player.health -= damage;
+        self.feel_hurt();
+        let message = match trap {
+            ChestTrap::Needle => {
+                format!("A needle jabs out of the lock! It does {damage} damage.")
+            }
+            ChestTrap::Poison => {
+                self.player.poisoned_turns = self.player.poisoned_turns.max(POISON_TURNS);
+                format!("Green gas bursts from the chest! It does {damage} damage, and you are poisoned.")
+            }
+            ChestTrap::Explosion => {
+                self.make_noise(Noise::Fight);
+                format!("A rune flares and the chest explodes! It does {damage} damage.")
+            }
+        };
+        self.log.push(LogCategory::Combat, message);
+        if self.player.is_alive() {
+            return false;
+        }
+        self.stats.killed_by = Some(trap.name().to_string());
+        self.game_state = GameState::GameOver;
+        true
+    }
+
+    /// Wakes the mimic passing as the chest at `pos`, which keeps hold of
+    /// everything the chest held. One woken by a hand on its lid starts a
+    /// fight; one spotted first is only roused.
+    fn wake_mimic(&mut self, pos: Position, grabbed: bool) {
+        let Some(hoard) = self.current_level_mut().take_chest(pos) else {
+            return;
+        };
+        let level = self.current_level().level_num + self.dungeon_difficulty() / 2 + 1;
+        let mut mimic = Enemy::mimic(level, hoard);
+        mimic.alert(self.player_position());
+        self.current_level_mut().enemies.insert(pos, mimic);
+        self.update_visibility();
+        if !grabbed {
+            self.log.push(
+                LogCategory::Combat,
+                "Teeth line the chest's lid: it's a mimic! It lurches awake.",
+            );
+            return;
+        }
+
+        self.log.push(
+            LogCategory::Combat,
+            "The chest's lid snaps open on rows of teeth: it's a mimic!",
+        );
+        self.opening_strike = false;
+        self.game_state = GameState::Combat(self.combat_participants(pos));
+        for enemy in self.combat_enemies() {
+            self.bestiary.record_encounter(&enemy);
+        }
+        self.log
+            .push(LogCategory::Combat, self.combat_start_message());
+        self.combat_started = true;
+    }
+
+    /// Leaves what the enemies slain in a fight dropped where they fell;
+    /// `positions` are where the fight's enemies stand
+    fn leave_loot(&mut self, positions: &[Position], loot: &[Loot]) {
+        for drop in loot {
+            if let Some(&pos) = positions.get(drop.enemy) {
+                self.current_level_mut()
+                    .drop_loot(pos, drop.gold, drop.items.clone());
+            }
+        }
+    }
+
+    /// Leaves a corpse where each enemy in `slain` fell, `positions` and
+    /// `enemies` being indexed alike. A Cleric's foes are left sanctified.
+    fn leave_corpses(&mut self, positions: &[Position], enemies: &[Enemy], slain: &[usize]) {
+        let turn = self.stats.turns;
+        let sanctified = self.player.class.class_type == ClassType::Cleric;
+        for &index in slain {
+            if let (Some(&pos), Some(enemy)) = (positions.get(index), enemies.get(index)) {
+                let corpse = Corpse {
+                    sanctified,
+                    ..Corpse::new(enemy, turn)
+                };
+                self.current_level_mut().corpses.insert(pos, corpse);
+            }
+        }
+    }
+
+    /// Sends fleeing the pack of each leader in `slain`, `positions` and
+    /// `enemies` being indexed alike, and returns a line for each pack
+    /// routed: "The goblins falter as their leader falls!"
+    fn rout_packs(
+        &mut self,
+        positions: &[Position],
+        enemies: &[Enemy],
+        slain: &[usize],
+    ) -> Vec<String> {
+        let mut messages = Vec::new();
+        for &index in slain {
+            let (Some(&pos), Some(leader)) = (positions.get(index), enemies.get(index)) else {
+                continue;
+            };
+            if !leader.leader {
+                continue;
+            }
+            let level = self.current_level_mut();
+            let pack = level.pack_of(pos, leader.aura_radius);
+            for member in &pack {
+                if let Some(enemy) = level.get_enemy_at_mut(member) {
+                    enemy.panic(PANIC_TURNS);
+                }
+            }
+            if !pack.is_empty() {
+                messages.push(format!(
+                    "The {} falter as their leader falls!",
+                    leader.enemy_type.plural()
+                ));
+            }
+        }
+        messages
+    }
+
+    /// Searches the corpse at `pos` if it hasn't been already, logging what
+    /// turned up
+    fn search_corpse_at(&mut self, pos: Position) {
+        let wisdom = self.player.stats.wisdom;
+        let Some(corpse) = self.dungeons[self.current_dungeon_index]
+            .current_level_mut()
+            .corpses
+            .get_mut(&pos)
+            .filter(|corpse| !corpse.searched)
+        else {
+            return;
+        };
+        let name = corpse.name.clone();
+        match corpse.search(wisdom, &mut self.rng.world) {
+            SearchFind::Gold(gold) => {
+                self.player.gold += gold;
+                self.stats.gold_earned += gold;
+                self.log.push(
+                    LogCategory::Loot,
+                    format!("You search the {name} corpse and find {gold} gold."),
+                );
+                self.pending_sounds.push(SoundEvent::ItemPickup);
+            }
+            SearchFind::Item(item) => {
+                self.log.push(
+                    LogCategory::Loot,
+                    format!(
+                        "You search the {name} corpse and find a {}.",
+                        item.name_with_stats()
+                    ),
+                );
+                // What doesn't fit is left lying beside it
+                if let Some(landed) = self.current_level_mut().drop_near(pos, item) {
+                    self.pick_up_at(landed);
+                }
+            }
+            SearchFind::Nothing => self.log.push(
+                LogCategory::Loot,
+                format!("You search the {name} corpse but find nothing of use."),
+            ),
+        }
+    }
+
+    /// Prays at the altar at `pos`, which answers only once: with a point in
+    /// a stat, with healing, or now and then with a curse that costs one
+    fn pray_at(&mut self, pos: Position) {
+        if !self.current_level_mut().spent_altars.insert(pos) {
+            return;
+        }
+        let message = match special_room::pray(&mut self.rng.world) {
+            Prayer::Boon(stat) => {
+                self.player.stats.modify_stat(stat, 1);
+                self.player.recalculate_derived_stats();
+                format!("A warm light answers your prayer. Your {stat} rises by 1!")
+            }
+            Prayer::Healed => {
+                self.player.health = self.player.max_health;
+                self.player.mana = self.player.max_mana;
+                "A gentle light washes over you. Your wounds close and your mind clears."
+                    .to_string()
+            }
+            Prayer::Curse(stat) => {
+                if self.player.stats.get(stat) > 1 {
+                    self.player.stats.modify_stat(stat, -1);
+                    self.player.recalculate_derived_stats();
+                }
+                format!("A cold whisper answers your prayer. Your {stat} falls by 1.")
+            }
+        };
+        self.log.push(LogCategory::System, message);
+    }
+
+    /// Picks up the gold lying at `pos`, if there is any, logging how much.
+    /// Returns whether there was.
+    fn collect_gold_at(&mut self, pos: Position) -> bool {
+        let Some(gold) = self.current_level_mut().take_gold_at(pos) else {
+            return false;
+        };
+        self.player.gold += gold;
+        self.stats.gold_earned += gold;
+        self.log
+            .push(LogCategory::Loot, format!("You pick up {gold} gold."));
+        self.pending_sounds.push(SoundEvent::ItemPickup);
+        true
+    }
+
+    /// Picks up the item at `pos`, logging what happened. Returns false if
+    /// it didn't fit in the inventory.
+    fn pick_up_at(&mut self, pos: Position) -> bool {
+        let Some(item) = self.current_level_mut().remove_item_at(&pos) else {
+            return false;
+        };
+        let was_encumbered = self.player.is_encumbered();
+        let add_result = InventoryManager::add_item(&mut self.player, item.clone());
+        if !add_result.success {
+            // Leave it where it was
+            self.current_level_mut().items.insert(pos, item);
+            self.log.push(LogCategory::Loot, add_result.message);
+            self.give_hint(HintId::FullInventory);
+            return false;
+        }
+        self.log.push(
+            LogCategory::Loot,
+            format!("You picked up {}.", item.name_with_stats()),
+        );
+        self.on_item_acquired(&item);
+        self.note_encumbrance(was_encumbered);
+        self.pending_sounds.push(SoundEvent::ItemPickup);
+        true
+    }
+
+    /// Warns the player when what they just picked up leaves them
+    /// encumbered
+    fn note_encumbrance(&mut self, was_encumbered: bool) {
+        if self.player.is_encumbered() && !was_encumbered {
+            self.log.push(LogCategory::Loot, ENCUMBERED_MESSAGE);
+        }
+    }
+
+    /// Logs the current level's enemy and loot budget, to help tune level
+    /// generation
+    #[cfg(debug_assertions)]
+    pub fn log_level_budget(&mut self) {
+        let enemies_left = self.current_level().enemy_points();
+        for line in self
+            .current_dungeon()
+            .current_budget()
+            .breakdown(enemies_left)
+        {
+            self.log.push(LogCategory::System, line);
+        }
+    }
+
+    /// Logs a reachability audit of the current level from the player's
+    /// tile, and files it for a bug report
+    #[cfg(debug_assertions)]
+    pub fn log_reachability(&mut self) {
+        let report = self.reachability_report();
+        for line in report.lines() {
+            self.log.push(LogCategory::System, line);
+        }
+        self.file_bug_report("Reachability audit", report.lines());
+    }
+
+    fn reachability_report(&self) -> ReachabilityReport {
+        self.current_level()
+            .validate_reachability(self.player_position())
+    }
+
+    /// Whether the player can't take a step or get to the stairs and exit
+    pub fn is_stuck(&self) -> bool {
+        !self.reachability_report().is_ok()
+    }
+
+    /// Pulls a stuck player free to the nearest open tile the stairs can be
+    /// walked to from, for a fifth of their health. The audit that found
+    /// them stuck is filed for a bug report.
+    pub fn unstick(&mut self) -> ActionResult {
+        let report = self.reachability_report();
+        if report.is_ok() {
+            return ActionResult::failure("You aren't stuck: the way on is open from here.");
+        }
+        self.file_bug_report("Player stuck", report.lines());
+        let Some(free) = self.current_level().nearest_unstuck_position(report.from) else {
+            return ActionResult::failure("There's nowhere to pull you free to.");
+        };
+        let cost = unstick_cost(self.player.health);
+        self.player.health -= cost;
+        self.current_level_mut().player_position = free;
+        self.update_visibility();
+        ActionResult::success(format!(
+            "You force your way free, losing {cost} HP in the struggle."
+        ))
+    }
+
+    /// Tells a Mage which way the stairs down (or the dungeon's exit) lie,
+    /// once a level and only while they're still undiscovered
+    pub fn sense_way_down(&mut self) -> ActionResult {
+        if self.player.class.class_type != ClassType::Mage {
+            return ActionResult::failure("Only a Mage can sense the way down.");
+        }
+        let level = self.current_level();
+        if level.way_sensed {
+            return ActionResult::failure("You've already sensed the way down on this level.");
+        }
+        let Some(way_down) = level.stairs_down_position.or(level.exit_position) else {
+            return ActionResult::failure("There's no way down from here to sense.");
+        };
+        if level
+            .get_tile(way_down.x, way_down.y)
+            .is_some_and(|tile| tile.explored)
+        {
+            return ActionResult::failure("You already know the way down.");
+        }
+        let here = level.player_position;
+        let (direction, arrow) = compass(way_down.x - here.x, way_down.y - here.y);
+        self.current_level_mut().way_sensed = true;
+        ActionResult::success(format!(
+            "You close your eyes and sense the way down to the {direction} {arrow}"
+        ))
+    }
+
+    /// Starts talking with the NPC at `pos`, if there's one there
+    pub fn start_talking(&mut self, pos: Position) {
+        if self.current_level().npcs.contains_key(&pos) {
+            self.game_state = GameState::Dialogue(pos);
+        }
+    }
+
+    /// The talk the player is in the middle of, for the front-end to show
+    pub fn dialogue(&self) -> Option<Dialogue> {
+        let GameState::Dialogue(pos) = self.game_state else {
+            return None;
+        };
+        self.current_level().npcs.get(&pos).map(Npc::dialogue)
+    }
+
+    /// Takes the NPC at `pos` up on their offer, which they make only once:
+    /// a hint marked on the map, something bought from them or an errand
+    /// for the quest journal
+    pub fn accept_offer(&mut self, pos: Position) -> ActionResult {
+        let Some(npc) = self.current_level().npcs.get(&pos) else {
+            return ActionResult::failure("There's no one there to talk to.");
+        };
+        let name = npc.kind.name();
+        if npc.spent {
+            return ActionResult::failure(format!("The {name} has nothing more for you."));
+        }
+        let result = match npc.offer.clone() {
+            NpcOffer::Hint => self.take_hint(name),
+            NpcOffer::Trade { item, price } => self.buy_from(name, item, price),
+            NpcOffer::Errand => self.take_errand(name),
+        };
+        if result.success {
+            if let Some(npc) = self.current_level_mut().npcs.get_mut(&pos) {
+                npc.spent = true;
+            }
+        }
+        result
+    }
+
+    /// Marks the way down on the map, or if that's been seen already the
+    /// nearest chest the player hasn't
+    fn take_hint(&mut self, name: &str) -> ActionResult {
+        let level = self.current_level();
+        let here = level.player_position;
+        let unseen = |pos: &Position| {
+            level
+                .get_tile(pos.x, pos.y)
+                .is_some_and(|tile| !tile.explored)
+        };
+        let way_down = level
+            .stairs_down_position
+            .or(level.exit_position)
+            .filter(unseen)
+            .map(|pos| (pos, "the way down"));
+        let chest = level
+            .chest_contents
+            .keys()
+            .copied()
+            .filter(|pos| {
+                unseen(pos)
+                    && level
+                        .get_tile(pos.x, pos.y)
+                        .is_some_and(|tile| tile.tile_type == TileType::Chest)
+            })
+            .min_by_key(|pos| {
+                let distance = (pos.x - here.x).abs().max((pos.y - here.y).abs());
+                (distance, pos.y, pos.x)
+            })
+            .map(|pos| (pos, "a chest"));
+        let Some((target, what)) = way_down.or(chest) else {
+            return ActionResult::failure(format!(
+                "The {name} shrugs. \"You've already seen all I know of this place.\""
+            ));
+        };
+        let (direction, arrow) = compass(target.x - here.x, target.y - here.y);
+        self.current_level_mut().reveal_around(target);
+        ActionResult::success(format!(
+            "The {name} marks {what} on your map, off to the {direction} {arrow}"
+        ))
+    }
+
+    /// Buys `item` from an NPC for `price` gold
+    fn buy_from(&mut self, name: &str, item: Item, price: u32) -> ActionResult {
+        if self.player.gold < price {
+            return ActionResult::failure(format!(
+                "You can't afford the {}: the {name} wants {price} gold.",
+                item.labelled_name()
+            ));
+        }
+        let was_encumbered = self.player.is_encumbered();
+        let added = InventoryManager::add_item(&mut self.player, item.clone());
+        if !added.success {
+            return added;
+        }
+        self.player.gold -= price;
+        self.on_item_acquired(&item);
+        self.note_encumbrance(was_encumbered);
+        ActionResult::success(format!(
+            "You hand over {price} gold, and the {name} gives you the {}.",
+            item.name_with_stats()
+        ))
+    }
+
+    /// Takes on a Ghostly Scholar's errand, adding it to the quest journal
+    fn take_errand(&mut self, name: &str) -> ActionResult {
+        let dungeon_index = self.current_dungeon_index;
+        let level_index = self.current_dungeon().current_level;
+        let errand = self.with_world_rng(|game, rng| {
+            Quest::scholar_errand(
+                &mut game.dungeons[dungeon_index],
+                dungeon_index,
+                level_index,
+                rng,
+            )
+        });
+        let Some(errand) = errand else {
+            return ActionResult::failure(format!(
+                "The {name} sighs. \"No, it's hopeless. Forget I asked.\""
+            ));
+        };
+        let message = format!("New quest: {}", errand.title);
+        self.quests.push(errand);
+        ActionResult::success(message)
+    }
+
+    /// The tiles beside the player that G reaches
+    fn beside_player(&self) -> [Position; 4] {
+        let here = self.player_position();
+        [(0, -1), (0, 1), (-1, 0), (1, 0)].map(|(dx, dy)| Position::new(here.x + dx, here.y + dy))
+    }
+
+    /// What G would do right now, looked for in order: loot underfoot, a
+    /// chest beside the player, someone to talk to or loot beside them, the
+    /// stairs underfoot, a gravestone or an altar beside them, then a corpse
+    /// underfoot or beside them
+    pub fn current_interaction(&self) -> Option<Interaction> {
+        let level = self.current_level();
+        let here = level.player_position;
+        let beside = self.beside_player();
+
+        let pick_up = |pos: Position| {
+            let item = level.items.get(&pos).map(|item| item.labelled_name());
+            let gold = level.gold.get(&pos).map(|gold| format!("{gold} gold"));
+            let what = match (item, gold) {
+                (Some(item), Some(gold)) => format!("{item} and {gold}"),
+                (item, gold) => item.or(gold)?,
+            };
+            Some(Interaction::PickUp { pos, what })
+        };
+        let is = |pos: Position, tile_type: TileType| {
+            level
+                .get_tile(pos.x, pos.y)
+                .is_some_and(|tile| tile.tile_type == tile_type)
+        };
+
+        pick_up(here)
+            .or_else(|| {
+                beside
+                    .into_iter()
+                    .find(|&pos| is(pos, TileType::Chest))
+                    .map(|pos| {
+                        let trapped = level.chest_contents.get(&pos).and_then(Chest::found_trap);
+                        match trapped {
+                            Some(_) if self.player.class.class_type == ClassType::Warrior => {
+                                Interaction::SmashChest(pos)
+                            }
+                            Some(_) => Interaction::DisarmTrap(pos),
+                            None => Interaction::OpenChest(pos),
+                        }
+                    })
+            })
+            .or_else(|| {
+                beside.into_iter().find_map(|pos| {
+                    level.npcs.get(&pos).map(|npc| Interaction::Talk {
+                        pos,
+                        name: npc.kind.name().to_string(),
+                    })
+                })
+            })
+            .or_else(|| beside.into_iter().find_map(pick_up))
+            .or_else(|| {
+                [true, false]
+                    .into_iter()
+                    .find(|&down| {
+                        let stairs = if down {
+                            TileType::StairsDown
+                        } else {
+                            TileType::StairsUp
+                        };
+                        is(here, stairs)
+                    })
+                    .map(|down| Interaction::TakeStairs { down })
+            })
+            .or_else(|| {
+                beside
+                    .into_iter()
+                    .find(|pos| level.graves.contains_key(pos))
+                    .map(Interaction::ReadGrave)
+            })
+            .or_else(|| {
+                beside
+                    .into_iter()
+                    .find(|&pos| is(pos, TileType::Altar) && !level.spent_altars.contains(&pos))
+                    .map(Interaction::Pray)
+            })
+            .or_else(|| {
+                std::iter::once(here).chain(beside).find_map(|pos| {
+                    level
+                        .corpses
+                        .get(&pos)
+                        .filter(|corpse| !corpse.searched)
+                        .map(|corpse| Interaction::Search {
+                            pos,
+                            name: corpse.name.clone(),
+                        })
+                })
+            })
+    }
+
+    /// Does whatever `current_interaction` says G does now: picks up loot,
+    /// opens a chest, starts a talk, takes the stairs, reads a grave or
+    /// searches a corpse.
+    /// The result goes in the log.
+    pub fn try_get_item(&mut self) {
+        match self.current_interaction() {
+            Some(Interaction::PickUp { pos, .. }) => {
+                self.collect_gold_at(pos);
+                if self.current_level().items.contains_key(&pos) {
+                    self.pick_up_at(pos);
+                }
+            }
+            Some(Interaction::OpenChest(pos)) => {
+                self.with_world_rng(|game, rng| game.check_chest(pos, rng));
+            }
+            Some(Interaction::DisarmTrap(pos)) => {
+                self.with_world_rng(|game, rng| game.disarm_chest(pos, rng));
+            }
+            Some(Interaction::SmashChest(pos)) => {
+                self.with_world_rng(|game, rng| game.smash_chest(pos, rng));
+            }
+            Some(Interaction::Talk { pos, .. }) => self.start_talking(pos),
+            Some(Interaction::TakeStairs { down }) => {
+                self.take_stairs(down);
+            }
+            Some(Interaction::ReadGrave(pos)) => {
+                if let Some(epitaph) = self.current_level().graves.get(&pos) {
+                    self.log.push(
+                        LogCategory::System,
+                        format!("The gravestone reads: {epitaph}"),
+                    );
+                }
+            }
+            Some(Interaction::Pray(pos)) => self.pray_at(pos),
+            Some(Interaction::Search { pos, .. }) => {
+                self.search_corpse_at(pos);
+            }
+            None => {
+                let here = self.player_position();
+                let level = self.current_level();
+                let searched = std::iter::once(here)
+                    .chain(self.beside_player())
+                    .find_map(|pos| level.corpses.get(&pos));
+                let prayed = self
+                    .beside_player()
+                    .iter()
+                    .any(|pos| level.spent_altars.contains(pos));
+                let message = match searched {
+                    Some(corpse) => {
+                        format!("You've already searched the {} corpse.", corpse.name)
+                    }
+                    None if prayed => "The altar has already answered you.".to_string(),
+                    None => "There's nothing here to pick up.".to_string(),
+                };
+                self.log.push(LogCategory::Loot, message);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run(options: &StartupOptions) {
+    // Initialize UI
+    let mut ui = UI::new();
+    if let Err(e) = ui.initialize() {
+        eprintln!("Error initializing UI: {e}");
+        return;
+    }
+
+    let mut saves = match &options.load_slot {
+        Some(slot) => SaveManager::in_slot(slot),
+        None => SaveManager::in_default_location(),
+    };
+    let files = DataFiles::in_default_location();
+    let (mut settings, mut warnings) = files.settings.load();
+    ui.apply_settings(&settings);
+    saves.set_autosave_interval(settings.autosave_interval);
+    let mut title = TitleState {
+        load_at_launch: options.load_slot.is_some(),
+        preset_player: options.preset_player(),
+        ..TitleState::default()
+    };
+    let mut audio = AudioManager::new();
+    audio.set_volume(settings.volume());
+
+    // A game lost to a crash last time is offered back before the title
+    let recovery = CrashRecovery::in_default_location();
+    if let Some(crashed) = recovery.summary() {
+        match ui.offer_crash_recovery(&crashed) {
+            Ok(true) => match recovery.restore() {
+                Ok(game) => title.recovered = Some(game),
+                Err(e) => title.notice = Some(e.to_string()),
+            },
+            Ok(false) => {
+                if let Err(e) = recovery.discard() {
+                    title.notice = Some(e.to_string());
+                }
+            }
+            Err(e) => {
+                eprintln!("Error reading key: {e}");
+                return;
+            }
+        }
+    }
+
+    // Each pass runs from the title screen until the game ends or the
+    // player quits back to the title
+    loop {
+        // Main menu loop; yields a saved game if the player continues one
+        let continued = match title_screen(&mut ui, &mut saves, &files, &mut settings, &mut title) {
+            TitleOutcome::Continue(game) => Some(*game),
+            TitleOutcome::NewGame => None,
+            TitleOutcome::Exit => {
+                if let Err(e) = ui.cleanup() {
+                    eprintln!("Error cleaning up UI: {e}");
+                }
+                return;
+            }
+        };
+
+        let fresh = continued.is_none();
+        let mut game = match continued {
+            Some(mut game) => {
+                game.log.push(
+                    LogCategory::System,
+                    format!("Welcome back, {}.", game.player.name),
+                );
+                game
+            }
+            None if title.daily.is_some() => {
+                // The date picks the class, so only the name is asked for
+                let challenge = title.daily.take().unwrap_or_else(DailyChallenge::today);
+                match ui.get_character_name() {
+                    Ok(name) => challenge.start(name),
+                    Err(e) => {
+                        eprintln!("Error during character creation: {e}");
+                        if let Err(e) = ui.cleanup() {
+                            eprintln!("Error cleaning up UI: {e}");
+                        }
+                        return;
+                    }
+                }
+            }
+            None => {
+                // Character creation, unless the character was given at launch
+                let creation = match title.preset_player.take() {
+                    Some(player) => Ok((player, false)),
+                    None => ui.character_creation().and_then(|player| {
+                        ui.choose_permadeath()
+                            .map(|permadeath| (player, permadeath))
+                    }),
+                };
+                let (player, permadeath) = match creation {
+                    Ok(choice) => choice,
+                    Err(e) => {
+                        eprintln!("Error during character creation: {e}");
+                        if let Err(e) = ui.cleanup() {
+                            eprintln!("Error cleaning up UI: {e}");
+                        }
+                        return;
+                    }
+                };
+
+                // Create new game; a recorded run needs a seed to be played
+                // back from
+                let mut start = options.clone();
+                if settings.record_replays {
+                    start.seed.get_or_insert_with(rand::random);
+                }
+                let mut game = Game::with_options(player, &start);
+                game.permadeath = permadeath;
+
+                // Show combat tutorial
+                if !options.skip_tutorial {
+                    if let Err(e) = ui.show_combat_tutorial() {
+                        eprintln!("Error showing combat tutorial: {e}");
+                        if let Err(e) = ui.cleanup() {
+                            eprintln!("Error cleaning up UI: {e}");
+                        }
+                        return;
+                    }
+                }
+
+                game
+            }
+        };
+
+        game.game_state = GameState::Playing;
+        game.apply_settings(&settings);
+        let bones_pool = files.bones.load();
+        game.haunt(bones_pool.clone());
+        if fresh && settings.record_replays {
+            game.replay = Replay::begin(&game, &settings, &bones_pool);
+        }
+        game.track_achievements(files.achievements.load());
+        for warning in std::mem::take(&mut warnings) {
+            game.log
+                .push(LogCategory::System, format!("Settings: {warning}"));
+        }
+
+        // Should the game panic, it's saved for the next launch to restore
+        let quit_to_menu = crash::guard(&mut game, &recovery, |game| {
+            // Set when the player quits from the pause menu
+            let mut quit_to_menu = false;
+
+            // Each pass plays until the game ends; a win can carry the character on
+            // into New Game+
+            loop {
+                // What each key does in each state; the I/O is left to this loop
+                let mut game_loop = GameLoop::new();
+                // Highlighted row of the inventory screen
+                let mut inventory_menu = MenuSelector::default();
+
+                // Game loop
+                'turns: while !matches!(game.game_state, GameState::GameOver | GameState::Victory) {
+                    // Update visibility
+                    game.update_visibility();
+
+                    audio.play_all(game.take_sounds());
+                    ui.show_effects(game.take_effects());
+                    save_achievements(&files.achievements, game);
+                    for report in game.take_bug_reports() {
+                        if let Err(e) = crash::log_report(report.heading, &report.lines) {
+                            game.log.push(
+                                LogCategory::System,
+                                format!("Could not write the bug report: {e}"),
+                            );
+                        }
+                    }
+
+                    // Permadeath games save themselves every few turns
+                    match saves.autosave_if_due(game) {
+                        Ok(true) => game.log.push(LogCategory::System, "Game autosaved."),
+                        Ok(false) => {}
+                        Err(e) => game
+                            .log
+                            .push(LogCategory::System, format!("Autosave failed: {e}")),
+                    }
+
+                    // Only the cells that changed go out, so every pass can draw
+                    let prompt = game.current_interaction().map(|i| i.prompt());
+                    if let Err(e) = ui.draw_game_screen(
+                        &game.player,
+                        game.current_level(),
+                        game.current_dungeon(),
+                        &game.log,
+                        prompt.as_deref(),
+                    ) {
+                        eprintln!("Error drawing game screen: {e}");
+                        break;
+                    }
+
+                    // Turn the key into an input for the state machine; screens
+                    // that only show something are drawn straight away
+                    let transition = match game.game_state {
+                        GameState::Playing => match ui.wait_for_key_or_resize() {
+                            // The terminal was resized; the next pass redraws to fit
+                            Ok(None) => Transition::default(),
+                            Ok(Some(key_event)) => {
+                                match playing_input(
+                                    &mut ui,
+                                    game,
+                                    &mut audio,
+                                    &mut settings,
+                                    &files,
+                                    key_event,
+                                ) {
+                                    Ok(input) => input.map_or_else(Transition::default, |input| {
+                                        game_loop.handle(game, input)
+                                    }),
+                                    Err(message) => {
+                                        eprintln!("{message}");
+                                        break;
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                eprintln!("Error reading key: {e}");
+                                break;
+                            }
+                        },
+                        GameState::Combat(_) => {
+                            // Make sure the enemies are still there
+                            let Some(round) = game_loop.combat_round(game) else {
+                                continue;
+                            };
+
+                            // A new fight starts with a clean message list
+                            if let Some(opening) = round.opening {
+                                ui.clear_messages();
+                                ui.add_message(opening);
+                            }
+
+                            // Draw the combat screen
+                            if let Err(e) = ui.draw_combat_screen(
+                                &game.player,
+                                &round.enemies,
+                                game_loop.combat_target,
+                                &round.context,
+                            ) {
+                                eprintln!("Error drawing combat screen: {e}");
+                                break;
+                            }
+
+                            // Get the combat action from the user
+                            match ui.handle_combat_action(
+                                &game.player,
+                                &round.enemies,
+                                &mut game_loop.combat_target,
+                                &round.context,
+                            ) {
+                                Ok(action) => game_loop.handle(game, Input::Combat(action)),
+                                Err(e) => {
+                                    eprintln!("Error handling combat action: {e}");
+                                    break;
+                                }
+                            }
+                        }
+                        GameState::Inventory => {
+                            inventory_menu.set_len(InventoryManager::get_item_count(&game.player));
+                            if let Err(e) = ui.draw_inventory_screen(
+                                &game.player,
+                                &inventory_menu,
+                                game.can_enchant(),
+                            ) {
+                                eprintln!("Error drawing inventory screen: {e}");
+                                break;
+                            }
+
+                            match ui.wait_for_key_or_resize() {
+                                Ok(None) => Transition::default(),
+                                Ok(Some(key_event)) => {
+                                    match inventory_input(
+                                        &mut ui,
+                                        game,
+                                        &mut inventory_menu,
+                                        key_event,
+                                    ) {
+                                        Ok(input) => input
+                                            .map_or_else(Transition::default, |input| {
+                                                game_loop.handle(game, input)
+                                            }),
+                                        Err(message) => {
+                                            eprintln!("{message}");
+                                            break;
+                                        }
+                                    }
+                                }
+                                Err(e) => {
+                                    eprintln!("Error reading key: {e}");
+                                    break;
+                                }
+                            }
+                        }
+                        GameState::Character => {
+                            if let Err(e) = ui.draw_character_screen(
+                                &game.player,
+                                game.stats.turns,
+                                game.ng_plus_counter,
+                                game.run_label().as_deref(),
+                                game.difficulty_note().as_deref(),
+                            ) {
+                                eprintln!("Error drawing character screen: {e}");
+                                break;
+                            }
+
+                            let inputs = ui
+                                .wait_for_key()
+                                .map_err(|e| format!("Error reading key: {e}"))
+                                .and_then(|key_event| character_input(&mut ui, game, key_event));
+                            match inputs {
+                                Ok(inputs) => {
+                                    inputs.into_iter().fold(Transition::default(), |_, input| {
+                                        game_loop.handle(game, input)
+                                    })
+                                }
+                                Err(message) => {
+                                    eprintln!("{message}");
+                                    break;
+                                }
+                            }
+                        }
+                        GameState::Paused => {
+                            match ui.pause_menu(&PauseOption::menu(!game.permadeath)) {
+                                Ok(choice) => game_loop.handle(game, Input::Choose(choice)),
+                                Err(e) => {
+                                    eprintln!("Error drawing pause menu: {e}");
+                                    break;
+                                }
+                            }
+                        }
+                        GameState::Dialogue(_) => match game.dialogue() {
+                            Some(dialogue) => match ui.show_dialogue(&dialogue) {
+                                Ok(true) => game_loop.handle(game, Input::Accept),
+                                Ok(false) => game_loop.handle(game, Input::Back),
+                                Err(e) => {
+                                    eprintln!("Error drawing dialogue: {e}");
+                                    break;
+                                }
+                            },
+                            None => game_loop.handle(game, Input::Back),
+                        },
+                        _ => Transition::default(),
+                    };
+
+                    // Then do what the state machine left to this front-end
+                    let mut played_combat_turn = false;
+                    for effect in transition.effects {
+                        match effect {
+                            Effect::CombatTurn {
+                                result,
+                                target,
+                                health_before,
+                            } => {
+                                audio.play_all(game.take_sounds());
+                                ui.show_effects(game.take_effects());
+                                // Play the turn's messages back one at a time
+                                if let Err(e) = ui.play_combat_turn(
+                                    &game.player,
+                                    &game.combat_enemies(),
+                                    target,
+                                    health_before,
+                                    &result,
+                                ) {
+                                    eprintln!("Error drawing combat messages: {e}");
+                                    break 'turns;
+                                }
+                                played_combat_turn = true;
+                            }
+                            Effect::LevelUp => {
+                                // Spend the stat points earned from leveling up
+                                match ui.draw_level_up_screen(&game.player) {
+                                    Ok(stats) => {
+                                        for stat in stats {
+                                            game_loop.handle(game, Input::AllocateStat(stat));
+                                        }
+                                    }
+                                    Err(e) => {
+                                        eprintln!("Error drawing level up screen: {e}");
+                                        break 'turns;
+                                    }
+                                }
+                            }
+                            Effect::Save => {
+                                let message = save_game(&mut saves, game);
+                                game.log.push(LogCategory::System, message);
+                            }
+                            Effect::ShowOptions => {
+                                if let Err(e) = ui.options_screen(&mut settings) {
+                                    eprintln!("Error drawing options: {e}");
+                                    break 'turns;
+                                }
+                                game.apply_settings(&settings);
+                                saves.set_autosave_interval(settings.autosave_interval);
+                                if let Err(e) = files.settings.save(&settings) {
+                                    game.log.push(
+                                        LogCategory::System,
+                                        format!("Settings not saved: {e}"),
+                                    );
+                                }
+                            }
+                            Effect::ExportMap => {
+                                let message =
+                                    MapExporter::in_default_location().export_message(game);
+                                game.log.push(LogCategory::System, message);
+                            }
+                            Effect::ShowHelp => {
+                                if let Err(e) = ui.show_help() {
+                                    eprintln!("Error showing help: {e}");
+                                    break 'turns;
+                                }
+                            }
+                            Effect::AskToQuit => {
+                                let options = PauseOption::menu(!game.permadeath);
+                                match ui.confirm_quit(&options, quit_question(game.permadeath)) {
+                                    Ok(true) => {
+                                        if let Err(e) = saves.save_on_quit(game) {
+                                            eprintln!("Error saving game: {e}");
+                                        }
+                                        quit_to_menu = true;
+                                        break 'turns;
+                                    }
+                                    Ok(false) => {}
+                                    Err(e) => {
+                                        eprintln!("Error reading key: {e}");
+                                        break 'turns;
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    // Check if combat is over
+                    if played_combat_turn {
+                        game_loop.handle(game, Input::CombatShown);
+                    }
+                }
+
+                // Reaching the exit or dying ends the loop before its sound is played
+                audio.play_all(game.take_sounds());
+                game.check_achievements();
+                save_achievements(&files.achievements, game);
+
+                // Handle game end
+                if matches!(game.game_state, GameState::GameOver | GameState::Victory) {
+                    keep_replay(&files.replays, game);
+                }
+                match game.game_state {
+                    GameState::GameOver => {
+                        // A permadeath character's save goes with them
+                        if let Err(e) = saves.on_player_death(game) {
+                            eprintln!("Error deleting save: {e}");
+                        }
+                        let placement = record_run(&files.hall, game);
+                        // Bones that can't be written just never turn up
+                        let _ = files.bones.record(BonesRecord::from_game(game));
+                        if let Err(e) = ui.draw_game_over(
+                            &game.player,
+                            &game.stats,
+                            placement.as_ref(),
+                            game.run_label().as_deref(),
+                        ) {
+                            eprintln!("Error drawing game over screen: {e}");
+                        }
+                    }
+                    GameState::Victory => {
+                        let placement = record_run(&files.hall, game);
+                        match ui.draw_victory_screen(
+                            &game.player,
+                            &game.quests,
+                            &game.stats,
+                            placement.as_ref(),
+                            game.run_label().as_deref(),
+                        ) {
+                            Ok(true) => {
+                                game.start_new_game_plus();
+                                continue;
+                            }
+                            Ok(false) => {}
+                            Err(e) => eprintln!("Error drawing victory screen: {e}"),
+                        }
+                    }
+                    _ => {}
+                }
+                break;
+            }
+            quit_to_menu
+        });
+
+        if !quit_to_menu {
+            break;
+        }
+    }
+
+    // Clean up
+    if let Err(e) = ui.cleanup() {
+        eprintln!("Error cleaning up UI: {e}");
+    }
+}
+
+/// The files the terminal game keeps besides the save
+#[cfg(not(target_arch = "wasm32"))]
+struct DataFiles {
+    hall: LeaderboardFile,
+    bones: BonesFile,
+    replays: ReplayFile,
+    achievements: AchievementsFile,
+    settings: SettingsFile,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DataFiles {
+    fn in_default_location() -> Self {
+        DataFiles {
+            hall: LeaderboardFile::in_default_location(),
+            bones: BonesFile::in_default_location(),
+            replays: ReplayFile::in_default_location(),
+            achievements: AchievementsFile::in_default_location(),
+            settings: SettingsFile::in_default_location(),
+        }
+    }
+}
+
+/// What the title screen carries from one visit to the next
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+struct TitleState {
+    /// Set by --load, and used up by the first game started
+    load_at_launch: bool,
+    /// Set by --class or a build code, and used up by the first game started
+    preset_player: Option<Player>,
+    /// A game lost to a crash that the player asked to have back
+    recovered: Option<Game>,
+    /// Set when the daily challenge is picked
+    daily: Option<DailyChallenge>,
+    /// Shown under the menu, for what went wrong last
+    notice: Option<String>,
+}
+
+/// Where the title screen leads
+#[cfg(not(target_arch = "wasm32"))]
+enum TitleOutcome {
+    Continue(Box<Game>),
+    NewGame,
+    Exit,
+}
+
+/// Shows the title screen until the player picks a game to play or leaves
+#[cfg(not(target_arch = "wasm32"))]
+fn title_screen(
+    ui: &mut UI,
+    saves: &mut SaveManager,
+    files: &DataFiles,
+    settings: &mut Settings,
+    title: &mut TitleState,
+) -> TitleOutcome {
+    loop {
+        if let Some(game) = title.recovered.take() {
+            return TitleOutcome::Continue(Box::new(game));
+        }
+        if std::mem::take(&mut title.load_at_launch) {
+            match saves.load() {
+                Ok(game) => return TitleOutcome::Continue(Box::new(game)),
+                Err(e) => title.notice = Some(e.to_string()),
+            }
+        }
+        if title.preset_player.is_some() {
+            return TitleOutcome::NewGame;
+        }
+
+        let choice = match ui.title_menu(saves.summary().as_ref(), title.notice.as_deref()) {
+            Ok(choice) => choice,
+            Err(e) => {
+                eprintln!("Error reading key: {e}");
+                return TitleOutcome::Exit;
+            }
+        };
+        match choice {
+            TitleOption::NewGame => return TitleOutcome::NewGame,
+            TitleOption::DailyChallenge => {
+                // Each day's challenge can be taken once
+                let challenge = DailyChallenge::today();
+                match files.hall.load().daily_result(&challenge.date) {
+                    Some(result) => title.notice = Some(challenge.already_taken(result)),
+                    None => {
+                        title.daily = Some(challenge);
+                        return TitleOutcome::NewGame;
+                    }
+                }
+            }
+            TitleOption::FromBuildCode => match ui.prompt_build_code() {
+                Ok(Some(code)) => match Player::import_code(&code) {
+                    Ok(player) => {
+                        // The build is a template for a fresh level 1 character
+                        title.preset_player = Some(player.at_first_level());
+                        return TitleOutcome::NewGame;
+                    }
+                    Err(e) => title.notice = Some(format!("That build code won't load: {e}")),
+                },
+                Ok(None) => {}
+                Err(e) => {
+                    eprintln!("Error reading key: {e}");
+                    return TitleOutcome::Exit;
+                }
+            },
+            TitleOption::Continue => {
+                if saves.has_save() {
+                    match saves.load() {
+                        Ok(game) => return TitleOutcome::Continue(Box::new(game)),
+                        Err(e) => title.notice = Some(e.to_string()),
+                    }
+                }
+            }
+            TitleOption::HallOfFame => {
+                let replay = files.replays.load();
+                match ui.draw_hall_of_fame(
+                    &files.hall.load(),
+                    replay.as_ref().map(Replay::summary).as_deref(),
+                ) {
+                    Ok(true) => match replay.map(|replay| watch_replay(ui, replay)) {
+                        Some(Ok(result)) => title.notice = result,
+                        Some(Err(e)) => {
+                            eprintln!("Error playing the replay: {e}");
+                            return TitleOutcome::Exit;
+                        }
+                        None => {}
+                    },
+                    Ok(false) => {}
+                    Err(e) => {
+                        eprintln!("Error drawing hall of fame: {e}");
+                        return TitleOutcome::Exit;
+                    }
+                }
+            }
+            TitleOption::Achievements => {
+                if let Err(e) = ui.draw_achievements(&files.achievements.load()) {
+                    eprintln!("Error drawing achievements: {e}");
+                    return TitleOutcome::Exit;
+                }
+            }
+            TitleOption::Options => {
+                if let Err(e) = ui.options_screen(settings) {
+                    eprintln!("Error drawing options: {e}");
+                    return TitleOutcome::Exit;
+                }
+                saves.set_autosave_interval(settings.autosave_interval);
+                title.notice = files
+                    .settings
+                    .save(settings)
+                    .err()
+                    .map(|e| format!("Settings not saved: {e}"));
+            }
+            TitleOption::Help => {
+                if let Err(e) = ui.show_help() {
+                    eprintln!("Error showing help: {e}");
+                    return TitleOutcome::Exit;
+                }
+            }
+            TitleOption::Exit => return TitleOutcome::Exit,
+        }
+    }
+}
+
+/// What a key pressed on the map asks of the state machine, if anything.
+/// Keys that only show something show it here; Err is the message for a
+/// screen that couldn't be drawn.
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg_attr(not(feature = "audio"), allow(unused_variables))]
+fn playing_input(
+    ui: &mut UI,
+    game: &mut Game,
+    audio: &mut AudioManager,
+    settings: &mut Settings,
+    files: &DataFiles,
+    key_event: crossterm::event::KeyEvent,
+) -> Result<Option<Input>, String> {
+    let input = match key_event.code {
+        // A fight is handled in the next loop iteration
+        KeyCode::Up => Some(Input::Move { dx: 0, dy: -1 }),
+        KeyCode::Down => Some(Input::Move { dx: 0, dy: 1 }),
+        KeyCode::Left => Some(Input::Move { dx: -1, dy: 0 }),
+        KeyCode::Right => Some(Input::Move { dx: 1, dy: 0 }),
+        KeyCode::Char(key @ ('>' | '<')) => Some(Input::Stairs { down: key == '>' }),
+        KeyCode::Char('i') => Some(Input::OpenInventory),
+        KeyCode::Char('c') => Some(Input::OpenCharacter),
+        KeyCode::Char('j') | KeyCode::Char('J') => {
+            ui.show_quest_journal(&game.quests)
+                .map_err(|e| format!("Error showing quest journal: {e}"))?;
+            None
+        }
+        KeyCode::Char('b') | KeyCode::Char('B') => {
+            ui.show_bestiary(&game.bestiary)
+                .map_err(|e| format!("Error showing bestiary: {e}"))?;
+            None
+        }
+        KeyCode::Char('l') | KeyCode::Char('L') => {
+            ui.show_message_log(&game.log)
+                .map_err(|e| format!("Error showing message log: {e}"))?;
+            None
+        }
+        KeyCode::Char('x') | KeyCode::Char('X') => {
+            let description = ui
+                .look_around(
+                    &game.player,
+                    game.current_level(),
+                    game.current_dungeon(),
+                    &game.log,
+                )
+                .map_err(|e| format!("Error in look mode: {e}"))?;
+            if let Some(description) = description {
+                game.log.push(LogCategory::System, description);
+            }
+            None
+        }
+        KeyCode::Char('f') | KeyCode::Char('F') => match game.area_ability() {
+            None => {
+                game.log.push(
+                    LogCategory::System,
+                    "You know no spell that reaches across the map.",
+                );
+                None
+            }
+            Some((index, name, target)) => {
+                let aim = match target {
+                    AreaTarget::Caster => Some(game.player_position()),
+                    AreaTarget::Tile { .. } => ui
+                        .aim_area_ability(
+                            &game.player,
+                            game.current_level(),
+                            game.current_dungeon(),
+                            &game.log,
+                            name,
+                        )
+                        .map_err(|e| format!("Error aiming {name}: {e}"))?,
+                };
+                aim.map(|aim| Input::CastArea { index, aim })
+            }
+        },
+        KeyCode::Char(key @ '1'..='5') => Some(Input::Hotbar(key as usize - '1' as usize)),
+        KeyCode::Tab => {
+            ui.show_threats = !ui.show_threats;
+            let state = if ui.show_threats { "on" } else { "off" };
+            game.log
+                .push(LogCategory::System, format!("Threat overlay {state}"));
+            None
+        }
+        KeyCode::Char('?') => {
+            ui.show_help()
+                .map_err(|e| format!("Error showing help: {e}"))?;
+            None
+        }
+        #[cfg(debug_assertions)]
+        KeyCode::Char('`') => {
+            game.log_level_budget();
+            game.log_reachability();
+            None
+        }
+        KeyCode::Char('u') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            Some(Input::Unstick)
+        }
+        // Try to get item at current position or adjacent chest
+        KeyCode::Char('g') => Some(Input::GetItem),
+        KeyCode::Char('e') | KeyCode::Char('E') => Some(Input::Sense),
+        KeyCode::Char('M') => Some(Input::ExportMap),
+        #[cfg(feature = "audio")]
+        KeyCode::Char('v') | KeyCode::Char('V') => {
+            let volume = audio.cycle_volume();
+            game.log
+                .push(LogCategory::System, format!("Sound: {volume}"));
+            settings.volume = volume.name().to_string();
+            if let Err(e) = files.settings.save(settings) {
+                game.log
+                    .push(LogCategory::System, format!("Settings not saved: {e}"));
+            }
+            None
+        }
+        KeyCode::F(5) => Some(Input::Save),
+        KeyCode::Esc => Some(Input::Pause),
+        KeyCode::Char('q') => Some(Input::Quit),
+        _ => None,
+    };
+    Ok(input)
+}
+
+/// What a key pressed on the inventory screen asks of the state machine,
+/// if anything, after any prompt it needs. Err is the message for a screen
+/// that couldn't be drawn.
+#[cfg(not(target_arch = "wasm32"))]
+fn inventory_input(
+    ui: &mut UI,
+    game: &Game,
+    inventory_menu: &mut MenuSelector,
+    key_event: crossterm::event::KeyEvent,
+) -> Result<Option<Input>, String> {
+    let read_error = |e: std::io::Error| format!("Error reading key: {e}");
+    let input = match key_event.code {
+        KeyCode::Char('d') | KeyCode::Char('D') => ui
+            .prompt_item_number(
+                "Drop which item? (1-9, Enter: highlighted)",
+                inventory_menu.selected(),
+            )
+            .map_err(read_error)?
+            .map(Input::DropItem),
+        KeyCode::Char('n') | KeyCode::Char('N') if game.can_enchant() => {
+            let index = ui
+                .prompt_item_number(
+                    "Enchant which item? (1-9, Enter: highlighted)",
+                    inventory_menu.selected(),
+                )
+                .map_err(read_error)?;
+            match index.map(|index| (index, game.enchant_offer(index))) {
+                Some((index, Some(offer))) => ui
+                    .confirm_enchant(&offer)
+                    .map_err(|e| format!("Error drawing enchanting: {e}"))?
+                    .then_some(Input::Enchant(index)),
+                Some((index, None)) => Some(Input::Enchant(index)),
+                None => None,
+            }
+        }
+        KeyCode::Char('a') | KeyCode::Char('A') => ui
+            .prompt_hotbar_slot()
+            .map_err(read_error)?
+            .map(|slot| Input::AssignHotbar {
+                index: inventory_menu.selected(),
+                slot,
+            }),
+        KeyCode::Char('r') | KeyCode::Char('R') => ui
+            .choose_recipe(&game.player)
+            .map_err(|e| format!("Error drawing crafting: {e}"))?
+            .map(Input::Craft),
+        KeyCode::Char('x') | KeyCode::Char('X') => {
+            let index = ui
+                .prompt_item_number(
+                    "Inspect which item? (1-9, Enter: highlighted)",
+                    inventory_menu.selected(),
+                )
+                .map_err(read_error)?;
+            if let Some(details) =
+                index.and_then(|index| InventoryManager::get_item_details(&game.player, index))
+            {
+                ui.draw_item_details(&details)
+                    .and_then(|_| ui.wait_for_key())
+                    .map_err(|e| format!("Error drawing item details: {e}"))?;
+            }
+            None
+        }
+        KeyCode::Char('e') | KeyCode::Esc => Some(Input::Back),
+        code => match inventory_menu.handle_key(code) {
+            MenuInput::Chosen(index) => Some(Input::UseItem(index)),
+            _ => None,
+        },
+    };
+    Ok(input)
+}
+
+/// What a key pressed on the character screen asks of the state machine:
+/// U spends stat points, B shows the build code and any other key returns
+/// to the game. Err is the message for a screen that couldn't be drawn.
+#[cfg(not(target_arch = "wasm32"))]
+fn character_input(
+    ui: &mut UI,
+    game: &Game,
+    key_event: crossterm::event::KeyEvent,
+) -> Result<Vec<Input>, String> {
+    match key_event.code {
+        KeyCode::Char('b') | KeyCode::Char('B') => {
+            ui.show_build_code(&game.player.export_code())
+                .map_err(|e| format!("Error showing build code: {e}"))?;
+            Ok(Vec::new())
+        }
+        KeyCode::Char('u') | KeyCode::Char('U') if game.player.unspent_stat_points > 0 => {
+            let stats = ui
+                .draw_level_up_screen(&game.player)
+                .map_err(|e| format!("Error drawing level up screen: {e}"))?;
+            Ok(stats.into_iter().map(Input::AllocateStat).collect())
+        }
+        _ => Ok(vec![Input::Back]),
+    }
+}
+
+/// Saves the game when asked to, returning the line for the message log;
+/// permadeath games only save themselves
+#[cfg(not(target_arch = "wasm32"))]
+fn save_game(saves: &mut SaveManager, game: &Game) -> String {
+    if game.permadeath {
+        return "Permadeath games save automatically.".to_string();
+    }
+    match saves.save(game) {
+        Ok(()) => "Game saved.".to_string(),
+        Err(e) => format!("Save failed: {e}"),
+    }
+}
+
+/// The compass point nearest the direction `dx`, `dy` on the map, where y
+/// grows southwards, and an arrow pointing that way
+fn compass(dx: i32, dy: i32) -> (&'static str, char) {
+    // Within about 22.5 degrees of an axis counts as straight along it
+    let (horizontal, vertical) = (dx.abs() > 2 * dy.abs(), dy.abs() > 2 * dx.abs());
+    match (dx.signum(), dy.signum()) {
+        (_, -1) if vertical => ("north", '↑'),
+        (_, 1) if vertical => ("south", '↓'),
+        (1, _) if horizontal => ("east", '→'),
+        (-1, _) if horizontal => ("west", '←'),
+        (1, -1) => ("north-east", '↗'),
+        (1, _) => ("south-east", '↘'),
+        (-1, -1) => ("north-west", '↖'),
+        (-1, _) => ("south-west", '↙'),
+        (0, 1) => ("south", '↓'),
+        _ => ("north", '↑'),
+    }
+}
+
+/// Stops recording a finished run and keeps it for the hall of fame to
+/// play back; a failed write only costs the replay
+#[cfg(not(target_arch = "wasm32"))]
+fn keep_replay(replays: &ReplayFile, game: &mut Game) {
+    let Some(mut replay) = game.replay.take() else {
+        return;
+    };
+    replay.finish(game);
+    if let Err(e) = replays.save(&replay) {
+        eprintln!("Error saving the replay: {e}");
+    }
+}
+
+/// Plays a recorded run back on the map and combat screens until it ends
+/// or Esc is pressed. Space pauses and + and - change the speed. Returns a
+/// notice for the title screen if the replay went out of step.
+#[cfg(not(target_arch = "wasm32"))]
+fn watch_replay(ui: &mut UI, replay: Replay) -> std::io::Result<Option<String>> {
+    let mut player = ReplayPlayer::new(replay);
+    loop {
+        let game = &player.game;
+        let status = format!(
+            "Replay: {} | Space: pause, +/-: speed, Esc: stop",
+            player.progress()
+        );
+        match game.game_state {
+            GameState::Combat(_) => {
+                ui.draw_combat_screen(
+                    &game.player,
+                    &game.combat_enemies(),
+                    0,
+                    &game.combat_context(),
+                )?;
+            }
+            _ => ui.draw_game_screen(
+                &game.player,
+                game.current_level(),
+                game.current_dungeon(),
+                &game.log,
+                Some(&status),
+            )?,
+        }
+
+        let wait = std::time::Duration::from_millis(player.step_millis);
+        match ui.poll_key(wait)?.map(|key| key.code) {
+            Some(KeyCode::Esc) => return Ok(None),
+            Some(KeyCode::Char(' ')) => player.paused = !player.paused,
+            Some(KeyCode::Char('+') | KeyCode::Char('=')) => player.faster(),
+            Some(KeyCode::Char('-')) => player.slower(),
+            _ => {}
+        }
+        if player.paused {
+            continue;
+        }
+        if player.is_finished() {
+            let notice = player.check_ending().err().map(|e| e.to_string());
+            ui.draw_game_screen(
+                &player.game.player,
+                player.game.current_level(),
+                player.game.current_dungeon(),
+                &player.game.log,
+                Some("The replay is over. Press any key..."),
+            )?;
+            ui.wait_for_key()?;
+            return Ok(notice);
+        }
+        if let Err(desync) = player.step() {
+            return Ok(Some(desync.to_string()));
+        }
+    }
+}
+
+/// Adds the finished game to the hall of fame; a failed write only costs
+/// the placement line on the end screen
+#[cfg(not(target_arch = "wasm32"))]
+fn record_run(hall: &LeaderboardFile, game: &Game) -> Option<Placement> {
+    match hall.record_game(game) {
+        Ok(placement) => Some(placement),
+        Err(e) => {
+            eprintln!("Error recording the run: {e}");
+            None
+        }
+    }
+}
+
+/// Writes the achievements if anything was unlocked since the last call;
+/// unlocks that can't be written are earned again in a later run
+#[cfg(not(target_arch = "wasm32"))]
+fn save_achievements(file: &AchievementsFile, game: &mut Game) {
+    let Some(profile) = game.take_unlocked_achievements() else {
+        return;
+    };
+    if let Err(e) = file.save(profile) {
+        game.log
+            .push(LogCategory::System, format!("Achievements not saved: {e}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bones::BonesRecord;
+    use crate::character::companion::KNOCKOUT_TURNS;
+    use crate::character::ClassType;
+    use crate::item::consumable::{HASTE_TURNS, TORCH_TURNS};
+    use crate::item::{Consumable, Equipment};
+    use crate::world::enemy::EnemyType;
+    use crate::world::light::DARK_VIEW_RADIUS;
+    use crate::world::weather::{RAIN_SIGHT_PENALTY, RAIN_TURNS};
+    use crate::world::{Chest, DungeonType, EnemyKind, Tile};
+    use rand::rngs::mock::StepRng;
+
+    fn test_game() -> Game {
+        let player = Player::new("Tester".to_string(), ClassType::Warrior);
+        let mut game = Game::new(player);
+        game.game_state = GameState::Playing;
+        // Weather turning up by chance would upset the tests around it, and
+        // so would hints about whatever happens to be in sight
+        game.ambient_events = false;
+        game.hints = false;
+        let pos = game.player_position();
+        game.current_level_mut().items.remove(&pos);
+        game
+    }
+
+    /// Texts of the log entries added since the last call
+    fn messages(game: &mut Game) -> Vec<String> {
+        game.log
+            .take_unread()
+            .into_iter()
+            .map(|entry| entry.text)
+            .collect()
+    }
+
+    fn give(game: &mut Game, item: Item) -> usize {
+        InventoryManager::add_item(&mut game.player, item);
+        InventoryManager::get_item_count(&game.player) - 1
+    }
+
+    #[test]
+    fn test_deferred_exploration_waits_for_the_front_end() {
+        let mut game = test_game();
+        game.defer_exploration = true;
+        for row in &mut game.current_level_mut().tiles {
+            for tile in row.iter_mut() {
+                tile.explored = false;
+            }
+        }
+        let pos = Position::new(10, 10);
+        game.current_level_mut().player_position = pos;
+        game.update_visibility();
+
+        // Sight is worked out at once; the wider area is left for later
+        let explored = |game: &Game, x: i32| game.current_level().tiles[10][x as usize].explored;
+        assert!(explored(&game, 11));
+        assert!(!explored(&game, 35));
+
+        let mut pass = game.take_exploration();
+        assert!(pass.is_some() && game.take_exploration().is_none());
+        let mut frames = 0;
+        while let Some(unfinished) = pass {
+            pass = game.explore(unfinished, 2);
+            frames += 1;
+        }
+        assert!(frames > 1);
+        assert!(explored(&game, 35));
+
+        // Without deferring, it's all done at once
+        game.defer_exploration = false;
+        game.current_level_mut().player_position = Position::new(40, 20);
+        game.update_visibility();
+        assert!(game.take_exploration().is_none());
+        assert!(game.current_level().tiles[20][65].explored);
+    }
+
+    #[test]
+    fn test_drop_places_item_on_tile_and_can_be_picked_up() {
+        let mut game = test_game();
+        let index = give(
+            &mut game,
+            Item::Equipment(Equipment::generate_with_rng(
+                1,
+                0,
+                &mut StdRng::seed_from_u64(1),
+            )),
+        );
+        let name = game.player.inventory.items[index].name_with_stats();
+        let count = InventoryManager::get_item_count(&game.player);
+
+        assert!(game.drop_item(index).success);
+        assert_eq!(InventoryManager::get_item_count(&game.player), count - 1);
+        let pos = game.player_position();
+        assert!(game.current_level().items.contains_key(&pos));
+
+        messages(&mut game);
+        game.try_get_item();
+        assert_eq!(messages(&mut game), vec![format!("You picked up {name}.")]);
+        assert_eq!(InventoryManager::get_item_count(&game.player), count);
+        assert!(!game.current_level().items.contains_key(&pos));
+    }
+
+    #[test]
+    fn test_drop_unequips_item() {
+        let mut game = test_game();
+        // A cursed roll couldn't be taken off to drop
+        let mut equipment = Equipment::generate_with_rng(1, 0, &mut StdRng::seed_from_u64(1));
+        equipment.cursed = false;
+        let index = give(&mut game, Item::Equipment(equipment));
+        game.player.inventory.equip_item(index).unwrap();
+
+        let result = game.drop_item(index);
+        assert!(result.message.contains("unequip"));
+        assert!(!game
+            .player
+            .inventory
+            .equipped
+            .values()
+            .any(|slot| *slot == Some(index)));
+    }
+
+    #[test]
+    fn test_drop_refuses_occupied_tile() {
+        let mut game = test_game();
+        let first = give(
+            &mut game,
+            Item::Equipment(Equipment::generate_with_rng(
+                1,
+                0,
+                &mut StdRng::seed_from_u64(1),
+            )),
+        );
+        assert!(game.drop_item(first).success);
+
+        let second = give(
+            &mut game,
+            Item::Equipment(Equipment::generate_with_rng(
+                1,
+                0,
+                &mut StdRng::seed_from_u64(1),
+            )),
+        );
+        let result = game.drop_item(second);
+        assert!(!result.success);
+        assert!(result.message.contains("Move to an empty spot"));
+    }
+
+    #[test]
+    fn test_graves_of_other_runs_are_guarded_and_readable() {
+        let mut bones = BonesPool::default();
+        for depth in 1..=6 {
+            bones.add(BonesRecord {
+                name: format!("Aldric{depth}"),
+                class: ClassType::Warrior,
+                level: 6,
+                depth,
+                dungeon_type: crate::world::DungeonType::Cavern,
+                killed_by: Some("Orc".to_string()),
+                items: vec![Item::Equipment(Equipment::weapon("Old Sword", 6))],
+                run_id: 1,
+            });
+        }
+
+        let mut game = test_game();
+        game.bones = bones.clone();
+        // Bones from this very run are never buried
+        game.run_id = 1;
+        for seed in 0..20 {
+            game.place_graves(&mut StdRng::seed_from_u64(seed));
+        }
+        assert!(game
+            .current_dungeon()
+            .levels
+            .iter()
+            .all(|level| level.graves.is_empty()));
+
+        game.run_id = 2;
+        let mut graves = 0;
+        for seed in 0..20 {
+            game.place_graves(&mut StdRng::seed_from_u64(seed));
+        }
+        for (index, level) in game.current_dungeon().levels.iter().enumerate() {
+            // The explored first level is left alone
+            assert!(index > 0 || level.graves.is_empty());
+            for (pos, epitaph) in &level.graves {
+                graves += 1;
+                assert!(epitaph.starts_with("Here lies Aldric"));
+                let beside = [(0, -1), (0, 1), (-1, 0), (1, 0)]
+                    .map(|(dx, dy)| Position::new(pos.x + dx, pos.y + dy));
+                assert!(beside.iter().any(|spot| level
+                    .enemies
+                    .get(spot)
+                    .is_some_and(|ghost| ghost.name.starts_with("Ghost of Aldric"))));
+                assert!(beside.iter().any(|spot| level
+                    .chest_contents
+                    .get(spot)
+                    .is_some_and(|chest| chest.items.len() == 1)));
+            }
+        }
+        assert!(graves > 0);
+
+        // Standing beside a grave, G reads it; walking into it doesn't move
+        let pos = game.player_position();
+        let grave = Position::new(pos.x, pos.y - 1);
+        game.current_level_mut().tiles[grave.y as usize][grave.x as usize] = Tile::grave();
+        game.current_level_mut()
+            .graves
+            .insert(grave, "Here lies Nobody.".to_string());
+        assert_eq!(
+            game.move_player(0, -1),
+            MoveOutcome::Blocked(BlockReason::Grave)
+        );
+        messages(&mut game);
+        game.try_get_item();
+        assert_eq!(
+            messages(&mut game),
+            ["The gravestone reads: Here lies Nobody."]
+        );
+    }
+
+    #[test]
+    fn test_items_next_to_the_player_are_pointed_out_once() {
+        let mut game = test_game();
+        let start = game.player_position();
+        let step = Position::new(start.x + 1, start.y);
+        let spot = Position::new(start.x + 2, start.y);
+        let level = game.current_level_mut();
+        level.items.clear();
+        for pos in [step, spot] {
+            level.tiles[pos.y as usize][pos.x as usize] = Tile::floor();
+            level.enemies.remove(&pos);
+        }
+        level
+            .items
+            .insert(spot, Item::Consumable(Consumable::mana_potion(15)));
+
+        messages(&mut game);
+        assert_eq!(game.move_player(1, 0), MoveOutcome::Moved);
+        assert_eq!(
+            messages(&mut game),
+            vec!["You see a Minor Mana Potion here.".to_string()]
+        );
+        assert_eq!(game.move_player(-1, 0), MoveOutcome::Moved);
+        assert_eq!(game.move_player(1, 0), MoveOutcome::Moved);
+        assert!(messages(&mut game).is_empty());
+
+        // Walking onto it names it with its numbers
+        assert_eq!(game.move_player(1, 0), MoveOutcome::Moved);
+        assert_eq!(
+            messages(&mut game),
+            vec!["You picked up Minor Mana Potion (+15 MP).".to_string()]
+        );
+    }
+
+    /// Puts a chest holding `items` and 20 gold just right of the player
+    fn place_chest(game: &mut Game, items: Vec<Item>) -> Position {
+        let pos = game.player_position();
+        let chest_pos = Position::new(pos.x + 1, pos.y);
+        let level = game.current_level_mut();
+        level.enemies.remove(&chest_pos);
+        level.tiles[chest_pos.y as usize][chest_pos.x as usize] = Tile::chest();
+        level.chest_contents.insert(
+            chest_pos,
+            Chest {
+                items,
+                gold: 20,
+                ..Chest::default()
+            },
+        );
+        chest_pos
+    }
+
+    #[test]
+    fn test_opening_a_chest_spills_its_loot_to_pick_up() {
+        let mut game = test_game();
+        let chest_pos = place_chest(
+            &mut game,
+            vec![
+                Item::Equipment(Equipment::weapon("Steel Dagger", 5)),
+                Item::Consumable(Consumable::health_potion(20)),
+            ],
+        );
+        let (gold, count) = (
+            game.player.gold,
+            InventoryManager::get_item_count(&game.player),
+        );
+
+        messages(&mut game);
+        assert_eq!(game.move_player(1, 0), MoveOutcome::OpenedChest);
+        assert_eq!(
+            messages(&mut game),
+            vec![
+                "You open the chest: Steel Dagger (+5 ATK), Minor Health Potion (+20 HP), 20 gold. Its contents spill onto the floor."
+                    .to_string()
+            ]
+        );
+        assert_eq!(
+            game.current_level().tiles[chest_pos.y as usize][chest_pos.x as usize].tile_type,
+            TileType::Floor
+        );
+        assert!(!game.current_level().chest_contents.contains_key(&chest_pos));
+        assert_eq!(game.player.gold, gold);
+
+        // Where the chest stood lie the gold and the first item
+        assert_eq!(game.move_player(1, 0), MoveOutcome::Moved);
+        let log = messages(&mut game);
+        assert_eq!(
+            log[..2],
+            [
+                "You picked up Steel Dagger (+5 ATK).",
+                "You pick up 20 gold."
+            ]
+        );
+        assert_eq!(game.player.gold, gold + 20);
+        assert_eq!(game.stats.gold_earned, 20);
+        assert_eq!(InventoryManager::get_item_count(&game.player), count + 1);
+    }
+
+    #[test]
+    fn test_items_that_do_not_fit_stay_on_the_floor() {
+        let mut game = test_game();
+        // Room left for just one more 6-weight weapon
+        while game.player.carry_capacity() - game.player.carried_weight() >= 12 {
+            give(&mut game, Item::Equipment(Equipment::weapon("Club", 1)));
+        }
+        let chest_pos = place_chest(
+            &mut game,
+            vec![
+                Item::Equipment(Equipment::weapon("Steel Dagger", 5)),
+                Item::Equipment(Equipment::weapon("Iron Mace", 6)),
+                Item::Equipment(Equipment::weapon("Short Sword", 7)),
+            ],
+        );
+        game.try_get_item();
+        assert_eq!(game.move_player(1, 0), MoveOutcome::Moved);
+        messages(&mut game);
+
+        // The rest are too heavy to take, and wait where they are
+        let (&pos, _) = game
+            .current_level()
+            .items
+            .iter()
+            .find(|(_, item)| item.name() == "Iron Mace")
+            .unwrap();
+        game.current_level_mut().player_position = pos;
+        game.try_get_item();
+        assert!(messages(&mut game)[0].contains("too heavy to carry"));
+        assert_eq!(game.current_level().items[&pos].name(), "Iron Mace");
+        assert!(game
+            .current_level()
+            .items
+            .values()
+            .any(|item| item.name() == "Short Sword"));
+        assert!(!game.current_level().items.contains_key(&chest_pos));
+    }
+
+    #[test]
+    fn test_an_item_too_heavy_to_carry_is_stepped_over() {
+        use crate::item::EquipmentSlot;
+
+        let mut game = test_game();
+        let mut level = Level::new(5, 3);
+        for x in 1..4 {
+            level.tiles[1][x] = Tile::floor();
+        }
+        level.player_position = Position::new(1, 1);
+        let mut plate = Equipment::armor("Breastplate", EquipmentSlot::Chest, 8);
+        plate.weight = Some(game.player.carry_capacity() + 1);
+        level
+            .items
+            .insert(Position::new(2, 1), Item::Equipment(plate));
+        let dungeon = game.current_dungeon_mut();
+        dungeon.levels[dungeon.current_level] = level;
+        messages(&mut game);
+
+        assert_eq!(game.move_player(1, 0), MoveOutcome::Moved);
+        assert_eq!(game.player_position(), Position::new(2, 1));
+        assert!(messages(&mut game)[0].contains("too heavy to carry"));
+        assert_eq!(
+            game.current_level().items[&Position::new(2, 1)].name(),
+            "Breastplate"
+        );
+        assert_eq!(game.move_player(1, 0), MoveOutcome::Moved);
+    }
+
+    #[test]
+    fn test_a_careful_look_finds_a_chest_trap_to_disarm_or_spring() {
+        use crate::item::consumable::ConsumableType;
+        use crate::world::chest::POISON_TURNS;
+
+        let mut game = test_game();
+        // Warriors smash chests open instead of disarming them
+        game.player.class.class_type = ClassType::Cleric;
+        game.player.max_health = 100;
+        game.player.health = 100;
+        let chest_pos = place_chest(
+            &mut game,
+            vec![Item::Consumable(Consumable::health_potion(20))],
+        );
+        game.current_level_mut()
+            .chest_contents
+            .get_mut(&chest_pos)
+            .unwrap()
+            .kind = ChestKind::Trapped(ChestTrap::Needle);
+        game.update_visibility();
+
+        // Until it's found, nothing gives the trap away
+        let describe = |game: &Game| game.current_level().describe_position(chest_pos);
+        assert_eq!(describe(&game).as_deref(), Some("Chest"));
+        assert_eq!(
+            game.current_interaction(),
+            Some(Interaction::OpenChest(chest_pos))
+        );
+        messages(&mut game);
+        game.check_chest(chest_pos, &mut lucky());
+        assert_eq!(
+            messages(&mut game),
+            ["You find a needle trap on the chest. Press G to try disarming it, or walk into the chest to open it anyway."]
+        );
+        assert_eq!(
+            describe(&game).as_deref(),
+            Some("Chest, with a needle trap found on it")
+        );
+        assert_eq!(
+            game.current_interaction(),
+            Some(Interaction::DisarmTrap(chest_pos))
+        );
+
+        // A steady hand disarms it and opens the chest unharmed
+        game.disarm_chest(chest_pos, &mut lucky());
+        let log = messages(&mut game);
+        assert_eq!(log[0], "You disarm the needle trap.");
+        assert!(log[1].starts_with("You open the chest"));
+        assert_eq!(game.player.health, 100);
+
+        // A fumble springs it, poison and all
+        let chest_pos = place_chest(&mut game, Vec::new());
+        let chest = game
+            .current_level_mut()
+            .chest_contents
+            .get_mut(&chest_pos)
+            .unwrap();
+        chest.kind = ChestKind::Trapped(ChestTrap::Poison);
+        chest.found = true;
+        game.disarm_chest(chest_pos, &mut unlucky());
+        let damage = ChestTrap::Poison.damage(game.current_level().level_num);
+        assert_eq!(
+            messages(&mut game)[..2],
+            [
+                "Your hand slips as you work at the poison gas trap!".to_string(),
+                format!("Green gas bursts from the chest! It does {damage} damage, and you are poisoned.")
+            ]
+        );
+        assert_eq!(game.player.health, 100 - damage);
+        assert_eq!(game.player.poisoned_turns, POISON_TURNS);
+
+        // The poison wears on each turn until an antidote clears it
+        assert!(!game.suffer_poison());
+        assert_eq!(game.player.health, 100 - damage - POISON_DAMAGE);
+        let antidote = Consumable {
+            name: "Antidote".to_string(),
+            description: String::new(),
+            consumable_type: ConsumableType::Antidote,
+            potency: 0,
+            value: 30,
+        };
+        assert_eq!(
+            antidote.use_effect(&mut game.player),
+            "The poison leaves your body"
+        );
+        assert_eq!(game.player.poisoned_turns, 0);
+    }
+
+    #[test]
+    fn test_walking_into_a_chest_springs_whatever_it_hides() {
+        let mut game = test_game();
+        game.player.health = 1;
+        let chest_pos = place_chest(&mut game, Vec::new());
+        game.current_level_mut()
+            .chest_contents
+            .get_mut(&chest_pos)
+            .unwrap()
+            .kind = ChestKind::Trapped(ChestTrap::Explosion);
+        game.move_player(1, 0);
+        assert!(matches!(game.game_state, GameState::GameOver));
+        assert_eq!(game.stats.killed_by.as_deref(), Some("explosive rune"));
+
+        // A mimic takes the chest's place, hoard and all, and the fight is on
+        let mut game = test_game();
+        let hoard = vec![Item::Equipment(Equipment::weapon("Steel Dagger", 5))];
+        let chest_pos = place_chest(&mut game, hoard);
+        game.current_level_mut()
+            .chest_contents
+            .get_mut(&chest_pos)
+            .unwrap()
+            .kind = ChestKind::Mimic;
+        messages(&mut game);
+        assert_eq!(game.move_player(1, 0), MoveOutcome::StartedCombat);
+        assert_eq!(
+            messages(&mut game)[0],
+            "The chest's lid snaps open on rows of teeth: it's a mimic!"
+        );
+        assert!(matches!(&game.game_state, GameState::Combat(foes) if foes.contains(&chest_pos)));
+        let level = game.current_level();
+        let mimic = &level.enemies[&chest_pos];
+        assert_eq!(mimic.enemy_type, EnemyType::Mimic);
+        assert_eq!(mimic.carried[0].name(), "Steel Dagger");
+        assert_eq!(
+            level.tiles[chest_pos.y as usize][chest_pos.x as usize].tile_type,
+            TileType::Floor
+        );
+        assert!(!level.chest_contents.contains_key(&chest_pos));
+
+        // Spotted first, it only wakes
+        let mut game = test_game();
+        let chest_pos = place_chest(&mut game, Vec::new());
+        game.current_level_mut()
+            .chest_contents
+            .get_mut(&chest_pos)
+            .unwrap()
+            .kind = ChestKind::Mimic;
+        game.check_chest(chest_pos, &mut lucky());
+        assert!(matches!(game.game_state, GameState::Playing));
+        assert_eq!(
+            game.current_level().enemies[&chest_pos].alertness,
+            Alertness::Alerted
+        );
+    }
+
+    #[test]
+    fn test_enemy_drops_stay_on_the_level() {
+        let mut game = test_game();
+        let goblin = Position::new(4, 2);
+        arena(&mut game, 10, 3, Position::new(5, 2), goblin);
+        game.player.max_health = 1000;
+        game.player.health = 1000;
+        game.current_level_mut()
+            .get_enemy_at_mut(&goblin)
+            .unwrap()
+            .health = 1;
+        let gold = game.player.gold;
+
+        while !game
+            .resolve_combat_turn(CombatAction::Attack, 0)
+            .enemy_defeated
+        {}
+        game.end_combat(CombatEnding::Victory);
+        let dropped = game.current_level().gold[&goblin];
+        assert!(dropped > 0);
+        assert_eq!(game.player.gold, gold);
+
+        // Saved, loaded, left and come back to, the gold is still there
+        let json = serde_json::to_string(&game).unwrap();
+        let mut game: Game = serde_json::from_str(&json).unwrap();
+        game.descend();
+        game.ascend();
+        assert_eq!(game.player_position(), Position::new(5, 2));
+        assert_eq!(game.current_level().gold.get(&goblin), Some(&dropped));
+
+        assert_eq!(game.move_player(-1, 0), MoveOutcome::Moved);
+        assert_eq!(game.player.gold, gold + dropped);
+        assert!(game.current_level().gold.is_empty());
+    }
+
+    #[test]
+    fn test_slain_enemies_leave_corpses_to_search_before_they_rot() {
+        use crate::world::corpse::CORPSE_DECAY_TURNS;
+
+        let mut game = test_game();
+        let goblin = Position::new(4, 2);
+        arena(&mut game, 10, 3, Position::new(5, 2), goblin);
+        game.player.max_health = 1000;
+        game.player.health = 1000;
+        game.current_level_mut()
+            .get_enemy_at_mut(&goblin)
+            .unwrap()
+            .health = 1;
+        while !game
+            .resolve_combat_turn(CombatAction::Attack, 0)
+            .enemy_defeated
+        {}
+        game.end_combat(CombatEnding::Victory);
+        let level = game.current_level_mut();
+        assert!(!level.corpses[&goblin].searched);
+        level.gold.clear();
+        level.items.clear();
+        level.tiles[2][4].explored = true;
+        assert_eq!(level.describe_position(goblin).unwrap(), "Goblin corpse");
+        messages(&mut game);
+
+        // G beside it searches it, once
+        game.try_get_item();
+        assert!(messages(&mut game)[0].starts_with("You search the Goblin corpse"));
+        assert!(game.current_level().corpses[&goblin].searched);
+        game.try_get_item();
+        assert_eq!(
+            messages(&mut game),
+            ["You've already searched the Goblin corpse."]
+        );
+
+        // It never gets in the way, and rots away in time
+        assert_eq!(game.move_player(-1, 0), MoveOutcome::Moved);
+        let died = game.current_level().corpses[&goblin].died_on_turn;
+        game.stats.turns = died + CORPSE_DECAY_TURNS - 2;
+        game.process_turn();
+        assert!(game.current_level().corpses.contains_key(&goblin));
+        game.process_turn();
+        assert!(game.current_level().corpses.is_empty());
+    }
+
+    #[test]
+    fn test_dark_mages_raise_unsearched_corpses() {
+        use crate::world::enemy::EnemyType;
+
+        let mut game = test_game();
+        let mage = Position::new(9, 2);
+        arena(&mut game, 10, 3, Position::new(1, 2), mage);
+        game.game_state = GameState::Playing;
+        game.player.max_health = 10_000;
+        game.player.health = 10_000;
+        let level = game.current_level_mut();
+        let mut dark_mage = Enemy::new("Dark Mage".to_string(), EnemyType::DarkMage, 3);
+        dark_mage.wind(10_000);
+        level.enemies.insert(mage, dark_mage);
+        let goblin = Enemy::new("Goblin".to_string(), EnemyType::Goblin, 4);
+        let searched = Position::new(7, 1);
+        let unsearched = Position::new(7, 3);
+        level.corpses.insert(searched, Corpse::new(&goblin, 0));
+        level.corpses.get_mut(&searched).unwrap().searched = true;
+        level.corpses.insert(unsearched, Corpse::new(&goblin, 0));
+
+        for _ in 0..200 {
+            game.process_turn();
+            if game.current_level().enemies.contains_key(&unsearched) {
+                break;
+            }
+        }
+        let risen = &game.current_level().enemies[&unsearched];
+        assert_eq!(risen.name, "Risen Goblin");
+        assert_eq!(risen.enemy_type, EnemyType::Skeleton);
+        assert!(!game.current_level().corpses.contains_key(&unsearched));
+        assert!(game.current_level().corpses.contains_key(&searched));
+        assert!(messages(&mut game)
+            .contains(&"The Dark Mage raises the Goblin corpse as a Risen Goblin!".to_string()));
+    }
+
+    #[test]
+    fn test_g_does_the_nearest_thing_first_and_the_prompt_says_so() {
+        let mut game = test_game();
+        let here = Position::new(3, 2);
+        arena(&mut game, 6, 3, here, Position::new(6, 3));
+        game.game_state = GameState::Playing;
+        game.current_level_mut().enemies.clear();
+        assert_eq!(game.current_interaction(), None);
+
+        // From last to first: a corpse, a grave, the stairs, loot beside,
+        // a chest beside, then loot underfoot
+        let goblin = Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1);
+        let level = game.current_level_mut();
+        level.corpses.insert(here, Corpse::new(&goblin, 0));
+        assert_eq!(
+            game.current_interaction().unwrap().prompt(),
+            "Press G to search the Goblin corpse"
+        );
+        let grave = Position::new(3, 1);
+        game.current_level_mut()
+            .graves
+            .insert(grave, "Here lies Bob".to_string());
+        assert_eq!(
+            game.current_interaction(),
+            Some(Interaction::ReadGrave(grave))
+        );
+        game.current_level_mut().tiles[2][3] = Tile::stairs_down();
+        assert_eq!(
+            game.current_interaction().unwrap().prompt(),
+            "Press > to descend the stairs"
+        );
+        let loot = Position::new(2, 2);
+        game.current_level_mut().gold.insert(loot, 12);
+        assert_eq!(
+            game.current_interaction().unwrap().prompt(),
+            "Press G to pick up 12 gold"
+        );
+        let chest = place_chest(&mut game, Vec::new());
+        assert_eq!(
+            game.current_interaction(),
+            Some(Interaction::OpenChest(chest))
+        );
+        game.current_level_mut()
+            .items
+            .insert(here, Item::Consumable(Consumable::health_potion(20)));
+        game.current_level_mut().gold.insert(here, 5);
+        assert_eq!(
+            game.current_interaction().unwrap().prompt(),
+            "Press G to pick up Minor Health Potion and 5 gold"
+        );
+
+        // G does just what it said
+        game.try_get_item();
+        assert!(game.current_level().items.is_empty());
+        assert_eq!(
+            game.current_interaction(),
+            Some(Interaction::OpenChest(chest))
+        );
+    }
+
+    #[test]
+    fn test_a_shrine_is_announced_once_and_its_altar_answers_once() {
+        use crate::world::level::Room;
+        use crate::world::{RoomKind, SpecialRoom};
+
+        let mut game = test_game();
+        arena(&mut game, 10, 5, Position::new(4, 3), Position::new(1, 1));
+        game.game_state = GameState::Playing;
+        let level = game.current_level_mut();
+        level.enemies.clear();
+        level.rooms = vec![Room::new(4, 0, 7, 6)];
+        level.special_rooms = vec![SpecialRoom::new(RoomKind::Shrine, 0)];
+        let altar = Position::new(7, 3);
+        level.tiles[3][7] = Tile::altar();
+        level.tiles[3][7].explored = true;
+        messages(&mut game);
+
+        game.move_player(1, 0);
+        assert_eq!(messages(&mut game), [RoomKind::Shrine.flavor()]);
+        game.move_player(1, 0);
+        assert!(!messages(&mut game).contains(&RoomKind::Shrine.flavor().to_string()));
+        // The altar stands in the way
+        game.move_player(1, 0);
+        assert_eq!(game.player_position(), Position::new(6, 3));
+        messages(&mut game);
+
+        assert_eq!(game.current_interaction(), Some(Interaction::Pray(altar)));
+        game.player.health = 1;
+        let stats = game.player.stats.clone();
+        game.try_get_item();
+        assert!(game.player.health == game.player.max_health || game.player.stats != stats);
+        assert_eq!(messages(&mut game).len(), 1);
+        assert!(game.current_level().spent_altars.contains(&altar));
+
+        // Spent, it has nothing more to give
+        assert_eq!(game.current_interaction(), None);
+        let stats = game.player.stats.clone();
+        game.try_get_item();
+        assert_eq!(game.player.stats, stats);
+        assert_eq!(messages(&mut game), ["The altar has already answered you."]);
+        assert_eq!(
+            game.current_level().describe_position(altar),
+            Some("Altar, silent now".to_string())
+        );
+    }
+
+    #[test]
+    fn test_gear_is_enchanted_at_a_shrine_for_gold_and_dust() {
+        use crate::world::level::Room;
+        use crate::world::SpecialRoom;
+
+        let mut game = test_game();
+        arena(&mut game, 10, 5, Position::new(4, 3), Position::new(1, 1));
+        game.game_state = GameState::Playing;
+        game.player.inventory.items.clear();
+        let dagger = give(
+            &mut game,
+            Item::Equipment(Equipment::weapon("Steel Dagger", 5)),
+        );
+        let potion = give(&mut game, Item::Consumable(Consumable::health_potion(20)));
+        for _ in 0..6 {
+            give(&mut game, Item::Consumable(Consumable::arcane_dust()));
+        }
+        game.player.gold = 10_000;
+        assert!(!game.can_enchant());
+        assert!(!game.enchant_item(dagger).success);
+
+        let level = game.current_level_mut();
+        level.rooms = vec![Room::new(2, 0, 7, 6)];
+        level.special_rooms = vec![SpecialRoom::new(RoomKind::Shrine, 0)];
+        assert!(game.can_enchant());
+        assert!(game.enchant_offer(potion).is_none());
+
+        // The first few always take, each costing more than the last
+        for level in 1..=enchant::SAFE_ENCHANT_LEVEL {
+            let gold = game.player.gold;
+            let cost = game.enchant_offer(dagger).unwrap().cost;
+            assert!(game.enchant_item_with_rng(dagger, &mut unlucky()).success);
+            assert_eq!(game.player.gold, gold - cost.gold);
+            let Some(Item::Equipment(equipment)) = game.player.inventory.items.get(dagger) else {
+                panic!("the dagger should still be there");
+            };
+            assert_eq!(equipment.name, format!("Steel Dagger +{level}"));
+            assert_eq!(equipment.power, 5 + level as i32);
+        }
+        assert_eq!(enchant::dust_held(&game.player), 2);
+
+        // Past them an attempt can fail, costing the offering but not the item
+        let gold = game.player.gold;
+        assert!(!game.enchant_item_with_rng(dagger, &mut unlucky()).success);
+        assert!(game.player.gold < gold);
+        assert_eq!(enchant::dust_held(&game.player), 0);
+        let Some(Item::Equipment(equipment)) = game.player.inventory.items.get(dagger) else {
+            panic!("a failed enchantment never harms the item");
+        };
+        assert_eq!(equipment.name, "Steel Dagger +3");
+        assert_eq!(equipment.enchant_history.last().unwrap(), "+4: failed");
+        assert!(matches!(
+            game.player.inventory.items.get(potion),
+            Some(Item::Consumable(_))
+        ));
+
+        // Without dust there's nothing more to offer
+        let gold = game.player.gold;
+        assert!(!game.enchant_item_with_rng(dagger, &mut lucky()).success);
+        assert_eq!(game.player.gold, gold);
+    }
+
+    #[test]
+    fn test_quest_items_cannot_be_dropped() {
+        let mut game = test_game();
+        let index = give(
+            &mut game,
+            Item::Quest {
+                id: "relic".to_string(),
+                name: "Ancient Relic".to_string(),
+                description: "Old".to_string(),
+            },
+        );
+
+        let result = game.drop_item(index);
+        assert!(!result.success);
+        assert!(result.message.contains("needed for a quest"));
+    }
+
+    #[test]
+    fn test_new_game_plus_keeps_the_character_for_a_harder_dungeon() {
+        let mut game = test_game();
+        game.player.level = 8;
+        game.player.gold = 250;
+        let sword = give(
+            &mut game,
+            Item::Equipment(Equipment::generate_with_rng(
+                1,
+                0,
+                &mut StdRng::seed_from_u64(1),
+            )),
+        );
+        assert!(game.use_item(sword).success);
+        give(
+            &mut game,
+            Item::Quest {
+                id: "relic".to_string(),
+                name: "Ancient Relic".to_string(),
+                description: "Old".to_string(),
+            },
+        );
+        let equipped = game.player.inventory.equipped.clone();
+        let difficulty = game.dungeon_difficulty();
+        game.game_state = GameState::Victory;
+
+        game.start_new_game_plus();
+
+        assert_eq!(game.ng_plus_counter, 1);
+        assert!(matches!(game.game_state, GameState::Playing));
+        assert_eq!(game.player.level, 8);
+        assert_eq!(game.player.gold, 250);
+        assert_eq!(game.player.inventory.equipped, equipped);
+        assert!(!game
+            .player
+            .inventory
+            .items
+            .iter()
+            .any(|item| matches!(item, Item::Quest { .. })));
+
+        // The new dungeon is built at the raised difficulty
+        assert_eq!(game.dungeon_difficulty(), difficulty + 4);
+        assert_eq!(game.current_dungeon().difficulty, difficulty + 4);
+        assert_eq!(game.current_dungeon_index, 0);
+        assert_eq!(game.current_dungeon().current_level, 0);
+    }
+
+    #[test]
+    fn test_launch_options_set_the_difficulty_and_world_seed() {
+        let options = StartupOptions {
+            seed: Some(7),
+            difficulty: Difficulty::Hard,
+            ..StartupOptions::default()
+        };
+        let player = || Player::new("Tester".to_string(), ClassType::Warrior);
+        let mut game = Game::with_options(player(), &options);
+        game.player.level = 4;
+        assert_eq!(game.dungeon_difficulty(), 6);
+
+        // The same seed gives the same dungeon
+        let other = Game::with_options(player(), &options);
+        let first = Game::with_options(player(), &options);
+        assert_eq!(first.current_dungeon().name, other.current_dungeon().name);
+        assert_eq!(
+            first.current_level().player_position,
+            other.current_level().player_position
+        );
+
+        game.difficulty = Difficulty::Easy;
+        assert_eq!(game.dungeon_difficulty(), 3);
+        game.player.level = 1;
+        assert_eq!(game.dungeon_difficulty(), 1);
+    }
+
+    #[test]
+    fn test_stairs_lead_to_the_matching_staircase() {
+        let mut game = test_game();
+        for level in &mut game.current_dungeon_mut().levels {
+            level.enemies.clear();
+        }
+        let marker = Position::new(1, 1);
+        game.current_level_mut().items.insert(
+            marker,
+            Item::Equipment(Equipment::generate_with_rng(
+                1,
+                0,
+                &mut StdRng::seed_from_u64(1),
+            )),
+        );
+
+        // Walk onto the stairs down from the west
+        let down = game.current_level().stairs_down_position.unwrap();
+        game.current_level_mut().player_position = Position::new(down.x - 1, down.y);
+        assert_eq!(game.move_player(1, 0), MoveOutcome::UsedStairs);
+        assert_eq!(game.current_dungeon().current_level, 1);
+        let up = game.current_level().stairs_up_position.unwrap();
+        assert_eq!(game.player_position(), up);
+        assert!(game.current_level().visible_tiles[up.y as usize][up.x as usize]);
+        assert!(messages(&mut game).contains(&"You descend to level 2.".to_string()));
+
+        // Step off the stairs and back onto them to climb up again
+        assert_eq!(game.move_player(-1, 0), MoveOutcome::Moved);
+        assert_eq!(game.move_player(1, 0), MoveOutcome::UsedStairs);
+        assert_eq!(game.current_dungeon().current_level, 0);
+        assert_eq!(game.player_position(), down);
+        assert!(messages(&mut game).contains(&"You ascend to level 1.".to_string()));
+
+        // The level above is as it was left
+        assert!(game.current_level().items.contains_key(&marker));
+    }
+
+    #[test]
+    fn test_walls_are_reported_and_stairs_can_wait_for_a_key() {
+        let mut game = test_game();
+        game.current_level_mut().enemies.clear();
+        let start = game.player_position();
+        let wall = Position::new(start.x + 1, start.y);
+        game.current_level_mut().tiles[wall.y as usize][wall.x as usize] = Tile::wall();
+
+        messages(&mut game);
+        assert_eq!(
+            game.move_player(1, 0),
+            MoveOutcome::Blocked(BlockReason::Wall)
+        );
+        assert_eq!(game.player_position(), start);
+        assert_eq!(messages(&mut game), ["You bump into a wall."]);
+        assert!(game.take_sounds().contains(&SoundEvent::Bump));
+
+        // With confirming on, stepping onto the stairs waits for '>'
+        game.confirm_stairs = true;
+        let down = game.current_level().stairs_down_position.unwrap();
+        game.current_level_mut().player_position = Position::new(down.x - 1, down.y);
+        assert_eq!(game.move_player(1, 0), MoveOutcome::AtStairs);
+        assert_eq!(game.player_position(), down);
+        assert_eq!(game.current_dungeon().current_level, 0);
+        assert_eq!(messages(&mut game), ["Press > to descend."]);
+
+        assert_eq!(
+            game.take_stairs(false),
+            MoveOutcome::Blocked(BlockReason::NoStairsUp)
+        );
+        assert_eq!(game.take_stairs(true), MoveOutcome::UsedStairs);
+        assert_eq!(game.current_dungeon().current_level, 1);
+    }
+
+    #[test]
+    fn test_a_torch_lights_dark_levels_until_it_burns_out() {
+        let mut game = test_game();
+        game.current_level_mut().ambient_light = AmbientLight::Dark;
+        let lit_tiles = |game: &Game| {
+            game.current_level()
+                .visible_tiles
+                .iter()
+                .flatten()
+                .filter(|&&visible| visible)
+                .count()
+        };
+
+        game.update_visibility();
+        assert_eq!(game.current_view_radius(), DARK_VIEW_RADIUS);
+        let dark = lit_tiles(&game);
+
+        let index = give(&mut game, Item::Consumable(Consumable::torch()));
+        game.use_item(index);
+        assert_eq!(game.player.torch_turns, TORCH_TURNS);
+        game.update_visibility();
+        assert_eq!(game.current_view_radius(), game.view_radius);
+        assert!(lit_tiles(&game) > dark);
+
+        // The last turn of the torch goes out mid-level; sight shrinks on
+        // the next update
+        game.player.torch_turns = 1;
+        messages(&mut game);
+        game.process_turn();
+        assert!(messages(&mut game).iter().any(|m| m.contains("burns out")));
+        game.update_visibility();
+        assert_eq!(lit_tiles(&game), dark);
+
+        // Torches don't burn where there's light enough
+        game.current_level_mut().ambient_light = AmbientLight::Normal;
+        game.player.torch_turns = 5;
+        game.process_turn();
+        assert_eq!(game.player.torch_turns, 5);
+    }
+
+    /// An rng whose every roll comes up; one whose rolls never do
+    fn lucky() -> StepRng {
+        StepRng::new(0, 0)
+    }
+
+    fn unlucky() -> StepRng {
+        StepRng::new(u64::MAX, 0)
+    }
+
+    #[test]
+    fn test_rain_shortens_sight_and_muffles_noise_until_it_stops() {
+        let mut game = test_game();
+        game.ambient_events = true;
+        game.current_dungeon_mut().dungeon_type = DungeonType::Forest;
+        game.current_level_mut().ambient_light = AmbientLight::Normal;
+        let player = game.player_position();
+        let heard_at = Noise::Chest.radius(game.player.stats.dexterity);
+        let listener = Position::new(player.x, player.y + heard_at);
+        let level = game.current_level_mut();
+        level.enemies.clear();
+        level.enemies.insert(
+            listener,
+            Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1),
+        );
+
+        messages(&mut game);
+
+        game.pass_ambient_time(&mut lucky());
+        assert_eq!(
+            messages(&mut game),
+            ["It starts to rain. You can't see as far, or be heard as far."]
+        );
+        assert_eq!(
+            game.current_view_radius(),
+            game.view_radius - RAIN_SIGHT_PENALTY
+        );
+        game.make_noise(Noise::Chest);
+        assert_eq!(
+            game.current_level().enemies[&listener].alertness,
+            Alertness::Unaware
+        );
+
+        for _ in 0..RAIN_TURNS {
+            game.pass_ambient_time(&mut unlucky());
+        }
+        assert_eq!(messages(&mut game), ["The rain stops."]);
+        assert_eq!(game.current_view_radius(), game.view_radius);
+        game.make_noise(Noise::Chest);
+        assert_eq!(
+            game.current_level().enemies[&listener].alertness,
+            Alertness::Suspicious
+        );
+    }
+
+    #[test]
+    fn test_tremors_shake_the_map_and_wake_enemies_nearby() {
+        let mut game = test_game();
+        let player = game.player_position();
+        let dir = if player.x < 40 { 1 } else { -1 };
+        let at = |dx: i32| Position::new(player.x + dir * dx, player.y);
+        let level = game.current_level_mut();
+        level.enemies.clear();
+        for dx in [TREMOR_WAKE_RADIUS, TREMOR_WAKE_RADIUS + 1] {
+            let enemy = Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1);
+            level.enemies.insert(at(dx), enemy);
+        }
+
+        messages(&mut game);
+
+        game.start_ambient_event(AmbientEvent::Tremor, &mut lucky());
+        let level = game.current_level();
+        assert_eq!(weather::shake_offset(level), 1);
+        assert_eq!(
+            level.enemies[&at(TREMOR_WAKE_RADIUS)].alertness,
+            Alertness::Suspicious
+        );
+        assert_eq!(
+            level.enemies[&at(TREMOR_WAKE_RADIUS + 1)].alertness,
+            Alertness::Unaware
+        );
+        assert_eq!(
+            messages(&mut game),
+            ["The ground shakes! Something nearby stirs."]
+        );
+
+        // Whispers say something and leave nothing behind
+        game.start_ambient_event(AmbientEvent::Whispers, &mut lucky());
+        assert!(WHISPERS.contains(&messages(&mut game)[0].as_str()));
+        assert_eq!(game.current_level().ambient_effects.len(), 1);
+    }
+
+    #[test]
+    fn test_ambient_events_can_be_turned_off() {
+        let mut game = test_game();
+        game.current_dungeon_mut().dungeon_type = DungeonType::Forest;
+        let mut settings = Settings::default();
+        game.apply_settings(&settings);
+        assert!(game.ambient_events);
+        game.start_ambient_event(AmbientEvent::Rain, &mut lucky());
+
+        // Turning them off stops the rain and keeps anything else away
+        settings.ambient_events = false;
+        game.apply_settings(&settings);
+        assert!(game.current_level().ambient_effects.is_empty());
+        game.pass_ambient_time(&mut lucky());
+        assert!(game.current_level().ambient_effects.is_empty());
+
+        settings.ambient_events = true;
+        game.apply_settings(&settings);
+        game.pass_ambient_time(&mut lucky());
+        assert!(weather::has_effect(
+            game.current_level(),
+            AmbientEvent::Rain
+        ));
+    }
+
+    #[test]
+    fn test_achievements_unlock_once_as_the_run_earns_them() {
+        let mut game = test_game();
+        // Untracked, nothing is unlocked
+        game.player.gold = 100;
+        game.process_turn();
+        assert!(game.take_unlocked_achievements().is_none());
+
+        game.track_achievements(AchievementProfile::default());
+        assert_eq!(
+            messages(&mut game).last().map(String::as_str),
+            Some("Achievement unlocked: Pocket Change - Carry 100 gold.")
+        );
+        let profile = game.take_unlocked_achievements().unwrap().clone();
+        assert_eq!(profile.summary(), "1 of 20 unlocked");
+
+        // A kill shows up after the turn; nothing is unlocked twice
+        game.stats.enemies_killed.insert(EnemyKind::Beast, 1);
+        game.process_turn();
+        game.process_turn();
+        let unlocked: Vec<String> = messages(&mut game)
+            .into_iter()
+            .filter(|message| message.starts_with("Achievement unlocked"))
+            .collect();
+        assert_eq!(
+            unlocked,
+            ["Achievement unlocked: First Blood - Slay your first enemy."]
+        );
+        assert!(game.take_unlocked_achievements().is_some());
+        assert!(game.take_unlocked_achievements().is_none());
+
+        // A later run picks up where the stored unlocks left off
+        let mut next = test_game();
+        next.player.gold = 100;
+        next.track_achievements(AchievementProfile::from_json(&profile.to_json()));
+        assert!(next.take_unlocked_achievements().is_none());
+        next.game_state = GameState::Victory;
+        next.check_achievements();
+        assert!(messages(&mut next)
+            .iter()
+            .any(|message| message.starts_with("Achievement unlocked: Warlord")));
+    }
+
+    #[test]
+    fn test_cleared_levels_refill_when_respawning_is_on() {
+        let mut game = test_game();
+        game.enemy_respawn = true;
+        game.current_level_mut().enemies.clear();
+
+        // Go down, wait out the delay and come back up
+        let down = game.current_level().stairs_down_position.unwrap();
+        game.current_level_mut().player_position = Position::new(down.x - 1, down.y);
+        assert_eq!(game.move_player(1, 0), MoveOutcome::UsedStairs);
+        game.stats.turns += game.current_dungeon().repopulation_delay();
+        assert_eq!(game.move_player(-1, 0), MoveOutcome::Moved);
+        messages(&mut game);
+        assert_eq!(game.move_player(1, 0), MoveOutcome::UsedStairs);
+        assert_eq!(game.current_dungeon().current_level, 0);
+
+        assert!(!game.current_level().enemies.is_empty());
+        assert!(messages(&mut game)
+            .contains(&"Something has moved in while you were away.".to_string()));
+        let player = game.player_position();
+        for pos in game.current_level().enemies.keys() {
+            let distance = (pos.x - player.x).abs().max((pos.y - player.y).abs());
+            assert!(distance > game.view_radius);
+        }
+    }
+
+    #[test]
+    fn test_throwing_hits_the_nearest_enemy_in_range() {
+        use crate::item::consumable::{Consumable, ConsumableType};
+        use crate::world::enemy::EnemyType;
+
+        let mut game = test_game();
+        let player = game.player_position();
+        let at = |dx: i32| Position::new(player.x + dx, player.y);
+        let knife = Item::Consumable(Consumable {
+            name: "Throwing Knife".to_string(),
+            description: String::new(),
+            consumable_type: ConsumableType::ThrowingKnife,
+            potency: 10,
+            value: 1,
+        });
+        let index = give(&mut game, knife);
+        let count = InventoryManager::get_item_count(&game.player);
+
+        // Nothing in range: the knife stays in the pack
+        game.current_level_mut().enemies.clear();
+        let far = Enemy::new("Far".to_string(), EnemyType::Goblin, 1);
+        game.current_level_mut()
+            .enemies
+            .insert(at(THROW_RANGE + 1), far);
+        let missed = game.use_item(index);
+        assert!(!missed.success);
+        assert!(missed.message.contains("no enemy in range"));
+        assert_eq!(InventoryManager::get_item_count(&game.player), count);
+
+        for (name, dx) in [("Near", 2), ("Further", 4)] {
+            let mut enemy = Enemy::new(name.to_string(), EnemyType::Goblin, 1);
+            enemy.health = 500;
+            game.current_level_mut().enemies.insert(at(dx), enemy);
+        }
+        let thrown = game.use_item(index);
+        assert!(thrown.success, "{}", thrown.message);
+        assert!(thrown.message.contains("Near"));
+        assert!(!thrown.consumed);
+        assert_eq!(InventoryManager::get_item_count(&game.player), count - 1);
+        assert!(game.current_level().get_enemy_at(&at(2)).unwrap().health < 500);
+        assert_eq!(
+            game.current_level().get_enemy_at(&at(4)).unwrap().health,
+            500
+        );
+    }
+
+    #[test]
+    fn test_a_fireball_aimed_on_the_map_hits_around_where_it_lands() {
+        use crate::combat::area::FIREBALL_RANGE;
+        use crate::world::enemy::EnemyType;
+
+        let mut game = test_game();
+        assert!(game.area_ability().is_none());
+        game.player = Player::new("Tester".to_string(), ClassType::Mage);
+        let (index, name, _) = game.area_ability().unwrap();
+        assert_eq!(name, "Fireball");
+
+        let player = game.player_position();
+        let at = |dx: i32, dy: i32| Position::new(player.x + dx, player.y + dy);
+        let level = game.current_level_mut();
+        level.enemies.clear();
+        for row in &mut level.visible_tiles {
+            row.fill(true);
+        }
+        for (dx, dy, health) in [(3, 0, 1), (3, 1, 500), (5, 0, 500)] {
+            let mut enemy = Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1);
+            enemy.health = health;
+            level.enemies.insert(at(dx, dy), enemy);
+        }
+
+        // Too far away costs nothing
+        let mana = game.player.mana;
+        let missed = game.cast_area_ability(index, at(FIREBALL_RANGE + 1, 0));
+        assert!(!missed.success);
+        assert_eq!(game.player.mana, mana);
+
+        let cast = game.cast_area_ability(index, at(3, 1));
+        assert!(cast.success, "{}", cast.message);
+        assert!(cast.message.contains("The fireball engulfs 2 enemies!"));
+        assert!(game.player.mana < mana);
+        assert_eq!(game.stats.total_kills(), 1);
+
+        let level = game.current_level();
+        assert!(level.get_enemy_at(&at(3, 0)).is_none());
+        let survivor = level.get_enemy_at(&at(3, 1)).unwrap();
+        assert!(survivor.health < 500);
+        assert_ne!(survivor.alertness, Alertness::Unaware);
+        assert_eq!(level.get_enemy_at(&at(5, 0)).unwrap().health, 500);
+    }
+
+    #[test]
+    fn test_nearby_enemies_join_the_fight() {
+        use crate::world::enemy::EnemyType;
+
+        let mut game = test_game();
+        let player = game.player_position();
+        let at = |dx: i32, dy: i32| Position::new(player.x + dx, player.y + dy);
+        let level = game.current_level_mut();
+        level.enemies.clear();
+        level.tiles[at(1, 0).y as usize][at(1, 0).x as usize] = Tile::floor();
+        for (name, pos) in [
+            ("Bumped", at(1, 0)),
+            ("Beside", at(0, 1)),
+            ("Behind", at(2, 1)),
+            ("Distant", at(4, 4)),
+        ] {
+            let enemy = Enemy::new(name.to_string(), EnemyType::Goblin, 1);
+            level.enemies.insert(pos, enemy);
+        }
+
+        assert_eq!(game.move_player(1, 0), MoveOutcome::StartedCombat);
+        let names: Vec<String> = game.combat_enemies().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["Bumped", "Beside", "Behind"]);
+        assert_eq!(
+            game.combat_start_message(),
+            "Combat started with Bumped, Beside and Behind!"
+        );
+
+        // Only the enemies that fell are cleared away once the fight ends
+        game.current_level_mut()
+            .get_enemy_at_mut(&at(0, 1))
+            .unwrap()
+            .health = 0;
+        game.end_combat(CombatEnding::Fled);
+        assert!(matches!(game.game_state, GameState::Playing));
+        let level = game.current_level();
+        assert!(level.get_enemy_at(&at(0, 1)).is_none());
+        assert!(level.get_enemy_at(&at(1, 0)).is_some());
+        assert!(level.get_enemy_at(&at(2, 1)).is_some());
+    }
+
+    #[test]
+    fn test_noise_draws_enemies_that_chase_once_they_see_the_player() {
+        use crate::world::enemy::EnemyType;
+        use crate::world::level::NOTICE_RADIUS;
+
+        let mut game = test_game();
+        game.player.stats.dexterity = 4;
+        let player = game.player_position();
+        // Face whichever way has room for an open, lit corridor
+        let dir = if player.x < 40 { 1 } else { -1 };
+        let at = |dx: i32| Position::new(player.x + dir * dx, player.y);
+        let level = game.current_level_mut();
+        level.enemies.clear();
+        for dx in 1..=14 {
+            for dy in -1..=1 {
+                let pos = Position::new(at(dx).x, player.y + dy);
+                let mut tile = Tile::floor();
+                tile.visible = true;
+                level.tiles[pos.y as usize][pos.x as usize] = tile;
+                level.items.remove(&pos);
+            }
+        }
+        level.enemies.insert(
+            at(NOTICE_RADIUS + 1),
+            Enemy::new("Near".to_string(), EnemyType::Goblin, 1),
+        );
+        level
+            .enemies
+            .insert(at(13), Enemy::new("Far".to_string(), EnemyType::Goblin, 1));
+        let alertness =
+            |game: &Game, pos: Position| game.current_level().get_enemy_at(&pos).unwrap().alertness;
+
+        // Footsteps don't carry that far, but opening a chest does
+        game.make_noise(Noise::Footsteps);
+        assert_eq!(alertness(&game, at(NOTICE_RADIUS + 1)), Alertness::Unaware);
+        game.make_noise(Noise::Chest);
+        assert_eq!(
+            alertness(&game, at(NOTICE_RADIUS + 1)),
+            Alertness::Suspicious
+        );
+        assert_eq!(alertness(&game, at(13)), Alertness::Unaware);
+        game.current_level_mut().enemies.remove(&at(13));
+
+        // Out of sight it goes to look, then spots the player and chases
+        game.process_turn();
+        assert_eq!(alertness(&game, at(NOTICE_RADIUS)), Alertness::Suspicious);
+        game.process_turn();
+        assert_eq!(alertness(&game, at(NOTICE_RADIUS - 1)), Alertness::Alerted);
+        for _ in 0..NOTICE_RADIUS {
+            game.process_turn();
+        }
+        assert_eq!(alertness(&game, at(1)), Alertness::Alerted);
+
+        // Fighting alerts everyone in the fight
+        let mut sleeper = Enemy::new("Sleeper".to_string(), EnemyType::Goblin, 1);
+        sleeper.health = 500;
+        let beside = Position::new(player.x, player.y + 1);
+        game.current_level_mut().tiles[beside.y as usize][beside.x as usize] = Tile::floor();
+        game.current_level_mut().enemies.insert(beside, sleeper);
+        assert_eq!(game.move_player(0, 1), MoveOutcome::StartedCombat);
+        game.resolve_combat_turn(CombatAction::Attack, 0);
+        assert_eq!(alertness(&game, beside), Alertness::Alerted);
+    }
+
+    /// A game of `class` beside a chest with a needle trap already found
+    /// on it, with enough health to take it springing
+    fn beside_found_trap(class: ClassType) -> (Game, Position) {
+        let mut game = test_game();
+        game.player.class.class_type = class;
+        game.player.max_health = 100;
+        game.player.health = 100;
+        let chest_pos = place_chest(&mut game, Vec::new());
+        let chest = game
+            .current_level_mut()
+            .chest_contents
+            .get_mut(&chest_pos)
+            .unwrap();
+        chest.kind = ChestKind::Trapped(ChestTrap::Needle);
+        chest.found = true;
+        messages(&mut game);
+        (game, chest_pos)
+    }
+
+    #[test]
+    fn test_a_warrior_smashes_open_a_trapped_chest_a_mage_would_fumble() {
+        // A roll of 0.6 beats a dexterity 5 disarm but not a strength 15 smash
+        let middling = || StepRng::new(0x9999_9999_9999_9999, 0);
+        let needle = ChestTrap::Needle.damage(1);
+
+        let (mut mage, chest_pos) = beside_found_trap(ClassType::Mage);
+        assert_eq!(
+            mage.current_interaction(),
+            Some(Interaction::DisarmTrap(chest_pos))
+        );
+        mage.player.stats.dexterity = 5;
+        mage.disarm_chest(chest_pos, &mut middling());
+        assert_eq!(
+            messages(&mut mage)[0],
+            "Your hand slips as you work at the needle trap!"
+        );
+        assert!(mage.player.health <= 100 - needle);
+
+        let (mut warrior, chest_pos) = beside_found_trap(ClassType::Warrior);
+        assert_eq!(
+            warrior.current_interaction(),
+            Some(Interaction::SmashChest(chest_pos))
+        );
+        warrior.player.stats.dexterity = 5;
+        warrior.player.stats.strength = 15;
+        warrior.smash_chest(chest_pos, &mut middling());
+        let log = messages(&mut warrior);
+        assert_eq!(
+            log[0],
+            "You smash the chest open, crushing the needle trap."
+        );
+        assert!(log[1].starts_with("You open the chest"));
+        assert_eq!(warrior.player.health, 100);
+    }
+
+    #[test]
+    fn test_a_ranger_looks_a_chest_over_before_walking_into_it() {
+        let (mut warrior, chest_pos) = beside_found_trap(ClassType::Warrior);
+        let mut ranger = warrior.clone();
+        ranger.player.class.class_type = ClassType::Ranger;
+        for game in [&mut warrior, &mut ranger] {
+            game.current_level_mut()
+                .chest_contents
+                .get_mut(&chest_pos)
+                .unwrap()
+                .found = false;
+        }
+
+        // Walking in springs the trap on anyone else
+        warrior.move_player(1, 0);
+        assert!(warrior.player.health < 100);
+        assert!(!warrior
+            .current_level()
+            .chest_contents
+            .contains_key(&chest_pos));
+
+        // A Ranger checks it first, so a sharp enough look stops them short
+        ranger.player.stats.dexterity = 10;
+        ranger.player.stats.wisdom = 10;
+        let mut careful = (0..20)
+            .map(|seed| {
+                let mut game = ranger.clone();
+                game.rng = RngStreams::seeded(seed);
+                game.move_player(1, 0);
+                game
+            })
+            .find(|game| game.current_level().chest_contents.contains_key(&chest_pos))
+            .expect("a Ranger spots the trap at least once");
+        assert!(careful.current_level().chest_contents[&chest_pos].found);
+        assert_eq!(careful.player.health, 100);
+        assert!(messages(&mut careful)
+            .iter()
+            .any(|line| line.starts_with("You find a needle trap on the chest")));
+    }
+
+    #[test]
+    fn test_a_ranger_glimpses_enemies_just_past_sight() {
+        use crate::world::fog_of_war::{FogOfWar, FogOfWarConfig};
+
+        let seen_at = |class: ClassType| {
+            let mut game = test_game();
+            let player = Position::new(1, 2);
+            let goblin = Position::new(1 + game.view_radius + 1, 2);
+            arena(&mut game, 30, 3, player, goblin);
+            game.game_state = GameState::Playing;
+            game.player.class.class_type = class;
+            for row in &mut game.current_level_mut().tiles {
+                for tile in row {
+                    tile.visible = false;
+                }
+            }
+            game.update_visibility();
+            let level = game.current_level();
+            assert!(!level.tiles[2][goblin.x as usize].visible);
+            FogOfWar::new(FogOfWarConfig::default())
+                .process_position(level, goblin, player, crate::world::GlyphSet::Ascii)
+                .character
+        };
+        let ranger = seen_at(ClassType::Ranger);
+        let warrior = seen_at(ClassType::Warrior);
+        assert_ne!(ranger, warrior);
+        assert_eq!(ranger, 'e');
+    }
+
+    #[test]
+    fn test_a_mage_senses_the_way_down_once_a_level() {
+        let mut game = test_game();
+        arena(&mut game, 40, 9, Position::new(2, 5), Position::new(40, 9));
+        game.game_state = GameState::Playing;
+        let level = game.current_level_mut();
+        level.enemies.clear();
+        level.stairs_down_position = Some(Position::new(38, 2));
+        level.tiles[2][38].explored = false;
+
+        let result = game.sense_way_down();
+        assert!(!result.success);
+        assert_eq!(result.message, "Only a Mage can sense the way down.");
+
+        game.player.class.class_type = ClassType::Mage;
+        let result = game.sense_way_down();
+        assert!(result.success);
+        assert_eq!(
+            result.message,
+            "You close your eyes and sense the way down to the east →"
+        );
+        assert!(!game.sense_way_down().success);
+
+        // Once it's been seen there's nothing left to sense
+        let level = game.current_level_mut();
+        level.way_sensed = false;
+        level.tiles[2][38].explored = true;
+        assert_eq!(
+            game.sense_way_down().message,
+            "You already know the way down."
+        );
+        assert_eq!(compass(-3, -3), ("north-west", '↖'));
+        assert_eq!(compass(1, 5), ("south", '↓'));
+    }
+
+    /// An open level with the player at (2, 5), no enemies and `npc`
+    /// standing just east of them
+    fn meet(game: &mut Game, npc: Npc) -> Position {
+        arena(game, 40, 9, Position::new(2, 5), Position::new(40, 9));
+        game.game_state = GameState::Playing;
+        let level = game.current_level_mut();
+        level.enemies.clear();
+        let pos = Position::new(3, 5);
+        level.npcs.insert(pos, npc);
+        pos
+    }
+
+    #[test]
+    fn test_a_hermit_marks_the_way_down_on_the_map() {
+        use crate::world::npc::NpcKind;
+
+        let mut game = test_game();
+        let hermit = Npc::new(NpcKind::Hermit, DungeonType::Ruins, NpcOffer::Hint);
+        let pos = meet(&mut game, hermit);
+        let stairs = Position::new(38, 2);
+        game.current_level_mut().stairs_down_position = Some(stairs);
+        game.current_level_mut().tiles[2][38].explored = false;
+
+        // Walking into them starts a talk, and costs no turn
+        let turns = game.stats.turns;
+        assert_eq!(game.move_player(1, 0), MoveOutcome::StartedTalking);
+        assert_eq!(game.game_state, GameState::Dialogue(pos));
+        assert_eq!(game.player_position(), Position::new(2, 5));
+        assert_eq!(game.stats.turns, turns);
+        let dialogue = game.dialogue().unwrap();
+        assert_eq!(dialogue.speaker, "Hermit");
+        assert!((3..=5).contains(&dialogue.lines.len()));
+        assert_eq!(dialogue.offer.as_deref(), Some("Ask what they know"));
+
+        let mut game_loop = GameLoop::new();
+        game_loop.handle(&mut game, Input::Accept);
+        assert_eq!(game.game_state, GameState::Playing);
+        assert_eq!(
+            messages(&mut game).last().unwrap(),
+            "The Hermit marks the way down on your map, off to the east →"
+        );
+        let level = game.current_level();
+        assert!(level.tiles[2][38].explored && level.revealed_tiles[1][37]);
+        assert!(level.npcs[&pos].spent);
+
+        // They've nothing more to give, though they'll still talk
+        assert_eq!(
+            game.current_interaction(),
+            Some(Interaction::Talk {
+                pos,
+                name: "Hermit".to_string()
+            })
+        );
+        game.try_get_item();
+        assert_eq!(game.dialogue().unwrap().offer, None);
+        game_loop.handle(&mut game, Input::Back);
+        assert_eq!(game.game_state, GameState::Playing);
+        assert!(!game.accept_offer(pos).success);
+    }
+
+    #[test]
+    fn test_once_the_way_down_is_known_a_hermit_points_out_a_chest() {
+        use crate::world::npc::NpcKind;
+
+        let mut game = test_game();
+        let hermit = Npc::new(NpcKind::Hermit, DungeonType::Cavern, NpcOffer::Hint);
+        let pos = meet(&mut game, hermit);
+        let level = game.current_level_mut();
+        level.tiles[8][20] = Tile::chest();
+        level
+            .chest_contents
+            .insert(Position::new(20, 8), Chest::default());
+
+        let result = game.accept_offer(pos);
+        assert!(result.success);
+        assert!(result.message.contains("marks a chest"));
+        assert!(game.current_level().tiles[8][20].explored);
+
+        // With everything seen, there's nothing to tell and no hint spent
+        let mut hermit = game.current_level().npcs[&pos].clone();
+        hermit.spent = false;
+        game.current_level_mut().npcs.insert(pos, hermit);
+        assert!(!game.accept_offer(pos).success);
+        assert!(!game.current_level().npcs[&pos].spent);
+    }
+
+    #[test]
+    fn test_trades_and_errands_are_taken_once() {
+        use crate::world::npc::NpcKind;
+
+        let mut game = test_game();
+        let potion = Item::Consumable(Consumable::health_potion(20));
+        let offer = NpcOffer::Trade {
+            item: potion,
+            price: 30,
+        };
+        let pos = meet(
+            &mut game,
+            Npc::new(NpcKind::LostAdventurer, DungeonType::Forest, offer),
+        );
+        game.player.gold = 10;
+        let carried = InventoryManager::get_item_count(&game.player);
+        assert!(!game.accept_offer(pos).success);
+        game.player.gold = 50;
+        assert!(game.accept_offer(pos).success);
+        assert_eq!(game.player.gold, 20);
+        assert_eq!(InventoryManager::get_item_count(&game.player), carried + 1);
+        assert!(!game.accept_offer(pos).success);
+
+        // A scholar's errand goes in the quest journal
+        let mut game = test_game();
+        let quests = game.quests.len();
+        let scholar = Npc::new(
+            NpcKind::GhostlyScholar,
+            DungeonType::Ruins,
+            NpcOffer::Errand,
+        );
+        let level_index = game.current_dungeon().current_level;
+        let dungeon = game.current_dungeon_mut();
+        dungeon
+            .current_level_mut()
+            .npcs
+            .insert(Position::new(1, 1), scholar);
+        let result = game.accept_offer(Position::new(1, 1));
+        assert!(result
+            .message
+            .starts_with("New quest: Find the scholar's folio"));
+        assert_eq!(game.quests.len(), quests + 1);
+        assert_eq!(game.quests[quests].target_level, level_index as u32 + 2);
+    }
+
+    #[test]
+    fn test_enemies_never_step_onto_npcs() {
+        use crate::world::npc::NpcKind;
+
+        let mut game = test_game();
+        let hermit = Npc::new(NpcKind::Hermit, DungeonType::Ruins, NpcOffer::Hint);
+        let pos = meet(&mut game, hermit);
+        assert!(!game.is_free_for_enemy(pos));
+        // Straight behind the hermit a goblin is held up; from the side it
+        // steps past
+        let player = game.player_position();
+        assert_eq!(game.step_toward(Position::new(4, 5), player), None);
+        assert_eq!(
+            game.step_toward(Position::new(4, 4), player),
+            Some(Position::new(3, 4))
+        );
+    }
+
+    #[test]
+    fn test_a_cleric_heals_more_from_potions_and_lays_the_slain_to_rest() {
+        let potion = Consumable::health_potion(20);
+        let mut warrior = test_game().player;
+        let mut cleric = Player::new("Tester".to_string(), ClassType::Cleric);
+        for player in [&mut warrior, &mut cleric] {
+            player.max_health = 100;
+            player.health = 50;
+            potion.use_effect(player);
+        }
+        assert_eq!(warrior.health, 70);
+        assert_eq!(cleric.health, 75);
+
+        let goblin = Enemy::new(
+            "Goblin".to_string(),
+            crate::world::enemy::EnemyType::Goblin,
+            1,
+        );
+        let mage = Position::new(1, 2);
+        let fallen = Position::new(3, 2);
+        for (class, raisable) in [(ClassType::Warrior, true), (ClassType::Cleric, false)] {
+            let mut game = test_game();
+            arena(&mut game, 10, 3, Position::new(8, 2), mage);
+            game.player.class.class_type = class;
+            game.leave_corpses(&[fallen], std::slice::from_ref(&goblin), &[0]);
+            let level = game.current_level();
+            assert_eq!(level.corpses[&fallen].sanctified, !raisable);
+            assert_eq!(level.corpse_to_raise(mage).is_some(), raisable);
+        }
+    }
+
+    /// Swaps the current level for an open, lit area `width` tiles wide and
+    /// `height` tall inside a wall, with the player at `player` and a goblin
+    /// at `goblin`, and starts a fight with it
+    fn arena(game: &mut Game, width: i32, height: i32, player: Position, goblin: Position) {
+        use crate::world::enemy::EnemyType;
+
+        let mut level = Level::new(width as usize + 2, height as usize + 2);
+        for y in 1..=height {
+            for x in 1..=width {
+                let mut tile = Tile::floor();
+                tile.visible = true;
+                level.tiles[y as usize][x as usize] = tile;
+            }
+        }
+        level.player_position = player;
+        level.enemies.insert(
+            goblin,
+            Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1),
+        );
+        let dungeon = game.current_dungeon_mut();
+        dungeon.levels[dungeon.current_level] = level;
+        game.game_state = GameState::Combat(vec![goblin]);
+        // Fleeing always works when there's somewhere to go
+        game.player.stats.dexterity = 100;
+    }
+
+    #[test]
+    fn test_a_walled_in_player_is_pulled_free_on_the_second_ctrl_u() {
+        let mut game = test_game();
+        arena(&mut game, 10, 5, Position::new(2, 3), Position::new(1, 1));
+        game.game_state = GameState::Playing;
+        let level = game.current_level_mut();
+        level.enemies.clear();
+        level.tiles[3][9] = Tile::stairs_down();
+        level.stairs_down_position = Some(Position::new(9, 3));
+        let mut game_loop = GameLoop::new();
+        game.take_bug_reports();
+        messages(&mut game);
+
+        // Nothing's wrong yet, so there's nothing to pull free from
+        assert!(!game.is_stuck());
+        game_loop.handle(&mut game, Input::Unstick);
+        game_loop.handle(&mut game, Input::Unstick);
+        assert_eq!(game.player_position(), Position::new(2, 3));
+        assert_eq!(
+            messages(&mut game),
+            ["You aren't stuck: the way on is open from here."; 2]
+        );
+
+        // A wall across the room cuts the player off from the stairs
+        for y in 1..=5 {
+            game.current_level_mut().tiles[y][5] = Tile::wall();
+        }
+        assert!(game.is_stuck());
+        game.player.health = 100;
+
+        // The first press only asks, and anything else takes the question back
+        game_loop.handle(&mut game, Input::Unstick);
+        assert!(messages(&mut game)[0].contains("cost of 20 HP"));
+        game_loop.handle(&mut game, Input::Move { dx: 0, dy: 1 });
+        game_loop.handle(&mut game, Input::Unstick);
+        assert_eq!(game.player_position(), Position::new(2, 4));
+        assert!(game.take_bug_reports().is_empty());
+
+        game_loop.handle(&mut game, Input::Unstick);
+        assert_eq!(game.player_position(), Position::new(6, 4));
+        assert_eq!(game.player.health, 80);
+        assert!(!game.is_stuck());
+
+        let reports = game.take_bug_reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].heading, "Player stuck");
+        assert!(reports[0].lines[1].starts_with("World seed"));
+        assert!(reports[0]
+            .lines
+            .contains(&"Stairs down at (9, 3): unreachable".to_string()));
+    }
+
+    #[test]
+    fn test_an_enemy_that_closes_in_starts_the_fight_and_strikes_first() {
+        let mut game = test_game();
+        let player = Position::new(2, 3);
+        let start = Position::new(4, 3);
+        arena(&mut game, 10, 5, player, start);
+        game.game_state = GameState::Playing;
+        game.rng = RngStreams::seeded(11);
+        game.player.health = 100;
+        let goblin = game.current_level_mut().get_enemy_at_mut(&start).unwrap();
+        goblin.alert(player);
+        goblin.health = 500;
+        // A turn long enough for the goblin to take one step
+        goblin.energy = 0;
+        game.player.energy = 0;
+        game.player.stats.dexterity = 5;
+        messages(&mut game);
+
+        // The same blow, rolled from the same dice
+        let mut expected_player = game.player.clone();
+        let mut expected_goblin = game.current_level().get_enemy_at(&start).unwrap().clone();
+        let expected = combat::first_strike_with_rng(
+            &mut expected_player,
+            &mut expected_goblin,
+            &CombatContext::default(),
+            &mut game.rng.combat.clone(),
+        );
+
+        game.process_turn();
+        let beside = Position::new(3, 3);
+        assert_eq!(game.game_state, GameState::Combat(vec![beside]));
+        assert_eq!(game.player.health, expected_player.health);
+        assert_eq!(
+            100 - game.player.health,
+            expected
+                .damage_taken_from
+                .first()
+                .map_or(0, |&(_, damage)| damage)
+        );
+        let mut announced = vec!["The Goblin attacks you!".to_string()];
+        announced.extend(expected.messages);
+        assert_eq!(messages(&mut game), announced);
+        assert_eq!(game.combat_start_message(), announced.join(" "));
+
+        // The fight goes on from the player's first turn as usual
+        let mut game_loop = GameLoop::new();
+        let round = game_loop.combat_round(&mut game).unwrap();
+        assert_eq!(round.opening, Some(announced.join(" ")));
+        game_loop.handle(&mut game, Input::Combat(CombatAction::Flee));
+        game_loop.handle(&mut game, Input::CombatShown);
+        if game.game_state == GameState::Playing {
+            assert_eq!(game.ambush, None);
+        }
+    }
+
+    #[test]
+    fn test_a_fallen_leader_sends_its_pack_running() {
+        use crate::world::level::Room;
+
+        let mut game = test_game();
+        let player = Position::new(2, 3);
+        let leader = Position::new(3, 3);
+        arena(&mut game, 12, 5, player, leader);
+        game.rng = RngStreams::seeded(5);
+        game.player.health = 1000;
+        let level = game.current_level_mut();
+        level.rooms = vec![Room::new(0, 0, 13, 6)];
+        let member = Position::new(6, 3);
+        level.enemies.insert(
+            member,
+            Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1),
+        );
+        let chief = level.get_enemy_at_mut(&leader).unwrap();
+        chief.promote_to_leader();
+        chief.health = 1;
+        level.rally_packs();
+        assert!(level.enemies[&member].rallied);
+        messages(&mut game);
+
+        while game.current_level().enemies[&leader].is_alive() {
+            game.resolve_combat_turn(CombatAction::Attack, 0);
+        }
+        assert!(
+            messages(&mut game).contains(&"The goblins falter as their leader falls!".to_string())
+        );
+        let goblin = &game.current_level().enemies[&member];
+        assert!(!goblin.rallied);
+        assert_eq!(goblin.panicked_turns, PANIC_TURNS);
+
+        // Even hunting the player, it runs the other way
+        game.end_combat(CombatEnding::Victory);
+        let goblin = game.current_level_mut().get_enemy_at_mut(&member).unwrap();
+        goblin.alert(player);
+        goblin.energy = 0;
+        game.player.energy = 0;
+        game.player.stats.dexterity = 5;
+        game.process_turn();
+        let (&fled, goblin) = game.current_level().enemies.iter().next().unwrap();
+        assert_eq!(fled.x, member.x + 1);
+        assert_eq!(goblin.panicked_turns, PANIC_TURNS - 1);
+    }
+
+    #[test]
+    fn test_adaptive_difficulty_scales_only_enemies_spawned_after_an_easy_fight() {
+        let mut game = test_game();
+        game.adaptive_difficulty = true;
+        let goblin = Position::new(3, 2);
+        arena(&mut game, 6, 3, Position::new(2, 2), goblin);
+        game.player.max_health = 1000;
+        game.player.health = 1000;
+        game.current_level_mut()
+            .get_enemy_at_mut(&goblin)
+            .unwrap()
+            .health = 1;
+        while game.current_level().enemies[&goblin].is_alive() {
+            game.resolve_combat_turn(CombatAction::Attack, 0);
+        }
+        game.end_combat(CombatEnding::Victory);
+
+        game.world_seed = Some(7);
+        let plain =
+            Dungeon::generate_with_rng(game.dungeon_difficulty(), &mut StdRng::seed_from_u64(7));
+        game.enter_new_dungeon();
+        let multiplier = game.adaptive.multiplier;
+        assert!((multiplier - 1.05).abs() < 1e-6);
+        let below = &game.dungeons[0].levels[1].enemies;
+        assert!(!below.is_empty());
+        for (position, enemy) in &plain.levels[1].enemies {
+            let mut expected = enemy.clone();
+            expected.scale_strength(multiplier);
+            assert_eq!(below[position].max_health, expected.max_health);
+            assert_eq!(below[position].gold_reward, expected.gold_reward);
+        }
+
+        // Reaching the next level moves the multiplier on but leaves the
+        // enemies already there as they were
+        let before: Vec<i32> = below.values().map(|enemy| enemy.max_health).collect();
+        game.descend();
+        assert!((game.adaptive.multiplier - 1.1).abs() < 1e-6);
+        let after: Vec<i32> = game
+            .current_level()
+            .enemies
+            .values()
+            .map(|enemy| enemy.max_health)
+            .collect();
+        assert_eq!(after, before);
+    }
+
+    #[test]
+    fn test_opening_strikes_and_flanking_come_from_the_map() {
+        let mut game = test_game();
+        arena(&mut game, 6, 3, Position::new(3, 2), Position::new(4, 2));
+        game.game_state = GameState::Playing;
+        game.player.max_health = 1000;
+        game.player.health = 1000;
+        let behind = Position::new(2, 2);
+        game.current_level_mut().enemies.insert(
+            behind,
+            Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1),
+        );
+        for enemy in game.current_level_mut().enemies.values_mut() {
+            enemy.health = 500;
+        }
+
+        // Bumping into an unaware goblin opens the fight on the front foot,
+        // with its friend at the player's back
+        assert_eq!(game.move_player(1, 0), MoveOutcome::StartedCombat);
+        let context = game.combat_context();
+        assert!(context.flanked && context.opening_strike);
+        let result = game.resolve_combat_turn(CombatAction::Attack, 0);
+        assert!(result.messages.contains(&"You are flanked!".to_string()));
+        // The opening strike lasts the first turn only
+        assert!(!game.combat_context().opening_strike);
+
+        // Struck from range first, an alert goblin is caught off guard too
+        game.end_combat(CombatEnding::Fled);
+        game.current_level_mut().player_position = Position::new(3, 2);
+        game.current_level_mut().enemies.remove(&behind);
+        let goblin = game
+            .current_level_mut()
+            .get_enemy_at_mut(&Position::new(4, 2))
+            .unwrap();
+        goblin.alertness = Alertness::Alerted;
+        game.move_player(1, 0);
+        assert!(!game.combat_context().opening_strike);
+        game.end_combat(CombatEnding::Fled);
+        game.current_level_mut().player_position = Position::new(3, 2);
+        let goblin = game
+            .current_level_mut()
+            .get_enemy_at_mut(&Position::new(4, 2))
+            .unwrap();
+        goblin.winded_turns = 0;
+        goblin.struck_from_range = true;
+        game.move_player(1, 0);
+        assert_eq!(
+            game.combat_context(),
+            CombatContext {
+                opening_strike: true,
+                ..CombatContext::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_reloading_a_save_replays_a_fight_the_same_way() {
+        let mut game = test_game();
+        arena(&mut game, 10, 3, Position::new(5, 2), Position::new(4, 2));
+        game.player.max_health = 1000;
+        game.player.health = 1000;
+        game.current_level_mut()
+            .get_enemy_at_mut(&Position::new(4, 2))
+            .unwrap()
+            .health = 200;
+        let saved = serde_json::to_string(&game).unwrap();
+
+        let fight = |game: &mut Game| {
+            (0..6)
+                .flat_map(|_| game.resolve_combat_turn(CombatAction::Attack, 0).messages)
+                .collect::<Vec<_>>()
+        };
+        let first = fight(&mut game);
+        let mut reloaded: Game = serde_json::from_str(&saved).unwrap();
+        assert_eq!(fight(&mut reloaded), first);
+
+        // Time spent exploring doesn't change how the fight goes
+        let mut wandered: Game = serde_json::from_str(&saved).unwrap();
+        wandered.with_world_rng(|_, rng| rng.gen::<u64>());
+        assert_eq!(fight(&mut wandered), first);
+    }
+
+    #[test]
+    fn test_fleeing_steps_away_and_leaves_the_enemy_winded() {
+        let mut game = test_game();
+        let goblin = Position::new(4, 2);
+        arena(&mut game, 10, 3, Position::new(5, 2), goblin);
+
+        let result = game.resolve_combat_turn(CombatAction::Flee, 0);
+        assert!(result.player_fled);
+        game.end_combat(CombatEnding::Fled);
+
+        let player = game.player_position();
+        let steps = (player.x - 5).abs().max((player.y - 2).abs());
+        assert!((1..=2).contains(&steps));
+        assert!((player.x - goblin.x).abs().max((player.y - goblin.y).abs()) > 1);
+
+        // Back at an ordinary pace, so the goblin acts once a turn
+        game.player.stats.dexterity = 5;
+
+        // The goblin catches its breath instead of giving chase
+        for _ in 0..FLEE_WINDED_TURNS {
+            game.process_turn();
+            assert!(game.current_level().enemies.contains_key(&goblin));
+        }
+        game.process_turn();
+        assert!(!game.current_level().enemies.contains_key(&goblin));
+    }
+
+    #[test]
+    fn test_return_scrolls_teleport_to_the_entrance_and_back() {
+        use crate::item::Consumable;
+
+        let mut game = test_game();
+        let stairs = game.current_level().stairs_down_position.unwrap();
+        game.current_level_mut().player_position = stairs;
+        assert_eq!(game.take_stairs(true), MoveOutcome::UsedStairs);
+        let deep = game.player_position();
+        game.current_level_mut().enemies.clear();
+        give(&mut game, Item::Consumable(Consumable::return_scroll()));
+        give(&mut game, Item::Consumable(Consumable::return_scroll()));
+        let scrolls = InventoryManager::get_item_count(&game.player);
+        messages(&mut game);
+
+        let index = scrolls - 1;
+        assert!(game.use_item(index).success);
+        let entrance = game.current_dungeon().levels[0].rooms[0].center();
+        assert_eq!(game.current_dungeon().current_level, 0);
+        assert_eq!(game.player_position(), entrance);
+        assert!(messages(&mut game).contains(&"You teleport to level 1.".to_string()));
+        let expected = ReturnPoint {
+            dungeon_index: 0,
+            level: 1,
+            position: deep,
+        };
+        assert_eq!(game.return_point, Some(expected));
+
+        // The way back is kept in the save
+        let saved = serde_json::to_string(&game).unwrap();
+        let mut game: Game = serde_json::from_str(&saved).unwrap();
+        assert_eq!(game.return_point, Some(expected));
+
+        game.current_level_mut().enemies.clear();
+        assert!(game.use_item(scrolls - 2).success);
+        assert_eq!(game.current_dungeon().current_level, 1);
+        assert_eq!(game.player_position(), deep);
+        assert_eq!(game.return_point, None);
+        assert_eq!(InventoryManager::get_item_count(&game.player), scrolls - 2);
+        assert!(game.current_level().visible_tiles[deep.y as usize][deep.x as usize]);
+    }
+
+    #[test]
+    fn test_return_scrolls_fail_in_combat() {
+        use crate::item::consumable::ConsumableType;
+        use crate::item::Consumable;
+
+        let mut game = test_game();
+        arena(&mut game, 10, 3, Position::new(5, 2), Position::new(4, 2));
+        let index = give(&mut game, Item::Consumable(Consumable::return_scroll()));
+        let health = game.player.health;
+
+        let result = game.resolve_combat_turn(CombatAction::UseItem(index), 0);
+        assert!(result.messages[0].contains("won't work in the middle of a fight"));
+        assert!(!result.potion_drunk);
+        assert_eq!(game.player.health, health, "trying costs nothing");
+        assert!(matches!(
+            InventoryManager::get_item(&game.player, index),
+            Some(Item::Consumable(scroll)) if scroll.consumable_type == ConsumableType::ReturnScroll
+        ));
+        assert_eq!(game.return_point, None);
+    }
+
+    #[test]
+    fn test_enemies_move_as_often_as_their_speed_allows() {
+        use crate::world::enemy::EnemyType;
+
+        let mut game = test_game();
+        let start = Position::new(12, 1);
+        arena(&mut game, 14, 1, Position::new(1, 1), start);
+        game.game_state = GameState::Playing;
+        game.player.stats.dexterity = 5;
+        let level = game.current_level_mut();
+        level.enemies.clear();
+        let mut slime = Enemy::new("Slime".to_string(), EnemyType::Slime, 1);
+        slime.alert(Position::new(1, 1));
+        level.enemies.insert(start, slime);
+        let slime_x = |game: &Game| game.current_level().enemies.keys().next().unwrap().x;
+
+        // A beast is twice as fast as the player
+        game.process_turn();
+        assert_eq!(slime_x(&game), 10);
+
+        // Slowed, it keeps pace with them
+        let pos = Position::new(10, 1);
+        game.current_level_mut()
+            .enemies
+            .get_mut(&pos)
+            .unwrap()
+            .slow(2);
+        game.process_turn();
+        assert_eq!(slime_x(&game), 9);
+
+        // A hasted player leaves it fewer steps per action
+        game.current_level_mut()
+            .enemies
+            .get_mut(&Position::new(9, 1))
+            .unwrap()
+            .slowed_turns = 0;
+        game.player.haste_turns = HASTE_TURNS;
+        assert_eq!(game.player.speed(), 15);
+        for _ in 0..3 {
+            game.process_turn();
+        }
+        assert_eq!(slime_x(&game), 5);
+    }
+
+    #[test]
+    fn test_terrain_slows_unsteadies_and_burns() {
+        use crate::world::enemy::EnemyType;
+
+        let mut game = test_game();
+        let start = Position::new(12, 1);
+        arena(&mut game, 14, 1, Position::new(1, 1), start);
+        game.game_state = GameState::Playing;
+        game.player.stats.dexterity = 5;
+        let level = game.current_level_mut();
+        level.enemies.clear();
+        let mut goblin = Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1);
+        goblin.alert(Position::new(1, 1));
+        level.enemies.insert(start, goblin);
+        for (x, tile) in [
+            (2, Tile::rubble()),
+            (3, Tile::shallow_water()),
+            (4, Tile::lava()),
+            (8, Tile::lava()),
+        ] {
+            level.tiles[1][x] = tile;
+        }
+        let goblin_x = |game: &Game| game.current_level().enemies.keys().next().unwrap().x;
+        messages(&mut game);
+
+        // Climbing over rubble gives the goblin two steps
+        assert_eq!(game.move_player(1, 0), MoveOutcome::Moved);
+        assert!(messages(&mut game)[0].contains("rubble"));
+        game.process_turn();
+        assert_eq!(goblin_x(&game), 10);
+
+        // Wading costs the player their footing until they step out
+        game.move_player(1, 0);
+        assert!(game.player.off_balance);
+        game.process_turn();
+        assert_eq!(goblin_x(&game), 9);
+
+        // The goblin won't follow into the lava, which burns every turn
+        let health = game.player.health;
+        game.move_player(1, 0);
+        assert!(!game.player.off_balance);
+        game.process_turn();
+        assert_eq!(goblin_x(&game), 9);
+        assert_eq!(game.player.health, health - LAVA_DAMAGE);
+
+        game.player.health = 1;
+        game.process_turn();
+        assert!(matches!(game.game_state, GameState::GameOver));
+        assert_eq!(game.stats.killed_by.as_deref(), Some("lava"));
+    }
+
+    #[test]
+    fn test_fleeing_fails_when_cornered() {
+        let mut game = test_game();
+        // A dead end one tile wide, the goblin blocking the way out
+        arena(&mut game, 6, 1, Position::new(1, 1), Position::new(2, 1));
+
+        let result = game.resolve_combat_turn(CombatAction::Flee, 0);
+        assert!(!result.player_fled);
+        assert!(result
+            .messages
+            .contains(&"There's nowhere to run!".to_string()));
+        assert_eq!(game.player_position(), Position::new(1, 1));
+    }
+
+    #[test]
+    fn test_fleeing_never_lands_on_someone_friendly() {
+        use crate::world::npc::NpcKind;
+
+        let mut game = test_game();
+        // The only way out of the corridor is past the hermit
+        arena(&mut game, 6, 1, Position::new(2, 1), Position::new(3, 1));
+        let hermit = Npc::new(NpcKind::Hermit, DungeonType::Ruins, NpcOffer::Hint);
+        game.current_level_mut()
+            .npcs
+            .insert(Position::new(1, 1), hermit);
+
+        let result = game.resolve_combat_turn(CombatAction::Flee, 0);
+        assert!(!result.player_fled);
+        assert_eq!(game.player_position(), Position::new(2, 1));
+    }
+
+    #[test]
+    fn test_companions_follow_trade_places_and_rest_off_the_map() {
+        let mut game = test_game();
+        arena(&mut game, 10, 3, Position::new(2, 2), Position::new(9, 3));
+        game.game_state = GameState::Playing;
+        game.current_level_mut().enemies.clear();
+        game.player.companion = Some(Companion::wolf_pup());
+        let beside = |game: &Game| {
+            let (player, pup) = (
+                game.player_position(),
+                game.current_level().companion_position.unwrap(),
+            );
+            pup != player && (pup.x - player.x).abs().max((pup.y - player.y).abs()) == 1
+        };
+
+        game.process_turn();
+        assert!(beside(&game));
+
+        // Walking into the companion swaps the two of you round
+        let pup = game.current_level().companion_position.unwrap();
+        assert_eq!(game.move_player(pup.x - 2, pup.y - 2), MoveOutcome::Moved);
+        assert_eq!(game.player_position(), pup);
+        assert_eq!(
+            game.current_level().companion_position,
+            Some(Position::new(2, 2))
+        );
+
+        for _ in 0..4 {
+            assert_eq!(game.move_player(1, 0), MoveOutcome::Moved);
+            game.process_turn();
+            assert!(beside(&game));
+        }
+
+        game.player.companion.as_mut().unwrap().take_damage(100);
+        game.process_turn();
+        assert_eq!(game.current_level().companion_position, None);
+        for _ in 2..KNOCKOUT_TURNS {
+            game.process_turn();
+        }
+        messages(&mut game);
+        game.process_turn();
+        assert_eq!(messages(&mut game), ["Fang gets back on its feet."]);
+        assert!(beside(&game));
+    }
+
+    #[test]
+    fn test_a_charm_tames_a_hurt_beast_beside_the_player() {
+        use crate::world::enemy::EnemyType;
+
+        let mut game = test_game();
+        arena(&mut game, 10, 3, Position::new(2, 2), Position::new(3, 2));
+        game.game_state = GameState::Playing;
+        let charm = give(&mut game, Item::Consumable(Consumable::taming_charm()));
+
+        // The goblin beside the player is no beast
+        let result = game.use_item(charm);
+        assert!(!result.success);
+        assert!(game.player.companion.is_none());
+
+        let mut slime = Enemy::new("Oozing Slime".to_string(), EnemyType::Slime, 1);
+        slime.health = 1;
+        game.current_level_mut()
+            .enemies
+            .insert(Position::new(2, 1), slime);
+        let result = game.use_item(charm);
+        assert!(result.success);
+        assert_eq!(game.player.companion.as_ref().unwrap().kind, "Slime");
+        let level = game.current_level();
+        assert_eq!(level.companion_position, Some(Position::new(2, 1)));
+        assert!(level.get_enemy_at(&Position::new(2, 1)).is_none());
+        assert!(level.get_enemy_at(&Position::new(3, 2)).is_some());
+    }
+
+    #[test]
+    fn test_hotbar_empties_its_slot_with_the_last_one_used() {
+        let mut game = test_game();
+        messages(&mut game);
+        let first = give(&mut game, Item::Consumable(Consumable::health_potion(200)));
+        give(&mut game, Item::Consumable(Consumable::health_potion(200)));
+        InventoryManager::assign_hotbar(&mut game.player, first, 2);
+        game.player.health = 1;
+
+        let result = game.use_hotbar(2);
+        assert!(result.success);
+        assert_eq!(game.player.health, game.player.max_health);
+        assert_eq!(
+            InventoryManager::hotbar_slots(&game.player)[2].label(),
+            "3: Superior Health Potion x1"
+        );
+
+        game.player.health = 1;
+        let result = game.use_hotbar(2);
+        assert!(result.success);
+        assert!(result
+            .message
+            .ends_with("That was your last Superior Health Potion; hotbar slot 3 is now empty."));
+        assert_eq!(game.player.inventory.hotbar.get(2), None);
+        assert_eq!(game.use_hotbar(2).message, "Hotbar slot 3 is empty.");
+
+        // A slot whose consumable was dropped empties when pressed
+        let torch = give(&mut game, Item::Consumable(Consumable::torch()));
+        InventoryManager::assign_hotbar(&mut game.player, torch, 0);
+        game.drop_item(torch);
+        let result = game.use_hotbar(0);
+        assert!(!result.success);
+        assert_eq!(
+            result.message,
+            "You have no Torch left; hotbar slot 1 is now empty."
+        );
+        assert_eq!(game.player.inventory.hotbar.get(0), None);
+    }
+
+    #[test]
+    fn test_only_the_final_levels_exit_wins() {
+        // A corridor with the player at its west end and an exit beside them
+        fn exit_corridor(game: &mut Game) {
+            let mut level = Level::new(5, 3);
+            for x in 1..4 {
+                level.tiles[1][x] = Tile::floor();
+            }
+            level.tiles[1][2] = Tile::exit();
+            level.player_position = Position::new(1, 1);
+            let dungeon = game.current_dungeon_mut();
+            dungeon.levels[dungeon.current_level] = level;
+        }
+
+        let mut game = test_game();
+        messages(&mut game);
+        assert!(!game.current_dungeon().is_final_level());
+        exit_corridor(&mut game);
+        assert_eq!(game.move_player(1, 0), MoveOutcome::UsedStairs);
+        assert_eq!(game.current_dungeon().current_level, 1);
+        assert!(matches!(game.game_state, GameState::Playing));
+        assert!(messages(&mut game)[0].starts_with("This is no way out yet"));
+
+        let last = game.current_dungeon().levels.len() - 1;
+        game.current_dungeon_mut().current_level = last;
+        exit_corridor(&mut game);
+        assert_eq!(game.move_player(1, 0), MoveOutcome::ReachedExit);
+        assert!(matches!(game.game_state, GameState::Victory));
+        assert_eq!(game.player_position(), Position::new(2, 1));
+    }
+
+    /// An open hall with the player at its west end and an archer that has
+    /// already spotted them `gap` tiles east
+    fn archery_range(game: &mut Game, gap: i32) -> Position {
+        use crate::world::enemy::EnemyType;
+
+        let mut level = Level::new(16, 5);
+        for row in &mut level.tiles[1..4] {
+            for tile in &mut row[1..15] {
+                *tile = Tile::floor();
+            }
+        }
+        level.player_position = Position::new(2, 2);
+        let archer = Position::new(2 + gap, 2);
+        let mut enemy = Enemy::new("Keen Goblin Archer".to_string(), EnemyType::GoblinArcher, 2);
+        enemy.alert(level.player_position);
+        level.enemies.insert(archer, enemy);
+        let dungeon = game.current_dungeon_mut();
+        dungeon.levels[dungeon.current_level] = level;
+        // One enemy action a turn
+        game.player.stats.dexterity = 5;
+        game.update_visibility();
+        messages(game);
+        archer
+    }
+
+    #[test]
+    fn test_archers_take_aim_then_shoot_only_with_a_clear_line() {
+        let mut game = test_game();
+        let archer = archery_range(&mut game, 4);
+        let health = game.player.health;
+
+        game.process_turn();
+        assert_eq!(
+            messages(&mut game),
+            ["The Keen Goblin Archer draws back its bowstring!"]
+        );
+        assert_eq!(game.player.health, health);
+        assert!(game.take_effects().is_empty());
+
+        game.process_turn();
+        let shot = messages(&mut game);
+        assert!(shot[0].starts_with("An arrow hits you for"), "{shot:?}");
+        assert!(game.player.health < health);
+        // Felt as much as a blow in a fight
+        assert_eq!(game.take_effects(), [ScreenEffect::Hurt]);
+        assert!(game.take_sounds().contains(&SoundEvent::Hurt));
+        assert!(game.current_level().enemies.contains_key(&archer));
+        assert!(matches!(game.game_state, GameState::Playing));
+
+        // Behind a wall the archer has no shot and comes looking instead
+        let mut game = test_game();
+        let archer = archery_range(&mut game, 4);
+        for row in &mut game.current_level_mut().tiles[1..4] {
+            row[4] = Tile::wall();
+        }
+        game.update_visibility();
+        assert!(!game.current_level().in_line_of_fire(archer, 6));
+        let health = game.player.health;
+        for _ in 0..3 {
+            game.process_turn();
+        }
+        assert!(messages(&mut game).iter().all(|m| !m.contains("bowstring")));
+        assert_eq!(game.player.health, health);
+    }
+
+    #[test]
+    fn test_archers_back_away_to_keep_their_distance() {
+        let mut game = test_game();
+        archery_range(&mut game, 1);
+        let archer_x = |game: &Game| game.current_level().enemies.keys().next().unwrap().x;
+
+        game.process_turn();
+        assert_eq!(archer_x(&game), 4);
+        game.process_turn();
+        assert_eq!(archer_x(&game), 5);
+
+        // Far enough off, it stands and takes aim
+        game.process_turn();
+        assert_eq!(archer_x(&game), 5);
+        assert!(messages(&mut game)
+            .iter()
+            .any(|m| m.contains("draws back its bowstring")));
+    }
+
+    #[test]
+    fn test_a_shot_can_kill_outside_a_fight() {
+        let mut game = test_game();
+        let archer = archery_range(&mut game, 4);
+        game.current_level_mut()
+            .get_enemy_at_mut(&archer)
+            .unwrap()
+            .taking_aim = true;
+        game.player.health = 1;
+
+        game.process_turn();
+        assert!(matches!(game.game_state, GameState::GameOver));
+        assert_eq!(game.stats.killed_by.as_deref(), Some("Keen Goblin Archer"));
+        assert!(game.bestiary.entries()[0].1.max_damage_taken > 0);
+    }
+
+    #[test]
+    fn test_each_hint_is_given_once_a_run() {
+        let mut game = test_game();
+        game.hints = true;
+        archery_range(&mut game, 4);
+
+        assert_eq!(game.move_player(0, -1), MoveOutcome::Moved);
+        let hint = HintId::EnemySighted.text().to_string();
+        assert!(messages(&mut game).contains(&hint));
+        assert_eq!(game.move_player(0, 1), MoveOutcome::Moved);
+        assert!(!messages(&mut game).contains(&hint));
+
+        game.player.health = game.player.max_health / 5;
+        game.hint_at_condition();
+        game.hint_at_condition();
+        let low_health = HintId::LowHealth.text().to_string();
+        let given = messages(&mut game);
+        assert_eq!(given.iter().filter(|m| **m == low_health).count(), 1);
+
+        // The run remembers what it was told, and a reset forgets it
+        let json = serde_json::to_string(&game).unwrap();
+        let mut loaded: Game = serde_json::from_str(&json).unwrap();
+        loaded.apply_settings(&Settings::default());
+        assert!(!loaded.give_hint(HintId::EnemySighted));
+        loaded.reset_hints();
+        assert!(loaded.give_hint(HintId::EnemySighted));
+    }
+
+    #[test]
+    fn test_hints_can_be_turned_off() {
+        let mut game = test_game();
+        let settings = Settings {
+            hints: false,
+            ..Settings::default()
+        };
+        game.apply_settings(&settings);
+        archery_range(&mut game, 4);
+
+        game.move_player(0, -1);
+        game.player.health = 1;
+        game.player.unspent_stat_points = 1;
+        game.hint_at_condition();
+        assert!(!messages(&mut game).iter().any(|m| m.starts_with("Hint:")));
+        assert!(game.seen_hints.is_empty());
+    }
+
+    #[test]
+    fn test_low_health_is_warned_once_per_threshold_crossed() {
+        let mut game = test_game();
+        game.player.max_health = 100;
+        messages(&mut game);
+        let warnings_at = |game: &mut Game, health| {
+            game.player.health = health;
+            game.warn_of_low_health();
+            messages(game)
+        };
+
+        assert!(warnings_at(&mut game, 60).is_empty());
+        assert_eq!(warnings_at(&mut game, 45), ["Low health!"]);
+        // Staying low, or dropping further within the band, says nothing
+        assert!(warnings_at(&mut game, 30).is_empty());
+        assert_eq!(warnings_at(&mut game, 20).len(), 1);
+        assert!(warnings_at(&mut game, 10).is_empty());
+
+        // Climbing back into a band doesn't warn, but falling out again does
+        assert!(warnings_at(&mut game, 40).is_empty());
+        assert_eq!(warnings_at(&mut game, 20).len(), 1);
+        assert!(warnings_at(&mut game, 80).is_empty());
+        assert_eq!(warnings_at(&mut game, 40), ["Low health!"]);
+
+        // A single blow down past both thresholds warns just the once
+        warnings_at(&mut game, 80);
+        assert_eq!(warnings_at(&mut game, 5).len(), 1);
+        // and nothing is said over a fallen player
+        warnings_at(&mut game, 80);
+        assert!(warnings_at(&mut game, 0).is_empty());
+    }
+
+    #[test]
+    fn test_a_whetstone_edge_lasts_its_turns_then_dulls() {
+        use crate::combat::Combatant;
+        use crate::item::consumable::{WHETSTONE_BONUS, WHETSTONE_TURNS};
+
+        let mut game = test_game();
+        arena(&mut game, 6, 6, Position::new(2, 2), Position::new(6, 6));
+        game.game_state = GameState::Playing;
+        game.current_level_mut().enemies.clear();
+        let attack = game.player.attack_damage();
+
+        let index = give(&mut game, Item::Consumable(Consumable::whetstone()));
+        assert!(game.use_item(index).success);
+        assert_eq!(game.player.attack_damage(), attack + WHETSTONE_BONUS);
+        messages(&mut game);
+
+        for _ in 1..WHETSTONE_TURNS {
+            game.process_turn();
+        }
+        assert_eq!(game.player.attack_damage(), attack + WHETSTONE_BONUS);
+        game.process_turn();
+        assert_eq!(game.player.attack_damage(), attack);
+        assert!(messages(&mut game).iter().any(|m| m.contains("dulled")));
+
+        // There's nothing to hone without a weapon in hand
+        let index = give(&mut game, Item::Consumable(Consumable::whetstone()));
+        game.player
+            .inventory
+            .equipped
+            .insert(crate::item::EquipmentSlot::Weapon, None);
+        assert!(!game.use_item(index).success);
+        assert_eq!(game.player.honed_turns, 0);
     }
 }
@@ -0,0 +1,79 @@
+//! What comes of the player trying to move, for the front-ends to react to
+
+/// Why the player stayed where they were
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockReason {
+    Wall,
+    /// The edge of the map, or stairs that lead nowhere
+    Obstacle,
+    /// Asked to go down while not standing on stairs down
+    NoStairsDown,
+    /// Asked to go up while not standing on stairs up
+    NoStairsUp,
+    /// A grave from an earlier run; it's read with G from beside it
+    Grave,
+    /// A shrine's altar; it's prayed at with G from beside it
+    Altar,
+}
+
+impl BlockReason {
+    /// What the message log says
+    pub fn message(self) -> &'static str {
+        match self {
+            BlockReason::Wall => "You bump into a wall.",
+            BlockReason::Obstacle => "Something blocks the way.",
+            BlockReason::NoStairsDown => "There are no stairs down here.",
+            BlockReason::NoStairsUp => "There are no stairs up here.",
+            BlockReason::Grave => "A gravestone stands here. Press G beside it to read it.",
+            BlockReason::Altar => "An altar stands here. Press G beside it to pray.",
+        }
+    }
+}
+
+/// The result of `Game::move_player` and `Game::take_stairs`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveOutcome {
+    /// Stepped onto the tile
+    Moved,
+    /// Bumped into an enemy and started a fight
+    StartedCombat,
+    /// Walked into someone friendly and started talking
+    StartedTalking,
+    /// Went up or down to another level
+    UsedStairs,
+    /// Stepped onto stairs that wait for '>' or '<', as the settings ask
+    AtStairs,
+    /// Walked into a chest and opened it without moving
+    OpenedChest,
+    /// Stepped onto the final level's exit and won
+    ReachedExit,
+    /// Didn't move; the reason is already in the log
+    Blocked(BlockReason),
+}
+
+impl MoveOutcome {
+    /// Whether the enemies get their turn afterwards. Starting a fight hands
+    /// over to combat instead, and bumping into something or someone costs
+    /// nothing.
+    pub fn passes_turn(self) -> bool {
+        !matches!(
+            self,
+            MoveOutcome::StartedCombat | MoveOutcome::StartedTalking | MoveOutcome::Blocked(_)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_completed_moves_pass_the_turn() {
+        assert!(MoveOutcome::Moved.passes_turn());
+        assert!(MoveOutcome::OpenedChest.passes_turn());
+        assert!(MoveOutcome::AtStairs.passes_turn());
+        assert!(!MoveOutcome::StartedCombat.passes_turn());
+        assert!(!MoveOutcome::StartedTalking.passes_turn());
+        assert!(!MoveOutcome::Blocked(BlockReason::Wall).passes_turn());
+    }
+}
@@ -0,0 +1,69 @@
+//! The pause menu every front-end opens with Escape during play
+
+use serde::{Deserialize, Serialize};
+
+/// One entry of the pause menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PauseOption {
+    Resume,
+    Save,
+    Options,
+    Help,
+    ResetHints,
+    ExportMap,
+    QuitToMenu,
+}
+
+impl PauseOption {
+    /// The menu in order; front-ends that can't save leave `Save` out
+    pub fn menu(can_save: bool) -> Vec<PauseOption> {
+        [
+            PauseOption::Resume,
+            PauseOption::Save,
+            PauseOption::Options,
+            PauseOption::Help,
+            PauseOption::ResetHints,
+            PauseOption::ExportMap,
+            PauseOption::QuitToMenu,
+        ]
+        .into_iter()
+        .filter(|&option| can_save || option != PauseOption::Save)
+        .collect()
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PauseOption::Resume => "Resume",
+            PauseOption::Save => "Save game",
+            PauseOption::Options => "Options",
+            PauseOption::Help => "Help",
+            PauseOption::ResetHints => "Reset tutorial hints",
+            PauseOption::ExportMap => "Export map",
+            PauseOption::QuitToMenu => "Quit to main menu",
+        }
+    }
+}
+
+/// Question asked before quitting to the title screen; a permadeath game
+/// is saved on the way out, so nothing is lost
+pub fn quit_question(permadeath: bool) -> &'static str {
+    if permadeath {
+        "Quit to the main menu? Your run is saved and can be continued."
+    } else {
+        "Really quit? Unsaved progress will be lost."
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_menu_leaves_out_save_when_saving_is_unavailable() {
+        assert_eq!(PauseOption::menu(true).len(), 7);
+        let menu = PauseOption::menu(false);
+        assert!(!menu.contains(&PauseOption::Save));
+        assert_eq!(menu.first(), Some(&PauseOption::Resume));
+        assert_eq!(menu.last(), Some(&PauseOption::QuitToMenu));
+    }
+}
@@ -0,0 +1,472 @@
+//! Recording a run and watching it back
+//!
+//! With replays turned on in the options, a new run keeps everything it
+//! started from (the world seed, the character, the settings and the bones
+//! of earlier runs) along with every input the game loop accepted and the
+//! turn it came on. Generation and every roll come from the seeded streams,
+//! so feeding the same inputs to a game rebuilt from the same start plays
+//! the run out again exactly. Before each input is played back it's checked
+//! against the game as it now stands; one that no longer makes sense stops
+//! the playback with a `Desync` rather than pushing the game somewhere the
+//! run never went.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Difficulty, Game, GameLoop, GameState, Input};
+use crate::bones::BonesPool;
+use crate::character::Player;
+use crate::daily::DailyChallenge;
+use crate::inventory::{InventoryManager, RECIPES};
+use crate::settings::Settings;
+use crate::startup::StartupOptions;
+
+/// Milliseconds between two steps of a replay unless the watcher changes it
+pub const DEFAULT_STEP_MILLIS: u64 = 200;
+
+/// Quickest and slowest a replay can be played, in milliseconds a step
+pub const MIN_STEP_MILLIS: u64 = 25;
+pub const MAX_STEP_MILLIS: u64 = 1600;
+
+/// One thing the player did during a recorded run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ReplayAction {
+    Input(Input),
+    /// The options were changed mid-run
+    Settings(Settings),
+}
+
+/// An action and the turn it was taken on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayStep {
+    pub turn: u32,
+    pub action: ReplayAction,
+}
+
+/// A recorded run: how it started and everything done in it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub permadeath: bool,
+    pub run_id: u64,
+    #[serde(default)]
+    pub daily: Option<DailyChallenge>,
+    /// The character as the run began
+    pub player: Player,
+    /// The settings the run began with
+    pub settings: Settings,
+    #[serde(default)]
+    pub bones: BonesPool,
+    pub steps: Vec<ReplayStep>,
+    /// `game_hash` of the game when recording stopped
+    #[serde(default)]
+    pub final_hash: Option<u64>,
+}
+
+impl Replay {
+    /// Starts recording `game`, which has to be a seeded run that hasn't
+    /// played a turn yet. `settings` and `bones` are what the front-end
+    /// gave it.
+    pub fn begin(game: &Game, settings: &Settings, bones: &BonesPool) -> Option<Self> {
+        if game.stats.turns > 0 {
+            return None;
+        }
+        Some(Replay {
+            seed: game.world_seed?,
+            difficulty: game.difficulty,
+            permadeath: game.permadeath,
+            run_id: game.run_id,
+            daily: game.daily.clone(),
+            player: game.player.clone(),
+            settings: settings.clone(),
+            bones: bones.clone(),
+            steps: Vec::new(),
+            final_hash: None,
+        })
+    }
+
+    pub fn record(&mut self, turn: u32, action: ReplayAction) {
+        self.steps.push(ReplayStep { turn, action });
+    }
+
+    /// Stops recording, noting how the game stood at the end
+    pub fn finish(&mut self, game: &Game) {
+        self.final_hash = Some(game_hash(game));
+    }
+
+    /// The game as the run began, ready for the first step
+    pub fn start_game(&self) -> Game {
+        let options = StartupOptions {
+            seed: Some(self.seed),
+            difficulty: self.difficulty,
+            ..StartupOptions::default()
+        };
+        let mut game = Game::with_options(self.player.clone(), &options);
+        game.permadeath = self.permadeath;
+        game.daily = self.daily.clone();
+        game.run_id = self.run_id;
+        game.game_state = GameState::Playing;
+        game.apply_settings(&self.settings);
+        game.haunt(self.bones.clone());
+        game
+    }
+
+    /// "Aria the Mage, 312 steps"
+    pub fn summary(&self) -> String {
+        format!(
+            "{} the {}, {} steps",
+            self.player.name,
+            self.player.class.class_type,
+            self.steps.len()
+        )
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    pub fn from_json(json: &str) -> Option<Self> {
+        serde_json::from_str(json).ok()
+    }
+}
+
+/// A fingerprint of everything about a game that play decides: the same
+/// inputs from the same start always give the same hash. The log is left
+/// out, since front-ends add their own lines to it, and so is the
+/// recording itself.
+pub fn game_hash(game: &Game) -> u64 {
+    let mut value = serde_json::to_value(game).unwrap_or_default();
+    if let Some(fields) = value.as_object_mut() {
+        fields.remove("log");
+        fields.remove("replay");
+    }
+    // FNV-1a, the same on every build and platform
+    value
+        .to_string()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// Whether the game loop would act on `input` in the game as it stands;
+/// only inputs it acts on are recorded, and a recorded one it wouldn't act
+/// on any more means a replay has gone astray
+pub fn accepts(game: &Game, input: &Input) -> bool {
+    let in_range = |index: &usize| *index < InventoryManager::get_item_count(&game.player);
+    match (&game.game_state, input) {
+        (_, Input::AllocateStat(_)) => game.player.unspent_stat_points > 0,
+        (
+            GameState::Playing,
+            Input::Move { .. }
+            | Input::Stairs { .. }
+            | Input::GetItem
+            | Input::Hotbar(_)
+            | Input::CastArea { .. }
+            | Input::OpenInventory
+            | Input::OpenCharacter
+            | Input::Save
+            | Input::ExportMap
+            | Input::Pause
+            | Input::Quit
+            | Input::Unstick
+            | Input::Sense,
+        ) => true,
+        (
+            GameState::Inventory,
+            Input::UseItem(index)
+            | Input::DropItem(index)
+            | Input::Enchant(index)
+            | Input::AssignHotbar { index, .. },
+        ) => in_range(index),
+        (GameState::Inventory, Input::Craft(recipe)) => *recipe < RECIPES.len(),
+        (
+            GameState::Inventory
+            | GameState::Character
+            | GameState::Paused
+            | GameState::Dialogue(_),
+            Input::Back,
+        ) => true,
+        (GameState::Dialogue(_), Input::Accept) => true,
+        (GameState::Combat(_), Input::Combat(_) | Input::CombatShown) => true,
+        (GameState::Paused, Input::Choose(_)) => true,
+        _ => false,
+    }
+}
+
+/// Why a replay couldn't go on
+#[derive(Debug, Clone, PartialEq)]
+pub struct Desync {
+    /// Index of the step that couldn't be played
+    pub step: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for Desync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The replay went out of step at step {}: {}",
+            self.step + 1,
+            self.reason
+        )
+    }
+}
+
+impl std::error::Error for Desync {}
+
+/// Plays a replay back a step at a time, the way a front-end loop would
+#[derive(Debug)]
+pub struct ReplayPlayer {
+    replay: Replay,
+    pub game: Game,
+    game_loop: GameLoop,
+    /// Index of the next step to play
+    next: usize,
+    pub paused: bool,
+    /// Milliseconds between two steps
+    pub step_millis: u64,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        let mut game = replay.start_game();
+        game.update_visibility();
+        ReplayPlayer {
+            replay,
+            game,
+            game_loop: GameLoop::new(),
+            next: 0,
+            paused: false,
+            step_millis: DEFAULT_STEP_MILLIS,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.replay.steps.len()
+    }
+
+    /// "Step 12 of 312, 200 ms a step"
+    pub fn progress(&self) -> String {
+        let pace = if self.paused {
+            "paused".to_string()
+        } else {
+            format!("{} ms a step", self.step_millis)
+        };
+        format!("Step {} of {}, {pace}", self.next, self.replay.steps.len())
+    }
+
+    /// Halves the wait between steps
+    pub fn faster(&mut self) {
+        self.step_millis = (self.step_millis / 2).max(MIN_STEP_MILLIS);
+    }
+
+    /// Doubles the wait between steps
+    pub fn slower(&mut self) {
+        self.step_millis = (self.step_millis * 2).min(MAX_STEP_MILLIS);
+    }
+
+    /// Plays the next step. Returns false once there are none left, and a
+    /// `Desync` for a step that no longer fits the game, leaving the game
+    /// as it was before it.
+    pub fn step(&mut self) -> Result<bool, Desync> {
+        let Some(step) = self.replay.steps.get(self.next).cloned() else {
+            return Ok(false);
+        };
+        let desync = |reason: String| Desync {
+            step: self.next,
+            reason,
+        };
+        if step.turn != self.game.stats.turns {
+            return Err(desync(format!(
+                "it was taken on turn {}, but the game is on turn {}",
+                step.turn, self.game.stats.turns
+            )));
+        }
+
+        match step.action {
+            ReplayAction::Settings(settings) => self.game.apply_settings(&settings),
+            ReplayAction::Input(input) => {
+                // A front-end readies each round of a fight before asking for
+                // the next input, but not between a turn and its showing
+                if input != Input::CombatShown {
+                    self.game_loop.combat_round(&mut self.game);
+                }
+                if !accepts(&self.game, &input) {
+                    return Err(desync(format!(
+                        "{input:?} can't be done while {:?}",
+                        self.game.game_state
+                    )));
+                }
+                self.game_loop.handle(&mut self.game, input);
+                self.game.update_visibility();
+            }
+        }
+        self.next += 1;
+        Ok(true)
+    }
+
+    /// Plays every step left, then checks the game ended where the
+    /// recording did
+    pub fn play_to_end(&mut self) -> Result<(), Desync> {
+        while self.step()? {}
+        self.check_ending()
+    }
+
+    /// Whether the game stands where the recording left it, once every
+    /// step is played
+    pub fn check_ending(&self) -> Result<(), Desync> {
+        match self.replay.final_hash {
+            Some(hash) if hash != game_hash(&self.game) => Err(Desync {
+                step: self.next.saturating_sub(1),
+                reason: "the run ended differently from the recording".to_string(),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Where the last recorded run is kept, next to the save
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ReplayFile {
+    path: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ReplayFile {
+    const FILE_NAME: &'static str = "replay.json";
+
+    /// Uses the replay file inside `dir`
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> Self {
+        ReplayFile {
+            path: dir.into().join(Self::FILE_NAME),
+        }
+    }
+
+    /// The last recorded run, or None if there isn't one or it won't read
+    pub fn load(&self) -> Option<Replay> {
+        std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|json| Replay::from_json(&json))
+    }
+
+    /// Keeps `replay` in place of the last one
+    pub fn save(&self, replay: &Replay) -> anyhow::Result<()> {
+        crate::save::write_atomically(&self.path, replay.to_json())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl crate::save::DataFile for ReplayFile {
+    fn in_dir(dir: std::path::PathBuf) -> Self {
+        Self::new(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::{ClassType, StatType};
+
+    /// Records a short seeded run, driven the way `ReplayPlayer` drives it
+    fn recorded_run() -> (Game, Replay) {
+        let options = StartupOptions {
+            seed: Some(42),
+            ..StartupOptions::default()
+        };
+        let player = Player::new("Tester".to_string(), ClassType::Warrior);
+        let mut game = Game::with_options(player, &options);
+        game.game_state = GameState::Playing;
+        let settings = Settings::default();
+        game.apply_settings(&settings);
+        game.replay = Replay::begin(&game, &settings, &BonesPool::default());
+        game.update_visibility();
+
+        let mut game_loop = GameLoop::new();
+        let mut inputs = vec![Input::OpenInventory, Input::Back];
+        for (dx, dy) in [(1, 0), (0, 1), (-1, 0), (0, -1)] {
+            inputs.extend(std::iter::repeat_n(Input::Move { dx, dy }, 4));
+        }
+        inputs.push(Input::AllocateStat(StatType::Strength));
+        game.player.unspent_stat_points = 0;
+        for input in inputs {
+            if input == Input::AllocateStat(StatType::Strength) {
+                game.apply_settings(&Settings {
+                    hints: !settings.hints,
+                    ..settings.clone()
+                });
+            }
+            game_loop.combat_round(&mut game);
+            game_loop.handle(&mut game, input);
+            game.update_visibility();
+        }
+
+        let mut replay = game.replay.take().expect("the run is recorded");
+        replay.finish(&game);
+        (game, replay)
+    }
+
+    #[test]
+    fn test_a_replay_plays_back_to_the_same_game() {
+        let (game, replay) = recorded_run();
+        assert!(replay.steps.len() > 2);
+        // Refused inputs aren't kept
+        assert!(!replay.steps.iter().any(
+            |step| step.action == ReplayAction::Input(Input::AllocateStat(StatType::Strength))
+        ));
+        assert!(replay
+            .steps
+            .iter()
+            .any(|step| matches!(step.action, ReplayAction::Settings(_))));
+
+        let mut player = ReplayPlayer::new(replay.clone());
+        assert_eq!(player.play_to_end(), Ok(()));
+        assert!(player.is_finished());
+        assert_eq!(game_hash(&player.game), game_hash(&game));
+
+        // And it survives being written out
+        let reread = Replay::from_json(&replay.to_json()).expect("the replay reads back");
+        assert_eq!(ReplayPlayer::new(reread).play_to_end(), Ok(()));
+    }
+
+    #[test]
+    fn test_a_step_that_no_longer_fits_stops_the_replay() {
+        let (_, mut replay) = recorded_run();
+        let back = replay
+            .steps
+            .iter()
+            .position(|step| step.action == ReplayAction::Input(Input::Back))
+            .expect("the inventory is closed");
+        replay.steps[back].action = ReplayAction::Input(Input::UseItem(99));
+
+        let mut player = ReplayPlayer::new(replay);
+        let desync = player.play_to_end().unwrap_err();
+        assert_eq!(desync.step, back);
+        assert!(desync.to_string().contains(&format!("step {}", back + 1)));
+    }
+
+    #[test]
+    fn test_a_different_ending_is_noticed() {
+        let (_, mut replay) = recorded_run();
+        replay.final_hash = replay.final_hash.map(|hash| hash ^ 1);
+        assert!(ReplayPlayer::new(replay).play_to_end().is_err());
+    }
+
+    #[test]
+    fn test_playback_speed_stays_in_bounds() {
+        let (_, replay) = recorded_run();
+        let mut player = ReplayPlayer::new(replay);
+        for _ in 0..10 {
+            player.faster();
+        }
+        assert_eq!(player.step_millis, MIN_STEP_MILLIS);
+        for _ in 0..10 {
+            player.slower();
+        }
+        assert_eq!(player.step_millis, MAX_STEP_MILLIS);
+        player.paused = true;
+        assert!(player.progress().ends_with("paused"));
+    }
+}
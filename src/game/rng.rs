@@ -0,0 +1,73 @@
+//! The random number streams a run rolls from
+//!
+//! Everything rolled during play comes from two generators saved with the
+//! game instead of the thread's, so reloading a save and doing the same
+//! things again turns out the same way. The world stream covers what goes
+//! on around the player: enemies wandering, ambient events, loot (what the
+//! slain drop and what corpses turn up), level-ups, graves and enemies
+//! moving back into levels. The combat stream covers the blows of a fight:
+//! damage, crits, dodges and fleeing. Keeping them apart means a turn spent
+//! exploring never changes how the next fight goes.
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// ChaCha stream the combat generator reads, so that from one seed it
+/// never repeats the world's rolls
+const COMBAT_STREAM: u64 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RngStreams {
+    pub world: ChaCha8Rng,
+    pub combat: ChaCha8Rng,
+}
+
+impl RngStreams {
+    /// Streams that roll the same for every run given `seed`
+    pub fn seeded(seed: u64) -> Self {
+        Self::from_world(ChaCha8Rng::seed_from_u64(seed))
+    }
+
+    /// Streams no other run shares
+    pub fn from_entropy() -> Self {
+        Self::from_world(ChaCha8Rng::from_entropy())
+    }
+
+    fn from_world(world: ChaCha8Rng) -> Self {
+        let mut combat = world.clone();
+        combat.set_stream(COMBAT_STREAM);
+        RngStreams { world, combat }
+    }
+}
+
+/// Saves from before the streams were kept start new ones
+impl Default for RngStreams {
+    fn default() -> Self {
+        Self::from_entropy()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_streams_survive_a_save_and_stay_apart() {
+        let mut streams = RngStreams::seeded(42);
+        assert_eq!(streams, RngStreams::seeded(42));
+        assert_ne!(
+            streams.world.gen::<u64>(),
+            streams.combat.gen::<u64>(),
+            "the combat stream repeats the world's"
+        );
+
+        let saved = serde_json::to_string(&streams).unwrap();
+        let mut loaded: RngStreams = serde_json::from_str(&saved).unwrap();
+        for _ in 0..10 {
+            assert_eq!(streams.world.gen::<u64>(), loaded.world.gen::<u64>());
+            assert_eq!(streams.combat.gen::<u64>(), loaded.combat.gen::<u64>());
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! What a page embedding the browser build can ask of the game
+//!
+//! The web front-end hands these to JavaScript as JSON: a snapshot of where
+//! the game stands, the moments worth reacting to as they happen, and the
+//! whole game as a string for the page to keep wherever it likes. They're
+//! kept here, clear of the DOM, so they're built and tested with the rest
+//! of the game.
+
+use serde::Serialize;
+
+use super::{Game, GameState};
+
+/// Newest log lines a snapshot carries
+pub const SNAPSHOT_MESSAGES: usize = 10;
+
+/// The player, as a snapshot shows them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlayerSnapshot {
+    pub name: String,
+    pub class: String,
+    pub level: u32,
+    pub experience: u32,
+    pub health: i32,
+    pub max_health: i32,
+    pub mana: i32,
+    pub max_mana: i32,
+    pub gold: u32,
+}
+
+/// Where the game stands, for the page to show however it likes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GameSnapshot {
+    pub player: PlayerSnapshot,
+    pub dungeon: String,
+    /// Level of the dungeon the player is on, counting from 1
+    pub level: usize,
+    /// What's on screen: one of the tags `state_tag` gives
+    pub state: &'static str,
+    /// The newest lines of the message log, oldest first
+    pub messages: Vec<String>,
+}
+
+impl GameSnapshot {
+    pub fn of(game: &Game) -> Self {
+        let player = &game.player;
+        let dungeon = game.current_dungeon();
+        GameSnapshot {
+            player: PlayerSnapshot {
+                name: player.name.clone(),
+                class: player.class.class_type.to_string(),
+                level: player.level,
+                experience: player.experience,
+                health: player.health,
+                max_health: player.max_health,
+                mana: player.mana,
+                max_mana: player.max_mana,
+                gold: player.gold,
+            },
+            dungeon: dungeon.name.clone(),
+            level: dungeon.current_level + 1,
+            state: state_tag(&game.game_state),
+            messages: game
+                .log
+                .latest(SNAPSHOT_MESSAGES)
+                .map(|entry| entry.text.clone())
+                .collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// The snapshot's name for a game state: "playing", "combat", "game_over"
+pub fn state_tag(state: &GameState) -> &'static str {
+    match state {
+        GameState::MainMenu => "main_menu",
+        GameState::Playing => "playing",
+        GameState::Combat(_) => "combat",
+        GameState::Inventory => "inventory",
+        GameState::Character => "character",
+        GameState::Paused => "paused",
+        GameState::Dialogue(_) => "dialogue",
+        GameState::GameOver => "game_over",
+        GameState::Victory => "victory",
+    }
+}
+
+/// A moment the page is told about as it happens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GameEvent {
+    /// The player reached `level`
+    LevelUp {
+        level: u32,
+    },
+    Death,
+    Victory,
+}
+
+impl GameEvent {
+    /// `{"type":"level_up","level":3}`
+    pub fn to_json(self) -> String {
+        serde_json::to_string(&self).unwrap_or_default()
+    }
+}
+
+/// Keeps an eye on a game for the moments the page is told about, by
+/// comparing it with how it stood when last looked at
+#[derive(Debug, Clone, Default)]
+pub struct EventWatch {
+    /// The player's level when last looked at
+    level: Option<u32>,
+    /// Whether the run had ended when last looked at
+    ended: bool,
+}
+
+impl EventWatch {
+    /// What happened since the game was last looked at. A new game or a
+    /// loaded one starting lower down counts as no level-up.
+    pub fn poll(&mut self, game: &Game) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        let level = game.player.level;
+        if self.level.is_some_and(|last| level > last) {
+            events.push(GameEvent::LevelUp { level });
+        }
+        self.level = Some(level);
+
+        let ending = match game.game_state {
+            GameState::GameOver => Some(GameEvent::Death),
+            GameState::Victory => Some(GameEvent::Victory),
+            _ => None,
+        };
+        if !self.ended {
+            events.extend(ending);
+        }
+        self.ended = ending.is_some();
+        events
+    }
+}
+
+/// The whole game as a string, for the page to keep; empty if it couldn't
+/// be written
+pub fn export_game(game: &Game) -> String {
+    serde_json::to_string(game).unwrap_or_default()
+}
+
+/// A game the page kept with `export_game`, ready to play on, or None if the
+/// string isn't one
+pub fn import_game(data: &str) -> Option<Game> {
+    let mut game: Game = serde_json::from_str(data).ok()?;
+    game.update_visibility();
+    Some(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::{ClassType, Player};
+    use crate::log::LogCategory;
+
+    fn test_game() -> Game {
+        let player = Player::new("Tester".to_string(), ClassType::Mage);
+        let mut game = Game::new(player);
+        game.game_state = GameState::Playing;
+        game
+    }
+
+    #[test]
+    fn test_snapshots_show_the_player_the_place_and_the_latest_lines() {
+        let mut game = test_game();
+        for turn in 0..15 {
+            game.log.push(LogCategory::System, format!("Line {turn}"));
+        }
+        let snapshot = GameSnapshot::of(&game);
+        assert_eq!(snapshot.player.name, "Tester");
+        assert_eq!(snapshot.player.class, "Mage");
+        assert_eq!(snapshot.level, 1);
+        assert_eq!(snapshot.state, "playing");
+        assert_eq!(snapshot.messages.len(), SNAPSHOT_MESSAGES);
+        assert_eq!(snapshot.messages.last().unwrap(), "Line 14");
+
+        let json: serde_json::Value = serde_json::from_str(&snapshot.to_json()).unwrap();
+        assert_eq!(json["player"]["health"], game.player.health);
+        assert_eq!(json["dungeon"], game.current_dungeon().name.as_str());
+        assert_eq!(state_tag(&GameState::Combat(Vec::new())), "combat");
+    }
+
+    #[test]
+    fn test_level_ups_and_endings_are_told_once() {
+        let mut game = test_game();
+        let mut watch = EventWatch::default();
+        assert!(watch.poll(&game).is_empty());
+
+        game.player.level += 1;
+        let level = game.player.level;
+        assert_eq!(watch.poll(&game), [GameEvent::LevelUp { level }]);
+        assert!(watch.poll(&game).is_empty());
+        assert_eq!(
+            GameEvent::LevelUp { level: 3 }.to_json(),
+            r#"{"type":"level_up","level":3}"#
+        );
+
+        game.game_state = GameState::GameOver;
+        assert_eq!(watch.poll(&game), [GameEvent::Death]);
+        assert!(watch.poll(&game).is_empty());
+        assert_eq!(GameEvent::Victory.to_json(), r#"{"type":"victory"}"#);
+
+        // A fresh character starting over is no level-up
+        let mut fresh = test_game();
+        fresh.player.level = 1;
+        assert!(watch.poll(&fresh).is_empty());
+    }
+
+    #[test]
+    fn test_saved_games_load_back_and_junk_is_refused() {
+        let mut game = test_game();
+        game.player.gold = 321;
+        game.log.push(LogCategory::Loot, "You find 321 gold.");
+        let loaded = import_game(&export_game(&game)).unwrap();
+        assert_eq!(GameSnapshot::of(&loaded), GameSnapshot::of(&game));
+
+        assert!(import_game("").is_none());
+        assert!(import_game("{\"player\": 3}").is_none());
+    }
+}
@@ -0,0 +1,212 @@
+//! Run statistics shown on the game over and victory screens
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::combat::CombatResult;
+use crate::world::EnemyKind;
+
+/// Counters describing what the player accomplished this run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameStats {
+    /// World turns elapsed
+    pub turns: u32,
+    pub enemies_killed: BTreeMap<EnemyKind, u32>,
+    pub damage_dealt: u32,
+    pub damage_taken: u32,
+    pub gold_earned: u32,
+    pub items_looted: u32,
+    /// Deepest dungeon level reached, counting from 1
+    pub deepest_level: u32,
+    pub potions_drunk: u32,
+    /// Name of the enemy that landed the killing blow
+    pub killed_by: Option<String>,
+}
+
+impl GameStats {
+    pub fn new() -> Self {
+        GameStats {
+            turns: 0,
+            enemies_killed: BTreeMap::new(),
+            damage_dealt: 0,
+            damage_taken: 0,
+            gold_earned: 0,
+            items_looted: 0,
+            deepest_level: 1,
+            potions_drunk: 0,
+            killed_by: None,
+        }
+    }
+
+    /// Records the outcome of one combat round; `slain` lists the kinds of
+    /// the enemies killed in it
+    pub fn record_combat(
+        &mut self,
+        result: &CombatResult,
+        slain: impl IntoIterator<Item = EnemyKind>,
+    ) {
+        self.damage_dealt += result.player_damage_dealt.max(0) as u32;
+        self.damage_taken += result.enemy_damage_dealt.max(0) as u32;
+
+        if result.potion_drunk {
+            self.potions_drunk += 1;
+        }
+        for kind in slain {
+            *self.enemies_killed.entry(kind).or_insert(0) += 1;
+        }
+    }
+
+    /// Notes that the player reached the given level (1-based)
+    pub fn record_depth(&mut self, level: u32) {
+        self.deepest_level = self.deepest_level.max(level);
+    }
+
+    pub fn total_kills(&self) -> u32 {
+        self.enemies_killed.values().sum()
+    }
+
+    /// Label/value rows for the end-of-game summary table
+    pub fn summary_rows(&self) -> Vec<(String, String)> {
+        let mut rows = vec![
+            ("Turns taken".to_string(), self.turns.to_string()),
+            ("Enemies slain".to_string(), self.total_kills().to_string()),
+        ];
+
+        for (kind, count) in &self.enemies_killed {
+            rows.push((format!("  {kind}"), count.to_string()));
+        }
+
+        rows.extend([
+            ("Damage dealt".to_string(), self.damage_dealt.to_string()),
+            ("Damage taken".to_string(), self.damage_taken.to_string()),
+            ("Gold earned".to_string(), self.gold_earned.to_string()),
+            ("Items looted".to_string(), self.items_looted.to_string()),
+            ("Deepest level".to_string(), self.deepest_level.to_string()),
+            ("Potions drunk".to_string(), self.potions_drunk.to_string()),
+        ]);
+
+        rows
+    }
+}
+
+impl Default for GameStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::{ClassType, Player};
+    use crate::game::{Game, GameState};
+    use crate::inventory::InventoryManager;
+    use crate::item::{Consumable, Item};
+
+    fn test_game() -> Game {
+        let player = Player::new("Tester".to_string(), ClassType::Warrior);
+        let mut game = Game::new(player);
+        game.game_state = GameState::Playing;
+        game
+    }
+
+    fn potion() -> Item {
+        Item::Consumable(Consumable::health_potion(20))
+    }
+
+    #[test]
+    fn test_scripted_run_updates_counters() {
+        let mut game = test_game();
+
+        // Three world turns pass
+        for _ in 0..3 {
+            game.process_turn();
+        }
+
+        // Pick up a potion from the floor, then drink it
+        let pos = game.player_position();
+        game.current_level_mut().items.insert(pos, potion());
+        game.try_get_item();
+        let index = InventoryManager::get_item_count(&game.player) - 1;
+        assert!(game.use_item(index).consumed);
+
+        // Win one round of combat against an undead enemy, drinking a potion
+        // in another
+        let mut round = CombatResult::new();
+        round.player_damage_dealt = 12;
+        round.enemy_damage_dealt = 5;
+        round.potion_drunk = true;
+        game.stats.record_combat(&round, []);
+
+        let mut finish = CombatResult::new();
+        finish.player_damage_dealt = 8;
+        finish.enemy_defeated = true;
+        game.stats.record_combat(&finish, [EnemyKind::Undead]);
+
+        // Pick up what it dropped
+        game.current_level_mut().drop_loot(pos, 15, vec![potion()]);
+        game.try_get_item();
+
+        let stats = &game.stats;
+        assert_eq!(stats.turns, 3);
+        assert_eq!(stats.items_looted, 2);
+        assert_eq!(stats.potions_drunk, 2);
+        assert_eq!(stats.damage_dealt, 20);
+        assert_eq!(stats.damage_taken, 5);
+        assert_eq!(stats.gold_earned, 15);
+        assert_eq!(stats.enemies_killed.get(&EnemyKind::Undead), Some(&1));
+        assert_eq!(stats.total_kills(), 1);
+    }
+
+    #[test]
+    fn test_only_potions_count_as_drunk() {
+        let mut game = test_game();
+        let pos = game.player_position();
+        game.current_level_mut()
+            .items
+            .insert(pos, Item::Consumable(Consumable::torch()));
+        game.try_get_item();
+        let index = InventoryManager::get_item_count(&game.player) - 1;
+        assert!(game.use_item(index).consumed, "the torch is lit");
+        assert_eq!(game.stats.potions_drunk, 0);
+    }
+
+    #[test]
+    fn test_turns_only_count_while_playing() {
+        let mut game = test_game();
+        game.game_state = GameState::Inventory;
+        game.process_turn();
+        assert_eq!(game.stats.turns, 0);
+    }
+
+    #[test]
+    fn test_deepest_level_never_decreases() {
+        let mut stats = GameStats::new();
+        assert_eq!(stats.deepest_level, 1);
+
+        stats.record_depth(3);
+        stats.record_depth(2);
+        assert_eq!(stats.deepest_level, 3);
+    }
+
+    #[test]
+    fn test_stats_survive_save_and_load() {
+        let mut stats = GameStats::new();
+        stats.turns = 42;
+        stats.record_depth(2);
+        stats.enemies_killed.insert(EnemyKind::Beast, 3);
+
+        let json = serde_json::to_string(&stats).unwrap();
+        let mut loaded: GameStats = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, stats);
+
+        // A loaded save keeps counting from where it left off
+        loaded.turns += 1;
+        assert_eq!(loaded.turns, 43);
+
+        // Saves from before stats existed start from a fresh record
+        let empty: GameStats = serde_json::from_str("{}").unwrap();
+        assert_eq!(empty, GameStats::new());
+    }
+}
@@ -0,0 +1,289 @@
+//! Working out what the player can see
+//!
+//! Sight is worked out at once whenever the player moves, since it decides
+//! what enemies and items they notice; the level remembers what was in
+//! sight, so only that and the new view are touched however big the level
+//! is. Marking the wider area around the player as explored only changes
+//! what the map shows, so it's a pass that can be resumed: the web
+//! front-end spreads it over several frames, and everything else finishes
+//! it straight away.
+
+use std::collections::HashSet;
+
+use crate::world::{Level, Position};
+
+/// How far either side of the player the explored area reaches, in tiles
+const EXPLORE_HALF_WIDTH: i32 = 30;
+/// How far above and below the player the explored area reaches, in tiles
+const EXPLORE_HALF_HEIGHT: i32 = 10;
+/// How many tiles past their sight a Ranger makes out enemies and loot
+pub const RANGER_GLIMPSE_RANGE: i32 = 2;
+
+fn on_level(level: &Level, x: i32, y: i32) -> bool {
+    x >= 0 && x < level.width as i32 && y >= 0 && y < level.height as i32
+}
+
+/// Marks everything within `view_radius` of the player as visible and
+/// explored, and nothing else as visible. Only what was visible before and
+/// what is now is touched, so the cost follows the view area and not the
+/// level's size. Returns how many tiles were touched.
+pub fn update_sight(level: &mut Level, view_radius: i32) -> usize {
+    let player_pos = level.player_position;
+
+    if level.visible_positions.is_empty() {
+        // Saves from before the level kept this set still mark what was in
+        // sight, so find it the slow way once
+        level.visible_positions = marked_visible(level);
+    }
+    let previous = std::mem::take(&mut level.visible_positions);
+    for pos in &previous {
+        if !on_level(level, pos.x, pos.y) {
+            continue;
+        }
+        level.visible_tiles[pos.y as usize][pos.x as usize] = false;
+        if let Some(tile) = level.get_tile_mut(pos.x, pos.y) {
+            tile.visible = false;
+        }
+    }
+
+    // Reveal a circular area around the player
+    for dy in -view_radius..=view_radius {
+        for dx in -view_radius..=view_radius {
+            let x = player_pos.x + dx;
+            let y = player_pos.y + dy;
+            if !on_level(level, x, y) || dx * dx + dy * dy > view_radius * view_radius {
+                continue;
+            }
+            level.visible_tiles[y as usize][x as usize] = true;
+            level.revealed_tiles[y as usize][x as usize] = true;
+            if let Some(tile) = level.get_tile_mut(x, y) {
+                tile.explored = true;
+                tile.visible = true;
+            }
+            level.visible_positions.insert(Position::new(x, y));
+        }
+    }
+    previous.len() + level.visible_positions.len()
+}
+
+/// Every position the level marks as visible, found by looking at them all
+fn marked_visible(level: &Level) -> HashSet<Position> {
+    let mut visible = HashSet::new();
+    for (y, row) in level.visible_tiles.iter().enumerate() {
+        for (x, &seen) in row.iter().enumerate() {
+            let tile_seen = level
+                .tiles
+                .get(y)
+                .and_then(|tiles| tiles.get(x))
+                .is_some_and(|tile| tile.visible);
+            if seen || tile_seen {
+                visible.insert(Position::new(x as i32, y as i32));
+            }
+        }
+    }
+    visible
+}
+
+/// Notes the ring of positions up to `extra` tiles past `view_radius`,
+/// where enemies and loot can be made out though the ground can't. An
+/// `extra` of 0 clears it.
+pub fn update_glimpse(level: &mut Level, view_radius: i32, extra: i32) {
+    level.glimpsed_positions.clear();
+    if extra <= 0 {
+        return;
+    }
+    let player_pos = level.player_position;
+    let outer = view_radius + extra;
+    for dy in -outer..=outer {
+        for dx in -outer..=outer {
+            let (x, y) = (player_pos.x + dx, player_pos.y + dy);
+            let distance = dx * dx + dy * dy;
+            if on_level(level, x, y)
+                && distance > view_radius * view_radius
+                && distance <= outer * outer
+            {
+                level.glimpsed_positions.insert(Position::new(x, y));
+            }
+        }
+    }
+}
+
+/// Marks the area around where the player stood as explored, a row at a
+/// time. It belongs to one level of one dungeon and does nothing anywhere
+/// else.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplorationPass {
+    pub dungeon_index: usize,
+    pub level_index: usize,
+    center: Position,
+    next_row: i32,
+    end_row: i32,
+}
+
+impl ExplorationPass {
+    pub fn new(dungeon_index: usize, level_index: usize, center: Position) -> Self {
+        ExplorationPass {
+            dungeon_index,
+            level_index,
+            center,
+            next_row: center.y - EXPLORE_HALF_HEIGHT,
+            end_row: center.y + EXPLORE_HALF_HEIGHT + 1,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next_row >= self.end_row
+    }
+
+    /// Marks up to `rows` more rows of `level`, returning true once the
+    /// whole area is done
+    pub fn advance(&mut self, level: &mut Level, rows: usize) -> bool {
+        for _ in 0..rows {
+            if self.is_done() {
+                break;
+            }
+            let y = self.next_row;
+            self.next_row += 1;
+            for x in self.center.x - EXPLORE_HALF_WIDTH..=self.center.x + EXPLORE_HALF_WIDTH {
+                if !on_level(level, x, y) {
+                    continue;
+                }
+                // Explored, not necessarily visible, for the fog of war
+                level.revealed_tiles[y as usize][x as usize] = true;
+                if let Some(tile) = level.get_tile_mut(x, y) {
+                    tile.explored = true;
+                }
+            }
+        }
+        self.is_done()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{Tile, TileType};
+
+    fn open_level() -> Level {
+        let mut level = Level::new(80, 40);
+        for row in &mut level.tiles {
+            for tile in row.iter_mut() {
+                *tile = Tile::new(TileType::Floor);
+            }
+        }
+        level.player_position = Position::new(40, 20);
+        level
+    }
+
+    fn explored(level: &Level) -> Vec<(usize, usize)> {
+        let mut explored = Vec::new();
+        for (y, row) in level.tiles.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                if tile.explored {
+                    explored.push((x, y));
+                }
+            }
+        }
+        explored
+    }
+
+    #[test]
+    fn test_a_pass_spread_over_frames_matches_one_done_at_once() {
+        let mut at_once = open_level();
+        let mut spread = open_level();
+        let center = at_once.player_position;
+
+        assert!(ExplorationPass::new(0, 0, center).advance(&mut at_once, usize::MAX));
+
+        let mut pass = ExplorationPass::new(0, 0, center);
+        let mut frames = 0;
+        while !pass.advance(&mut spread, 4) {
+            frames += 1;
+            // Part way through, only some of the rows are marked
+            assert!(explored(&spread).len() < explored(&at_once).len());
+        }
+        assert_eq!(frames, (2 * EXPLORE_HALF_HEIGHT as usize + 1) / 4);
+        assert_eq!(explored(&spread), explored(&at_once));
+    }
+
+    #[test]
+    fn test_sight_is_a_circle_and_the_pass_stays_on_the_level() {
+        let mut level = open_level();
+        level.player_position = Position::new(1, 1);
+        // As a save from before `visible_positions` loads
+        level.visible_tiles[30][70] = true;
+        level.tiles[30][70].visible = true;
+        update_sight(&mut level, 3);
+
+        assert!(level.visible_tiles[1][4] && level.tiles[1][4].explored);
+        assert!(!level.visible_tiles[4][4]);
+        assert!(!level.visible_tiles[30][70] && !level.tiles[30][70].visible);
+
+        // Rows and columns off the level are skipped
+        let mut pass = ExplorationPass::new(0, 0, level.player_position);
+        assert!(pass.advance(&mut level, usize::MAX));
+        assert!(level.tiles[11][31].explored);
+        assert!(!level.tiles[12][1].explored);
+    }
+
+    /// Positions within `radius` of `center`, as the sight circle covers them
+    fn circle(center: Position, radius: i32) -> HashSet<Position> {
+        let mut circle = HashSet::new();
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                if dx * dx + dy * dy <= radius * radius {
+                    circle.insert(Position::new(center.x + dx, center.y + dy));
+                }
+            }
+        }
+        circle
+    }
+
+    fn visible(level: &Level) -> HashSet<Position> {
+        let mut visible = HashSet::new();
+        for (y, row) in level.tiles.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                assert_eq!(tile.visible, level.visible_tiles[y][x]);
+                if tile.visible {
+                    visible.insert(Position::new(x as i32, y as i32));
+                }
+            }
+        }
+        visible
+    }
+
+    #[test]
+    fn test_sight_on_a_large_level_is_exactly_the_circle_and_costs_its_area() {
+        let mut level = Level::new(200, 200);
+        for row in &mut level.tiles {
+            for tile in row.iter_mut() {
+                *tile = Tile::new(TileType::Floor);
+            }
+        }
+        let radius = 10;
+        let area = circle(Position::new(0, 0), radius).len();
+
+        let mut previous = HashSet::new();
+        for center in [
+            Position::new(100, 100),
+            Position::new(101, 100),
+            Position::new(150, 30),
+            Position::new(199, 199),
+        ] {
+            level.player_position = center;
+            let touched = update_sight(&mut level, radius);
+
+            // What was seen from the last spot is hidden again, and no more
+            // than the old and new circles were touched to do it
+            let expected: HashSet<Position> = circle(center, radius)
+                .into_iter()
+                .filter(|pos| on_level(&level, pos.x, pos.y))
+                .collect();
+            assert_eq!(visible(&level), expected);
+            assert_eq!(level.visible_positions, expected);
+            assert_eq!(touched, previous.len() + expected.len());
+            assert!(touched <= 2 * area);
+            previous = expected;
+        }
+    }
+}
@@ -1,99 +1,255 @@
 //! GUI module for Windows graphical interface using egui
 //! Provides a native Windows application with text-based gameplay
 
-#[cfg(all(feature = "gui", target_os = "windows"))]
-use crate::character::{ClassType, Player};
-#[cfg(all(feature = "gui", target_os = "windows"))]
-use crate::game::Game;
-#[cfg(all(feature = "gui", target_os = "windows"))]
-use crate::input::InputHandler;
-#[cfg(all(feature = "gui", target_os = "windows"))]
-use crate::inventory::InventoryManager;
-#[cfg(all(feature = "gui", target_os = "windows"))]
-use crate::item::{equipment, Item};
-#[cfg(all(feature = "gui", target_os = "windows"))]
+use crate::achievements::AchievementsFile;
+use crate::audio::AudioManager;
+use crate::bones::{BonesFile, BonesRecord};
+use crate::character::{Class, ClassType, Player, StatAllocation, StatType};
+use crate::combat::{AreaTarget, CombatEnding, CombatPlayback};
+use crate::daily::DailyChallenge;
+use crate::effects::{ActiveEffects, ScreenEffect, SHAKE_PIXELS};
+use crate::game::{
+    quit_question, Effect, Game, GameLoop, GameState, Input, PauseOption, PendingGame,
+};
+use crate::hall_of_fame::{LeaderboardFile, Placement};
+use crate::help::{self, KEY_SECTIONS, TIPS};
+use crate::input::gamepad::{Gamepad, PadContext};
+use crate::input::{Direction, InputAction, InputHandler};
+use crate::inventory::{InventoryManager, HOTBAR_SLOTS, RECIPES};
+use crate::item::consumable::Material;
+use crate::item::{equipment, Item, Rarity};
+use crate::log::LogCategory;
+use crate::map_export::MapExporter;
+use crate::menu::{MenuInput, MenuSelector};
+use crate::quest::QuestStatus;
+use crate::render::{legend, view_origin, Bar, CombatLog, CombatView, MapFrame, StatPanel};
+use crate::save::{DataFile, SaveManager};
+use crate::settings::{Setting, Settings, SettingsFile};
+use crate::startup::StartupOptions;
+use crate::world::fog_of_war::FogColor;
 use crate::world::{FogOfWar, Position};
-#[cfg(all(feature = "gui", target_os = "windows"))]
 use eframe::egui;
-#[cfg(all(feature = "gui", target_os = "windows"))]
 use egui::{Color32, FontFamily, FontId, RichText};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Map viewport within the terminal buffer, centered on the player
+const MAP_VIEW_X: usize = 5;
+const MAP_VIEW_Y: usize = 3;
+const MAP_VIEW_WIDTH: usize = 90;
+const MAP_VIEW_HEIGHT: usize = 35;
+
+// Estimated size of a monospace character cell, relative to the font size
+const CHAR_WIDTH_RATIO: f32 = 0.6;
+const CHAR_HEIGHT_RATIO: f32 = 1.2;
+// Smallest font a small window shrinks the text to
+const MIN_FONT_SIZE: f32 = 8.0;
+
+// Keys listed beside the map; the help screen has the rest
+const CONTROLS: [&str; 9] = [
+    "WASD: Move",
+    "1-5: Hotbar",
+    "I: Toggle Inventory",
+    "C: Toggle Character",
+    "G: Interact",
+    "J: Journal  B: Bestiary",
+    "Mouse: Look at tile",
+    "F5: Save  Esc/Q: Pause",
+    "?: Help",
+];
 
-#[cfg(all(feature = "gui", target_os = "windows"))]
 #[derive(Debug, Clone, PartialEq)]
 enum CharacterCreationState {
     EnteringName,
     SelectingClass,
+    CustomizingStats,
+    ChoosingMode,
+}
+
+/// A list whose entries the arrow keys move through
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MenuList {
+    Main,
+    Class,
+    Stats,
+    Mode,
+    CombatActions,
+    Abilities,
+    CombatItems,
+    Inventory,
+    LevelUp,
+}
+
+// Entries of the main menu, and where "Next Target" sits among the combat
+// actions of a group fight
+const MAIN_MENU_LEN: usize = 9;
+const COMBAT_NEXT_TARGET: usize = 4;
+// Rows of the combat log shown at once; PageUp and PageDown show the rest
+const COMBAT_LOG_ROWS: usize = 10;
+
+// Points the help window scrolls per arrow key press
+const HELP_SCROLL_STEP: f32 = 40.0;
+
+/// A new game's dungeon being generated on a background thread, so the
+/// window keeps drawing meanwhile
+struct Generation {
+    /// Levels generated so far
+    progress: Arc<AtomicUsize>,
+    /// Levels the dungeon will have
+    total: usize,
+    worker: std::thread::JoinHandle<Game>,
 }
 
-#[cfg(all(feature = "gui", target_os = "windows"))]
 pub struct EchoesApp {
     game: Option<Game>,
     terminal_buffer: Vec<Vec<char>>,
     color_buffer: Vec<Vec<Option<Color32>>>,
+    background_buffer: Vec<Vec<Option<Color32>>>, // Fills behind characters, such as the threat tint
+    show_threats: bool, // Whether the map tints the tiles enemies in sight can reach
     last_key: Option<char>,
     show_combat_tutorial: bool,
     font_size: f32,
     cursor_pos: (usize, usize),
     terminal_size: (usize, usize),
     ui_messages: Vec<String>,
-    message_log: Vec<(String, f64)>, // Messages with timestamps for fading
-    message_log_visible: bool,       // Toggle for message log visibility
+    message_log_visible: bool, // Toggle for message log visibility
     game_initialized: bool,
     character_name: String,
     character_class: Option<ClassType>,
+    stat_allocation: Option<StatAllocation>, // Points spent at creation; None keeps the class's stats
     creating_character: bool,
     character_creation_state: CharacterCreationState,
     showing_inventory: bool, // Whether the inventory screen is shown
+    showing_crafting: bool,  // Whether the recipe list is open beside the inventory
     showing_character: bool, // Whether the character screen is shown
     main_menu: bool,
     input_handler: InputHandler,
     frame_count: u64,
     in_combat: bool,
-    combat_enemy_pos: Option<Position>,
-    combat_messages: Vec<String>,
+    game_loop: GameLoop, // What each key does in each state, and the enemy being attacked
+    combat_messages: CombatLog,
+    combat_playback: CombatPlayback, // Paces the messages of the last combat turn
+    time: f64,                       // Seconds since the app started, from egui
+    effects: ActiveEffects,          // Flashes and the map's shake, as the game queues them
     showing_ability_selection: bool, // Whether the ability selection screen is shown
-    showing_victory_screen: bool,    // Whether the victory screen is shown
+    showing_item_selection: bool,    // Whether the combat item list is shown
+    showing_end_screen: bool,        // Whether the victory or game over screen is shown
+    showing_level_up: bool,          // Whether the stat allocation window is shown
+    showing_journal: bool,           // Whether the quest journal is shown
+    showing_bestiary: bool,          // Whether the bestiary is shown
+    saves: SaveManager,
+    menu_notice: Option<String>, // Problem shown on the main menu, such as a refused load
+    display_notice: Option<(String, u64)>, // New font size or fullscreen state, and the frame it changed
+    audio: AudioManager,                   // Sound effects; silent without the audio feature
+    hall_of_fame: LeaderboardFile,
+    bones: BonesFile, // Characters who died, whose graves later runs may find
+    showing_hall_of_fame: bool, // Whether the main menu shows the best runs
+    achievements: AchievementsFile, // Achievements unlocked over every run
+    showing_achievements: bool, // Whether the main menu shows the achievements
+    run_recorded: bool, // Whether the finished game is in the hall of fame
+    run_placement: Option<Placement>, // Where the finished game placed
+    gamepad: Gamepad, // Controller input, a no-op without one
+    settings: Settings,
+    settings_file: SettingsFile,
+    options_selected: Option<usize>, // Highlighted row while the options screen is shown
+    fullscreen: bool,                // Fullscreen state the window was last given
+    pause_selected: usize,           // Highlighted entry of the pause menu
+    confirming_quit: bool,           // Whether the pause menu asks before quitting
+    showing_help: bool,              // Whether the help window is open
+    help_scroll: f32,                // Scrolling the help window asked for by the keys
+    menu: MenuSelector,              // Highlighted entry of the list on screen
+    menu_list: Option<MenuList>,     // The list `menu` follows, if one is shown
+    startup: StartupOptions,         // What was asked for on the command line
+    daily: Option<DailyChallenge>,   // The daily challenge being set up, if picked
+    generation: Option<Generation>,  // The new game's world, while it's generated
+    build_code: String,              // Text of the build code field
+    entering_build_code: bool,       // Whether the main menu asks for a build code
+    build_code_notice: Option<String>, // What importing on the character screen found
+    aiming_area: Option<usize>,      // Area ability being aimed with the mouse, by index
 }
 
-#[cfg(all(feature = "gui", target_os = "windows"))]
 impl Default for EchoesApp {
     fn default() -> Self {
+        let settings_file = SettingsFile::in_default_location();
+        let (settings, warnings) = settings_file.load();
         let mut app = Self {
             game: None,
             terminal_buffer: vec![vec![' '; 80]; 25],
             color_buffer: vec![vec![None; 80]; 25],
+            background_buffer: vec![vec![None; 80]; 25],
+            show_threats: false,
             last_key: None,
             show_combat_tutorial: true,
-            font_size: 14.0,
+            font_size: settings.font_size as f32,
             cursor_pos: (0, 0),
             terminal_size: (150, 50),
             ui_messages: Vec::with_capacity(25), // Pre-allocate more space for extended message history
-            message_log: Vec::with_capacity(50), // Larger capacity for dedicated message log
             message_log_visible: true,           // Show message log by default
             game_initialized: false,
             character_name: String::new(),
             character_class: None,
+            stat_allocation: None,
             creating_character: false,
             character_creation_state: CharacterCreationState::EnteringName,
             showing_inventory: false,
+            showing_crafting: false,
             showing_character: false,
             main_menu: true,
             input_handler: InputHandler::new(),
             frame_count: 0,
             in_combat: false,
-            combat_enemy_pos: None,
-            combat_messages: Vec::new(),
+            game_loop: GameLoop::new(),
+            combat_messages: CombatLog::default(),
+            combat_playback: CombatPlayback::default(),
+            time: 0.0,
+            effects: ActiveEffects::default(),
             showing_ability_selection: false,
-            showing_victory_screen: false,
+            showing_item_selection: false,
+            showing_end_screen: false,
+            showing_level_up: false,
+            showing_journal: false,
+            showing_bestiary: false,
+            saves: SaveManager::in_default_location(),
+            menu_notice: None,
+            generation: None,
+            display_notice: None,
+            audio: AudioManager::new(),
+            hall_of_fame: LeaderboardFile::in_default_location(),
+            bones: BonesFile::in_default_location(),
+            showing_hall_of_fame: false,
+            achievements: AchievementsFile::in_default_location(),
+            showing_achievements: false,
+            run_recorded: false,
+            run_placement: None,
+            gamepad: Gamepad::new(),
+            fullscreen: settings.fullscreen,
+            settings,
+            settings_file,
+            options_selected: None,
+            pause_selected: 0,
+            confirming_quit: false,
+            showing_help: false,
+            help_scroll: 0.0,
+            menu: MenuSelector::default(),
+            menu_list: None,
+            startup: StartupOptions::default(),
+            daily: None,
+            build_code: String::new(),
+            entering_build_code: false,
+            build_code_notice: None,
+            aiming_area: None,
         };
+        app.apply_settings();
+        for warning in warnings {
+            app.add_message(format!("Settings: {warning}"));
+        }
         app.init_terminal();
         app
     }
 }
 
-#[cfg(all(feature = "gui", target_os = "windows"))]
 impl EchoesApp {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>, startup: StartupOptions) -> Self {
         // Configure dark theme and colors for terminal appearance
         let mut visuals = egui::Visuals::dark();
         visuals.window_fill = Color32::BLACK;
@@ -105,11 +261,22 @@ impl EchoesApp {
 
         let mut app = Self::default();
         app.init_terminal();
-        app
-    }
 
-    fn create_fog_of_war() -> FogOfWar {
-        crate::world::create_standard_fog_of_war()
+        // A save or character given on the command line skips the main menu
+        if let Some(slot) = &startup.load_slot {
+            app.saves = SaveManager::in_slot(slot);
+        }
+        let preset_player = startup.preset_player();
+        app.startup = startup;
+        if app.startup.load_slot.is_some() {
+            app.continue_saved_game();
+        } else if let Some(player) = preset_player {
+            app.main_menu = false;
+            app.character_name = player.name;
+            app.character_class = Some(player.class.class_type);
+            app.finish_character_creation(false);
+        }
+        app
     }
 
     fn init_terminal(&mut self) {
@@ -119,6 +286,7 @@ impl EchoesApp {
             vec![Some(Color32::from_rgb(192, 192, 192)); self.terminal_size.0];
             self.terminal_size.1
         ];
+        self.background_buffer = vec![vec![None; self.terminal_size.0]; self.terminal_size.1];
         self.clear_screen();
         self.show_main_menu();
     }
@@ -132,6 +300,9 @@ impl EchoesApp {
                 *color = Some(Color32::from_rgb(192, 192, 192));
             }
         }
+        for line in &mut self.background_buffer {
+            line.fill(None);
+        }
         self.cursor_pos = (0, 0);
     }
 
@@ -174,28 +345,436 @@ impl EchoesApp {
         );
 
         self.print_at(center_x, center_y + 2, "1. Start New Game", None);
-        self.print_at(center_x, center_y + 3, "2. Exit", None);
+        self.print_at(center_x, center_y + 3, "2. Daily Challenge", None);
+        match self.saves.summary() {
+            Some(summary) => {
+                self.print_at(center_x, center_y + 4, "3. Continue", None);
+                self.print_at(
+                    center_x + 3,
+                    center_y + 5,
+                    &summary.to_string(),
+                    Some(Color32::YELLOW),
+                );
+            }
+            None => self.print_at(
+                center_x,
+                center_y + 4,
+                "3. Continue (no saved game)",
+                Some(Color32::DARK_GRAY),
+            ),
+        }
+        self.print_at(center_x, center_y + 6, "4. Start from Build Code", None);
+        self.print_at(center_x, center_y + 7, "5. Hall of Fame", None);
+        self.print_at(center_x, center_y + 8, "6. Achievements", None);
+        self.print_at(center_x, center_y + 9, "7. Options", None);
+        self.print_at(center_x, center_y + 10, "8. Help", None);
+        self.print_at(center_x, center_y + 11, "9. Exit", None);
+        if let Some(selected) = self.menu_highlight(MenuList::Main) {
+            let rows = [2, 3, 4, 6, 7, 8, 9, 10, 11];
+            self.print_at(
+                center_x - 2,
+                center_y + rows[selected],
+                ">",
+                Some(Color32::YELLOW),
+            );
+        }
 
         self.print_at(
             center_x,
-            center_y + 6,
-            "Press 1 to start or 2 to exit",
+            center_y + 13,
+            "Up/Down and Enter to choose, or press 1-9",
+            Some(Color32::from_rgb(0, 255, 255)),
+        );
+
+        if let Some(notice) = self.menu_notice.clone() {
+            self.print_at(center_x, center_y + 14, &notice, Some(Color32::RED));
+        }
+    }
+
+    /// Lists the settings in place of the main menu, the selected one
+    /// highlighted
+    fn show_options(&mut self) {
+        self.clear_screen();
+        let title = "*** OPTIONS ***";
+        let x = 10;
+        let selected = self.options_selected.unwrap_or(0);
+
+        self.print_at(
+            (self.terminal_size.0.saturating_sub(title.len())) / 2,
+            5,
+            title,
+            Some(Color32::YELLOW),
+        );
+
+        for (i, row) in self.settings.rows().iter().enumerate() {
+            if i == selected {
+                self.print_at(x, 8 + i, &format!("> {row}"), Some(Color32::YELLOW));
+            } else {
+                self.print_at(x, 8 + i, &format!("  {row}"), None);
+            }
+        }
+
+        self.print_at(
+            x,
+            10 + Setting::ALL.len(),
+            "Up/Down to choose, Left/Right to change, Escape to return to the main menu",
+            Some(Color32::from_rgb(0, 255, 255)),
+        );
+    }
+
+    /// Moves through and changes the settings, applying and saving every
+    /// change at once
+    fn handle_options_input(&mut self, action: &InputAction) {
+        let Some(selected) = self.options_selected else {
+            return;
+        };
+        let count = Setting::ALL.len();
+        match action {
+            InputAction::Move(Direction::North) => {
+                self.options_selected = Some((selected + count - 1) % count);
+            }
+            InputAction::Move(Direction::South) => {
+                self.options_selected = Some((selected + 1) % count);
+            }
+            InputAction::Move(direction @ (Direction::West | Direction::East)) => {
+                self.settings
+                    .adjust(Setting::ALL[selected], *direction == Direction::East);
+                self.apply_settings();
+                self.menu_notice = self
+                    .settings_file
+                    .save(&self.settings)
+                    .err()
+                    .map(|e| format!("Settings not saved: {e}"));
+            }
+            InputAction::Exit | InputAction::Enter => {
+                self.options_selected = None;
+                // Opened from the pause menu, the options go back to it
+                if !self.is_paused() {
+                    self.show_main_menu();
+                }
+                return;
+            }
+            _ => {}
+        }
+        self.show_options();
+    }
+
+    /// Changes the font size or fullscreen state from the keyboard, keeps
+    /// the choice in the settings file and flashes the new value on screen.
+    /// The screen isn't cleared: every frame draws it again at the new size.
+    fn adjust_display(&mut self, setting: Setting, forward: bool) {
+        self.settings.adjust(setting, forward);
+        self.apply_settings();
+        let notice = match self.settings_file.save(&self.settings) {
+            Ok(()) => format!("{}: {}", setting.label(), self.settings.value_text(setting)),
+            Err(e) => format!("Settings not saved: {e}"),
+        };
+        self.display_notice = Some((notice, self.frame_count));
+        // The options screen shows the new value too
+        if self.options_selected.is_some() {
+            self.show_options();
+        }
+    }
+
+    /// Whether a game is running with its pause menu open
+    fn is_paused(&self) -> bool {
+        self.game_initialized
+            && self
+                .game
+                .as_ref()
+                .is_some_and(|game| matches!(game.game_state, GameState::Paused))
+    }
+
+    /// Whether the player is talking with an NPC
+    fn is_talking(&self) -> bool {
+        self.game_initialized
+            && self
+                .game
+                .as_ref()
+                .is_some_and(|game| matches!(game.game_state, GameState::Dialogue(_)))
+    }
+
+    /// Y or Enter takes up the NPC's offer; Escape or N leaves the talk
+    fn handle_dialogue_input(&mut self, action: &InputAction) {
+        match action {
+            InputAction::Character('y' | 'Y') | InputAction::Enter => self.answer_dialogue(true),
+            InputAction::Character('n' | 'N') | InputAction::Exit => self.answer_dialogue(false),
+            _ => {}
+        }
+    }
+
+    /// Ends the talk, taking up the offer first if `accept`
+    fn answer_dialogue(&mut self, accept: bool) {
+        let Some(game) = self.game.as_mut() else {
+            return;
+        };
+        let input = if accept { Input::Accept } else { Input::Back };
+        self.game_loop.handle(game, input);
+        self.show_new_log_entries();
+    }
+
+    /// Opens the pause menu over a game being explored, optionally straight
+    /// at the quit question; true if it opened
+    fn pause(&mut self, confirm_quit: bool) -> bool {
+        if !self.game_initialized || self.show_combat_tutorial || self.in_combat {
+            return false;
+        }
+        let Some(game) = self.game.as_mut() else {
+            return false;
+        };
+        if !game.pause() {
+            return false;
+        }
+        self.pause_selected = 0;
+        self.confirming_quit = confirm_quit;
+        true
+    }
+
+    /// The pause menu's entries; permadeath games can't be saved by hand
+    fn pause_options(&self) -> Vec<PauseOption> {
+        let permadeath = self.game.as_ref().is_some_and(|game| game.permadeath);
+        PauseOption::menu(!permadeath)
+    }
+
+    /// Moves through the pause menu and whatever page of it is open
+    fn handle_pause_input(&mut self, action: &InputAction) {
+        if self.options_selected.is_some() {
+            self.handle_options_input(action);
+            return;
+        }
+        if self.confirming_quit {
+            match action {
+                InputAction::Character('y' | 'Y') | InputAction::Enter => self.quit_to_main_menu(),
+                InputAction::Character('n' | 'N') | InputAction::Exit => {
+                    self.confirming_quit = false;
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        let options = self.pause_options();
+        let count = options.len();
+        match action {
+            InputAction::Move(Direction::North) => {
+                self.pause_selected = (self.pause_selected + count - 1) % count;
+            }
+            InputAction::Move(Direction::South) => {
+                self.pause_selected = (self.pause_selected + 1) % count;
+            }
+            InputAction::Enter => self.choose_pause_option(options[self.pause_selected % count]),
+            InputAction::MenuOption(n) => {
+                if let Some(&option) = options.get((*n as usize).wrapping_sub(1)) {
+                    self.choose_pause_option(option);
+                }
+            }
+            InputAction::Exit => self.choose_pause_option(PauseOption::Resume),
+            _ => {}
+        }
+    }
+
+    fn choose_pause_option(&mut self, option: PauseOption) {
+        match option {
+            PauseOption::Resume => {
+                if let Some(game) = self.game.as_mut() {
+                    game.resume();
+                }
+            }
+            PauseOption::Save => {
+                self.save_game();
+                if let Some(game) = self.game.as_mut() {
+                    game.resume();
+                }
+            }
+            PauseOption::Options => self.options_selected = Some(0),
+            PauseOption::Help => self.showing_help = true,
+            PauseOption::ResetHints => {
+                if let Some(game) = self.game.as_mut() {
+                    game.reset_hints();
+                    game.resume();
+                }
+            }
+            PauseOption::ExportMap => {
+                let Some(game) = self.game.as_mut() else {
+                    return;
+                };
+                game.resume();
+                let message = MapExporter::in_default_location().export_message(game);
+                self.add_message(message);
+            }
+            PauseOption::QuitToMenu => self.confirming_quit = true,
+        }
+    }
+
+    /// Leaves the game for the main menu, saving a permadeath game first
+    fn quit_to_main_menu(&mut self) {
+        self.confirming_quit = false;
+        if let Some(game) = self.game.as_mut() {
+            game.resume();
+            if let Err(e) = self.saves.save_on_quit(game) {
+                self.menu_notice = Some(format!("Save failed: {e}"));
+            }
+        }
+        self.game_initialized = false;
+        self.main_menu = true;
+        self.show_main_menu();
+    }
+
+    /// Hands the current settings to everything that uses them; the window's
+    /// fullscreen state follows in `update`
+    fn apply_settings(&mut self) {
+        self.font_size = self.settings.font_size as f32;
+        self.combat_playback = CombatPlayback::new(self.settings.combat_message_delay());
+        self.saves
+            .set_autosave_interval(self.settings.autosave_interval);
+        self.audio.set_volume(self.settings.volume());
+        if let Some(game) = self.game.as_mut() {
+            game.apply_settings(&self.settings);
+        }
+    }
+
+    /// Draws `bar` from the chosen glyph set a cell at a time, since its
+    /// block characters are wider than one byte
+    fn print_bar(&mut self, x: usize, y: usize, bar: &Bar, color: FogColor) {
+        let color = self.glyph_color(color);
+        let cells = bar.cells(self.settings.glyph_set());
+        for (i, cell) in cells.chars().enumerate() {
+            self.print_at(x + i, y, &cell.to_string(), Some(color));
+        }
+    }
+
+    /// Display color for a map glyph in the chosen color scheme
+    fn glyph_color(&self, color: FogColor) -> Color32 {
+        FogOfWar::to_egui_color(&self.settings.color_scheme().apply(color))
+    }
+
+    /// Lists the best recorded runs in place of the main menu
+    fn show_hall_of_fame(&mut self) {
+        self.clear_screen();
+        let title = "*** HALL OF FAME ***";
+        let x = 10;
+
+        self.print_at(
+            (self.terminal_size.0.saturating_sub(title.len())) / 2,
+            5,
+            title,
+            Some(Color32::YELLOW),
+        );
+
+        let leaderboard = self.hall_of_fame.load();
+        let rows = leaderboard.rows();
+        if rows.is_empty() {
+            self.print_at(x, 8, "No finished runs yet.", Some(Color32::DARK_GRAY));
+        }
+        for (i, row) in rows.iter().enumerate() {
+            self.print_at(x, 8 + i, row, None);
+        }
+
+        let daily_rows = leaderboard.daily_rows();
+        let mut bottom = 8 + rows.len().max(1);
+        if !daily_rows.is_empty() {
+            self.print_at(x, bottom + 1, "Daily Challenges", Some(Color32::YELLOW));
+            for (i, row) in daily_rows.iter().enumerate() {
+                self.print_at(x, bottom + 2 + i, row, None);
+            }
+            bottom += 2 + daily_rows.len();
+        }
+
+        self.print_at(
+            x,
+            bottom + 2,
+            "Press any key to return to the main menu",
+            Some(Color32::from_rgb(0, 255, 255)),
+        );
+    }
+
+    /// Lists every achievement in place of the main menu, unlocked ones
+    /// with the day they were
+    fn show_achievements(&mut self) {
+        self.clear_screen();
+        let profile = self.achievements.load();
+        let title = format!("*** ACHIEVEMENTS - {} ***", profile.summary());
+        let x = 10;
+
+        self.print_at(
+            (self.terminal_size.0.saturating_sub(title.len())) / 2,
+            5,
+            &title,
+            Some(Color32::YELLOW),
+        );
+
+        let rows = profile.rows();
+        for (i, (row, unlocked)) in rows.iter().enumerate() {
+            let color = if *unlocked {
+                Color32::from_rgb(255, 156, 255)
+            } else {
+                Color32::DARK_GRAY
+            };
+            self.print_at(x, 8 + i, row, Some(color));
+        }
+
+        self.print_at(
+            x,
+            10 + rows.len(),
+            "Press any key to return to the main menu",
             Some(Color32::from_rgb(0, 255, 255)),
         );
     }
 
     fn handle_main_menu_input(&mut self, action: &crate::input::InputAction) {
+        if self.options_selected.is_some() {
+            self.handle_options_input(action);
+            return;
+        }
+        if self.showing_hall_of_fame || self.showing_achievements {
+            self.showing_hall_of_fame = false;
+            self.showing_achievements = false;
+            self.show_main_menu();
+            return;
+        }
+
         match action {
             crate::input::InputAction::MenuOption(1) => {
-                self.main_menu = false;
-                self.creating_character = true;
-                self.character_name.clear(); // Clear any residual input
-                self.character_class = None; // Reset class selection
-                self.character_creation_state = CharacterCreationState::EnteringName; // Reset to name input
-                self.input_handler.clear_state(); // Clear input state
-                self.show_character_creation();
+                self.daily = None;
+                self.begin_character_creation();
             }
             crate::input::InputAction::MenuOption(2) => {
+                // Each day's challenge can be taken once
+                let challenge = DailyChallenge::today();
+                match self.hall_of_fame.load().daily_result(&challenge.date) {
+                    Some(result) => {
+                        self.menu_notice = Some(challenge.already_taken(result));
+                        self.show_main_menu();
+                    }
+                    None => {
+                        self.daily = Some(challenge);
+                        self.begin_character_creation();
+                    }
+                }
+            }
+            crate::input::InputAction::MenuOption(3) if self.saves.has_save() => {
+                self.continue_saved_game();
+            }
+            crate::input::InputAction::MenuOption(4) => {
+                self.build_code.clear();
+                self.entering_build_code = true;
+            }
+            crate::input::InputAction::MenuOption(5) => {
+                self.showing_hall_of_fame = true;
+                self.show_hall_of_fame();
+            }
+            crate::input::InputAction::MenuOption(6) => {
+                self.showing_achievements = true;
+                self.show_achievements();
+            }
+            crate::input::InputAction::MenuOption(7) => {
+                self.options_selected = Some(0);
+                self.show_options();
+            }
+            crate::input::InputAction::MenuOption(8) => {
+                self.showing_help = true;
+            }
+            crate::input::InputAction::MenuOption(9) => {
                 // Exit application - will be handled by the framework
                 std::process::exit(0);
             }
@@ -203,6 +782,88 @@ impl EchoesApp {
         }
     }
 
+    /// Leaves the main menu for the name prompt
+    fn begin_character_creation(&mut self) {
+        self.main_menu = false;
+        self.creating_character = true;
+        self.character_name.clear(); // Clear any residual input
+        self.character_class = None; // Reset class selection
+        self.stat_allocation = None;
+        self.character_creation_state = CharacterCreationState::EnteringName; // Reset to name input
+        self.input_handler.clear_state(); // Clear input state
+        self.show_character_creation();
+    }
+
+    /// Loads the saved game and drops straight into it
+    fn continue_saved_game(&mut self) {
+        match self.saves.load() {
+            Ok(mut game) => {
+                game.game_state = crate::game::GameState::Playing;
+                game.apply_settings(&self.settings);
+                game.haunt(self.bones.load());
+                game.track_achievements(self.achievements.load());
+                let name = game.player.name.clone();
+                self.game = Some(game);
+                self.menu_notice = None;
+                self.main_menu = false;
+                self.creating_character = false;
+                self.show_combat_tutorial = false;
+                self.showing_end_screen = false;
+                self.run_recorded = false;
+                self.run_placement = None;
+                self.in_combat = false;
+                self.game_loop.combat_target = 0;
+                self.game_initialized = true;
+                self.input_handler.clear_state();
+                self.add_message(format!("Welcome back, {name}."));
+            }
+            Err(e) => {
+                self.menu_notice = Some(e.to_string());
+                self.show_main_menu();
+            }
+        }
+    }
+
+    /// Saves the game on request; permadeath games only save themselves
+    fn save_game(&mut self) {
+        let Some(game) = self.game.as_ref() else {
+            return;
+        };
+
+        let message = if game.permadeath {
+            "Permadeath games save automatically.".to_string()
+        } else {
+            match self.saves.save(game) {
+                Ok(()) => "💾 Game saved.".to_string(),
+                Err(e) => format!("Save failed: {e}"),
+            }
+        };
+        self.add_message(message);
+    }
+
+    /// Autosaves permadeath games and deletes their save once the
+    /// character dies; runs every frame before the game is drawn
+    fn update_save_file(&mut self) {
+        let Some(game) = self.game.as_ref() else {
+            return;
+        };
+
+        if matches!(game.game_state, crate::game::GameState::GameOver) {
+            if game.permadeath && self.saves.has_save() {
+                if let Err(e) = self.saves.on_player_death(game) {
+                    self.add_message(format!("Could not delete the save: {e}"));
+                }
+            }
+            return;
+        }
+
+        match self.saves.autosave_if_due(game) {
+            Ok(true) => self.add_message("💾 Game autosaved.".to_string()),
+            Ok(false) => {}
+            Err(e) => self.add_message(format!("Autosave failed: {e}")),
+        }
+    }
+
     fn show_character_creation(&mut self) {
         self.clear_screen();
 
@@ -210,6 +871,10 @@ impl EchoesApp {
         let center_x = (self.terminal_size.0 - title.len()) / 2;
 
         self.print_at(center_x, 5, title, Some(Color32::YELLOW));
+        if let Some(challenge) = &self.daily {
+            let line = format!("{}: you play a {}", challenge.label(), challenge.class());
+            self.print_at(10, 7, &line, Some(Color32::from_rgb(255, 0, 255)));
+        }
 
         match self.character_creation_state {
             CharacterCreationState::EnteringName => {
@@ -244,10 +909,88 @@ impl EchoesApp {
                 self.print_at(10, 16, "2. Mage - Powerful spellcaster", None);
                 self.print_at(10, 17, "3. Ranger - Balanced archer", None);
                 self.print_at(10, 18, "4. Cleric - Healer and support", None);
+                if let Some(selected) = self.menu_highlight(MenuList::Class) {
+                    self.print_at(8, 15 + selected, ">", Some(Color32::YELLOW));
+                }
                 self.print_at(
                     10,
                     20,
-                    "(Press number key to select class)",
+                    "(Up/Down and Enter, or a number key, to select class)",
+                    Some(Color32::DARK_GRAY),
+                );
+            }
+            CharacterCreationState::CustomizingStats => {
+                let Some(allocation) = self.stat_allocation.clone() else {
+                    return;
+                };
+                self.print_at(10, 10, &format!("Name: {}", self.character_name), None);
+                self.print_at(
+                    10,
+                    12,
+                    &format!(
+                        "Customize stats - points left: {} of {}",
+                        allocation.points_left(),
+                        crate::character::allocation::STAT_POINT_POOL
+                    ),
+                    Some(Color32::from_rgb(0, 255, 255)),
+                );
+                for (i, stat) in StatType::iter().enumerate() {
+                    let line = format!(
+                        "{:<13} < {:>2} >   ({}-{})",
+                        stat.to_string(),
+                        allocation.stats().get(stat),
+                        allocation.floor(stat),
+                        allocation.cap(stat)
+                    );
+                    self.print_at(10, 14 + i, &line, None);
+                }
+                if let Some(selected) = self.menu_highlight(MenuList::Stats) {
+                    self.print_at(8, 14 + selected, ">", Some(Color32::YELLOW));
+                }
+                let preview = allocation.preview();
+                self.print_at(
+                    10,
+                    20,
+                    &format!(
+                        "HP {}  MP {}  Attack {}  Defense {}",
+                        preview.max_health, preview.max_mana, preview.attack, preview.defense
+                    ),
+                    Some(Color32::GREEN),
+                );
+                let confirm = if allocation.is_complete() {
+                    "Enter to confirm"
+                } else {
+                    "spend every point, then Enter to confirm"
+                };
+                self.print_at(
+                    10,
+                    22,
+                    &format!("(Up/Down: stat, Left/Right: adjust, {confirm}, Esc for the class's own stats)"),
+                    Some(Color32::DARK_GRAY),
+                );
+            }
+            CharacterCreationState::ChoosingMode => {
+                self.print_at(10, 10, &format!("Name: {}", self.character_name), None);
+                self.print_at(
+                    10,
+                    13,
+                    "Choose your fate:",
+                    Some(Color32::from_rgb(0, 255, 255)),
+                );
+                self.print_at(10, 15, "1. Standard - Save anywhere with F5", None);
+                self.print_at(
+                    10,
+                    16,
+                    "2. Permadeath - Autosaves as you play; death deletes the save",
+                    None,
+                );
+                if let Some(selected) = self.menu_highlight(MenuList::Mode) {
+                    self.print_at(8, 15 + selected, ">", Some(Color32::YELLOW));
+                }
+                self.print_at(
+                    10,
+                    18,
+                    "(Up/Down and Enter, or a number key, to select a mode, Esc to go back)",
                     Some(Color32::DARK_GRAY),
                 );
             }
@@ -260,7 +1003,9 @@ impl EchoesApp {
                 match action {
                     crate::input::InputAction::Character(c) => {
                         // Add character to name if it's valid and we have space
-                        if (c.is_alphanumeric() || *c == ' ') && self.character_name.len() < 20 {
+                        if InputHandler::is_name_character(action)
+                            && self.character_name.len() < crate::character::player::MAX_NAME_LENGTH
+                        {
                             self.character_name.push(*c);
                             self.show_character_creation();
                         }
@@ -271,6 +1016,14 @@ impl EchoesApp {
                             self.show_character_creation();
                         }
                     }
+                    crate::input::InputAction::Enter if self.daily.is_some() => {
+                        // The date picks the class, so the name is all it needs
+                        if self.character_name.is_empty() {
+                            self.character_name =
+                                crate::character::player::DEFAULT_NAME.to_string();
+                        }
+                        self.start_daily_challenge();
+                    }
                     crate::input::InputAction::Enter => {
                         // Proceed to class selection if we have a name
                         if !self.character_name.is_empty() {
@@ -278,7 +1031,8 @@ impl EchoesApp {
                             self.show_character_creation();
                         } else {
                             // Set default name if empty
-                            self.character_name = "Hero".to_string();
+                            self.character_name =
+                                crate::character::player::DEFAULT_NAME.to_string();
                             self.character_creation_state = CharacterCreationState::SelectingClass;
                             self.show_character_creation();
                         }
@@ -287,6 +1041,7 @@ impl EchoesApp {
                         // Go back to main menu
                         self.main_menu = true;
                         self.creating_character = false;
+                        self.daily = None;
                         self.character_name.clear();
                         self.character_creation_state = CharacterCreationState::EnteringName;
                         self.show_main_menu();
@@ -297,20 +1052,16 @@ impl EchoesApp {
             CharacterCreationState::SelectingClass => {
                 match action {
                     crate::input::InputAction::MenuOption(1) => {
-                        self.character_class = Some(crate::character::ClassType::Warrior);
-                        self.finish_character_creation();
+                        self.begin_stat_customization(crate::character::ClassType::Warrior);
                     }
                     crate::input::InputAction::MenuOption(2) => {
-                        self.character_class = Some(crate::character::ClassType::Mage);
-                        self.finish_character_creation();
+                        self.begin_stat_customization(crate::character::ClassType::Mage);
                     }
                     crate::input::InputAction::MenuOption(3) => {
-                        self.character_class = Some(crate::character::ClassType::Ranger);
-                        self.finish_character_creation();
+                        self.begin_stat_customization(crate::character::ClassType::Ranger);
                     }
                     crate::input::InputAction::MenuOption(4) => {
-                        self.character_class = Some(crate::character::ClassType::Cleric);
-                        self.finish_character_creation();
+                        self.begin_stat_customization(crate::character::ClassType::Cleric);
                     }
                     crate::input::InputAction::Backspace | crate::input::InputAction::Exit => {
                         // Go back to name input
@@ -320,27 +1071,216 @@ impl EchoesApp {
                     _ => {}
                 }
             }
+            CharacterCreationState::CustomizingStats => match action {
+                crate::input::InputAction::Move(
+                    direction @ (Direction::West | Direction::East),
+                ) => {
+                    self.sync_menu();
+                    let stat = StatType::iter().nth(self.menu.selected());
+                    if let (Some(allocation), Some(stat)) = (&mut self.stat_allocation, stat) {
+                        allocation.adjust(stat, *direction == Direction::East);
+                    }
+                    self.show_character_creation();
+                }
+                crate::input::InputAction::MenuOption(_) => {
+                    // Enter confirms once every point is spent
+                    if self
+                        .stat_allocation
+                        .as_ref()
+                        .is_some_and(|a| a.is_complete())
+                    {
+                        self.character_creation_state = CharacterCreationState::ChoosingMode;
+                        self.show_character_creation();
+                    }
+                }
+                crate::input::InputAction::Exit => {
+                    // Keep the class's own stats
+                    self.stat_allocation = None;
+                    self.character_creation_state = CharacterCreationState::ChoosingMode;
+                    self.show_character_creation();
+                }
+                crate::input::InputAction::Backspace => {
+                    self.stat_allocation = None;
+                    self.character_creation_state = CharacterCreationState::SelectingClass;
+                    self.show_character_creation();
+                }
+                _ => {}
+            },
+            CharacterCreationState::ChoosingMode => match action {
+                crate::input::InputAction::MenuOption(1) => self.finish_character_creation(false),
+                crate::input::InputAction::MenuOption(2) => self.finish_character_creation(true),
+                crate::input::InputAction::Backspace | crate::input::InputAction::Exit => {
+                    // Go back to class selection
+                    self.stat_allocation = None;
+                    self.character_creation_state = CharacterCreationState::SelectingClass;
+                    self.show_character_creation();
+                }
+                _ => {}
+            },
         }
     }
 
-    fn finish_character_creation(&mut self) {
+    /// Records the chosen class and opens the optional point-buy
+    fn begin_stat_customization(&mut self, class_type: ClassType) {
+        self.character_class = Some(class_type);
+        self.stat_allocation = Some(StatAllocation::new(class_type));
+        self.character_creation_state = CharacterCreationState::CustomizingStats;
+        self.show_character_creation();
+    }
+
+    fn finish_character_creation(&mut self, permadeath: bool) {
         if let Some(class_type) = self.character_class {
-            let _class = crate::character::Class::new(class_type);
-            let player = Player::new(self.character_name.clone(), class_type);
-            self.game = Some(Game::new(player));
-            self.creating_character = false;
-            self.game_initialized = true;
-            self.show_combat_tutorial = true;
-            self.display_combat_tutorial();
+            let name = self.character_name.clone();
+            let player = match self.stat_allocation.take() {
+                Some(allocation) => allocation
+                    .finish(name.clone())
+                    .unwrap_or_else(|_| Player::new(name, class_type)),
+                None => Player::new(name, class_type),
+            };
+            let mut pending = Game::start(player, &self.startup);
+            pending.game_mut().permadeath = permadeath;
+            self.generate_world(pending);
         }
     }
 
-    fn display_combat_tutorial(&mut self) {
-        self.clear_screen();
-
-        let tutorial_lines = vec![
-            "=== COMBAT TUTORIAL ===",
-            "",
+    /// Starts a new game on the template of the build code in the text
+    /// field, or says on the main menu why the code won't load
+    fn start_from_build_code(&mut self) {
+        match Player::import_code(&self.build_code) {
+            Ok(player) => {
+                self.entering_build_code = false;
+                self.main_menu = false;
+                self.daily = None;
+                self.generate_world(Game::start(player.at_first_level(), &self.startup));
+            }
+            Err(e) => {
+                self.menu_notice = Some(format!("That build code won't load: {e}"));
+                self.show_main_menu();
+            }
+        }
+    }
+
+    /// Asks for a build code over the main menu
+    fn show_build_code_window(&mut self, ui: &mut egui::Ui) {
+        let mut start = false;
+        let mut close = false;
+
+        egui::Window::new("Start from Build Code")
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .fixed_size([480.0, 160.0])
+            .collapsible(false)
+            .resizable(false)
+            .show(ui.ctx(), |ui| {
+                ui.label("Paste a build code to start a new level 1 character from it:");
+                let field = ui.add(
+                    egui::TextEdit::singleline(&mut self.build_code).desired_width(f32::INFINITY),
+                );
+                field.request_focus();
+                ui.horizontal(|ui| {
+                    if ui.button("Import").clicked() {
+                        start = true;
+                    }
+                    if ui.button("Cancel (Esc)").clicked() {
+                        close = true;
+                    }
+                });
+            });
+
+        if start {
+            self.start_from_build_code();
+        } else if close {
+            self.entering_build_code = false;
+            self.show_main_menu();
+        }
+    }
+
+    /// Starts today's challenge for the named character
+    fn start_daily_challenge(&mut self) {
+        if let Some(challenge) = self.daily.take() {
+            let pending = challenge.begin(self.character_name.clone());
+            self.generate_world(pending);
+        }
+    }
+
+    /// Generates the new game's dungeon on a background thread, showing
+    /// how far along it is until `poll_generation` finds it done
+    fn generate_world(&mut self, pending: PendingGame) {
+        let progress = Arc::new(AtomicUsize::new(0));
+        let total = pending.levels_total();
+        let counter = Arc::clone(&progress);
+        let worker = std::thread::spawn(move || pending.finish_counting(&counter));
+        self.generation = Some(Generation {
+            progress,
+            total,
+            worker,
+        });
+        self.creating_character = false;
+        self.main_menu = false;
+        self.show_generation_progress(0, total);
+    }
+
+    /// "Generating world... 2/5 levels" in the middle of the screen
+    fn show_generation_progress(&mut self, built: usize, total: usize) {
+        self.clear_screen();
+        let text = format!("Generating world... {built}/{total} levels");
+        let x = self.terminal_size.0.saturating_sub(text.len()) / 2;
+        self.print_at(x, self.terminal_size.1 / 2, &text, Some(Color32::YELLOW));
+    }
+
+    /// Starts the new game once its world is generated, or shows how far
+    /// along it is. Returns true while it's still being generated.
+    fn poll_generation(&mut self, ctx: &egui::Context) -> bool {
+        let Some(generation) = &self.generation else {
+            return false;
+        };
+        if !generation.worker.is_finished() {
+            let (built, total) = (
+                generation.progress.load(Ordering::Relaxed),
+                generation.total,
+            );
+            self.show_generation_progress(built, total);
+            ctx.request_repaint_after(std::time::Duration::from_millis(50));
+            return true;
+        }
+
+        if let Some(generation) = self.generation.take() {
+            match generation.worker.join() {
+                Ok(game) => self.begin_game(game),
+                Err(_) => {
+                    self.menu_notice = Some("The world couldn't be generated.".to_string());
+                    self.main_menu = true;
+                    self.show_main_menu();
+                }
+            }
+        }
+        false
+    }
+
+    /// Drops into a freshly created game, through the combat tutorial unless
+    /// it was skipped
+    fn begin_game(&mut self, mut game: Game) {
+        game.apply_settings(&self.settings);
+        game.haunt(self.bones.load());
+        game.track_achievements(self.achievements.load());
+        self.game = Some(game);
+        self.run_recorded = false;
+        self.run_placement = None;
+        self.creating_character = false;
+        self.game_initialized = true;
+        if self.startup.skip_tutorial {
+            self.start_game();
+        } else {
+            self.show_combat_tutorial = true;
+            self.display_combat_tutorial();
+        }
+    }
+
+    fn display_combat_tutorial(&mut self) {
+        self.clear_screen();
+
+        let tutorial_lines = vec![
+            "=== COMBAT TUTORIAL ===",
+            "",
             "Welcome to your first combat encounter!",
             "",
             "Combat in Echoes RPG is turn-based:",
@@ -388,6 +1328,39 @@ impl EchoesApp {
         // Don't render here, will be handled in main update loop
     }
 
+    /// Describes the map tile shown at a terminal buffer cell, for the
+    /// mouse-hover look tooltip
+    fn describe_map_cell(&self, col: usize, row: usize) -> Option<String> {
+        let pos = self.map_cell_position(col, row)?;
+        self.game.as_ref()?.current_level().describe_position(pos)
+    }
+
+    /// The level position shown at buffer cell `(col, row)`, while the map
+    /// is on screen
+    fn map_cell_position(&self, col: usize, row: usize) -> Option<Position> {
+        let game = self.game.as_ref()?;
+        let overlay_open = self.showing_inventory
+            || self.showing_character
+            || self.showing_journal
+            || self.showing_bestiary;
+        if self.in_combat || self.showing_end_screen || overlay_open || !self.game_initialized {
+            return None;
+        }
+
+        let in_view = (MAP_VIEW_X..MAP_VIEW_X + MAP_VIEW_WIDTH).contains(&col)
+            && (MAP_VIEW_Y..MAP_VIEW_Y + MAP_VIEW_HEIGHT).contains(&row);
+        if !in_view {
+            return None;
+        }
+
+        let level = game.current_level();
+        let origin = view_origin(level.player_position, MAP_VIEW_WIDTH, MAP_VIEW_HEIGHT);
+        Some(Position::new(
+            origin.x + (col - MAP_VIEW_X) as i32,
+            origin.y + (row - MAP_VIEW_Y) as i32,
+        ))
+    }
+
     fn render_game_screen_safe(&mut self, game: &Game) {
         // Request a repaint to keep UI responsive
         if self.showing_inventory || self.showing_character {
@@ -396,147 +1369,264 @@ impl EchoesApp {
 
         self.clear_screen();
 
-        // Render game map using centralized fog of war system
-        let level = game.current_level();
-        let player_pos = level.player_position;
-        let fog_of_war = Self::create_fog_of_war();
-
-        // Calculate view area (centered on player) - use larger screen
-        let view_width = 90;
-        let view_height = 35;
-        let start_x = 5;
-        let start_y = 3;
-
-        // Draw map
-        for screen_y in 0..view_height {
-            for screen_x in 0..view_width {
-                let map_x = player_pos.x - view_width as i32 / 2 + screen_x as i32;
-                let map_y = player_pos.y - view_height as i32 / 2 + screen_y as i32;
-                let pos = Position::new(map_x, map_y);
-
-                // Use centralized fog of war processing
-                let fog_result = fog_of_war.process_position(level, pos, player_pos);
-
-                // Convert fog color to egui color
-                let egui_color = fog_result.color.map(|c| FogOfWar::to_egui_color(&c));
-
-                if fog_result.should_render {
-                    self.print_at(
-                        start_x + screen_x,
-                        start_y + screen_y,
-                        &fog_result.character.to_string(),
-                        egui_color,
-                    );
+        // Draw the map, centered on the player
+        let start_x = MAP_VIEW_X;
+        let start_y = MAP_VIEW_Y;
+        let mut frame = MapFrame::centered(
+            game.current_level(),
+            MAP_VIEW_WIDTH,
+            MAP_VIEW_HEIGHT,
+            self.settings.glyph_set(),
+        );
+        if self.show_threats {
+            frame.show_threats(game.current_level());
+        }
+        for cell in &frame.cells {
+            self.print_at(
+                start_x + cell.x,
+                start_y + cell.y,
+                &cell.glyph.symbol.to_string(),
+                Some(self.glyph_color(cell.glyph.color)),
+            );
+            if let Some(background) = cell.glyph.background {
+                let fill = self.glyph_color(background);
+                if let Some(slot) = self
+                    .background_buffer
+                    .get_mut(start_y + cell.y)
+                    .and_then(|line| line.get_mut(start_x + cell.x))
+                {
+                    *slot = Some(fill);
                 }
             }
         }
 
         // Draw UI panel
-        let ui_x = start_x + view_width + 3;
-        let player = &game.player;
+        let ui_x = start_x + MAP_VIEW_WIDTH + 3;
+        let panel = StatPanel::new(&game.player, game.current_dungeon());
 
         self.print_at(
             ui_x,
             start_y,
-            &player.name,
+            &panel.name,
             Some(Color32::from_rgb(0, 255, 255)),
         );
-        self.print_at(
-            ui_x,
-            start_y + 1,
-            &format!("Level {} {}", player.level, player.class.class_type),
-            None,
-        );
-        self.print_at(
-            ui_x,
-            start_y + 2,
-            &format!("HP: {}/{}", player.health, player.max_health),
-            None,
-        );
-        self.print_at(
-            ui_x,
-            start_y + 3,
-            &format!("MP: {}/{}", player.mana, player.max_mana),
-            None,
-        );
-        self.print_at(
-            ui_x,
-            start_y + 4,
-            &format!("XP: {}/{}", player.experience, player.level * 100),
-            None,
-        );
-        self.print_at(ui_x, start_y + 5, &format!("Gold: {}", player.gold), None);
+        self.flash(ui_x, start_y, panel.name.len(), ScreenEffect::LevelUp);
+        for (i, row) in panel.rows.iter().enumerate() {
+            self.print_at(ui_x, start_y + 1 + i, row, None);
+            self.flash(ui_x, start_y + 1 + i, row.len(), ScreenEffect::LevelUp);
+        }
+        // "HP ########-------- 34/50", the bar colored by how full it is
+        let bars_y = start_y + 1 + panel.rows.len();
+        for (i, stat) in panel.bars.iter().enumerate() {
+            let bar_x = ui_x + stat.label.len() + 1;
+            self.print_at(ui_x, bars_y + i, stat.label, None);
+            self.print_bar(bar_x, bars_y + i, &stat.bar, stat.color);
+            self.print_at(
+                bar_x + stat.bar.width + 1,
+                bars_y + i,
+                &stat.bar.value(),
+                None,
+            );
+            let width = bar_x + stat.bar.width + 1 + stat.bar.value().len() - ui_x;
+            self.flash(ui_x, bars_y + i, width, ScreenEffect::LevelUp);
+            if i == 0 {
+                self.flash(ui_x, bars_y, width, ScreenEffect::Hurt);
+            }
+        }
+        self.print_at(ui_x, start_y + 6, &panel.location, None);
+        if let Some(light) = &panel.light {
+            self.print_at(ui_x, start_y + 7, light, Some(Color32::YELLOW));
+        }
+        if let Some(companion) = &panel.companion {
+            self.print_at(ui_x, start_y + 8, companion, Some(Color32::GREEN));
+        }
+        if let Some(ambient) = &panel.ambient {
+            self.print_at(ui_x, start_y + 9, ambient, Some(Color32::LIGHT_BLUE));
+        }
+
+        // Draw the hotbar under the map, empty slots dimmed
+        let slot_width = MAP_VIEW_WIDTH / HOTBAR_SLOTS;
+        for (i, slot) in InventoryManager::hotbar_slots(&game.player)
+            .iter()
+            .enumerate()
+        {
+            let color = slot.is_empty().then_some(Color32::DARK_GRAY);
+            self.print_at(
+                start_x + i * slot_width,
+                start_y + MAP_VIEW_HEIGHT + 1,
+                &slot.label_within(slot_width - 1),
+                color,
+            );
+        }
+        // What G would do right now, under the hotbar
+        if let Some(interaction) = game.current_interaction() {
+            self.print_at(
+                start_x,
+                start_y + MAP_VIEW_HEIGHT + 2,
+                &interaction.prompt(),
+                Some(Color32::YELLOW),
+            );
+        }
 
         // Draw controls
-        let controls_y = start_y + 8;
+        let controls_y = start_y + 10;
         self.print_at(
             ui_x,
             controls_y,
             "Controls:",
             Some(Color32::from_rgb(0, 255, 255)),
         );
-        self.print_at(ui_x, controls_y + 1, "WASD: Move", None);
-        self.print_at(ui_x, controls_y + 2, "I: Toggle Inventory", None);
-        self.print_at(ui_x, controls_y + 3, "C: Toggle Character", None);
-        self.print_at(ui_x, controls_y + 4, "G: Get item", None);
-        self.print_at(ui_x, controls_y + 5, "Q: Quit", None);
+        for (i, line) in CONTROLS.iter().enumerate() {
+            self.print_at(ui_x, controls_y + 1 + i, line, None);
+        }
 
         // Draw legend
-        let legend_y = controls_y + 8;
+        let legend_y = controls_y + 1 + CONTROLS.len();
         self.print_at(
             ui_x,
             legend_y,
             "Legend:",
             Some(Color32::from_rgb(0, 255, 255)),
         );
-        self.print_at(ui_x, legend_y + 1, "@ - You", None);
-        self.print_at(ui_x, legend_y + 2, "E - Enemy", None);
-        self.print_at(ui_x, legend_y + 3, "! - Item", None);
-        self.print_at(ui_x, legend_y + 4, "# - Wall", None);
-        self.print_at(ui_x, legend_y + 5, ". - Floor", None);
-        self.print_at(ui_x, legend_y + 6, "+ - Door", None);
-        self.print_at(ui_x, legend_y + 7, "C - Chest", None);
-        self.print_at(ui_x, legend_y + 8, "> - Stairs Down", None);
-        self.print_at(ui_x, legend_y + 9, "< - Stairs Up", None);
-        self.print_at(ui_x, legend_y + 10, "E - Exit", None);
-    }
-
-    fn render_victory_screen(&mut self, game: &crate::game::Game) {
+        for (i, entry) in legend(self.settings.glyph_set()).iter().enumerate() {
+            self.print_at(
+                ui_x,
+                legend_y + 1 + i,
+                &entry.glyph.symbol.to_string(),
+                Some(self.glyph_color(entry.glyph.color)),
+            );
+            self.print_at(
+                ui_x + 1,
+                legend_y + 1 + i,
+                &format!(" - {}", entry.label),
+                None,
+            );
+        }
+    }
+
+    /// Switches to the victory or game over screen, adding the run to the
+    /// hall of fame the first time
+    fn show_end_screen(&mut self) {
+        self.showing_end_screen = true;
+        if self.run_recorded {
+            return;
+        }
+        let Some(game) = self.game.as_mut() else {
+            return;
+        };
+
+        self.run_recorded = true;
+        // Winning or dying can be what earns an achievement
+        game.check_achievements();
+        self.drain_game_messages();
+        let Some(game) = self.game.as_ref() else {
+            return;
+        };
+        // Bones that can't be written just never turn up
+        if matches!(game.game_state, crate::game::GameState::GameOver) {
+            let _ = self.bones.record(BonesRecord::from_game(game));
+        }
+        match self.hall_of_fame.record_game(game) {
+            Ok(placement) => self.run_placement = Some(placement),
+            Err(e) => self.add_message(format!("Could not record the run: {e}")),
+        }
+    }
+
+    fn render_end_screen(&mut self, game: &crate::game::Game) {
         self.clear_screen();
 
-        // Draw victory screen
-        self.print_at(
-            5,
-            8,
-            "🎉 CONGRATULATIONS! 🎉",
-            Some(Color32::from_rgb(255, 255, 0)),
-        );
+        let mut y = 8;
+        if matches!(game.game_state, crate::game::GameState::GameOver) {
+            self.print_at(5, y, "💀 GAME OVER 💀", Some(Color32::from_rgb(255, 0, 0)));
+            if let Some(label) = game.run_label() {
+                self.print_at(5, y + 1, &label, Some(Color32::from_rgb(255, 0, 255)));
+            }
 
-        self.print_at(
-            5,
-            10,
-            "You have successfully completed the dungeon!",
-            Some(Color32::from_rgb(255, 255, 255)),
-        );
+            let death_message = format!(
+                "{} died at level {} after a brave adventure.",
+                game.player.name, game.player.level
+            );
+            self.print_at(
+                5,
+                y + 2,
+                &death_message,
+                Some(Color32::from_rgb(255, 255, 255)),
+            );
+            y += 4;
+        } else {
+            // Draw victory screen
+            self.print_at(
+                5,
+                y,
+                "🎉 CONGRATULATIONS! 🎉",
+                Some(Color32::from_rgb(255, 255, 0)),
+            );
+            if let Some(label) = game.run_label() {
+                self.print_at(5, y + 1, &label, Some(Color32::from_rgb(255, 0, 255)));
+            }
 
-        let completion_message = format!(
-            "{} completed the adventure at level {} and saved the realm!",
-            game.player.name, game.player.level
-        );
+            self.print_at(
+                5,
+                y + 2,
+                "You have successfully completed the dungeon!",
+                Some(Color32::from_rgb(255, 255, 255)),
+            );
+
+            let completion_message = format!(
+                "{} completed the adventure at level {} and saved the realm!",
+                game.player.name, game.player.level
+            );
+
+            self.print_at(
+                5,
+                y + 4,
+                &completion_message,
+                Some(Color32::from_rgb(0, 255, 0)),
+            );
+            y += 6;
+
+            for quest in game.quests.iter().filter(|q| q.is_complete()) {
+                let line = format!(
+                    "Quest complete: {} (+{} XP, +{} gold)",
+                    quest.item_name, quest.reward_experience, quest.reward_gold
+                );
+                self.print_at(5, y, &line, Some(Color32::from_rgb(255, 255, 0)));
+                y += 1;
+            }
+            y += 1;
+        }
 
-        self.print_at(
-            5,
-            12,
-            &completion_message,
-            Some(Color32::from_rgb(0, 255, 0)),
-        );
+        // Run summary table
+        self.print_at(5, y, "Run Summary", Some(Color32::from_rgb(0, 255, 255)));
+        for (label, value) in game.stats.summary_rows() {
+            y += 1;
+            self.print_at(5, y, &format!("{label:<20}{value:>8}"), None);
+        }
 
-        self.print_at(
-            5,
-            15,
-            "Press any key to return to main menu...",
-            Some(Color32::from_rgb(200, 200, 200)),
-        );
+        if let Some(placement) = self.run_placement {
+            y += 2;
+            self.print_at(5, y, &placement.to_string(), Some(Color32::YELLOW));
+        }
+
+        let prompt = if matches!(game.game_state, crate::game::GameState::Victory) {
+            "1. New Game+ (tougher dungeons)   2. Return to main menu"
+        } else {
+            "Press any key to return to main menu..."
+        };
+        self.print_at(5, y + 2, prompt, Some(Color32::from_rgb(200, 200, 200)));
+    }
+
+    /// Carries the victorious character on into New Game+
+    fn start_new_game_plus(&mut self) {
+        let Some(game) = self.game.as_mut() else {
+            return;
+        };
+        game.start_new_game_plus();
+        self.showing_end_screen = false;
+        self.run_recorded = false;
+        self.run_placement = None;
+        self.in_combat = false;
+        self.input_handler.clear_state();
     }
 
     fn handle_game_input(&mut self, key: char) {
@@ -546,53 +1636,65 @@ impl EchoesApp {
             } else {
                 match key {
                     'w' | 'W' => {
-                        if game.move_player(0, -1) {
-                            game.update_visibility();
-                            if !matches!(game.game_state, crate::game::GameState::Combat(_)) {
-                                game.process_turn();
-                            }
-                            self.check_for_combat();
-                        }
+                        self.game_loop.handle(game, Input::Move { dx: 0, dy: -1 });
+                        self.check_for_combat();
                     }
                     's' | 'S' => {
-                        if game.move_player(0, 1) {
-                            game.update_visibility();
-                            if !matches!(game.game_state, crate::game::GameState::Combat(_)) {
-                                game.process_turn();
-                            }
-                            self.check_for_combat();
-                        }
+                        self.game_loop.handle(game, Input::Move { dx: 0, dy: 1 });
+                        self.check_for_combat();
                     }
                     'a' | 'A' => {
-                        if game.move_player(-1, 0) {
-                            game.update_visibility();
-                            if !matches!(game.game_state, crate::game::GameState::Combat(_)) {
-                                game.process_turn();
-                            }
-                            self.check_for_combat();
-                        }
+                        self.game_loop.handle(game, Input::Move { dx: -1, dy: 0 });
+                        self.check_for_combat();
                     }
                     'd' | 'D' => {
-                        if game.move_player(1, 0) {
-                            game.update_visibility();
-                            if !matches!(game.game_state, crate::game::GameState::Combat(_)) {
-                                game.process_turn();
-                            }
-                            self.check_for_combat();
-                        }
+                        self.game_loop.handle(game, Input::Move { dx: 1, dy: 0 });
+                        self.check_for_combat();
+                    }
+                    '>' | '<' => {
+                        self.game_loop
+                            .handle(game, Input::Stairs { down: key == '>' });
+                        self.check_for_combat();
+                    }
+                    #[cfg(debug_assertions)]
+                    '`' => {
+                        game.log_level_budget();
+                        game.log_reachability();
+                    }
+                    '\u{15}' => {
+                        self.game_loop.handle(game, Input::Unstick);
+                        self.check_for_combat();
                     }
                     'g' | 'G' => {
                         // Try to get item at current position or adjacent chest
-                        if let Some(result) = game.try_get_item() {
-                            // Add a visual prefix for item/chest interactions with color coding
-                            let message = if result.contains("chest") {
-                                format!("📦 {result}")
-                            } else {
-                                format!("🔍 {result}")
-                            };
-                            self.add_message(message);
-                        }
+                        self.game_loop.handle(game, Input::GetItem);
+                        self.check_for_combat();
+                    }
+                    'e' | 'E' => {
+                        self.game_loop.handle(game, Input::Sense);
+                        self.check_for_combat();
+                    }
+                    '1'..='5' if !self.showing_character => {
+                        let slot = key as usize - '1' as usize;
+                        self.game_loop.handle(game, Input::Hotbar(slot));
+                        self.show_new_log_entries();
+                        self.check_for_combat();
                     }
+                    'f' | 'F' => match game.area_ability() {
+                        None => self.add_message(
+                            "You know no spell that reaches across the map.".to_string(),
+                        ),
+                        Some((index, _, AreaTarget::Caster)) => {
+                            let center = game.player_position();
+                            self.cast_area_ability(index, center);
+                        }
+                        Some((index, name, AreaTarget::Tile { .. })) => {
+                            self.aiming_area = Some(index);
+                            self.add_message(format!(
+                                "🔥 Click a tile to cast {name} there, or press Esc to cancel"
+                            ));
+                        }
+                    },
                     'i' | 'I' => {
                         // Toggle inventory screen
                         self.showing_inventory = !self.showing_inventory;
@@ -600,6 +1702,7 @@ impl EchoesApp {
                             self.showing_character = false; // Close character screen if open
                             self.add_message("🎒 Inventory opened - Press number keys 1-9 to equip items or use the Equip buttons".to_string());
                         } else {
+                            self.showing_crafting = false;
                             self.add_message("🎒 Inventory closed".to_string());
                         }
                     }
@@ -613,6 +1716,23 @@ impl EchoesApp {
                             self.add_message("👤 Character screen closed".to_string());
                         }
                     }
+                    #[cfg(feature = "audio")]
+                    'v' | 'V' => {
+                        let volume = self.audio.cycle_volume();
+                        self.add_message(format!("🔊 Sound: {volume}"));
+                        self.settings.volume = volume.name().to_string();
+                        if let Err(e) = self.settings_file.save(&self.settings) {
+                            self.add_message(format!("Settings not saved: {e}"));
+                        }
+                    }
+                    'j' | 'J' => {
+                        // Toggle quest journal
+                        self.showing_journal = !self.showing_journal;
+                    }
+                    'b' | 'B' => {
+                        // Toggle bestiary
+                        self.showing_bestiary = !self.showing_bestiary;
+                    }
                     'm' | 'M' => {
                         // Toggle message log visibility
                         self.toggle_message_log();
@@ -625,11 +1745,21 @@ impl EchoesApp {
                             .to_string(),
                         );
                     }
+                    '\t' => {
+                        // Toggle the threat overlay
+                        self.show_threats = !self.show_threats;
+                        self.add_message(
+                            if self.show_threats {
+                                "⚠ Threat overlay on (press Tab to hide)"
+                            } else {
+                                "⚠ Threat overlay off (press Tab to show)"
+                            }
+                            .to_string(),
+                        );
+                    }
                     'q' | 'Q' => {
-                        // Quit to main menu
-                        self.game_initialized = false;
-                        self.main_menu = true;
-                        self.show_main_menu();
+                        // Quitting goes through the pause menu's question
+                        self.pause(true);
                     }
                     _ => {}
                 }
@@ -637,30 +1767,33 @@ impl EchoesApp {
         }
     }
 
+    /// Casts the area ability at `index` at `aim` and lets the turn pass
+    /// if it went off
+    fn cast_area_ability(&mut self, index: usize, aim: Position) {
+        self.aiming_area = None;
+        let Some(game) = self.game.as_mut() else {
+            return;
+        };
+        self.game_loop.handle(game, Input::CastArea { index, aim });
+        self.show_new_log_entries();
+        self.check_for_combat();
+    }
+
     fn check_for_combat(&mut self) {
         if let Some(ref mut game) = self.game {
             match game.game_state {
-                crate::game::GameState::Combat(enemy_pos) => {
+                crate::game::GameState::Combat(_) => {
                     if !self.in_combat || game.combat_started {
                         self.in_combat = true;
-                        self.combat_enemy_pos = Some(enemy_pos);
-                        // Get enemy name before setting combat_started to false
-                        let enemy_name = game
-                            .current_level()
-                            .get_enemy_at(&enemy_pos)
-                            .map(|e| e.name.clone())
-                            .unwrap_or_else(|| "Unknown Enemy".to_string());
-
+                        self.game_loop.combat_target = 0;
                         self.combat_messages.clear();
-                        self.combat_messages
-                            .push(format!("Combat started with {enemy_name}!"));
+                        self.combat_messages.push(game.combat_start_message());
                         game.combat_started = false;
                     }
                 }
                 _ => {
                     if self.in_combat {
                         self.in_combat = false;
-                        self.combat_enemy_pos = None;
                     }
                 }
             }
@@ -668,168 +1801,494 @@ impl EchoesApp {
 
         // Check for victory state
         if let Some(ref game) = self.game {
-            if matches!(game.game_state, crate::game::GameState::Victory) {
-                self.showing_victory_screen = true;
+            if matches!(
+                game.game_state,
+                crate::game::GameState::Victory | crate::game::GameState::GameOver
+            ) {
+                self.show_end_screen();
             }
         }
     }
 
-    /// Handle inventory hotkey actions (1-9 keys for equipping/using items)
-    fn handle_inventory_hotkey(&mut self, action: &crate::input::InputAction) {
-        use crate::input::InputAction;
-
-        let item_index = match action {
+    /// Handle inventory hotkey actions (1-9 keys for equipping/using items).
+    /// Returns whether the key was one of them.
+    fn handle_inventory_hotkey(&mut self, action: &crate::input::InputAction) -> bool {
+        let item_index = if InputHandler::is_menu_option(action) {
             // Handle MenuOption actions (number keys 1-9)
-            InputAction::MenuOption(n) if *n >= 1 && *n <= 9 => Some(*n as usize - 1),
+            InputHandler::get_menu_option(action)
+                .filter(|n| (1..=9).contains(n))
+                .map(|n| n as usize - 1)
+        } else {
             // Handle Character actions for backward compatibility
-            InputAction::Character(c) if c.is_ascii_digit() && *c != '0' => {
-                c.to_digit(10).map(|d| d as usize - 1)
-            }
-            _ => None,
+            InputHandler::get_character(action)
+                .filter(|c| c.is_ascii_digit() && *c != '0')
+                .and_then(|c| c.to_digit(10))
+                .map(|d| d as usize - 1)
         };
 
         if let Some(index) = item_index {
-            if let Some(game) = &mut self.game {
-                if index < InventoryManager::get_item_count(&game.player) {
-                    let result = InventoryManager::use_item(&mut game.player, index);
-                    if result.success {
-                        self.add_message("🎒 Item used successfully!".to_string());
-                    } else {
-                        self.add_message(format!("🎒 Error: {}", result.message));
-                    }
+            self.use_inventory_item(index);
+        }
+        item_index.is_some()
+    }
+
+    /// Uses or equips the item at `index` of the inventory
+    fn use_inventory_item(&mut self, index: usize) {
+        if let Some(game) = &mut self.game {
+            if index < InventoryManager::get_item_count(&game.player) {
+                let result = game.use_item(index);
+                if result.success {
+                    self.add_message("🎒 Item used successfully!".to_string());
                 } else {
-                    self.add_message("🎒 Invalid item number".to_string());
+                    self.add_message(format!("🎒 Error: {}", result.message));
                 }
+            } else {
+                self.add_message("🎒 Invalid item number".to_string());
             }
         }
     }
 
     fn handle_combat_input(&mut self, key: char) {
         if let Some(ref mut game) = self.game {
-            if let Some(enemy_pos) = self.combat_enemy_pos {
-                // Handle ability selection screen
-                if self.showing_ability_selection {
-                    match key {
-                        '1'..='9' => {
-                            let index = key.to_digit(10).unwrap() as usize - 1;
-                            if index < game.player.class.abilities.len() {
-                                self.showing_ability_selection = false;
-                                self.process_combat_action(
-                                    crate::combat::CombatAction::UseAbility(index),
-                                    enemy_pos,
-                                );
-                            }
-                        }
-                        _ => {
-                            // Cancel ability selection on any other key
-                            self.showing_ability_selection = false;
-                        }
+            // Handle ability selection screen
+            if self.showing_ability_selection {
+                match key {
+                    '1'..='9' => {
+                        let index = key.to_digit(10).unwrap() as usize - 1;
+                        self.choose_ability(index);
+                    }
+                    _ => {
+                        // Cancel ability selection on any other key
+                        self.showing_ability_selection = false;
                     }
-                    return;
                 }
+                return;
+            }
 
-                // Handle main combat input
-                let action = match key {
-                    '1' => Some(crate::combat::CombatAction::Attack),
-                    '2' => {
-                        // Show ability selection screen
-                        if !game.player.class.abilities.is_empty() {
-                            self.showing_ability_selection = true;
-                            None
-                        } else {
-                            self.combat_messages
-                                .push("No abilities available!".to_string());
-                            None
-                        }
+            // Handle item selection screen
+            if self.showing_item_selection {
+                match key {
+                    '1'..='9' => {
+                        let index = key.to_digit(10).unwrap() as usize - 1;
+                        self.choose_combat_item(index);
                     }
-                    '3' => {
-                        // Use first consumable if available
-                        let consumables: Vec<_> = game
-                            .player
-                            .inventory
-                            .items
-                            .iter()
-                            .enumerate()
-                            .filter(|(_, item)| matches!(item, crate::item::Item::Consumable(_)))
-                            .collect();
-                        if !consumables.is_empty() {
-                            Some(crate::combat::CombatAction::UseItem(consumables[0].0))
-                        } else {
-                            self.combat_messages
-                                .push("No consumables available!".to_string());
-                            None
-                        }
+                    _ => {
+                        // Cancel item selection on any other key
+                        self.showing_item_selection = false;
                     }
-                    '4' => Some(crate::combat::CombatAction::Flee),
-                    _ => None,
-                };
+                }
+                return;
+            }
 
-                if let Some(combat_action) = action {
-                    self.process_combat_action(combat_action, enemy_pos);
+            // Handle main combat input
+            let action = match key {
+                '1' => Some(crate::combat::CombatAction::Attack),
+                '2' => {
+                    // Show ability selection screen
+                    if !game.player.available_abilities().is_empty() {
+                        self.showing_ability_selection = true;
+                        None
+                    } else {
+                        self.combat_messages
+                            .push("No abilities available!".to_string());
+                        None
+                    }
+                }
+                '3' => {
+                    // Show item selection screen
+                    if !InventoryManager::get_consumables(&game.player).is_empty() {
+                        self.showing_item_selection = true;
+                    } else {
+                        self.combat_messages
+                            .push("No consumables available!".to_string());
+                    }
+                    None
                 }
+                '4' => Some(crate::combat::CombatAction::Flee),
+                't' | 'T' => {
+                    // Aim at the next enemy still standing
+                    let enemies = game.combat_enemies();
+                    if enemies.len() > 1 {
+                        self.game_loop.combat_target =
+                            crate::combat::next_target(&enemies, self.game_loop.combat_target);
+                    }
+                    None
+                }
+                _ => None,
+            };
+
+            if let Some(combat_action) = action {
+                self.process_combat_action(combat_action);
             }
         }
     }
 
-    fn process_combat_action(&mut self, action: crate::combat::CombatAction, enemy_pos: Position) {
-        if let Some(ref mut game) = self.game {
-            if let Some(enemy) = game.current_level().get_enemy_at(&enemy_pos) {
-                let mut enemy_clone = enemy.clone();
-                let mut player_clone = game.player.clone();
-                let result =
-                    crate::combat::process_combat_turn(&mut player_clone, &mut enemy_clone, action);
+    /// Uses the ability at `index` of the ability list, if there's mana for
+    /// it and it isn't cooling down
+    fn choose_ability(&mut self, index: usize) {
+        let Some(game) = self.game.as_ref() else {
+            return;
+        };
+        if let Some(ability) = game.player.available_abilities().get(index) {
+            if game.player.mana < Class::mana_cost(ability) {
+                self.combat_messages
+                    .push(format!("Not enough mana to use {ability}!"));
+                return;
+            }
+            if game.player.cooldown_left(index) > 0 {
+                self.combat_messages.push(format!(
+                    "{ability} isn't ready yet {}",
+                    game.player.cooldown_label(index)
+                ));
+                return;
+            }
+            self.showing_ability_selection = false;
+            self.process_combat_action(crate::combat::CombatAction::UseAbility(index));
+        }
+    }
 
-                // Update game state
-                game.player = player_clone;
-                if !result.enemy_defeated && !result.player_fled {
-                    if let Some(enemy_ref) = game.current_level_mut().get_enemy_at_mut(&enemy_pos) {
-                        *enemy_ref = enemy_clone;
-                    }
+    /// Uses the consumable at `index` of the combat item list
+    fn choose_combat_item(&mut self, index: usize) {
+        let Some(game) = self.game.as_ref() else {
+            return;
+        };
+        let item_index = InventoryManager::get_consumables(&game.player)
+            .get(index)
+            .map(|(item_index, _)| *item_index);
+        if let Some(item_index) = item_index {
+            self.showing_item_selection = false;
+            self.process_combat_action(crate::combat::CombatAction::UseItem(item_index));
+        }
+    }
+
+    fn process_combat_action(&mut self, action: crate::combat::CombatAction) {
+        let Some(game) = self.game.as_mut() else {
+            return;
+        };
+        let turn = self.game_loop.handle(game, Input::Combat(action));
+        for effect in turn.effects {
+            match effect {
+                Effect::CombatTurn {
+                    result,
+                    target,
+                    health_before,
+                } => {
+                    let health_after = game
+                        .combat_enemies()
+                        .get(target)
+                        .map_or(0, |enemy| enemy.health);
+
+                    // The fight only ends once its messages have played out
+                    let ending = result.ending(&game.player);
+                    self.combat_playback.start_turn(
+                        self.time,
+                        result.messages,
+                        (health_before, health_after),
+                        ending,
+                    );
                 }
+                Effect::LevelUp => self.showing_level_up = true,
+                _ => {}
+            }
+        }
+    }
+
+    /// Moves due combat messages onto the combat log and ends the fight
+    /// once the last turn has played out
+    fn advance_combat_playback(&mut self) {
+        let revealed = self.combat_playback.tick(self.time);
+        self.combat_messages.extend(revealed);
+
+        if let Some(ending) = self.combat_playback.take_ending() {
+            self.finish_combat(ending);
+        }
+    }
 
-                // Add combat messages
-                for message in &result.messages {
-                    self.combat_messages.push(message.clone());
+    fn finish_combat(&mut self, ending: CombatEnding) {
+        let Some(game) = self.game.as_mut() else {
+            return;
+        };
+
+        // The fight's messages and its outcome are already in the game log
+        self.game_loop.handle(game, Input::CombatShown);
+        self.in_combat = false;
+        self.game_loop.combat_target = 0;
+        self.combat_messages.clear();
+
+        if ending == CombatEnding::Defeat {
+            self.show_end_screen();
+        }
+    }
+
+    /// What the gamepad's buttons should do on the current screen. In combat
+    /// the shoulder buttons cycle through the actions or the open list.
+    fn gamepad_context(&self) -> PadContext {
+        let Some(game) = self.game.as_ref() else {
+            return PadContext::Menu;
+        };
+        if self.showing_end_screen && matches!(game.game_state, crate::game::GameState::Victory) {
+            return PadContext::Choices(vec![
+                InputAction::MenuOption(1),
+                InputAction::MenuOption(2),
+            ]);
+        }
+        if !self.game_initialized
+            || self.showing_end_screen
+            || self.main_menu
+            || self.creating_character
+            || self.show_combat_tutorial
+            || self.showing_level_up
+            || self.combat_playback.is_playing()
+            || self.is_paused()
+        {
+            return PadContext::Menu;
+        }
+        if !self.in_combat {
+            return PadContext::Exploring;
+        }
+
+        let count = if self.showing_ability_selection {
+            game.player.available_abilities().len()
+        } else if self.showing_item_selection {
+            InventoryManager::get_consumables(&game.player).len()
+        } else {
+            4
+        };
+        let mut choices: Vec<InputAction> = (1..=count.min(9) as u8)
+            .map(InputAction::MenuOption)
+            .collect();
+        if !self.showing_ability_selection
+            && !self.showing_item_selection
+            && game.combat_enemies().len() > 1
+        {
+            choices.push(InputAction::Character('t'));
+        }
+        PadContext::Choices(choices)
+    }
+
+    /// Color for line `index` of a combat list: highlighted when the gamepad
+    /// or the arrow keys have it selected
+    fn choice_color(&self, index: usize, color: Option<Color32>) -> Option<Color32> {
+        let selected = self
+            .gamepad
+            .selected()
+            .or_else(|| self.menu_list.map(|_| self.menu.selected()));
+        if selected == Some(index) {
+            Some(Color32::YELLOW)
+        } else {
+            color
+        }
+    }
+
+    /// The list on screen that the arrow keys move through, and how many
+    /// entries it has; None while they move the player or a screen handles
+    /// them itself
+    fn current_menu(&self) -> Option<(MenuList, usize)> {
+        if self.is_paused() || self.showing_end_screen {
+            return None;
+        }
+        if self.showing_level_up {
+            return Some((MenuList::LevelUp, StatType::iter().count()));
+        }
+        if self.main_menu {
+            let listed = self.options_selected.is_none()
+                && !self.showing_hall_of_fame
+                && !self.showing_achievements
+                && !self.entering_build_code;
+            return listed.then_some((MenuList::Main, MAIN_MENU_LEN));
+        }
+        if self.creating_character {
+            return match self.character_creation_state {
+                CharacterCreationState::EnteringName => None,
+                CharacterCreationState::SelectingClass => {
+                    Some((MenuList::Class, ClassType::ALL.len()))
+                }
+                CharacterCreationState::CustomizingStats => {
+                    Some((MenuList::Stats, StatType::iter().count()))
                 }
+                CharacterCreationState::ChoosingMode => Some((MenuList::Mode, 2)),
+            };
+        }
+        let game = self
+            .game
+            .as_ref()
+            .filter(|_| self.game_initialized && !self.show_combat_tutorial)?;
+        if self.in_combat {
+            Some(if self.showing_ability_selection {
+                (MenuList::Abilities, game.player.available_abilities().len())
+            } else if self.showing_item_selection {
+                (
+                    MenuList::CombatItems,
+                    InventoryManager::get_consumables(&game.player).len(),
+                )
+            } else if game.combat_enemies().len() > 1 {
+                (MenuList::CombatActions, COMBAT_NEXT_TARGET + 1)
+            } else {
+                (MenuList::CombatActions, COMBAT_NEXT_TARGET)
+            })
+        } else if self.showing_inventory {
+            Some((
+                MenuList::Inventory,
+                InventoryManager::get_item_count(&game.player),
+            ))
+        } else {
+            None
+        }
+    }
 
-                // Check if combat is over
-                if result.enemy_defeated {
-                    game.current_level_mut().remove_enemy_at(&enemy_pos);
-                    game.game_state = crate::game::GameState::Playing;
-                    game.combat_started = false;
-                    self.in_combat = false;
-                    self.combat_enemy_pos = None;
-                    // Add victory message directly to message log
-                    self.add_message("⚔️ You were victorious!".to_string());
+    /// Keeps `menu` on the list now on screen; a newly shown list starts at
+    /// its first entry
+    fn sync_menu(&mut self) {
+        let current = self.current_menu();
+        let list = current.map(|(list, _)| list);
+        if list != self.menu_list {
+            self.menu_list = list;
+            self.menu.reset();
+            self.redraw_menu();
+        }
+        if let Some((_, len)) = current {
+            self.menu.set_len(len);
+        }
+    }
 
-                    // Add any other combat messages to the message log
-                    let messages: Vec<String> = self.combat_messages.drain(..).collect();
-                    for msg in messages {
-                        self.add_message(msg);
-                    }
-                } else if result.player_fled {
-                    game.game_state = crate::game::GameState::Playing;
-                    game.combat_started = false;
-                    self.in_combat = false;
-                    self.combat_enemy_pos = None;
-                    // Add fled message directly to message log
-                    self.add_message("🏃 You fled from combat!".to_string());
+    /// The highlighted entry of `list`, if it's the list on screen
+    fn menu_highlight(&self, list: MenuList) -> Option<usize> {
+        (self.menu_list == Some(list)).then(|| self.menu.selected())
+    }
 
-                    // Add any other combat messages to the message log
-                    let messages: Vec<String> = self.combat_messages.drain(..).collect();
-                    for msg in messages {
-                        self.add_message(msg);
-                    }
-                } else if !game.player.is_alive() {
-                    game.game_state = crate::game::GameState::GameOver;
-                    self.in_combat = false;
-                    self.combat_enemy_pos = None;
+    /// Redraws the screens that aren't redrawn every frame
+    fn redraw_menu(&mut self) {
+        match self.menu_list {
+            Some(MenuList::Main) => self.show_main_menu(),
+            Some(MenuList::Class | MenuList::Stats | MenuList::Mode) => {
+                self.show_character_creation()
+            }
+            _ => {}
+        }
+    }
+
+    /// Moves the highlight with the up and down arrows and picks the
+    /// highlighted entry with Enter; true if the action was used
+    fn handle_menu_input(&mut self, action: &InputAction) -> bool {
+        if !matches!(
+            action,
+            InputAction::Move(Direction::North | Direction::South) | InputAction::Enter
+        ) {
+            return false;
+        }
+        self.sync_menu();
+        let Some(list) = self.menu_list else {
+            return false;
+        };
+        match self.menu.handle_action(action) {
+            MenuInput::Moved => self.redraw_menu(),
+            MenuInput::Chosen(index) => self.choose_menu_entry(list, index),
+            MenuInput::Cancelled | MenuInput::Ignored => {}
+        }
+        true
+    }
+
+    /// Does what picking entry `index` of `list` with its number key does
+    fn choose_menu_entry(&mut self, list: MenuList, index: usize) {
+        let option = InputAction::MenuOption(index as u8 + 1);
+        match list {
+            MenuList::Main => self.handle_main_menu_input(&option),
+            MenuList::Class | MenuList::Stats | MenuList::Mode => {
+                self.handle_character_creation_input(&option)
+            }
+            MenuList::CombatActions if index == COMBAT_NEXT_TARGET => self.handle_combat_input('t'),
+            MenuList::CombatActions => {
+                self.handle_combat_input(char::from_digit(index as u32 + 1, 10).unwrap_or('0'))
+            }
+            MenuList::Abilities => self.choose_ability(index),
+            MenuList::CombatItems => self.choose_combat_item(index),
+            MenuList::Inventory => self.use_inventory_item(index),
+            MenuList::LevelUp => {
+                if let Some(stat) = StatType::iter().nth(index) {
+                    self.allocate_stat_point(stat);
                 }
             }
         }
     }
 
     fn handle_input(&mut self, action: &crate::input::InputAction) {
+        // The help window takes every key until it's closed
+        if self.showing_help {
+            self.handle_help_input(action);
+            return;
+        }
+
+        // The build code field takes the typing; Esc backs out and Enter
+        // imports
+        if self.entering_build_code {
+            match action {
+                InputAction::Exit => {
+                    self.entering_build_code = false;
+                    self.show_main_menu();
+                }
+                InputAction::Enter => self.start_from_build_code(),
+                _ => {}
+            }
+            return;
+        }
+
+        // Number keys spend stat points while the level up window is open
+        if self.showing_level_up {
+            if self.handle_menu_input(action) {
+                return;
+            }
+            let stat_index = match action {
+                crate::input::InputAction::MenuOption(n) if (1..=5).contains(n) => {
+                    Some(*n as usize - 1)
+                }
+                crate::input::InputAction::Character(c @ '1'..='5') => {
+                    c.to_digit(10).map(|d| d as usize - 1)
+                }
+                _ => None,
+            };
+            if let Some(stat) = stat_index.and_then(|i| StatType::iter().nth(i)) {
+                self.allocate_stat_point(stat);
+            }
+            return;
+        }
+
+        // While a combat turn plays out, Space or Enter shows the rest at once
+        if self.combat_playback.is_playing() {
+            if matches!(
+                action,
+                crate::input::InputAction::Enter | crate::input::InputAction::Character(' ')
+            ) {
+                let rest = self.combat_playback.skip();
+                self.combat_messages.extend(rest);
+            }
+            return;
+        }
+
+        // The pause menu takes every key until it's closed
+        if self.is_paused() {
+            self.handle_pause_input(action);
+            return;
+        }
+
+        // So does a talk with an NPC
+        if self.is_talking() {
+            self.handle_dialogue_input(action);
+            return;
+        }
+
+        // '?' opens the help anywhere but the name prompt, which takes it as text
+        let naming = self.creating_character
+            && self.character_creation_state == CharacterCreationState::EnteringName;
+        if *action == crate::input::InputAction::Character('?') && !naming {
+            self.showing_help = true;
+            return;
+        }
+
+        // The arrows and Enter move through and pick from the list on screen
+        if self.handle_menu_input(action) {
+            return;
+        }
+
         // Skip processing character/inventory keys if those screens are already open
         if self.showing_inventory || self.showing_character {
             if let crate::input::InputAction::Character('i')
@@ -845,9 +2304,19 @@ impl EchoesApp {
                 return;
             }
 
-            // Handle number keys for equipping items in inventory
             if self.showing_inventory {
-                self.handle_inventory_hotkey(action);
+                if let crate::input::InputAction::Character('r')
+                | crate::input::InputAction::Character('R') = action
+                {
+                    self.showing_crafting = !self.showing_crafting;
+                    return;
+                }
+            }
+
+            // Number keys pick from the inventory while it's open, rather
+            // than using the hotbar as well
+            if self.showing_inventory && self.handle_inventory_hotkey(action) {
+                return;
             }
         }
 
@@ -867,6 +2336,10 @@ impl EchoesApp {
 
         if self.main_menu {
             self.handle_main_menu_input(action);
+        } else if *action == crate::input::InputAction::Save {
+            if self.game_initialized && !self.show_combat_tutorial {
+                self.save_game();
+            }
         } else if self.creating_character {
             self.handle_character_creation_input(action);
         } else if self.show_combat_tutorial {
@@ -887,6 +2360,8 @@ impl EchoesApp {
             crate::input::InputAction::Character(c) => *c,
             crate::input::InputAction::Enter => '\r',
             crate::input::InputAction::Backspace => '\u{8}',
+            // Ctrl+U, as a terminal would send it
+            crate::input::InputAction::Unstick => '\u{15}',
             crate::input::InputAction::MenuOption(n) => {
                 char::from_digit(*n as u32, 10).unwrap_or('0')
             }
@@ -905,86 +2380,130 @@ impl EchoesApp {
     fn render_combat_screen_safe(&mut self, game: &crate::game::Game) {
         self.clear_screen();
 
-        // Show ability selection screen if active
+        // Show ability or item selection screen if active
         if self.showing_ability_selection {
             self.render_ability_selection_screen(game);
             return;
         }
+        if self.showing_item_selection {
+            self.render_item_selection_screen(game);
+            return;
+        }
 
         // Draw combat UI
         self.print_at(5, 3, "=== COMBAT ===", Some(Color32::from_rgb(255, 255, 0)));
 
-        if let Some(enemy_pos) = self.combat_enemy_pos {
-            if let Some(enemy) = game.current_level().get_enemy_at(&enemy_pos) {
-                // Display enemy info
-                self.print_at(
-                    5,
-                    5,
-                    &format!("Enemy: {}", enemy.name),
-                    Some(Color32::from_rgb(255, 100, 100)),
-                );
-                self.print_at(
-                    5,
-                    6,
-                    &format!("HP: {}/{}", enemy.health, enemy.max_health),
-                    None,
-                );
+        let enemies = game.combat_enemies();
+        if enemies.is_empty() {
+            return;
+        }
+        // Once a turn has played out, move the aim off an enemy that fell
+        if !self.combat_playback.is_playing() {
+            self.game_loop.combat_target =
+                crate::combat::living_target(&enemies, self.game_loop.combat_target).unwrap_or(0);
+        }
+        let target = self.game_loop.combat_target.min(enemies.len() - 1);
+        let mut view = CombatView::new(&game.player, &enemies, target, &game.combat_context());
+        if self.combat_playback.is_playing() {
+            // The action is already chosen, so there's nothing to forecast
+            view.forecast.clear();
+            view.set_enemy_health(
+                self.combat_playback.enemy_health(self.time),
+                enemies[target].max_health,
+            );
+        }
 
-                // Display player info
-                self.print_at(
-                    5,
-                    8,
-                    &format!("Player: {}", game.player.name),
-                    Some(Color32::from_rgb(100, 255, 100)),
-                );
-                self.print_at(
-                    5,
-                    9,
-                    &format!("HP: {}/{}", game.player.health, game.player.max_health),
-                    None,
-                );
-                self.print_at(
-                    5,
-                    10,
-                    &format!("MP: {}/{}", game.player.mana, game.player.max_mana),
-                    None,
-                );
+        // Display enemy info
+        self.print_at(
+            5,
+            5,
+            &format!("Enemy: {}", view.enemy),
+            Some(Color32::from_rgb(255, 100, 100)),
+        );
+        self.print_at(5, 6, &view.enemy_health, None);
+        self.print_bar(
+            6 + view.enemy_health.len(),
+            6,
+            &view.enemy_bar,
+            view.enemy_bar.level().color(),
+        );
+        let enemy_width = view.enemy_health.len() + 1 + view.enemy_bar.width;
+        self.flash(5, 6, enemy_width, ScreenEffect::CriticalHit);
 
-                // Display combat options
-                self.print_at(
-                    5,
-                    12,
-                    "Combat Actions:",
-                    Some(Color32::from_rgb(255, 255, 255)),
-                );
-                self.print_at(5, 13, "1 - Attack", None);
-                self.print_at(5, 14, "2 - Use Ability", None);
-                self.print_at(5, 15, "3 - Use Item", None);
-                self.print_at(5, 16, "4 - Flee", None);
-
-                // Display combat messages
-                self.print_at(5, 18, "Combat Log:", Some(Color32::from_rgb(255, 255, 255)));
-                let start_line = 19;
-                let max_messages = 10;
-                let message_start = if self.combat_messages.len() > max_messages {
-                    self.combat_messages.len() - max_messages
-                } else {
-                    0
-                };
+        // In a group fight, list everyone below the target
+        for (i, line) in view.enemies.iter().enumerate() {
+            let color = if line.starts_with('>') {
+                Color32::from_rgb(255, 100, 100)
+            } else {
+                Color32::GRAY
+            };
+            self.print_at(5, 7 + i, line, Some(color));
+        }
+        let mut shift = if view.enemies.is_empty() {
+            0
+        } else {
+            view.enemies.len() + 1
+        };
 
-                // Clone the messages to avoid borrow checker issues
-                let messages_to_display: Vec<String> = self
-                    .combat_messages
-                    .iter()
-                    .skip(message_start)
-                    .cloned()
-                    .collect();
-                for (i, message) in messages_to_display.iter().enumerate() {
-                    if i < max_messages {
-                        self.print_at(5, start_line + i, message, None);
-                    }
-                }
-            }
+        // Display player info
+        self.print_at(
+            5,
+            8 + shift,
+            &format!("Player: {}", game.player.name),
+            Some(Color32::from_rgb(100, 255, 100)),
+        );
+        self.print_at(5, 9 + shift, &view.player_health, None);
+        self.flash(5, 9 + shift, view.player_health.len(), ScreenEffect::Hurt);
+        self.print_at(5, 10 + shift, &view.player_mana, None);
+        for line in &view.buffs {
+            shift += 1;
+            self.print_at(5, 10 + shift, line, Some(Color32::from_rgb(120, 220, 120)));
+        }
+
+        // What attacking is likely to do, before the player commits
+        for line in &view.forecast {
+            shift += 1;
+            self.print_at(5, 11 + shift, line, Some(Color32::from_rgb(220, 200, 120)));
+        }
+        if !view.forecast.is_empty() {
+            shift += 1;
+        }
+        // Why the numbers are what they are this turn
+        for line in &view.modifiers {
+            self.print_at(5, 11 + shift, line, Some(Color32::from_rgb(220, 140, 255)));
+            shift += 1;
+        }
+
+        // Display combat options
+        self.print_at(
+            5,
+            12 + shift,
+            "Combat Actions:",
+            Some(Color32::from_rgb(255, 255, 255)),
+        );
+        for (i, action) in view.actions.iter().enumerate() {
+            let color = self.choice_color(i, None);
+            self.print_at(5, 13 + shift + i, action, color);
+        }
+
+        // Display combat messages
+        let log_row = 14 + shift + view.actions.len();
+        let heading = self.combat_messages.heading(COMBAT_LOG_ROWS);
+        self.print_at(5, log_row, &heading, Some(Color32::from_rgb(255, 255, 255)));
+        if self.combat_playback.is_playing() {
+            self.print_at(
+                6 + heading.len(),
+                log_row,
+                "(Space: skip)",
+                Some(Color32::DARK_GRAY),
+            );
+        }
+        let start_line = log_row + 1;
+
+        // Clone the messages to avoid borrow checker issues
+        let messages_to_display = self.combat_messages.visible(COMBAT_LOG_ROWS).to_vec();
+        for (i, message) in messages_to_display.iter().enumerate() {
+            self.print_at(5, start_line + i, message, None);
         }
     }
 
@@ -994,11 +2513,17 @@ impl EchoesApp {
         // Store indexes of items to equip or use
         let mut equip_item_index: Option<usize> = None;
         let mut use_item_index: Option<usize> = None;
+        let mut drop_item_index: Option<usize> = None;
+        let mut enchant_item_index: Option<usize> = None;
+        let mut hotbar_assignment: Option<(usize, usize)> = None;
+        let mut craft_recipe: Option<usize> = None;
         // Static variable to persist across frames for feedback messages
         static mut EQUIP_RESULT_MESSAGE: Option<(String, u64)> = None;
 
+        let highlighted = self.menu_highlight(MenuList::Inventory);
         if let Some(ref game) = self.game {
             let player = &game.player;
+            let at_shrine = game.can_enchant();
 
             // Create a window for the inventory
             let window = egui::Window::new("Inventory")
@@ -1011,6 +2536,7 @@ impl EchoesApp {
                 ui.add_space(10.0);
 
                 ui.label(format!("Gold: {}", player.gold));
+                ui.label(InventoryManager::weight_text(player));
                 ui.separator();
 
                 // List inventory items
@@ -1019,7 +2545,7 @@ impl EchoesApp {
                 } else {
                     egui::ScrollArea::vertical().show(ui, |ui| {
                         // Add keyboard shortcut hint at the top of the inventory
-                        ui.label("Press 1-9 keys to quickly equip equipment items");
+                        ui.label("Up/Down and Enter, or 1-9, to use or equip an item");
                         ui.separator();
 
                         let items = InventoryManager::get_items(player);
@@ -1027,20 +2553,41 @@ impl EchoesApp {
                             ui.horizontal(|ui| {
                                 let is_equipped = item_info.is_equipped;
 
-                                let item_name = &item_info.name;
-                                let prefix = format!("{}. ", i + 1);
+                                let item_name = &format!(
+                                    "{}{}{}",
+                                    item_info.name,
+                                    item_info.durability_tag(),
+                                    item_info.weight_tag()
+                                );
+                                let marker = if highlighted == Some(i) { "> " } else { "" };
+                                let prefix = format!("{marker}{}. ", i + 1);
 
-                                // Create appropriate text with formatting
-                                let text = if is_equipped {
-                                    egui::RichText::new(prefix + item_name + " [Equipped]")
-                                        .color(Color32::from_rgb(0, 255, 0))
-                                        .strong()
+                                // Create appropriate text with formatting, colored by rarity
+                                let mut text = if is_equipped {
+                                    egui::RichText::new(prefix + item_name + " [Equipped]").strong()
                                 } else {
                                     egui::RichText::new(prefix + item_name)
                                 };
+                                if let Some(rarity) = item_info.rarity {
+                                    text = text.color(rarity_color(rarity));
+                                }
 
-                                // Show item name
-                                ui.label(text);
+                                // Show item name, with its full details on hover
+                                let label = ui.label(text);
+                                if let Some(details) = InventoryManager::get_item_details(player, i)
+                                {
+                                    label.on_hover_ui(|ui| {
+                                        ui.strong(&details.name);
+                                        ui.label(
+                                            RichText::new(&details.description)
+                                                .color(Color32::GRAY),
+                                        );
+                                        ui.separator();
+                                        for line in details.lines() {
+                                            ui.label(line);
+                                        }
+                                    });
+                                }
 
                                 // Add interaction buttons based on item type
                                 if let Some(item) = InventoryManager::get_item(player, i) {
@@ -1049,17 +2596,47 @@ impl EchoesApp {
                                             if !is_equipped && ui.button("Equip").clicked() {
                                                 equip_item_index = Some(i);
                                             }
+                                            if let Some(offer) =
+                                                at_shrine.then(|| game.enchant_offer(i)).flatten()
+                                            {
+                                                ui.menu_button("Enchant", |ui| {
+                                                    for line in offer.lines() {
+                                                        ui.label(line);
+                                                    }
+                                                    if offer.affordable()
+                                                        && ui.button("Offer it up").clicked()
+                                                    {
+                                                        enchant_item_index = Some(i);
+                                                        ui.close_menu();
+                                                    }
+                                                });
+                                            }
                                         }
                                         Item::Consumable(_) => {
                                             if ui.button("Use").clicked() {
                                                 use_item_index = Some(i);
                                             }
+                                            ui.menu_button("Hotbar", |ui| {
+                                                for slot in 0..HOTBAR_SLOTS {
+                                                    if ui
+                                                        .button(format!("Slot {}", slot + 1))
+                                                        .clicked()
+                                                    {
+                                                        hotbar_assignment = Some((i, slot));
+                                                        ui.close_menu();
+                                                    }
+                                                }
+                                            });
                                         }
                                         Item::Quest { .. } => {
                                             ui.label("Quest item");
                                         }
                                     }
                                 }
+
+                                if ui.button("Drop").clicked() {
+                                    drop_item_index = Some(i);
+                                }
                             });
                         }
                     });
@@ -1068,7 +2645,14 @@ impl EchoesApp {
                 // Show keyboard shortcuts reminder
                 ui.separator();
                 ui.label("Keyboard shortcuts:");
-                ui.label("• 1-9: Equip corresponding item");
+                ui.label("• Up/Down + Enter or 1-9: Use or equip an item");
+                ui.label("• Drop: Leave an item on the floor");
+                ui.label("• Hotbar: Put a consumable on keys 1-5 for use on the map");
+                ui.label("• Hover an item: Inspect its full stats");
+                ui.label("• R or Crafting: Make potions and more from materials");
+                if at_shrine {
+                    ui.label("• Enchant: Offer gold and Arcane Dust to strengthen gear");
+                }
                 ui.label("• I or ESC: Close inventory");
 
                 // Show feedback message if we have one
@@ -1093,11 +2677,86 @@ impl EchoesApp {
                     if ui.button("Close Inventory").clicked() {
                         self.showing_inventory = false;
                     }
+                    if ui.button("Crafting").clicked() {
+                        self.showing_crafting = !self.showing_crafting;
+                    }
                 });
             });
+
+            if self.showing_crafting {
+                egui::Window::new("Crafting")
+                    .collapsible(false)
+                    .resizable(false)
+                    .show(ui.ctx(), |ui| {
+                        for (i, recipe) in RECIPES.iter().enumerate() {
+                            let craftable = InventoryManager::can_craft(player, recipe);
+                            ui.horizontal(|ui| {
+                                let color = if craftable {
+                                    Color32::GREEN
+                                } else {
+                                    Color32::GRAY
+                                };
+                                ui.label(
+                                    RichText::new(format!("{}. {}", i + 1, recipe.label()))
+                                        .color(color),
+                                );
+                                if ui
+                                    .add_enabled(craftable, egui::Button::new("Craft"))
+                                    .clicked()
+                                {
+                                    craft_recipe = Some(i);
+                                }
+                            });
+                        }
+                        ui.separator();
+                        let materials = Material::ALL
+                            .iter()
+                            .map(|material| {
+                                format!(
+                                    "{} {}",
+                                    InventoryManager::material_count(player, *material),
+                                    material.name()
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        ui.label(format!("Carrying: {materials}"));
+                        if ui.button("Close").clicked() {
+                            self.showing_crafting = false;
+                        }
+                    });
+            }
+        }
+
+        if let Some(recipe) = craft_recipe.and_then(|i| RECIPES.get(i)) {
+            if let Some(game) = &mut self.game {
+                let result = InventoryManager::craft(&mut game.player, recipe);
+                self.add_message(format!("⚒ {}", result.message));
+            }
+        }
+
+        // Process equip/use/drop actions outside the UI closure to avoid borrow issues
+        if let Some((index, slot)) = hotbar_assignment {
+            if let Some(game) = &mut self.game {
+                let result = InventoryManager::assign_hotbar(&mut game.player, index, slot);
+                self.add_message(format!("🎒 {}", result.message));
+            }
+        }
+
+        if let Some(index) = drop_item_index {
+            if let Some(game) = &mut self.game {
+                let result = game.drop_item(index);
+                self.add_message(format!("🎒 {}", result.message));
+            }
+        }
+
+        if let Some(index) = enchant_item_index {
+            if let Some(game) = &mut self.game {
+                let result = game.enchant_item(index);
+                self.add_message(format!("✨ {}", result.message));
+            }
         }
 
-        // Process equip/use actions outside the UI closure to avoid borrow issues
         if let Some(index) = equip_item_index {
             if let Some(game) = &mut self.game {
                 if index < game.player.inventory.items.len() {
@@ -1135,7 +2794,7 @@ impl EchoesApp {
                     if let Some(Item::Consumable(_)) =
                         InventoryManager::get_item(&game.player, index)
                     {
-                        let result = InventoryManager::use_item(&mut game.player, index);
+                        let result = game.use_item(index);
                         self.add_message(format!("🧪 {}", result.message));
                     }
                 }
@@ -1157,6 +2816,20 @@ impl EchoesApp {
             window.show(ui.ctx(), |ui| {
                 ui.heading(format!("{} - Level {}", player.name, player.level));
                 ui.label(format!("Class: {}", player.class.class_type));
+                let (trait_name, trait_text) = player.class.class_type.passive();
+                ui.label(format!("Trait: {trait_name} - {trait_text}"));
+                if let Some(label) = game.run_label() {
+                    ui.label(RichText::new(label).color(Color32::YELLOW));
+                }
+                if game.ng_plus_counter > 0 {
+                    ui.label(
+                        RichText::new(format!("New Game+ {}", game.ng_plus_counter))
+                            .color(Color32::from_rgb(255, 0, 255)),
+                    );
+                }
+                if let Some(note) = game.difficulty_note() {
+                    ui.label(RichText::new(note).color(Color32::GRAY));
+                }
                 ui.add_space(10.0);
 
                 // Stats section
@@ -1166,12 +2839,37 @@ impl EchoesApp {
                 ui.label(format!(
                     "Experience: {}/{}",
                     player.experience,
-                    player.level * 100
+                    player.xp_for_next_level()
                 ));
                 ui.label(format!("Gold: {}", player.gold));
+                ui.label(format!("Carry capacity: {}", player.carry_capacity()));
+                ui.label(format!("Turns: {}", game.stats.turns));
+                if player.unspent_stat_points > 0 {
+                    ui.label(
+                        RichText::new(format!(
+                            "Unspent stat points: {}",
+                            player.unspent_stat_points
+                        ))
+                        .color(Color32::YELLOW),
+                    );
+                    if ui.button("Allocate Stat Points").clicked() {
+                        self.showing_level_up = true;
+                    }
+                }
 
                 ui.add_space(10.0);
 
+                if let Some(companion) = &player.companion {
+                    ui.heading("Companion");
+                    ui.label(companion.title());
+                    ui.label(format!(
+                        "{}, Attack: {}",
+                        companion.status_text(),
+                        companion.attack
+                    ));
+                    ui.add_space(10.0);
+                }
+
                 // Equipment section
                 ui.heading("Equipment");
                 for slot in equipment::EquipmentSlot::iter() {
@@ -1186,6 +2884,34 @@ impl EchoesApp {
                     ui.label(format!("{slot}: {equipped}"));
                 }
 
+                // A code others can start a new game from
+                ui.add_space(10.0);
+                ui.heading("Build Code");
+                ui.horizontal(|ui| {
+                    if ui.button("Export").clicked() {
+                        self.build_code = player.export_code();
+                        ui.output_mut(|output| output.copied_text = self.build_code.clone());
+                    }
+                    if ui.button("Import").clicked() {
+                        self.build_code_notice =
+                            Some(match Player::import_code(&self.build_code) {
+                                Ok(build) => format!(
+                                    "A level {} {} named {}: start from it on the main menu",
+                                    build.level, build.class.class_type, build.name
+                                ),
+                                Err(e) => format!("That build code won't load: {e}"),
+                            });
+                    }
+                });
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.build_code)
+                        .hint_text("Export copies your code; paste one to check it")
+                        .desired_width(f32::INFINITY),
+                );
+                if let Some(notice) = &self.build_code_notice {
+                    ui.label(notice);
+                }
+
                 ui.separator();
                 ui.label("Press C or ESC to close character screen");
 
@@ -1199,27 +2925,423 @@ impl EchoesApp {
         }
     }
 
-    /// Adds a message to both the UI messages list and the message log with timestamp
+    /// Adds a message to the game's log, or straight to the status bar
+    /// when no game is running
     fn add_message(&mut self, message: String) {
-        // Add to UI messages (short-term display)
-        self.ui_messages.push(message.clone());
+        match self.game.as_mut() {
+            Some(game) => {
+                game.log.push(LogCategory::System, message);
+                self.show_new_log_entries();
+            }
+            None => self.push_recent(message),
+        }
+    }
+
+    /// Adds a message to the status bar's short list of recent messages
+    fn push_recent(&mut self, message: String) {
+        self.ui_messages.push(message);
 
         // Limit UI messages to 8 most recent for better history in status bar
         if self.ui_messages.len() > 8 {
             self.ui_messages.remove(0);
         }
+    }
 
-        // Add to message log with current timestamp
+    /// Timestamps the log entries not shown yet, so the log panel can fade
+    /// them as they age, and puts them on the status bar
+    fn show_new_log_entries(&mut self) {
+        let Some(game) = self.game.as_mut() else {
+            return;
+        };
         let current_time = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_secs_f64();
+        game.log.stamp_unread(current_time);
+        for entry in game.log.take_unread() {
+            self.push_recent(entry.text);
+        }
+    }
+
+    /// Spends one of the player's unspent stat points
+    fn allocate_stat_point(&mut self, stat: StatType) {
+        let allocated = self
+            .game
+            .as_mut()
+            .is_some_and(|game| game.player.allocate_stat_point(stat).is_ok());
+
+        if allocated {
+            self.add_message(format!("⭐ {stat} increased by 1"));
+        }
+        if self
+            .game
+            .as_ref()
+            .is_none_or(|game| game.player.unspent_stat_points == 0)
+        {
+            self.showing_level_up = false;
+        }
+    }
+
+    /// Displays the level up window for spending stat points
+    fn show_level_up_screen(&mut self, ui: &mut egui::Ui) {
+        let Some(ref game) = self.game else {
+            return;
+        };
+        let player = &game.player;
+        let highlighted = self.menu_highlight(MenuList::LevelUp);
+        let mut chosen_stat = None;
+        let mut close = false;
+
+        egui::Window::new("Level Up!")
+            .fixed_size([350.0, 300.0])
+            .collapsible(false)
+            .resizable(false)
+            .show(ui.ctx(), |ui| {
+                ui.heading(format!("{} reached level {}", player.name, player.level));
+                ui.label(format!(
+                    "Stat points to spend: {}",
+                    player.unspent_stat_points
+                ));
+                ui.add_space(10.0);
+
+                for (i, stat) in StatType::iter().enumerate() {
+                    let value = match stat {
+                        StatType::Strength => player.stats.strength,
+                        StatType::Intelligence => player.stats.intelligence,
+                        StatType::Dexterity => player.stats.dexterity,
+                        StatType::Constitution => player.stats.constitution,
+                        StatType::Wisdom => player.stats.wisdom,
+                    };
+                    let marker = if highlighted == Some(i) { "> " } else { "" };
+                    if ui
+                        .button(format!("{marker}{}. {stat}: {value}  (+1)", i + 1))
+                        .clicked()
+                    {
+                        chosen_stat = Some(stat);
+                    }
+                }
+
+                ui.separator();
+                ui.label("Up/Down and Enter, or 1-5, to raise a stat; ESC to decide later");
+                if ui.button("Decide Later").clicked() {
+                    close = true;
+                }
+            });
+
+        if let Some(stat) = chosen_stat {
+            self.allocate_stat_point(stat);
+        }
+        if close {
+            self.showing_level_up = false;
+        }
+    }
+
+    /// Displays the quest journal window
+    fn show_quest_journal(&mut self, ui: &mut egui::Ui) {
+        let Some(ref game) = self.game else {
+            return;
+        };
+        let mut close = false;
+
+        egui::Window::new("Quest Journal")
+            .fixed_size([400.0, 300.0])
+            .collapsible(false)
+            .resizable(false)
+            .show(ui.ctx(), |ui| {
+                for (heading, completed) in [("Active Quests", false), ("Completed Quests", true)] {
+                    ui.heading(heading);
+                    let mut any = false;
+                    for quest in game.quests.iter().filter(|q| q.is_complete() == completed) {
+                        any = true;
+                        let color = match quest.status {
+                            QuestStatus::Active => Color32::WHITE,
+                            QuestStatus::ItemFound => Color32::GREEN,
+                            QuestStatus::Completed => Color32::GRAY,
+                        };
+                        ui.label(
+                            RichText::new(format!("{} [{}]", quest.title, quest.status_label()))
+                                .color(color),
+                        );
+                        ui.label(RichText::new(&quest.description).color(Color32::GRAY));
+                    }
+                    if !any {
+                        ui.label("None");
+                    }
+                    ui.add_space(10.0);
+                }
+
+                ui.separator();
+                ui.label("Press J to close the journal");
+                if ui.button("Close Journal").clicked() {
+                    close = true;
+                }
+            });
+
+        if close {
+            self.showing_journal = false;
+        }
+    }
+
+    /// Displays the bestiary window
+    fn show_bestiary(&mut self, ui: &mut egui::Ui) {
+        let Some(ref game) = self.game else {
+            return;
+        };
+        let mut close = false;
+
+        egui::Window::new("Bestiary")
+            .fixed_size([400.0, 300.0])
+            .collapsible(false)
+            .resizable(false)
+            .show(ui.ctx(), |ui| {
+                if game.bestiary.is_empty() {
+                    ui.label("You haven't fought anything yet.");
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        for (name, entry) in game.bestiary.entries() {
+                            let color = if entry.is_identified() {
+                                Color32::GREEN
+                            } else {
+                                Color32::WHITE
+                            };
+                            ui.label(
+                                RichText::new(format!("{name} [{}]", entry.stats_text()))
+                                    .color(color),
+                            );
+                            ui.label(
+                                RichText::new(format!(
+                                    "Met {}, slain {}, worst hit taken {}",
+                                    entry.encountered, entry.killed, entry.max_damage_taken
+                                ))
+                                .color(Color32::GRAY),
+                            );
+                        }
+                    });
+
+                ui.separator();
+                ui.label("Press B to close the bestiary");
+                if ui.button("Close Bestiary").clicked() {
+                    close = true;
+                }
+            });
+
+        if close {
+            self.showing_bestiary = false;
+        }
+    }
+
+    /// Up and Down scroll the help window; Escape, Enter or '?' close it
+    fn handle_help_input(&mut self, action: &InputAction) {
+        match action {
+            InputAction::Move(Direction::North) => self.help_scroll += HELP_SCROLL_STEP,
+            InputAction::Move(Direction::South) => self.help_scroll -= HELP_SCROLL_STEP,
+            InputAction::Exit | InputAction::Enter | InputAction::Character('?') => {
+                self.showing_help = false;
+            }
+            _ => {}
+        }
+    }
+
+    /// Displays the help window: every key by context, the map symbols and
+    /// some tips, scrolled with the mouse wheel or the arrow keys
+    fn show_help_window(&mut self, ui: &mut egui::Ui) {
+        let scroll = std::mem::take(&mut self.help_scroll);
+        let mut close = false;
+
+        egui::Window::new("Help")
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .fixed_size([480.0, 420.0])
+            .collapsible(false)
+            .resizable(false)
+            .show(ui.ctx(), |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(380.0)
+                    .show(ui, |ui| {
+                        ui.scroll_with_delta(egui::vec2(0.0, scroll));
+                        for section in &KEY_SECTIONS {
+                            ui.heading(section.title);
+                            egui::Grid::new(section.title).show(ui, |ui| {
+                                for (key, action) in section.keys {
+                                    ui.label(RichText::new(*key).color(Color32::YELLOW));
+                                    ui.label(*action);
+                                    ui.end_row();
+                                }
+                            });
+                            ui.add_space(8.0);
+                        }
+
+                        ui.heading(help::SYMBOLS_TITLE);
+                        egui::Grid::new("help_symbols").show(ui, |ui| {
+                            for entry in legend(self.settings.glyph_set()) {
+                                ui.label(
+                                    RichText::new(entry.glyph.symbol.to_string())
+                                        .monospace()
+                                        .color(self.glyph_color(entry.glyph.color)),
+                                );
+                                ui.label(entry.label);
+                                ui.end_row();
+                            }
+                        });
+                        ui.add_space(8.0);
+
+                        ui.heading(help::TIPS_TITLE);
+                        for tip in TIPS {
+                            ui.label(format!("• {tip}"));
+                        }
+                    });
+
+                ui.separator();
+                if ui.button("Close (Esc)").clicked() {
+                    close = true;
+                }
+            });
+
+        if close {
+            self.showing_help = false;
+        }
+    }
+
+    /// Draws a talk with an NPC as a window over the map; its keys are
+    /// handled by `handle_dialogue_input`
+    fn show_dialogue(&mut self, ui: &mut egui::Ui) {
+        let Some(dialogue) = self.game.as_ref().and_then(Game::dialogue) else {
+            return;
+        };
+        let mut answer = None;
+
+        egui::Window::new(dialogue.speaker)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .collapsible(false)
+            .resizable(false)
+            .show(ui.ctx(), |ui| {
+                for line in &dialogue.lines {
+                    ui.label(line);
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if let Some(offer) = &dialogue.offer {
+                        if ui.button(format!("{offer} (Y)")).clicked() {
+                            answer = Some(true);
+                        }
+                    }
+                    if ui.button("Leave (Esc)").clicked() {
+                        answer = Some(false);
+                    }
+                });
+            });
+
+        if let Some(accept) = answer {
+            self.answer_dialogue(accept);
+        }
+    }
+
+    /// Draws the pause menu as a window over the dimmed game; the menu's
+    /// keys are handled by `handle_pause_input`
+    fn show_pause_menu(&mut self, ui: &mut egui::Ui) {
+        let ctx = ui.ctx().clone();
+        ctx.layer_painter(egui::LayerId::new(
+            egui::Order::PanelResizeLine,
+            egui::Id::new("pause_dim"),
+        ))
+        .rect_filled(ctx.screen_rect(), 0.0, Color32::from_black_alpha(160));
+
+        let options = self.pause_options();
+        let permadeath = self.game.as_ref().is_some_and(|game| game.permadeath);
+        let mut chosen = None;
+        let mut answer = None;
+
+        egui::Window::new("Paused")
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .collapsible(false)
+            .resizable(false)
+            .show(&ctx, |ui| {
+                if let Some(selected) = self.options_selected {
+                    ui.heading("Options");
+                    for (i, row) in self.settings.rows().iter().enumerate() {
+                        let color = if i == selected {
+                            Color32::YELLOW
+                        } else {
+                            Color32::WHITE
+                        };
+                        ui.label(RichText::new(row).color(color));
+                    }
+                    ui.separator();
+                    ui.label("Up/Down to choose, Left/Right to change, Escape to go back");
+                } else if self.confirming_quit {
+                    ui.label(RichText::new(quit_question(permadeath)).color(Color32::RED));
+                    ui.horizontal(|ui| {
+                        if ui.button("Yes (Y)").clicked() {
+                            answer = Some(true);
+                        }
+                        if ui.button("No (N)").clicked() {
+                            answer = Some(false);
+                        }
+                    });
+                } else {
+                    for (i, option) in options.iter().enumerate() {
+                        let label = format!("{}. {}", i + 1, option.label());
+                        if ui
+                            .selectable_label(i == self.pause_selected, label)
+                            .clicked()
+                        {
+                            chosen = Some(*option);
+                        }
+                    }
+                    ui.separator();
+                    ui.label("Up/Down and Enter to choose, Escape to resume");
+                }
+            });
+
+        match answer {
+            Some(true) => self.quit_to_main_menu(),
+            Some(false) => self.confirming_quit = false,
+            None => {}
+        }
+        if let Some(option) = chosen {
+            self.choose_pause_option(option);
+        }
+    }
 
-        self.message_log.push((message, current_time));
+    /// Shows the game's new log entries, plays its queued sounds and saves
+    /// any achievements it unlocked
+    fn drain_game_messages(&mut self) {
+        let Some(game) = self.game.as_mut() else {
+            return;
+        };
+        let sounds = game.take_sounds();
+        let effects = game.take_effects();
+        let saved = game
+            .take_unlocked_achievements()
+            .map(|profile| self.achievements.save(profile));
+        let reports = game.take_bug_reports();
+        if let Some(Err(e)) = saved {
+            self.add_message(format!("Achievements not saved: {e}"));
+        }
+        for report in reports {
+            if let Err(e) = crate::crash::log_report(report.heading, &report.lines) {
+                self.add_message(format!("Could not write the bug report: {e}"));
+            }
+        }
+        self.show_new_log_entries();
+        self.audio.play_all(sounds);
+        if self.settings.screen_effects {
+            self.effects.start(effects, self.time * 1000.0);
+        }
+    }
 
-        // Limit message log size
-        if self.message_log.len() > 100 {
-            self.message_log.remove(0);
+    /// Fills `width` cells from `(x, y)` with `effect`'s color while it's
+    /// flashing
+    fn flash(&mut self, x: usize, y: usize, width: usize, effect: ScreenEffect) {
+        if !self.effects.is_showing(effect, self.time * 1000.0) {
+            return;
+        }
+        let fill = self.glyph_color(effect.color());
+        if let Some(line) = self.background_buffer.get_mut(y) {
+            for slot in line.iter_mut().skip(x).take(width) {
+                *slot = Some(fill);
+            }
         }
     }
 
@@ -1247,33 +3369,118 @@ impl EchoesApp {
             Some(Color32::from_rgb(255, 255, 255)),
         );
 
-        for (i, ability) in game.player.class.abilities.iter().enumerate() {
-            self.print_at(5, 7 + i, &format!("{} - {}", i + 1, ability), None);
+        // Abilities the player can't afford or that are cooling down are
+        // greyed out
+        let abilities = game.player.available_abilities();
+        for (i, ability) in abilities.iter().enumerate() {
+            let cost = Class::mana_cost(ability);
+            let cooldown = game.player.cooldown_label(i);
+            let (line, color) = if game.player.mana < cost {
+                (
+                    format!("{} - {ability} ({cost} mana, not enough) {cooldown}", i + 1),
+                    Some(Color32::DARK_GRAY),
+                )
+            } else if game.player.cooldown_left(i) > 0 {
+                (
+                    format!("{} - {ability} ({cost} mana) {cooldown}", i + 1),
+                    Some(Color32::DARK_GRAY),
+                )
+            } else {
+                (
+                    format!("{} - {ability} ({cost} mana) {cooldown}", i + 1),
+                    None,
+                )
+            };
+            let color = self.choice_color(i, color);
+            self.print_at(5, 7 + i, &line, color);
         }
 
+        self.print_at(
+            5,
+            7 + abilities.len() + 1,
+            &format!("Mana: {}/{}", game.player.mana, game.player.max_mana),
+            Some(Color32::from_rgb(0, 128, 255)),
+        );
+
         // Instructions
         self.print_at(
             5,
-            7 + game.player.class.abilities.len() + 2,
-            "Press the number key to select an ability, or ESC to cancel",
+            7 + abilities.len() + 3,
+            "Up/Down and Enter, or the number key, to select an ability; ESC to cancel",
+            Some(Color32::from_rgb(200, 200, 200)),
+        );
+    }
+
+    fn render_item_selection_screen(&mut self, game: &crate::game::Game) {
+        self.clear_screen();
+
+        self.print_at(
+            5,
+            3,
+            "=== SELECT ITEM ===",
+            Some(Color32::from_rgb(255, 255, 0)),
+        );
+        self.print_at(
+            5,
+            5,
+            "Usable Items:",
+            Some(Color32::from_rgb(255, 255, 255)),
+        );
+
+        let consumables = InventoryManager::get_consumables(&game.player);
+        for (i, (_, consumable)) in consumables.iter().enumerate() {
+            let line = format!(
+                "{} - {} ({})",
+                i + 1,
+                consumable.name,
+                consumable.effect_text()
+            );
+            let color = self.choice_color(i, None);
+            self.print_at(5, 7 + i, &line, color);
+        }
+
+        self.print_at(
+            5,
+            7 + consumables.len() + 2,
+            "Up/Down and Enter, or the number key, to select an item; ESC to cancel",
             Some(Color32::from_rgb(200, 200, 200)),
         );
     }
 }
 
-#[cfg(all(feature = "gui", target_os = "windows"))]
 impl eframe::App for EchoesApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // Increment frame counter
         self.frame_count += 1;
+        self.time = ctx.input(|i| i.time);
 
         // Process input using centralized handler
-        let actions = self.input_handler.process_input(ctx, self.frame_count);
+        let mut actions = self.input_handler.process_input(ctx, self.frame_count);
+        let context = self.gamepad_context();
+        actions.extend(self.gamepad.poll(&context, self.time));
+        if let Some(interval) = self.gamepad.poll_interval() {
+            ctx.request_repaint_after(interval);
+        }
+
+        // Nothing to do but wait while a new world is generated
+        if self.poll_generation(ctx) {
+            actions.clear();
+        }
 
-        // Check if Escape key is pressed to close any open screens
-        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
-            if self.showing_victory_screen {
-                self.showing_victory_screen = false;
+        // Check if Escape (or the gamepad's cancel button) is pressed to close
+        // any open screens; with none open it pauses the game
+        if actions.contains(&InputAction::Exit) && !self.is_paused() && !self.showing_help {
+            let closed = self.aiming_area.is_some()
+                || self.showing_end_screen
+                || self.showing_ability_selection
+                || self.showing_item_selection
+                || self.showing_level_up
+                || self.showing_inventory
+                || self.showing_character
+                || self.showing_journal
+                || self.showing_bestiary;
+            if self.showing_end_screen {
+                self.showing_end_screen = false;
                 self.game_initialized = false;
                 self.main_menu = true;
                 self.show_main_menu();
@@ -1283,7 +3490,20 @@ impl eframe::App for EchoesApp {
                 self.combat_messages
                     .push("Ability selection cancelled".to_string());
             }
-            if self.showing_inventory {
+            if self.showing_item_selection {
+                self.showing_item_selection = false;
+                self.combat_messages
+                    .push("Item selection cancelled".to_string());
+            }
+            if self.showing_level_up {
+                self.showing_level_up = false;
+            }
+            if self.aiming_area.take().is_some() {
+                self.add_message("You lower your hands without casting.".to_string());
+            }
+            if self.showing_crafting {
+                self.showing_crafting = false;
+            } else if self.showing_inventory {
                 self.showing_inventory = false;
                 self.add_message("🎒 Inventory closed".to_string());
             }
@@ -1291,13 +3511,59 @@ impl eframe::App for EchoesApp {
                 self.showing_character = false;
                 self.add_message("👤 Character screen closed".to_string());
             }
+            if self.showing_journal {
+                self.showing_journal = false;
+            }
+            if self.showing_bestiary {
+                self.showing_bestiary = false;
+            }
+            // The Escape that opened the menu mustn't close it again
+            if !closed && !self.main_menu && self.pause(false) {
+                actions.retain(|action| *action != InputAction::Exit);
+            }
         }
 
         // Handle each action
         for action in actions {
-            // If victory screen is shown, any key press returns to main menu
-            if self.showing_victory_screen {
-                self.showing_victory_screen = false;
+            // The display keys work on every screen
+            match action {
+                InputAction::ZoomIn | InputAction::ZoomOut => {
+                    self.adjust_display(Setting::FontSize, action == InputAction::ZoomIn);
+                    continue;
+                }
+                InputAction::ToggleFullscreen => {
+                    self.adjust_display(Setting::Fullscreen, true);
+                    continue;
+                }
+                // Earlier rounds of a long fight are a page away
+                InputAction::PageUp if self.in_combat => {
+                    self.combat_messages.page_up(COMBAT_LOG_ROWS);
+                    continue;
+                }
+                InputAction::PageDown if self.in_combat => {
+                    self.combat_messages.page_down(COMBAT_LOG_ROWS);
+                    continue;
+                }
+                _ => {}
+            }
+
+            // The victory screen offers New Game+; otherwise any key on the
+            // end screen returns to main menu
+            if self.showing_end_screen {
+                let won = self
+                    .game
+                    .as_ref()
+                    .is_some_and(|game| matches!(game.game_state, crate::game::GameState::Victory));
+                match action {
+                    InputAction::MenuOption(1) | InputAction::Character('n' | 'N') if won => {
+                        self.start_new_game_plus();
+                        continue;
+                    }
+                    InputAction::MenuOption(2) | InputAction::Exit => {}
+                    _ if won => continue,
+                    _ => {}
+                }
+                self.showing_end_screen = false;
                 self.game_initialized = false;
                 self.main_menu = true;
                 self.show_main_menu();
@@ -1306,6 +3572,28 @@ impl eframe::App for EchoesApp {
 
             self.handle_input(&action);
         }
+        self.sync_menu();
+
+        if self.fullscreen != self.settings.fullscreen {
+            self.fullscreen = self.settings.fullscreen;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(self.fullscreen));
+        }
+
+        // Flash the new font size or fullscreen state for a moment
+        if let Some((notice, shown_at)) = &self.display_notice {
+            if self.frame_count > shown_at + 90 {
+                self.display_notice = None;
+            } else {
+                egui::Area::new(egui::Id::new("display_notice"))
+                    .anchor(egui::Align2::RIGHT_TOP, [-20.0, 20.0])
+                    .show(ctx, |ui| {
+                        egui::Frame::popup(ui.style()).show(ui, |ui| {
+                            ui.label(RichText::new(notice).size(18.0).color(Color32::YELLOW));
+                        });
+                    });
+                ctx.request_repaint();
+            }
+        }
 
         // Main UI with dark terminal theme - remove borders and center content
         egui::CentralPanel::default()
@@ -1316,13 +3604,15 @@ impl eframe::App for EchoesApp {
                 ui.visuals_mut().window_fill = Color32::BLACK;
                 ui.visuals_mut().panel_fill = Color32::BLACK;
 
-                // Set monospace font for terminal display
-                let font_id = FontId::new(self.font_size, FontFamily::Monospace);
-
-                // Calculate responsive sizing
+                // Calculate responsive sizing; a font too big for the window
+                // shrinks to fit rather than cut off the screen
                 let available_size = ui.available_size();
-                let char_width = self.font_size * 0.6;
-                let char_height = self.font_size * 1.2;
+                let font_size = fitted_font_size(self.font_size, available_size, self.terminal_size);
+                let char_width = font_size * CHAR_WIDTH_RATIO;
+                let char_height = font_size * CHAR_HEIGHT_RATIO;
+
+                // Set monospace font for terminal display
+                let font_id = FontId::new(font_size, FontFamily::Monospace);
 
                 let max_cols = ((available_size.x * 0.9) / char_width) as usize;
                 let max_rows = ((available_size.y * 0.9) / char_height) as usize;
@@ -1346,20 +3636,26 @@ impl eframe::App for EchoesApp {
                             );
                         });
 
-                        ui.add_space(15.0);
+                        // Being hurt jolts the screen a pixel or two for a few frames
+                        let (shake_x, shake_y) = self.effects.shake_offset(self.time * 1000.0);
+                        ui.add_space(15.0 + shake_y);
 
-                        // Terminal content with explicit centering
+                        // Terminal content with explicit centering; row rects are kept
+                        // to map the mouse pointer back to buffer cells
+                        let mut row_rects = Vec::new();
                         for (y, line) in self.terminal_buffer.iter().enumerate() {
                             if y >= max_rows.saturating_sub(3) {
                                 break;
                             } // Leave space for UI elements
 
-                            ui.horizontal(|ui| {
+                            let row = ui.horizontal(|ui| {
                                 ui.spacing_mut().item_spacing.x = 0.0;
+                                ui.add_space(SHAKE_PIXELS + shake_x);
 
-                                // Group consecutive characters with same color into segments
+                                // Group consecutive characters with same colors into segments
                                 let mut current_segment = String::new();
                                 let mut current_color = Color32::from_rgb(192, 192, 192);
+                                let mut current_background: Option<Color32> = None;
                                 let mut segment_start = true;
 
                                 for (x, &ch) in line.iter().enumerate() {
@@ -1374,20 +3670,33 @@ impl eframe::App for EchoesApp {
                                     } else {
                                         Some(Color32::from_rgb(192, 192, 192))
                                     };
-
-                                    // If color changes or this is the first character, start new segment
-                                    if segment_start || color.unwrap_or(Color32::from_rgb(192, 192, 192)) != current_color {
+                                    let background = self
+                                        .background_buffer
+                                        .get(y)
+                                        .and_then(|line| line.get(x))
+                                        .copied()
+                                        .flatten();
+
+                                    // If a color changes or this is the first character, start new segment
+                                    if segment_start
+                                        || color.unwrap_or(Color32::from_rgb(192, 192, 192)) != current_color
+                                        || background != current_background
+                                    {
                                         // Render previous segment if it exists
                                         if !current_segment.is_empty() {
-                                            let text = RichText::new(&current_segment)
+                                            let mut text = RichText::new(&current_segment)
                                                 .font(font_id.clone())
                                                 .color(current_color);
+                                            if let Some(fill) = current_background {
+                                                text = text.background_color(fill);
+                                            }
                                             ui.label(text);
                                         }
 
                                         // Start new segment
                                         current_segment = ch.to_string();
                                         current_color = color.unwrap_or(Color32::from_rgb(192, 192, 192));
+                                        current_background = background;
                                         segment_start = false;
                                     } else {
                                         // Add to current segment
@@ -1397,22 +3706,73 @@ impl eframe::App for EchoesApp {
 
                                 // Render final segment
                                 if !current_segment.is_empty() {
-                                    let text = RichText::new(&current_segment)
+                                    let mut text = RichText::new(&current_segment)
                                         .font(font_id.clone())
                                         .color(current_color);
+                                    if let Some(fill) = current_background {
+                                        text = text.background_color(fill);
+                                    }
                                     ui.label(text);
                                 }
                             });
+                            row_rects.push(row.response.rect);
+                        }
+
+                        // Look tooltip: describe whatever map tile is under the pointer
+                        // (not while the pause menu covers the map)
+                        let pointer = ctx
+                            .input(|i| i.pointer.hover_pos())
+                            .filter(|_| !self.is_paused());
+                        if let Some(pointer) = pointer {
+                            let glyph_width = ui.fonts(|f| f.glyph_width(&font_id, 'W'));
+                            let hovered = row_rects
+                                .iter()
+                                .position(|rect| rect.y_range().contains(pointer.y))
+                                .filter(|_| glyph_width > 0.0)
+                                .and_then(|row| {
+                                    let left = row_rects[row].left() + SHAKE_PIXELS;
+                                    (pointer.x >= left).then(|| {
+                                        (((pointer.x - left) / glyph_width) as usize, row)
+                                    })
+                                });
+                            // While an area ability is aimed, a click casts it there
+                            let clicked = ctx.input(|i| i.pointer.primary_clicked());
+                            let aimed = self.aiming_area.zip(
+                                hovered
+                                    .filter(|_| clicked)
+                                    .and_then(|(col, row)| self.map_cell_position(col, row)),
+                            );
+                            if let Some((index, aim)) = aimed {
+                                self.cast_area_ability(index, aim);
+                            }
+                            if let Some(description) =
+                                hovered.and_then(|(col, row)| self.describe_map_cell(col, row))
+                            {
+                                egui::show_tooltip_at_pointer(
+                                    ctx,
+                                    egui::Id::new("map_look_tooltip"),
+                                    |ui| {
+                                        ui.label(description);
+                                    },
+                                );
+                            }
                         }
                     });
                 });
 
                 // Render game if active
                 if self.game_initialized && !self.show_combat_tutorial && self.game.is_some() {
+                    self.advance_combat_playback();
+                    if self.combat_playback.is_playing() || self.effects.expire(self.time * 1000.0)
+                    {
+                        ctx.request_repaint();
+                    }
+                    self.update_save_file();
+                    self.drain_game_messages();
                     // Clone the game data only at render time to avoid stale state
                     let game_clone = self.game.clone().unwrap();
-                    if self.showing_victory_screen {
-                        self.render_victory_screen(&game_clone);
+                    if self.showing_end_screen {
+                        self.render_end_screen(&game_clone);
                     } else if self.in_combat {
                         self.render_combat_screen_safe(&game_clone);
                     } else {
@@ -1436,6 +3796,34 @@ impl eframe::App for EchoesApp {
                     ctx.request_repaint();
                 }
 
+                if self.showing_level_up && self.game_initialized {
+                    self.show_level_up_screen(ui);
+                }
+
+                if self.showing_journal && self.game_initialized {
+                    self.show_quest_journal(ui);
+                }
+
+                if self.showing_bestiary && self.game_initialized {
+                    self.show_bestiary(ui);
+                }
+
+                if self.is_paused() {
+                    self.show_pause_menu(ui);
+                }
+
+                if self.is_talking() {
+                    self.show_dialogue(ui);
+                }
+
+                if self.showing_help {
+                    self.show_help_window(ui);
+                }
+
+                if self.entering_build_code {
+                    self.show_build_code_window(ui);
+                }
+
                 if self.showing_character && self.game_initialized {
                     self.show_character_screen(ui);
                     // Check if character screen was closed via button
@@ -1477,16 +3865,41 @@ impl eframe::App for EchoesApp {
                                 .color(Color32::from_rgb(180, 180, 180))
                                 .small(),
                         );
+                        if self.gamepad.is_connected() {
+                            ui.label(
+                                RichText::new("(gamepad connected)")
+                                    .color(Color32::from_rgb(0, 255, 0))
+                                    .small(),
+                            );
+                        }
                     });
 
                     // Full message log (when visible)
-                    if self.message_log_visible && !self.message_log.is_empty() {
+                    let log = self.game.as_ref().map(|game| &game.log);
+                    if let Some(log) = log.filter(|log| self.message_log_visible && !log.is_empty()) {
                         // Calculate current time to fade old messages
                         let current_time = std::time::SystemTime::now()
                             .duration_since(std::time::UNIX_EPOCH)
                             .unwrap_or_default()
                             .as_secs_f64();
 
+                        // Messages naming a notable item take that item's rarity color
+                        let notable_items: Vec<(String, Rarity)> = self
+                            .game
+                            .as_ref()
+                            .map(|game| {
+                                InventoryManager::get_items(&game.player)
+                                    .into_iter()
+                                    .filter_map(|info| match info.rarity {
+                                        Some(rarity) if rarity > Rarity::Common => {
+                                            Some((info.name, rarity))
+                                        }
+                                        _ => None,
+                                    })
+                                    .collect()
+                            })
+                            .unwrap_or_default();
+
                         // Create a scrollable message log area
                         egui::ScrollArea::vertical()
                             .max_height(150.0)
@@ -1499,22 +3912,39 @@ impl eframe::App for EchoesApp {
 
                                     // Display message log with timestamps
                                     ui.add_space(5.0);
-                                    for (i, (msg, time)) in self.message_log.iter().enumerate() {
+                                    for (i, entry) in log.iter().enumerate() {
+                                        let msg = &entry.text;
                                         // Fade older messages (30 seconds to full fade)
-                                        let age = current_time - time;
+                                        let age = current_time - entry.timestamp.unwrap_or(current_time);
                                         let alpha = (1.0 - (age / 30.0)).clamp(0.3, 1.0);
-                                        let color = if msg.contains("chest") || msg.contains("item") {
-                                            Color32::from_rgba_premultiplied(200, 255, 200, (alpha * 255.0) as u8)
-                                        } else if msg.contains("combat") || msg.contains("attack") || msg.contains("damage") {
-                                            Color32::from_rgba_premultiplied(255, 200, 200, (alpha * 255.0) as u8)
+                                        let rarity = notable_items
+                                            .iter()
+                                            .find(|(name, _)| msg.contains(name.as_str()))
+                                            .map(|(_, rarity)| *rarity);
+                                        let color = if let Some(rarity) = rarity {
+                                            let (r, g, b) = rarity.rgb();
+                                            Color32::from_rgba_unmultiplied(r, g, b, (alpha * 255.0) as u8)
                                         } else {
-                                            Color32::from_rgba_premultiplied(255, 255, 255, (alpha * 255.0) as u8)
+                                            let (r, g, b) = match entry.category {
+                                                LogCategory::Loot => (200, 255, 200),
+                                                LogCategory::Combat => (255, 200, 200),
+                                                LogCategory::Movement => (200, 230, 255),
+                                                LogCategory::System => (255, 255, 255),
+                                                LogCategory::Achievement => (255, 156, 255),
+                                                LogCategory::Hint => (255, 255, 156),
+                                            };
+                                            Color32::from_rgba_premultiplied(r, g, b, (alpha * 255.0) as u8)
                                         };
 
                                         ui.horizontal(|ui| {
                                             // Add small indicator for message type
-                                            let indicator = if i == self.message_log.len() - 1 { "➤ " } else { "• " };
+                                            let indicator = if i == log.len() - 1 { "➤ " } else { "• " };
                                             ui.label(RichText::new(indicator).color(color));
+                                            ui.label(
+                                                RichText::new(format!("[T{}]", entry.turn))
+                                                    .color(Color32::from_gray(120))
+                                                    .small(),
+                                            );
                                             ui.label(RichText::new(msg).color(color));
                                         });
                                     }
@@ -1552,11 +3982,25 @@ impl eframe::App for EchoesApp {
     }
 }
 
-#[cfg(all(feature = "gui", target_os = "windows"))]
-pub fn run_gui() -> Result<(), eframe::Error> {
+/// The largest font, up to `chosen`, at which the whole `cols` by `rows`
+/// buffer fits in `available` with the margins the layout leaves
+fn fitted_font_size(chosen: f32, available: egui::Vec2, (cols, rows): (usize, usize)) -> f32 {
+    let by_width = available.x * 0.9 / ((cols + 5) as f32 * CHAR_WIDTH_RATIO);
+    let by_height = available.y * 0.9 / ((rows + 3) as f32 * CHAR_HEIGHT_RATIO);
+    chosen.min(by_width).min(by_height).max(MIN_FONT_SIZE)
+}
+
+/// Display color for an equipment rarity tier
+fn rarity_color(rarity: Rarity) -> Color32 {
+    let (r, g, b) = rarity.rgb();
+    Color32::from_rgb(r, g, b)
+}
+
+pub fn run_gui(startup: StartupOptions) -> Result<(), eframe::Error> {
+    let (settings, _) = SettingsFile::in_default_location().load();
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_fullscreen(true)
+            .with_fullscreen(settings.fullscreen)
             .with_title("Echoes of the Forgotten Realm") // This still is not centered correctly.
             .with_resizable(true)
             .with_maximize_button(true)
@@ -1567,9 +4011,8 @@ pub fn run_gui() -> Result<(), eframe::Error> {
     eframe::run_native(
         "Echoes of the Forgotten Realm",
         options,
-        Box::new(|cc| Box::new(EchoesApp::new(cc))),
+        Box::new(move |cc| Box::new(EchoesApp::new(cc, startup))),
     )
 }
 
-#[cfg(all(feature = "gui", target_os = "windows"))]
 impl EchoesApp {}
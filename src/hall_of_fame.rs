@@ -0,0 +1,433 @@
+//! The hall of fame: a local leaderboard of finished runs
+//!
+//! Every run that ends in death or victory is recorded with a score built
+//! from the character's level, how deep they got and the gold they carried.
+//! Daily challenges are kept apart, one result per day.
+//! The desktop front-ends keep the list as JSON next to the save file and the
+//! web version keeps it in `localStorage`. A missing or unreadable list is
+//! treated as empty, so a corrupt file only costs the old entries.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::character::ClassType;
+use crate::game::{Game, GameState};
+
+/// How many runs the Hall of Fame screen lists
+pub const TOP_RUNS: usize = 10;
+
+/// Outcome recorded for a run that reached the exit
+pub const VICTORY: &str = "Victory";
+
+/// One finished run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub name: String,
+    pub class: ClassType,
+    pub level: u32,
+    /// Deepest dungeon level reached, counting from 1
+    pub deepest_level: u32,
+    pub dungeons_cleared: u32,
+    pub gold: u32,
+    pub turns: u32,
+    /// "Victory", or what killed the character
+    pub outcome: String,
+    /// Day the run ended, as YYYY-MM-DD
+    pub date: String,
+}
+
+impl RunRecord {
+    /// Describes a game that has just ended
+    pub fn from_game(game: &Game, date: String) -> Self {
+        let won = matches!(game.game_state, GameState::Victory);
+        let outcome = if won {
+            VICTORY.to_string()
+        } else {
+            match &game.stats.killed_by {
+                Some(enemy) => format!("Slain by {enemy}"),
+                None => "Died".to_string(),
+            }
+        };
+
+        RunRecord {
+            name: game.player.name.clone(),
+            class: game.player.class.class_type,
+            level: game.player.level,
+            deepest_level: game.stats.deepest_level,
+            dungeons_cleared: game.current_dungeon_index as u32 + u32::from(won),
+            gold: game.player.gold,
+            turns: game.stats.turns,
+            outcome,
+            date,
+        }
+    }
+
+    /// Ranking score; levels and depth outweigh gold, and clearing a
+    /// dungeon is worth the most
+    pub fn score(&self) -> u32 {
+        self.level * 100 + self.deepest_level * 50 + self.dungeons_cleared * 500 + self.gold
+    }
+}
+
+/// Where a newly recorded run landed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    /// Position among all recorded runs, counting from 1
+    pub rank: usize,
+    pub total: usize,
+}
+
+impl fmt::Display for Placement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.rank == 1 {
+            write!(f, "New best! ")?;
+        } else if self.rank <= TOP_RUNS {
+            write!(f, "Into the Hall of Fame! ")?;
+        }
+        write!(f, "#{} of {} runs", self.rank, self.total)
+    }
+}
+
+/// Every recorded run, best first
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Leaderboard {
+    runs: Vec<RunRecord>,
+    /// Daily challenge results by the day of the challenge
+    #[serde(default)]
+    daily: BTreeMap<String, RunRecord>,
+}
+
+impl Leaderboard {
+    /// Parses a stored leaderboard; anything unreadable starts a fresh one
+    pub fn from_json(json: &str) -> Self {
+        serde_json::from_str(json).unwrap_or_default()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Adds a run and reports its rank. A run that ties an older one ranks
+    /// below it.
+    pub fn record(&mut self, run: RunRecord) -> Placement {
+        let rank = self
+            .runs
+            .iter()
+            .take_while(|other| other.score() >= run.score())
+            .count();
+        self.runs.insert(rank, run);
+        Placement {
+            rank: rank + 1,
+            total: self.runs.len(),
+        }
+    }
+
+    /// Adds a game that has just ended: a daily challenge to its day, any
+    /// other run to the main list
+    pub fn record_game(&mut self, game: &Game) -> Placement {
+        match &game.daily {
+            Some(daily) => self.record_daily(RunRecord::from_game(game, daily.date.clone())),
+            None => self.record(RunRecord::from_game(game, today())),
+        }
+    }
+
+    /// Adds the result of the daily challenge of `run.date` and reports its
+    /// rank among every day's. A day keeps its best result, should a
+    /// character carry on into New Game+ and finish again.
+    pub fn record_daily(&mut self, run: RunRecord) -> Placement {
+        let score = run.score();
+        match self.daily.get(&run.date) {
+            Some(kept) if kept.score() >= score => {}
+            _ => {
+                self.daily.insert(run.date.clone(), run);
+            }
+        }
+        let rank = self
+            .daily
+            .values()
+            .filter(|other| other.score() > score)
+            .count();
+        Placement {
+            rank: rank + 1,
+            total: self.daily.len(),
+        }
+    }
+
+    /// The result recorded for the daily challenge of `date`, if it's been
+    /// taken
+    pub fn daily_result(&self, date: &str) -> Option<&RunRecord> {
+        self.daily.get(date)
+    }
+
+    /// The best `TOP_RUNS` runs
+    pub fn top(&self) -> &[RunRecord] {
+        &self.runs[..self.runs.len().min(TOP_RUNS)]
+    }
+
+    /// Text rows for the Hall of Fame screen, one per run
+    pub fn rows(&self) -> Vec<String> {
+        self.top()
+            .iter()
+            .enumerate()
+            .map(|(i, run)| {
+                format!(
+                    "{:>2}. {:<12} {:<7} L{:<2} D{:<2} {:>5}g {:>6}  {} ({})",
+                    i + 1,
+                    run.name,
+                    run.class.to_string(),
+                    run.level,
+                    run.deepest_level,
+                    run.gold,
+                    run.score(),
+                    run.outcome,
+                    run.date
+                )
+            })
+            .collect()
+    }
+
+    /// Text rows for the latest `TOP_RUNS` daily challenges, newest first
+    pub fn daily_rows(&self) -> Vec<String> {
+        self.daily
+            .iter()
+            .rev()
+            .take(TOP_RUNS)
+            .map(|(date, run)| {
+                format!(
+                    "{date}  {:<12} {:<7} L{:<2} D{:<2} {:>6}  {}",
+                    run.name,
+                    run.class.to_string(),
+                    run.level,
+                    run.deepest_level,
+                    run.score(),
+                    run.outcome
+                )
+            })
+            .collect()
+    }
+}
+
+/// Today's date as YYYY-MM-DD, in UTC
+pub fn today() -> String {
+    #[cfg(target_arch = "wasm32")]
+    let seconds = (js_sys::Date::now() / 1000.0) as i64;
+    #[cfg(not(target_arch = "wasm32"))]
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or(0);
+
+    let (year, month, day) = civil_date(seconds);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Converts a Unix timestamp to a (year, month, day) calendar date, using
+/// Howard Hinnant's days-to-civil algorithm
+fn civil_date(unix_seconds: i64) -> (i64, u32, u32) {
+    let days = unix_seconds.div_euclid(86_400) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// The leaderboard file kept next to the save
+#[cfg(not(target_arch = "wasm32"))]
+pub struct LeaderboardFile {
+    path: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl LeaderboardFile {
+    const FILE_NAME: &'static str = "hall_of_fame.json";
+
+    /// Uses the leaderboard file inside `dir`
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> Self {
+        LeaderboardFile {
+            path: dir.into().join(Self::FILE_NAME),
+        }
+    }
+
+    /// Reads the leaderboard, or an empty one if the file is missing or
+    /// corrupt
+    pub fn load(&self) -> Leaderboard {
+        std::fs::read_to_string(&self.path)
+            .map(|json| Leaderboard::from_json(&json))
+            .unwrap_or_default()
+    }
+
+    /// Adds a game that has just ended to the file and returns where it
+    /// placed, in the daily section for a daily challenge
+    pub fn record_game(&self, game: &Game) -> anyhow::Result<Placement> {
+        self.update(|leaderboard| leaderboard.record_game(game))
+    }
+
+    /// Changes the leaderboard with `change` and writes it back
+    fn update(
+        &self,
+        change: impl FnOnce(&mut Leaderboard) -> Placement,
+    ) -> anyhow::Result<Placement> {
+        let mut leaderboard = self.load();
+        let placement = change(&mut leaderboard);
+        crate::save::write_atomically(&self.path, leaderboard.to_json())?;
+        Ok(placement)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl crate::save::DataFile for LeaderboardFile {
+    fn in_dir(dir: std::path::PathBuf) -> Self {
+        Self::new(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(target_arch = "wasm32"))]
+    use crate::save::TempDir;
+
+    fn run(name: &str, level: u32, deepest_level: u32, gold: u32) -> RunRecord {
+        RunRecord {
+            name: name.to_string(),
+            class: ClassType::Warrior,
+            level,
+            deepest_level,
+            dungeons_cleared: 0,
+            gold,
+            turns: 100,
+            outcome: "Slain by Goblin".to_string(),
+            date: "2024-01-01".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_score_weighs_level_depth_and_gold() {
+        assert_eq!(run("A", 3, 2, 40).score(), 300 + 100 + 40);
+
+        let mut cleared = run("B", 3, 2, 40);
+        cleared.dungeons_cleared = 1;
+        assert!(cleared.score() > run("A", 3, 2, 40).score());
+        assert!(run("C", 5, 1, 0).score() > run("D", 4, 1, 90).score());
+    }
+
+    #[test]
+    fn test_runs_are_sorted_best_first() {
+        let mut leaderboard = Leaderboard::default();
+        assert_eq!(leaderboard.record(run("Low", 1, 1, 0)).rank, 1);
+        assert_eq!(leaderboard.record(run("High", 9, 4, 100)).rank, 1);
+
+        let placement = leaderboard.record(run("Middle", 4, 2, 10));
+        assert_eq!(placement, Placement { rank: 2, total: 3 });
+        assert_eq!(placement.to_string(), "Into the Hall of Fame! #2 of 3 runs");
+
+        // A tie ranks below the run that got there first
+        assert_eq!(leaderboard.record(run("Tie", 4, 2, 10)).rank, 3);
+
+        let names: Vec<&str> = leaderboard.top().iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["High", "Middle", "Tie", "Low"]);
+    }
+
+    #[test]
+    fn test_top_lists_ten_runs() {
+        let mut leaderboard = Leaderboard::default();
+        for level in 1..=12 {
+            leaderboard.record(run("Hero", level, 1, 0));
+        }
+        assert_eq!(leaderboard.runs.len(), 12);
+        assert_eq!(leaderboard.top().len(), TOP_RUNS);
+        assert_eq!(leaderboard.top()[0].level, 12);
+
+        let placement = leaderboard.record(run("Last", 1, 1, 0));
+        assert_eq!(placement.to_string(), "#13 of 13 runs");
+    }
+
+    #[test]
+    fn test_daily_results_are_kept_apart_by_day() {
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.record(run("Regular", 9, 9, 900));
+
+        let mut first = run("Ann", 3, 2, 10);
+        first.date = "2024-06-01".to_string();
+        let mut second = run("Bob", 5, 3, 0);
+        second.date = "2024-06-02".to_string();
+        assert_eq!(leaderboard.record_daily(first.clone()).rank, 1);
+        assert_eq!(
+            leaderboard.record_daily(second.clone()),
+            Placement { rank: 1, total: 2 }
+        );
+
+        // A day keeps its best result
+        let mut worse = run("Ann", 1, 1, 0);
+        worse.date = first.date.clone();
+        leaderboard.record_daily(worse);
+        assert_eq!(leaderboard.daily_result("2024-06-01"), Some(&first));
+        assert_eq!(leaderboard.daily_result("2024-06-03"), None);
+
+        assert_eq!(leaderboard.top().len(), 1);
+        let rows = leaderboard.daily_rows();
+        assert_eq!(rows.len(), 2);
+        assert!(rows[0].starts_with("2024-06-02  Bob"));
+
+        // Leaderboards saved before daily challenges still load
+        let old = r#"{"runs":[]}"#;
+        assert_eq!(Leaderboard::from_json(old), Leaderboard::default());
+        let restored = Leaderboard::from_json(&leaderboard.to_json());
+        assert_eq!(restored, leaderboard);
+    }
+
+    #[test]
+    fn test_corrupt_json_starts_fresh() {
+        assert!(Leaderboard::from_json("not json").runs.is_empty());
+        assert!(Leaderboard::from_json("").runs.is_empty());
+    }
+
+    #[test]
+    fn test_civil_date() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(951_782_400), (2000, 2, 29));
+        assert_eq!(civil_date(1_704_067_199), (2023, 12, 31));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_file_round_trip() {
+        let dir = TempDir::new("hall_of_fame");
+        let file = LeaderboardFile::new(dir.path());
+
+        // A missing file is an empty leaderboard
+        assert!(file.load().runs.is_empty());
+
+        file.update(|leaderboard| leaderboard.record(run("First", 2, 1, 5)))
+            .unwrap();
+        let placement = file
+            .update(|leaderboard| leaderboard.record(run("Second", 6, 3, 50)))
+            .unwrap();
+        assert_eq!(placement.to_string(), "New best! #1 of 2 runs");
+
+        let loaded = file.load();
+        assert_eq!(loaded.runs.len(), 2);
+        assert_eq!(loaded.top()[0], run("Second", 6, 3, 50));
+
+        // A corrupt file is replaced rather than blocking new records
+        std::fs::write(dir.path().join(LeaderboardFile::FILE_NAME), "{broken").unwrap();
+        assert!(file.load().runs.is_empty());
+        assert_eq!(
+            file.update(|leaderboard| leaderboard.record(run("Third", 1, 1, 0)))
+                .unwrap()
+                .total,
+            1
+        );
+    }
+}
@@ -0,0 +1,179 @@
+//! The help screen's contents
+//!
+//! The terminal, GUI and web front-ends all show this same text, so a key
+//! or tip added here appears everywhere at once. Where a front-end differs
+//! the description says so.
+
+use crate::render::legend;
+use crate::world::GlyphSet;
+
+/// Keys that do something in one part of the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HelpSection {
+    pub title: &'static str,
+    /// Each key and what it does
+    pub keys: &'static [(&'static str, &'static str)],
+}
+
+pub const KEY_SECTIONS: [HelpSection; 4] = [
+    HelpSection {
+        title: "Exploration",
+        keys: &[
+            ("Arrow keys", "Move (GUI: WASD too; web: swipe or D-pad)"),
+            ("G", "Do what the prompt under the map says"),
+            (
+                "> / <",
+                "Take the stairs you stand on down / up (GUI: . and ,)",
+            ),
+            ("1-5", "Use the consumable on that hotbar slot"),
+            ("E", "Sense which way the stairs down lie (Mages, once a level)"),
+            (
+                "Y / Esc",
+                "Talking with someone (walk into them or G): take up their offer / leave",
+            ),
+            ("I", "Inventory"),
+            ("C", "Character stats"),
+            ("J", "Quest journal"),
+            ("B", "Bestiary of the enemies you've met"),
+            ("L", "Message log (GUI: M)"),
+            ("X", "Look around (GUI: hover the map; web: click a tile)"),
+            (
+                "Tab",
+                "Show or hide the tiles enemies in sight can reach (red tint)",
+            ),
+            (
+                "F",
+                "Cast Fireball at a tile or Consecrate around you (GUI: click to aim; not on the web)",
+            ),
+            ("F5", "Save game (permadeath games autosave instead)"),
+            (
+                "Shift+M",
+                "Export the explored map to a text file (GUI: pause menu; web: downloads it)",
+            ),
+            (
+                "Ctrl+U",
+                "Stuck? Press twice to force your way free, for a fifth of your health",
+            ),
+            ("Ctrl +/-", "Bigger / smaller text (GUI only)"),
+            ("F11", "Toggle fullscreen (GUI only)"),
+            ("V", "Cycle sound volume (audio builds only)"),
+            ("?", "This help screen"),
+            ("Esc", "Pause menu"),
+            ("Q", "Quit to the title screen (asks first)"),
+        ],
+    },
+    HelpSection {
+        title: "Inventory",
+        keys: &[
+            ("1-9", "Use or equip an item"),
+            ("D", "Drop an item, then its number (GUI: Drop button)"),
+            ("X", "Inspect an item, then its number (GUI: hover it)"),
+            (
+                "N",
+                "At a shrine, enchant gear with gold and Arcane Dust, then its number (GUI: Enchant button)",
+            ),
+            (
+                "A",
+                "Put the highlighted item on the hotbar, then its slot (GUI: Hotbar button; web: A, the item's number, then the slot)",
+            ),
+            (
+                "R",
+                "Craft from gathered materials, then the recipe's number (GUI: Crafting button)",
+            ),
+            ("Esc", "Close the inventory"),
+        ],
+    },
+    HelpSection {
+        title: "Combat",
+        keys: &[
+            ("1", "Attack"),
+            ("2", "Use an ability"),
+            ("3", "Use an item"),
+            ("4", "Flee"),
+            ("T / Tab", "Aim at the next enemy in a group fight"),
+            ("Space / Enter", "Show the rest of a turn's messages"),
+            ("PgUp / PgDn", "Page back through the fight's log (not on the web)"),
+        ],
+    },
+    HelpSection {
+        title: "Menus",
+        keys: &[
+            ("Up / Down", "Move the highlight"),
+            ("Enter", "Pick the highlighted entry"),
+            ("1-9", "Pick an entry directly"),
+            ("Esc", "Go back"),
+        ],
+    },
+];
+
+pub const SYMBOLS_TITLE: &str = "Symbols";
+pub const TIPS_TITLE: &str = "Tips";
+
+/// How the game's less obvious mechanics work
+pub const TIPS: [&str; 14] = [
+    "Unaware enemies wander; noise from doors, chests and fights draws them in.",
+    "Dexterity muffles your footsteps, so nimble characters sneak better.",
+    "An unaware enemy takes extra damage from your first hit: a sneak attack.",
+    "Caverns are dark: use a torch from your inventory to see as far as usual.",
+    "Throwables used from the inventory hit the nearest enemy in sight.",
+    "Press G beside a chest to check it for traps first; walking into it skips the check.",
+    "Turn on Confirm stairs in the options to stop on stairs until you press > or <.",
+    "Fleeing steps you out of reach and winds the enemy; cornered, you can't flee.",
+    "Press U on the character screen to spend stat points after levelling up.",
+    "Press B on the character screen for a build code others can start a new game from.",
+    "A Taming Charm wins over a beast below a quarter of its health as a companion.",
+    "Strength raises how much you can carry; an encumbered player takes blows fleeing.",
+    "Unidentified gear reveals itself after 50 turns worn, but it might be cursed.",
+    "Rain in the forest hides you as well as it hides them: your noise carries half as far.",
+];
+
+/// The whole help screen as plain lines of text, for front-ends without
+/// headings or columns, with the map symbols drawn from `glyphs`
+pub fn help_lines(glyphs: GlyphSet) -> Vec<String> {
+    let key_width = KEY_SECTIONS
+        .iter()
+        .flat_map(|section| section.keys.iter())
+        .map(|(key, _)| key.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = Vec::new();
+    for section in &KEY_SECTIONS {
+        lines.push(format!("== {} ==", section.title));
+        for (key, action) in section.keys {
+            lines.push(format!("  {key:<key_width$}  {action}"));
+        }
+        lines.push(String::new());
+    }
+
+    lines.push(format!("== {SYMBOLS_TITLE} =="));
+    for entry in legend(glyphs) {
+        lines.push(format!("  {}  {}", entry.glyph.symbol, entry.label));
+    }
+    lines.push(String::new());
+
+    lines.push(format!("== {TIPS_TITLE} =="));
+    for tip in TIPS {
+        lines.push(format!("  - {tip}"));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_help_lists_every_section_symbol_and_tip() {
+        let lines = help_lines(GlyphSet::Unicode);
+        for section in &KEY_SECTIONS {
+            assert!(lines.contains(&format!("== {} ==", section.title)));
+        }
+        for entry in legend(GlyphSet::Unicode) {
+            assert!(lines.contains(&format!("  {}  {}", entry.glyph.symbol, entry.label)));
+        }
+        for tip in TIPS {
+            assert!(lines.iter().any(|line| line.ends_with(tip)));
+        }
+    }
+}
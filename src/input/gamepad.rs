@@ -0,0 +1,271 @@
+//! Gamepad support for the GUI
+//!
+//! Buttons and the left stick are turned into the same `InputAction`s the
+//! keyboard produces, so the rest of the GUI handles them unchanged. The GUI
+//! describes the screen it is showing with a `PadContext`, which decides what
+//! the south button does. Without a gamepad, or on a system gilrs can't read,
+//! polling quietly returns nothing.
+
+use std::time::Duration;
+
+use gilrs::{Axis, Button, EventType, Gilrs};
+
+use super::{Direction, InputAction};
+
+/// How far the left stick must lean before it counts as a direction
+const STICK_DEADZONE: f32 = 0.4;
+
+/// Seconds between repeated moves while the stick is held over
+const STICK_REPEAT_DELAY: f64 = 0.2;
+
+/// What kind of screen the gamepad is driving
+#[derive(Debug, Clone, PartialEq)]
+pub enum PadContext {
+    /// Menus and dialogs: south confirms like Enter
+    Menu,
+    /// Walking the map: south picks up items and opens chests like G
+    Exploring,
+    /// A list the shoulder buttons cycle through; south picks the
+    /// highlighted entry
+    Choices(Vec<InputAction>),
+}
+
+#[derive(Default)]
+pub struct Gamepad {
+    gilrs: Option<Gilrs>,
+    stick: StickRepeat,
+    choices: Vec<InputAction>,
+    selected: usize,
+}
+
+impl Gamepad {
+    /// Starts listening for gamepads, if the platform supports them
+    pub fn new() -> Self {
+        Self {
+            gilrs: Gilrs::new().ok(),
+            ..Self::default()
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.gilrs
+            .as_ref()
+            .is_some_and(|gilrs| gilrs.gamepads().any(|(_, pad)| pad.is_connected()))
+    }
+
+    /// The highlighted entry of the current choice list, while a gamepad is
+    /// connected to move it
+    pub fn selected(&self) -> Option<usize> {
+        (self.is_connected() && !self.choices.is_empty()).then_some(self.selected)
+    }
+
+    /// How soon the GUI should check again; egui only repaints on its own
+    /// input, so gamepad events would otherwise wait for the mouse
+    pub fn poll_interval(&self) -> Option<Duration> {
+        self.gilrs.as_ref()?;
+        Some(if self.is_connected() {
+            Duration::from_millis(16)
+        } else {
+            Duration::from_millis(250)
+        })
+    }
+
+    /// Reads everything the gamepads did since the last call. `now` is in
+    /// seconds and only paces the stick's repeated moves.
+    pub fn poll(&mut self, context: &PadContext, now: f64) -> Vec<InputAction> {
+        self.set_context(context);
+
+        let Some(gilrs) = self.gilrs.as_mut() else {
+            return Vec::new();
+        };
+        let mut pressed = Vec::new();
+        while let Some(event) = gilrs.next_event() {
+            if let EventType::ButtonPressed(button, _) = event.event {
+                pressed.push(button);
+            }
+        }
+        let (x, y) = gilrs
+            .gamepads()
+            .find(|(_, pad)| pad.is_connected())
+            .map_or((0.0, 0.0), |(_, pad)| {
+                (pad.value(Axis::LeftStickX), pad.value(Axis::LeftStickY))
+            });
+
+        let mut actions: Vec<InputAction> = pressed
+            .into_iter()
+            .filter_map(|button| self.button_action(button, context))
+            .collect();
+        actions.extend(self.stick.update(x, y, now).map(InputAction::Move));
+        actions
+    }
+
+    /// Keeps the highlight on a choice list, starting over when it changes
+    fn set_context(&mut self, context: &PadContext) {
+        let choices: &[InputAction] = match context {
+            PadContext::Choices(choices) => choices,
+            _ => &[],
+        };
+        if self.choices != choices {
+            self.choices = choices.to_vec();
+            self.selected = 0;
+        }
+    }
+
+    /// The action for one button press, moving the highlight for the
+    /// shoulder buttons
+    fn button_action(&mut self, button: Button, context: &PadContext) -> Option<InputAction> {
+        match button {
+            Button::DPadUp => Some(InputAction::Move(Direction::North)),
+            Button::DPadDown => Some(InputAction::Move(Direction::South)),
+            Button::DPadLeft => Some(InputAction::Move(Direction::West)),
+            Button::DPadRight => Some(InputAction::Move(Direction::East)),
+            Button::South => match context {
+                PadContext::Menu => Some(InputAction::Enter),
+                PadContext::Exploring => Some(InputAction::Character('g')),
+                PadContext::Choices(choices) => choices.get(self.selected).cloned(),
+            },
+            Button::East => Some(InputAction::Exit),
+            Button::West => Some(InputAction::Character('i')),
+            Button::North => Some(InputAction::Character('c')),
+            Button::LeftTrigger | Button::RightTrigger if !self.choices.is_empty() => {
+                let len = self.choices.len();
+                self.selected = if button == Button::LeftTrigger {
+                    (self.selected + len - 1) % len
+                } else {
+                    (self.selected + 1) % len
+                };
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Turns a held stick into one move, then a move every `STICK_REPEAT_DELAY`
+#[derive(Debug, Default)]
+struct StickRepeat {
+    held: Option<Direction>,
+    next_repeat: f64,
+}
+
+impl StickRepeat {
+    fn update(&mut self, x: f32, y: f32, now: f64) -> Option<Direction> {
+        let Some(direction) = stick_direction(x, y) else {
+            self.held = None;
+            return None;
+        };
+        if self.held.as_ref() == Some(&direction) && now < self.next_repeat {
+            return None;
+        }
+        self.held = Some(direction.clone());
+        self.next_repeat = now + STICK_REPEAT_DELAY;
+        Some(direction)
+    }
+}
+
+/// The direction a stick leans in, by its stronger axis; up is positive y
+fn stick_direction(x: f32, y: f32) -> Option<Direction> {
+    if x.abs().max(y.abs()) < STICK_DEADZONE {
+        None
+    } else if x.abs() > y.abs() {
+        Some(if x > 0.0 {
+            Direction::East
+        } else {
+            Direction::West
+        })
+    } else {
+        Some(if y > 0.0 {
+            Direction::North
+        } else {
+            Direction::South
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stick_deadzone_and_direction() {
+        assert_eq!(stick_direction(0.2, -0.3), None);
+        assert_eq!(stick_direction(0.9, 0.5), Some(Direction::East));
+        assert_eq!(stick_direction(-0.6, 0.1), Some(Direction::West));
+        assert_eq!(stick_direction(0.1, 0.7), Some(Direction::North));
+        assert_eq!(stick_direction(0.0, -1.0), Some(Direction::South));
+    }
+
+    #[test]
+    fn test_held_stick_repeats_after_a_delay() {
+        let mut stick = StickRepeat::default();
+        assert_eq!(stick.update(1.0, 0.0, 0.0), Some(Direction::East));
+        assert_eq!(stick.update(1.0, 0.0, 0.1), None);
+        assert_eq!(stick.update(1.0, 0.0, 0.2), Some(Direction::East));
+
+        // Changing direction or letting go and leaning again moves at once
+        assert_eq!(stick.update(0.0, 1.0, 0.25), Some(Direction::North));
+        assert_eq!(stick.update(0.0, 0.0, 0.3), None);
+        assert_eq!(stick.update(0.0, 1.0, 0.31), Some(Direction::North));
+    }
+
+    #[test]
+    fn test_south_button_depends_on_the_screen() {
+        let mut pad = Gamepad::default();
+        assert_eq!(
+            pad.button_action(Button::South, &PadContext::Menu),
+            Some(InputAction::Enter)
+        );
+        assert_eq!(
+            pad.button_action(Button::South, &PadContext::Exploring),
+            Some(InputAction::Character('g'))
+        );
+        assert_eq!(
+            pad.button_action(Button::East, &PadContext::Exploring),
+            Some(InputAction::Exit)
+        );
+        assert_eq!(
+            pad.button_action(Button::DPadLeft, &PadContext::Menu),
+            Some(InputAction::Move(Direction::West))
+        );
+    }
+
+    #[test]
+    fn test_shoulder_buttons_cycle_the_choices() {
+        let mut pad = Gamepad::default();
+        let context = PadContext::Choices(vec![
+            InputAction::MenuOption(1),
+            InputAction::MenuOption(2),
+            InputAction::MenuOption(3),
+        ]);
+        pad.set_context(&context);
+
+        assert_eq!(pad.button_action(Button::LeftTrigger, &context), None);
+        assert_eq!(
+            pad.button_action(Button::South, &context),
+            Some(InputAction::MenuOption(3))
+        );
+        pad.button_action(Button::RightTrigger, &context);
+        pad.button_action(Button::RightTrigger, &context);
+        assert_eq!(
+            pad.button_action(Button::South, &context),
+            Some(InputAction::MenuOption(2))
+        );
+
+        // A different list starts from its first entry
+        let other = PadContext::Choices(vec![InputAction::MenuOption(1)]);
+        pad.set_context(&other);
+        assert_eq!(
+            pad.button_action(Button::South, &other),
+            Some(InputAction::MenuOption(1))
+        );
+    }
+
+    #[test]
+    fn test_missing_gamepad_is_silent() {
+        let mut pad = Gamepad::default();
+        assert!(pad.poll(&PadContext::Exploring, 0.0).is_empty());
+        assert!(!pad.is_connected());
+        assert_eq!(pad.selected(), None);
+        assert_eq!(pad.poll_interval(), None);
+    }
+}
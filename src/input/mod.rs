@@ -3,11 +3,13 @@
 //! This module centralizes all input processing to avoid duplicate key handling
 //! and provides a clean interface for different game states.
 
-#[cfg(all(feature = "gui", target_os = "windows"))]
 use egui::{Event, Key};
 
+use crate::menu::{MenuInput, MenuSelector};
+
+pub mod gamepad;
+
 #[derive(Debug, Clone, PartialEq)]
-#[cfg(all(feature = "gui", target_os = "windows"))]
 pub enum InputAction {
     // Character input for names, etc.
     Character(char),
@@ -18,12 +20,21 @@ pub enum InputAction {
     MenuOption(u8), // 1-9 for menu options
     // Game actions
     Move(Direction),
+    Save,
     Exit,
+    // Display controls: Ctrl+Plus/Minus and F11
+    ZoomIn,
+    ZoomOut,
+    ToggleFullscreen,
+    // Paging through the combat log
+    PageUp,
+    PageDown,
+    // Ctrl+U: pull a stuck player free
+    Unstick,
     Invalid,
 }
 
 #[derive(Debug, Clone, PartialEq)]
-#[cfg(all(feature = "gui", target_os = "windows"))]
 pub enum Direction {
     North,
     South,
@@ -31,7 +42,6 @@ pub enum Direction {
     West,
 }
 
-#[cfg(all(feature = "gui", target_os = "windows"))]
 #[derive(Default)]
 pub struct InputHandler {
     // Event queue for robust input handling
@@ -41,7 +51,6 @@ pub struct InputHandler {
     last_processed_frame: u64,
 }
 
-#[cfg(all(feature = "gui", target_os = "windows"))]
 impl InputHandler {
     pub fn new() -> Self {
         Self::default()
@@ -60,7 +69,10 @@ impl InputHandler {
             // Process key press events (not text events to avoid duplicates)
             for event in &i.events {
                 if let Event::Key {
-                    key, pressed: true, ..
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
                 } = event
                 {
                     let event_id = format!("{key:?}");
@@ -72,7 +84,12 @@ impl InputHandler {
 
                     self.processed_events.insert(event_id);
 
-                    let action = self.key_to_action(key);
+                    let action = if modifiers.command {
+                        self.shortcut_to_action(key)
+                            .unwrap_or_else(|| self.key_to_action(key))
+                    } else {
+                        self.key_to_action(key)
+                    };
                     if action != InputAction::Invalid {
                         self.action_queue.push_back(action);
                     }
@@ -94,6 +111,17 @@ impl InputHandler {
         actions
     }
 
+    /// The action for `key` pressed with Ctrl (Cmd on macOS), if it has one
+    fn shortcut_to_action(&self, key: &Key) -> Option<InputAction> {
+        match key {
+            // Plus shares its key with Equals on most layouts
+            Key::Plus | Key::Equals => Some(InputAction::ZoomIn),
+            Key::Minus => Some(InputAction::ZoomOut),
+            Key::U => Some(InputAction::Unstick),
+            _ => None,
+        }
+    }
+
     /// Convert egui Key to InputAction
     fn key_to_action(&self, key: &Key) -> InputAction {
         match key {
@@ -139,9 +167,20 @@ impl InputHandler {
 
             // Special keys
             Key::Space => InputAction::Character(' '),
+            Key::Questionmark => InputAction::Character('?'),
+            Key::Backtick => InputAction::Character('`'),
+            Key::Tab => InputAction::Character('\t'),
+            // Shift doesn't change the key egui reports, so these stand in
+            // for '>' and '<' whether or not it's held
+            Key::Period => InputAction::Character('>'),
+            Key::Comma => InputAction::Character('<'),
             Key::Enter => InputAction::Enter,
             Key::Backspace => InputAction::Backspace,
             Key::Escape => InputAction::Exit,
+            Key::PageUp => InputAction::PageUp,
+            Key::PageDown => InputAction::PageDown,
+            Key::F5 => InputAction::Save,
+            Key::F11 => InputAction::ToggleFullscreen,
 
             // Movement keys (WASD and arrow keys)
             Key::ArrowUp => InputAction::Move(Direction::North),
@@ -160,7 +199,6 @@ impl InputHandler {
 }
 
 /// Helper functions for common input patterns
-#[cfg(all(feature = "gui", target_os = "windows"))]
 impl InputHandler {
     /// Extract character from action if it's a character action
     pub fn get_character(action: &InputAction) -> Option<char> {
@@ -169,6 +207,39 @@ impl InputHandler {
             _ => None,
         }
     }
+
+    /// Whether the action is a character allowed in a player name
+    pub fn is_name_character(action: &InputAction) -> bool {
+        matches!(action, InputAction::Character(c) if crate::character::player::is_name_character(*c))
+    }
+
+    /// Whether the action is a numbered menu option
+    pub fn is_menu_option(action: &InputAction) -> bool {
+        matches!(action, InputAction::MenuOption(_))
+    }
+
+    /// Extract the menu number from action if it's a menu option
+    pub fn get_menu_option(action: &InputAction) -> Option<u8> {
+        match action {
+            InputAction::MenuOption(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// Menu navigation from GUI actions: the up and down arrows move the
+/// highlight, Enter picks it and the number keys pick an entry directly
+impl MenuSelector {
+    pub fn handle_action(&mut self, action: &InputAction) -> MenuInput {
+        match action {
+            InputAction::Move(Direction::North) => self.up(),
+            InputAction::Move(Direction::South) => self.down(),
+            InputAction::Enter => self.confirm(),
+            InputAction::Exit => MenuInput::Cancelled,
+            InputAction::MenuOption(n) => self.choose_number(*n as usize),
+            _ => MenuInput::Ignored,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -189,6 +260,23 @@ mod tests {
             InputAction::Character(' ')
         );
         assert_eq!(handler.key_to_action(&Key::Enter), InputAction::Enter);
+        assert_eq!(
+            handler.key_to_action(&Key::F11),
+            InputAction::ToggleFullscreen
+        );
+        assert_eq!(
+            handler.shortcut_to_action(&Key::Equals),
+            Some(InputAction::ZoomIn)
+        );
+        assert_eq!(
+            handler.shortcut_to_action(&Key::Minus),
+            Some(InputAction::ZoomOut)
+        );
+        assert_eq!(
+            handler.shortcut_to_action(&Key::U),
+            Some(InputAction::Unstick)
+        );
+        assert_eq!(handler.shortcut_to_action(&Key::S), None);
         assert_eq!(
             handler.key_to_action(&Key::Backspace),
             InputAction::Backspace
@@ -248,4 +336,28 @@ mod tests {
             InputAction::Character('0')
         );
     }
+
+    #[test]
+    fn test_arrow_keys_navigate_menus() {
+        let handler = InputHandler::new();
+        let mut menu = MenuSelector::new(4);
+
+        let down = handler.key_to_action(&Key::ArrowDown);
+        assert_eq!(menu.handle_action(&down), MenuInput::Moved);
+        assert_eq!(menu.handle_action(&down), MenuInput::Moved);
+        let up = handler.key_to_action(&Key::ArrowUp);
+        assert_eq!(menu.handle_action(&up), MenuInput::Moved);
+        assert_eq!(
+            menu.handle_action(&handler.key_to_action(&Key::Enter)),
+            MenuInput::Chosen(1)
+        );
+        assert_eq!(
+            menu.handle_action(&InputAction::MenuOption(4)),
+            MenuInput::Chosen(3)
+        );
+        assert_eq!(
+            menu.handle_action(&InputAction::Character('w')),
+            MenuInput::Ignored
+        );
+    }
 }
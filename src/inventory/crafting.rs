@@ -0,0 +1,82 @@
+//! Recipes for making consumables out of materials
+//!
+//! Herbs, iron shards and crystal dust turn up on the ground and on slain
+//! enemies. From the inventory the player can combine them into potions and
+//! the like; `InventoryManager::craft` does the combining. Items don't
+//! stack, so a recipe needing two Herbs uses up two separate items.
+
+use crate::item::consumable::{ConsumableType, Material};
+use crate::item::{Consumable, Item};
+
+/// One thing the player can make, and what it takes
+#[derive(Debug, Clone, Copy)]
+pub struct Recipe {
+    /// What comes out, as the recipe list names it
+    pub name: &'static str,
+    /// Each material used and how many of it
+    pub ingredients: &'static [(Material, u32)],
+    make: fn() -> Consumable,
+}
+
+/// Everything that can be crafted, in the order the crafting screen lists
+/// it
+pub const RECIPES: [Recipe; 5] = [
+    Recipe {
+        name: "Minor Health Potion",
+        ingredients: &[(Material::Herbs, 2)],
+        make: || Consumable::health_potion(40),
+    },
+    Recipe {
+        name: "Minor Mana Potion",
+        ingredients: &[(Material::Herbs, 1), (Material::CrystalDust, 1)],
+        make: || Consumable::mana_potion(40),
+    },
+    Recipe {
+        name: "Antidote",
+        ingredients: &[(Material::Herbs, 3)],
+        make: || Consumable {
+            name: "Antidote".to_string(),
+            description: "Cures poison status".to_string(),
+            consumable_type: ConsumableType::Antidote,
+            potency: 1,
+            value: 30,
+        },
+    },
+    Recipe {
+        name: "Whetstone",
+        ingredients: &[(Material::IronShard, 3)],
+        make: Consumable::whetstone,
+    },
+    Recipe {
+        name: "Flash Powder",
+        ingredients: &[(Material::CrystalDust, 2), (Material::IronShard, 1)],
+        make: || Consumable {
+            name: "Flash Powder".to_string(),
+            description: "Throw at an enemy to stun it for 2 turns".to_string(),
+            consumable_type: ConsumableType::FlashPowder,
+            potency: 2,
+            value: 40,
+        },
+    },
+];
+
+impl Recipe {
+    /// A freshly made one of what the recipe makes
+    pub fn output(&self) -> Item {
+        Item::Consumable((self.make)())
+    }
+
+    /// "2 Herbs, 1 Crystal Dust"
+    pub fn ingredients_text(&self) -> String {
+        self.ingredients
+            .iter()
+            .map(|(material, count)| format!("{count} {}", material.name()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// "Whetstone: 3 Iron Shard"
+    pub fn label(&self) -> String {
+        format!("{}: {}", self.name, self.ingredients_text())
+    }
+}
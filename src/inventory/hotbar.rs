@@ -0,0 +1,127 @@
+//! Quick-use hotbar: consumables bound to the number keys
+//!
+//! Items don't stack, so a slot remembers the name of what was put on it
+//! and uses whichever one of that name the player carries first. The slots
+//! are kept with the inventory, and so with the save.
+
+use serde::{Deserialize, Serialize};
+
+/// Number of hotbar slots, bound to the keys 1 to 5
+pub const HOTBAR_SLOTS: usize = 5;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hotbar {
+    /// Name of the consumable on each slot
+    slots: [Option<String>; HOTBAR_SLOTS],
+}
+
+impl Hotbar {
+    /// Name of the consumable on 0-based `slot`, if any
+    pub fn get(&self, slot: usize) -> Option<&str> {
+        self.slots.get(slot)?.as_deref()
+    }
+
+    /// Puts `name` on `slot`, taking it off any other slot it was on
+    pub fn assign(&mut self, slot: usize, name: &str) {
+        if slot >= HOTBAR_SLOTS {
+            return;
+        }
+        for other in self.slots.iter_mut() {
+            if other.as_deref() == Some(name) {
+                *other = None;
+            }
+        }
+        self.slots[slot] = Some(name.to_string());
+    }
+
+    pub fn clear(&mut self, slot: usize) {
+        if let Some(entry) = self.slots.get_mut(slot) {
+            *entry = None;
+        }
+    }
+}
+
+/// One hotbar slot as the front-ends show it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HotbarSlot {
+    /// The number key that uses it
+    pub key: usize,
+    pub name: Option<String>,
+    /// How many of the consumable the player carries
+    pub count: usize,
+}
+
+impl HotbarSlot {
+    /// Whether there's nothing the key would use; such slots are dimmed
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// "1: Minor Health Potion x3", or "2: -" for an empty slot
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{}: {name} x{}", self.key, self.count),
+            None => format!("{}: -", self.key),
+        }
+    }
+
+    /// The label cut down to `width` characters, shortening the name
+    /// rather than losing the key or the count
+    pub fn label_within(&self, width: usize) -> String {
+        let label = self.label();
+        let Some(name) = &self.name else {
+            return label;
+        };
+        if label.chars().count() <= width {
+            return label;
+        }
+        let room = width.saturating_sub(label.chars().count() - name.chars().count() + 1);
+        let short: String = name.chars().take(room).collect();
+        format!("{}: {short}… x{}", self.key, self.count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_a_consumable_sits_on_one_slot_at_a_time() {
+        let mut hotbar = Hotbar::default();
+        hotbar.assign(0, "Torch");
+        hotbar.assign(3, "Torch");
+        assert_eq!(hotbar.get(0), None);
+        assert_eq!(hotbar.get(3), Some("Torch"));
+
+        hotbar.assign(HOTBAR_SLOTS, "Antidote");
+        assert_eq!(hotbar, {
+            let mut expected = Hotbar::default();
+            expected.assign(3, "Torch");
+            expected
+        });
+
+        hotbar.clear(3);
+        assert_eq!(hotbar, Hotbar::default());
+    }
+
+    #[test]
+    fn test_slot_labels() {
+        let slot = HotbarSlot {
+            key: 1,
+            name: Some("Minor Health Potion".to_string()),
+            count: 3,
+        };
+        assert_eq!(slot.label(), "1: Minor Health Potion x3");
+        assert_eq!(slot.label_within(30), "1: Minor Health Potion x3");
+        assert_eq!(slot.label_within(16), "1: Minor Hea… x3");
+        assert!(!slot.is_empty());
+
+        let slot = HotbarSlot {
+            key: 2,
+            name: None,
+            count: 0,
+        };
+        assert_eq!(slot.label(), "2: -");
+        assert!(slot.is_empty());
+    }
+}
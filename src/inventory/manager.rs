@@ -1,21 +1,34 @@
 //! Inventory Manager - Core inventory data structure and operations
 
-use super::{ActionResult, ItemInfo};
-use crate::character::Player;
-use crate::item::{Equipment, EquipmentSlot, Item};
+use super::{ActionResult, Hotbar, HotbarSlot, ItemDetails, ItemInfo, Recipe, HOTBAR_SLOTS};
+use crate::character::{Player, StatType};
+use crate::item::consumable::{ConsumableType, Material};
+use crate::item::equipment::{EquipmentType, Wear};
+use crate::item::{Consumable, Equipment, EquipmentSlot, Item};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Slots whose equipment protects the player
+const ARMOR_SLOTS: [EquipmentSlot; 5] = [
+    EquipmentSlot::Head,
+    EquipmentSlot::Chest,
+    EquipmentSlot::Hands,
+    EquipmentSlot::Feet,
+    EquipmentSlot::Shield,
+];
+
 /// Core inventory data structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Inventory {
     pub items: Vec<Item>,
-    pub max_size: usize,
     pub equipped: HashMap<EquipmentSlot, Option<usize>>, // Stores index to items vec
+    #[serde(default)]
+    pub hotbar: Hotbar,
 }
 
 impl Inventory {
-    pub fn new(max_size: usize) -> Self {
+    pub fn new() -> Self {
         let mut equipped = HashMap::new();
         for slot in EquipmentSlot::iter() {
             equipped.insert(slot, None);
@@ -23,18 +36,19 @@ impl Inventory {
 
         Inventory {
             items: Vec::new(),
-            max_size,
             equipped,
+            hotbar: Hotbar::default(),
         }
     }
 
-    pub fn add_item(&mut self, item: Item) -> Result<(), String> {
-        if self.items.len() >= self.max_size {
-            return Err("Inventory is full".to_string());
-        }
-
+    /// Packs `item` whatever it weighs; `InventoryManager::add_item`
+    /// checks it against the player's carry capacity first
+    pub fn add_item(&mut self, item: Item) {
         self.items.push(item);
-        Ok(())
+    }
+
+    pub fn total_weight(&self) -> u32 {
+        self.items.iter().map(Item::weight).sum()
     }
 
     pub fn equip_item(&mut self, index: usize) -> Result<(), String> {
@@ -44,6 +58,18 @@ impl Inventory {
 
         // Check if item is equipment
         if let Item::Equipment(ref equipment) = self.items[index] {
+            if let Some(worn) = self.cursed_in_slot(equipment.slot) {
+                return Err(format!(
+                    "Your {} is cursed and won't come off",
+                    worn.display_name()
+                ));
+            }
+            if equipment.is_broken() {
+                return Err(format!(
+                    "{} is broken and must be repaired first",
+                    equipment.display_name()
+                ));
+            }
             let slot = equipment.slot;
 
             // Unequip current item in that slot if any
@@ -74,22 +100,134 @@ impl Inventory {
         let mut total = 0;
 
         // Check each armor slot
-        for slot in [
-            EquipmentSlot::Head,
-            EquipmentSlot::Chest,
-            EquipmentSlot::Hands,
-            EquipmentSlot::Feet,
-            EquipmentSlot::Shield,
-        ] {
+        for slot in ARMOR_SLOTS {
             if let Some(Some(index)) = self.equipped.get(&slot) {
                 if let Some(Item::Equipment(equipment)) = self.items.get(*index) {
-                    total += equipment.power;
+                    total += equipment.effective_power();
                 }
             }
         }
 
         total
     }
+
+    /// Rolls wear on the equipped weapon, for each attack the player makes
+    pub fn wear_weapon<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Vec<String> {
+        self.wear_slots(&[EquipmentSlot::Weapon], rng)
+    }
+
+    /// Rolls wear on every equipped piece of armor, for each hit the player
+    /// takes
+    pub fn wear_armor<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Vec<String> {
+        self.wear_slots(&ARMOR_SLOTS, rng)
+    }
+
+    /// Wears down the equipment in `slots`, unequipping anything that
+    /// breaks. Returns the warnings for the player.
+    fn wear_slots<R: Rng + ?Sized>(&mut self, slots: &[EquipmentSlot], rng: &mut R) -> Vec<String> {
+        let mut messages = Vec::new();
+        for slot in slots {
+            let Some(Some(index)) = self.equipped.get(slot).copied() else {
+                continue;
+            };
+            let Some(Item::Equipment(equipment)) = self.items.get_mut(index) else {
+                continue;
+            };
+            match equipment.wear(rng) {
+                Some(Wear::BadlyDamaged) => {
+                    messages.push(format!("Your {} is badly damaged", equipment.short_name()));
+                }
+                Some(Wear::Broken) => {
+                    messages.push(format!("Your {} breaks!", equipment.short_name()));
+                    self.equipped.insert(*slot, None);
+                }
+                Some(Wear::Worn) | None => {}
+            }
+        }
+        messages
+    }
+
+    /// The item worn in `slot`, if there is one
+    pub fn equipped_in(&self, slot: EquipmentSlot) -> Option<&Equipment> {
+        let index = (*self.equipped.get(&slot)?)?;
+        match self.items.get(index) {
+            Some(Item::Equipment(equipment)) => Some(equipment),
+            _ => None,
+        }
+    }
+
+    /// The cursed item worn in `slot`, if there is one
+    pub fn cursed_in_slot(&self, slot: EquipmentSlot) -> Option<&Equipment> {
+        self.equipped_in(slot).filter(|equipment| equipment.cursed)
+    }
+
+    /// Whether the item at `index` is worn and can't come off
+    pub fn is_stuck(&self, index: usize) -> bool {
+        EquipmentSlot::iter().any(|slot| {
+            self.equipped.get(&slot) == Some(&Some(index)) && self.cursed_in_slot(slot).is_some()
+        })
+    }
+
+    /// Index of the item a Scroll of Identify reveals: worn gear first,
+    /// then the first unidentified item in the pack
+    pub fn next_unidentified(&self) -> Option<usize> {
+        let unidentified = |index: &usize| matches!(self.items.get(*index), Some(Item::Equipment(equipment)) if !equipment.is_identified());
+        let mut worn: Vec<usize> = self.equipped.values().flatten().copied().collect();
+        worn.sort_unstable();
+        worn.into_iter()
+            .find(unidentified)
+            .or_else(|| (0..self.items.len()).find(unidentified))
+    }
+
+    /// Lifts every curse on the items carried, along with the stat
+    /// penalties they brought. Returns the names of the freed items.
+    pub fn remove_curses(&mut self) -> Vec<String> {
+        let mut lifted = Vec::new();
+        for item in &mut self.items {
+            if let Item::Equipment(equipment) = item {
+                if equipment.cursed {
+                    equipment.cursed = false;
+                    equipment.stat_bonuses.retain(|_, bonus| *bonus > 0);
+                    lifted.push(equipment.display_name().to_string());
+                }
+            }
+        }
+        lifted
+    }
+
+    /// Counts a turn of wear on every unidentified item the player has on,
+    /// returning the message for each one the player has become attuned to
+    pub fn attune_equipped(&mut self) -> Vec<String> {
+        let mut worn: Vec<usize> = self.equipped.values().flatten().copied().collect();
+        worn.sort_unstable();
+        worn.into_iter()
+            .filter_map(|index| match self.items.get_mut(index) {
+                Some(Item::Equipment(equipment)) => equipment.attune(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Index of the most worn piece of equipment, if anything needs repair
+    pub fn most_worn_equipment(&self) -> Option<usize> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| match item {
+                Item::Equipment(equipment) if equipment.durability.fraction() < 1.0 => {
+                    Some((index, equipment.durability.fraction()))
+                }
+                _ => None,
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    }
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// High-level inventory manager that provides a clean interface
@@ -119,13 +257,30 @@ impl InventoryManager {
                 };
 
                 ItemInfo {
-                    name: item.name().to_string(),
+                    name: item.labelled_name(),
                     is_equipped,
+                    rarity: item.rarity(),
+                    durability: item.durability(),
+                    weight: item.weight(),
                 }
             })
             .collect()
     }
 
+    /// Consumables the player can use in combat, with their inventory indices
+    pub fn get_consumables(player: &Player) -> Vec<(usize, &Consumable)> {
+        player
+            .inventory
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| match item {
+                Item::Consumable(consumable) => Some((index, consumable)),
+                _ => None,
+            })
+            .collect()
+    }
+
     /// Check if inventory is empty
     pub fn is_empty(player: &Player) -> bool {
         player.inventory.items.is_empty()
@@ -154,7 +309,11 @@ impl InventoryManager {
         equipment: crate::item::Equipment,
     ) -> ActionResult {
         match player.inventory.equip_item(index) {
-            Ok(()) => ActionResult::success(format!("Equipped {}", equipment.name)),
+            Ok(()) if equipment.cursed => ActionResult::success(format!(
+                "Equipped {}. A chill runs through you: it's cursed and won't come off!",
+                equipment.display_name()
+            )),
+            Ok(()) => ActionResult::success(format!("Equipped {}", equipment.display_name())),
             Err(err) => ActionResult::failure(err),
         }
     }
@@ -165,15 +324,59 @@ impl InventoryManager {
         index: usize,
         consumable: crate::item::Consumable,
     ) -> ActionResult {
-        // Remove from inventory first
-        player.inventory.items.remove(index);
-
-        // Update equipped indices after removal
-        for idx in player.inventory.equipped.values_mut().flatten() {
-            if *idx > index {
-                *idx -= 1;
-            }
+        // Throwables need an enemy, which only the game can pick
+        if consumable.is_thrown() {
+            return ActionResult::failure(consumable.use_effect(player));
+        }
+        if consumable.consumable_type == ConsumableType::RepairKit
+            && player.inventory.most_worn_equipment().is_none()
+        {
+            return ActionResult::failure("Nothing needs repairing");
+        }
+        // Taming needs a beast, which only the game can find
+        if consumable.consumable_type == ConsumableType::TamingCharm {
+            return ActionResult::failure(consumable.use_effect(player));
+        }
+        // Teleporting moves the player between levels, which only the game
+        // can do
+        if consumable.consumable_type == ConsumableType::ReturnScroll {
+            return ActionResult::failure(consumable.use_effect(player));
+        }
+        // Dust is only offered up at a shrine, when enchanting, and
+        // materials only go into crafting
+        if consumable.consumable_type == ConsumableType::ArcaneDust || consumable.is_material() {
+            return ActionResult::failure(consumable.use_effect(player));
+        }
+        if consumable.consumable_type == ConsumableType::Whetstone
+            && player.inventory.get_equipped_weapon().is_none()
+        {
+            return ActionResult::failure(consumable.use_effect(player));
+        }
+        if consumable.consumable_type == ConsumableType::IdentifyScroll
+            && player.inventory.next_unidentified().is_none()
+        {
+            return ActionResult::failure("You have nothing to identify");
+        }
+        if consumable.consumable_type == ConsumableType::RemoveCurseScroll
+            && !player
+                .inventory
+                .items
+                .iter()
+                .any(|item| matches!(item, Item::Equipment(equipment) if equipment.cursed))
+        {
+            return ActionResult::failure("Nothing you carry is cursed");
         }
+        if consumable.consumable_type == ConsumableType::SmellingSalts
+            && !player
+                .companion
+                .as_ref()
+                .is_some_and(|companion| companion.is_knocked_out())
+        {
+            return ActionResult::failure(consumable.use_effect(player));
+        }
+
+        // Remove from inventory first
+        Self::remove_item(player, index);
 
         // Apply effect and get message
         let result = consumable.use_effect(player);
@@ -181,34 +384,716 @@ impl InventoryManager {
     }
 
     /// Get equipped item in a specific slot
-    #[cfg(all(feature = "gui", target_os = "windows"))]
+    #[cfg(feature = "gui")]
     pub fn get_equipped_item(player: &Player, slot: EquipmentSlot) -> Option<ItemInfo> {
         if let Some(Some(index)) = player.inventory.equipped.get(&slot) {
             if let Some(item) = player.inventory.items.get(*index) {
                 return Some(ItemInfo {
-                    name: item.name().to_string(),
+                    name: item.labelled_name(),
                     is_equipped: true,
+                    rarity: item.rarity(),
+                    durability: item.durability(),
+                    weight: item.weight(),
                 });
             }
         }
         None
     }
 
-    /// Add an item to inventory
+    /// Add an item to inventory, unless it weighs more than the player
+    /// can still carry
     pub fn add_item(player: &mut Player, item: Item) -> ActionResult {
-        match player.inventory.add_item(item) {
-            Ok(()) => ActionResult::success("Item added to inventory"),
-            Err(err) => ActionResult::failure(err),
+        let spare = player
+            .carry_capacity()
+            .saturating_sub(player.carried_weight());
+        if item.weight() > spare {
+            return ActionResult::failure(format!(
+                "The {} is too heavy to carry (weight {}, {} to spare)",
+                item.name(),
+                item.weight(),
+                spare
+            ));
+        }
+        player.inventory.add_item(item);
+        ActionResult::success("Item added to inventory")
+    }
+
+    /// "Weight: 43/65" for the inventory screens, noting when the player
+    /// is encumbered
+    pub fn weight_text(player: &Player) -> String {
+        let text = format!(
+            "Weight: {}/{}",
+            player.carried_weight(),
+            player.carry_capacity()
+        );
+        if player.is_encumbered() {
+            format!("{text} (encumbered)")
+        } else {
+            text
         }
     }
 
+    /// Remove an item by index, keeping equipped slot indices valid
+    pub fn remove_item(player: &mut Player, index: usize) -> Option<Item> {
+        if index >= player.inventory.items.len() {
+            return None;
+        }
+
+        let item = player.inventory.items.remove(index);
+
+        // Unequip the removed item and shift indices after it
+        for slot in player.inventory.equipped.values_mut() {
+            match slot {
+                Some(idx) if *idx == index => *slot = None,
+                Some(idx) if *idx > index => *idx -= 1,
+                _ => {}
+            }
+        }
+
+        Some(item)
+    }
+
     /// Get item by index
     pub fn get_item(player: &Player, index: usize) -> Option<&Item> {
         player.inventory.items.get(index)
     }
 
+    /// Get the full inspection details of the item at `index`
+    pub fn get_item_details(player: &Player, index: usize) -> Option<ItemDetails> {
+        let item = player.inventory.items.get(index)?;
+
+        let details = match item {
+            Item::Equipment(equipment) => {
+                let is_equipped =
+                    player.inventory.equipped.get(&equipment.slot) == Some(&Some(index));
+
+                let mut rows = vec![
+                    (
+                        "Type".to_string(),
+                        format!("{:?}", equipment.equipment_type),
+                    ),
+                    ("Slot".to_string(), equipment.slot.to_string()),
+                    ("Power".to_string(), equipment.power.to_string()),
+                    ("Durability".to_string(), equipment.durability.to_string()),
+                    ("Rarity".to_string(), equipment.rarity.to_string()),
+                    (
+                        "Required level".to_string(),
+                        equipment.level_requirement.to_string(),
+                    ),
+                ];
+                // What an unidentified item does stays hidden
+                let identified = equipment.is_identified();
+                if identified && equipment.equipment_type == EquipmentType::Weapon {
+                    rows.insert(
+                        3,
+                        ("Damage type".to_string(), equipment.damage_type.to_string()),
+                    );
+                }
+                if identified {
+                    for stat in StatType::iter() {
+                        if let Some(bonus) = equipment.stat_bonuses.get(&stat) {
+                            rows.push((stat.to_string(), format!("{bonus:+}")));
+                        }
+                    }
+                    if equipment.cursed {
+                        rows.push(("Cursed".to_string(), "Yes".to_string()));
+                    }
+                    if !equipment.enchant_history.is_empty() {
+                        rows.push((
+                            "Enchantment".to_string(),
+                            format!("+{}", equipment.enchant_level),
+                        ));
+                        for attempt in &equipment.enchant_history {
+                            let (level, outcome) =
+                                attempt.split_once(": ").unwrap_or(("", attempt));
+                            rows.push((format!("  Enchanting {level}"), outcome.to_string()));
+                        }
+                    }
+                } else {
+                    rows.push(("Identified".to_string(), "No".to_string()));
+                }
+                rows.push(("Weight".to_string(), equipment.weight().to_string()));
+                rows.push(("Value".to_string(), format!("{} gold", equipment.value)));
+                rows.push((
+                    "Equipped".to_string(),
+                    if is_equipped { "Yes" } else { "No" }.to_string(),
+                ));
+
+                let description = if identified {
+                    equipment.description.clone()
+                } else {
+                    "You can't tell what it does until it's identified.".to_string()
+                };
+
+                ItemDetails {
+                    name: equipment.labelled_name(),
+                    description,
+                    rarity: Some(equipment.rarity),
+                    rows,
+                }
+            }
+            Item::Consumable(consumable) => ItemDetails {
+                name: consumable.name.clone(),
+                description: consumable.description.clone(),
+                rarity: None,
+                rows: vec![
+                    ("Type".to_string(), "Consumable".to_string()),
+                    ("Effect".to_string(), consumable.effect_text()),
+                    ("Potency".to_string(), consumable.potency.to_string()),
+                    ("Weight".to_string(), consumable.weight().to_string()),
+                    ("Value".to_string(), format!("{} gold", consumable.value)),
+                ],
+            },
+            Item::Quest {
+                name, description, ..
+            } => ItemDetails {
+                name: name.clone(),
+                description: description.clone(),
+                rarity: None,
+                rows: vec![
+                    ("Type".to_string(), "Quest item".to_string()),
+                    ("Weight".to_string(), item.weight().to_string()),
+                ],
+            },
+        };
+
+        Some(details)
+    }
+
     /// Get item count
     pub fn get_item_count(player: &Player) -> usize {
         player.inventory.items.len()
     }
+
+    /// How many of `material` the player carries
+    pub fn material_count(player: &Player, material: Material) -> u32 {
+        player
+            .inventory
+            .items
+            .iter()
+            .filter(|item| {
+                matches!(item, Item::Consumable(consumable)
+                    if consumable.consumable_type == ConsumableType::Material(material))
+            })
+            .count() as u32
+    }
+
+    /// Whether the player carries everything `recipe` takes, and could
+    /// carry what it makes once those are used up
+    pub fn can_craft(player: &Player, recipe: &Recipe) -> bool {
+        Self::missing_ingredients(player, recipe).is_none() && Self::crafted_fits(player, recipe)
+    }
+
+    /// Uses up the materials `recipe` takes and packs what it makes
+    pub fn craft(player: &mut Player, recipe: &Recipe) -> ActionResult {
+        if let Some(missing) = Self::missing_ingredients(player, recipe) {
+            return ActionResult::failure(format!(
+                "{} takes {}; you're short of {missing}",
+                recipe.name,
+                recipe.ingredients_text()
+            ));
+        }
+        if !Self::crafted_fits(player, recipe) {
+            return ActionResult::failure(format!(
+                "You couldn't carry the {}; make room for it first",
+                recipe.name
+            ));
+        }
+
+        for &(material, count) in recipe.ingredients {
+            for _ in 0..count {
+                let index = player.inventory.items.iter().rposition(|item| {
+                    matches!(item, Item::Consumable(consumable)
+                        if consumable.consumable_type == ConsumableType::Material(material))
+                });
+                if let Some(index) = index {
+                    Self::remove_item(player, index);
+                }
+            }
+        }
+        player.inventory.add_item(recipe.output());
+        ActionResult::success(format!(
+            "Crafted {} from {}",
+            recipe.name,
+            recipe.ingredients_text()
+        ))
+    }
+
+    /// The first material the player has too little of for `recipe`
+    fn missing_ingredients(player: &Player, recipe: &Recipe) -> Option<&'static str> {
+        recipe
+            .ingredients
+            .iter()
+            .find(|&&(material, count)| Self::material_count(player, material) < count)
+            .map(|(material, _)| material.name())
+    }
+
+    /// Whether what `recipe` makes fits in the player's pack in place of
+    /// the materials it uses
+    fn crafted_fits(player: &Player, recipe: &Recipe) -> bool {
+        let used: u32 = recipe
+            .ingredients
+            .iter()
+            .map(|&(material, count)| Consumable::material(material).weight() * count)
+            .sum();
+        let carried = player.carried_weight().saturating_sub(used);
+        carried + recipe.output().weight() <= player.carry_capacity()
+    }
+
+    /// Puts the consumable at inventory `index` on 0-based hotbar `slot`
+    pub fn assign_hotbar(player: &mut Player, index: usize, slot: usize) -> ActionResult {
+        if slot >= HOTBAR_SLOTS {
+            return ActionResult::failure("Invalid hotbar slot");
+        }
+        let name = match player.inventory.items.get(index) {
+            Some(Item::Consumable(consumable)) => consumable.name.clone(),
+            Some(_) => return ActionResult::failure("Only consumables go on the hotbar"),
+            None => return ActionResult::failure("Invalid item index"),
+        };
+        player.inventory.hotbar.assign(slot, &name);
+        ActionResult::success(format!("{name} is now on hotbar slot {}", slot + 1))
+    }
+
+    /// Inventory index of the first consumable matching hotbar `slot`
+    pub fn hotbar_item(player: &Player, slot: usize) -> Option<usize> {
+        let name = player.inventory.hotbar.get(slot)?;
+        player.inventory.items.iter().position(
+            |item| matches!(item, Item::Consumable(consumable) if consumable.name == name),
+        )
+    }
+
+    /// Every hotbar slot with how many of its consumable the player carries
+    pub fn hotbar_slots(player: &Player) -> Vec<HotbarSlot> {
+        (0..HOTBAR_SLOTS)
+            .map(|slot| {
+                let name = player.inventory.hotbar.get(slot);
+                let count = player
+                    .inventory
+                    .items
+                    .iter()
+                    .filter(|item| {
+                        matches!(item, Item::Consumable(consumable) if Some(consumable.name.as_str()) == name)
+                    })
+                    .count();
+                HotbarSlot {
+                    key: slot + 1,
+                    name: name.map(str::to_string),
+                    count,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::ClassType;
+    use crate::combat::{Combatant, DamageType};
+    use crate::inventory::RECIPES;
+    use crate::item::consumable::{Consumable, ConsumableType};
+    use crate::item::equipment::{EquipmentType, Rarity};
+    use crate::item::Durability;
+    use rand::rngs::mock::StepRng;
+
+    fn player_with(items: Vec<Item>) -> Player {
+        let mut player = Player::new("Tester".to_string(), ClassType::Warrior);
+        player.inventory.items.clear();
+        for slot in EquipmentSlot::iter() {
+            player.inventory.equipped.insert(slot, None);
+        }
+        for item in items {
+            player.inventory.add_item(item);
+        }
+        player
+    }
+
+    fn row<'a>(details: &'a ItemDetails, label: &str) -> Option<&'a str> {
+        details
+            .rows
+            .iter()
+            .find(|(l, _)| l == label)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn test_equipment_details_list_every_stat() {
+        let mut stat_bonuses = HashMap::new();
+        stat_bonuses.insert(StatType::Strength, 2);
+        stat_bonuses.insert(StatType::Wisdom, 1);
+        let sword = Equipment {
+            name: "Gleaming Sword".to_string(),
+            description: "A well-balanced blade".to_string(),
+            equipment_type: EquipmentType::Weapon,
+            slot: EquipmentSlot::Weapon,
+            power: 9,
+            value: 120,
+            stat_bonuses,
+            level_requirement: 3,
+            rarity: Rarity::Rare,
+            damage_type: DamageType::Fire,
+            durability: Durability {
+                current: 9,
+                max: 40,
+            },
+            weight: Some(7),
+            disguise: None,
+            turns_worn: 0,
+            cursed: false,
+            enchant_level: 0,
+            enchant_history: Vec::new(),
+        };
+        let mut player = player_with(vec![Item::Equipment(sword)]);
+        player.inventory.equip_item(0).unwrap();
+
+        let details = InventoryManager::get_item_details(&player, 0).unwrap();
+        assert_eq!(details.name, "Gleaming Sword");
+        assert_eq!(details.rarity, Some(Rarity::Rare));
+        assert_eq!(row(&details, "Type"), Some("Weapon"));
+        assert_eq!(row(&details, "Slot"), Some("Weapon"));
+        assert_eq!(row(&details, "Power"), Some("9"));
+        assert_eq!(row(&details, "Durability"), Some("9/40"));
+        assert_eq!(row(&details, "Rarity"), Some("Rare"));
+        assert_eq!(row(&details, "Damage type"), Some("Fire"));
+        assert_eq!(row(&details, "Strength"), Some("+2"));
+        assert_eq!(row(&details, "Wisdom"), Some("+1"));
+        assert_eq!(row(&details, "Dexterity"), None);
+        assert_eq!(row(&details, "Weight"), Some("7"));
+        assert_eq!(row(&details, "Value"), Some("120 gold"));
+        assert_eq!(row(&details, "Equipped"), Some("Yes"));
+    }
+
+    #[test]
+    fn test_consumable_details_show_effect_and_potency() {
+        let potion = Consumable {
+            name: "Regular Health Potion".to_string(),
+            description: "Restores 60 health points when consumed".to_string(),
+            consumable_type: ConsumableType::HealthPotion,
+            potency: 60,
+            value: 30,
+        };
+        let player = player_with(vec![Item::Consumable(potion)]);
+
+        let details = InventoryManager::get_item_details(&player, 0).unwrap();
+        assert_eq!(details.rarity, None);
+        assert_eq!(row(&details, "Effect"), Some("Restores 60 health"));
+        assert_eq!(row(&details, "Potency"), Some("60"));
+        assert!(details.lines().contains(&"Value: 30 gold".to_string()));
+    }
+
+    #[test]
+    fn test_consumables_keep_their_inventory_indices() {
+        let player = player_with(vec![
+            Item::Equipment(Equipment::weapon("Iron Sword", 4)),
+            Item::Consumable(Consumable::health_potion(30)),
+            Item::Equipment(Equipment::armor("Leather Boots", EquipmentSlot::Feet, 1)),
+            Item::Consumable(Consumable::mana_potion(60)),
+        ]);
+
+        let consumables = InventoryManager::get_consumables(&player);
+        let listed: Vec<(usize, &str)> = consumables
+            .iter()
+            .map(|(index, consumable)| (*index, consumable.name.as_str()))
+            .collect();
+        assert_eq!(
+            listed,
+            [(1, "Minor Health Potion"), (3, "Regular Mana Potion")]
+        );
+    }
+
+    #[test]
+    fn test_quest_item_details_and_invalid_index() {
+        let relic = Item::Quest {
+            id: "relic".to_string(),
+            name: "Ancient Relic".to_string(),
+            description: "Wanted by the sage".to_string(),
+        };
+        let player = player_with(vec![relic]);
+
+        let details = InventoryManager::get_item_details(&player, 0).unwrap();
+        assert_eq!(details.description, "Wanted by the sage");
+        assert_eq!(details.lines(), ["Type: Quest item", "Weight: 1"]);
+
+        assert!(InventoryManager::get_item_details(&player, 5).is_none());
+    }
+
+    /// Equipment one use away from breaking
+    fn fragile(mut equipment: Equipment) -> Item {
+        equipment.durability.current = 1;
+        Item::Equipment(equipment)
+    }
+
+    fn repair_kit(potency: i32) -> Item {
+        Item::Consumable(Consumable {
+            name: "Repair Kit".to_string(),
+            description: String::new(),
+            consumable_type: ConsumableType::RepairKit,
+            potency,
+            value: 40,
+        })
+    }
+
+    #[test]
+    fn test_broken_equipment_is_unequipped_and_loses_its_bonus() {
+        // Every wear roll succeeds
+        let mut rng = StepRng::new(0, 0);
+        let mut player = player_with(vec![
+            fragile(Equipment::weapon("Iron Sword", 9)),
+            fragile(Equipment::armor("Iron Helm", EquipmentSlot::Head, 4)),
+        ]);
+        player.inventory.equip_item(0).unwrap();
+        player.inventory.equip_item(1).unwrap();
+        let (attack, defense) = (player.attack_damage(), player.defense());
+        let sword = player
+            .inventory
+            .get_equipped_weapon()
+            .unwrap()
+            .effective_power();
+        let helm = player.inventory.get_total_armor_defense();
+
+        assert_eq!(
+            player.inventory.wear_weapon(&mut rng),
+            ["Your sword breaks!"]
+        );
+        assert!(player.inventory.get_equipped_weapon().is_none());
+        // Back to fighting bare-handed, for 1 damage
+        assert_eq!(player.attack_damage(), attack - sword + 1);
+
+        assert_eq!(player.inventory.wear_armor(&mut rng), ["Your helm breaks!"]);
+        assert_eq!(player.defense(), defense - helm);
+
+        // Broken gear stays in the pack but can't be put back on
+        assert_eq!(InventoryManager::get_item_count(&player), 2);
+        assert!(!InventoryManager::use_item(&mut player, 0).success);
+        assert_eq!(
+            InventoryManager::get_items(&player)[0].durability_tag(),
+            " [Broken]"
+        );
+    }
+
+    #[test]
+    fn test_repair_kit_fixes_the_most_worn_item() {
+        let mut worn = Equipment::armor("Leather Boots", EquipmentSlot::Feet, 1);
+        worn.durability.current = 30;
+        let mut player = player_with(vec![
+            Item::Equipment(worn),
+            fragile(Equipment::weapon("Iron Sword", 9)),
+            repair_kit(15),
+        ]);
+        player.inventory.items[1] = match player.inventory.items[1].clone() {
+            Item::Equipment(mut sword) => {
+                sword.durability.current = 0;
+                Item::Equipment(sword)
+            }
+            item => item,
+        };
+
+        let result = InventoryManager::use_item(&mut player, 2);
+        assert!(result.success);
+        assert_eq!(result.message, "You repair your Iron Sword (15/40)");
+        assert_eq!(InventoryManager::get_item_count(&player), 2);
+        assert!(InventoryManager::use_item(&mut player, 1).success);
+
+        // With everything in full repair the kit is kept
+        let mut player = player_with(vec![
+            Item::Equipment(Equipment::weapon("Iron Sword", 9)),
+            repair_kit(15),
+        ]);
+        assert!(!InventoryManager::use_item(&mut player, 1).success);
+        assert_eq!(InventoryManager::get_item_count(&player), 2);
+    }
+
+    fn scroll(consumable_type: ConsumableType) -> Item {
+        Item::Consumable(Consumable {
+            name: format!("{consumable_type:?}"),
+            description: String::new(),
+            consumable_type,
+            potency: 1,
+            value: 60,
+        })
+    }
+
+    #[test]
+    fn test_cursed_gear_stays_on_until_the_curse_is_lifted() {
+        let mut cursed = Equipment::weapon("Gleaming Sword of Woe", 9);
+        cursed.disguise = Some("Humming Sword".to_string());
+        cursed.cursed = true;
+        cursed.stat_bonuses.insert(StatType::Dexterity, -2);
+        let mut player = player_with(vec![
+            Item::Equipment(cursed),
+            Item::Equipment(Equipment::weapon("Iron Sword", 4)),
+            scroll(ConsumableType::IdentifyScroll),
+            scroll(ConsumableType::RemoveCurseScroll),
+        ]);
+        assert_eq!(
+            InventoryManager::get_items(&player)[0].name,
+            "Humming Sword (unidentified)"
+        );
+        let details = InventoryManager::get_item_details(&player, 0).unwrap();
+        assert_eq!(row(&details, "Identified"), Some("No"));
+        assert_eq!(row(&details, "Dexterity"), None);
+
+        let equipped = InventoryManager::use_item(&mut player, 0);
+        assert!(equipped.message.contains("it's cursed and won't come off"));
+        assert!(player.inventory.is_stuck(0));
+        let swap = InventoryManager::use_item(&mut player, 1);
+        assert!(!swap.success);
+        assert_eq!(
+            swap.message,
+            "Your Humming Sword is cursed and won't come off"
+        );
+
+        let identified = InventoryManager::use_item(&mut player, 2);
+        assert_eq!(
+            identified.message,
+            "The scroll reveals the Humming Sword: it is a Gleaming Sword of Woe \
+             (+9 ATK, -2 DEX)! It's cursed!"
+        );
+        let details = InventoryManager::get_item_details(&player, 0).unwrap();
+        assert_eq!(row(&details, "Cursed"), Some("Yes"));
+        assert!(!InventoryManager::use_item(&mut player, 1).success);
+
+        let lifted = InventoryManager::use_item(&mut player, 2);
+        assert!(lifted.success);
+        assert_eq!(
+            lifted.message,
+            "The curse on your Gleaming Sword of Woe lifts"
+        );
+        assert!(!player.inventory.is_stuck(0));
+        assert!(InventoryManager::use_item(&mut player, 1).success);
+        assert_eq!(
+            player.inventory.get_equipped_weapon().unwrap().name,
+            "Iron Sword"
+        );
+
+        // With nothing to identify the scroll is kept
+        let mut player = player_with(vec![scroll(ConsumableType::IdentifyScroll)]);
+        assert!(!InventoryManager::use_item(&mut player, 0).success);
+        assert_eq!(InventoryManager::get_item_count(&player), 1);
+    }
+
+    #[test]
+    fn test_hotbar_counts_what_matches_each_slot() {
+        let mut player = player_with(vec![
+            Item::Consumable(Consumable::health_potion(30)),
+            Item::Consumable(Consumable::mana_potion(60)),
+            Item::Consumable(Consumable::health_potion(30)),
+            Item::Consumable(Consumable::health_potion(120)),
+        ]);
+        assert!(InventoryManager::assign_hotbar(&mut player, 2, 0).success);
+        let mana = InventoryManager::assign_hotbar(&mut player, 1, 4);
+        assert_eq!(mana.message, "Regular Mana Potion is now on hotbar slot 5");
+
+        let slots = InventoryManager::hotbar_slots(&player);
+        assert_eq!(slots[0].label(), "1: Minor Health Potion x2");
+        assert!(slots[1].is_empty());
+        assert_eq!(slots[4].label(), "5: Regular Mana Potion x1");
+        assert_eq!(InventoryManager::hotbar_item(&player, 0), Some(0));
+        assert_eq!(InventoryManager::hotbar_item(&player, 1), None);
+
+        // Only consumables go on the hotbar
+        let mut player = player_with(vec![Item::Quest {
+            id: "relic".to_string(),
+            name: "Relic".to_string(),
+            description: String::new(),
+        }]);
+        assert!(!InventoryManager::assign_hotbar(&mut player, 0, 0).success);
+        assert!(!InventoryManager::assign_hotbar(&mut player, 5, 0).success);
+    }
+
+    fn material(material: Material) -> Item {
+        Item::Consumable(Consumable::material(material))
+    }
+
+    #[test]
+    fn test_crafting_uses_up_exactly_the_ingredients() {
+        let recipe = RECIPES
+            .iter()
+            .find(|recipe| recipe.name == "Minor Mana Potion")
+            .unwrap();
+        let mut player = player_with(vec![
+            material(Material::Herbs),
+            Item::Equipment(Equipment::weapon("Iron Sword", 4)),
+            material(Material::Herbs),
+            material(Material::IronShard),
+        ]);
+        let _ = player.inventory.equip_item(1);
+        assert!(!InventoryManager::can_craft(&player, recipe));
+        let result = InventoryManager::craft(&mut player, recipe);
+        assert!(!result.success);
+        assert_eq!(
+            result.message,
+            "Minor Mana Potion takes 1 Herbs, 1 Crystal Dust; you're short of Crystal Dust"
+        );
+        assert_eq!(player.inventory.items.len(), 4);
+
+        player.inventory.add_item(material(Material::CrystalDust));
+        assert!(InventoryManager::can_craft(&player, recipe));
+        assert!(InventoryManager::craft(&mut player, recipe).success);
+        assert_eq!(
+            InventoryManager::material_count(&player, Material::Herbs),
+            1
+        );
+        assert_eq!(
+            InventoryManager::material_count(&player, Material::CrystalDust),
+            0
+        );
+        assert_eq!(
+            InventoryManager::material_count(&player, Material::IronShard),
+            1
+        );
+        assert_eq!(
+            player.inventory.items.last().map(Item::name),
+            Some("Minor Mana Potion")
+        );
+        // Taking items out from in front of the sword kept it worn
+        assert_eq!(
+            player
+                .inventory
+                .get_equipped_weapon()
+                .map(|w| w.name.as_str()),
+            Some("Iron Sword")
+        );
+
+        // Materials can't be used on their own
+        let herbs = player
+            .inventory
+            .items
+            .iter()
+            .position(|item| item.name() == "Herbs")
+            .unwrap();
+        assert!(!InventoryManager::use_item(&mut player, herbs).success);
+    }
+
+    #[test]
+    fn test_crafting_fails_without_room_for_what_it_makes() {
+        let whetstone = RECIPES
+            .iter()
+            .find(|recipe| recipe.name == "Whetstone")
+            .unwrap();
+        let mut player = player_with(vec![
+            material(Material::IronShard),
+            material(Material::IronShard),
+            material(Material::IronShard),
+        ]);
+        assert!(InventoryManager::can_craft(&player, whetstone));
+
+        // Loaded past what the player can carry, even the shards' weight
+        // coming off isn't enough
+        while player.carried_weight() <= player.carry_capacity() + 3 {
+            player
+                .inventory
+                .add_item(Item::Equipment(Equipment::weapon("Anvil", 1)));
+        }
+        assert!(!InventoryManager::can_craft(&player, whetstone));
+        let count = player.inventory.items.len();
+        let result = InventoryManager::craft(&mut player, whetstone);
+        assert!(!result.success);
+        assert!(result.message.contains("make room"));
+        assert_eq!(player.inventory.items.len(), count);
+        assert_eq!(
+            InventoryManager::material_count(&player, Material::IronShard),
+            3
+        );
+    }
 }
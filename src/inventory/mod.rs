@@ -51,16 +51,63 @@
 //! }
 //! ```
 
+pub mod crafting;
+pub mod hotbar;
 pub mod manager;
 
+pub use crafting::{Recipe, RECIPES};
+pub use hotbar::{Hotbar, HotbarSlot, HOTBAR_SLOTS};
 pub use manager::InventoryManager;
 
+use crate::item::{Durability, Rarity};
+
 /// Information about an inventory item for display purposes
 #[derive(Debug, Clone)]
 pub struct ItemInfo {
     pub name: String,
 
     pub is_equipped: bool,
+    /// Rarity tier, for equipment only
+    pub rarity: Option<Rarity>,
+    /// Wear, for equipment only
+    pub durability: Option<Durability>,
+    pub weight: u32,
+}
+
+impl ItemInfo {
+    /// " (6 wt)" style weight suffix for inventory lists
+    pub fn weight_tag(&self) -> String {
+        format!(" ({} wt)", self.weight)
+    }
+
+    /// " [12/40]" style durability suffix for inventory lists, empty for
+    /// items that don't wear
+    pub fn durability_tag(&self) -> String {
+        self.durability
+            .map(|durability| format!(" [{durability}]"))
+            .unwrap_or_default()
+    }
+}
+
+/// Full description of a single item for the inspection views
+#[derive(Debug, Clone)]
+pub struct ItemDetails {
+    pub name: String,
+    pub description: String,
+    /// Rarity tier, for equipment only
+    pub rarity: Option<Rarity>,
+    /// Label/value rows such as ("Power", "7")
+    pub rows: Vec<(String, String)>,
+}
+
+impl ItemDetails {
+    /// Rows formatted as "Label: value" lines
+    pub fn lines(&self) -> Vec<String> {
+        self.rows
+            .iter()
+            .map(|(label, value)| format!("{label}: {value}"))
+            .collect()
+    }
 }
 
 /// Action result from inventory operations
@@ -68,6 +115,8 @@ pub struct ItemInfo {
 pub struct ActionResult {
     pub success: bool,
     pub message: String,
+    /// Whether a consumable was used up
+    pub consumed: bool,
 }
 
 impl ActionResult {
@@ -75,6 +124,7 @@ impl ActionResult {
         Self {
             success: true,
             message: message.into(),
+            consumed: false,
         }
     }
 
@@ -82,6 +132,7 @@ impl ActionResult {
         Self {
             success: true,
             message: message.into(),
+            consumed: true,
         }
     }
 
@@ -89,6 +140,7 @@ impl ActionResult {
         Self {
             success: false,
             message: message.into(),
+            consumed: false,
         }
     }
 }
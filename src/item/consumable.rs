@@ -1,8 +1,43 @@
 use crate::character::Player;
+use crate::combat::{Combatant, DamageType};
+use crate::item::Item;
+use crate::world::Enemy;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+/// Turns a torch burns for
+pub const TORCH_TURNS: u32 = 150;
+
+/// Turns a Haste Potion or the Haste ability lasts
+pub const HASTE_TURNS: u32 = 20;
+
+/// Turns a whetstone's edge lasts on the player's weapon
+pub const WHETSTONE_TURNS: u32 = 100;
+
+/// Attack a whetstone's edge adds to the player's weapon
+pub const WHETSTONE_BONUS: i32 = 2;
+
+/// Something gathered to craft with rather than to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Material {
+    Herbs,
+    IronShard,
+    CrystalDust,
+}
+
+impl Material {
+    pub const ALL: [Material; 3] = [Material::Herbs, Material::IronShard, Material::CrystalDust];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Material::Herbs => "Herbs",
+            Material::IronShard => "Iron Shard",
+            Material::CrystalDust => "Crystal Dust",
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ConsumableType {
     HealthPotion,
@@ -13,6 +48,47 @@ pub enum ConsumableType {
     DexterityElixir,
     ConstitutionElixir,
     WisdomElixir,
+    /// Thrown: bursts into flames on the enemy
+    FireBomb,
+    /// Thrown: a plain blade
+    ThrowingKnife,
+    /// Thrown: blinds the enemy, costing it turns
+    FlashPowder,
+    /// Restores durability to the most worn piece of equipment
+    RepairKit,
+    /// Lights dark levels for `potency` turns
+    Torch,
+    /// Makes a badly hurt beast next to the player their companion
+    TamingCharm,
+    /// Wakes a knocked out companion
+    SmellingSalts,
+    /// Reveals an unidentified piece of equipment, worn gear first
+    IdentifyScroll,
+    /// Lifts the curse from everything the player carries
+    RemoveCurseScroll,
+    /// Hastes the player for `potency` turns
+    HastePotion,
+    /// Thrown: slows the enemy for `potency` turns
+    SlowingDust,
+    /// Teleports the player to the dungeon's entrance, or back to where the
+    /// last one was read
+    ReturnScroll,
+    /// Offered with gold at a shrine to enchant a piece of equipment
+    ArcaneDust,
+    /// Combined with other materials into something useful
+    Material(Material),
+    /// Hones the equipped weapon for `potency` turns
+    Whetstone,
+}
+
+impl ConsumableType {
+    /// Whether using one counts as drinking a potion
+    pub fn is_potion(&self) -> bool {
+        matches!(
+            self,
+            ConsumableType::HealthPotion | ConsumableType::ManaPotion | ConsumableType::HastePotion
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,10 +101,284 @@ pub struct Consumable {
 }
 
 impl Consumable {
+    pub fn health_potion(potency: i32) -> Self {
+        Consumable {
+            name: format!("{} Health Potion", potion_quality(potency)),
+            description: format!("Restores {potency} health points when consumed"),
+            consumable_type: ConsumableType::HealthPotion,
+            potency,
+            value: potency as u32 / 2,
+        }
+    }
+
+    pub fn mana_potion(potency: i32) -> Self {
+        Consumable {
+            name: format!("{} Mana Potion", potion_quality(potency)),
+            description: format!("Restores {potency} mana points when consumed"),
+            consumable_type: ConsumableType::ManaPotion,
+            potency,
+            value: potency as u32 / 2,
+        }
+    }
+
+    /// A scroll that heals like a health potion when read
+    pub fn healing_scroll(potency: i32) -> Self {
+        Consumable {
+            name: "Scroll of Healing".to_string(),
+            description: format!("Restores {potency} health points when read"),
+            consumable_type: ConsumableType::HealthPotion,
+            potency,
+            value: potency as u32,
+        }
+    }
+
+    /// A torch that burns for `TORCH_TURNS` turns on dark levels
+    pub fn torch() -> Self {
+        Consumable {
+            name: "Torch".to_string(),
+            description: format!("Lights up dark places for {TORCH_TURNS} turns"),
+            consumable_type: ConsumableType::Torch,
+            potency: TORCH_TURNS as i32,
+            value: 15,
+        }
+    }
+
+    /// A charm that tames a weakened beast
+    pub fn taming_charm() -> Self {
+        Consumable {
+            name: "Taming Charm".to_string(),
+            description: "Use next to a badly hurt beast to make it your companion".to_string(),
+            consumable_type: ConsumableType::TamingCharm,
+            potency: 1,
+            value: 150,
+        }
+    }
+
+    /// A scroll that carries the player to the dungeon's entrance and back
+    pub fn return_scroll() -> Self {
+        Consumable {
+            name: "Scroll of Return".to_string(),
+            description: "Read to return to the dungeon's entrance; read another to come back"
+                .to_string(),
+            consumable_type: ConsumableType::ReturnScroll,
+            potency: 1,
+            value: 120,
+        }
+    }
+
+    /// A pinch of the dust magical enemies leave, used for enchanting
+    pub fn arcane_dust() -> Self {
+        Consumable {
+            name: "Arcane Dust".to_string(),
+            description: "Offer it with gold at a shrine to enchant your gear".to_string(),
+            consumable_type: ConsumableType::ArcaneDust,
+            potency: 1,
+            value: 25,
+        }
+    }
+
+    /// A bundle of `material`, found on the ground or left by enemies
+    pub fn material(material: Material) -> Self {
+        let description = match material {
+            Material::Herbs => "Bitter leaves that grow wherever there's light and damp",
+            Material::IronShard => "A jagged scrap of old iron",
+            Material::CrystalDust => "Glittering dust scraped from a crystal",
+        };
+        Consumable {
+            name: material.name().to_string(),
+            description: format!("{description}; a crafting material"),
+            consumable_type: ConsumableType::Material(material),
+            potency: 1,
+            value: 10,
+        }
+    }
+
+    /// A stone that sharpens the player's weapon for `WHETSTONE_TURNS`
+    /// turns
+    pub fn whetstone() -> Self {
+        Consumable {
+            name: "Whetstone".to_string(),
+            description: format!(
+                "Hones your weapon for +{WHETSTONE_BONUS} attack over {WHETSTONE_TURNS} turns"
+            ),
+            consumable_type: ConsumableType::Whetstone,
+            potency: WHETSTONE_TURNS as i32,
+            value: 40,
+        }
+    }
+
+    /// Whether this is only good for crafting
+    pub fn is_material(&self) -> bool {
+        matches!(self.consumable_type, ConsumableType::Material(_))
+    }
+
+    /// Weight against the player's carry capacity; every consumable of a
+    /// type weighs the same
+    pub fn weight(&self) -> u32 {
+        match self.consumable_type {
+            ConsumableType::FireBomb | ConsumableType::Torch => 2,
+            ConsumableType::RepairKit => 4,
+            _ => 1,
+        }
+    }
+
+    /// Whether this is thrown at an enemy rather than used on yourself
+    pub fn is_thrown(&self) -> bool {
+        matches!(
+            self.consumable_type,
+            ConsumableType::FireBomb
+                | ConsumableType::ThrowingKnife
+                | ConsumableType::FlashPowder
+                | ConsumableType::SlowingDust
+        )
+    }
+
+    /// Type of the damage a thrown consumable deals, if it deals any
+    pub fn damage_type(&self) -> Option<DamageType> {
+        match self.consumable_type {
+            ConsumableType::FireBomb => Some(DamageType::Fire),
+            ConsumableType::ThrowingKnife => Some(DamageType::Physical),
+            _ => None,
+        }
+    }
+
+    /// Throws this at `enemy`, returning the damage dealt and what happened.
+    /// Throws always land, but the enemy's defense and resistances apply.
+    pub fn throw_at(&self, enemy: &mut Enemy) -> (i32, String) {
+        if let Some(damage_type) = self.damage_type() {
+            let damage = enemy.take_hit(self.potency, damage_type);
+            let message = match self.consumable_type {
+                ConsumableType::FireBomb => format!(
+                    "The {} bursts into flames, burning the {} for {damage} damage!",
+                    self.name, enemy.name
+                ),
+                _ => format!(
+                    "Your {} hits the {} for {damage} damage!",
+                    self.name, enemy.name
+                ),
+            };
+            return (damage, message);
+        }
+
+        match self.consumable_type {
+            ConsumableType::FlashPowder => {
+                let turns = self.potency.max(1) as u32;
+                enemy.stun(turns);
+                (
+                    0,
+                    format!(
+                        "The {} blinds the {}, stunning it for {turns} turns!",
+                        self.name, enemy.name
+                    ),
+                )
+            }
+            ConsumableType::SlowingDust => {
+                let turns = self.potency.max(1) as u32;
+                enemy.slow(turns);
+                (
+                    0,
+                    format!(
+                        "The {} settles on the {}, slowing it for {turns} turns!",
+                        self.name, enemy.name
+                    ),
+                )
+            }
+            _ => (0, format!("The {} can't be thrown.", self.name)),
+        }
+    }
+
+    /// Exact effect of drinking or throwing this consumable, for inspection
+    /// views
+    pub fn effect_text(&self) -> String {
+        match self.consumable_type {
+            ConsumableType::HealthPotion => format!("Restores {} health", self.potency),
+            ConsumableType::ManaPotion => format!("Restores {} mana", self.potency),
+            ConsumableType::Antidote => "Cures poison".to_string(),
+            ConsumableType::StrengthElixir => {
+                format!("Permanently raises Strength by {}", self.potency)
+            }
+            ConsumableType::IntelligenceElixir => {
+                format!("Permanently raises Intelligence by {}", self.potency)
+            }
+            ConsumableType::DexterityElixir => {
+                format!("Permanently raises Dexterity by {}", self.potency)
+            }
+            ConsumableType::ConstitutionElixir => {
+                format!("Permanently raises Constitution by {}", self.potency)
+            }
+            ConsumableType::WisdomElixir => {
+                format!("Permanently raises Wisdom by {}", self.potency)
+            }
+            ConsumableType::FireBomb => {
+                format!("Thrown: deals {} fire damage", self.potency)
+            }
+            ConsumableType::ThrowingKnife => format!("Thrown: deals {} damage", self.potency),
+            ConsumableType::FlashPowder => {
+                format!("Thrown: stuns an enemy for {} turns", self.potency)
+            }
+            ConsumableType::RepairKit => format!(
+                "Restores {} durability to your most worn equipment",
+                self.potency
+            ),
+            ConsumableType::Torch => format!("Lights dark places for {} turns", self.potency),
+            ConsumableType::TamingCharm => {
+                "Tames an adjacent beast below a quarter of its health".to_string()
+            }
+            ConsumableType::SmellingSalts => "Wakes a knocked out companion".to_string(),
+            ConsumableType::IdentifyScroll => "Identifies an unknown item".to_string(),
+            ConsumableType::RemoveCurseScroll => "Lifts every curse on your gear".to_string(),
+            ConsumableType::HastePotion => {
+                format!("Makes you faster for {} turns", self.potency)
+            }
+            ConsumableType::SlowingDust => {
+                format!("Thrown: slows an enemy for {} turns", self.potency)
+            }
+            ConsumableType::ReturnScroll => {
+                "Teleports you to the entrance, or back to where you left".to_string()
+            }
+            ConsumableType::ArcaneDust => "Used to enchant gear at a shrine".to_string(),
+            ConsumableType::Material(_) => "Used to craft other items".to_string(),
+            ConsumableType::Whetstone => format!(
+                "Adds {WHETSTONE_BONUS} to your weapon's attack for {} turns",
+                self.potency
+            ),
+        }
+    }
+
+    /// Key numbers for pickup and loot messages: "+20 HP"
+    pub fn stat_tag(&self) -> String {
+        let stat = |name: &str| format!("+{} {name}", self.potency);
+        match self.consumable_type {
+            ConsumableType::HealthPotion => stat("HP"),
+            ConsumableType::ManaPotion => stat("MP"),
+            ConsumableType::Antidote => "cures poison".to_string(),
+            ConsumableType::StrengthElixir => stat("STR"),
+            ConsumableType::IntelligenceElixir => stat("INT"),
+            ConsumableType::DexterityElixir => stat("DEX"),
+            ConsumableType::ConstitutionElixir => stat("CON"),
+            ConsumableType::WisdomElixir => stat("WIS"),
+            ConsumableType::FireBomb => format!("{} fire damage", self.potency),
+            ConsumableType::ThrowingKnife => format!("{} damage", self.potency),
+            ConsumableType::FlashPowder => format!("stuns {} turns", self.potency),
+            ConsumableType::RepairKit => format!("repairs {}", self.potency),
+            ConsumableType::Torch => format!("{} turns of light", self.potency),
+            ConsumableType::TamingCharm => "tames a beast".to_string(),
+            ConsumableType::SmellingSalts => "wakes a companion".to_string(),
+            ConsumableType::IdentifyScroll => "identifies an item".to_string(),
+            ConsumableType::RemoveCurseScroll => "lifts curses".to_string(),
+            ConsumableType::HastePotion => format!("haste {} turns", self.potency),
+            ConsumableType::SlowingDust => format!("slows {} turns", self.potency),
+            ConsumableType::ReturnScroll => "teleports".to_string(),
+            ConsumableType::ArcaneDust => "enchanting reagent".to_string(),
+            ConsumableType::Material(_) => "crafting material".to_string(),
+            ConsumableType::Whetstone => format!("+{WHETSTONE_BONUS} ATK {} turns", self.potency),
+        }
+    }
+
     pub fn use_effect(&self, player: &mut Player) -> String {
         match self.consumable_type {
             ConsumableType::HealthPotion => {
-                let heal_amount = self.potency;
+                let heal_amount = player.potion_healing(self.potency);
                 player.heal(heal_amount);
                 format!("You restored {heal_amount} health points")
             }
@@ -38,8 +388,11 @@ impl Consumable {
                 format!("You restored {mana_amount} mana points")
             }
             ConsumableType::Antidote => {
-                // In a more complex game, this would remove poison status
-                "You feel purified".to_string()
+                if std::mem::take(&mut player.poisoned_turns) > 0 {
+                    "The poison leaves your body".to_string()
+                } else {
+                    "You feel purified".to_string()
+                }
             }
             ConsumableType::StrengthElixir => {
                 use crate::character::StatType;
@@ -59,38 +412,172 @@ impl Consumable {
             ConsumableType::ConstitutionElixir => {
                 use crate::character::StatType;
                 player.stats.modify_stat(StatType::Constitution, 1);
-                player.max_health = 10 + (player.stats.constitution * 5);
+                player.recalculate_derived_stats();
                 "Your constitution increases permanently by 1".to_string()
             }
             ConsumableType::WisdomElixir => {
                 use crate::character::StatType;
                 player.stats.modify_stat(StatType::Wisdom, 1);
-                player.max_mana = 5 + (player.stats.wisdom * 3);
+                player.recalculate_derived_stats();
                 "Your wisdom increases permanently by 1".to_string()
             }
+            ConsumableType::FireBomb
+            | ConsumableType::ThrowingKnife
+            | ConsumableType::FlashPowder
+            | ConsumableType::SlowingDust => {
+                format!("The {} has to be thrown at an enemy.", self.name)
+            }
+            ConsumableType::HastePotion => {
+                player.haste_turns = player.haste_turns.max(self.potency.max(0) as u32);
+                format!(
+                    "Your heart races: you'll move faster for {} turns",
+                    player.haste_turns
+                )
+            }
+            ConsumableType::RepairKit => {
+                let Some(index) = player.inventory.most_worn_equipment() else {
+                    return "Nothing needs repairing".to_string();
+                };
+                let Some(Item::Equipment(equipment)) = player.inventory.items.get_mut(index) else {
+                    return "Nothing needs repairing".to_string();
+                };
+                equipment.repair(self.potency.max(0) as u32);
+                format!(
+                    "You repair your {} ({})",
+                    equipment.display_name(),
+                    equipment.durability
+                )
+            }
+            ConsumableType::Torch => {
+                // A fresh torch is lit from the last one's stub
+                player.torch_turns += self.potency.max(0) as u32;
+                format!("You light a torch ({} turns of light)", player.torch_turns)
+            }
+            ConsumableType::TamingCharm => {
+                format!("The {} has to be used next to a weakened beast.", self.name)
+            }
+            ConsumableType::ReturnScroll => {
+                format!("The {} only works away from a fight.", self.name)
+            }
+            ConsumableType::ArcaneDust => {
+                format!("The {} is offered at a shrine to enchant gear.", self.name)
+            }
+            ConsumableType::Material(_) => {
+                format!(
+                    "You can only craft with {}: press R in the inventory.",
+                    self.name
+                )
+            }
+            ConsumableType::Whetstone => match player.inventory.get_equipped_weapon() {
+                Some(weapon) => {
+                    let weapon = weapon.short_name();
+                    player.honed_turns = player.honed_turns.max(self.potency.max(0) as u32);
+                    format!(
+                        "You hone your {weapon}: +{WHETSTONE_BONUS} attack for {} turns",
+                        player.honed_turns
+                    )
+                }
+                None => "You have no weapon to hone".to_string(),
+            },
+            ConsumableType::SmellingSalts => match player.companion.as_mut() {
+                Some(companion) if companion.is_knocked_out() => {
+                    companion.revive();
+                    format!("{} wakes with a start and gets back up!", companion.name)
+                }
+                Some(companion) => format!("{} doesn't need waking", companion.name),
+                None => "You have no companion to wake".to_string(),
+            },
+            ConsumableType::IdentifyScroll => {
+                let reveal = player.inventory.next_unidentified().and_then(|index| {
+                    match player.inventory.items.get_mut(index) {
+                        Some(Item::Equipment(equipment)) => equipment.identify(),
+                        _ => None,
+                    }
+                });
+                match reveal {
+                    Some(reveal) => format!("The scroll reveals {reveal}"),
+                    None => "You have nothing to identify".to_string(),
+                }
+            }
+            ConsumableType::RemoveCurseScroll => {
+                let lifted = player.inventory.remove_curses();
+                if lifted.is_empty() {
+                    "Nothing you carry is cursed".to_string()
+                } else {
+                    format!("The curse on your {} lifts", lifted.join(" and "))
+                }
+            }
         }
     }
 
-    pub fn generate_random(level: u32) -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn generate_with_rng<R: Rng + ?Sized>(level: u32, rng: &mut R) -> Self {
+        // Choose consumable type; the stronger throwables only turn up
+        // deeper in
+        let mut types = vec![
+            ConsumableType::HealthPotion,
+            ConsumableType::ManaPotion,
+            ConsumableType::Antidote,
+            ConsumableType::StrengthElixir,
+            ConsumableType::IntelligenceElixir,
+            ConsumableType::DexterityElixir,
+            ConsumableType::ConstitutionElixir,
+            ConsumableType::WisdomElixir,
+            ConsumableType::ThrowingKnife,
+            ConsumableType::RepairKit,
+            ConsumableType::Torch,
+            ConsumableType::SmellingSalts,
+            ConsumableType::IdentifyScroll,
+            ConsumableType::RemoveCurseScroll,
+        ];
+        // Taming charms are rare finds
+        if rng.gen_ratio(1, 4) {
+            types.push(ConsumableType::TamingCharm);
+        }
+        if level >= 2 {
+            types.push(ConsumableType::FireBomb);
+            types.push(ConsumableType::HastePotion);
+            types.push(ConsumableType::SlowingDust);
+        }
+        if level >= 3 {
+            types.push(ConsumableType::FlashPowder);
+            types.push(ConsumableType::ReturnScroll);
+        }
+        let consumable_type = types.swap_remove(rng.gen_range(0..types.len()));
+        Self::of_type_with_rng(consumable_type, level, rng)
+    }
 
-        // Choose consumable type
-        let consumable_type = match rng.gen_range(0..8) {
-            0 => ConsumableType::HealthPotion,
-            1 => ConsumableType::ManaPotion,
-            2 => ConsumableType::Antidote,
-            3 => ConsumableType::StrengthElixir,
-            4 => ConsumableType::IntelligenceElixir,
-            5 => ConsumableType::DexterityElixir,
-            6 => ConsumableType::ConstitutionElixir,
-            _ => ConsumableType::WisdomElixir,
-        };
+    /// A scroll of one of the kinds a library keeps: healing, identify,
+    /// remove curse or return
+    pub fn scroll_with_rng<R: Rng + ?Sized>(level: u32, rng: &mut R) -> Self {
+        match rng.gen_range(0..4) {
+            0 => Self::healing_scroll(20 + level as i32 * 10),
+            1 => Self::of_type_with_rng(ConsumableType::IdentifyScroll, level, rng),
+            2 => Self::of_type_with_rng(ConsumableType::RemoveCurseScroll, level, rng),
+            _ => Self::return_scroll(),
+        }
+    }
 
+    /// A consumable of `consumable_type` for dungeon level `level`
+    fn of_type_with_rng<R: Rng + ?Sized>(
+        consumable_type: ConsumableType,
+        level: u32,
+        rng: &mut R,
+    ) -> Self {
         // Generate potency based on level
         let potency = match consumable_type {
             ConsumableType::HealthPotion | ConsumableType::ManaPotion => {
                 20 + level as i32 * 10 + rng.gen_range(0..10)
             }
+            ConsumableType::ThrowingKnife => 6 + level as i32 * 3,
+            ConsumableType::FireBomb => 12 + level as i32 * 5 + rng.gen_range(0..5),
+            // Turns the enemy is stunned for
+            ConsumableType::FlashPowder => 2,
+            ConsumableType::RepairKit => 15 + level as i32 * 2,
+            ConsumableType::Torch => TORCH_TURNS as i32,
+            ConsumableType::HastePotion => HASTE_TURNS as i32,
+            ConsumableType::Whetstone => WHETSTONE_TURNS as i32,
+            // Turns the enemy is slowed for
+            ConsumableType::SlowingDust => 5,
             // Antidotes don't have variable potency, stat elixirs always give +1
             _ => 1,
         };
@@ -98,32 +585,14 @@ impl Consumable {
         // Set name and description based on type
         let (name, description) = match consumable_type {
             ConsumableType::HealthPotion => {
-                let quality = if potency < 50 {
-                    "Minor"
-                } else if potency < 100 {
-                    "Regular"
-                } else if potency < 150 {
-                    "Greater"
-                } else {
-                    "Superior"
-                };
-
+                let quality = potion_quality(potency);
                 (
                     format!("{quality} Health Potion"),
                     format!("Restores {potency} health points when consumed"),
                 )
             }
             ConsumableType::ManaPotion => {
-                let quality = if potency < 50 {
-                    "Minor"
-                } else if potency < 100 {
-                    "Regular"
-                } else if potency < 150 {
-                    "Greater"
-                } else {
-                    "Superior"
-                };
-
+                let quality = potion_quality(potency);
                 (
                     format!("{quality} Mana Potion"),
                     format!("Restores {potency} mana points when consumed"),
@@ -150,12 +619,87 @@ impl Consumable {
                 "Elixir of Wisdom".to_string(),
                 "Permanently increases Wisdom by 1".to_string(),
             ),
+            ConsumableType::FireBomb => (
+                "Fire Bomb".to_string(),
+                format!("Throw at an enemy to burn it for {potency} fire damage"),
+            ),
+            ConsumableType::ThrowingKnife => (
+                "Throwing Knife".to_string(),
+                format!("Throw at an enemy for {potency} damage"),
+            ),
+            ConsumableType::FlashPowder => (
+                "Flash Powder".to_string(),
+                format!("Throw at an enemy to stun it for {potency} turns"),
+            ),
+            ConsumableType::RepairKit => (
+                "Repair Kit".to_string(),
+                format!("Restores {potency} durability to your most worn equipment"),
+            ),
+            ConsumableType::Torch => (
+                "Torch".to_string(),
+                format!("Lights up dark places for {potency} turns"),
+            ),
+            ConsumableType::TamingCharm => {
+                let charm = Self::taming_charm();
+                (charm.name, charm.description)
+            }
+            ConsumableType::SmellingSalts => (
+                "Smelling Salts".to_string(),
+                "Wakes a knocked out companion".to_string(),
+            ),
+            ConsumableType::IdentifyScroll => (
+                "Scroll of Identify".to_string(),
+                "Reveals what an unidentified item really is".to_string(),
+            ),
+            ConsumableType::RemoveCurseScroll => (
+                "Scroll of Remove Curse".to_string(),
+                "Lifts the curse from cursed gear so it can be taken off".to_string(),
+            ),
+            ConsumableType::HastePotion => (
+                "Potion of Haste".to_string(),
+                format!("Makes you move faster for {potency} turns"),
+            ),
+            ConsumableType::SlowingDust => (
+                "Slowing Dust".to_string(),
+                format!("Throw at an enemy to slow it for {potency} turns"),
+            ),
+            ConsumableType::ReturnScroll => {
+                let scroll = Self::return_scroll();
+                (scroll.name, scroll.description)
+            }
+            ConsumableType::ArcaneDust => {
+                let dust = Self::arcane_dust();
+                (dust.name, dust.description)
+            }
+            ConsumableType::Material(material) => {
+                let material = Self::material(material);
+                (material.name, material.description)
+            }
+            ConsumableType::Whetstone => {
+                let stone = Self::whetstone();
+                (stone.name, stone.description)
+            }
         };
 
         // Generate value based on type and potency
         let value = match consumable_type {
             ConsumableType::HealthPotion | ConsumableType::ManaPotion => potency as u32 / 2,
             ConsumableType::Antidote => 30,
+            ConsumableType::ThrowingKnife => 10 + level * 2,
+            ConsumableType::FireBomb
+            | ConsumableType::FlashPowder
+            | ConsumableType::SlowingDust => 25 + level * 5,
+            ConsumableType::HastePotion => 50,
+            ConsumableType::RepairKit => 40 + level * 5,
+            ConsumableType::Torch => 15,
+            ConsumableType::SmellingSalts => 35,
+            ConsumableType::IdentifyScroll => 60,
+            ConsumableType::RemoveCurseScroll => 80,
+            ConsumableType::TamingCharm => 150,
+            ConsumableType::ReturnScroll => 120,
+            ConsumableType::ArcaneDust => 25,
+            ConsumableType::Material(_) => 10,
+            ConsumableType::Whetstone => 40,
             _ => 100 + level * 20, // Stat elixirs are valuable
         };
 
@@ -169,6 +713,19 @@ impl Consumable {
     }
 }
 
+/// Name prefix for a health or mana potion of the given strength
+fn potion_quality(potency: i32) -> &'static str {
+    if potency < 50 {
+        "Minor"
+    } else if potency < 100 {
+        "Regular"
+    } else if potency < 150 {
+        "Greater"
+    } else {
+        "Superior"
+    }
+}
+
 impl fmt::Display for Consumable {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.name)
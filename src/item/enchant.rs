@@ -0,0 +1,169 @@
+//! Enchanting gear at a shrine
+//!
+//! Gold and Arcane Dust, offered at a shrine, work another point of power
+//! into a piece of identified equipment and now and then a point in a stat
+//! as well. Each enchantment costs far more than the last, and past
+//! `SAFE_ENCHANT_LEVEL` an attempt can fail, using up what was offered but
+//! never harming the item.
+
+use crate::character::Player;
+use crate::item::consumable::ConsumableType;
+use crate::item::Item;
+
+/// Enchantments that always take; the next one can fail
+pub const SAFE_ENCHANT_LEVEL: u32 = 3;
+
+/// Gold the first enchantment costs; each one after doubles it
+pub const BASE_ENCHANT_GOLD: u32 = 40;
+
+/// How much less likely each enchantment past the safe ones is to take
+pub const FAILURE_STEP: f64 = 0.2;
+
+/// Lowest chance an enchantment ever has of taking
+pub const MIN_SUCCESS_CHANCE: f64 = 0.2;
+
+/// Chance an enchantment that takes also adds a point to a stat
+pub const STAT_BONUS_CHANCE: f64 = 0.25;
+
+/// What enchanting a piece at some level takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnchantCost {
+    pub gold: u32,
+    /// Arcane Dust used up
+    pub dust: u32,
+}
+
+/// What enchanting a piece already at `level` costs: the gold doubles
+/// each time and the dust goes up every other one
+pub fn enchant_cost(level: u32) -> EnchantCost {
+    EnchantCost {
+        gold: BASE_ENCHANT_GOLD.saturating_mul(2u32.saturating_pow(level)),
+        dust: 1 + level / 2,
+    }
+}
+
+/// Chance that enchanting a piece already at `level` takes: certain up to
+/// `SAFE_ENCHANT_LEVEL`, less likely with each one after
+pub fn success_chance(level: u32) -> f64 {
+    if level < SAFE_ENCHANT_LEVEL {
+        return 1.0;
+    }
+    let risky = (level - SAFE_ENCHANT_LEVEL + 1) as f64;
+    (1.0 - FAILURE_STEP * risky).max(MIN_SUCCESS_CHANCE)
+}
+
+/// `base` enchanted `level` times: "Steel Dagger +2", or just the base
+/// name before the first
+pub fn enchanted_name(base: &str, level: u32) -> String {
+    match level {
+        0 => base.to_string(),
+        level => format!("{base} +{level}"),
+    }
+}
+
+/// Arcane Dust the player carries
+pub fn dust_held(player: &Player) -> u32 {
+    player
+        .inventory
+        .items
+        .iter()
+        .filter(|item| {
+            matches!(item, Item::Consumable(consumable)
+                if consumable.consumable_type == ConsumableType::ArcaneDust)
+        })
+        .count() as u32
+}
+
+/// Enchanting one piece the player carries, as the enchanting screen
+/// offers it before they confirm
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnchantOffer {
+    /// Inventory index of the piece
+    pub index: usize,
+    /// Its name now: "Steel Dagger +1"
+    pub name: String,
+    /// What it'll be called if it takes: "Steel Dagger +2"
+    pub next_name: String,
+    pub level: u32,
+    pub cost: EnchantCost,
+    /// Chance it takes, from 0 to 1
+    pub chance: f64,
+    pub gold_held: u32,
+    pub dust_held: u32,
+}
+
+impl EnchantOffer {
+    /// The offer for the piece at inventory `index`, or None if that's not
+    /// identified equipment
+    pub fn for_item(player: &Player, index: usize) -> Option<Self> {
+        let Some(Item::Equipment(equipment)) = player.inventory.items.get(index) else {
+            return None;
+        };
+        if !equipment.is_identified() {
+            return None;
+        }
+        let level = equipment.enchant_level;
+        Some(EnchantOffer {
+            index,
+            name: equipment.name.clone(),
+            next_name: enchanted_name(equipment.base_name(), level + 1),
+            level,
+            cost: enchant_cost(level),
+            chance: success_chance(level),
+            gold_held: player.gold,
+            dust_held: dust_held(player),
+        })
+    }
+
+    pub fn affordable(&self) -> bool {
+        self.gold_held >= self.cost.gold && self.dust_held >= self.cost.dust
+    }
+
+    /// The lines the enchanting screen shows about it
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("{} -> {}", self.name, self.next_name),
+            format!(
+                "Cost: {} gold (you have {}), {} Arcane Dust (you have {})",
+                self.cost.gold, self.gold_held, self.cost.dust, self.dust_held
+            ),
+            format!("Chance it takes: {:.0}%", self.chance * 100.0),
+        ];
+        if self.chance < 1.0 {
+            lines.push("If it fails, the gold and dust are lost; the item is never harmed.".into());
+        }
+        if !self.affordable() {
+            lines.push("You can't afford this yet.".to_string());
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_each_enchantment_costs_more_and_risks_more_past_the_safe_ones() {
+        assert_eq!(enchant_cost(0), EnchantCost { gold: 40, dust: 1 });
+        assert_eq!(enchant_cost(1), EnchantCost { gold: 80, dust: 1 });
+        assert_eq!(enchant_cost(4), EnchantCost { gold: 640, dust: 3 });
+        for level in 0..10 {
+            assert!(enchant_cost(level + 1).gold >= enchant_cost(level).gold * 2);
+        }
+        assert_eq!(enchant_cost(40).gold, u32::MAX);
+
+        for level in 0..SAFE_ENCHANT_LEVEL {
+            assert_eq!(success_chance(level), 1.0);
+        }
+        assert!((success_chance(SAFE_ENCHANT_LEVEL) - 0.8).abs() < 1e-9);
+        assert!(success_chance(SAFE_ENCHANT_LEVEL + 1) < success_chance(SAFE_ENCHANT_LEVEL));
+        assert_eq!(success_chance(50), MIN_SUCCESS_CHANCE);
+    }
+
+    #[test]
+    fn test_enchanted_names_carry_their_level() {
+        assert_eq!(enchanted_name("Steel Dagger", 0), "Steel Dagger");
+        assert_eq!(enchanted_name("Steel Dagger", 3), "Steel Dagger +3");
+    }
+}
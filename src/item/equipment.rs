@@ -1,4 +1,8 @@
 use crate::character::StatType;
+use crate::combat::DamageType;
+use crate::item::enchant;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::SliceRandom;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -26,6 +30,19 @@ impl EquipmentSlot {
         ]
         .into_iter()
     }
+
+    /// What a piece of equipment for this slot weighs unless it says
+    /// otherwise
+    pub fn default_weight(self) -> u32 {
+        match self {
+            EquipmentSlot::Head => 4,
+            EquipmentSlot::Chest => 12,
+            EquipmentSlot::Hands => 2,
+            EquipmentSlot::Feet => 4,
+            EquipmentSlot::Weapon => 6,
+            EquipmentSlot::Shield => 8,
+        }
+    }
 }
 
 impl fmt::Display for EquipmentSlot {
@@ -41,6 +58,143 @@ impl fmt::Display for EquipmentSlot {
     }
 }
 
+/// Quality tier of a piece of equipment
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+}
+
+impl Rarity {
+    pub fn iter() -> impl Iterator<Item = Rarity> {
+        [Rarity::Common, Rarity::Uncommon, Rarity::Rare, Rarity::Epic].into_iter()
+    }
+
+    /// Roll a rarity; higher levels and a positive `bonus` (used for chests)
+    /// shift the odds toward the better tiers
+    pub fn roll<R: Rng + ?Sized>(level: u32, bonus: u32, rng: &mut R) -> Rarity {
+        let shift = (level.saturating_sub(1) * 2 + bonus).min(40);
+        let weights = [60 - shift, 25, 12 + shift * 2 / 3, 3 + shift / 3];
+        let index = WeightedIndex::new(weights)
+            .map(|dist| dist.sample(rng))
+            .unwrap_or(0);
+        Rarity::iter().nth(index).unwrap_or_default()
+    }
+
+    /// Multiplier applied to the base power of the item
+    pub fn power_multiplier(&self) -> f32 {
+        match self {
+            Rarity::Common => 1.0,
+            Rarity::Uncommon => 1.25,
+            Rarity::Rare => 1.5,
+            Rarity::Epic => 2.0,
+        }
+    }
+
+    /// Number of distinct stats the item boosts
+    pub fn stat_bonus_count(&self) -> usize {
+        match self {
+            Rarity::Common => 1,
+            Rarity::Uncommon => 2,
+            Rarity::Rare => 3,
+            Rarity::Epic => 4,
+        }
+    }
+
+    /// Display color shared by the GUI and web front-ends
+    /// (white/green/blue/purple)
+    #[cfg(any(feature = "gui", target_arch = "wasm32"))]
+    pub fn rgb(&self) -> (u8, u8, u8) {
+        match self {
+            Rarity::Common => (255, 255, 255),
+            Rarity::Uncommon => (30, 255, 0),
+            Rarity::Rare => (0, 112, 221),
+            Rarity::Epic => (163, 53, 238),
+        }
+    }
+}
+
+impl fmt::Display for Rarity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rarity::Common => write!(f, "Common"),
+            Rarity::Uncommon => write!(f, "Uncommon"),
+            Rarity::Rare => write!(f, "Rare"),
+            Rarity::Epic => write!(f, "Epic"),
+        }
+    }
+}
+
+/// Durability of new equipment
+pub const MAX_DURABILITY: u32 = 40;
+
+/// Chance that one use wears an item down by a point
+pub const WEAR_CHANCE: f64 = 0.25;
+
+/// Turns an unidentified item must be worn before the player learns what
+/// it is
+pub const ATTUNE_TURNS: u32 = 50;
+
+/// Chance that an unidentified item is cursed
+pub const CURSE_CHANCE: f64 = 0.25;
+
+/// How worn a piece of equipment is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Durability {
+    pub current: u32,
+    pub max: u32,
+}
+
+impl Durability {
+    pub fn full(max: u32) -> Self {
+        Durability { current: max, max }
+    }
+
+    /// Fraction of the durability left, from 0.0 to 1.0
+    pub fn fraction(&self) -> f32 {
+        if self.max == 0 {
+            return 0.0;
+        }
+        self.current as f32 / self.max as f32
+    }
+
+    /// At or below a quarter of its durability
+    pub fn is_badly_damaged(&self) -> bool {
+        self.current * 4 <= self.max
+    }
+}
+
+/// Items saved before durability existed come back in full repair
+impl Default for Durability {
+    fn default() -> Self {
+        Durability::full(MAX_DURABILITY)
+    }
+}
+
+impl fmt::Display for Durability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.current == 0 {
+            write!(f, "Broken")
+        } else {
+            write!(f, "{}/{}", self.current, self.max)
+        }
+    }
+}
+
+/// What one use did to a piece of equipment, when it wore it down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wear {
+    Worn,
+    /// Just dropped to a quarter of its durability
+    BadlyDamaged,
+    Broken,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EquipmentType {
     Armor,
@@ -57,12 +211,263 @@ pub struct Equipment {
     pub value: u32,
     pub stat_bonuses: HashMap<StatType, i32>,
     pub level_requirement: u32,
+    #[serde(default)]
+    pub rarity: Rarity,
+    /// What the weapon's hits are made of; always physical for armor
+    #[serde(default)]
+    pub damage_type: DamageType,
+    #[serde(default)]
+    pub durability: Durability,
+    /// Weight against the player's carry capacity; items saved before
+    /// weight existed have none and weigh their slot's default
+    #[serde(default)]
+    pub weight: Option<u32>,
+    /// The vague name shown until the item is identified, such as
+    /// "Shimmering Sword"; its stat bonuses stay hidden until then
+    #[serde(default)]
+    pub disguise: Option<String>,
+    /// Turns the item has been worn while unidentified
+    #[serde(default)]
+    pub turns_worn: u32,
+    /// A cursed item can't be taken off until a Remove Curse scroll is read
+    #[serde(default)]
+    pub cursed: bool,
+    /// Times it's been enchanted, shown after its name as "+N"
+    #[serde(default)]
+    pub enchant_level: u32,
+    /// What each attempt at enchanting it did, oldest first:
+    /// "+1: +1 ATK, +1 STR" or "+4: failed"
+    #[serde(default)]
+    pub enchant_history: Vec<String>,
 }
 
 impl Equipment {
-    pub fn generate_random(level: u32) -> Self {
-        let mut rng = rand::thread_rng();
+    /// A plain common weapon with no stat bonuses, such as starting gear
+    pub fn weapon(name: &str, power: i32) -> Self {
+        Self::plain(
+            name,
+            format!("A weapon that deals {power} physical damage. Required level: 1"),
+            EquipmentType::Weapon,
+            EquipmentSlot::Weapon,
+            power,
+        )
+    }
 
+    /// A plain common piece of armor with no stat bonuses
+    pub fn armor(name: &str, slot: EquipmentSlot, power: i32) -> Self {
+        Self::plain(
+            name,
+            format!("A piece of armor that provides {power} protection. Required level: 1"),
+            EquipmentType::Armor,
+            slot,
+            power,
+        )
+    }
+
+    fn plain(
+        name: &str,
+        description: String,
+        equipment_type: EquipmentType,
+        slot: EquipmentSlot,
+        power: i32,
+    ) -> Self {
+        Equipment {
+            name: name.to_string(),
+            description,
+            equipment_type,
+            slot,
+            power,
+            value: power as u32 * 5,
+            stat_bonuses: HashMap::new(),
+            level_requirement: 1,
+            rarity: Rarity::Common,
+            damage_type: DamageType::Physical,
+            durability: Durability::default(),
+            weight: None,
+            disguise: None,
+            turns_worn: 0,
+            cursed: false,
+            enchant_level: 0,
+            enchant_history: Vec::new(),
+        }
+    }
+
+    pub fn is_identified(&self) -> bool {
+        self.disguise.is_none()
+    }
+
+    /// The name the player knows the item by: its disguise until it's
+    /// identified
+    pub fn display_name(&self) -> &str {
+        self.disguise.as_deref().unwrap_or(&self.name)
+    }
+
+    /// The display name, marked while the item is unidentified:
+    /// "Shimmering Sword (unidentified)"
+    pub fn labelled_name(&self) -> String {
+        if self.is_identified() {
+            self.name.clone()
+        } else {
+            format!("{} (unidentified)", self.display_name())
+        }
+    }
+
+    /// Reveals what the item is, returning the message telling the player
+    /// about it, or None if it was already known
+    pub fn identify(&mut self) -> Option<String> {
+        let disguise = self.disguise.take()?;
+        let curse = if self.cursed { " It's cursed!" } else { "" };
+        Some(format!(
+            "the {disguise}: it is a {} ({})!{curse}",
+            self.name,
+            self.stat_tag()
+        ))
+    }
+
+    /// Counts one turn of wearing an unidentified item, identifying it once
+    /// it's been worn for `ATTUNE_TURNS`
+    pub fn attune(&mut self) -> Option<String> {
+        if self.is_identified() {
+            return None;
+        }
+        self.turns_worn += 1;
+        if self.turns_worn < ATTUNE_TURNS {
+            return None;
+        }
+        self.identify()
+            .map(|reveal| format!("You feel attuned to {reveal}"))
+    }
+
+    pub fn weight(&self) -> u32 {
+        self.weight.unwrap_or_else(|| self.slot.default_weight())
+    }
+
+    /// The name without its enchantment: "Steel Dagger" for
+    /// "Steel Dagger +2"
+    pub fn base_name(&self) -> &str {
+        let suffix = format!(" +{}", self.enchant_level);
+        match self.enchant_level {
+            0 => &self.name,
+            _ => self.name.strip_suffix(&suffix).unwrap_or(&self.name),
+        }
+    }
+
+    /// Works another point of power into the item, and a point of `stat`
+    /// too if given, renaming it "Steel Dagger +1" and so on. Returns what
+    /// it gained: "+1 ATK, +1 STR".
+    pub fn enchant(&mut self, stat: Option<StatType>) -> String {
+        self.power += 1;
+        self.value += enchant::enchant_cost(self.enchant_level).gold / 2;
+        self.name = enchant::enchanted_name(self.base_name(), self.enchant_level + 1);
+        self.enchant_level += 1;
+
+        let mut gained = match self.equipment_type {
+            EquipmentType::Weapon => "+1 ATK".to_string(),
+            EquipmentType::Armor => "+1 DEF".to_string(),
+        };
+        if let Some(stat) = stat {
+            *self.stat_bonuses.entry(stat).or_insert(0) += 1;
+            gained.push_str(&format!(", +1 {}", stat.abbreviation()));
+        }
+        self.enchant_history
+            .push(format!("+{}: {gained}", self.enchant_level));
+        gained
+    }
+
+    /// Notes an attempt at enchanting the item that didn't take
+    pub fn record_failed_enchant(&mut self) {
+        self.enchant_history
+            .push(format!("+{}: failed", self.enchant_level + 1));
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.durability.current == 0
+    }
+
+    /// Power after wear: below half durability the item loses up to a
+    /// fifth of its power, and a broken item has none
+    pub fn effective_power(&self) -> i32 {
+        if self.is_broken() {
+            return 0;
+        }
+        let fraction = self.durability.fraction();
+        if fraction >= 0.5 {
+            return self.power;
+        }
+        (self.power as f32 * (0.8 + 0.4 * fraction)).round() as i32
+    }
+
+    /// Rolls whether one use wears the item down, reporting the wear if it
+    /// did
+    pub fn wear<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<Wear> {
+        if self.is_broken() || !rng.gen_bool(WEAR_CHANCE) {
+            return None;
+        }
+        let was_badly_damaged = self.durability.is_badly_damaged();
+        self.durability.current -= 1;
+        Some(if self.is_broken() {
+            Wear::Broken
+        } else if self.durability.is_badly_damaged() && !was_badly_damaged {
+            Wear::BadlyDamaged
+        } else {
+            Wear::Worn
+        })
+    }
+
+    /// Restores up to `amount` durability, returning how much was restored
+    pub fn repair(&mut self, amount: u32) -> u32 {
+        let restored = amount.min(self.durability.max - self.durability.current);
+        self.durability.current += restored;
+        restored
+    }
+
+    /// Key numbers for pickup and loot messages: "+7 ATK, +2 STR". The
+    /// stat bonuses of an unidentified item show as "???".
+    pub fn stat_tag(&self) -> String {
+        let power = match self.equipment_type {
+            EquipmentType::Weapon => format!("+{} ATK", self.power),
+            EquipmentType::Armor => format!("+{} DEF", self.power),
+        };
+        if !self.is_identified() {
+            return format!("{power}, ???");
+        }
+        let mut bonuses: Vec<String> = StatType::iter()
+            .filter_map(|stat| {
+                let bonus = *self.stat_bonuses.get(&stat)?;
+                Some(format!("{bonus:+} {}", stat.abbreviation()))
+            })
+            .collect();
+        bonuses.insert(0, power);
+        bonuses.join(", ")
+    }
+
+    /// Short name for wear messages, such as "sword" for "Rusty Sword"
+    pub fn short_name(&self) -> String {
+        let name = self.display_name();
+        name.split_whitespace()
+            .last()
+            .unwrap_or(name)
+            .to_lowercase()
+    }
+
+    /// Generate equipment, rolling its rarity with the given bonus toward
+    /// the better tiers
+    pub fn generate_with_rng<R: Rng + ?Sized>(level: u32, rarity_bonus: u32, rng: &mut R) -> Self {
+        let rarity = Rarity::roll(level, rarity_bonus, rng);
+        Self::generate_with_rarity(level, rarity, rng)
+    }
+
+    /// Generate a weapon, as carried by an enemy
+    pub fn generate_weapon_with_rng<R: Rng + ?Sized>(
+        level: u32,
+        rarity_bonus: u32,
+        rng: &mut R,
+    ) -> Self {
+        let rarity = Rarity::roll(level, rarity_bonus, rng);
+        Self::generate_for_slot(level, EquipmentSlot::Weapon, rarity, rng)
+    }
+
+    fn generate_with_rarity<R: Rng + ?Sized>(level: u32, rarity: Rarity, rng: &mut R) -> Self {
         // Randomly determine slot
         let slot = match rng.gen_range(0..6) {
             0 => EquipmentSlot::Head,
@@ -72,7 +477,15 @@ impl Equipment {
             4 => EquipmentSlot::Weapon,
             _ => EquipmentSlot::Shield,
         };
+        Self::generate_for_slot(level, slot, rarity, rng)
+    }
 
+    fn generate_for_slot<R: Rng + ?Sized>(
+        level: u32,
+        slot: EquipmentSlot,
+        rarity: Rarity,
+        rng: &mut R,
+    ) -> Self {
         let equipment_type = match slot {
             EquipmentSlot::Weapon => EquipmentType::Weapon,
             _ => EquipmentType::Armor,
@@ -152,30 +565,32 @@ impl Equipment {
             },
         };
 
-        let name = format!("{prefix} {item_type}");
+        // Roughly one weapon in four carries an element, named after it
+        let damage_type = if equipment_type == EquipmentType::Weapon && rng.gen_bool(0.25) {
+            DamageType::random_element(rng)
+        } else {
+            DamageType::Physical
+        };
+
+        let name = match damage_type.weapon_prefix() {
+            Some(element) => format!("{prefix} {element} {item_type}"),
+            None => format!("{prefix} {item_type}"),
+        };
 
-        // Generate power based on level
+        // Generate power based on level, scaled by rarity
         let power_base = 2 + level;
         let power_variation = rng.gen_range(0..=3);
-        let power = power_base + power_variation;
+        let power =
+            ((power_base + power_variation) as f32 * rarity.power_multiplier()).round() as u32;
 
         // Generate value based on level and power
-        let value = (level * 10 + power as u32 * 5) * rng.gen_range(1..=3);
+        let value = (level * 10 + power * 5) * rng.gen_range(1..=3);
 
-        // Generate stat bonuses
+        // Generate stat bonuses, more of them for rarer items
         let mut stat_bonuses = HashMap::new();
-        let num_bonuses = rng.gen_range(1..=2).min((level / 3 + 1) as usize);
-
-        let stat_types = [
-            StatType::Strength,
-            StatType::Intelligence,
-            StatType::Dexterity,
-            StatType::Constitution,
-            StatType::Wisdom,
-        ];
-
-        for _ in 0..num_bonuses {
-            let stat = stat_types[rng.gen_range(0..stat_types.len())];
+        let stat_types: Vec<StatType> = StatType::iter().collect();
+
+        for &stat in stat_types.choose_multiple(rng, rarity.stat_bonus_count()) {
             let bonus = rng.gen_range(1..=(level / 2 + 1));
             stat_bonuses.insert(stat, bonus as i32);
         }
@@ -186,9 +601,10 @@ impl Equipment {
         // Generate description
         let description = match equipment_type {
             EquipmentType::Weapon => format!(
-                "A {} that deals {} damage. Required level: {}",
+                "A {} that deals {} {} damage. Required level: {}",
                 item_type.to_lowercase(),
                 power,
+                damage_type.to_string().to_lowercase(),
                 level_requirement
             ),
             EquipmentType::Armor => format!(
@@ -199,6 +615,24 @@ impl Equipment {
             ),
         };
 
+        // Rare and better items hide what they are until identified, and
+        // some of those carry a curse that turns one of their bonuses into
+        // a penalty
+        let disguise = (rarity >= Rarity::Rare).then(|| {
+            let look = UNIDENTIFIED_LOOKS.choose(rng).copied().unwrap_or("Strange");
+            format!("{look} {item_type}")
+        });
+        let cursed = disguise.is_some() && rng.gen_bool(CURSE_CHANCE);
+        if cursed {
+            let boosted: Vec<StatType> = stat_types
+                .into_iter()
+                .filter(|stat| stat_bonuses.contains_key(stat))
+                .collect();
+            if let Some(&stat) = boosted.choose(rng) {
+                stat_bonuses.insert(stat, -rng.gen_range(1..=2));
+            }
+        }
+
         Equipment {
             name,
             description,
@@ -208,16 +642,255 @@ impl Equipment {
             value,
             stat_bonuses,
             level_requirement,
+            rarity,
+            damage_type,
+            durability: Durability::default(),
+            weight: Some(item_type_weight(item_type)),
+            disguise,
+            turns_worn: 0,
+            cursed,
+            enchant_level: 0,
+            enchant_history: Vec::new(),
         }
     }
 }
 
+/// How an unidentified item looks before the player knows what it is
+const UNIDENTIFIED_LOOKS: [&str; 6] = [
+    "Shimmering",
+    "Glowing",
+    "Humming",
+    "Runed",
+    "Strange",
+    "Whispering",
+];
+
+/// Weight of a generated item of the given kind: a robe is lighter than
+/// a breastplate
+fn item_type_weight(item_type: &str) -> u32 {
+    match item_type {
+        "Cap" | "Hood" => 2,
+        "Helm" => 4,
+        "Robe" => 5,
+        "Armor" => 10,
+        "Breastplate" => 14,
+        "Gloves" => 1,
+        "Bracers" => 2,
+        "Gauntlets" => 3,
+        "Boots" => 3,
+        "Greaves" => 4,
+        "Sabatons" => 5,
+        "Bow" => 3,
+        "Staff" => 4,
+        "Sword" => 6,
+        "Axe" => 7,
+        "Mace" => 8,
+        "Buckler" => 5,
+        "Shield" => 8,
+        "Barrier" => 10,
+        _ => 5,
+    }
+}
+
 impl fmt::Display for Equipment {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{} (Lvl {}): {} [Power: {}]",
-            self.name, self.level_requirement, self.slot, self.power
+            "{} <{}> (Lvl {}): {} [Power: {}]",
+            self.name, self.rarity, self.level_requirement, self.slot, self.power
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::CHEST_RARITY_BONUS;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn tally(level: u32, bonus: u32) -> HashMap<Rarity, u32> {
+        let mut rng = StdRng::seed_from_u64(1536);
+        let mut counts = HashMap::new();
+        for _ in 0..10_000 {
+            *counts
+                .entry(Rarity::roll(level, bonus, &mut rng))
+                .or_insert(0) += 1;
+        }
+        counts
+    }
+
+    fn rare_or_better(counts: &HashMap<Rarity, u32>) -> u32 {
+        counts
+            .iter()
+            .filter(|(rarity, _)| **rarity >= Rarity::Rare)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    #[test]
+    fn test_rarity_distribution_favours_common_at_low_level() {
+        let counts = tally(1, 0);
+
+        let common = counts[&Rarity::Common];
+        let uncommon = counts[&Rarity::Uncommon];
+        let rare = counts[&Rarity::Rare];
+        let epic = counts[&Rarity::Epic];
+        assert!(common > uncommon && uncommon > rare && rare > epic);
+        assert!((5_500..6_500).contains(&common));
+        assert!((150..450).contains(&epic));
+    }
+
+    #[test]
+    fn test_chests_and_depth_improve_rarity_odds() {
+        let base = rare_or_better(&tally(1, 0));
+        let chest = rare_or_better(&tally(1, CHEST_RARITY_BONUS));
+        let deep = rare_or_better(&tally(10, 0));
+
+        assert!(chest > base);
+        assert!(deep > base);
+    }
+
+    #[test]
+    fn test_power_and_bonuses_scale_with_rarity() {
+        let items: Vec<Equipment> = Rarity::iter()
+            .map(|rarity| {
+                let mut rng = StdRng::seed_from_u64(7);
+                Equipment::generate_with_rarity(5, rarity, &mut rng)
+            })
+            .collect();
+
+        for pair in items.windows(2) {
+            assert!(pair[1].power > pair[0].power);
+        }
+        for item in &items {
+            assert_eq!(item.stat_bonuses.len(), item.rarity.stat_bonus_count());
+        }
+    }
+
+    #[test]
+    fn test_rarity_survives_save_and_load() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let item = Equipment::generate_with_rarity(2, Rarity::Epic, &mut rng);
+
+        let json = serde_json::to_string(&item).unwrap();
+        let loaded: Equipment = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.rarity, Rarity::Epic);
+
+        // Equipment saved before rarity existed loads as Common
+        let mut value = serde_json::to_value(&item).unwrap();
+        value.as_object_mut().unwrap().remove("rarity");
+        let legacy: Equipment = serde_json::from_value(value).unwrap();
+        assert_eq!(legacy.rarity, Rarity::Common);
+    }
+
+    #[test]
+    fn test_rare_items_stay_unidentified_until_worn_long_enough() {
+        let mut rng = StdRng::seed_from_u64(1579);
+        let common = Equipment::generate_with_rarity(5, Rarity::Uncommon, &mut rng);
+        assert!(common.is_identified());
+        assert_eq!(common.labelled_name(), common.name);
+
+        let mut item = Equipment::generate_with_rarity(5, Rarity::Rare, &mut rng);
+        let disguise = item.disguise.clone().unwrap();
+        assert_eq!(item.display_name(), disguise);
+        assert_eq!(item.labelled_name(), format!("{disguise} (unidentified)"));
+        assert!(item.stat_tag().ends_with(", ???"));
+
+        // The disguise survives a save, and items saved before
+        // identification existed load identified
+        let mut value = serde_json::to_value(&item).unwrap();
+        let loaded: Equipment = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(loaded.disguise.as_deref(), Some(disguise.as_str()));
+        value.as_object_mut().unwrap().remove("disguise");
+        let legacy: Equipment = serde_json::from_value(value).unwrap();
+        assert!(legacy.is_identified());
+
+        for _ in 1..ATTUNE_TURNS {
+            assert_eq!(item.attune(), None);
+        }
+        let reveal = item.attune().unwrap();
+        assert!(reveal.starts_with(&format!("You feel attuned to the {disguise}: it is a ")));
+        assert!(reveal.contains(&item.name));
+        assert!(item.is_identified());
+        assert!(!item.stat_tag().contains("???"));
+        assert_eq!(item.attune(), None);
+    }
+
+    #[test]
+    fn test_elemental_weapons_are_named_after_their_element() {
+        let mut rng = StdRng::seed_from_u64(1544);
+        let weapons: Vec<Equipment> = (0..500)
+            .map(|_| Equipment::generate_with_rarity(5, Rarity::Common, &mut rng))
+            .filter(|item| item.equipment_type == EquipmentType::Weapon)
+            .collect();
+
+        assert!(weapons
+            .iter()
+            .any(|weapon| weapon.damage_type != DamageType::Physical));
+        for weapon in &weapons {
+            match weapon.damage_type.weapon_prefix() {
+                Some(element) => assert!(weapon.name.contains(element), "{}", weapon.name),
+                None => assert_eq!(weapon.name.split(' ').count(), 2),
+            }
+        }
+    }
+
+    #[test]
+    fn test_damage_type_defaults_to_physical_for_old_saves() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let item = Equipment::generate_with_rarity(2, Rarity::Common, &mut rng);
+
+        let mut value = serde_json::to_value(&item).unwrap();
+        value.as_object_mut().unwrap().remove("damage_type");
+        let legacy: Equipment = serde_json::from_value(value).unwrap();
+        assert_eq!(legacy.damage_type, DamageType::Physical);
+    }
+
+    #[test]
+    fn test_wear_warns_at_a_quarter_and_breaks_at_zero() {
+        // Every wear roll succeeds
+        let mut rng = rand::rngs::mock::StepRng::new(0, 0);
+        let mut sword = Equipment::weapon("Iron Sword", 10);
+        sword.durability.current = 11;
+
+        assert_eq!(sword.wear(&mut rng), Some(Wear::BadlyDamaged));
+        assert_eq!(sword.durability.current, 10);
+        assert_eq!(sword.wear(&mut rng), Some(Wear::Worn));
+
+        sword.durability.current = 1;
+        assert_eq!(sword.wear(&mut rng), Some(Wear::Broken));
+        assert!(sword.is_broken());
+        assert_eq!(sword.wear(&mut rng), None);
+
+        // Rolls that miss leave it alone
+        let mut never = rand::rngs::mock::StepRng::new(u64::MAX, 0);
+        let mut shield = Equipment::armor("Buckler", EquipmentSlot::Shield, 3);
+        assert_eq!(shield.wear(&mut never), None);
+        assert_eq!(shield.durability, Durability::full(MAX_DURABILITY));
+    }
+
+    #[test]
+    fn test_power_drops_below_half_durability() {
+        let mut sword = Equipment::weapon("Iron Sword", 10);
+        assert_eq!(sword.effective_power(), 10);
+
+        sword.durability.current = MAX_DURABILITY / 2;
+        assert_eq!(sword.effective_power(), 10);
+        sword.durability.current = MAX_DURABILITY / 4;
+        assert_eq!(sword.effective_power(), 9);
+        sword.durability.current = 0;
+        assert_eq!(sword.effective_power(), 0);
+
+        assert_eq!(sword.repair(100), MAX_DURABILITY);
+        assert_eq!(sword.effective_power(), 10);
+    }
+
+    #[test]
+    fn test_durability_defaults_to_full_for_old_saves() {
+        let mut value = serde_json::to_value(Equipment::weapon("Iron Sword", 4)).unwrap();
+        value.as_object_mut().unwrap().remove("durability");
+        let legacy: Equipment = serde_json::from_value(value).unwrap();
+        assert_eq!(legacy.durability, Durability::full(MAX_DURABILITY));
+    }
+}
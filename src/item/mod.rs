@@ -1,13 +1,17 @@
 pub mod consumable;
+pub mod enchant;
 pub mod equipment;
 
 // Re-exports
 pub use consumable::Consumable;
-pub use equipment::{Equipment, EquipmentSlot};
+pub use equipment::{Durability, Equipment, EquipmentSlot, Rarity};
 
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 
+/// Extra weight toward the better rarity tiers for items found in chests
+pub const CHEST_RARITY_BONUS: u32 = 15;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Item {
     Equipment(Equipment),
@@ -22,41 +26,88 @@ pub enum Item {
 impl Item {
     pub fn name(&self) -> &str {
         match self {
-            Item::Equipment(equipment) => &equipment.name,
+            Item::Equipment(equipment) => equipment.display_name(),
             Item::Consumable(consumable) => &consumable.name,
             Item::Quest { name, .. } => name,
         }
     }
 
-    // Generate a random item with appropriate stats for the given level
-    pub fn generate_random(level: u32) -> Self {
-        let mut rng = rand::thread_rng();
+    /// The name with the item's key numbers, as pickup and loot messages
+    /// show it: "Steel Longsword (+7 ATK)"
+    pub fn name_with_stats(&self) -> String {
+        match self {
+            Item::Equipment(equipment) => {
+                format!("{} ({})", equipment.labelled_name(), equipment.stat_tag())
+            }
+            Item::Consumable(consumable) => {
+                format!("{} ({})", consumable.name, consumable.stat_tag())
+            }
+            Item::Quest { name, .. } => name.clone(),
+        }
+    }
+
+    /// Weight against the player's carry capacity
+    pub fn weight(&self) -> u32 {
+        match self {
+            Item::Equipment(equipment) => equipment.weight(),
+            Item::Consumable(consumable) => consumable.weight(),
+            Item::Quest { .. } => 1,
+        }
+    }
 
+    /// The name inventory lists show, marking unidentified equipment
+    pub fn labelled_name(&self) -> String {
+        match self {
+            Item::Equipment(equipment) => equipment.labelled_name(),
+            _ => self.name().to_string(),
+        }
+    }
+
+    /// Rarity tier for equipment; other items have none
+    pub fn rarity(&self) -> Option<Rarity> {
+        match self {
+            Item::Equipment(equipment) => Some(equipment.rarity),
+            _ => None,
+        }
+    }
+
+    /// Wear for equipment; other items don't wear
+    pub fn durability(&self) -> Option<Durability> {
+        match self {
+            Item::Equipment(equipment) => Some(equipment.durability),
+            _ => None,
+        }
+    }
+
+    // Generate a random item with appropriate stats for the given level
+    pub fn generate_with_rng<R: Rng + ?Sized>(level: u32, rng: &mut R) -> Self {
         // Determine item type (70% equipment, 30% consumable)
         if rng.gen_bool(0.7) {
             // Generate equipment
-            Item::Equipment(Equipment::generate_random(level))
+            Item::Equipment(Equipment::generate_with_rng(level, 0, rng))
         } else {
             // Generate consumable
-            Item::Consumable(Consumable::generate_random(level))
+            Item::Consumable(Consumable::generate_with_rng(level, rng))
         }
     }
 
     /// Generate an item specifically for a chest with guaranteed quality
     /// This helps ensure consistent behavior across all platforms
-    pub fn generate_for_chest(level: u32) -> Self {
-        let mut rng = rand::thread_rng();
-
+    pub fn generate_for_chest<R: Rng + ?Sized>(level: u32, rng: &mut R) -> Self {
         // For chests, slightly bias toward equipment (80%)
         // and ensure higher quality items
         let effective_level = level + 1; // Chests always contain better items
 
         if rng.gen_bool(0.8) {
-            // Equipment with boosted stats for chests
-            Item::Equipment(Equipment::generate_random(effective_level))
+            // Equipment with boosted stats and rarity odds for chests
+            Item::Equipment(Equipment::generate_with_rng(
+                effective_level,
+                CHEST_RARITY_BONUS,
+                rng,
+            ))
         } else {
             // Valuable consumables for chests
-            Item::Consumable(Consumable::generate_random(effective_level))
+            Item::Consumable(Consumable::generate_with_rng(effective_level, rng))
         }
     }
 }
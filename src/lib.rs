@@ -1,8 +1,23 @@
 #![cfg_attr(target_arch = "wasm32", no_main)]
 
+mod achievements;
+mod audio;
+mod base64;
+mod bestiary;
+mod bones;
 mod character;
+mod daily;
+mod effects;
+mod hall_of_fame;
+mod help;
 mod inventory;
 mod item;
+mod log;
+mod map_export;
+mod quest;
+mod render;
+mod settings;
+mod startup;
 mod world;
 
 // Combat module is safe for WASM (no terminal dependencies)
@@ -10,8 +25,14 @@ mod combat;
 
 // Only include terminal-specific modules for non-WASM targets
 #[cfg(not(target_arch = "wasm32"))]
+mod crash;
+#[cfg(not(target_arch = "wasm32"))]
+mod menu;
+#[cfg(not(target_arch = "wasm32"))]
 mod platform;
 #[cfg(not(target_arch = "wasm32"))]
+mod save;
+#[cfg(not(target_arch = "wasm32"))]
 mod ui;
 
 // Game module has conditional compilation internally
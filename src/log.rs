@@ -0,0 +1,227 @@
+//! The game's message history, shared by every front-end
+//!
+//! Everything the game has to tell the player goes into the `GameLog` owned
+//! by `Game`, tagged with the turn it happened on and what kind of message
+//! it is. The log is saved with the game. Each front-end draws its own
+//! window onto it, and takes the entries it hasn't shown yet with
+//! `take_unread` to feed a status area.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Default number of entries kept before the oldest ones are dropped
+pub const DEFAULT_LOG_CAPACITY: usize = 500;
+
+/// What a log message is about, so front-ends can color it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LogCategory {
+    Combat,
+    Loot,
+    #[default]
+    System,
+    Movement,
+    /// An achievement unlocked, shown apart from everything else
+    Achievement,
+    /// A tutorial hint, shown the first time something comes up
+    Hint,
+}
+
+/// A single message in the log, tagged with the turn it was recorded on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub turn: u32,
+    #[serde(default)]
+    pub category: LogCategory,
+    pub text: String,
+    /// Wall-clock time in seconds, used by the GUI to fade old messages
+    pub timestamp: Option<f64>,
+}
+
+/// Bounded message history; the oldest entries are dropped once full
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameLog {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+    current_turn: u32,
+    /// How many of the newest entries no front-end has taken yet
+    #[serde(skip)]
+    unread: usize,
+}
+
+impl GameLog {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_LOG_CAPACITY)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        GameLog {
+            entries: VecDeque::with_capacity(capacity.min(64)),
+            capacity: capacity.max(1),
+            current_turn: 0,
+            unread: 0,
+        }
+    }
+
+    /// Changes how many entries are kept, dropping the oldest if there are
+    /// now too many
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+        self.unread = self.unread.min(self.entries.len());
+    }
+
+    /// Sets the turn number that subsequent messages are tagged with
+    pub fn set_turn(&mut self, turn: u32) {
+        self.current_turn = turn;
+    }
+
+    pub fn push(&mut self, category: LogCategory, text: impl Into<String>) {
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(LogEntry {
+            turn: self.current_turn,
+            category,
+            text: text.into(),
+            timestamp: None,
+        });
+        self.unread = (self.unread + 1).min(self.entries.len());
+    }
+
+    /// Returns the entries pushed since the last call, oldest first
+    #[cfg(any(feature = "gui", target_arch = "wasm32", test))]
+    pub fn take_unread(&mut self) -> Vec<LogEntry> {
+        let start = self.entries.len() - self.unread;
+        self.unread = 0;
+        self.entries.iter().skip(start).cloned().collect()
+    }
+
+    /// Records the wall-clock time on entries that haven't been taken yet,
+    /// so the GUI can fade them as they age
+    #[cfg(feature = "gui")]
+    pub fn stamp_unread(&mut self, timestamp: f64) {
+        let start = self.entries.len() - self.unread;
+        for entry in self.entries.iter_mut().skip(start) {
+            entry.timestamp.get_or_insert(timestamp);
+        }
+    }
+
+    /// The newest `count` entries, oldest first
+    pub fn latest(&self, count: usize) -> impl Iterator<Item = &LogEntry> {
+        self.window(self.len().saturating_sub(count), count)
+    }
+
+    /// Iterates over all entries from oldest to newest
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &LogEntry> + ExactSizeIterator {
+        self.entries.iter()
+    }
+
+    /// Returns up to `count` entries starting at index `start` (oldest first)
+    pub fn window(&self, start: usize, count: usize) -> impl Iterator<Item = &LogEntry> {
+        self.iter().skip(start).take(count)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Default for GameLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_and_iter_order() {
+        let mut log = GameLog::new();
+        log.push(LogCategory::System, "first");
+        log.push(LogCategory::System, "second");
+
+        let texts: Vec<&str> = log.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, vec!["first", "second"]);
+    }
+
+    #[test]
+    fn test_capacity_drops_oldest() {
+        let mut log = GameLog::with_capacity(3);
+        for i in 0..5 {
+            log.push(LogCategory::System, format!("msg {i}"));
+        }
+
+        assert_eq!(log.len(), 3);
+        let texts: Vec<&str> = log.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, vec!["msg 2", "msg 3", "msg 4"]);
+
+        // Shrinking the log drops the oldest entries straight away
+        log.set_capacity(1);
+        assert_eq!(log.len(), 1);
+        assert_eq!(log.iter().next().unwrap().text, "msg 4");
+    }
+
+    #[test]
+    fn test_default_capacity() {
+        let mut log = GameLog::new();
+        for i in 0..(DEFAULT_LOG_CAPACITY + 20) {
+            log.push(LogCategory::System, format!("msg {i}"));
+        }
+
+        assert_eq!(log.len(), DEFAULT_LOG_CAPACITY);
+        assert_eq!(log.iter().next().unwrap().text, "msg 20");
+    }
+
+    #[test]
+    fn test_window() {
+        let mut log = GameLog::with_capacity(10);
+        for i in 0..6 {
+            log.push(LogCategory::System, format!("msg {i}"));
+        }
+
+        let window: Vec<&str> = log.window(2, 3).map(|e| e.text.as_str()).collect();
+        assert_eq!(window, vec!["msg 2", "msg 3", "msg 4"]);
+
+        // Windows running past the end are truncated
+        assert_eq!(log.window(5, 10).count(), 1);
+    }
+
+    #[test]
+    fn test_entries_tagged_with_turn() {
+        let mut log = GameLog::new();
+        log.push(LogCategory::System, "before");
+        log.set_turn(7);
+        log.push(LogCategory::Combat, "after");
+
+        let entries: Vec<&LogEntry> = log.iter().collect();
+        assert_eq!(entries[0].turn, 0);
+        assert_eq!(entries[1].turn, 7);
+        assert_eq!(entries[1].category, LogCategory::Combat);
+    }
+
+    #[test]
+    fn test_take_unread_returns_each_entry_once() {
+        let mut log = GameLog::with_capacity(3);
+        log.push(LogCategory::System, "old");
+        assert_eq!(log.take_unread().len(), 1);
+        assert!(log.take_unread().is_empty());
+
+        // Only the entries still held can be unread
+        for i in 0..5 {
+            log.push(LogCategory::Loot, format!("msg {i}"));
+        }
+        let texts: Vec<String> = log.take_unread().into_iter().map(|e| e.text).collect();
+        assert_eq!(texts, vec!["msg 2", "msg 3", "msg 4"]);
+
+        let latest: Vec<&str> = log.latest(2).map(|e| e.text.as_str()).collect();
+        assert_eq!(latest, vec!["msg 3", "msg 4"]);
+    }
+}
@@ -1,14 +1,38 @@
+mod achievements;
+mod audio;
+mod base64;
+mod bestiary;
+mod bones;
 mod character;
 mod combat;
+mod daily;
+mod effects;
 mod game;
+mod hall_of_fame;
+mod help;
 mod inventory;
 mod item;
+mod log;
+mod map_export;
+mod quest;
+mod render;
+mod settings;
+mod startup;
 mod ui;
 mod world;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod crash;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod menu;
+
 #[cfg(not(target_arch = "wasm32"))]
 mod platform;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod save;
+
 #[cfg(feature = "gui")]
 mod gui;
 
@@ -18,22 +42,42 @@ mod input;
 #[cfg(target_arch = "wasm32")]
 mod web;
 
+#[cfg(not(target_arch = "wasm32"))]
+use startup::{Frontend, StartupOptions};
+
 #[cfg(not(target_arch = "wasm32"))]
 fn main() {
-    // Check if GUI feature is enabled and we're on Windows
-    #[cfg(all(feature = "gui", target_os = "windows"))]
-    {
-        // Run GUI version on Windows when feature is enabled
-        if let Err(e) = gui::run_gui() {
+    // Bad arguments are reported before the terminal is touched
+    let options = match StartupOptions::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("Error: {e}\n");
+            eprintln!("{}", startup::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.show_help {
+        println!("{}", startup::USAGE);
+        return;
+    }
+
+    // The GUI is the default whenever it is compiled in
+    #[cfg(feature = "gui")]
+    if options.frontend != Some(Frontend::Terminal) {
+        if let Err(e) = gui::run_gui(options) {
             eprintln!("Failed to run GUI: {e}");
             std::process::exit(1);
         }
+        return;
     }
-    #[cfg(not(all(feature = "gui", target_os = "windows")))]
-    {
-        // Fall back to terminal version
-        run_terminal_version();
+
+    #[cfg(not(feature = "gui"))]
+    if options.frontend == Some(Frontend::Gui) {
+        eprintln!("This build does not include the GUI (rebuild with --features gui).");
+        eprintln!("Starting the terminal version instead.");
     }
+
+    run_terminal_version(&options);
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -41,11 +85,8 @@ fn main() {
     // WASM entry point is handled in web.rs
 }
 
-#[cfg(all(
-    not(target_arch = "wasm32"),
-    not(all(feature = "gui", target_os = "windows"))
-))]
-fn run_terminal_version() {
+#[cfg(not(target_arch = "wasm32"))]
+fn run_terminal_version(options: &StartupOptions) {
     // Check if running in a compatible terminal
     if !platform::is_terminal_compatible() {
         eprintln!("Error: This game requires a terminal environment to run.");
@@ -65,9 +106,15 @@ fn run_terminal_version() {
     }
 
     // Check terminal size
-    if !platform::is_terminal_size_adequate() {
+    let adequate = match options.min_terminal_size {
+        Some(min_size) => platform::is_terminal_size_at_least(min_size),
+        None => platform::is_terminal_size_adequate(),
+    };
+    if !adequate {
         let (current_w, current_h) = platform::get_terminal_size();
-        let (rec_w, rec_h) = platform::get_recommended_size();
+        let (rec_w, rec_h) = options
+            .min_terminal_size
+            .unwrap_or_else(platform::get_recommended_size);
         eprintln!(
             "Warning: Terminal size ({current_w}, {current_h}) is smaller than recommended ({rec_w}, {rec_h})"
         );
@@ -95,9 +142,12 @@ fn run_terminal_version() {
         std::process::exit(1);
     }
 
+    // Panics go to the crash log rather than over the game screen
+    crash::install_panic_hook();
+
     // Run the game
     let result = std::panic::catch_unwind(|| {
-        game::run();
+        game::run(options);
     });
 
     // Ensure cleanup happens even if game panics
@@ -107,7 +157,14 @@ fn run_terminal_version() {
 
     // Handle any panics that occurred
     if let Err(panic) = result {
-        eprintln!("Game crashed: {panic:?}");
+        eprintln!("Game crashed: {}", crash::panic_message(panic.as_ref()));
+        if let Some(crash) = panic.downcast_ref::<crash::RecoveredCrash>() {
+            eprintln!(
+                "Your progress was saved to {}; restore it the next time you start the game.",
+                crash.saved_to.display()
+            );
+        }
+        eprintln!("Details were written to {}", crash::log_path().display());
         std::process::exit(1);
     }
 }
@@ -0,0 +1,275 @@
+//! Writing the current level's map out to share or keep
+//!
+//! The export draws only what the player has explored, with the same glyph
+//! table the game draws the map with, so it never gives away anything the
+//! player hasn't seen. Enemies and loot are left off: it's the layout that's
+//! worth keeping. A header says whose map it is and where it came from.
+//! With the `png-export` feature a picture of it is written alongside, a
+//! block of color for each tile.
+
+use crate::game::Game;
+use crate::world::{Level, MapFeature, TileAppearance};
+
+/// Width and height, in pixels, of one tile in an exported picture
+#[cfg(feature = "png-export")]
+pub const PNG_TILE_PIXELS: u32 = 8;
+
+/// The explored part of `level`, a row of ASCII glyphs a line with the
+/// player marked; unexplored tiles are blank and the map is cropped to
+/// what's been explored
+pub fn map_rows(level: &Level) -> Vec<String> {
+    let Some((left, top, right, bottom)) = explored_bounds(level) else {
+        return Vec::new();
+    };
+    (top..=bottom)
+        .map(|y| {
+            let row: String = (left..=right)
+                .map(|x| match feature_at(level, x, y) {
+                    Some(feature) => TileAppearance::of(feature).ascii,
+                    None => ' ',
+                })
+                .collect();
+            row.trim_end().to_string()
+        })
+        .collect()
+}
+
+/// The whole export: a header naming the dungeon, level, seed, date and
+/// player, then the map
+pub fn map_text(game: &Game, date: &str) -> String {
+    let seed = game
+        .world_seed
+        .map_or_else(|| "random".to_string(), |seed| seed.to_string());
+    let mut lines = vec![
+        format!(
+            "{}, level {}",
+            game.current_dungeon().name,
+            game.current_level().level_num
+        ),
+        format!("Seed: {seed}"),
+        format!("Date: {date}"),
+        format!(
+            "Explorer: {} the {}",
+            game.player.name, game.player.class.class_type
+        ),
+        String::new(),
+    ];
+    lines.extend(map_rows(game.current_level()));
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+/// A file name for the export of the level the player is on:
+/// "map-dark-cave-3.txt"
+pub fn file_name(game: &Game, extension: &str) -> String {
+    let dungeon: String = game
+        .current_dungeon()
+        .name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let dungeon = dungeon
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    format!(
+        "map-{dungeon}-{}.{extension}",
+        game.current_level().level_num
+    )
+}
+
+/// What's drawn at (`x`, `y`): the player, an explored tile or nothing
+fn feature_at(level: &Level, x: usize, y: usize) -> Option<MapFeature> {
+    let tile = &level.tiles[y][x];
+    if !tile.explored {
+        return None;
+    }
+    if level.player_position.x == x as i32 && level.player_position.y == y as i32 {
+        Some(MapFeature::Player)
+    } else {
+        Some(MapFeature::Tile(tile.tile_type))
+    }
+}
+
+/// Leftmost, topmost, rightmost and bottommost explored columns and rows
+fn explored_bounds(level: &Level) -> Option<(usize, usize, usize, usize)> {
+    let mut bounds: Option<(usize, usize, usize, usize)> = None;
+    for (y, row) in level.tiles.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            if !tile.explored {
+                continue;
+            }
+            bounds = Some(match bounds {
+                None => (x, y, x, y),
+                Some((left, top, right, bottom)) => {
+                    (left.min(x), top.min(y), right.max(x), bottom.max(y))
+                }
+            });
+        }
+    }
+    bounds
+}
+
+/// Writes map exports into the data directory
+#[cfg(not(target_arch = "wasm32"))]
+pub struct MapExporter {
+    dir: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MapExporter {
+    /// Writes exports into `dir`
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> Self {
+        MapExporter { dir: dir.into() }
+    }
+
+    /// Writes the current level's map, returning the path of the text file
+    /// (the picture, when there is one, sits beside it)
+    pub fn export(&self, game: &Game) -> anyhow::Result<std::path::PathBuf> {
+        use anyhow::Context;
+
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Could not create {}", self.dir.display()))?;
+        let path = self.dir.join(file_name(game, "txt"));
+        let text = map_text(game, &crate::hall_of_fame::today());
+        std::fs::write(&path, text)
+            .with_context(|| format!("Could not write {}", path.display()))?;
+        #[cfg(feature = "png-export")]
+        {
+            let picture = self.dir.join(file_name(game, "png"));
+            write_png(game.current_level(), &picture)
+                .with_context(|| format!("Could not write {}", picture.display()))?;
+        }
+        Ok(path)
+    }
+
+    /// The message log line for an export of `game`
+    pub fn export_message(&self, game: &Game) -> String {
+        match self.export(game) {
+            Ok(path) => format!("Map exported to {}", path.display()),
+            Err(e) => format!("Map not exported: {e:#}"),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl crate::save::DataFile for MapExporter {
+    fn in_dir(dir: std::path::PathBuf) -> Self {
+        Self::new(dir)
+    }
+}
+
+/// Draws the explored part of `level` as a picture, each tile a block of
+/// its in-game color, and saves it as a PNG at `path`
+#[cfg(all(feature = "png-export", not(target_arch = "wasm32")))]
+fn write_png(level: &Level, path: &std::path::Path) -> anyhow::Result<()> {
+    let width = level.width as u32 * PNG_TILE_PIXELS;
+    let height = level.height as u32 * PNG_TILE_PIXELS;
+    let picture = image::RgbImage::from_fn(width, height, |px, py| {
+        let (x, y) = (
+            (px / PNG_TILE_PIXELS) as usize,
+            (py / PNG_TILE_PIXELS) as usize,
+        );
+        match feature_at(level, x, y) {
+            Some(feature) => {
+                let color = TileAppearance::of(feature).color;
+                image::Rgb([color.r, color.g, color.b])
+            }
+            None => image::Rgb([0, 0, 0]),
+        }
+    });
+    picture.save(path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::{ClassType, Player};
+    #[cfg(not(target_arch = "wasm32"))]
+    use crate::save::TempDir;
+    use crate::world::{Position, Tile, TileType};
+
+    /// A game whose current level is a walled room with only its left part
+    /// explored
+    fn explored_game() -> Game {
+        let mut game = Game::new(Player::new("Tester".to_string(), ClassType::Ranger));
+        let mut level = Level::new(8, 5);
+        for y in 1..4 {
+            for x in 1..7 {
+                level.tiles[y][x] = Tile::floor();
+            }
+        }
+        level.tiles[2][6] = Tile::new(TileType::StairsDown);
+        level.tiles[1][2] = Tile::chest();
+        level.player_position = Position::new(1, 2);
+        for row in level.tiles.iter_mut().take(4) {
+            for tile in row.iter_mut().take(4) {
+                tile.explored = true;
+            }
+        }
+        let dungeon = game.current_dungeon_index;
+        let current = game.dungeons[dungeon].current_level;
+        game.dungeons[dungeon].levels[current] = level;
+        game
+    }
+
+    #[test]
+    fn test_only_the_explored_part_is_drawn() {
+        let game = explored_game();
+        assert_eq!(
+            map_rows(game.current_level()),
+            ["####", "#.C.", "#@..", "#..."]
+        );
+
+        // The stairs turn up once they've been explored, and not before
+        let mut game = game;
+        game.current_level_mut().tiles[2][6].explored = true;
+        assert_eq!(map_rows(game.current_level())[2], "#@..  >");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_the_export_is_headed_and_written_to_the_data_dir() {
+        let mut game = explored_game();
+        game.world_seed = Some(99);
+        let text = map_text(&game, "2024-06-01");
+        let dungeon = game.current_dungeon().name.clone();
+        let level = game.current_level().level_num;
+        assert!(text.starts_with(&format!(
+            "{dungeon}, level {level}\nSeed: 99\nDate: 2024-06-01\nExplorer: Tester the Ranger\n\n####\n"
+        )));
+
+        let dir = TempDir::new("map");
+        let exporter = MapExporter::new(dir.path());
+        let path = exporter.export(&game).unwrap();
+        assert!(path.starts_with(dir.path()));
+        assert!(path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("map-"));
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.ends_with("####\n#.C.\n#@..\n#...\n"));
+        assert!(exporter
+            .export_message(&game)
+            .starts_with("Map exported to "));
+    }
+
+    #[test]
+    fn test_file_names_are_plain() {
+        let mut game = explored_game();
+        let dungeon = game.current_dungeon_index;
+        game.dungeons[dungeon].name = "The Goblin's  Warren".to_string();
+        game.current_level_mut().level_num = 3;
+        assert_eq!(file_name(&game, "txt"), "map-the-goblin-s-warren-3.txt");
+    }
+}
@@ -0,0 +1,159 @@
+//! Keyboard selection for menus and lists
+//!
+//! A `MenuSelector` keeps the highlighted row of a list. The arrow keys move
+//! the highlight, Enter picks it and the number keys still pick an entry
+//! directly, so every menu works without the number row and lists longer
+//! than nine entries can be reached.
+
+use crossterm::event::KeyCode;
+
+/// What a key did to a menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuInput {
+    /// The highlight moved; the menu should be redrawn
+    Moved,
+    /// The entry at this index was picked
+    Chosen(usize),
+    /// Escape: leave the menu without picking anything
+    Cancelled,
+    /// A key the menu doesn't use, left to the screen
+    Ignored,
+}
+
+/// The highlighted entry of a list of `len` entries
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MenuSelector {
+    selected: usize,
+    len: usize,
+}
+
+impl MenuSelector {
+    pub fn new(len: usize) -> Self {
+        Self { selected: 0, len }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn is_selected(&self, index: usize) -> bool {
+        index == self.selected
+    }
+
+    /// Prefix for row `index`: an arrow on the highlighted one
+    pub fn marker(&self, index: usize) -> &'static str {
+        if self.is_selected(index) {
+            "> "
+        } else {
+            "  "
+        }
+    }
+
+    /// Follows a list that grew or shrank, keeping the highlight on it
+    pub fn set_len(&mut self, len: usize) {
+        self.len = len;
+        self.selected = self.selected.min(len.saturating_sub(1));
+    }
+
+    /// Moves the highlight back to the first entry
+    pub fn reset(&mut self) {
+        self.selected = 0;
+    }
+
+    /// Moves the highlight up, wrapping from the first entry to the last
+    pub fn up(&mut self) -> MenuInput {
+        if self.len == 0 {
+            return MenuInput::Ignored;
+        }
+        self.selected = (self.selected + self.len - 1) % self.len;
+        MenuInput::Moved
+    }
+
+    /// Moves the highlight down, wrapping from the last entry to the first
+    pub fn down(&mut self) -> MenuInput {
+        if self.len == 0 {
+            return MenuInput::Ignored;
+        }
+        self.selected = (self.selected + 1) % self.len;
+        MenuInput::Moved
+    }
+
+    /// Picks the highlighted entry
+    pub fn confirm(&self) -> MenuInput {
+        if self.len == 0 {
+            MenuInput::Ignored
+        } else {
+            MenuInput::Chosen(self.selected)
+        }
+    }
+
+    /// Picks entry `number`, counted from 1 like the keys; other numbers
+    /// are ignored
+    pub fn choose_number(&mut self, number: usize) -> MenuInput {
+        match number.checked_sub(1).filter(|&index| index < self.len) {
+            Some(index) => {
+                self.selected = index;
+                MenuInput::Chosen(index)
+            }
+            None => MenuInput::Ignored,
+        }
+    }
+
+    /// What a terminal key does to the menu
+    pub fn handle_key(&mut self, code: KeyCode) -> MenuInput {
+        match code {
+            KeyCode::Up => self.up(),
+            KeyCode::Down => self.down(),
+            KeyCode::Enter => self.confirm(),
+            KeyCode::Esc => MenuInput::Cancelled,
+            KeyCode::Char(c) => match c.to_digit(10) {
+                Some(digit) => self.choose_number(digit as usize),
+                None => MenuInput::Ignored,
+            },
+            _ => MenuInput::Ignored,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arrows_wrap_and_enter_picks_the_highlight() {
+        let mut menu = MenuSelector::new(3);
+        assert_eq!(menu.handle_key(KeyCode::Up), MenuInput::Moved);
+        assert_eq!(menu.selected(), 2);
+        assert_eq!(menu.handle_key(KeyCode::Down), MenuInput::Moved);
+        assert_eq!(menu.handle_key(KeyCode::Down), MenuInput::Moved);
+        assert_eq!(menu.handle_key(KeyCode::Enter), MenuInput::Chosen(1));
+        assert_eq!(menu.marker(1), "> ");
+        assert_eq!(menu.marker(0), "  ");
+        assert_eq!(menu.handle_key(KeyCode::Esc), MenuInput::Cancelled);
+    }
+
+    #[test]
+    fn test_number_keys_still_pick_directly() {
+        let mut menu = MenuSelector::new(3);
+        assert_eq!(menu.handle_key(KeyCode::Char('3')), MenuInput::Chosen(2));
+        assert_eq!(menu.selected(), 2);
+        assert_eq!(menu.handle_key(KeyCode::Char('4')), MenuInput::Ignored);
+        assert_eq!(menu.handle_key(KeyCode::Char('0')), MenuInput::Ignored);
+        assert_eq!(menu.handle_key(KeyCode::Char('x')), MenuInput::Ignored);
+    }
+
+    #[test]
+    fn test_long_lists_are_reachable_and_shrinking_keeps_the_highlight() {
+        let mut menu = MenuSelector::new(12);
+        for _ in 0..11 {
+            menu.down();
+        }
+        assert_eq!(menu.confirm(), MenuInput::Chosen(11));
+
+        menu.set_len(4);
+        assert_eq!(menu.selected(), 3);
+        menu.set_len(0);
+        assert_eq!(menu.confirm(), MenuInput::Ignored);
+        assert_eq!(menu.down(), MenuInput::Ignored);
+    }
+}
@@ -4,25 +4,19 @@
 //! instead of platform-specific APIs, ensuring consistent behavior across
 //! Windows, macOS, and Linux.
 
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 use anyhow::{Context, Result};
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 use crossterm::{
     cursor, execute,
     style::Color,
     terminal::{self, Clear, ClearType},
 };
-#[cfg(all(windows, not(all(feature = "gui", target_os = "windows"))))]
+#[cfg(windows)]
 use std::env;
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 use std::io::stdout;
-#[cfg(all(windows, not(all(feature = "gui", target_os = "windows"))))]
+#[cfg(windows)]
 use std::process::Command;
-#[cfg(all(windows, not(all(feature = "gui", target_os = "windows"))))]
-use std::time::{Duration, Instant};
 
 /// Initialize cross-platform terminal settings
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 pub fn init_terminal() -> Result<()> {
     // Enable raw mode for input handling
     terminal::enable_raw_mode().context("Failed to enable raw mode")?;
@@ -38,7 +32,6 @@ pub fn init_terminal() -> Result<()> {
 }
 
 /// Cleanup terminal state
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 pub fn cleanup_terminal() -> Result<()> {
     // Show cursor
     execute!(stdout(), cursor::Show).context("Failed to show cursor")?;
@@ -54,7 +47,6 @@ pub fn cleanup_terminal() -> Result<()> {
 }
 
 /// Get terminal size with fallback defaults
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 pub fn get_terminal_size() -> (u16, u16) {
     match terminal::size() {
         Ok((width, height)) => {
@@ -71,7 +63,6 @@ pub fn get_terminal_size() -> (u16, u16) {
 }
 
 /// Clear the terminal screen in a cross-platform way
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 pub fn clear_screen() -> Result<()> {
     #[cfg(windows)]
     {
@@ -92,7 +83,6 @@ pub fn clear_screen() -> Result<()> {
 }
 
 /// Check if the current terminal supports the features we need
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 pub fn check_terminal_compatibility() -> Result<()> {
     // Check if we can get terminal size
     terminal::size().context("Terminal does not support size detection")?;
@@ -105,7 +95,6 @@ pub fn check_terminal_compatibility() -> Result<()> {
 }
 
 /// Platform-specific error handling with helpful messages
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 pub fn handle_error(error: &anyhow::Error) -> String {
     let platform_info = get_platform_info();
 
@@ -118,7 +107,6 @@ pub fn handle_error(error: &anyhow::Error) -> String {
 }
 
 /// Get current platform information
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 pub fn get_platform_info() -> String {
     format!(
         "{} {} ({})",
@@ -129,7 +117,6 @@ pub fn get_platform_info() -> String {
 }
 
 /// Get platform-specific troubleshooting tips
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 fn get_troubleshooting_tips() -> &'static str {
     #[cfg(windows)]
     return "• Use Windows Terminal or PowerShell for best experience\n\
@@ -156,7 +143,6 @@ fn get_troubleshooting_tips() -> &'static str {
 }
 
 /// Display welcome message with platform-specific formatting
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 pub fn show_welcome_message() -> Result<()> {
     clear_screen()?;
 
@@ -203,7 +189,6 @@ pub fn show_welcome_message() -> Result<()> {
 }
 
 /// Normalize key events across platforms
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 pub fn normalize_key_event(key_event: crossterm::event::KeyEvent) -> crossterm::event::KeyEvent {
     #[cfg(windows)]
     {
@@ -226,32 +211,8 @@ pub fn normalize_key_event(key_event: crossterm::event::KeyEvent) -> crossterm::
     }
 }
 
-/// Windows-specific frame rate limiting to improve performance
-#[cfg(windows)]
-#[cfg(all(windows, not(all(feature = "gui", target_os = "windows"))))]
-static mut LAST_FRAME_TIME: Option<Instant> = None;
-
-/// Limit frame rate on Windows to reduce terminal load
-#[cfg(all(windows, not(all(feature = "gui", target_os = "windows"))))]
-pub fn windows_frame_limit() {
-    const TARGET_FRAME_TIME: Duration = Duration::from_millis(16); // ~60 FPS
-
-    unsafe {
-        let now = Instant::now();
-
-        if let Some(last_frame) = LAST_FRAME_TIME {
-            let elapsed = now.duration_since(last_frame);
-            if elapsed < TARGET_FRAME_TIME {
-                std::thread::sleep(TARGET_FRAME_TIME - elapsed);
-            }
-        }
-
-        LAST_FRAME_TIME = Some(Instant::now());
-    }
-}
-
 /// Set Command Prompt to full screen mode
-#[cfg(all(windows, not(all(feature = "gui", target_os = "windows"))))]
+#[cfg(windows)]
 pub fn set_cmd_fullscreen() -> Result<()> {
     if is_command_prompt() {
         // Method 1: Resize console buffer and window
@@ -308,7 +269,7 @@ pub fn set_cmd_fullscreen() -> Result<()> {
 }
 
 /// Detect if running in Command Prompt (cmd.exe) for specialized optimizations
-#[cfg(all(windows, not(all(feature = "gui", target_os = "windows"))))]
+#[cfg(windows)]
 pub fn is_command_prompt() -> bool {
     // Check COMSPEC environment variable and terminal capabilities
     if let Ok(comspec) = env::var("COMSPEC") {
@@ -337,7 +298,7 @@ pub fn is_command_prompt() -> bool {
 }
 
 /// Get optimized color palette for Command Prompt
-#[cfg(all(windows, not(all(feature = "gui", target_os = "windows"))))]
+#[cfg(windows)]
 pub fn get_cmd_color_palette() -> Vec<(Color, Color)> {
     // Simplified color palette that works well in Command Prompt
     vec![
@@ -351,25 +312,7 @@ pub fn get_cmd_color_palette() -> Vec<(Color, Color)> {
     ]
 }
 
-/// Command Prompt specific frame limiting (more aggressive)
-#[cfg(all(windows, not(all(feature = "gui", target_os = "windows"))))]
-pub fn cmd_frame_limit() {
-    const CMD_TARGET_FRAME_TIME: Duration = Duration::from_millis(33); // ~30 FPS for cmd
-
-    unsafe {
-        let now = Instant::now();
-        if let Some(last_frame) = LAST_FRAME_TIME {
-            let elapsed = now.duration_since(last_frame);
-            if elapsed < CMD_TARGET_FRAME_TIME {
-                std::thread::sleep(CMD_TARGET_FRAME_TIME - elapsed);
-            }
-        }
-        LAST_FRAME_TIME = Some(Instant::now());
-    }
-}
-
 /// Check if running in a compatible terminal environment
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 pub fn is_terminal_compatible() -> bool {
     // Check if stdout is a TTY
     if !atty::is(atty::Stream::Stdout) {
@@ -385,16 +328,19 @@ pub fn is_terminal_compatible() -> bool {
 }
 
 /// Get recommended terminal size for optimal gameplay
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 pub fn get_recommended_size() -> (u16, u16) {
     (150, 50) // Width x Height in characters
 }
 
 /// Check if terminal size is adequate
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 pub fn is_terminal_size_adequate() -> bool {
+    is_terminal_size_at_least((140, 45))
+}
+
+/// Whether the terminal has at least `min_size` columns and rows
+pub fn is_terminal_size_at_least(min_size: (u16, u16)) -> bool {
     let (current_width, current_height) = get_terminal_size();
-    let (min_width, min_height) = (140, 45);
+    let (min_width, min_height) = min_size;
 
     current_width >= min_width && current_height >= min_height
 }
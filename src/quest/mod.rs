@@ -0,0 +1,224 @@
+//! Fetch quests: each dungeon hides one quest item in a chest on a deeper
+//! level, and carrying it out through the exit pays a bonus reward.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::item::Item;
+use crate::world::Dungeon;
+
+/// Names of the artifacts a fetch quest can ask for
+const QUEST_ARTIFACTS: [(&str, &str); 5] = [
+    (
+        "Sunstone",
+        "A warm amber gem that glows with captured daylight.",
+    ),
+    (
+        "Moonpearl Amulet",
+        "A pale pendant that hums softly in the dark.",
+    ),
+    (
+        "Codex of Echoes",
+        "A crumbling tome whose pages whisper when opened.",
+    ),
+    (
+        "Ancestral Signet",
+        "A heavy ring bearing the crest of a fallen house.",
+    ),
+    (
+        "Heart of the Mountain",
+        "A fist-sized crystal pulsing with deep magic.",
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuestStatus {
+    /// Accepted, item not yet found
+    Active,
+    /// The quest item is in the player's inventory
+    ItemFound,
+    /// Turned in at the dungeon exit
+    Completed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Quest {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    /// Id of the `Item::Quest` that must be recovered
+    pub item_id: String,
+    pub item_name: String,
+    pub dungeon_index: usize,
+    /// 1-based level number the item is hidden on
+    pub target_level: u32,
+    pub status: QuestStatus,
+    pub reward_experience: u32,
+    pub reward_gold: u32,
+}
+
+impl Quest {
+    /// Creates a fetch quest for a dungeon and hides its item in a chest on
+    /// one of the deeper levels. Returns `None` if no chest could be placed.
+    pub fn generate_for_dungeon<R: Rng + ?Sized>(
+        dungeon: &mut Dungeon,
+        dungeon_index: usize,
+        rng: &mut R,
+    ) -> Option<Quest> {
+        let (item_name, item_description) =
+            QUEST_ARTIFACTS[rng.gen_range(0..QUEST_ARTIFACTS.len())];
+
+        // Never the first level, so the player has to go looking for it
+        let first_candidate = 1.min(dungeon.levels.len() - 1);
+        let level_index = rng.gen_range(first_candidate..dungeon.levels.len());
+        let target_level = level_index as u32 + 1;
+
+        let id = format!(
+            "quest-{dungeon_index}-{}",
+            item_name.to_lowercase().replace(' ', "-")
+        );
+        let item = Item::Quest {
+            id: id.clone(),
+            name: item_name.to_string(),
+            description: item_description.to_string(),
+        };
+
+        dungeon.levels[level_index].place_in_chest(item, rng)?;
+
+        Some(Quest {
+            title: format!("Recover the {item_name} from level {target_level}"),
+            description: format!(
+                "The {item_name} lies somewhere in the {}. Find it and bring it out through the exit.",
+                dungeon.name
+            ),
+            item_id: id.clone(),
+            item_name: item_name.to_string(),
+            id,
+            dungeon_index,
+            target_level,
+            status: QuestStatus::Active,
+            reward_experience: 150 * dungeon.difficulty + 50 * dungeon.levels.len() as u32,
+            reward_gold: 100 * dungeon.difficulty,
+        })
+    }
+
+    /// Creates the errand a Ghostly Scholar met on level `level_index`
+    /// sends the player on: their folio, hidden in a chest on the level
+    /// below, or on theirs if it's the last. Returns `None` if no chest
+    /// could be placed.
+    pub fn scholar_errand<R: Rng + ?Sized>(
+        dungeon: &mut Dungeon,
+        dungeon_index: usize,
+        level_index: usize,
+        rng: &mut R,
+    ) -> Option<Quest> {
+        let folio_level = (level_index + 1).min(dungeon.levels.len() - 1);
+        let target_level = folio_level as u32 + 1;
+        let id = format!("errand-{dungeon_index}-{level_index}");
+        let item_name = "Scholar's Folio";
+        let item = Item::Quest {
+            id: id.clone(),
+            name: item_name.to_string(),
+            description: "A ghost's notes, the ink faded almost to nothing.".to_string(),
+        };
+
+        dungeon.levels[folio_level].place_in_chest(item, rng)?;
+
+        Some(Quest {
+            title: format!("Find the scholar's folio on level {target_level}"),
+            description: format!(
+                "A Ghostly Scholar lost their folio on level {target_level} of the {}. Carry it out through the exit so their work isn't lost.",
+                dungeon.name
+            ),
+            item_id: id.clone(),
+            item_name: item_name.to_string(),
+            id,
+            dungeon_index,
+            target_level,
+            status: QuestStatus::Active,
+            reward_experience: 50 * dungeon.difficulty + 25 * target_level,
+            reward_gold: 40 * dungeon.difficulty,
+        })
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.status == QuestStatus::Completed
+    }
+
+    /// Whether `item` is the artifact this quest asks for
+    pub fn matches_item(&self, item: &Item) -> bool {
+        matches!(item, Item::Quest { id, .. } if *id == self.item_id)
+    }
+
+    pub fn status_label(&self) -> &'static str {
+        match self.status {
+            QuestStatus::Active => "Active",
+            QuestStatus::ItemFound => "Item found - reach the exit",
+            QuestStatus::Completed => "Completed",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{DungeonType, TileType};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn test_dungeon() -> Dungeon {
+        Dungeon::new("Test Ruins".to_string(), DungeonType::Ruins, 1, 3)
+    }
+
+    #[test]
+    fn test_quest_item_hidden_in_deeper_chest() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut dungeon = test_dungeon();
+        let quest = Quest::generate_for_dungeon(&mut dungeon, 0, &mut rng).unwrap();
+
+        assert!(quest.target_level >= 2);
+        assert_eq!(quest.status, QuestStatus::Active);
+
+        let level = &dungeon.levels[quest.target_level as usize - 1];
+        let (pos, _) = level
+            .chest_contents
+            .iter()
+            .find(|(_, chest)| chest.items.iter().any(|item| quest.matches_item(item)))
+            .expect("quest item should be placed on the target level");
+        assert_eq!(
+            level.tiles[pos.y as usize][pos.x as usize].tile_type,
+            TileType::Chest
+        );
+    }
+
+    #[test]
+    fn test_scholar_errands_lead_one_level_down() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut dungeon = test_dungeon();
+        let errand = Quest::scholar_errand(&mut dungeon, 0, 0, &mut rng).unwrap();
+        assert_eq!(errand.target_level, 2);
+        assert!(dungeon.levels[1]
+            .chest_contents
+            .values()
+            .any(|chest| chest.items.iter().any(|item| errand.matches_item(item))));
+
+        // Met on the last level, the folio is on that one
+        let last = Quest::scholar_errand(&mut dungeon, 0, 2, &mut rng).unwrap();
+        assert_eq!(last.target_level, 3);
+        assert_ne!(last.id, errand.id);
+    }
+
+    #[test]
+    fn test_quest_serialization_roundtrip() {
+        let mut rng = StdRng::seed_from_u64(9);
+        let mut dungeon = test_dungeon();
+        let mut quest = Quest::generate_for_dungeon(&mut dungeon, 0, &mut rng).unwrap();
+        quest.status = QuestStatus::ItemFound;
+
+        let json = serde_json::to_string(&quest).unwrap();
+        let loaded: Quest = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.id, quest.id);
+        assert_eq!(loaded.status, QuestStatus::ItemFound);
+        assert_eq!(loaded.target_level, quest.target_level);
+    }
+}
@@ -0,0 +1,1083 @@
+//! Presentation-agnostic view of the game state.
+//!
+//! The terminal, GUI and web front-ends all draw the same map, legend, stat
+//! panel and combat screen. This module builds those as plain data so each
+//! front-end only has to turn them into crossterm, egui or canvas calls, and
+//! the glyphs, colors and wording stay the same everywhere.
+
+use crate::character::{Buff, Companion, Player};
+use crate::combat::{forecast, CombatContext};
+use crate::world::appearance::TILE_APPEARANCES;
+use crate::world::fog_factory::create_standard_fog_of_war;
+use crate::world::fog_of_war::FogColor;
+use crate::world::weather;
+use crate::world::{
+    AmbientEffect, AmbientLight, Dungeon, Enemy, GlyphSet, Level, MapFeature, Position,
+    TileAppearance,
+};
+use serde::{Deserialize, Serialize};
+
+/// A character and the color it is drawn in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Glyph {
+    pub symbol: char,
+    pub color: FogColor,
+    /// Fill behind the character, or None for the screen's own background
+    pub background: Option<FogColor>,
+}
+
+impl Glyph {
+    fn from_appearance(appearance: &TileAppearance, set: GlyphSet) -> Self {
+        Glyph {
+            symbol: appearance.symbol(set),
+            color: appearance.color,
+            background: None,
+        }
+    }
+}
+
+/// Dark red fill behind the tiles an enemy can reach, while the threat
+/// overlay is on
+pub const THREAT_TINT: FogColor = FogColor {
+    r: 90,
+    g: 0,
+    b: 0,
+    a: 255,
+};
+
+/// How a map feature looks in plain view, drawn from `set`
+pub fn glyph(feature: MapFeature, set: GlyphSet) -> Glyph {
+    Glyph::from_appearance(TileAppearance::of(feature), set)
+}
+
+/// One symbol explained in the map legend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LegendEntry {
+    pub glyph: Glyph,
+    pub label: &'static str,
+}
+
+/// Every symbol that can appear on the map as drawn from `set`, in display
+/// order
+pub fn legend(set: GlyphSet) -> Vec<LegendEntry> {
+    TILE_APPEARANCES
+        .iter()
+        .map(|appearance| LegendEntry {
+            glyph: Glyph::from_appearance(appearance, set),
+            label: appearance.label,
+        })
+        .collect()
+}
+
+/// A glyph placed at a column and row of the map view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapCell {
+    pub glyph: Glyph,
+    pub x: usize,
+    pub y: usize,
+}
+
+/// The part of a level shown on screen, with fog of war applied. Cells
+/// that should stay blank are left out.
+#[derive(Debug, Clone)]
+pub struct MapFrame {
+    pub cells: Vec<MapCell>,
+    /// The map position shown in the top-left cell
+    origin: Position,
+}
+
+impl MapFrame {
+    /// A view of the given size with the player in the middle
+    pub fn centered(level: &Level, width: usize, height: usize, glyphs: GlyphSet) -> Self {
+        let origin = view_origin(level.player_position, width, height);
+        Self::from_origin(level, origin, width, height, glyphs)
+    }
+
+    /// A view of the given size whose top-left cell shows `origin`, jolted
+    /// sideways while the ground shakes
+    pub fn from_origin(
+        level: &Level,
+        origin: Position,
+        width: usize,
+        height: usize,
+        glyphs: GlyphSet,
+    ) -> Self {
+        let fog_of_war = create_standard_fog_of_war();
+        let mut cells = Vec::new();
+        let origin = Position::new(origin.x - weather::shake_offset(level) as i32, origin.y);
+
+        for y in 0..height {
+            for x in 0..width {
+                let pos = Position::new(origin.x + x as i32, origin.y + y as i32);
+                let result = fog_of_war.process_position(level, pos, level.player_position, glyphs);
+                if result.should_render {
+                    let glyph = Glyph {
+                        symbol: result.character,
+                        color: result.color.unwrap_or(FogColor::BLACK),
+                        background: None,
+                    };
+                    cells.push(MapCell { glyph, x, y });
+                }
+            }
+        }
+
+        Self { cells, origin }
+    }
+
+    /// Tints the cells an enemy in sight could hit the player on, so the
+    /// player can see which steps are safe
+    pub fn show_threats(&mut self, level: &Level) {
+        let threatened = level.threatened_tiles(true);
+        for cell in &mut self.cells {
+            let pos = Position::new(self.origin.x + cell.x as i32, self.origin.y + cell.y as i32);
+            if threatened.contains(&pos) {
+                cell.glyph.background = Some(THREAT_TINT);
+            }
+        }
+    }
+}
+
+/// Top-left map position of a view of the given size centered on `center`
+pub fn view_origin(center: Position, width: usize, height: usize) -> Position {
+    Position::new(center.x - width as i32 / 2, center.y - height as i32 / 2)
+}
+
+/// Fewest map cells a resizable view shows across
+pub const MIN_VIEW_COLUMNS: usize = 24;
+/// Most map cells a resizable view shows across; a whole level's width
+pub const MAX_VIEW_COLUMNS: usize = 80;
+/// Fewest map cells a resizable view shows down
+pub const MIN_VIEW_ROWS: usize = 12;
+/// Most map cells a resizable view shows down; a whole level's height
+pub const MAX_VIEW_ROWS: usize = 45;
+/// Smallest cell, in CSS pixels, used while there's room for the fewest
+/// cells
+pub const MIN_CELL_SIZE: f64 = 10.0;
+/// Largest cell, in CSS pixels; bigger screens show the same view
+/// surrounded by space
+pub const MAX_CELL_SIZE: f64 = 32.0;
+
+/// How many map cells a view of a given size in CSS pixels shows and how
+/// big each is, for the web canvas that follows the page's size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewLayout {
+    pub columns: usize,
+    pub rows: usize,
+    /// Side of a cell in device pixels, so glyphs are drawn sharp
+    pub cell_pixels: u32,
+    /// Device pixels per CSS pixel
+    pub pixel_ratio: f64,
+}
+
+impl ViewLayout {
+    /// The layout for `width` by `height` CSS pixels on a screen with
+    /// `pixel_ratio` device pixels to each. Cells grow until the most cells
+    /// fit, and shrink below the smallest size only when even the fewest
+    /// wouldn't fit otherwise.
+    pub fn fit(width: f64, height: f64, pixel_ratio: f64) -> Self {
+        let pixel_ratio = if pixel_ratio.is_finite() && pixel_ratio > 0.0 {
+            pixel_ratio
+        } else {
+            1.0
+        };
+        let cell = (width / MAX_VIEW_COLUMNS as f64)
+            .max(height / MAX_VIEW_ROWS as f64)
+            .clamp(MIN_CELL_SIZE, MAX_CELL_SIZE);
+        let columns = ((width / cell) as usize).clamp(MIN_VIEW_COLUMNS, MAX_VIEW_COLUMNS);
+        let rows = ((height / cell) as usize).clamp(MIN_VIEW_ROWS, MAX_VIEW_ROWS);
+        let cell = cell.min(width / columns as f64).min(height / rows as f64);
+        ViewLayout {
+            columns,
+            rows,
+            cell_pixels: ((cell * pixel_ratio).floor() as u32).max(1),
+            pixel_ratio,
+        }
+    }
+
+    /// Side of a cell in CSS pixels
+    pub fn cell_size(&self) -> f64 {
+        self.cell_pixels as f64 / self.pixel_ratio
+    }
+
+    /// Width of the view in CSS pixels
+    pub fn width(&self) -> f64 {
+        self.columns as f64 * self.cell_size()
+    }
+
+    /// Height of the view in CSS pixels
+    pub fn height(&self) -> f64 {
+        self.rows as f64 * self.cell_size()
+    }
+}
+
+/// A cell of the view that looks different from the last frame drawn;
+/// `glyph` is `None` where the cell is now blank
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileChange {
+    pub x: usize,
+    pub y: usize,
+    pub glyph: Option<Glyph>,
+}
+
+/// What each cell of a view showed in the last frame, so a front-end that
+/// keeps its drawing between frames only has to redraw the cells that
+/// changed. The terminal gets the same from its double buffer; the web
+/// canvas keeps one of these.
+#[derive(Debug, Clone)]
+pub struct TileBuffer {
+    width: usize,
+    cells: Vec<Option<Glyph>>,
+    /// Set when the drawing can't be trusted, such as after the canvas was
+    /// cleared or drawn over, so the next frame redraws every cell
+    stale: bool,
+}
+
+impl TileBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        TileBuffer {
+            width,
+            cells: vec![None; width * height],
+            stale: true,
+        }
+    }
+
+    /// Forgets what was drawn; the next frame is drawn in full
+    pub fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    /// Whether the next frame will be drawn in full
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    /// Records `cells` as the new frame and returns the cells that differ
+    /// from the last one. Cells outside the view are ignored. After a
+    /// full redraw only the non-blank cells are returned, since the
+    /// front-end is expected to have cleared the view first.
+    pub fn update(&mut self, cells: impl IntoIterator<Item = MapCell>) -> Vec<TileChange> {
+        let mut frame = vec![None; self.cells.len()];
+        for cell in cells {
+            if cell.x < self.width {
+                if let Some(slot) = frame.get_mut(cell.y * self.width + cell.x) {
+                    *slot = Some(cell.glyph);
+                }
+            }
+        }
+
+        let full = std::mem::take(&mut self.stale);
+        let changes = frame
+            .iter()
+            .zip(&self.cells)
+            .enumerate()
+            .filter(|(_, (new, old))| if full { new.is_some() } else { new != old })
+            .map(|(index, (new, _))| TileChange {
+                x: index % self.width,
+                y: index / self.width,
+                glyph: *new,
+            })
+            .collect();
+        self.cells = frame;
+        changes
+    }
+}
+
+/// Frames drawn and tiles redrawn over the last whole second, for the
+/// front-ends' debug overlay
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameStats {
+    /// Frames drawn in the last whole second
+    pub frames_per_second: u32,
+    /// Tiles redrawn in the last whole second
+    pub tiles_per_second: u32,
+    window_start: f64,
+    frames: u32,
+    tiles: u32,
+    /// Tiles redrawn so far in the frame being drawn
+    frame_tiles: u32,
+}
+
+impl FrameStats {
+    /// Counts tiles redrawn in the frame being drawn
+    pub fn add_tiles(&mut self, tiles: usize) {
+        self.frame_tiles += tiles as u32;
+    }
+
+    /// Counts a frame finished at `now`, in milliseconds. Returns true when
+    /// a second has ended and the rates changed.
+    pub fn end_frame(&mut self, now: f64) -> bool {
+        let mut rolled_over = false;
+        if now - self.window_start >= 1000.0 {
+            // Going a whole second without a frame counts as idle
+            let idle = now - self.window_start >= 2000.0;
+            self.frames_per_second = if idle { 0 } else { self.frames };
+            self.tiles_per_second = if idle { 0 } else { self.tiles };
+            self.window_start = now;
+            self.frames = 0;
+            self.tiles = 0;
+            rolled_over = true;
+        }
+        self.frames += 1;
+        self.tiles += std::mem::take(&mut self.frame_tiles);
+        rolled_over
+    }
+}
+
+/// The player summary shown beside the map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatPanel {
+    pub name: String,
+    pub rows: Vec<String>,
+    /// Health, mana and progress to the next level
+    pub bars: Vec<StatBar>,
+    pub location: String,
+    /// The torch's turns left, or a warning on a dark level without one
+    pub light: Option<String>,
+    /// How the player's companion is doing, if they have one
+    pub companion: Option<String>,
+    /// The weather and the like going on here, with their turns left
+    pub ambient: Option<String>,
+}
+
+impl StatPanel {
+    pub fn new(player: &Player, dungeon: &Dungeon) -> Self {
+        let effects = &dungeon.levels[dungeon.current_level].ambient_effects;
+        Self {
+            name: player.name.clone(),
+            rows: vec![
+                format!("Level {} {}", player.level, player.class.class_type),
+                format!("Gold: {}", player.gold),
+            ],
+            bars: vec![
+                StatBar::graded("HP", player.health, player.max_health),
+                StatBar::graded("MP", player.mana, player.max_mana),
+                StatBar {
+                    label: "XP",
+                    bar: Bar::new(
+                        player.experience as i32,
+                        player.xp_for_next_level() as i32,
+                        PANEL_BAR_WIDTH,
+                    ),
+                    color: XP_BAR_COLOR,
+                },
+            ],
+            location: format!("{} - Level {}", dungeon.name, dungeon.current_level + 1),
+            light: match player.torch_turns {
+                0 if dungeon.levels[dungeon.current_level].ambient_light == AmbientLight::Dark => {
+                    Some("It is very dark here".to_string())
+                }
+                0 => None,
+                turns => Some(format!("Torch: {turns} turns left")),
+            },
+            companion: player.companion.as_ref().map(Companion::status_text),
+            ambient: (!effects.is_empty()).then(|| {
+                let labels: Vec<String> = effects.iter().map(AmbientEffect::label).collect();
+                labels.join(", ")
+            }),
+        }
+    }
+}
+
+/// The two sides of a fight and the actions the player can pick
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CombatView {
+    /// The enemy the player's next action is aimed at
+    pub enemy: String,
+    /// "HP: 7/12"
+    pub enemy_health: String,
+    /// The target's health as a bar, colored like the player's
+    pub enemy_bar: Bar,
+    /// Every enemy in a group fight with its health, the target marked with
+    /// `>`; empty when fighting a single enemy
+    pub enemies: Vec<String>,
+    pub player_health: String,
+    pub player_mana: String,
+    /// The player's buffs with the turns they have left, one line each
+    pub buffs: Vec<String>,
+    pub actions: Vec<String>,
+    /// What attacking the target is likely to do and the chance of fleeing,
+    /// one line each
+    pub forecast: Vec<String>,
+    /// How where the player stands changes the blows, one line each
+    pub modifiers: Vec<String>,
+    target: usize,
+}
+
+impl CombatView {
+    /// Builds the view for a fight against `enemies`, aimed at
+    /// `enemies[target]`, where `context` says what the player's position
+    /// does to the blows and whether they can flee
+    pub fn new(player: &Player, enemies: &[Enemy], target: usize, context: &CombatContext) -> Self {
+        let target = target.min(enemies.len().saturating_sub(1));
+        let mut actions: Vec<String> = ["Attack", "Use Ability", "Use Item", "Flee"]
+            .iter()
+            .enumerate()
+            .map(|(i, action)| format!("{} - {action}", i + 1))
+            .collect();
+        if enemies.len() > 1 {
+            actions.push("T - Next Target".to_string());
+        }
+
+        let mut view = Self {
+            enemy: enemies.get(target).map(enemy_label).unwrap_or_default(),
+            enemy_health: String::new(),
+            enemy_bar: Bar::new(0, 0, HEALTH_BAR_WIDTH),
+            enemies: Vec::new(),
+            player_health: format!("HP: {}/{}", player.health, player.max_health),
+            player_mana: format!("MP: {}/{}", player.mana, player.max_mana),
+            buffs: player.buffs.iter().map(Buff::label).collect(),
+            actions,
+            forecast: enemies
+                .get(target)
+                .map(|enemy| {
+                    let forecast = forecast(player, enemy, context.cornered);
+                    vec![forecast.attack_line(), forecast.counter_line()]
+                })
+                .unwrap_or_default(),
+            modifiers: context.labels(),
+            target,
+        };
+        if enemies.len() > 1 {
+            view.enemies = enemies
+                .iter()
+                .enumerate()
+                .map(|(i, enemy)| {
+                    roster_line(
+                        &enemy_label(enemy),
+                        enemy.health,
+                        enemy.max_health,
+                        i == target,
+                    )
+                })
+                .collect();
+        }
+        if let Some(enemy) = enemies.get(target) {
+            view.set_enemy_health(enemy.health, enemy.max_health);
+        }
+        view
+    }
+
+    /// Shows a different health for the target, such as one easing down
+    /// after a hit
+    pub fn set_enemy_health(&mut self, health: i32, max_health: i32) {
+        self.enemy_health = format!("HP: {health}/{max_health}");
+        self.enemy_bar = Bar::new(health, max_health, HEALTH_BAR_WIDTH);
+        if let Some(line) = self.enemies.get_mut(self.target) {
+            *line = roster_line(&self.enemy, health, max_health, true);
+        }
+    }
+}
+
+/// A fight's log as the combat screens show it: the newest lines that fit,
+/// or older ones the player has paged back to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CombatLog {
+    lines: Vec<String>,
+    /// How many lines back from the newest the view is scrolled
+    scroll: usize,
+}
+
+impl CombatLog {
+    /// Adds a line and jumps back to the newest
+    pub fn push(&mut self, line: String) {
+        self.lines.push(line);
+        self.scroll = 0;
+    }
+
+    pub fn extend(&mut self, lines: impl IntoIterator<Item = String>) {
+        for line in lines {
+            self.push(line);
+        }
+    }
+
+    /// Empties the log for a new fight
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.scroll = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Pages back through older lines, `rows` at a time
+    pub fn page_up(&mut self, rows: usize) {
+        self.scroll = (self.scroll + rows).min(self.lines.len().saturating_sub(rows));
+    }
+
+    /// Pages forward towards the newest line
+    pub fn page_down(&mut self, rows: usize) {
+        self.scroll = self.scroll.saturating_sub(rows);
+    }
+
+    /// The lines that fit in `rows` rows at the current scroll
+    pub fn visible(&self, rows: usize) -> &[String] {
+        let scroll = self.scroll.min(self.lines.len().saturating_sub(rows));
+        let end = self.lines.len() - scroll;
+        &self.lines[end.saturating_sub(rows)..end]
+    }
+
+    /// The log's heading, saying how to page when not everything fits:
+    /// "Combat Log (lines 3-8 of 12, PgUp/PgDn to scroll):"
+    pub fn heading(&self, rows: usize) -> String {
+        if self.lines.len() <= rows {
+            return "Combat Log:".to_string();
+        }
+        let scroll = self.scroll.min(self.lines.len() - rows);
+        let end = self.lines.len() - scroll;
+        format!(
+            "Combat Log (lines {}-{end} of {}, PgUp/PgDn to scroll):",
+            end - rows + 1,
+            self.lines.len()
+        )
+    }
+}
+
+/// "Sneaky Goblin (Humanoid, wielding Rusty Sword)"
+fn enemy_label(enemy: &Enemy) -> String {
+    match enemy.wielding() {
+        Some(wielding) => format!("{} ({}, {wielding})", enemy.name, enemy.kind),
+        None => format!("{} ({})", enemy.name, enemy.kind),
+    }
+}
+
+/// One enemy's line in a group fight's roster
+fn roster_line(enemy: &str, health: i32, max_health: i32, targeted: bool) -> String {
+    let marker = if targeted { '>' } else { ' ' };
+    if health > 0 {
+        format!("{marker} {enemy} HP: {health}/{max_health}")
+    } else {
+        format!("{marker} {enemy} - slain")
+    }
+}
+
+const HEALTH_BAR_WIDTH: usize = 20;
+/// Cells across each bar in the stat panel
+pub const PANEL_BAR_WIDTH: usize = 16;
+/// The experience bar doesn't warn of anything, so it keeps one color
+const XP_BAR_COLOR: FogColor = FogColor::rgb(150, 120, 255);
+
+/// How full a health or mana bar is, which picks its color: below half is
+/// low and below a quarter is critical
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum BarLevel {
+    #[default]
+    Healthy,
+    Low,
+    Critical,
+}
+
+impl BarLevel {
+    /// The level of a bar at `current / max`
+    pub fn of(current: i32, max: i32) -> Self {
+        // Compared in whole numbers so 49 of 100 is low and 50 isn't
+        let (current, max) = (current.max(0) as i64, max.max(1) as i64);
+        if current * 4 < max {
+            BarLevel::Critical
+        } else if current * 2 < max {
+            BarLevel::Low
+        } else {
+            BarLevel::Healthy
+        }
+    }
+
+    pub fn color(self) -> FogColor {
+        match self {
+            BarLevel::Healthy => FogColor::rgb(0, 200, 0),
+            BarLevel::Low => FogColor::rgb(230, 200, 0),
+            BarLevel::Critical => FogColor::rgb(220, 40, 40),
+        }
+    }
+
+    /// What the player is told on health dropping to this level, if
+    /// anything
+    pub fn warning(self) -> Option<&'static str> {
+        match self {
+            BarLevel::Healthy => None,
+            BarLevel::Low => Some("Low health!"),
+            BarLevel::Critical => Some("Low health! One more bad hit could be your last."),
+        }
+    }
+}
+
+/// A gauge `width` cells across, filled in proportion to `current / max`.
+/// Every bar the front-ends draw comes from here, so they fill and color
+/// alike.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bar {
+    pub current: i32,
+    pub max: i32,
+    pub width: usize,
+    /// Cells filled; any health left shows at least one
+    pub filled: usize,
+}
+
+impl Bar {
+    pub fn new(current: i32, max: i32, width: usize) -> Self {
+        let filled = if max > 0 {
+            (current.clamp(0, max) as usize * width).div_ceil(max as usize)
+        } else {
+            0
+        };
+        Bar {
+            current,
+            max,
+            width,
+            filled,
+        }
+    }
+
+    /// How full the bar is, from 0.0 to 1.0
+    pub fn fraction(&self) -> f32 {
+        if self.max > 0 {
+            self.current.clamp(0, self.max) as f32 / self.max as f32
+        } else {
+            0.0
+        }
+    }
+
+    pub fn level(&self) -> BarLevel {
+        BarLevel::of(self.current, self.max)
+    }
+
+    /// The bar as text: `█████░░░░░`, or `#####-----` from the ASCII set
+    pub fn cells(&self, set: GlyphSet) -> String {
+        let (full, empty) = match set {
+            GlyphSet::Ascii => ('#', '-'),
+            GlyphSet::Unicode => ('█', '░'),
+        };
+        std::iter::repeat_n(full, self.filled)
+            .chain(std::iter::repeat_n(empty, self.width - self.filled))
+            .collect()
+    }
+
+    /// "34/50"
+    pub fn value(&self) -> String {
+        format!("{}/{}", self.current, self.max)
+    }
+}
+
+/// One of the bars in the stat panel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatBar {
+    /// "HP"
+    pub label: &'static str,
+    pub bar: Bar,
+    pub color: FogColor,
+}
+
+impl StatBar {
+    /// A bar colored by how full it is
+    fn graded(label: &'static str, current: i32, max: i32) -> Self {
+        let bar = Bar::new(current, max, PANEL_BAR_WIDTH);
+        StatBar {
+            label,
+            color: bar.level().color(),
+            bar,
+        }
+    }
+
+    /// "HP ########-------- 34/50"
+    pub fn text(&self, set: GlyphSet) -> String {
+        format!(
+            "{} {} {}",
+            self.label,
+            self.bar.cells(set),
+            self.bar.value()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::ClassType;
+    use crate::world::enemy::EnemyType;
+    use crate::world::{Alertness, AmbientEvent, DungeonType, Tile, TileType};
+    use std::collections::HashSet;
+
+    fn open_level() -> Level {
+        let mut level = Level::new(10, 10);
+        for row in level.tiles.iter_mut() {
+            for tile in row.iter_mut() {
+                *tile = Tile::floor();
+                tile.explored = true;
+                tile.visible = true;
+            }
+        }
+        level.player_position = Position::new(5, 5);
+        level
+    }
+
+    #[test]
+    fn test_legend_matches_the_appearance_table() {
+        for set in GlyphSet::ALL {
+            let entries = legend(set);
+            assert_eq!(entries.len(), TILE_APPEARANCES.len());
+            for (entry, appearance) in entries.iter().zip(&TILE_APPEARANCES) {
+                assert_eq!(entry.label, appearance.label);
+                assert_eq!(entry.glyph, glyph(appearance.feature, set));
+            }
+        }
+        assert_eq!(legend(GlyphSet::Ascii)[0].glyph.symbol, '@');
+    }
+
+    #[test]
+    fn test_map_frame_is_centered_on_the_player() {
+        let mut level = open_level();
+        level.companion_position = Some(Position::new(5, 6));
+        let enemy_pos = Position::new(6, 5);
+        level.enemies.insert(
+            enemy_pos,
+            Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1),
+        );
+
+        for set in GlyphSet::ALL {
+            let frame = MapFrame::centered(&level, 5, 5, set);
+            let glyph_at = |x, y| {
+                frame
+                    .cells
+                    .iter()
+                    .find(|cell| cell.x == x && cell.y == y)
+                    .map(|cell| cell.glyph)
+            };
+
+            assert_eq!(glyph_at(2, 2), Some(glyph(MapFeature::Player, set)));
+            assert_eq!(
+                glyph_at(3, 2),
+                Some(glyph(MapFeature::Enemy(Alertness::Unaware), set))
+            );
+            assert_eq!(glyph_at(2, 3), Some(glyph(MapFeature::Companion, set)));
+            assert_eq!(
+                glyph_at(1, 2),
+                Some(glyph(MapFeature::Tile(TileType::Floor), set))
+            );
+        }
+        let unicode = MapFrame::centered(&level, 5, 5, GlyphSet::Unicode);
+        assert!(unicode.cells.iter().any(|cell| cell.glyph.symbol == '·'));
+    }
+
+    #[test]
+    fn test_the_threat_overlay_tints_only_reachable_cells() {
+        let mut level = open_level();
+        level.enemies.insert(
+            Position::new(7, 5),
+            Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1),
+        );
+        let mut frame = MapFrame::from_origin(&level, Position::new(0, 0), 10, 10, GlyphSet::Ascii);
+        assert!(frame
+            .cells
+            .iter()
+            .all(|cell| cell.glyph.background.is_none()));
+
+        frame.show_threats(&level);
+        let tinted: HashSet<Position> = frame
+            .cells
+            .iter()
+            .filter(|cell| cell.glyph.background == Some(THREAT_TINT))
+            .map(|cell| Position::new(cell.x as i32, cell.y as i32))
+            .collect();
+        assert_eq!(tinted, level.threatened_tiles(true));
+        assert!(tinted.contains(&Position::new(6, 5)));
+        assert!(!tinted.contains(&Position::new(5, 5)));
+    }
+
+    #[test]
+    fn test_tile_buffer_returns_only_changed_cells() {
+        let level = open_level();
+        let mut buffer = TileBuffer::new(10, 10);
+        let frame = MapFrame::from_origin(&level, Position::new(0, 0), 10, 10, GlyphSet::Ascii);
+
+        // The first frame is drawn in full, then nothing changes
+        assert_eq!(buffer.update(frame.cells.clone()).len(), frame.cells.len());
+        assert!(!buffer.is_stale());
+        assert!(buffer.update(frame.cells.clone()).is_empty());
+
+        // The player steps right: their old and new cells are redrawn
+        let mut moved = level.clone();
+        moved.player_position = Position::new(6, 5);
+        let frame = MapFrame::from_origin(&moved, Position::new(0, 0), 10, 10, GlyphSet::Ascii);
+        let changes = buffer.update(frame.cells.clone());
+        let mut changed: Vec<_> = changes.iter().map(|change| (change.x, change.y)).collect();
+        changed.sort();
+        assert_eq!(changed, vec![(5, 5), (6, 5)]);
+
+        // A cell that goes blank is reported as cleared
+        let without_player = frame
+            .cells
+            .iter()
+            .copied()
+            .filter(|cell| (cell.x, cell.y) != (6, 5));
+        assert_eq!(
+            buffer.update(without_player),
+            vec![TileChange {
+                x: 6,
+                y: 5,
+                glyph: None
+            }]
+        );
+
+        buffer.invalidate();
+        assert_eq!(buffer.update(frame.cells.clone()).len(), frame.cells.len());
+    }
+
+    #[test]
+    fn test_frame_stats_roll_over_each_second() {
+        let mut stats = FrameStats::default();
+        stats.add_tiles(1000);
+        assert!(stats.end_frame(1000.0));
+        for i in 1..30 {
+            stats.add_tiles(2);
+            assert!(!stats.end_frame(1000.0 + i as f64 * 30.0));
+        }
+        stats.add_tiles(1);
+        assert!(stats.end_frame(2000.0));
+        assert_eq!(stats.frames_per_second, 30);
+        assert_eq!(stats.tiles_per_second, 1000 + 29 * 2);
+
+        // After a long pause the rates drop to nothing
+        assert!(stats.end_frame(10_000.0));
+        assert_eq!(stats.frames_per_second, 0);
+        assert_eq!(stats.tiles_per_second, 0);
+    }
+
+    #[test]
+    fn test_view_layout_fits_phones_tablets_and_large_screens() {
+        // A phone held upright: the smallest cells, sharp at three device
+        // pixels to each
+        let phone = ViewLayout::fit(360.0, 300.0, 3.0);
+        assert_eq!((phone.columns, phone.rows), (36, 30));
+        assert_eq!(phone.cell_pixels, 30);
+        assert_eq!(phone.cell_size(), MIN_CELL_SIZE);
+
+        // A tablet has room for every column at a slightly larger size
+        let tablet = ViewLayout::fit(1000.0, 500.0, 2.0);
+        assert_eq!((tablet.columns, tablet.rows), (80, 40));
+        assert_eq!(tablet.cell_pixels, 25);
+
+        // A 4K screen stops at the largest cells and the whole level
+        let large = ViewLayout::fit(3500.0, 1900.0, 1.0);
+        assert_eq!((large.columns, large.rows), (80, 45));
+        assert_eq!(large.cell_pixels, MAX_CELL_SIZE as u32);
+        assert_eq!(large.width(), 80.0 * MAX_CELL_SIZE);
+
+        // Too small for the fewest cells: they shrink to fit instead
+        let tiny = ViewLayout::fit(120.0, 60.0, 1.0);
+        assert_eq!((tiny.columns, tiny.rows), (MIN_VIEW_COLUMNS, MIN_VIEW_ROWS));
+        assert_eq!(tiny.cell_pixels, 5);
+        assert!(tiny.width() <= 120.0 && tiny.height() <= 60.0);
+
+        // Nothing measured yet
+        let hidden = ViewLayout::fit(0.0, 0.0, f64::NAN);
+        assert_eq!(
+            (hidden.columns, hidden.rows),
+            (MIN_VIEW_COLUMNS, MIN_VIEW_ROWS)
+        );
+        assert_eq!((hidden.cell_pixels, hidden.pixel_ratio), (1, 1.0));
+    }
+
+    #[test]
+    fn test_resized_views_stay_centered_on_the_player() {
+        let level = open_level();
+        for layout in [
+            ViewLayout::fit(360.0, 300.0, 3.0),
+            ViewLayout::fit(1000.0, 500.0, 2.0),
+        ] {
+            let frame = MapFrame::centered(&level, layout.columns, layout.rows, GlyphSet::Ascii);
+            let player = frame
+                .cells
+                .iter()
+                .find(|cell| cell.glyph == glyph(MapFeature::Player, GlyphSet::Ascii))
+                .unwrap();
+            assert_eq!((player.x, player.y), (layout.columns / 2, layout.rows / 2));
+        }
+    }
+
+    #[test]
+    fn test_map_frame_hides_unexplored_tiles() {
+        let mut level = open_level();
+        level.tiles[5][4].explored = false;
+        level.tiles[5][4].visible = false;
+
+        let frame = MapFrame::from_origin(&level, Position::new(0, 0), 10, 10, GlyphSet::Ascii);
+
+        let hidden = frame.cells.iter().find(|cell| cell.x == 4 && cell.y == 5);
+        assert!(hidden.is_none_or(|cell| cell.glyph.symbol == ' '));
+    }
+
+    #[test]
+    fn test_stat_panel_lists_player_and_location() {
+        let player = Player::new("Aria".to_string(), ClassType::Mage);
+        let dungeon = Dungeon::new("Test Ruins".to_string(), DungeonType::Ruins, 1, 1);
+
+        let panel = StatPanel::new(&player, &dungeon);
+
+        assert_eq!(panel.name, "Aria");
+        assert_eq!(
+            panel.rows,
+            ["Level 1 Mage".to_string(), format!("Gold: {}", player.gold)]
+        );
+        let labels: Vec<&str> = panel.bars.iter().map(|bar| bar.label).collect();
+        assert_eq!(labels, ["HP", "MP", "XP"]);
+        assert_eq!(
+            panel.bars[0].text(GlyphSet::Ascii),
+            format!(
+                "HP {} {}/{}",
+                "#".repeat(PANEL_BAR_WIDTH),
+                player.health,
+                player.max_health
+            )
+        );
+        assert_eq!(panel.bars[0].color, BarLevel::Healthy.color());
+        assert_eq!(panel.bars[2].bar.filled, 0);
+        assert_eq!(panel.location, "Test Ruins - Level 1");
+        assert_eq!(panel.light, None);
+        assert_eq!(panel.companion, None);
+        assert_eq!(panel.ambient, None);
+
+        let ranger = Player::new("Wren".to_string(), ClassType::Ranger);
+        let panel = StatPanel::new(&ranger, &dungeon);
+        assert_eq!(panel.companion.as_deref(), Some("Fang: 20/20 HP"));
+    }
+
+    #[test]
+    fn test_stat_panel_and_map_show_the_weather() {
+        let player = Player::new("Aria".to_string(), ClassType::Mage);
+        let mut dungeon = Dungeon::new("Test Caves".to_string(), DungeonType::Cavern, 1, 1);
+        let level = &mut dungeon.levels[0];
+        weather::start_effect(level, AmbientEvent::Rain);
+        weather::start_effect(level, AmbientEvent::Tremor);
+
+        let panel = StatPanel::new(&player, &dungeon);
+        assert_eq!(
+            panel.ambient.as_deref(),
+            Some("Rain (30 turns), Tremors (3 turns)")
+        );
+
+        // The shaking ground pushes the whole map a column over
+        let mut level = open_level();
+        let still = MapFrame::centered(&level, 5, 5, GlyphSet::Ascii);
+        weather::start_effect(&mut level, AmbientEvent::Tremor);
+        let shaken = MapFrame::centered(&level, 5, 5, GlyphSet::Ascii);
+        let player_x = |frame: &MapFrame| {
+            frame
+                .cells
+                .iter()
+                .find(|cell| cell.glyph.symbol == '@')
+                .map(|cell| cell.x)
+        };
+        assert_eq!(player_x(&shaken), player_x(&still).map(|x| x + 1));
+    }
+
+    #[test]
+    fn test_combat_log_pages_back_and_jumps_to_new_lines() {
+        let mut log = CombatLog::default();
+        log.extend((1..=12).map(|i| format!("line {i}")));
+        assert_eq!(
+            log.visible(5),
+            ["line 8", "line 9", "line 10", "line 11", "line 12"]
+        );
+        assert_eq!(
+            log.heading(5),
+            "Combat Log (lines 8-12 of 12, PgUp/PgDn to scroll):"
+        );
+
+        log.page_up(5);
+        assert_eq!(log.visible(5)[0], "line 3");
+        // Paging stops with the oldest line at the top
+        log.page_up(5);
+        assert_eq!(log.visible(5)[0], "line 1");
+        assert_eq!(
+            log.heading(5),
+            "Combat Log (lines 1-5 of 12, PgUp/PgDn to scroll):"
+        );
+        log.page_down(5);
+        assert_eq!(log.visible(5)[0], "line 6");
+
+        // A new line shows the newest again
+        log.push("line 13".to_string());
+        assert_eq!(log.visible(5).last().unwrap(), "line 13");
+        assert_eq!(log.visible(20).len(), 13);
+        assert_eq!(log.heading(20), "Combat Log:");
+    }
+
+    #[test]
+    fn test_bars_fill_in_proportion() {
+        assert_eq!(Bar::new(10, 10, 10).cells(GlyphSet::Ascii), "##########");
+        assert_eq!(Bar::new(5, 10, 10).cells(GlyphSet::Ascii), "#####-----");
+        assert_eq!(Bar::new(5, 10, 4).cells(GlyphSet::Unicode), "██░░");
+        // Any health left shows, and none shows as empty
+        assert_eq!(Bar::new(1, 10, 4).filled, 1);
+        assert_eq!(Bar::new(0, 10, 4).cells(GlyphSet::Ascii), "----");
+        assert_eq!(Bar::new(-3, 10, 4).filled, 0);
+        assert_eq!(Bar::new(3, 0, 4).filled, 0);
+        assert_eq!(Bar::new(15, 10, 4).filled, 4);
+
+        assert_eq!(Bar::new(34, 50, 16).fraction(), 0.68);
+        assert_eq!(Bar::new(-1, 50, 16).fraction(), 0.0);
+        assert_eq!(Bar::new(34, 50, 16).value(), "34/50");
+    }
+
+    #[test]
+    fn test_bars_turn_yellow_below_half_and_red_below_a_quarter() {
+        assert_eq!(BarLevel::of(50, 100), BarLevel::Healthy);
+        assert_eq!(BarLevel::of(49, 100), BarLevel::Low);
+        assert_eq!(BarLevel::of(25, 100), BarLevel::Low);
+        assert_eq!(BarLevel::of(24, 100), BarLevel::Critical);
+        assert_eq!(BarLevel::of(0, 0), BarLevel::Critical);
+        assert!(BarLevel::Healthy < BarLevel::Low && BarLevel::Low < BarLevel::Critical);
+        assert_eq!(BarLevel::Healthy.warning(), None);
+        assert_eq!(BarLevel::Low.warning(), Some("Low health!"));
+    }
+
+    #[test]
+    fn test_combat_view_lists_every_enemy_in_a_group() {
+        let player = Player::new("Hero".to_string(), ClassType::Warrior);
+        let mut enemies = vec![
+            Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1),
+            Enemy::new("Orc".to_string(), EnemyType::Orc, 1),
+        ];
+
+        let single = CombatView::new(&player, &enemies[..1], 0, &CombatContext::default());
+        assert!(single.enemies.is_empty());
+        assert_eq!(single.actions.len(), 4);
+        assert!(single.forecast[0].starts_with("Attack: "));
+        assert!(single.forecast[1].starts_with("Goblin hits back"));
+        assert!(single.buffs.is_empty());
+
+        enemies[0].health = 0;
+        let cornered = CombatContext {
+            cornered: true,
+            ..CombatContext::default()
+        };
+        let mut view = CombatView::new(&player, &enemies, 1, &cornered);
+        assert_eq!(view.modifiers, ["Cornered: there's nowhere to flee"]);
+        assert!(view.forecast[1].ends_with("Flee: 0%"));
+        assert!(view.enemy.starts_with("Orc"));
+        assert!(view.enemies[0].ends_with("- slain"));
+        assert!(view.enemies[1].starts_with("> Orc"));
+        assert_eq!(view.actions.last().unwrap(), "T - Next Target");
+
+        // Easing the target's health updates its roster line too
+        view.set_enemy_health(3, 20);
+        assert!(view.enemies[1].ends_with("HP: 3/20"));
+        assert_eq!(view.enemy_health, "HP: 3/20");
+        assert_eq!(view.enemy_bar.level(), BarLevel::Critical);
+    }
+
+    #[test]
+    fn test_combat_view_shows_buffs_with_turns_left() {
+        let mut player = Player::new("Hero".to_string(), ClassType::Cleric);
+        player.level = 3;
+        player.start_buff(2);
+        player.tick_buffs();
+        let enemies = [Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1)];
+
+        let view = CombatView::new(&player, &enemies, 0, &CombatContext::default());
+        assert_eq!(view.buffs, ["Blessing +2 (4 turns)"]);
+    }
+}
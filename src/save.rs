@@ -0,0 +1,401 @@
+//! Saving and loading games, including the permadeath rules
+//!
+//! A permadeath game is written automatically every few turns and when the
+//! player quits, and its save is deleted when the character dies. Every write
+//! bumps a counter stored in the file, so restoring an older copy, or loading
+//! the same save twice in one session, can be refused.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::game::Game;
+use crate::settings::DEFAULT_AUTOSAVE_INTERVAL;
+
+const SAVE_FILE_NAME: &str = "savegame.json";
+
+/// Save slot the title screen continues
+pub const DEFAULT_SLOT: &str = "default";
+
+/// On-disk save format
+#[derive(Serialize, Deserialize)]
+struct SaveFile<G> {
+    /// Increases every time the file is written
+    save_counter: u64,
+    game: G,
+}
+
+/// What the title screen shows about an existing save
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveSummary {
+    pub name: String,
+    pub level: u32,
+    pub dungeon: String,
+    pub permadeath: bool,
+}
+
+impl SaveSummary {
+    pub fn from_game(game: &Game) -> Self {
+        SaveSummary {
+            name: game.player.name.clone(),
+            level: game.player.level,
+            dungeon: game.current_dungeon().name.clone(),
+            permadeath: game.permadeath,
+        }
+    }
+
+    pub fn mode(&self) -> &'static str {
+        if self.permadeath {
+            "Permadeath"
+        } else {
+            "Standard"
+        }
+    }
+}
+
+impl std::fmt::Display for SaveSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}, level {} - {} ({})",
+            self.name,
+            self.level,
+            self.dungeon,
+            self.mode()
+        )
+    }
+}
+
+/// The game's directory inside the per-user data directory (`%APPDATA%` on
+/// Windows, `$XDG_DATA_HOME` or `~/.local/share` elsewhere), falling back to
+/// the working directory
+pub fn data_dir() -> PathBuf {
+    #[cfg(windows)]
+    let base = std::env::var_os("APPDATA").map(PathBuf::from);
+    #[cfg(not(windows))]
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")));
+
+    base.unwrap_or_default().join("echoes_rpg")
+}
+
+/// A file the game keeps in its data directory, next to the save
+#[cfg(not(target_arch = "wasm32"))]
+pub trait DataFile: Sized {
+    /// Uses the file inside `dir`
+    fn in_dir(dir: PathBuf) -> Self;
+
+    /// Uses the same data directory as the save file
+    fn in_default_location() -> Self {
+        Self::in_dir(data_dir())
+    }
+}
+
+/// Writes `contents` to `path`, creating its directory if need be. It goes
+/// to a temporary file first and is renamed over `path`, so a crash never
+/// leaves half a file.
+pub fn write_atomically(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Could not create {}", dir.display()))?;
+    }
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, contents)
+        .with_context(|| format!("Could not write {}", temp_path.display()))?;
+    fs::rename(&temp_path, path).with_context(|| format!("Could not write {}", path.display()))
+}
+
+/// A fresh, empty directory under the system temp dir for a test to write
+/// its files in, removed again when the test ends
+#[cfg(test)]
+pub struct TempDir(PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("echoes_rpg_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        TempDir(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Reads and writes the single save slot
+pub struct SaveManager {
+    path: PathBuf,
+    /// Counter of the save loaded most recently this session
+    last_loaded_counter: Option<u64>,
+    /// Turn of the last autosave, so a turn is never saved twice
+    last_autosave_turn: Option<u32>,
+    /// Turns between automatic saves in permadeath mode
+    autosave_interval: u32,
+}
+
+impl SaveManager {
+    /// Uses the save file inside `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        SaveManager {
+            path: dir.into().join(SAVE_FILE_NAME),
+            last_loaded_counter: None,
+            last_autosave_turn: None,
+            autosave_interval: DEFAULT_AUTOSAVE_INTERVAL,
+        }
+    }
+
+    pub fn set_autosave_interval(&mut self, turns: u32) {
+        self.autosave_interval = turns.max(1);
+    }
+
+    /// Uses the save file in the game's data directory
+    pub fn in_default_location() -> Self {
+        Self::new(data_dir())
+    }
+
+    /// Uses a named save slot in the game's data directory, as given to
+    /// `--load`; any slot but the default one has a file of its own
+    pub fn in_slot(slot: &str) -> Self {
+        let mut saves = Self::in_default_location();
+        if slot != DEFAULT_SLOT {
+            saves.path = data_dir().join(format!("savegame-{slot}.json"));
+        }
+        saves
+    }
+
+    pub fn has_save(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Describes the existing save, if there is a readable one
+    pub fn summary(&self) -> Option<SaveSummary> {
+        self.read()
+            .ok()
+            .map(|file| SaveSummary::from_game(&file.game))
+    }
+
+    fn read(&self) -> Result<SaveFile<Game>> {
+        let json = fs::read_to_string(&self.path)
+            .with_context(|| format!("Could not read {}", self.path.display()))?;
+        serde_json::from_str(&json).context("The save file is corrupted")
+    }
+
+    /// Writes the game, bumping the save counter
+    pub fn save(&mut self, game: &Game) -> Result<()> {
+        let previous = self.read().map(|file| file.save_counter).unwrap_or(0);
+        let save_counter = previous.max(self.last_loaded_counter.unwrap_or(0)) + 1;
+
+        let json = serde_json::to_string(&SaveFile { save_counter, game })?;
+        write_atomically(&self.path, json)
+    }
+
+    /// Loads the saved game. A permadeath save is refused if it isn't newer
+    /// than the one loaded last this session
+    pub fn load(&mut self) -> Result<Game> {
+        let file = self.read()?;
+
+        if file.game.permadeath
+            && self
+                .last_loaded_counter
+                .is_some_and(|counter| file.save_counter <= counter)
+        {
+            bail!("This permadeath save has already been loaded and can't be restored again.");
+        }
+
+        self.last_loaded_counter = Some(file.save_counter);
+        self.last_autosave_turn = Some(file.game.stats.turns);
+
+        let mut game = file.game;
+        game.update_visibility();
+        Ok(game)
+    }
+
+    pub fn delete(&self) -> Result<()> {
+        if self.has_save() {
+            fs::remove_file(&self.path)
+                .with_context(|| format!("Could not delete {}", self.path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Saves a permadeath game every `autosave_interval` turns. Returns
+    /// whether a save was written
+    pub fn autosave_if_due(&mut self, game: &Game) -> Result<bool> {
+        let turn = game.stats.turns;
+        let due = game.permadeath
+            && turn > 0
+            && turn.is_multiple_of(self.autosave_interval)
+            && self.last_autosave_turn != Some(turn);
+        if !due {
+            return Ok(false);
+        }
+
+        self.save(game)?;
+        self.last_autosave_turn = Some(turn);
+        Ok(true)
+    }
+
+    /// Saves a permadeath game when the player quits
+    pub fn save_on_quit(&mut self, game: &Game) -> Result<()> {
+        if game.permadeath {
+            self.save(game)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the save of a permadeath character that just died
+    pub fn on_player_death(&self, game: &Game) -> Result<()> {
+        if game.permadeath {
+            self.delete()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::{ClassType, Player};
+    use crate::game::GameState;
+    use crate::log::LogCategory;
+
+    fn test_game(permadeath: bool) -> Game {
+        let player = Player::new("Tester".to_string(), ClassType::Warrior);
+        let mut game = Game::new(player);
+        game.game_state = GameState::Playing;
+        game.permadeath = permadeath;
+        game
+    }
+
+    fn counter_of(saves: &SaveManager) -> u64 {
+        saves.read().unwrap().save_counter
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let dir = TempDir::new("round_trip");
+        let mut saves = SaveManager::new(dir.path());
+        let mut game = test_game(false);
+        game.stats.turns = 12;
+        game.player.gold = 99;
+        game.ng_plus_counter = 2;
+        game.log.set_turn(12);
+        game.log
+            .push(LogCategory::Loot, "You picked up a Rusty Key.");
+
+        saves.save(&game).unwrap();
+        let summary = saves.summary().unwrap();
+        assert_eq!(summary.name, "Tester");
+        assert_eq!(summary.mode(), "Standard");
+
+        let loaded = saves.load().unwrap();
+        assert_eq!(loaded.stats.turns, 12);
+        assert_eq!(loaded.player.gold, 99);
+        assert_eq!(loaded.ng_plus_counter, 2);
+        let entry = loaded.log.iter().last().unwrap();
+        assert_eq!(
+            (entry.turn, entry.category, entry.text.as_str()),
+            (12, LogCategory::Loot, "You picked up a Rusty Key.")
+        );
+        assert_eq!(
+            loaded.current_level().enemies.len(),
+            game.current_level().enemies.len()
+        );
+
+        // Standard games can be reloaded freely
+        assert!(saves.load().is_ok());
+    }
+
+    #[test]
+    fn test_permadeath_save_deleted_on_death() {
+        let dir = TempDir::new("death");
+        let mut saves = SaveManager::new(dir.path());
+        let mut game = test_game(true);
+
+        saves.save(&game).unwrap();
+        assert!(saves.has_save());
+
+        game.game_state = GameState::GameOver;
+        saves.on_player_death(&game).unwrap();
+        assert!(!saves.has_save());
+        assert!(saves.load().is_err());
+
+        // Standard saves survive a death
+        let standard = test_game(false);
+        saves.save(&standard).unwrap();
+        saves.on_player_death(&standard).unwrap();
+        assert!(saves.has_save());
+    }
+
+    #[test]
+    fn test_permadeath_save_cannot_be_loaded_twice() {
+        let dir = TempDir::new("reload");
+        let mut saves = SaveManager::new(dir.path());
+        let game = test_game(true);
+
+        saves.save(&game).unwrap();
+        assert!(saves.load().is_ok());
+        assert!(saves.load().is_err());
+
+        // Once the game has been saved again, the newer save loads
+        saves.save(&game).unwrap();
+        assert!(saves.load().is_ok());
+    }
+
+    #[test]
+    fn test_restored_older_copy_is_refused() {
+        let dir = TempDir::new("tamper");
+        let mut saves = SaveManager::new(dir.path());
+        let game = test_game(true);
+
+        saves.save(&game).unwrap();
+        let backup = fs::read_to_string(&saves.path).unwrap();
+        let first = counter_of(&saves);
+
+        saves.save(&game).unwrap();
+        assert!(counter_of(&saves) > first);
+        assert!(saves.load().is_ok());
+
+        // Putting back the earlier file doesn't rewind the game
+        fs::write(&saves.path, backup).unwrap();
+        let err = saves.load().unwrap_err();
+        assert!(err.to_string().contains("already been loaded"));
+    }
+
+    #[test]
+    fn test_autosave_every_interval_in_permadeath_only() {
+        let dir = TempDir::new("autosave");
+        let mut saves = SaveManager::new(dir.path());
+        let mut game = test_game(true);
+
+        game.stats.turns = DEFAULT_AUTOSAVE_INTERVAL - 1;
+        assert!(!saves.autosave_if_due(&game).unwrap());
+
+        game.stats.turns = DEFAULT_AUTOSAVE_INTERVAL;
+        assert!(saves.autosave_if_due(&game).unwrap());
+        // The same turn isn't saved twice
+        assert!(!saves.autosave_if_due(&game).unwrap());
+
+        // The interval follows the player's setting
+        saves.set_autosave_interval(7);
+        game.stats.turns = 35;
+        assert!(saves.autosave_if_due(&game).unwrap());
+        game.stats.turns = 40;
+        assert!(!saves.autosave_if_due(&game).unwrap());
+
+        let mut standard = test_game(false);
+        standard.stats.turns = DEFAULT_AUTOSAVE_INTERVAL;
+        let mut standard_saves = SaveManager::new(dir.path().join("standard"));
+        assert!(!standard_saves.autosave_if_due(&standard).unwrap());
+        assert!(!standard_saves.has_save());
+    }
+}
@@ -0,0 +1,586 @@
+//! Player preferences shared by every front-end
+//!
+//! The desktop front-ends keep the settings as JSON in the per-user config
+//! directory and the web version keeps them in `localStorage`. Values outside
+//! the ranges the options screen offers are pulled back into range when the
+//! settings are loaded, with a warning for each, so a hand-edited file can't
+//! break the game. A missing file just means the defaults.
+
+use serde::{Deserialize, Serialize};
+
+use crate::audio::Volume;
+use crate::combat::playback::MESSAGE_DELAY;
+use crate::game::DEFAULT_VIEW_RADIUS;
+use crate::log::DEFAULT_LOG_CAPACITY;
+use crate::world::fog_of_war::FogColor;
+use crate::world::GlyphSet;
+
+/// Smallest and largest value of a numeric setting, and how far one press
+/// of left or right moves it
+#[derive(Debug, Clone, Copy)]
+struct Bounds {
+    min: u32,
+    max: u32,
+    step: u32,
+}
+
+/// Turns between autosaves of a permadeath game unless the player changes it
+pub const DEFAULT_AUTOSAVE_INTERVAL: u32 = 20;
+
+const MESSAGE_LOG_SIZE_BOUNDS: Bounds = Bounds {
+    min: 50,
+    max: 2000,
+    step: 50,
+};
+const VIEW_RADIUS_BOUNDS: Bounds = Bounds {
+    min: 3,
+    max: 20,
+    step: 1,
+};
+const COMBAT_MESSAGE_DELAY_BOUNDS: Bounds = Bounds {
+    min: 0,
+    max: 1500,
+    step: 100,
+};
+const AUTOSAVE_INTERVAL_BOUNDS: Bounds = Bounds {
+    min: 5,
+    max: 100,
+    step: 5,
+};
+const FONT_SIZE_BOUNDS: Bounds = Bounds {
+    min: 10,
+    max: 40,
+    step: 2,
+};
+
+/// How the map and legend are colored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorScheme {
+    /// The glyphs' own colors
+    #[default]
+    Classic,
+    /// Shades of grey only
+    Greyscale,
+    /// An old amber monochrome monitor
+    Amber,
+}
+
+impl ColorScheme {
+    pub const ALL: [ColorScheme; 3] = [
+        ColorScheme::Classic,
+        ColorScheme::Greyscale,
+        ColorScheme::Amber,
+    ];
+
+    /// Name stored in the settings file
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorScheme::Classic => "classic",
+            ColorScheme::Greyscale => "greyscale",
+            ColorScheme::Amber => "amber",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|scheme| scheme.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Recolors a glyph color; brightness is kept, so fog still dims tiles
+    pub fn apply(self, color: FogColor) -> FogColor {
+        let luma = || 0.299 * color.r as f32 + 0.587 * color.g as f32 + 0.114 * color.b as f32;
+        match self {
+            ColorScheme::Classic => color,
+            ColorScheme::Greyscale => {
+                let grey = luma() as u8;
+                FogColor {
+                    r: grey,
+                    g: grey,
+                    b: grey,
+                    ..color
+                }
+            }
+            ColorScheme::Amber => {
+                let level = luma() / 255.0;
+                FogColor {
+                    r: (255.0 * level) as u8,
+                    g: (176.0 * level) as u8,
+                    b: 0,
+                    ..color
+                }
+            }
+        }
+    }
+}
+
+/// One line of the options screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    MessageLogSize,
+    ViewRadius,
+    CombatMessageDelay,
+    ColorScheme,
+    Glyphs,
+    AutosaveInterval,
+    EnemyRespawn,
+    AdaptiveDifficulty,
+    ConfirmStairs,
+    AmbientEvents,
+    Hints,
+    ScreenEffects,
+    RecordReplays,
+    FontSize,
+    Fullscreen,
+}
+
+impl Setting {
+    /// Every setting, in the order the options screen lists them
+    pub const ALL: [Setting; 15] = [
+        Setting::MessageLogSize,
+        Setting::ViewRadius,
+        Setting::CombatMessageDelay,
+        Setting::ColorScheme,
+        Setting::Glyphs,
+        Setting::AutosaveInterval,
+        Setting::EnemyRespawn,
+        Setting::AdaptiveDifficulty,
+        Setting::ConfirmStairs,
+        Setting::AmbientEvents,
+        Setting::Hints,
+        Setting::ScreenEffects,
+        Setting::RecordReplays,
+        Setting::FontSize,
+        Setting::Fullscreen,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Setting::MessageLogSize => "Message log size",
+            Setting::ViewRadius => "View radius",
+            Setting::CombatMessageDelay => "Combat message delay",
+            Setting::ColorScheme => "Color scheme",
+            Setting::Glyphs => "Map glyphs",
+            Setting::AutosaveInterval => "Autosave interval",
+            Setting::EnemyRespawn => "Enemy respawning",
+            Setting::AdaptiveDifficulty => "Adaptive difficulty",
+            Setting::ConfirmStairs => "Confirm stairs",
+            Setting::AmbientEvents => "Ambient events",
+            Setting::Hints => "Tutorial hints",
+            Setting::ScreenEffects => "Screen effects",
+            Setting::RecordReplays => "Record replays",
+            Setting::FontSize => "Font size (GUI)",
+            Setting::Fullscreen => "Fullscreen (GUI)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Messages kept in the scrollable message log
+    pub message_log_size: u32,
+    /// How far the player can see, in tiles
+    pub view_radius: u32,
+    /// Milliseconds between two combat messages
+    pub combat_message_delay_ms: u32,
+    /// Name of a `ColorScheme`
+    pub color_scheme: String,
+    /// Name of the `GlyphSet` the map is drawn with
+    pub glyph_set: String,
+    /// Turns between autosaves of a permadeath game
+    pub autosave_interval: u32,
+    /// Whether enemies move back into levels left alone for a while
+    pub enemy_respawn: bool,
+    /// Whether enemies spawned from now on get a little harder or easier
+    /// to match how the player is getting on
+    pub adaptive_difficulty: bool,
+    /// Whether walking onto stairs waits for '>' or '<' before taking them
+    pub confirm_stairs: bool,
+    /// Whether weather, rockslides and the like happen in the dungeons
+    pub ambient_events: bool,
+    /// Whether tips are given the first time things come up in a run
+    pub hints: bool,
+    /// Whether being hurt, critical hits and level-ups flash the screen
+    /// and shake the map
+    pub screen_effects: bool,
+    /// Whether new runs are recorded for watching back from the hall of fame
+    pub record_replays: bool,
+    /// Points the GUI draws its text at
+    pub font_size: u32,
+    /// Whether the GUI window fills the screen
+    pub fullscreen: bool,
+    /// Name of the sound effect `Volume`, cycled in the game with 'v'
+    pub volume: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            message_log_size: DEFAULT_LOG_CAPACITY as u32,
+            view_radius: DEFAULT_VIEW_RADIUS as u32,
+            combat_message_delay_ms: (MESSAGE_DELAY * 1000.0) as u32,
+            color_scheme: ColorScheme::default().name().to_string(),
+            glyph_set: GlyphSet::default().name().to_string(),
+            autosave_interval: DEFAULT_AUTOSAVE_INTERVAL,
+            enemy_respawn: false,
+            adaptive_difficulty: false,
+            confirm_stairs: false,
+            ambient_events: true,
+            hints: true,
+            screen_effects: true,
+            record_replays: false,
+            font_size: 14,
+            fullscreen: true,
+            volume: Volume::default().name().to_string(),
+        }
+    }
+}
+
+impl Settings {
+    /// Parses stored settings, clamping anything out of range. Returns the
+    /// settings and a warning for every value that had to be changed.
+    pub fn from_json(json: &str) -> (Self, Vec<String>) {
+        match serde_json::from_str::<Settings>(json) {
+            Ok(mut settings) => {
+                let warnings = settings.clamp();
+                (settings, warnings)
+            }
+            Err(e) => (
+                Settings::default(),
+                vec![format!(
+                    "Could not read the settings ({e}); using the defaults"
+                )],
+            ),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Pulls every value back into the range the options screen offers,
+    /// returning a warning for each one changed
+    pub fn clamp(&mut self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let numbers = [
+            (
+                Setting::MessageLogSize,
+                &mut self.message_log_size,
+                MESSAGE_LOG_SIZE_BOUNDS,
+            ),
+            (
+                Setting::ViewRadius,
+                &mut self.view_radius,
+                VIEW_RADIUS_BOUNDS,
+            ),
+            (
+                Setting::CombatMessageDelay,
+                &mut self.combat_message_delay_ms,
+                COMBAT_MESSAGE_DELAY_BOUNDS,
+            ),
+            (
+                Setting::AutosaveInterval,
+                &mut self.autosave_interval,
+                AUTOSAVE_INTERVAL_BOUNDS,
+            ),
+            (Setting::FontSize, &mut self.font_size, FONT_SIZE_BOUNDS),
+        ];
+        for (setting, value, bounds) in numbers {
+            let clamped = (*value).clamp(bounds.min, bounds.max);
+            if clamped != *value {
+                warnings.push(format!(
+                    "{} {} is out of range; using {}",
+                    setting.label(),
+                    value,
+                    clamped
+                ));
+                *value = clamped;
+            }
+        }
+
+        if ColorScheme::from_name(&self.color_scheme).is_none() {
+            warnings.push(format!(
+                "Unknown color scheme \"{}\"; using {}",
+                self.color_scheme,
+                ColorScheme::default().name()
+            ));
+            self.color_scheme = ColorScheme::default().name().to_string();
+        }
+        if GlyphSet::from_name(&self.glyph_set).is_none() {
+            warnings.push(format!(
+                "Unknown glyph set \"{}\"; using {}",
+                self.glyph_set,
+                GlyphSet::default().name()
+            ));
+            self.glyph_set = GlyphSet::default().name().to_string();
+        }
+        if Volume::from_name(&self.volume).is_none() {
+            warnings.push(format!(
+                "Unknown volume \"{}\"; using {}",
+                self.volume,
+                Volume::default().name()
+            ));
+            self.volume = Volume::default().name().to_string();
+        }
+        warnings
+    }
+
+    pub fn color_scheme(&self) -> ColorScheme {
+        ColorScheme::from_name(&self.color_scheme).unwrap_or_default()
+    }
+
+    pub fn glyph_set(&self) -> GlyphSet {
+        GlyphSet::from_name(&self.glyph_set).unwrap_or_default()
+    }
+
+    pub fn volume(&self) -> Volume {
+        Volume::from_name(&self.volume).unwrap_or_default()
+    }
+
+    /// Combat message delay in seconds, as `CombatPlayback` takes it
+    pub fn combat_message_delay(&self) -> f64 {
+        f64::from(self.combat_message_delay_ms) / 1000.0
+    }
+
+    /// Moves a setting one step: `forward` is right on the options screen.
+    /// Numbers stop at their limits, the color scheme and glyph set wrap
+    /// around and the on/off settings toggle.
+    pub fn adjust(&mut self, setting: Setting, forward: bool) {
+        let step = |value: &mut u32, bounds: Bounds| {
+            *value = if forward {
+                value.saturating_add(bounds.step).min(bounds.max)
+            } else {
+                value.saturating_sub(bounds.step).max(bounds.min)
+            };
+        };
+        match setting {
+            Setting::MessageLogSize => step(&mut self.message_log_size, MESSAGE_LOG_SIZE_BOUNDS),
+            Setting::ViewRadius => step(&mut self.view_radius, VIEW_RADIUS_BOUNDS),
+            Setting::CombatMessageDelay => step(
+                &mut self.combat_message_delay_ms,
+                COMBAT_MESSAGE_DELAY_BOUNDS,
+            ),
+            Setting::AutosaveInterval => {
+                step(&mut self.autosave_interval, AUTOSAVE_INTERVAL_BOUNDS)
+            }
+            Setting::FontSize => step(&mut self.font_size, FONT_SIZE_BOUNDS),
+            Setting::ColorScheme => {
+                let schemes = ColorScheme::ALL;
+                let current = schemes
+                    .iter()
+                    .position(|&scheme| scheme == self.color_scheme())
+                    .unwrap_or(0);
+                let next = if forward {
+                    (current + 1) % schemes.len()
+                } else {
+                    (current + schemes.len() - 1) % schemes.len()
+                };
+                self.color_scheme = schemes[next].name().to_string();
+            }
+            // Only two sets, so either direction swaps them
+            Setting::Glyphs => {
+                let next = match self.glyph_set() {
+                    GlyphSet::Ascii => GlyphSet::Unicode,
+                    GlyphSet::Unicode => GlyphSet::Ascii,
+                };
+                self.glyph_set = next.name().to_string();
+            }
+            Setting::EnemyRespawn => self.enemy_respawn = !self.enemy_respawn,
+            Setting::AdaptiveDifficulty => self.adaptive_difficulty = !self.adaptive_difficulty,
+            Setting::ConfirmStairs => self.confirm_stairs = !self.confirm_stairs,
+            Setting::AmbientEvents => self.ambient_events = !self.ambient_events,
+            Setting::Hints => self.hints = !self.hints,
+            Setting::ScreenEffects => self.screen_effects = !self.screen_effects,
+            Setting::RecordReplays => self.record_replays = !self.record_replays,
+            Setting::Fullscreen => self.fullscreen = !self.fullscreen,
+        }
+    }
+
+    /// A setting's current value as the options screen shows it
+    pub fn value_text(&self, setting: Setting) -> String {
+        match setting {
+            Setting::MessageLogSize => format!("{} messages", self.message_log_size),
+            Setting::ViewRadius => format!("{} tiles", self.view_radius),
+            Setting::CombatMessageDelay if self.combat_message_delay_ms == 0 => "Off".to_string(),
+            Setting::CombatMessageDelay => format!("{:.1} s", self.combat_message_delay()),
+            Setting::ColorScheme => self.color_scheme().name().to_string(),
+            Setting::Glyphs => self.glyph_set().name().to_string(),
+            Setting::AutosaveInterval => format!("every {} turns", self.autosave_interval),
+            Setting::EnemyRespawn if self.enemy_respawn => "On".to_string(),
+            Setting::EnemyRespawn => "Off".to_string(),
+            Setting::AdaptiveDifficulty if self.adaptive_difficulty => "On".to_string(),
+            Setting::AdaptiveDifficulty => "Off".to_string(),
+            Setting::ConfirmStairs if self.confirm_stairs => "On".to_string(),
+            Setting::ConfirmStairs => "Off".to_string(),
+            Setting::AmbientEvents if self.ambient_events => "On".to_string(),
+            Setting::AmbientEvents => "Off".to_string(),
+            Setting::Hints if self.hints => "On".to_string(),
+            Setting::Hints => "Off".to_string(),
+            Setting::ScreenEffects if self.screen_effects => "On".to_string(),
+            Setting::ScreenEffects => "Off".to_string(),
+            Setting::RecordReplays if self.record_replays => "On".to_string(),
+            Setting::RecordReplays => "Off".to_string(),
+            Setting::FontSize => format!("{} pt", self.font_size),
+            Setting::Fullscreen if self.fullscreen => "On".to_string(),
+            Setting::Fullscreen => "Off".to_string(),
+        }
+    }
+
+    /// Text rows for the options screen, one per setting
+    pub fn rows(&self) -> Vec<String> {
+        Setting::ALL
+            .iter()
+            .map(|&setting| format!("{:<22} < {} >", setting.label(), self.value_text(setting)))
+            .collect()
+    }
+}
+
+/// Directory for the settings file: the game's folder in the per-user config
+/// directory, falling back to the working directory
+#[cfg(not(target_arch = "wasm32"))]
+pub fn config_dir() -> std::path::PathBuf {
+    dirs::config_dir().unwrap_or_default().join("echoes_rpg")
+}
+
+/// The settings file in the config directory
+#[cfg(not(target_arch = "wasm32"))]
+pub struct SettingsFile {
+    path: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SettingsFile {
+    const FILE_NAME: &'static str = "settings.json";
+
+    /// Uses the settings file inside `dir`
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> Self {
+        SettingsFile {
+            path: dir.into().join(Self::FILE_NAME),
+        }
+    }
+
+    pub fn in_default_location() -> Self {
+        Self::new(config_dir())
+    }
+
+    /// Reads the settings and the warnings for anything clamped; a missing
+    /// file gives the defaults without a warning
+    pub fn load(&self) -> (Settings, Vec<String>) {
+        match std::fs::read_to_string(&self.path) {
+            Ok(json) => Settings::from_json(&json),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (Settings::default(), Vec::new()),
+            Err(e) => (
+                Settings::default(),
+                vec![format!("Could not read {}: {e}", self.path.display())],
+            ),
+        }
+    }
+
+    pub fn save(&self, settings: &Settings) -> anyhow::Result<()> {
+        crate::save::write_atomically(&self.path, settings.to_json())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(target_arch = "wasm32"))]
+    use crate::save::TempDir;
+
+    #[test]
+    fn test_out_of_range_values_are_clamped_with_warnings() {
+        let (settings, warnings) = Settings::from_json(
+            r#"{"view_radius": 99, "message_log_size": 1, "color_scheme": "neon", "glyph_set": "emoji", "volume": "eleven"}"#,
+        );
+        assert_eq!(settings.view_radius, VIEW_RADIUS_BOUNDS.max);
+        assert_eq!(settings.message_log_size, MESSAGE_LOG_SIZE_BOUNDS.min);
+        assert_eq!(settings.color_scheme(), ColorScheme::Classic);
+        assert_eq!(settings.glyph_set(), GlyphSet::Ascii);
+        assert_eq!(settings.volume(), Volume::High);
+        assert_eq!(warnings.len(), 5);
+
+        // Missing fields take their defaults without a warning
+        assert_eq!(
+            settings.autosave_interval,
+            Settings::default().autosave_interval
+        );
+
+        let (settings, warnings) = Settings::from_json("{broken");
+        assert_eq!(settings, Settings::default());
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_adjust_stops_at_limits_and_cycles_schemes() {
+        let mut settings = Settings::default();
+        for _ in 0..50 {
+            settings.adjust(Setting::ViewRadius, true);
+        }
+        assert_eq!(settings.view_radius, VIEW_RADIUS_BOUNDS.max);
+
+        settings.combat_message_delay_ms = 100;
+        settings.adjust(Setting::CombatMessageDelay, false);
+        settings.adjust(Setting::CombatMessageDelay, false);
+        assert_eq!(settings.value_text(Setting::CombatMessageDelay), "Off");
+
+        settings.adjust(Setting::ColorScheme, false);
+        assert_eq!(settings.color_scheme(), ColorScheme::Amber);
+        settings.adjust(Setting::ColorScheme, true);
+        assert_eq!(settings.color_scheme(), ColorScheme::Classic);
+
+        settings.adjust(Setting::Glyphs, false);
+        assert_eq!(settings.glyph_set(), GlyphSet::Unicode);
+        settings.adjust(Setting::Glyphs, true);
+        assert_eq!(settings.value_text(Setting::Glyphs), "ascii");
+
+        let fullscreen = settings.fullscreen;
+        settings.adjust(Setting::Fullscreen, true);
+        assert_eq!(settings.fullscreen, !fullscreen);
+
+        settings.adjust(Setting::FontSize, true);
+        assert_eq!(settings.value_text(Setting::FontSize), "16 pt");
+        for _ in 0..20 {
+            settings.adjust(Setting::FontSize, false);
+        }
+        assert_eq!(settings.font_size, FONT_SIZE_BOUNDS.min);
+
+        // Respawning is off unless the player asks for it
+        assert_eq!(settings.value_text(Setting::EnemyRespawn), "Off");
+        settings.adjust(Setting::EnemyRespawn, false);
+        assert!(settings.enemy_respawn);
+        // And so is adaptive difficulty
+        assert_eq!(settings.value_text(Setting::AdaptiveDifficulty), "Off");
+        settings.adjust(Setting::AdaptiveDifficulty, true);
+        assert!(settings.adaptive_difficulty);
+        assert_eq!(settings.rows().len(), Setting::ALL.len());
+    }
+
+    #[test]
+    fn test_color_schemes_keep_brightness() {
+        let white = ColorScheme::Greyscale.apply(FogColor::WHITE);
+        assert_eq!((white.r, white.g, white.b), (255, 255, 255));
+
+        let bright = ColorScheme::Amber.apply(FogColor::rgb(0, 255, 0));
+        let dim = ColorScheme::Amber.apply(FogColor::rgb(0, 255, 0).dimmed(0.5));
+        assert_eq!(bright.b, 0);
+        assert!(dim.r < bright.r && dim.g < bright.g);
+        assert_eq!(ColorScheme::Classic.apply(FogColor::GREY), FogColor::GREY);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_file_round_trip() {
+        let dir = TempDir::new("settings");
+        let file = SettingsFile::new(dir.path());
+
+        assert_eq!(file.load(), (Settings::default(), Vec::new()));
+
+        let mut settings = Settings::default();
+        settings.adjust(Setting::ViewRadius, false);
+        settings.adjust(Setting::ColorScheme, true);
+        settings.volume = Volume::Muted.name().to_string();
+        file.save(&settings).unwrap();
+        assert_eq!(file.load(), (settings, Vec::new()));
+    }
+}
@@ -0,0 +1,226 @@
+//! Command-line options for launching the game
+//!
+//! Everything the menus would otherwise ask can be given up front: the
+//! world seed, the character, the difficulty, whether to show the combat
+//! tutorial and which save to continue. The options are parsed before the
+//! terminal is touched, so a bad value prints the usage and exits without
+//! leaving the terminal in raw mode.
+
+use crate::character::{player, ClassType, Player};
+use crate::game::Difficulty;
+
+/// Shown for `--help` and after a bad argument
+pub const USAGE: &str = "\
+Usage: echoes_rpg [OPTIONS]
+
+Options:
+  --seed <NUMBER>         Generate the dungeons from this seed
+  --class <CLASS>         Skip character creation: warrior, mage, ranger or cleric
+  --name <NAME>           Name of the character given with --class
+  --difficulty <LEVEL>    easy, normal or hard (default: normal)
+  --skip-tutorial         Don't show the combat tutorial
+  --load <SLOT>           Continue the game saved in this slot
+  --gui                   Use the graphical front-end
+  --terminal              Use the terminal front-end
+  --size <WIDTHxHEIGHT>   Smallest terminal size to accept without a warning
+  -h, --help              Show this message";
+
+/// Front-end requested on the command line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Frontend {
+    Gui,
+    Terminal,
+}
+
+/// How the game was asked to start
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StartupOptions {
+    /// Seed for generating the dungeons, instead of a random one
+    pub seed: Option<u64>,
+    /// Class and name of a character to start with straight away
+    pub class: Option<ClassType>,
+    pub name: Option<String>,
+    pub difficulty: Difficulty,
+    pub skip_tutorial: bool,
+    /// Save slot to continue instead of showing the title screen
+    pub load_slot: Option<String>,
+    /// `--gui` or `--terminal`; the last one given wins
+    pub frontend: Option<Frontend>,
+    /// Terminal size below which the game warns, in columns and rows
+    pub min_terminal_size: Option<(u16, u16)>,
+    /// `--help` was given
+    pub show_help: bool,
+}
+
+impl StartupOptions {
+    /// Reads the options from the arguments, without the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = StartupOptions::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--seed" => {
+                    let seed = value()?;
+                    options.seed = Some(
+                        seed.parse()
+                            .map_err(|_| format!("Invalid seed '{seed}': expected a number"))?,
+                    );
+                }
+                "--class" => {
+                    let class = value()?;
+                    options.class = Some(
+                        ClassType::ALL
+                            .into_iter()
+                            .find(|class_type| class_type.to_string().eq_ignore_ascii_case(&class))
+                            .ok_or_else(|| format!("Unknown class '{class}'"))?,
+                    );
+                }
+                "--name" => {
+                    let name = value()?;
+                    let name = name.trim();
+                    if name.is_empty()
+                        || name.chars().count() > player::MAX_NAME_LENGTH
+                        || !name.chars().all(player::is_name_character)
+                    {
+                        return Err(format!(
+                            "Invalid name '{name}': use up to {} letters, digits and spaces",
+                            player::MAX_NAME_LENGTH
+                        ));
+                    }
+                    options.name = Some(name.to_string());
+                }
+                "--difficulty" => {
+                    let difficulty = value()?;
+                    options.difficulty = Difficulty::from_name(&difficulty)
+                        .ok_or_else(|| format!("Unknown difficulty '{difficulty}'"))?;
+                }
+                "--skip-tutorial" => options.skip_tutorial = true,
+                "--load" => {
+                    let slot = value()?;
+                    if slot.is_empty()
+                        || !slot
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                    {
+                        return Err(format!("Invalid save slot '{slot}'"));
+                    }
+                    options.load_slot = Some(slot);
+                }
+                "--gui" => options.frontend = Some(Frontend::Gui),
+                "--terminal" => options.frontend = Some(Frontend::Terminal),
+                "--size" => {
+                    let size = value()?;
+                    options.min_terminal_size = Some(parse_size(&size).ok_or_else(|| {
+                        format!("Invalid size '{size}': expected WIDTHxHEIGHT, such as 120x40")
+                    })?);
+                }
+                "-h" | "--help" => options.show_help = true,
+                _ => return Err(format!("Unknown argument '{arg}'")),
+            }
+        }
+
+        if options.name.is_some() && options.class.is_none() {
+            return Err("--name needs --class as well".to_string());
+        }
+        if options.load_slot.is_some() && options.class.is_some() {
+            return Err("--load can't be combined with --class".to_string());
+        }
+        Ok(options)
+    }
+
+    /// The character given with `--class` and `--name`, if any
+    pub fn preset_player(&self) -> Option<Player> {
+        let class = self.class?;
+        let name = self
+            .name
+            .clone()
+            .unwrap_or_else(|| player::DEFAULT_NAME.to_string());
+        Some(Player::new(name, class))
+    }
+}
+
+/// Reads "120x40" as (120, 40)
+fn parse_size(size: &str) -> Option<(u16, u16)> {
+    let (width, height) = size.split_once(['x', 'X'])?;
+    let size = (width.parse().ok()?, height.parse().ok()?);
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<StartupOptions, String> {
+        StartupOptions::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_no_arguments_means_the_menus() {
+        assert_eq!(parse(&[]).unwrap(), StartupOptions::default());
+    }
+
+    #[test]
+    fn test_every_option_is_read() {
+        let options = parse(&[
+            "--seed",
+            "42",
+            "--class",
+            "ranger",
+            "--name",
+            "Foo",
+            "--difficulty",
+            "Hard",
+            "--skip-tutorial",
+            "--gui",
+            "--terminal",
+            "--size",
+            "120x40",
+        ])
+        .unwrap();
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.class, Some(ClassType::Ranger));
+        assert_eq!(options.difficulty, Difficulty::Hard);
+        assert!(options.skip_tutorial);
+        assert_eq!(options.frontend, Some(Frontend::Terminal));
+        assert_eq!(options.min_terminal_size, Some((120, 40)));
+
+        let player = options.preset_player().unwrap();
+        assert_eq!(player.name, "Foo");
+        assert_eq!(player.class.class_type, ClassType::Ranger);
+
+        let options = parse(&["--load", "second", "-h"]).unwrap();
+        assert_eq!(options.load_slot.as_deref(), Some("second"));
+        assert!(options.show_help);
+        assert!(options.preset_player().is_none());
+    }
+
+    #[test]
+    fn test_a_class_without_a_name_gets_the_default_one() {
+        let player = parse(&["--class", "mage"])
+            .unwrap()
+            .preset_player()
+            .unwrap();
+        assert_eq!(player.name, player::DEFAULT_NAME);
+    }
+
+    #[test]
+    fn test_bad_arguments_are_refused() {
+        for args in [
+            &["--seed"][..],
+            &["--seed", "-3"],
+            &["--class", "bard"],
+            &["--name", "Foo"],
+            &["--class", "mage", "--name", "Robert'); DROP"],
+            &["--difficulty", "nightmare"],
+            &["--load", "../escape"],
+            &["--load", "one", "--class", "mage"],
+            &["--size", "120"],
+            &["--size", "0x40"],
+            &["--fast"],
+        ] {
+            assert!(parse(args).is_err(), "{args:?} was accepted");
+        }
+    }
+}
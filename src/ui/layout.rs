@@ -0,0 +1,190 @@
+//! Screen arithmetic for the terminal front-end that can't underflow
+//!
+//! Terminal coordinates are unsigned, so centring text wider than its box or
+//! drawing a border line above row 0 would wrap around to a huge coordinate,
+//! or panic in a debug build. Everything here saturates or clamps instead:
+//! text too wide for its space starts at the left edge, a box too big for
+//! the terminal shrinks to fit, and a border that can't fit its title is
+//! drawn without it.
+
+/// Offset that centres `len` cells in `space` cells; 0 when they don't fit
+pub fn center(space: u16, len: usize) -> u16 {
+    let len = u16::try_from(len).unwrap_or(u16::MAX);
+    space.saturating_sub(len) / 2
+}
+
+/// A box centred on the terminal, with a border line free above and below
+/// it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    /// Top-left cell of the box
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Layout {
+    /// Centres a `width` by `height` box in the terminal, shrinking it to
+    /// fit. The border drawn around a box takes the row above and the row
+    /// below it, so those are kept on screen too.
+    pub fn centered(term_width: u16, term_height: u16, width: u16, height: u16) -> Self {
+        let width = width.min(term_width);
+        let height = height.min(term_height.saturating_sub(2));
+        Layout {
+            x: center(term_width, width.into()),
+            y: center(term_height, height.into()).max(1),
+            width,
+            height,
+        }
+    }
+
+    /// Column that centres `text` in the box
+    pub fn center_x(&self, text: &str) -> u16 {
+        self.x + center(self.width, text.chars().count())
+    }
+}
+
+/// Where the lines of a border go once fitted to the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Border {
+    pub left: u16,
+    pub right: u16,
+    pub top: u16,
+    pub bottom: u16,
+    /// Column the title starts at in the top line, if it fits
+    pub title_x: Option<u16>,
+}
+
+impl Border {
+    /// Fits a border around `height` rows starting at (`x`, `y`), its sides
+    /// being the first and last of `width` columns and its top and bottom
+    /// lines the rows above and below. A border that would run off the
+    /// terminal is pulled back onto it; None if there's no room for one at
+    /// all.
+    pub fn fit(
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        term_width: u16,
+        term_height: u16,
+        title: &str,
+    ) -> Option<Self> {
+        let (term_width, term_height) = (usize::from(term_width), usize::from(term_height));
+        if width < 2 || term_width < 2 || term_height < 2 {
+            return None;
+        }
+
+        let left = x.min(term_width - 2);
+        let right = (left + width - 1).min(term_width - 1);
+        let top = y.saturating_sub(1).min(term_height - 2);
+        let bottom = (y + height).clamp(top + 1, term_height - 1);
+
+        let inner = right - left - 1;
+        let title_len = title.chars().count();
+        let title_x = (title_len <= inner).then(|| left + 1 + (inner - title_len) / 2);
+
+        // Everything is within the terminal, so fits in a u16
+        let cell = |n: usize| n as u16;
+        Some(Border {
+            left: cell(left),
+            right: cell(right),
+            top: cell(top),
+            bottom: cell(bottom),
+            title_x: title_x.map(cell),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every terminal size from 20x10 to 300x100, in steps
+    fn terminal_sizes() -> impl Iterator<Item = (u16, u16)> {
+        (20..=300)
+            .step_by(7)
+            .chain([300])
+            .flat_map(|width| (10..=100).step_by(3).chain([100]).map(move |h| (width, h)))
+    }
+
+    #[test]
+    fn test_center_saturates() {
+        assert_eq!(center(60, 29), 15);
+        assert_eq!(center(10, 29), 0);
+        assert_eq!(center(10, usize::MAX), 0);
+    }
+
+    #[test]
+    fn test_boxes_fit_every_terminal() {
+        // The sizes the screens ask for, from the smallest to the widest
+        let boxes = [
+            (40, 8),
+            (60, 20),
+            (70, 30),
+            (80, 24),
+            (100, 40),
+            (1000, 1000),
+        ];
+        for (term_width, term_height) in terminal_sizes() {
+            for (width, height) in boxes {
+                let area = Layout::centered(term_width, term_height, width, height);
+                assert!(area.width <= width && area.height <= height);
+                assert!(area.x + area.width <= term_width);
+                // Room for the border lines above and below
+                assert!(area.y >= 1);
+                assert!(area.y + area.height < term_height);
+                let long_title = "A title far wider than any box on a narrow terminal screen";
+                assert!(area.center_x(long_title) >= area.x);
+
+                let border = Border::fit(
+                    area.x.into(),
+                    area.y.into(),
+                    area.width.into(),
+                    area.height.into(),
+                    term_width,
+                    term_height,
+                    "Echoes of the Forgotten Realm",
+                )
+                .unwrap();
+                assert!(border.left < border.right && border.right < term_width);
+                assert!(border.top < border.bottom && border.bottom < term_height);
+                if let Some(title_x) = border.title_x {
+                    assert!(title_x > border.left && title_x + 29 <= border.right);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_borders_are_pulled_onto_the_terminal() {
+        // A box at the very top can't have its top line above row 0
+        let border = Border::fit(0, 0, 30, 5, 80, 24, "Title").unwrap();
+        assert_eq!((border.left, border.top, border.bottom), (0, 0, 5));
+        assert_eq!(border.title_x, Some(12));
+
+        // Too big for the terminal: it shrinks and drops the title
+        let border = Border::fit(70, 20, 60, 30, 80, 24, "Echoes of the Forgotten Realm").unwrap();
+        assert_eq!((border.right, border.bottom), (79, 23));
+        assert_eq!(border.title_x, None);
+
+        assert_eq!(Border::fit(0, 0, 1, 5, 80, 24, ""), None);
+        assert_eq!(Border::fit(0, 0, 30, 5, 1, 1, ""), None);
+    }
+
+    #[test]
+    fn test_layout_matches_the_old_centring_when_it_fits() {
+        let area = Layout::centered(120, 40, 60, 20);
+        assert_eq!(
+            area,
+            Layout {
+                x: 30,
+                y: 10,
+                width: 60,
+                height: 20
+            }
+        );
+        assert_eq!(area.center_x("Echoes of the Forgotten Realm"), 45);
+    }
+}
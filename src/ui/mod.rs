@@ -1,80 +1,374 @@
-#[cfg(all(windows, not(all(feature = "gui", target_os = "windows"))))]
+#[cfg(windows)]
 use crossterm::event::KeyEventKind;
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 use crossterm::{
     cursor,
     event::{self, Event, KeyCode, KeyEvent},
     execute,
-    style::{self, Color},
+    style::{self, Attribute, Color},
     terminal::{self},
 };
 
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 use std::io::{self, stdout};
-
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
-use crate::character::{ClassType, Player};
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
-use crate::combat::{CombatAction, CombatResult};
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
-use crate::inventory::InventoryManager;
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
-use crate::item::Item;
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
+use std::time::{Duration, Instant};
+
+use crate::achievements::AchievementProfile;
+use crate::bestiary::Bestiary;
+use crate::character::allocation::STAT_POINT_POOL;
+use crate::character::{player, Class, ClassType, Player, StatAllocation, StatType};
+use crate::combat::{
+    self, CombatAction, CombatContext, CombatEnding, CombatPlayback, CombatResult, Combatant,
+};
+use crate::effects::ScreenEffect;
+use crate::game::{GameStats, PauseOption};
+use crate::hall_of_fame::{Leaderboard, Placement};
+use crate::help;
+use crate::inventory::{HotbarSlot, InventoryManager, ItemDetails, HOTBAR_SLOTS, RECIPES};
+use crate::item::consumable::Material;
+use crate::item::enchant::EnchantOffer;
+use crate::item::Rarity;
+use crate::log::{GameLog, LogCategory};
+use crate::menu::{MenuInput, MenuSelector};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::platform;
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
-use crate::world::{Dungeon, Enemy, FogOfWar, Level, Position};
+use crate::quest::{Quest, QuestStatus};
+use crate::render::{legend, CombatLog, CombatView, MapFrame, StatPanel};
+use crate::save::SaveSummary;
+use crate::settings::{Setting, Settings};
+use crate::world::fog_of_war::FogColor;
+use crate::world::{Dialogue, Dungeon, Enemy, FogOfWar, Level, Position};
+
+mod layout;
+mod screen;
+use layout::{Border, Layout};
+use screen::{Cell, DoubleBuffer, ScreenBuffer};
 
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 const SCREEN_HEIGHT: usize = 35;
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
-const MAP_WIDTH: usize = 70;
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
-const MAP_HEIGHT: usize = 25;
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
+const MIN_MAP_WIDTH: usize = 40;
+const MIN_MAP_HEIGHT: usize = 15;
 const UI_PANEL_WIDTH: usize = 35; // Increased panel width for better readability
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 const BORDER_PADDING: usize = 4; // Increased padding inside the border
+const SCREEN_MARGIN: usize = 2; // Gap between the game screen and the terminal edge
+const SIDE_COLUMN_GAP: usize = 2;
+const SIDE_COLUMN_WIDTH: usize = 20; // Controls and legend, right of the border
+const MESSAGE_LOG_LINES: usize = 3; // Header plus at least two messages
+const MIN_COMBAT_LOG_ROWS: usize = 5; // Shown even when the screen is short
+/// How long a flashed line stays in reverse video
+const FLASH_DURATION: Duration = Duration::from_millis(120);
+
+/// Keys listed beside the map and on the pause menu's controls screen
+const CONTROLS: [&str; 12] = [
+    "↑↓←→: Move",
+    "1-5: Hotbar",
+    "I: Inventory",
+    "C: Character",
+    "G: Interact",
+    "L: Message log",
+    "J: Quest journal",
+    "B: Bestiary",
+    "X: Look around",
+    "F5: Save game",
+    "?: Help",
+    "Esc/Q: Pause menu",
+];
+
+/// Index of "Next Target" in a group fight's list of combat actions
+const COMBAT_NEXT_TARGET: usize = 4;
+
+/// An entry of the title screen's menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TitleOption {
+    NewGame,
+    DailyChallenge,
+    Continue,
+    /// A new game on the template of a shared build
+    FromBuildCode,
+    HallOfFame,
+    /// Every achievement, and which have been unlocked
+    Achievements,
+    Options,
+    Help,
+    Exit,
+}
+
+impl TitleOption {
+    /// Every entry, in the order the title screen lists them
+    pub const ALL: [TitleOption; 9] = [
+        TitleOption::NewGame,
+        TitleOption::DailyChallenge,
+        TitleOption::Continue,
+        TitleOption::FromBuildCode,
+        TitleOption::HallOfFame,
+        TitleOption::Achievements,
+        TitleOption::Options,
+        TitleOption::Help,
+        TitleOption::Exit,
+    ];
+}
+
+/// Asks the terminal to put text on the system clipboard with an OSC 52
+/// escape sequence
+struct CopyToClipboard<'a>(&'a str);
+
+impl crossterm::Command for CopyToClipboard<'_> {
+    fn write_ansi(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
+        write!(
+            f,
+            "\x1b]52;c;{}\x07",
+            crate::base64::encode_standard(self.0.as_bytes())
+        )
+    }
+
+    /// The legacy Windows console can't reach the clipboard this way, so
+    /// there the copy quietly doesn't happen
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Text color for row `index` of a menu, picked out when it's highlighted
+fn menu_color(menu: &MenuSelector, index: usize) -> Color {
+    if menu.is_selected(index) {
+        Color::Yellow
+    } else {
+        Color::White
+    }
+}
+
+/// Splits `text` into lines of at most `width` characters, breaking between
+/// words; a word longer than a line gets one to itself
+fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+/// Terminal color for a fresh message in the log
+fn log_color(category: LogCategory) -> Color {
+    match category {
+        LogCategory::Combat => Color::Red,
+        LogCategory::Loot => Color::Green,
+        LogCategory::System => Color::White,
+        LogCategory::Movement => Color::Cyan,
+        LogCategory::Achievement => Color::Magenta,
+        LogCategory::Hint => Color::Yellow,
+    }
+}
+
+/// Terminal color for an equipment rarity tier
+/// Hotbar slots with nothing for their key to use are dimmed
+fn hotbar_color(slot: &HotbarSlot) -> Color {
+    if slot.is_empty() {
+        Color::DarkGrey
+    } else {
+        Color::White
+    }
+}
+
+fn rarity_color(rarity: Rarity) -> Color {
+    match rarity {
+        Rarity::Common => Color::White,
+        Rarity::Uncommon => Color::Green,
+        Rarity::Rare => Color::Blue,
+        Rarity::Epic => Color::Magenta,
+    }
+}
+
+/// Where each part of the game screen goes for one terminal size. The map
+/// view grows with the terminal, from the minimum view up to the whole level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GameLayout {
+    map_width: usize,
+    map_height: usize,
+    border_x: usize,
+    border_y: usize,
+}
+
+impl GameLayout {
+    /// Columns used by everything except the map view
+    const FIXED_WIDTH: usize = 2 * SCREEN_MARGIN
+        + 2 * BORDER_PADDING
+        + UI_PANEL_WIDTH
+        + SIDE_COLUMN_GAP
+        + SIDE_COLUMN_WIDTH;
+    /// Rows used by everything except the map view
+    const FIXED_HEIGHT: usize = 2 * SCREEN_MARGIN + 2 * BORDER_PADDING + 1 + MESSAGE_LOG_LINES;
+
+    /// Fits the game screen into a terminal, or None if it is too small
+    fn fit(
+        term_width: usize,
+        term_height: usize,
+        level_width: usize,
+        level_height: usize,
+    ) -> Option<Self> {
+        let map_width = term_width.checked_sub(Self::FIXED_WIDTH)?.min(level_width);
+        let map_height = term_height
+            .checked_sub(Self::FIXED_HEIGHT)?
+            .min(level_height);
+        if map_width < MIN_MAP_WIDTH.min(level_width)
+            || map_height < MIN_MAP_HEIGHT.min(level_height)
+        {
+            return None;
+        }
+
+        let mut layout = Self {
+            map_width,
+            map_height,
+            border_x: 0,
+            border_y: 0,
+        };
+        let (total_width, total_height) = layout.total_size();
+        layout.border_x = ((term_width - total_width) / 2).max(SCREEN_MARGIN);
+        layout.border_y = ((term_height - total_height) / 2).max(SCREEN_MARGIN);
+        Some(layout)
+    }
+
+    /// Smallest terminal that fits the minimum map view
+    fn minimum_terminal_size() -> (usize, usize) {
+        (
+            Self::FIXED_WIDTH + MIN_MAP_WIDTH,
+            Self::FIXED_HEIGHT + MIN_MAP_HEIGHT,
+        )
+    }
+
+    /// Size of the bordered area holding the map and stat panel
+    fn outer_size(&self) -> (usize, usize) {
+        (
+            self.map_width + UI_PANEL_WIDTH + 2 * BORDER_PADDING,
+            self.map_height + 2 * BORDER_PADDING,
+        )
+    }
+
+    /// Size of the border plus the side column and message log around it
+    fn total_size(&self) -> (usize, usize) {
+        let (outer_width, outer_height) = self.outer_size();
+        (
+            outer_width + SIDE_COLUMN_GAP + SIDE_COLUMN_WIDTH,
+            outer_height + 1 + MESSAGE_LOG_LINES,
+        )
+    }
+
+    /// Top-left cell of the map view
+    fn content_origin(&self) -> (usize, usize) {
+        (
+            self.border_x + BORDER_PADDING,
+            self.border_y + BORDER_PADDING,
+        )
+    }
+
+    /// Column of the divider between the map and the stat panel
+    fn panel_x(&self) -> usize {
+        self.content_origin().0 + self.map_width
+    }
+
+    /// Column of the controls and legend, right of the border
+    fn side_column_x(&self) -> usize {
+        self.border_x + self.outer_size().0 + SIDE_COLUMN_GAP
+    }
+
+    /// Row of the message log header, below the border
+    fn log_y(&self) -> usize {
+        self.border_y + self.outer_size().1 + 1
+    }
 
-/// Create fog of war configuration for terminal rendering
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
-fn create_fog_of_war() -> FogOfWar {
-    crate::world::create_standard_fog_of_war()
+    /// Messages that fit under the log header in a terminal `term_height`
+    /// rows tall; always at least the two the layout reserves
+    fn message_rows(&self, term_height: usize) -> usize {
+        term_height
+            .saturating_sub(self.log_y() + 1 + SCREEN_MARGIN)
+            .max(MESSAGE_LOG_LINES - 1)
+    }
 }
 
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 pub struct UI {
-    pub messages: Vec<String>,
-    pub max_messages: usize,
+    /// The combat screen's log of the current fight; the game's own log
+    /// is kept in `Game::log`
+    pub messages: CombatLog,
+    /// Rows the combat log had the last time it was drawn, a page for
+    /// PageUp and PageDown
+    combat_log_rows: usize,
+    /// Player preferences, applied with `apply_settings`
+    pub settings: Settings,
+    /// The game screen last written to the terminal
+    screen: DoubleBuffer,
+    /// Highlighted entry of the title screen, kept while other screens are open
+    title_menu: MenuSelector,
+    /// Highlighted combat action, kept from turn to turn
+    combat_menu: MenuSelector,
+    /// Whether the map tints the tiles enemies in sight can reach; Tab
+    /// turns it on and off
+    pub show_threats: bool,
+    /// Effects to flash on the next frame, in reverse video
+    flashes: Vec<ScreenEffect>,
 }
 
-#[cfg(not(all(feature = "gui", target_os = "windows")))]
 impl UI {
     pub fn new() -> Self {
         UI {
-            messages: Vec::new(),
-            max_messages: 5,
+            messages: CombatLog::default(),
+            combat_log_rows: MIN_COMBAT_LOG_ROWS,
+            settings: Settings::default(),
+            screen: DoubleBuffer::new(),
+            title_menu: MenuSelector::new(TitleOption::ALL.len()),
+            combat_menu: MenuSelector::default(),
+            show_threats: false,
+            flashes: Vec::new(),
+        }
+    }
+
+    /// Takes on new preferences for everything the terminal draws
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.settings = settings.clone();
+    }
+
+    /// Flashes `effects` on the next frame drawn, unless screen effects
+    /// are turned off
+    pub fn show_effects(&mut self, effects: Vec<ScreenEffect>) {
+        if self.settings.screen_effects {
+            self.flashes.extend(effects);
+        }
+    }
+
+    /// Reverse video while `effect` is flashing, for a line drawn straight
+    /// to the terminal
+    fn flash_attribute(&self, effect: ScreenEffect) -> Attribute {
+        if self.flashes.contains(&effect) {
+            Attribute::Reverse
+        } else {
+            Attribute::NoReverse
         }
     }
 
+    /// Terminal color for a map glyph in the chosen color scheme
+    fn glyph_color(&self, color: FogColor) -> Color {
+        FogOfWar::to_terminal_color(&self.settings.color_scheme().apply(color))
+    }
+
     pub fn show_combat_tutorial(&mut self) -> io::Result<()> {
         self.clear_screen()?;
 
         // Draw a flashy combat intro
         let (term_width, term_height) = platform::get_terminal_size();
         let title = "*** COMBAT TUTORIAL ***";
-        let title_pos_x = (term_width - title.len() as u16) / 2;
+        let title_pos_x = layout::center(term_width, title.len());
 
         execute!(
             stdout(),
-            cursor::MoveTo(title_pos_x, term_height / 2 - 2),
+            cursor::MoveTo(title_pos_x, (term_height / 2).saturating_sub(2)),
             style::SetForegroundColor(Color::Red),
             style::Print(title)
         )?;
 
         let subtitle = "Prepare for battle!";
-        let subtitle_pos_x = (term_width - subtitle.len() as u16) / 2;
+        let subtitle_pos_x = layout::center(term_width, subtitle.len());
 
         execute!(
             stdout(),
@@ -92,10 +386,15 @@ impl UI {
         // Draw bordered tutorial with responsive sizing
         let (term_width, term_height) = terminal::size()?;
         let max_border_width = 80;
-        let border_width = (max_border_width).min(term_width as usize - 10);
-        let border_height = 24;
-        let start_x = ((term_width as i32 - border_width as i32) / 2).max(0) as u16;
-        let start_y = ((term_height as i32 - border_height as i32) / 2).max(0) as u16;
+        let area = Layout::centered(
+            term_width,
+            term_height,
+            max_border_width.min(term_width.saturating_sub(10)),
+            24,
+        );
+        let (start_x, start_y) = (area.x, area.y);
+        let border_width = area.width as usize;
+        let border_height = area.height as usize;
 
         self.draw_game_border(
             start_x as usize,
@@ -105,11 +404,11 @@ impl UI {
         )?;
 
         let title = "Combat Tutorial";
-        let title_pos_x = start_x + (border_width as u16 - title.len() as u16) / 2;
+        let title_pos_x = area.center_x(title);
 
         execute!(
             stdout(),
-            cursor::MoveTo(title_pos_x, start_y - 1),
+            cursor::MoveTo(title_pos_x, start_y.saturating_sub(1)),
             style::SetForegroundColor(Color::Cyan),
             style::Print(title),
             style::SetForegroundColor(Color::White)
@@ -118,7 +417,7 @@ impl UI {
         // Content positioning with responsive width
         let text_x = start_x + 3;
         let mut text_y = start_y + 2;
-        let available_width = border_width - 6; // 3 chars padding on each side
+        let available_width = border_width.saturating_sub(6); // 3 chars padding on each side
         let separator = "─".repeat(available_width);
 
         // Helper function to wrap text to available width
@@ -168,7 +467,7 @@ impl UI {
             style::SetForegroundColor(Color::White),
             style::Print(&wrap_text(
                 " - Basic attack using your weapon.",
-                available_width - 10
+                available_width.saturating_sub(10)
             ))
         )?;
 
@@ -181,7 +480,7 @@ impl UI {
             style::SetForegroundColor(Color::White),
             style::Print(&wrap_text(
                 " - Use special ability (costs mana).",
-                available_width - 14
+                available_width.saturating_sub(14)
             ))
         )?;
 
@@ -194,7 +493,7 @@ impl UI {
             style::SetForegroundColor(Color::White),
             style::Print(&wrap_text(
                 " - Use consumable from inventory.",
-                available_width - 12
+                available_width.saturating_sub(12)
             ))
         )?;
 
@@ -207,7 +506,7 @@ impl UI {
             style::SetForegroundColor(Color::White),
             style::Print(&wrap_text(
                 " - Attempt to escape (chance based on dexterity).",
-                available_width - 8
+                available_width.saturating_sub(8)
             ))
         )?;
 
@@ -256,7 +555,7 @@ impl UI {
             cursor::MoveTo(example_x, text_y),
             style::Print(wrap_text(
                 "You encounter a Goblin (HP: 20/20)",
-                available_width - 2
+                available_width.saturating_sub(2)
             ))
         )?;
 
@@ -276,7 +575,7 @@ impl UI {
             cursor::MoveTo(example_x, text_y),
             style::Print(wrap_text(
                 "You attack the Goblin for 8 damage!",
-                available_width - 2
+                available_width.saturating_sub(2)
             ))
         )?;
 
@@ -296,7 +595,7 @@ impl UI {
             cursor::MoveTo(example_x, text_y),
             style::Print(wrap_text(
                 "The Goblin hits you for 5 damage!",
-                available_width - 2
+                available_width.saturating_sub(2)
             ))
         )?;
 
@@ -383,28 +682,47 @@ impl UI {
     }
 
     pub fn clear_screen(&mut self) -> io::Result<()> {
+        // Whatever comes next replaces the game screen
+        self.screen.invalidate();
         platform::clear_screen().map_err(io::Error::other)?;
         Ok(())
     }
 
+    /// Adds a line to the combat screen's log
     pub fn add_message(&mut self, message: String) {
         self.messages.push(message);
-        if self.messages.len() > self.max_messages {
-            self.messages.remove(0);
-        }
     }
 
+    /// Clears the combat screen's log for a new fight and highlights Attack
     pub fn clear_messages(&mut self) {
         self.messages.clear();
+        self.combat_menu.reset();
     }
 
-    pub fn add_messages_from_combat(&mut self, result: &CombatResult) {
-        for message in &result.messages {
-            self.add_message(message.clone());
+    /// Shows the title menu until an entry is picked; `save` describes the
+    /// game offered by Continue and `notice` reports a problem such as a
+    /// refused load
+    pub fn title_menu(
+        &mut self,
+        save: Option<&SaveSummary>,
+        notice: Option<&str>,
+    ) -> io::Result<TitleOption> {
+        loop {
+            self.draw_title_screen(save, notice)?;
+            let code = self.wait_for_key()?.code;
+            match self.title_menu.handle_key(code) {
+                MenuInput::Chosen(index) => return Ok(TitleOption::ALL[index]),
+                MenuInput::Moved | MenuInput::Cancelled | MenuInput::Ignored => {}
+            }
         }
     }
 
-    pub fn draw_title_screen(&mut self) -> io::Result<()> {
+    /// Draws the title menu with its highlighted entry
+    fn draw_title_screen(
+        &mut self,
+        save: Option<&SaveSummary>,
+        notice: Option<&str>,
+    ) -> io::Result<()> {
         self.clear_screen()?;
 
         // Get actual terminal size
@@ -413,11 +731,11 @@ impl UI {
         let title = "Echoes of the Forgotten Realm";
         let author = "A Rusty Adventure";
 
-        // Draw a decorative border around the title area
-        let border_width = 60;
-        let border_height = 16;
-        let start_x = ((term_width as i32 - border_width as i32) / 2).max(0) as u16;
-        let start_y = ((term_height as i32 - border_height as i32) / 2).max(0) as u16;
+        // Draw a decorative border around the title area, with room below
+        // the options for a notice of up to three lines
+        let area = Layout::centered(term_width, term_height, 60, 22);
+        let (start_x, start_y) = (area.x, area.y);
+        let (border_width, border_height) = (area.width, area.height);
 
         self.draw_game_border(
             start_x as usize,
@@ -427,8 +745,8 @@ impl UI {
         )?;
 
         // Calculate centered positions relative to the border
-        let title_pos_x = start_x + (border_width - title.len() as u16) / 2;
-        let author_pos_x = start_x + (border_width - author.len() as u16) / 2;
+        let title_pos_x = area.center_x(title);
+        let author_pos_x = area.center_x(author);
         let option_pos_x = start_x + border_width / 4;
 
         execute!(
@@ -439,12 +757,63 @@ impl UI {
             cursor::MoveTo(author_pos_x, start_y + 5),
             style::SetForegroundColor(Color::White),
             style::Print(author),
-            cursor::MoveTo(option_pos_x + 5, start_y + 8),
-            style::Print("1. New Game"),
-            cursor::MoveTo(option_pos_x + 5, start_y + 10),
-            style::Print("2. Exit"),
-            cursor::MoveTo(start_x + 5, start_y + border_height - 2),
-            style::Print("Press the corresponding key to select an option..."),
+        )?;
+
+        for (i, option) in TitleOption::ALL.iter().enumerate() {
+            let (row, label) = match option {
+                TitleOption::NewGame => (7, "1. New Game"),
+                TitleOption::DailyChallenge => (8, "2. Daily Challenge"),
+                TitleOption::Continue if save.is_some() => (9, "3. Continue"),
+                TitleOption::Continue => (9, "3. Continue (no saved game)"),
+                TitleOption::FromBuildCode => (11, "4. Start from Build Code"),
+                TitleOption::HallOfFame => (12, "5. Hall of Fame"),
+                TitleOption::Achievements => (13, "6. Achievements"),
+                TitleOption::Options => (14, "7. Options"),
+                TitleOption::Help => (15, "8. Help"),
+                TitleOption::Exit => (16, "9. Exit"),
+            };
+            let color = match option {
+                TitleOption::Continue if save.is_none() && !self.title_menu.is_selected(i) => {
+                    Color::DarkGrey
+                }
+                _ => menu_color(&self.title_menu, i),
+            };
+            execute!(
+                stdout(),
+                cursor::MoveTo(option_pos_x + 3, start_y + row),
+                style::SetForegroundColor(color),
+                style::Print(format!("{}{label}", self.title_menu.marker(i))),
+                style::SetForegroundColor(Color::White)
+            )?;
+        }
+
+        if let Some(summary) = save {
+            execute!(
+                stdout(),
+                cursor::MoveTo(start_x + 5, start_y + 10),
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(summary.to_string()),
+                style::SetForegroundColor(Color::White)
+            )?;
+        }
+
+        if let Some(notice) = notice {
+            let lines = wrap_words(notice, border_width.saturating_sub(10).into());
+            for (i, line) in lines.iter().take(3).enumerate() {
+                execute!(
+                    stdout(),
+                    cursor::MoveTo(start_x + 5, start_y + 17 + i as u16),
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(line),
+                    style::SetForegroundColor(Color::White)
+                )?;
+            }
+        }
+
+        execute!(
+            stdout(),
+            cursor::MoveTo(start_x + 5, start_y + border_height.saturating_sub(2)),
+            style::Print("Up/Down and Enter, or a number key, to select"),
         )?;
 
         Ok(())
@@ -457,13 +826,19 @@ impl UI {
         // Class selection screen
         let class_type = self.choose_character_class()?;
 
-        let player = Player::new(name, class_type);
+        // Optional point-buy; Esc keeps the class's own stats
+        let player = match self.customize_stats(class_type)? {
+            Some(allocation) => allocation
+                .finish(name.clone())
+                .unwrap_or_else(|_| Player::new(name, class_type)),
+            None => Player::new(name, class_type),
+        };
         Ok(player)
     }
 
-    fn get_character_name(&mut self) -> io::Result<String> {
+    /// Asks for the character's name
+    pub fn get_character_name(&mut self) -> io::Result<String> {
         let mut name = String::new();
-        let max_name_length = 20;
 
         loop {
             self.clear_screen()?;
@@ -472,10 +847,9 @@ impl UI {
             let (term_width, term_height) = terminal::size()?;
 
             // Create a centered box for name input
-            let border_width = 60;
-            let border_height = 12;
-            let start_x = ((term_width as i32 - border_width as i32) / 2).max(0) as u16;
-            let start_y = ((term_height as i32 - border_height) / 2).max(0) as u16;
+            let area = Layout::centered(term_width, term_height, 60, 12);
+            let (start_x, start_y) = (area.x, area.y);
+            let (border_width, border_height) = (area.width, area.height);
 
             self.draw_game_border(
                 start_x as usize,
@@ -485,7 +859,7 @@ impl UI {
             )?;
 
             let title = "Character Creation";
-            let title_pos_x = start_x + (border_width - title.len() as u16) / 2;
+            let title_pos_x = area.center_x(title);
 
             // Display current name with cursor
             let display_name = if name.is_empty() {
@@ -496,7 +870,7 @@ impl UI {
 
             execute!(
                 stdout(),
-                cursor::MoveTo(title_pos_x, start_y - 1),
+                cursor::MoveTo(title_pos_x, start_y.saturating_sub(1)),
                 style::SetForegroundColor(Color::Cyan),
                 style::Print(title),
                 cursor::MoveTo(start_x + 5, start_y + 3),
@@ -528,7 +902,7 @@ impl UI {
                     KeyCode::Enter => {
                         // Confirm name entry
                         if name.is_empty() {
-                            name = "Hero".to_string();
+                            name = player::DEFAULT_NAME.to_string();
                         }
                         break;
                     }
@@ -538,7 +912,7 @@ impl UI {
                     }
                     KeyCode::Char(c) => {
                         // Add character if name isn't too long and character is valid
-                        if name.len() < max_name_length && (c.is_alphanumeric() || c == ' ') {
+                        if name.len() < player::MAX_NAME_LENGTH && player::is_name_character(c) {
                             name.push(c);
                         }
                     }
@@ -562,64 +936,180 @@ impl UI {
         Ok(name.trim().to_string())
     }
 
-    fn choose_character_class(&mut self) -> io::Result<ClassType> {
-        self.clear_screen()?;
+    /// Asks for a build code to start a new game from; None if the player
+    /// backs out with Esc
+    pub fn prompt_build_code(&mut self) -> io::Result<Option<String>> {
+        let mut code = String::new();
 
-        // Get actual terminal size
-        let (term_width, term_height) = terminal::size()?;
+        loop {
+            self.clear_screen()?;
+
+            let (term_width, term_height) = terminal::size()?;
+            let area = Layout::centered(term_width, term_height, 70, 16);
+            let (start_x, start_y) = (area.x, area.y);
+            self.draw_game_border(
+                start_x as usize,
+                start_y as usize,
+                area.width as usize,
+                area.height as usize,
+            )?;
+
+            let title = "Start from Build Code";
+            execute!(
+                stdout(),
+                cursor::MoveTo(area.center_x(title), start_y.saturating_sub(1)),
+                style::SetForegroundColor(Color::Cyan),
+                style::Print(title),
+                cursor::MoveTo(start_x + 5, start_y + 2),
+                style::SetForegroundColor(Color::White),
+                style::Print("Paste or type a build code:"),
+                style::SetForegroundColor(Color::Yellow)
+            )?;
+
+            // The end of the code so far, a few lines of it
+            let width = usize::from(area.width.saturating_sub(10)).max(1);
+            let typed: Vec<char> = code.chars().chain(['_']).collect();
+            let lines: Vec<String> = typed.chunks(width).map(String::from_iter).collect();
+            for (i, line) in lines.iter().skip(lines.len().saturating_sub(7)).enumerate() {
+                execute!(
+                    stdout(),
+                    cursor::MoveTo(start_x + 5, start_y + 4 + i as u16),
+                    style::Print(line)
+                )?;
+            }
+
+            execute!(
+                stdout(),
+                cursor::MoveTo(start_x + 5, start_y + area.height.saturating_sub(2)),
+                style::SetForegroundColor(Color::Green),
+                style::Print("ENTER to start, ESC to go back"),
+                style::SetForegroundColor(Color::White),
+                cursor::Hide
+            )?;
+
+            match self.wait_for_key()?.code {
+                KeyCode::Enter if !code.is_empty() => break,
+                KeyCode::Backspace => {
+                    code.pop();
+                }
+                KeyCode::Char(c) if !c.is_whitespace() => code.push(c),
+                KeyCode::Esc => return Ok(None),
+                _ => {}
+            }
+        }
+
+        self.flush_input_buffer()?;
+        Ok(Some(code))
+    }
 
-        // Create a centered box for class selection
-        let border_width = 70;
-        let border_height = 14;
-        let start_x = ((term_width as i32 - border_width as i32) / 2).max(0) as u16;
-        let start_y = ((term_height as i32 - border_height) / 2).max(0) as u16;
+    /// Shows the player's build code and offers it to the terminal's
+    /// clipboard, until a key is pressed
+    pub fn show_build_code(&mut self, code: &str) -> io::Result<()> {
+        self.clear_screen()?;
 
+        let (term_width, term_height) = terminal::size()?;
+        let area = Layout::centered(term_width, term_height, 70, 20);
+        let (start_x, start_y) = (area.x, area.y);
         self.draw_game_border(
             start_x as usize,
             start_y as usize,
-            border_width as usize,
-            border_height as usize,
+            area.width as usize,
+            area.height as usize,
         )?;
 
-        let title = "Choose Your Class";
-        let title_pos_x = start_x + (border_width - title.len() as u16) / 2;
-
+        let title = "Build Code";
         execute!(
             stdout(),
-            cursor::MoveTo(title_pos_x, start_y - 1),
+            cursor::MoveTo(area.center_x(title), start_y.saturating_sub(1)),
             style::SetForegroundColor(Color::Cyan),
             style::Print(title),
+            cursor::MoveTo(start_x + 5, start_y + 1),
+            style::SetForegroundColor(Color::White),
+            style::Print("Share this code to let others start from your build:"),
+            style::SetForegroundColor(Color::Yellow)
+        )?;
+
+        let width = usize::from(area.width.saturating_sub(10)).max(1);
+        let chars: Vec<char> = code.chars().collect();
+        let rows = usize::from(area.height.saturating_sub(7));
+        for (i, line) in chars.chunks(width).take(rows).enumerate() {
+            execute!(
+                stdout(),
+                cursor::MoveTo(start_x + 5, start_y + 3 + i as u16),
+                style::Print(String::from_iter(line))
+            )?;
+        }
+
+        // Terminals without clipboard support just ignore the request
+        execute!(
+            stdout(),
+            CopyToClipboard(code),
+            cursor::MoveTo(start_x + 5, start_y + area.height.saturating_sub(3)),
+            style::SetForegroundColor(Color::Green),
+            style::Print("Copied to the clipboard where the terminal allows it."),
+            cursor::MoveTo(start_x + 5, start_y + area.height.saturating_sub(2)),
             style::SetForegroundColor(Color::White),
-            cursor::MoveTo(start_x + 5, start_y + 3),
-            style::Print("1. Warrior - A powerful melee fighter with high health"),
-            cursor::MoveTo(start_x + 5, start_y + 5),
-            style::Print("2. Mage - A spellcaster with powerful magical abilities"),
-            cursor::MoveTo(start_x + 5, start_y + 7),
-            style::Print("3. Ranger - A skilled archer with balanced stats"),
-            cursor::MoveTo(start_x + 5, start_y + 9),
-            style::Print("4. Cleric - A healer with supportive abilities"),
-            cursor::MoveTo(start_x + 5, start_y + 12),
-            style::Print("Press the number key to select your class..."),
-            cursor::Hide
+            style::Print("Press any key to return...")
         )?;
 
+        self.wait_for_key()?;
+        Ok(())
+    }
+
+    fn choose_character_class(&mut self) -> io::Result<ClassType> {
+        let mut menu = MenuSelector::new(ClassType::ALL.len());
+
         let class_type = loop {
-            if let Event::Key(key_event) = event::read()? {
-                // On Windows, only process key press events
-                #[cfg(windows)]
-                {
-                    if key_event.kind != KeyEventKind::Press {
-                        continue;
-                    }
-                }
+            self.clear_screen()?;
 
-                match key_event.code {
-                    KeyCode::Char('1') => break ClassType::Warrior,
-                    KeyCode::Char('2') => break ClassType::Mage,
-                    KeyCode::Char('3') => break ClassType::Ranger,
-                    KeyCode::Char('4') => break ClassType::Cleric,
-                    _ => {}
-                }
+            // Get actual terminal size
+            let (term_width, term_height) = terminal::size()?;
+
+            // Create a centered box for class selection
+            let area = Layout::centered(term_width, term_height, 70, 14);
+            let (start_x, start_y) = (area.x, area.y);
+            let (border_width, border_height) = (area.width, area.height);
+
+            self.draw_game_border(
+                start_x as usize,
+                start_y as usize,
+                border_width as usize,
+                border_height as usize,
+            )?;
+
+            let title = "Choose Your Class";
+            let title_pos_x = area.center_x(title);
+
+            execute!(
+                stdout(),
+                cursor::MoveTo(title_pos_x, start_y.saturating_sub(1)),
+                style::SetForegroundColor(Color::Cyan),
+                style::Print(title),
+            )?;
+            for (i, class_type) in ClassType::ALL.iter().enumerate() {
+                execute!(
+                    stdout(),
+                    cursor::MoveTo(start_x + 3, start_y + 3 + 2 * i as u16),
+                    style::SetForegroundColor(menu_color(&menu, i)),
+                    style::Print(format!(
+                        "{}{}. {} - {}",
+                        menu.marker(i),
+                        i + 1,
+                        class_type,
+                        class_type.description()
+                    )),
+                )?;
+            }
+            execute!(
+                stdout(),
+                cursor::MoveTo(start_x + 5, start_y + 12),
+                style::SetForegroundColor(Color::White),
+                style::Print("Up/Down and Enter, or a number key, to select your class"),
+                cursor::Hide
+            )?;
+
+            if let MenuInput::Chosen(index) = menu.handle_key(self.wait_for_key()?.code) {
+                break ClassType::ALL[index];
             }
         };
 
@@ -629,1075 +1119,2263 @@ impl UI {
         Ok(class_type)
     }
 
-    /// Flush any remaining input events from the buffer to prevent interference
-    fn flush_input_buffer(&mut self) -> io::Result<()> {
-        use crossterm::event::{poll, read};
-        use std::time::Duration;
+    /// Lets the player spend a pool of points on their stats. Returns the
+    /// finished allocation, or None to keep the class's own stats.
+    fn customize_stats(&mut self, class_type: ClassType) -> io::Result<Option<StatAllocation>> {
+        let stats: Vec<StatType> = StatType::iter().collect();
+        let mut allocation = StatAllocation::new(class_type);
+        let mut selected = 0;
+        let mut notice = String::new();
 
-        // Read and discard any pending input events
-        while poll(Duration::from_millis(1))? {
-            let _ = read()?;
-        }
+        let chosen = loop {
+            self.clear_screen()?;
 
-        Ok(())
-    }
+            let (term_width, term_height) = terminal::size()?;
 
-    pub fn draw_game_screen(
-        &mut self,
-        player: &Player,
-        level: &Level,
-        dungeon: &Dungeon,
-    ) -> io::Result<()> {
-        self.clear_screen()?;
+            let area = Layout::centered(term_width, term_height, 70, 18);
+            let (start_x, start_y) = (area.x, area.y);
+            let (border_width, border_height) = (area.width, area.height);
 
-        // Get actual terminal size
-        let (term_width, term_height) = terminal::size()?;
+            self.draw_game_border(
+                start_x as usize,
+                start_y as usize,
+                border_width as usize,
+                border_height as usize,
+            )?;
 
-        // Define our game dimensions with added padding
-        let content_width = MAP_WIDTH + UI_PANEL_WIDTH;
-        let content_height = MAP_HEIGHT;
+            let title = format!("Customize Your {class_type}");
+            let title_pos_x = area.center_x(&title);
 
-        // Add border padding to create outer border dimensions
-        let outer_width = content_width + (BORDER_PADDING * 2);
-        let outer_height = content_height + (BORDER_PADDING * 2);
+            execute!(
+                stdout(),
+                cursor::MoveTo(title_pos_x, start_y.saturating_sub(1)),
+                style::SetForegroundColor(Color::Cyan),
+                style::Print(&title),
+                cursor::MoveTo(start_x + 3, start_y + 2),
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(format!(
+                    "Points left: {} of {STAT_POINT_POOL}",
+                    allocation.points_left()
+                )),
+            )?;
+            for (i, stat) in stats.iter().enumerate() {
+                let color = if i == selected {
+                    Color::Green
+                } else {
+                    Color::White
+                };
+                execute!(
+                    stdout(),
+                    cursor::MoveTo(start_x + 3, start_y + 4 + i as u16),
+                    style::SetForegroundColor(color),
+                    style::Print(format!(
+                        "{}{:<13} < {:>2} >   ({}-{})",
+                        if i == selected { "> " } else { "  " },
+                        stat.to_string(),
+                        allocation.stats().get(*stat),
+                        allocation.floor(*stat),
+                        allocation.cap(*stat)
+                    )),
+                )?;
+            }
 
-        // Make sure we have enough space
-        if term_width < (outer_width as u16 + 2) || term_height < (outer_height as u16 + 2) {
-            // Terminal too small, display error message
+            let preview = allocation.preview();
             execute!(
                 stdout(),
-                cursor::MoveTo(0, 0),
-                style::SetForegroundColor(Color::Red),
+                cursor::MoveTo(start_x + 3, start_y + 10),
+                style::SetForegroundColor(Color::Cyan),
                 style::Print(format!(
-                    "Terminal too small! Need at least {}x{}",
-                    outer_width + 2,
-                    outer_height + 2
-                ))
+                    "HP {}  MP {}  Attack {}  Defense {}",
+                    preview.max_health, preview.max_mana, preview.attack, preview.defense
+                )),
+                cursor::MoveTo(start_x + 3, start_y + 12),
+                style::SetForegroundColor(Color::Red),
+                style::Print(&notice),
+                cursor::MoveTo(start_x + 3, start_y + 14),
+                style::SetForegroundColor(Color::White),
+                style::Print("Up/Down: Stat  Left/Right: Adjust  Enter: Confirm"),
+                cursor::MoveTo(start_x + 3, start_y + 15),
+                style::Print("Esc: Keep the class's own stats"),
+                cursor::Hide
             )?;
-            return Ok(());
-        }
 
-        // Calculate the starting coordinates to center the game (for outer border)
-        let border_start_x = ((term_width as usize - outer_width) / 2).max(2);
-        let border_start_y = ((term_height as usize - outer_height) / 2).max(2);
+            notice.clear();
+            match self.wait_for_key()?.code {
+                KeyCode::Up => selected = selected.checked_sub(1).unwrap_or(stats.len() - 1),
+                KeyCode::Down => selected = (selected + 1) % stats.len(),
+                KeyCode::Left => {
+                    allocation.lower(stats[selected]);
+                }
+                KeyCode::Right => {
+                    allocation.raise(stats[selected]);
+                }
+                KeyCode::Enter if allocation.is_complete() => break Some(allocation),
+                KeyCode::Enter => {
+                    notice = format!(
+                        "Spend {} more point(s), or Esc to keep the defaults",
+                        allocation.points_left()
+                    );
+                }
+                KeyCode::Esc => break None,
+                _ => {}
+            }
+        };
 
-        // Calculate inner content starting position (inside the border)
-        let content_start_x = border_start_x + BORDER_PADDING;
-        let content_start_y = border_start_y + BORDER_PADDING;
+        self.flush_input_buffer()?;
 
-        // Draw border around the game area
-        self.draw_game_border(border_start_x, border_start_y, outer_width, outer_height)?;
+        Ok(chosen)
+    }
 
-        // Calculate center point of our view
-        let center_x = MAP_WIDTH / 2;
-        let center_y = MAP_HEIGHT / 2;
+    /// Asks whether the new character plays with permadeath
+    pub fn choose_permadeath(&mut self) -> io::Result<bool> {
+        let modes = [
+            "1. Standard - Save anywhere with F5",
+            "2. Permadeath - Autosaves as you play; death deletes the save",
+        ];
+        let mut menu = MenuSelector::new(modes.len());
 
-        // Windows-specific optimized rendering
-        #[cfg(windows)]
-        {
-            // Check if running in Command Prompt for specialized optimization
-            let is_cmd = platform::is_command_prompt();
-
-            if is_cmd {
-                // Command Prompt specialized rendering - line-by-line with minimal colors
-                self.render_cmd_optimized(
-                    level,
-                    center_x,
-                    center_y,
-                    content_start_x,
-                    content_start_y,
-                )?;
-            } else {
-                // Standard Windows Terminal/PowerShell rendering with centralized fog of war
-                // Batch all rendering operations for better Windows performance
-                let mut render_buffer = Vec::new();
-                let fog_of_war = create_fog_of_war();
-
-                for screen_y in 0..MAP_HEIGHT {
-                    for screen_x in 0..MAP_WIDTH {
-                        // Calculate map coordinates by offsetting from player position
-                        let map_x = level.player_position.x - center_x as i32 + screen_x as i32;
-                        let map_y = level.player_position.y - center_y as i32 + screen_y as i32;
-                        let pos = Position::new(map_x, map_y);
-
-                        // Use centralized fog of war processing
-                        let fog_result =
-                            fog_of_war.process_position(level, pos, level.player_position);
-
-                        // Convert fog color to terminal color
-                        let terminal_color = if let Some(fog_color) = fog_result.color {
-                            FogOfWar::to_terminal_color(&fog_color)
-                        } else {
-                            Color::Black
-                        };
-
-                        if fog_result.should_render {
-                            render_buffer.push((
-                                (content_start_x + screen_x) as u16,
-                                (content_start_y + screen_y) as u16,
-                                terminal_color,
-                                fog_result.character,
-                            ));
-                        }
-                    }
-                }
+        let permadeath = loop {
+            self.clear_screen()?;
 
-                // Batch render all characters with minimal color changes
-                let mut current_color = Color::White;
-                for (x, y, color, ch) in render_buffer {
-                    queue!(stdout(), cursor::MoveTo(x, y))?;
-                    if color != current_color {
-                        queue!(stdout(), style::SetForegroundColor(color))?;
-                        current_color = color;
-                    }
-                    queue!(stdout(), style::Print(ch))?;
-                }
-                stdout().flush()?;
-            }
-        }
+            let (term_width, term_height) = terminal::size()?;
 
-        // Non-Windows platforms use original rendering
-        // Non-Windows systems with full ANSI support using centralized fog of war
-        #[cfg(not(windows))]
-        {
-            let fog_of_war = create_fog_of_war();
-
-            for screen_y in 0..MAP_HEIGHT {
-                for screen_x in 0..MAP_WIDTH {
-                    // Calculate map coordinates by offsetting from player position
-                    let map_x = level.player_position.x - center_x as i32 + screen_x as i32;
-                    let map_y = level.player_position.y - center_y as i32 + screen_y as i32;
-                    let pos = Position::new(map_x, map_y);
-
-                    // Use centralized fog of war processing
-                    let fog_result = fog_of_war.process_position(level, pos, level.player_position);
-
-                    if fog_result.should_render {
-                        // Convert fog color to terminal color
-                        let terminal_color = if let Some(fog_color) = fog_result.color {
-                            FogOfWar::to_terminal_color(&fog_color)
-                        } else {
-                            Color::Black
-                        };
-
-                        execute!(
-                            stdout(),
-                            cursor::MoveTo(
-                                (content_start_x + screen_x) as u16,
-                                (content_start_y + screen_y) as u16
-                            ),
-                            style::SetForegroundColor(terminal_color),
-                            style::Print(fog_result.character)
-                        )?;
-                    }
-                }
-            }
-        }
+            let area = Layout::centered(term_width, term_height, 70, 12);
+            let (start_x, start_y) = (area.x, area.y);
+            let (border_width, border_height) = (area.width, area.height);
 
-        // UI panel starts to the right of the map
-        let ui_start_x = content_start_x + MAP_WIDTH;
+            self.draw_game_border(
+                start_x as usize,
+                start_y as usize,
+                border_width as usize,
+                border_height as usize,
+            )?;
 
-        // Draw vertical divider between map and UI panel
-        #[cfg(windows)]
-        {
-            // Batch vertical divider rendering on Windows
-            queue!(stdout(), style::SetForegroundColor(Color::White))?;
-            for y in 0..MAP_HEIGHT {
-                queue!(
+            let title = "Choose Your Fate";
+            let title_pos_x = area.center_x(title);
+
+            execute!(
+                stdout(),
+                cursor::MoveTo(title_pos_x, start_y.saturating_sub(1)),
+                style::SetForegroundColor(Color::Cyan),
+                style::Print(title),
+            )?;
+            for (i, mode) in modes.iter().enumerate() {
+                execute!(
                     stdout(),
-                    cursor::MoveTo(ui_start_x as u16, (content_start_y + y) as u16),
-                    style::Print("│")
+                    cursor::MoveTo(start_x + 3, start_y + 3 + 2 * i as u16),
+                    style::SetForegroundColor(menu_color(&menu, i)),
+                    style::Print(format!("{}{mode}", menu.marker(i))),
                 )?;
             }
-            stdout().flush()?;
-        }
-        #[cfg(not(windows))]
-        {
-            for y in 0..MAP_HEIGHT {
+            execute!(
+                stdout(),
+                cursor::MoveTo(start_x + 5, start_y + 9),
+                style::SetForegroundColor(Color::White),
+                style::Print("Up/Down and Enter, or a number key, to select a mode"),
+                cursor::Hide
+            )?;
+
+            if let MenuInput::Chosen(index) = menu.handle_key(self.wait_for_key()?.code) {
+                break index == 1;
+            }
+        };
+
+        self.flush_input_buffer()?;
+
+        Ok(permadeath)
+    }
+
+    /// Asks whether to restore the game that was running when the game last
+    /// crashed
+    pub fn offer_crash_recovery(&mut self, crashed: &SaveSummary) -> io::Result<bool> {
+        let choices = ["1. Restore it", "2. Discard it"];
+        let mut menu = MenuSelector::new(choices.len());
+
+        let restore = loop {
+            self.clear_screen()?;
+
+            let (term_width, term_height) = terminal::size()?;
+
+            let area = Layout::centered(term_width, term_height, 70, 12);
+            let (start_x, start_y) = (area.x, area.y);
+            let (border_width, border_height) = (area.width, area.height);
+
+            self.draw_game_border(
+                start_x as usize,
+                start_y as usize,
+                border_width as usize,
+                border_height as usize,
+            )?;
+
+            execute!(
+                stdout(),
+                cursor::MoveTo(start_x + 3, start_y + 2),
+                style::SetForegroundColor(Color::Red),
+                style::Print("The game crashed last time, but your run was saved:"),
+                cursor::MoveTo(start_x + 5, start_y + 3),
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(crashed.to_string()),
+            )?;
+            for (i, choice) in choices.iter().enumerate() {
                 execute!(
                     stdout(),
-                    cursor::MoveTo(ui_start_x as u16, (content_start_y + y) as u16),
-                    style::SetForegroundColor(Color::White),
-                    style::Print("│")
+                    cursor::MoveTo(start_x + 3, start_y + 5 + 2 * i as u16),
+                    style::SetForegroundColor(menu_color(&menu, i)),
+                    style::Print(format!("{}{choice}", menu.marker(i))),
                 )?;
             }
+            execute!(
+                stdout(),
+                cursor::MoveTo(start_x + 5, start_y + 10),
+                style::SetForegroundColor(Color::White),
+                style::Print("Up/Down and Enter, or a number key, to select"),
+                cursor::Hide
+            )?;
+
+            if let MenuInput::Chosen(index) = menu.handle_key(self.wait_for_key()?.code) {
+                break index == 0;
+            }
+        };
+
+        self.flush_input_buffer()?;
+
+        Ok(restore)
+    }
+
+    /// Flush any remaining input events from the buffer to prevent interference
+    fn flush_input_buffer(&mut self) -> io::Result<()> {
+        use crossterm::event::{poll, read};
+
+        // Read and discard any pending input events
+        while poll(Duration::from_millis(1))? {
+            let _ = read()?;
+        }
+
+        Ok(())
+    }
+
+    /// Look mode: moves a cursor over the map with the arrow keys, showing
+    /// what is under it. Returns the description confirmed with Enter, or
+    /// None if the player backed out with Esc
+    pub fn look_around(
+        &mut self,
+        player: &Player,
+        level: &Level,
+        dungeon: &Dungeon,
+        log: &GameLog,
+    ) -> io::Result<Option<String>> {
+        let describe = |pos| {
+            level
+                .describe_position(pos)
+                .unwrap_or_else(|| "You can't see that.".to_string())
+        };
+        let picked = self.pick_tile(player, level, dungeon, log, 'x', |pos| {
+            format!(
+                "Look: {}  (arrows move, Enter to note, Esc to exit)",
+                describe(pos)
+            )
+        })?;
+        Ok(picked.map(describe))
+    }
+
+    /// Aims an area ability with the look cursor. Returns the tile picked
+    /// with Enter, or None if the player backed out with Esc
+    pub fn aim_area_ability(
+        &mut self,
+        player: &Player,
+        level: &Level,
+        dungeon: &Dungeon,
+        log: &GameLog,
+        ability: &str,
+    ) -> io::Result<Option<Position>> {
+        self.pick_tile(player, level, dungeon, log, 'f', |pos| {
+            let under = level
+                .describe_position(pos)
+                .unwrap_or_else(|| "out of sight".to_string());
+            format!("{ability} at: {under}  (arrows aim, Enter to cast, Esc to cancel)")
+        })
+    }
+
+    /// Moves a cursor over the map with the arrow keys, starting on the
+    /// player, with `status` describing the tile under it above the map.
+    /// Returns the tile picked with Enter, or None if the player backed out
+    /// with Esc or `exit_key`.
+    fn pick_tile(
+        &mut self,
+        player: &Player,
+        level: &Level,
+        dungeon: &Dungeon,
+        log: &GameLog,
+        exit_key: char,
+        status: impl Fn(Position) -> String,
+    ) -> io::Result<Option<Position>> {
+        let mut target = level.player_position;
+
+        loop {
+            let (term_width, term_height) = terminal::size()?;
+            let mut frame = ScreenBuffer::new(term_width as usize, term_height as usize);
+            let Some(layout) =
+                self.compose_game_screen(&mut frame, player, level, dungeon, log, None)
+            else {
+                self.screen.present(frame, &mut stdout())?;
+                return Ok(None);
+            };
+            let (content_x, content_y) = layout.content_origin();
+            let center_x = (layout.map_width / 2) as i32;
+            let center_y = (layout.map_height / 2) as i32;
+
+            // Pull the cursor back into view if the terminal shrank
+            target = Position::new(
+                target.x.clamp(
+                    level.player_position.x - center_x,
+                    level.player_position.x - center_x + layout.map_width as i32 - 1,
+                ),
+                target.y.clamp(
+                    level.player_position.y - center_y,
+                    level.player_position.y - center_y + layout.map_height as i32 - 1,
+                ),
+            );
+
+            // Highlight the cursor on the map, which is centered on the player
+            let screen_x = content_x + (target.x - level.player_position.x + center_x) as usize;
+            let screen_y = content_y + (target.y - level.player_position.y + center_y) as usize;
+            frame.set(
+                screen_x,
+                screen_y,
+                Cell {
+                    symbol: 'X',
+                    fg: Color::Black,
+                    bg: Color::DarkYellow,
+                    reverse: false,
+                },
+            );
+            // The status line takes the place of the border's top edge
+            let status_y = layout.border_y - 1;
+            frame.clear_row(status_y);
+            frame.print(layout.border_x, status_y, &status(target), Color::Yellow);
+            self.screen.present(frame, &mut stdout())?;
+
+            let Some(key_event) = self.wait_for_key_or_resize()? else {
+                continue;
+            };
+            let (dx, dy) = match key_event.code {
+                KeyCode::Up => (0, -1),
+                KeyCode::Down => (0, 1),
+                KeyCode::Left => (-1, 0),
+                KeyCode::Right => (1, 0),
+                KeyCode::Enter => return Ok(Some(target)),
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Char(c) if c.eq_ignore_ascii_case(&exit_key) => return Ok(None),
+                _ => continue,
+            };
+
+            // Keep the cursor inside the visible part of the map
+            let offset_x = target.x + dx - level.player_position.x;
+            let offset_y = target.y + dy - level.player_position.y;
+            if (-center_x..layout.map_width as i32 - center_x).contains(&offset_x)
+                && (-center_y..layout.map_height as i32 - center_y).contains(&offset_y)
+            {
+                target = Position::new(target.x + dx, target.y + dy);
+            }
+        }
+    }
+
+    /// Draws the map, panels and log, with `prompt` saying what G would do
+    /// right now under the hotbar
+    pub fn draw_game_screen(
+        &mut self,
+        player: &Player,
+        level: &Level,
+        dungeon: &Dungeon,
+        log: &GameLog,
+        prompt: Option<&str>,
+    ) -> io::Result<()> {
+        let (term_width, term_height) = terminal::size()?;
+        let size = (term_width as usize, term_height as usize);
+        // A flash is one frame in reverse video, then the frame as it is
+        if !self.flashes.is_empty() {
+            let mut frame = ScreenBuffer::new(size.0, size.1);
+            self.compose_game_screen(&mut frame, player, level, dungeon, log, prompt);
+            self.screen.present(frame, &mut stdout())?;
+            self.flashes.clear();
+            std::thread::sleep(FLASH_DURATION);
+        }
+        let mut frame = ScreenBuffer::new(size.0, size.1);
+        self.compose_game_screen(&mut frame, player, level, dungeon, log, prompt);
+        self.screen.present(frame, &mut stdout())
+    }
+
+    /// Draws the game screen into `frame`, fitted to its size. Returns the
+    /// layout used, or None if the frame is too small for the game.
+    fn compose_game_screen(
+        &self,
+        frame: &mut ScreenBuffer,
+        player: &Player,
+        level: &Level,
+        dungeon: &Dungeon,
+        log: &GameLog,
+        prompt: Option<&str>,
+    ) -> Option<GameLayout> {
+        // Fit the screen to the terminal; this is redone on every resize
+        let (term_width, term_height) = frame.size();
+        let Some(layout) = GameLayout::fit(term_width, term_height, level.width, level.height)
+        else {
+            // Terminal too small, display error message
+            let (min_width, min_height) = GameLayout::minimum_terminal_size();
+            frame.print(
+                0,
+                0,
+                &format!("Terminal too small! Need at least {min_width}x{min_height}"),
+                Color::Red,
+            );
+            return None;
+        };
+        let (border_start_x, border_start_y) = (layout.border_x, layout.border_y);
+        let (outer_width, outer_height) = layout.outer_size();
+
+        // Calculate inner content starting position (inside the border)
+        let (content_start_x, content_start_y) = layout.content_origin();
+
+        // Draw border around the game area, its top edge above the content
+        frame.draw_box(
+            border_start_x,
+            border_start_y - 1,
+            outer_width,
+            outer_height + 2,
+            "Echoes of the Forgotten Realm",
+        );
+
+        // Build the visible part of the map, centered on the player
+        let mut map = MapFrame::centered(
+            level,
+            layout.map_width,
+            layout.map_height,
+            self.settings.glyph_set(),
+        );
+        if self.show_threats {
+            map.show_threats(level);
+        }
+        for cell in &map.cells {
+            frame.set(
+                content_start_x + cell.x,
+                content_start_y + cell.y,
+                Cell {
+                    bg: cell
+                        .glyph
+                        .background
+                        .map_or(Color::Reset, |bg| self.glyph_color(bg)),
+                    ..Cell::new(cell.glyph.symbol, self.glyph_color(cell.glyph.color))
+                },
+            );
+        }
+
+        // UI panel starts to the right of the map, behind a vertical divider
+        let ui_start_x = layout.panel_x();
+        for y in 0..layout.map_height {
+            frame.set(
+                ui_start_x,
+                content_start_y + y,
+                Cell::new('│', Color::White),
+            );
         }
 
         // Draw player stats in the UI panel
         let ui_text_x = ui_start_x + 2; // Offset from the divider
 
-        // Player stats rendering with Windows optimization
-        #[cfg(windows)]
-        {
-            let is_cmd = platform::is_command_prompt();
+        // Player stats and location
+        let panel = StatPanel::new(player, dungeon);
+        let level_up = self.flashes.contains(&ScreenEffect::LevelUp);
+        let hurt = self.flashes.contains(&ScreenEffect::Hurt);
+        frame.print(ui_text_x, content_start_y + 1, &panel.name, Color::Cyan);
+        if level_up {
+            frame.reverse(ui_text_x, content_start_y + 1, panel.name.chars().count());
+        }
+        for (i, row) in panel.rows.iter().enumerate() {
+            frame.print(ui_text_x, content_start_y + 2 + i, row, Color::White);
+            if level_up {
+                frame.reverse(ui_text_x, content_start_y + 2 + i, row.chars().count());
+            }
+        }
+        // "HP ########-------- 34/50", the bar colored by how full it is
+        let bars_y = content_start_y + 2 + panel.rows.len();
+        for (i, stat) in panel.bars.iter().enumerate() {
+            let cells = stat.bar.cells(self.settings.glyph_set());
+            let bar_x = ui_text_x + stat.label.len() + 1;
+            frame.print(ui_text_x, bars_y + i, stat.label, Color::White);
+            frame.print(bar_x, bars_y + i, &cells, self.glyph_color(stat.color));
+            frame.print(
+                bar_x + stat.bar.width + 1,
+                bars_y + i,
+                &stat.bar.value(),
+                Color::White,
+            );
+            if level_up || (hurt && i == 0) {
+                let width = bar_x + stat.bar.width + 1 + stat.bar.value().len() - ui_text_x;
+                frame.reverse(ui_text_x, bars_y + i, width);
+            }
+        }
+        let location_y = bars_y + 1 + panel.bars.len();
+        frame.print(ui_text_x, location_y, "Location:", Color::Cyan);
+        frame.print(ui_text_x, location_y + 1, &panel.location, Color::White);
+        if let Some(light) = &panel.light {
+            frame.print(ui_text_x, location_y + 3, light, Color::Yellow);
+        }
+        if let Some(companion) = &panel.companion {
+            frame.print(ui_text_x, location_y + 4, companion, Color::Green);
+        }
+        if let Some(ambient) = &panel.ambient {
+            frame.print(ui_text_x, location_y + 5, ambient, Color::Cyan);
+        }
 
-            if is_cmd {
-                // Simplified UI for Command Prompt - fewer colors, simpler layout
-                queue!(
-                    stdout(),
-                    cursor::MoveTo(ui_text_x as u16, (content_start_y + 1) as u16),
-                    style::SetForegroundColor(Color::White),
-                    style::Print(format!("{} L{}", player.name, player.level))
-                )?;
-                queue!(
-                    stdout(),
-                    cursor::MoveTo(ui_text_x as u16, (content_start_y + 2) as u16),
-                    style::Print(format!("HP:{}/{}", player.health, player.max_health))
-                )?;
-                queue!(
-                    stdout(),
-                    cursor::MoveTo(ui_text_x as u16, (content_start_y + 3) as u16),
-                    style::Print(format!("MP:{}/{}", player.mana, player.max_mana))
-                )?;
-                queue!(
-                    stdout(),
-                    cursor::MoveTo(ui_text_x as u16, (content_start_y + 4) as u16),
-                    style::Print(format!("Gold:{}", player.gold))
-                )?;
-                stdout().flush()?;
+        // The hotbar runs under the map and stat panel, inside the border
+        let hotbar_y = content_start_y + layout.map_height + 1;
+        let slot_width = (layout.map_width + UI_PANEL_WIDTH) / HOTBAR_SLOTS;
+        for (i, slot) in InventoryManager::hotbar_slots(player).iter().enumerate() {
+            frame.print(
+                content_start_x + i * slot_width,
+                hotbar_y,
+                &slot.label_within(slot_width - 1),
+                hotbar_color(slot),
+            );
+        }
+        if let Some(prompt) = prompt {
+            let prompt: String = prompt
+                .chars()
+                .take(layout.map_width + UI_PANEL_WIDTH)
+                .collect();
+            frame.print(content_start_x, hotbar_y + 1, &prompt, Color::Yellow);
+        }
+
+        // Draw message log below the border
+        let log_start_y = layout.log_y();
+
+        // The newest messages, as many as fit; this turn's stand out
+        let rows = layout.message_rows(term_height);
+        let latest_turn = log.latest(1).next().map(|entry| entry.turn);
+        let this_turn = log
+            .iter()
+            .rev()
+            .take_while(|entry| Some(entry.turn) == latest_turn)
+            .count();
+        let hidden = this_turn.saturating_sub(rows);
+
+        let title = "Message Log";
+        frame.print(border_start_x, log_start_y, title, Color::Cyan);
+        let hint = if hidden > 0 {
+            format!(" (+{hidden} more this turn, L to read them)")
+        } else {
+            " (L for the full log)".to_string()
+        };
+        frame.print(
+            border_start_x + title.len(),
+            log_start_y,
+            &hint,
+            Color::DarkGrey,
+        );
+
+        // Calculate available width for messages
+        let available_width = outer_width;
+
+        for (i, entry) in log.latest(rows).enumerate() {
+            // Truncate long messages
+            let truncated_message: String = if entry.text.chars().count() > available_width {
+                let kept: String = entry
+                    .text
+                    .chars()
+                    .take(available_width.saturating_sub(3))
+                    .collect();
+                format!("{kept}...")
             } else {
-                queue!(
-                    stdout(),
-                    cursor::MoveTo(ui_text_x as u16, (content_start_y + 1) as u16),
-                    style::SetForegroundColor(Color::Cyan),
-                    style::Print(format!("{}", player.name))
-                )?;
-                queue!(
-                    stdout(),
-                    cursor::MoveTo(ui_text_x as u16, (content_start_y + 2) as u16),
-                    style::SetForegroundColor(Color::White)
-                )?;
-                queue!(
+                entry.text.clone()
+            };
+
+            let color = if Some(entry.turn) == latest_turn {
+                log_color(entry.category)
+            } else {
+                Color::DarkGrey
+            };
+            frame.print(
+                border_start_x,
+                log_start_y + 1 + i,
+                &truncated_message,
+                color,
+            );
+        }
+
+        // Controls and the symbol legend below them, outside the game border
+        let controls_col_x = layout.side_column_x();
+        let controls_start_y = border_start_y + 2; // Starting near the top of the border
+        let legend_col_x = controls_col_x;
+        let legend_start_y = controls_start_y + 2 + CONTROLS.len(); // Below controls
+
+        // Draw symbol legend outside the game border (right side)
+        frame.print(legend_col_x, legend_start_y, "Symbol Legend:", Color::Cyan);
+        for (i, entry) in legend(self.settings.glyph_set()).iter().enumerate() {
+            let y = legend_start_y + 1 + i;
+            frame.set(
+                legend_col_x,
+                y,
+                Cell::new(entry.glyph.symbol, self.glyph_color(entry.glyph.color)),
+            );
+            frame.print(
+                legend_col_x + 1,
+                y,
+                &format!(" - {}", entry.label),
+                Color::White,
+            );
+        }
+
+        // Draw controls outside the game border
+        frame.print(controls_col_x, controls_start_y, "Controls:", Color::Cyan);
+        for (i, line) in CONTROLS.iter().enumerate() {
+            frame.print(controls_col_x, controls_start_y + 1 + i, line, Color::White);
+        }
+
+        Some(layout)
+    }
+
+    // Helper function to draw a border around the game area
+    fn draw_game_border(
+        &self,
+        start_x: usize,
+        start_y: usize,
+        width: usize,
+        height: usize,
+    ) -> io::Result<()> {
+        let (term_width, term_height) = terminal::size()?;
+        let title = "Echoes of the Forgotten Realm";
+        // Too small a terminal for any border at all: draw none
+        let Some(border) = Border::fit(
+            start_x,
+            start_y,
+            width,
+            height,
+            term_width,
+            term_height,
+            title,
+        ) else {
+            return Ok(());
+        };
+        let line = |left: &str, right: &str| {
+            format!(
+                "{left}{}{right}",
+                "─".repeat((border.right - border.left - 1) as usize)
+            )
+        };
+
+        execute!(
+            stdout(),
+            style::SetForegroundColor(Color::White),
+            cursor::MoveTo(border.left, border.top),
+            style::Print(line("┌", "┐")),
+            cursor::MoveTo(border.left, border.bottom),
+            style::Print(line("└", "┘"))
+        )?;
+
+        // The title sits in the top line, if there's room for it
+        if let Some(title_x) = border.title_x {
+            execute!(
+                stdout(),
+                cursor::MoveTo(title_x, border.top),
+                style::SetForegroundColor(Color::Cyan),
+                style::Print(title),
+                style::SetForegroundColor(Color::White)
+            )?;
+        }
+
+        for y in border.top + 1..border.bottom {
+            execute!(
+                stdout(),
+                cursor::MoveTo(border.left, y),
+                style::Print("│"),
+                cursor::MoveTo(border.right, y),
+                style::Print("│")
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Draws the inventory with the item `menu` highlights
+    /// Draws the inventory; `at_shrine` adds enchanting to the keys shown
+    pub fn draw_inventory_screen(
+        &mut self,
+        player: &Player,
+        menu: &MenuSelector,
+        at_shrine: bool,
+    ) -> io::Result<()> {
+        self.clear_screen()?;
+
+        execute!(
+            stdout(),
+            cursor::MoveTo(30, 1),
+            style::SetForegroundColor(Color::Cyan),
+            style::Print("Inventory"),
+            style::SetForegroundColor(Color::White),
+            cursor::MoveTo(10, 3),
+            style::Print(format!("Gold: {}", player.gold)),
+            cursor::MoveTo(30, 3),
+            style::Print(InventoryManager::weight_text(player))
+        )?;
+
+        if InventoryManager::is_empty(player) {
+            execute!(
+                stdout(),
+                cursor::MoveTo(10, 5),
+                style::Print("Your inventory is empty.")
+            )?;
+        } else {
+            execute!(
+                stdout(),
+                cursor::MoveTo(5, 5),
+                style::Print("Items:"),
+                cursor::MoveTo(5, 6),
+                style::Print("------")
+            )?;
+
+            let items = InventoryManager::get_items(player);
+            for (i, item_info) in items.iter().enumerate() {
+                let equipped_marker = if item_info.is_equipped { " [E]" } else { "" };
+                let (color, rarity_tag) = match item_info.rarity {
+                    Some(rarity) => (rarity_color(rarity), format!(" <{rarity}>")),
+                    None => (Color::White, String::new()),
+                };
+
+                execute!(
                     stdout(),
+                    cursor::MoveTo(3, 7 + i as u16),
+                    style::SetForegroundColor(menu_color(menu, i)),
+                    style::Print(format!("{}{}. ", menu.marker(i), i + 1)),
+                    style::SetForegroundColor(color),
+                    style::Print(&item_info.name),
+                    style::SetForegroundColor(Color::White),
                     style::Print(format!(
-                        "Level {} {}",
-                        player.level, player.class.class_type
+                        "{rarity_tag}{}{equipped_marker}{}",
+                        item_info.durability_tag(),
+                        item_info.weight_tag()
                     ))
                 )?;
-                queue!(
-                    stdout(),
-                    cursor::MoveTo(ui_text_x as u16, (content_start_y + 3) as u16),
-                    style::Print(format!("HP: {}/{}", player.health, player.max_health))
-                )?;
-                queue!(
-                    stdout(),
-                    cursor::MoveTo(ui_text_x as u16, (content_start_y + 4) as u16),
-                    style::Print(format!("MP: {}/{}", player.mana, player.max_mana))
-                )?;
-                queue!(
-                    stdout(),
-                    cursor::MoveTo(ui_text_x as u16, (content_start_y + 5) as u16),
-                    style::Print(format!("XP: {}/{}", player.experience, player.level * 100))
-                )?;
-                queue!(
-                    stdout(),
-                    cursor::MoveTo(ui_text_x as u16, (content_start_y + 6) as u16),
-                    style::Print(format!("Gold: {}", player.gold))
-                )?;
-                stdout().flush()?;
             }
         }
-        #[cfg(not(windows))]
-        {
+
+        // What's on the hotbar, so it can be seen while being filled
+        execute!(
+            stdout(),
+            cursor::MoveTo(10, SCREEN_HEIGHT as u16 - 5),
+            style::SetForegroundColor(Color::Cyan),
+            style::Print("Hotbar:")
+        )?;
+        for (i, slot) in InventoryManager::hotbar_slots(player).iter().enumerate() {
+            execute!(
+                stdout(),
+                cursor::MoveTo(
+                    18 + 20 * (i as u16 % 3),
+                    SCREEN_HEIGHT as u16 - 5 + i as u16 / 3
+                ),
+                style::SetForegroundColor(hotbar_color(slot)),
+                style::Print(slot.label_within(19))
+            )?;
+        }
+
+        let keys = if at_shrine {
+            "Enter/number: use/equip | D: drop | X: inspect | A: hotbar | R: craft | N: enchant | E: exit"
+        } else {
+            "Up/Down+Enter or number: use/equip | D: drop | X: inspect | A: hotbar | R: craft | E/Esc: exit"
+        };
+        execute!(
+            stdout(),
+            cursor::MoveTo(10, SCREEN_HEIGHT as u16 - 3),
+            style::SetForegroundColor(Color::White),
+            style::Print(keys)
+        )?;
+
+        Ok(())
+    }
+
+    /// Shows a prompt below the inventory and reads an item number (1-9),
+    /// or Enter for the `highlighted` item, returning its 0-based index or
+    /// None if another key was pressed
+    pub fn prompt_item_number(
+        &mut self,
+        prompt: &str,
+        highlighted: usize,
+    ) -> io::Result<Option<usize>> {
+        execute!(
+            stdout(),
+            cursor::MoveTo(10, SCREEN_HEIGHT as u16 - 2),
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(prompt),
+            style::SetForegroundColor(Color::White)
+        )?;
+
+        match self.wait_for_key()?.code {
+            KeyCode::Char(c) if ('1'..='9').contains(&c) => {
+                Ok(c.to_digit(10).map(|d| d as usize - 1))
+            }
+            KeyCode::Enter => Ok(Some(highlighted)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Shows what enchanting an item takes and asks whether to go ahead,
+    /// returning true for Y or Enter
+    pub fn confirm_enchant(&mut self, offer: &EnchantOffer) -> io::Result<bool> {
+        self.clear_screen()?;
+        execute!(
+            stdout(),
+            cursor::MoveTo(30, 1),
+            style::SetForegroundColor(Color::Magenta),
+            style::Print("Enchant at the Shrine"),
+            style::SetForegroundColor(Color::White)
+        )?;
+        for (i, line) in offer.lines().iter().enumerate() {
+            execute!(
+                stdout(),
+                cursor::MoveTo(10, 4 + i as u16),
+                style::Print(line)
+            )?;
+        }
+
+        let prompt = if offer.affordable() {
+            "Offer it up? (Y/Enter: yes, any other key: no)"
+        } else {
+            "Press any key to go back..."
+        };
+        execute!(
+            stdout(),
+            cursor::MoveTo(10, 5 + offer.lines().len() as u16),
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(prompt),
+            style::SetForegroundColor(Color::White)
+        )?;
+
+        let confirmed = matches!(
+            self.wait_for_key()?.code,
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter
+        );
+        Ok(confirmed && offer.affordable())
+    }
+
+    /// Lists the recipes, those the player has the materials for in green,
+    /// and reads which to craft, returning its index or None to go back
+    pub fn choose_recipe(&mut self, player: &Player) -> io::Result<Option<usize>> {
+        self.clear_screen()?;
+        execute!(
+            stdout(),
+            cursor::MoveTo(30, 1),
+            style::SetForegroundColor(Color::Yellow),
+            style::Print("Crafting"),
+            style::SetForegroundColor(Color::White)
+        )?;
+        for (i, recipe) in RECIPES.iter().enumerate() {
+            let color = if InventoryManager::can_craft(player, recipe) {
+                Color::Green
+            } else {
+                Color::DarkGrey
+            };
+            execute!(
+                stdout(),
+                cursor::MoveTo(10, 4 + i as u16),
+                style::SetForegroundColor(color),
+                style::Print(format!("{}. {}", i + 1, recipe.label()))
+            )?;
+        }
+
+        let materials = Material::ALL
+            .iter()
+            .map(|material| {
+                format!(
+                    "{} {}",
+                    InventoryManager::material_count(player, *material),
+                    material.name()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        execute!(
+            stdout(),
+            cursor::MoveTo(10, 5 + RECIPES.len() as u16),
+            style::SetForegroundColor(Color::White),
+            style::Print(format!("Carrying: {materials}")),
+            cursor::MoveTo(10, 7 + RECIPES.len() as u16),
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(format!(
+                "Number (1-{}): craft | any other key: back",
+                RECIPES.len()
+            )),
+            style::SetForegroundColor(Color::White)
+        )?;
+
+        match self.wait_for_key()?.code {
+            KeyCode::Char(c) => Ok(c
+                .to_digit(10)
+                .map(|d| d as usize)
+                .filter(|d| (1..=RECIPES.len()).contains(d))
+                .map(|d| d - 1)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Shows a talk with an NPC and asks whether to take them up on their
+    /// offer, returning true for Y or Enter
+    pub fn show_dialogue(&mut self, dialogue: &Dialogue) -> io::Result<bool> {
+        self.clear_screen()?;
+        execute!(
+            stdout(),
+            cursor::MoveTo(30, 1),
+            style::SetForegroundColor(Color::Cyan),
+            style::Print(dialogue.speaker),
+            style::SetForegroundColor(Color::White)
+        )?;
+        for (i, line) in dialogue.lines.iter().enumerate() {
+            execute!(
+                stdout(),
+                cursor::MoveTo(10, 4 + i as u16),
+                style::Print(line)
+            )?;
+        }
+
+        let prompt = match &dialogue.offer {
+            Some(offer) => format!("{offer}? (Y/Enter: yes, any other key: leave)"),
+            None => "Press any key to leave...".to_string(),
+        };
+        execute!(
+            stdout(),
+            cursor::MoveTo(10, 5 + dialogue.lines.len() as u16),
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(prompt),
+            style::SetForegroundColor(Color::White)
+        )?;
+
+        let accepted = matches!(
+            self.wait_for_key()?.code,
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter
+        );
+        Ok(accepted && dialogue.offer.is_some())
+    }
+
+    /// Shows a prompt below the inventory and reads a hotbar slot (1-5) for
+    /// the highlighted item, returning it 0-based or None if another key
+    /// was pressed
+    pub fn prompt_hotbar_slot(&mut self) -> io::Result<Option<usize>> {
+        execute!(
+            stdout(),
+            cursor::MoveTo(10, SCREEN_HEIGHT as u16 - 2),
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(format!(
+                "Put the highlighted item on which hotbar slot? (1-{HOTBAR_SLOTS})"
+            )),
+            style::SetForegroundColor(Color::White)
+        )?;
+
+        match self.wait_for_key()?.code {
+            KeyCode::Char(c) => Ok(c
+                .to_digit(10)
+                .map(|d| d as usize)
+                .filter(|d| (1..=HOTBAR_SLOTS).contains(d))
+                .map(|d| d - 1)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Draws the inspection pane for one item beside the inventory list
+    pub fn draw_item_details(&mut self, details: &ItemDetails) -> io::Result<()> {
+        let x = 45;
+        let width = 32;
+        let name_color = details.rarity.map(rarity_color).unwrap_or(Color::Yellow);
+
+        // Word-wrap the description to fit beside the item list
+        let mut description_lines: Vec<String> = Vec::new();
+        for word in details.description.split_whitespace() {
+            match description_lines.last_mut() {
+                Some(line) if line.len() + 1 + word.len() <= width => {
+                    line.push(' ');
+                    line.push_str(word);
+                }
+                _ => description_lines.push(word.to_string()),
+            }
+        }
+
+        execute!(
+            stdout(),
+            cursor::MoveTo(x, 5),
+            style::SetForegroundColor(name_color),
+            style::Print(&details.name),
+            style::SetForegroundColor(Color::White),
+            cursor::MoveTo(x, 6),
+            style::Print("-".repeat(details.name.len())),
+            style::SetForegroundColor(Color::DarkGrey)
+        )?;
+
+        let mut y = 7;
+        for line in &description_lines {
+            execute!(stdout(), cursor::MoveTo(x, y), style::Print(line))?;
+            y += 1;
+        }
+
+        execute!(stdout(), style::SetForegroundColor(Color::White))?;
+        y += 1;
+        for line in details.lines() {
+            execute!(stdout(), cursor::MoveTo(x, y), style::Print(line))?;
+            y += 1;
+        }
+
+        execute!(
+            stdout(),
+            cursor::MoveTo(x, y + 1),
+            style::SetForegroundColor(Color::Yellow),
+            style::Print("Press any key to close..."),
+            style::SetForegroundColor(Color::White)
+        )?;
+
+        Ok(())
+    }
+
+    /// Draws the character sheet; `run_label` names a special run, such as
+    /// a daily challenge, and `difficulty_note` is shown below it
+    pub fn draw_character_screen(
+        &mut self,
+        player: &Player,
+        turns: u32,
+        ng_plus: u32,
+        run_label: Option<&str>,
+        difficulty_note: Option<&str>,
+    ) -> io::Result<()> {
+        self.clear_screen()?;
+        let (trait_name, trait_text) = player.class.class_type.passive();
+
+        execute!(
+            stdout(),
+            cursor::MoveTo(30, 1),
+            style::SetForegroundColor(Color::Cyan),
+            style::Print("Character Sheet"),
+            style::SetForegroundColor(Color::White),
+            cursor::MoveTo(10, 3),
+            style::Print(format!("Name: {}", player.name)),
+            cursor::MoveTo(10, 4),
+            style::Print(format!("Class: {}", player.class.class_type)),
+            cursor::MoveTo(10, 24),
+            style::Print(format!("Trait: {trait_name} - {trait_text}")),
+            cursor::MoveTo(10, 5),
+            style::Print(format!("Level: {}", player.level)),
+            cursor::MoveTo(10, 6),
+            style::Print(format!(
+                "Experience: {}/{}",
+                player.experience,
+                player.xp_for_next_level()
+            )),
+            cursor::MoveTo(10, 7),
+            style::Print(format!("Health: {}/{}", player.health, player.max_health)),
+            cursor::MoveTo(10, 8),
+            style::Print(format!("Mana: {}/{}", player.mana, player.max_mana)),
+            cursor::MoveTo(10, 9),
+            style::Print(format!("Gold: {}", player.gold)),
+            cursor::MoveTo(40, 3),
+            style::Print(format!("Turns: {turns}")),
+            cursor::MoveTo(10, 11),
+            style::SetForegroundColor(Color::Cyan),
+            style::Print("Stats:"),
+            style::SetForegroundColor(Color::White),
+            cursor::MoveTo(10, 12),
+            style::Print(format!("Strength: {}", player.stats.strength)),
+            cursor::MoveTo(10, 13),
+            style::Print(format!("Intelligence: {}", player.stats.intelligence)),
+            cursor::MoveTo(10, 14),
+            style::Print(format!("Dexterity: {}", player.stats.dexterity)),
+            cursor::MoveTo(10, 15),
+            style::Print(format!("Constitution: {}", player.stats.constitution)),
+            cursor::MoveTo(10, 16),
+            style::Print(format!("Wisdom: {}", player.stats.wisdom)),
+            cursor::MoveTo(40, 11),
+            style::SetForegroundColor(Color::Cyan),
+            style::Print("Abilities:"),
+            style::SetForegroundColor(Color::White)
+        )?;
+
+        if ng_plus > 0 {
+            execute!(
+                stdout(),
+                cursor::MoveTo(40, 4),
+                style::SetForegroundColor(Color::Magenta),
+                style::Print(format!("New Game+: {ng_plus}")),
+                style::SetForegroundColor(Color::White)
+            )?;
+        }
+        if let Some(label) = run_label {
             execute!(
                 stdout(),
-                cursor::MoveTo(ui_text_x as u16, (content_start_y + 1) as u16),
-                style::SetForegroundColor(Color::Cyan),
-                style::Print(player.name.to_string()),
-                cursor::MoveTo(ui_text_x as u16, (content_start_y + 2) as u16),
-                style::SetForegroundColor(Color::White),
-                style::Print(format!(
-                    "Level {} {}",
-                    player.level, player.class.class_type
-                )),
-                cursor::MoveTo(ui_text_x as u16, (content_start_y + 3) as u16),
-                style::Print(format!("HP: {}/{}", player.health, player.max_health)),
-                cursor::MoveTo(ui_text_x as u16, (content_start_y + 4) as u16),
-                style::Print(format!("MP: {}/{}", player.mana, player.max_mana)),
-                cursor::MoveTo(ui_text_x as u16, (content_start_y + 5) as u16),
-                style::Print(format!("XP: {}/{}", player.experience, player.level * 100)),
-                cursor::MoveTo(ui_text_x as u16, (content_start_y + 6) as u16),
-                style::Print(format!("Gold: {}", player.gold))
+                cursor::MoveTo(40, 5),
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(label),
+                style::SetForegroundColor(Color::White)
+            )?;
+        }
+        if let Some(note) = difficulty_note {
+            execute!(
+                stdout(),
+                cursor::MoveTo(40, 6),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(note),
+                style::SetForegroundColor(Color::White)
             )?;
         }
 
-        // Location information with Windows optimization
-        #[cfg(windows)]
-        {
-            let is_cmd = platform::is_command_prompt();
-
-            if is_cmd {
-                // Simplified location info for Command Prompt
-                queue!(
-                    stdout(),
-                    cursor::MoveTo(ui_text_x as u16, (content_start_y + 6) as u16),
-                    style::SetForegroundColor(Color::White),
-                    style::Print(format!("{} L{}", dungeon.name, dungeon.current_level + 1))
-                )?;
-                stdout().flush()?;
-            } else {
-                queue!(
-                    stdout(),
-                    cursor::MoveTo(ui_text_x as u16, (content_start_y + 8) as u16),
-                    style::SetForegroundColor(Color::Cyan),
-                    style::Print("Location:")
-                )?;
-                queue!(
-                    stdout(),
-                    cursor::MoveTo(ui_text_x as u16, (content_start_y + 9) as u16),
-                    style::SetForegroundColor(Color::White),
-                    style::Print(format!(
-                        "{} - Level {}",
-                        dungeon.name,
-                        dungeon.current_level + 1
-                    ))
-                )?;
-                stdout().flush()?;
-            }
+        // Display unlocked abilities
+        for (i, ability) in player.available_abilities().iter().enumerate() {
+            execute!(
+                stdout(),
+                cursor::MoveTo(40, 12 + i as u16),
+                style::Print(format!("{}. {}", i + 1, ability))
+            )?;
         }
-        #[cfg(not(windows))]
-        {
+
+        // Display derived stats
+        execute!(
+            stdout(),
+            cursor::MoveTo(40, 18),
+            style::SetForegroundColor(Color::Cyan),
+            style::Print("Combat Stats:"),
+            style::SetForegroundColor(Color::White),
+            cursor::MoveTo(40, 19),
+            style::Print(format!("Attack: {}", player.attack_damage())),
+            cursor::MoveTo(40, 20),
+            style::Print(format!("Defense: {}", player.defense())),
+            cursor::MoveTo(40, 21),
+            style::Print(format!("Carry capacity: {}", player.carry_capacity()))
+        )?;
+
+        if let Some(companion) = &player.companion {
             execute!(
                 stdout(),
-                cursor::MoveTo(ui_text_x as u16, (content_start_y + 8) as u16),
+                cursor::MoveTo(10, 18),
                 style::SetForegroundColor(Color::Cyan),
-                style::Print("Location:"),
-                cursor::MoveTo(ui_text_x as u16, (content_start_y + 9) as u16),
+                style::Print("Companion:"),
                 style::SetForegroundColor(Color::White),
+                cursor::MoveTo(10, 19),
+                style::Print(companion.title()),
+                cursor::MoveTo(10, 20),
                 style::Print(format!(
-                    "{} - Level {}",
-                    dungeon.name,
-                    dungeon.current_level + 1
+                    "{}, Attack: {}",
+                    companion.status_text(),
+                    companion.attack
                 ))
             )?;
         }
 
-        // Draw message log below the border
-        let log_start_y = border_start_y + outer_height + 1; // Position below the border
-
-        // Draw message log header
         execute!(
             stdout(),
-            cursor::MoveTo(border_start_x as u16, log_start_y as u16),
-            style::SetForegroundColor(Color::Cyan),
-            style::Print(format!(
-                "Message Log: [{}/{}]",
-                self.messages.len().min(2),
-                self.messages.len()
-            ))
+            cursor::MoveTo(10, 23),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("Press B to show and copy your build code"),
+            style::SetForegroundColor(Color::White)
         )?;
 
-        // Calculate available width for messages
-        let available_width = outer_width;
+        if player.unspent_stat_points > 0 {
+            execute!(
+                stdout(),
+                cursor::MoveTo(10, 22),
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(format!(
+                    "Unspent stat points: {} (press U to allocate)",
+                    player.unspent_stat_points
+                )),
+                style::SetForegroundColor(Color::White)
+            )?;
+        }
+
+        execute!(
+            stdout(),
+            cursor::MoveTo(10, SCREEN_HEIGHT as u16 - 3),
+            style::Print("Press any key to return...")
+        )?;
 
-        // Show the most recent messages first (reversed)
-        let recent_messages: Vec<&String> = self.messages.iter().rev().take(2).collect();
+        Ok(())
+    }
 
-        for (i, message) in recent_messages.iter().enumerate() {
-            // Truncate long messages
-            let truncated_message = if message.len() > available_width {
-                format!("{}...", &message[0..available_width.saturating_sub(3)])
-            } else {
-                message.to_string()
-            };
+    /// Lets the player spend unspent stat points, returning the stats
+    /// picked in order for the game to raise. Esc leaves the remaining
+    /// points for later.
+    pub fn draw_level_up_screen(&mut self, player: &Player) -> io::Result<Vec<StatType>> {
+        let mut menu = MenuSelector::new(StatType::iter().count());
+        // Raised here as they're picked, so the screen shows the new values
+        let mut player = player.clone();
+        let mut picked = Vec::new();
+        while player.unspent_stat_points > 0 {
+            self.clear_screen()?;
 
             execute!(
                 stdout(),
-                cursor::MoveTo(border_start_x as u16, log_start_y as u16 + 1 + i as u16),
+                cursor::MoveTo(30, 1),
+                style::SetForegroundColor(Color::Yellow),
+                style::Print("Level Up!"),
                 style::SetForegroundColor(Color::White),
-                style::Print(truncated_message)
+                cursor::MoveTo(10, 3),
+                style::Print(format!(
+                    "{} reached level {}. Points to spend: {}",
+                    player.name, player.level, player.unspent_stat_points
+                ))
+            )?;
+
+            for (i, stat) in StatType::iter().enumerate() {
+                let value = match stat {
+                    StatType::Strength => player.stats.strength,
+                    StatType::Intelligence => player.stats.intelligence,
+                    StatType::Dexterity => player.stats.dexterity,
+                    StatType::Constitution => player.stats.constitution,
+                    StatType::Wisdom => player.stats.wisdom,
+                };
+                execute!(
+                    stdout(),
+                    cursor::MoveTo(8, 5 + i as u16),
+                    style::SetForegroundColor(menu_color(&menu, i)),
+                    style::Print(format!("{}{}. {stat}: {value}", menu.marker(i), i + 1)),
+                    style::SetForegroundColor(Color::White)
+                )?;
+            }
+
+            execute!(
+                stdout(),
+                cursor::MoveTo(10, 11),
+                style::Print(format!(
+                    "HP: {}/{}  MP: {}/{}",
+                    player.health, player.max_health, player.mana, player.max_mana
+                )),
+                cursor::MoveTo(10, 13),
+                style::Print("Up/Down and Enter, or 1-5, to raise a stat; Esc to decide later...")
             )?;
+
+            match menu.handle_key(self.wait_for_key()?.code) {
+                MenuInput::Chosen(index) => {
+                    if let Some(stat) = StatType::iter().nth(index) {
+                        if player.allocate_stat_point(stat).is_ok() {
+                            self.add_message(format!("{stat} increased by 1"));
+                            picked.push(stat);
+                        }
+                    }
+                }
+                MenuInput::Cancelled => break,
+                MenuInput::Moved | MenuInput::Ignored => {}
+            }
+        }
+
+        Ok(picked)
+    }
+
+    pub fn draw_combat_screen(
+        &mut self,
+        player: &Player,
+        enemies: &[Enemy],
+        target: usize,
+        context: &CombatContext,
+    ) -> io::Result<()> {
+        let view = CombatView::new(player, enemies, target, context);
+        self.draw_combat_view(player, &view)
+    }
+
+    /// Shows a combat turn's messages one at a time while the target's health
+    /// bar eases down; any key shows the rest at once. Returns how the fight
+    /// ended, once everything has been shown
+    pub fn play_combat_turn(
+        &mut self,
+        player: &Player,
+        enemies: &[Enemy],
+        target: usize,
+        health_before: i32,
+        result: &CombatResult,
+    ) -> io::Result<Option<CombatEnding>> {
+        let Some(enemy) = enemies.get(target) else {
+            return Ok(result.ending(player));
+        };
+        let clock = Instant::now();
+        let mut playback = CombatPlayback::new(self.settings.combat_message_delay());
+        playback.start_turn(
+            0.0,
+            result.messages.iter().cloned(),
+            (health_before, enemy.health),
+            result.ending(player),
+        );
+
+        // The action is already chosen, so there's nothing to forecast
+        let mut view = CombatView::new(player, enemies, target, &CombatContext::default());
+        view.forecast.clear();
+        while playback.is_playing() {
+            let now = clock.elapsed().as_secs_f64();
+            for message in playback.tick(now) {
+                self.add_message(message);
+            }
+            view.set_enemy_health(playback.enemy_health(now), enemy.max_health);
+            self.draw_combat_view(player, &view)?;
+            // The turn's flashes last a frame; a level-up waits for the map
+            self.flashes
+                .retain(|&effect| effect == ScreenEffect::LevelUp);
+
+            if event::poll(Duration::from_millis(50))? {
+                if let Event::Key(_) = event::read()? {
+                    for message in playback.skip() {
+                        self.add_message(message);
+                    }
+                }
+            }
         }
 
-        // Position for Symbol Legend outside the game border (right side)
-        let legend_col_x = border_start_x + outer_width + 2; // 2 spaces after border
-        let legend_start_y = border_start_y + 10; // Below controls
+        Ok(playback.take_ending())
+    }
 
-        // Position for Controls outside the game border
-        let controls_col_x = border_start_x + outer_width + 2; // 2 spaces after border
-        let controls_start_y = border_start_y + 2; // Starting near the top of the border
+    fn draw_combat_view(&mut self, player: &Player, view: &CombatView) -> io::Result<()> {
+        self.clear_screen()?;
 
-        // Draw symbol legend outside the game border (right side)
         execute!(
             stdout(),
-            cursor::MoveTo(legend_col_x as u16, legend_start_y as u16),
-            style::SetForegroundColor(Color::Cyan),
-            style::Print("Symbol Legend:")
-        )?;
-
-        // Create a legend of symbols and their meanings
-        #[cfg(windows)]
-        let symbols = if platform::is_command_prompt() {
-            // Simplified legend for Command Prompt
-            vec![
-                ('@', "You", Color::Yellow),
-                ('E', "Enemy", Color::Red),
-                ('!', "Item", Color::Green),
-                ('#', "Wall", Color::White),
-                ('.', "Floor", Color::White),
-                ('E', "Exit", Color::Green),
-            ]
+            cursor::MoveTo(30, 1),
+            style::SetForegroundColor(Color::Red),
+            style::Print("Combat!"),
+            style::SetForegroundColor(Color::White),
+            cursor::MoveTo(10, 3),
+            style::Print(format!("Enemy: {}", view.enemy)),
+            cursor::MoveTo(10, 4),
+            style::SetAttribute(self.flash_attribute(ScreenEffect::CriticalHit)),
+            style::Print(&view.enemy_health),
+            style::SetAttribute(Attribute::NoReverse),
+            style::Print(" "),
+            style::SetForegroundColor(self.glyph_color(view.enemy_bar.level().color())),
+            style::Print(view.enemy_bar.cells(self.settings.glyph_set())),
+            style::SetForegroundColor(Color::White)
+        )?;
+
+        // In a group fight, list everyone below the target
+        for (i, line) in view.enemies.iter().enumerate() {
+            execute!(
+                stdout(),
+                cursor::MoveTo(10, 5 + i as u16),
+                style::Print(line)
+            )?;
+        }
+        let mut shift = if view.enemies.is_empty() {
+            0
         } else {
-            vec![
-                ('@', "You (the player)", Color::Yellow),
-                ('E', "Enemy", Color::Red),
-                ('!', "Item", Color::Green),
-                ('#', "Wall", Color::White),
-                ('.', "Floor", Color::DarkGrey),
-                ('+', "Door", Color::Magenta),
-                ('C', "Chest", Color::Cyan),
-                ('>', "Stairs Down", Color::Blue),
-                ('<', "Stairs Up", Color::Blue),
-                ('E', "Exit", Color::Green),
-            ]
+            view.enemies.len() as u16 + 1
         };
 
-        #[cfg(not(windows))]
-        let symbols = vec![
-            ('@', "You (the player)", Color::Yellow),
-            ('E', "Enemy", Color::Red),
-            ('!', "Item", Color::Green),
-            ('#', "Wall", Color::White),
-            ('.', "Floor", Color::DarkGrey),
-            ('+', "Door", Color::Magenta),
-            ('C', "Chest", Color::Cyan),
-            ('>', "Stairs Down", Color::Blue),
-            ('<', "Stairs Up", Color::Blue),
-            ('E', "Exit", Color::Green),
-        ];
+        execute!(
+            stdout(),
+            cursor::MoveTo(10, 6 + shift),
+            style::Print(format!("Player: {}", player.name)),
+            cursor::MoveTo(10, 7 + shift),
+            style::SetAttribute(self.flash_attribute(ScreenEffect::Hurt)),
+            style::Print(&view.player_health),
+            style::SetAttribute(Attribute::NoReverse),
+            cursor::MoveTo(10, 8 + shift),
+            style::Print(&view.player_mana),
+            style::SetForegroundColor(Color::Green)
+        )?;
+        for line in &view.buffs {
+            shift += 1;
+            execute!(stdout(), cursor::MoveTo(10, 8 + shift), style::Print(line))?;
+        }
+        execute!(stdout(), style::SetForegroundColor(Color::DarkYellow))?;
+        for line in &view.forecast {
+            shift += 1;
+            execute!(stdout(), cursor::MoveTo(10, 9 + shift), style::Print(line))?;
+        }
+        if !view.forecast.is_empty() {
+            shift += 1;
+        }
+        // Why the numbers are what they are this turn
+        execute!(stdout(), style::SetForegroundColor(Color::Magenta))?;
+        for line in &view.modifiers {
+            execute!(stdout(), cursor::MoveTo(10, 9 + shift), style::Print(line))?;
+            shift += 1;
+        }
 
-        for (i, (symbol, meaning, color)) in symbols.iter().enumerate() {
-            if !meaning.is_empty() {
-                execute!(
-                    stdout(),
-                    cursor::MoveTo(legend_col_x as u16, (legend_start_y + 1 + i) as u16),
-                    style::SetForegroundColor(*color),
-                    style::Print(*symbol),
-                    style::SetForegroundColor(Color::White),
-                    style::Print(format!(" - {meaning}"))
-                )?;
-            }
+        execute!(
+            stdout(),
+            cursor::MoveTo(10, 10 + shift),
+            style::SetForegroundColor(Color::Cyan),
+            style::Print("Actions:"),
+            style::SetForegroundColor(Color::White)
+        )?;
+        self.combat_menu.set_len(view.actions.len());
+        for (i, action) in view.actions.iter().enumerate() {
+            execute!(
+                stdout(),
+                cursor::MoveTo(8, 11 + shift + i as u16),
+                style::SetForegroundColor(menu_color(&self.combat_menu, i)),
+                style::Print(format!("{}{action}", self.combat_menu.marker(i))),
+                style::SetForegroundColor(Color::White)
+            )?;
         }
 
-        // Draw controls outside the game border
+        // The fight's log fills the rest of the screen, newest at the bottom
+        let log_row = 12 + shift + view.actions.len() as u16;
+        let (term_width, term_height) = terminal::size()?;
+        self.combat_log_rows =
+            (term_height.saturating_sub(log_row + 2) as usize).max(MIN_COMBAT_LOG_ROWS);
+        let max_text_width = (term_width as usize).saturating_sub(12).max(10);
         execute!(
             stdout(),
-            cursor::MoveTo(controls_col_x as u16, controls_start_y as u16),
+            cursor::MoveTo(10, log_row),
             style::SetForegroundColor(Color::Cyan),
-            style::Print("Controls:"),
-            cursor::MoveTo(controls_col_x as u16, (controls_start_y + 1) as u16),
-            style::SetForegroundColor(Color::White),
-            style::Print("↑↓←→: Move"),
-            cursor::MoveTo(controls_col_x as u16, (controls_start_y + 2) as u16),
-            style::Print("I: Inventory"),
-            cursor::MoveTo(controls_col_x as u16, (controls_start_y + 3) as u16),
-            style::Print("C: Character"),
-            cursor::MoveTo(controls_col_x as u16, (controls_start_y + 4) as u16),
-            style::Print("G: Get item"),
-            cursor::MoveTo(controls_col_x as u16, (controls_start_y + 5) as u16),
-            style::Print("Q: Quit")
+            style::Print(self.messages.heading(self.combat_log_rows)),
+            style::SetForegroundColor(Color::White)
         )?;
 
+        for (i, message) in self
+            .messages
+            .visible(self.combat_log_rows)
+            .iter()
+            .enumerate()
+        {
+            let text: String = message.chars().take(max_text_width).collect();
+            execute!(
+                stdout(),
+                cursor::MoveTo(10, log_row + 1 + i as u16),
+                style::Print(text)
+            )?;
+        }
+
         Ok(())
     }
 
-    // Helper function to draw a border around the game area
-    fn draw_game_border(
-        &self,
-        start_x: usize,
-        start_y: usize,
-        width: usize,
-        height: usize,
-    ) -> io::Result<()> {
-        // Check terminal dimensions
-        let (term_width, term_height) = terminal::size()?;
-
-        // Ensure we don't start drawing outside the terminal
-        let safe_start_x = start_x.min(term_width as usize - 1);
-        let safe_start_y = start_y.min(term_height as usize - 1);
-
-        // Draw title at the top of the border
-        let title = "Echoes of the Forgotten Realm";
-        let title_start = safe_start_x + (width - title.len()) / 2;
+    pub fn draw_ability_selection(&mut self, player: &Player) -> io::Result<usize> {
+        self.clear_screen()?;
 
-        // Draw top border with title
         execute!(
             stdout(),
-            cursor::MoveTo(safe_start_x as u16, (safe_start_y - 1) as u16),
-            style::SetForegroundColor(Color::White),
-            style::Print("┌")
+            cursor::MoveTo(30, 1),
+            style::SetForegroundColor(Color::Cyan),
+            style::Print("Select Ability"),
+            style::SetForegroundColor(Color::White)
         )?;
 
-        for x in 1..width - 1 {
-            let pos_x = start_x + x;
-            if pos_x >= title_start && pos_x < title_start + title.len() {
-                // Part of the title
-                let char_idx = pos_x - title_start;
-                execute!(
-                    stdout(),
-                    cursor::MoveTo((safe_start_x + x) as u16, (safe_start_y - 1) as u16),
-                    style::SetForegroundColor(Color::Cyan),
-                    style::Print(title.chars().nth(char_idx).unwrap_or(' '))
-                )?;
-            } else {
-                // Regular border
+        let abilities = player.available_abilities();
+        if abilities.is_empty() {
+            execute!(
+                stdout(),
+                cursor::MoveTo(10, 5),
+                style::Print("You don't have any abilities yet!")
+            )?;
+
+            execute!(
+                stdout(),
+                cursor::MoveTo(10, 7),
+                style::Print("Press any key to return to combat...")
+            )?;
+
+            event::read()?;
+            return Err(io::Error::other("No abilities available"));
+        }
+
+        let mut menu = MenuSelector::new(abilities.len());
+        loop {
+            for (i, ability) in abilities.iter().enumerate() {
+                let cost = Class::mana_cost(ability);
+                let color = if menu.is_selected(i) {
+                    Color::Yellow
+                } else if player.mana < cost || player.cooldown_left(i) > 0 {
+                    Color::DarkGrey
+                } else {
+                    Color::White
+                };
                 execute!(
                     stdout(),
-                    cursor::MoveTo(pos_x as u16, (start_y - 1) as u16),
-                    style::SetForegroundColor(Color::White),
-                    style::Print("─")
+                    cursor::MoveTo(8, 5 + i as u16),
+                    style::SetForegroundColor(color),
+                    style::Print(format!(
+                        "{}{}. {} ({} mana) {}",
+                        menu.marker(i),
+                        i + 1,
+                        ability,
+                        cost,
+                        player.cooldown_label(i)
+                    )),
+                    style::SetForegroundColor(Color::White)
                 )?;
             }
+
+            execute!(
+                stdout(),
+                cursor::MoveTo(10, 5 + abilities.len() as u16 + 2),
+                style::Print("Up/Down and Enter, or a number key, to select; ESC to cancel")
+            )?;
+
+            match menu.handle_key(self.wait_for_key()?.code) {
+                // Abilities still cooling down can't be picked
+                MenuInput::Chosen(index) if player.cooldown_left(index) > 0 => {}
+                MenuInput::Chosen(index) => return Ok(index),
+                MenuInput::Cancelled => return Err(io::Error::other("Cancelled")),
+                MenuInput::Moved | MenuInput::Ignored => {}
+            }
         }
+    }
 
-        execute!(
-            stdout(),
-            cursor::MoveTo((safe_start_x + width - 1) as u16, (safe_start_y - 1) as u16),
-            style::SetForegroundColor(Color::White),
-            style::Print("┐")
-        )?;
+    pub fn draw_item_selection(&mut self, player: &Player) -> io::Result<usize> {
+        self.clear_screen()?;
 
-        // Draw bottom border
         execute!(
             stdout(),
-            cursor::MoveTo(safe_start_x as u16, (safe_start_y + height) as u16),
-            style::SetForegroundColor(Color::White),
-            style::Print("└")
+            cursor::MoveTo(30, 1),
+            style::SetForegroundColor(Color::Cyan),
+            style::Print("Select Item"),
+            style::SetForegroundColor(Color::White)
         )?;
 
-        for x in 1..width - 1 {
+        let consumables = InventoryManager::get_consumables(player);
+
+        if consumables.is_empty() {
             execute!(
                 stdout(),
-                cursor::MoveTo((safe_start_x + x) as u16, (safe_start_y + height) as u16),
-                style::SetForegroundColor(Color::White),
-                style::Print("─")
+                cursor::MoveTo(10, 5),
+                style::Print("You don't have any usable items!")
             )?;
-        }
-
-        execute!(
-            stdout(),
-            cursor::MoveTo(
-                (safe_start_x + width - 1) as u16,
-                (safe_start_y + height) as u16
-            ),
-            style::SetForegroundColor(Color::White),
-            style::Print("┘")
-        )?;
 
-        // Draw left and right borders
-        for y in 0..height {
             execute!(
                 stdout(),
-                cursor::MoveTo(safe_start_x as u16, (safe_start_y + y) as u16),
-                style::SetForegroundColor(Color::White),
-                style::Print("│")
+                cursor::MoveTo(10, 7),
+                style::Print("Press any key to return to combat...")
             )?;
 
+            event::read()?;
+            return Err(io::Error::other("No usable items available"));
+        }
+
+        let mut menu = MenuSelector::new(consumables.len());
+        loop {
+            for (i, (_item_index, consumable)) in consumables.iter().enumerate() {
+                execute!(
+                    stdout(),
+                    cursor::MoveTo(8, 5 + i as u16),
+                    style::SetForegroundColor(menu_color(&menu, i)),
+                    style::Print(format!(
+                        "{}{}. {} ({})",
+                        menu.marker(i),
+                        i + 1,
+                        consumable.name,
+                        consumable.effect_text()
+                    )),
+                    style::SetForegroundColor(Color::White)
+                )?;
+            }
+
             execute!(
                 stdout(),
-                cursor::MoveTo((safe_start_x + width - 1) as u16, (safe_start_y + y) as u16),
-                style::SetForegroundColor(Color::White),
-                style::Print("│")
+                cursor::MoveTo(10, 5 + consumables.len() as u16 + 2),
+                style::Print("Up/Down and Enter, or a number key, to select; ESC to cancel")
             )?;
+
+            match menu.handle_key(self.wait_for_key()?.code) {
+                MenuInput::Chosen(index) => return Ok(consumables[index].0),
+                MenuInput::Cancelled => return Err(io::Error::other("Cancelled")),
+                MenuInput::Moved | MenuInput::Ignored => {}
+            }
         }
+    }
 
-        Ok(())
+    /// Waits for a combat action, picked with the arrows and Enter or its
+    /// number key. T or Tab moves `target` to the next enemy still standing
+    /// and redraws the screen.
+    pub fn handle_combat_action(
+        &mut self,
+        player: &Player,
+        enemies: &[Enemy],
+        target: &mut usize,
+        context: &CombatContext,
+    ) -> io::Result<CombatAction> {
+        loop {
+            let code = self.wait_for_key()?.code;
+            let choice = match code {
+                KeyCode::Char('t') | KeyCode::Char('T') | KeyCode::Tab => {
+                    MenuInput::Chosen(COMBAT_NEXT_TARGET)
+                }
+                // Earlier rounds of a long fight are a page away
+                KeyCode::PageUp => {
+                    self.messages.page_up(self.combat_log_rows);
+                    MenuInput::Moved
+                }
+                KeyCode::PageDown => {
+                    self.messages.page_down(self.combat_log_rows);
+                    MenuInput::Moved
+                }
+                _ => self.combat_menu.handle_key(code),
+            };
+
+            match choice {
+                MenuInput::Chosen(0) => return Ok(CombatAction::Attack),
+                MenuInput::Chosen(1) => {
+                    if let Ok(ability_index) = self.draw_ability_selection(player) {
+                        return Ok(CombatAction::UseAbility(ability_index));
+                    }
+                }
+                MenuInput::Chosen(2) => {
+                    if let Ok(item_index) = self.draw_item_selection(player) {
+                        return Ok(CombatAction::UseItem(item_index));
+                    }
+                }
+                MenuInput::Chosen(3) => return Ok(CombatAction::Flee),
+                MenuInput::Chosen(COMBAT_NEXT_TARGET) if enemies.len() > 1 => {
+                    *target = combat::next_target(enemies, *target);
+                }
+                MenuInput::Moved => {}
+                _ => continue,
+            }
+            self.draw_combat_screen(player, enemies, *target, context)?;
+        }
     }
 
-    pub fn draw_inventory_screen(&mut self, player: &Player) -> io::Result<()> {
+    /// Shows active and completed quests until a key is pressed
+    pub fn show_quest_journal(&mut self, quests: &[Quest]) -> io::Result<()> {
         self.clear_screen()?;
 
         execute!(
             stdout(),
             cursor::MoveTo(30, 1),
             style::SetForegroundColor(Color::Cyan),
-            style::Print("Inventory"),
-            style::SetForegroundColor(Color::White),
-            cursor::MoveTo(10, 3),
-            style::Print(format!("Gold: {}", player.gold))
+            style::Print("Quest Journal"),
+            style::SetForegroundColor(Color::White)
         )?;
 
-        if InventoryManager::is_empty(player) {
-            execute!(
-                stdout(),
-                cursor::MoveTo(10, 5),
-                style::Print("Your inventory is empty.")
-            )?;
-        } else {
+        let mut row = 3;
+        for (heading, completed) in [("Active Quests", false), ("Completed Quests", true)] {
             execute!(
                 stdout(),
-                cursor::MoveTo(5, 5),
-                style::Print("Items:"),
-                cursor::MoveTo(5, 6),
-                style::Print("------")
+                cursor::MoveTo(5, row),
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(heading),
+                style::SetForegroundColor(Color::White)
             )?;
-
-            let items = InventoryManager::get_items(player);
-            for (i, item_info) in items.iter().enumerate() {
-                let equipped_marker = if item_info.is_equipped { " [E]" } else { "" };
-
+            row += 1;
+
+            let mut any = false;
+            for quest in quests.iter().filter(|q| q.is_complete() == completed) {
+                any = true;
+                let color = match quest.status {
+                    QuestStatus::Active => Color::White,
+                    QuestStatus::ItemFound => Color::Green,
+                    QuestStatus::Completed => Color::DarkGrey,
+                };
                 execute!(
                     stdout(),
-                    cursor::MoveTo(5, 7 + i as u16),
-                    style::Print(format!("{}. {}{}", i + 1, item_info.name, equipped_marker))
+                    cursor::MoveTo(7, row),
+                    style::SetForegroundColor(color),
+                    style::Print(format!("{} [{}]", quest.title, quest.status_label())),
+                    cursor::MoveTo(9, row + 1),
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(&quest.description),
+                    style::SetForegroundColor(Color::White)
                 )?;
+                row += 2;
+            }
+
+            if !any {
+                execute!(stdout(), cursor::MoveTo(7, row), style::Print("None"))?;
+                row += 1;
             }
+            row += 1;
         }
 
         execute!(
             stdout(),
-            cursor::MoveTo(10, SCREEN_HEIGHT as u16 - 3),
-            style::Print("Press a number key to use/equip an item, E to exit...")
+            cursor::MoveTo(10, row + 1),
+            style::Print("Press any key to return...")
         )?;
 
+        self.wait_for_key()?;
         Ok(())
     }
 
-    pub fn draw_character_screen(&mut self, player: &Player) -> io::Result<()> {
+    pub fn show_bestiary(&mut self, bestiary: &Bestiary) -> io::Result<()> {
         self.clear_screen()?;
 
         execute!(
             stdout(),
             cursor::MoveTo(30, 1),
             style::SetForegroundColor(Color::Cyan),
-            style::Print("Character Sheet"),
-            style::SetForegroundColor(Color::White),
-            cursor::MoveTo(10, 3),
-            style::Print(format!("Name: {}", player.name)),
-            cursor::MoveTo(10, 4),
-            style::Print(format!("Class: {}", player.class.class_type)),
-            cursor::MoveTo(10, 5),
-            style::Print(format!("Level: {}", player.level)),
-            cursor::MoveTo(10, 6),
-            style::Print(format!(
-                "Experience: {}/{}",
-                player.experience,
-                player.level * 100
-            )),
-            cursor::MoveTo(10, 7),
-            style::Print(format!("Health: {}/{}", player.health, player.max_health)),
-            cursor::MoveTo(10, 8),
-            style::Print(format!("Mana: {}/{}", player.mana, player.max_mana)),
-            cursor::MoveTo(10, 9),
-            style::Print(format!("Gold: {}", player.gold)),
-            cursor::MoveTo(10, 11),
-            style::SetForegroundColor(Color::Cyan),
-            style::Print("Stats:"),
-            style::SetForegroundColor(Color::White),
-            cursor::MoveTo(10, 12),
-            style::Print(format!("Strength: {}", player.stats.strength)),
-            cursor::MoveTo(10, 13),
-            style::Print(format!("Intelligence: {}", player.stats.intelligence)),
-            cursor::MoveTo(10, 14),
-            style::Print(format!("Dexterity: {}", player.stats.dexterity)),
-            cursor::MoveTo(10, 15),
-            style::Print(format!("Constitution: {}", player.stats.constitution)),
-            cursor::MoveTo(10, 16),
-            style::Print(format!("Wisdom: {}", player.stats.wisdom)),
-            cursor::MoveTo(40, 11),
-            style::SetForegroundColor(Color::Cyan),
-            style::Print("Abilities:"),
+            style::Print("Bestiary"),
             style::SetForegroundColor(Color::White)
         )?;
 
-        // Display abilities
-        for (i, ability) in player.class.abilities.iter().enumerate() {
+        let mut row = 3;
+        if bestiary.is_empty() {
             execute!(
                 stdout(),
-                cursor::MoveTo(40, 12 + i as u16),
-                style::Print(format!("{}. {}", i + 1, ability))
+                cursor::MoveTo(5, row),
+                style::Print("You haven't fought anything yet.")
             )?;
+            row += 1;
+        }
+        for (name, entry) in bestiary.entries() {
+            let color = if entry.is_identified() {
+                Color::Green
+            } else {
+                Color::White
+            };
+            execute!(
+                stdout(),
+                cursor::MoveTo(5, row),
+                style::SetForegroundColor(color),
+                style::Print(format!("{name} [{}]", entry.stats_text())),
+                cursor::MoveTo(7, row + 1),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!(
+                    "Met {}, slain {}, worst hit taken {}",
+                    entry.encountered, entry.killed, entry.max_damage_taken
+                )),
+                style::SetForegroundColor(Color::White)
+            )?;
+            row += 2;
         }
-
-        // Display derived stats
-        execute!(
-            stdout(),
-            cursor::MoveTo(40, 18),
-            style::SetForegroundColor(Color::Cyan),
-            style::Print("Combat Stats:"),
-            style::SetForegroundColor(Color::White),
-            cursor::MoveTo(40, 19),
-            style::Print(format!("Attack: {}", player.attack_damage())),
-            cursor::MoveTo(40, 20),
-            style::Print(format!("Defense: {}", player.defense()))
-        )?;
 
         execute!(
             stdout(),
-            cursor::MoveTo(10, SCREEN_HEIGHT as u16 - 3),
+            cursor::MoveTo(10, row + 1),
             style::Print("Press any key to return...")
         )?;
 
+        self.wait_for_key()?;
         Ok(())
     }
 
-    pub fn draw_combat_screen(&mut self, player: &Player, enemy: &Enemy) -> io::Result<()> {
+    /// Shows the full message log and lets the player scroll through it.
+    /// Returns when Esc, Enter or L is pressed.
+    pub fn show_message_log(&mut self, log: &GameLog) -> io::Result<()> {
+        let (_, term_height) = terminal::size()?;
+        // Rows taken by the title, separator and footer
+        let page_size = (term_height as usize).saturating_sub(6).max(1);
+        let max_offset = log.len().saturating_sub(page_size);
+
+        // Start at the newest page
+        let mut offset = max_offset;
+
+        loop {
+            self.draw_message_log_screen(log, offset, page_size)?;
+
+            match self.wait_for_key()?.code {
+                KeyCode::PageUp => offset = offset.saturating_sub(page_size),
+                KeyCode::PageDown => offset = (offset + page_size).min(max_offset),
+                KeyCode::Up => offset = offset.saturating_sub(1),
+                KeyCode::Down => offset = (offset + 1).min(max_offset),
+                KeyCode::Home => offset = 0,
+                KeyCode::End => offset = max_offset,
+                KeyCode::Esc | KeyCode::Enter | KeyCode::Char('l') | KeyCode::Char('L') => {
+                    return Ok(())
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn draw_message_log_screen(
+        &mut self,
+        log: &GameLog,
+        offset: usize,
+        page_size: usize,
+    ) -> io::Result<()> {
         self.clear_screen()?;
 
-        execute!(
-            stdout(),
-            cursor::MoveTo(30, 1),
-            style::SetForegroundColor(Color::Red),
-            style::Print("Combat!"),
-            style::SetForegroundColor(Color::White),
-            cursor::MoveTo(10, 3),
-            style::Print(format!("You are fighting a {}!", enemy.name)),
-            cursor::MoveTo(10, 5),
-            style::Print(format!(
-                "Player HP: {}/{}",
-                player.health, player.max_health
-            )),
-            cursor::MoveTo(10, 6),
-            style::Print(format!("Player MP: {}/{}", player.mana, player.max_mana)),
-            cursor::MoveTo(10, 8),
-            style::Print(format!("Enemy HP: {}/{}", enemy.health, enemy.max_health)),
-            cursor::MoveTo(10, 10),
-            style::SetForegroundColor(Color::Cyan),
-            style::Print("Actions:"),
-            style::SetForegroundColor(Color::White),
-            cursor::MoveTo(10, 11),
-            style::Print("1. Attack"),
-            cursor::MoveTo(10, 12),
-            style::Print("2. Use Ability"),
-            cursor::MoveTo(10, 13),
-            style::Print("3. Use Item"),
-            cursor::MoveTo(10, 14),
-            style::Print("4. Flee")
-        )?;
+        let (term_width, _) = terminal::size()?;
+        let max_text_width = (term_width as usize).saturating_sub(16).max(10);
 
-        // Display message log
         execute!(
             stdout(),
-            cursor::MoveTo(10, 16),
+            cursor::MoveTo(2, 1),
             style::SetForegroundColor(Color::Cyan),
-            style::Print("Combat Log:"),
+            style::Print(format!("Message Log ({} messages)", log.len())),
+            cursor::MoveTo(2, 2),
+            style::Print("─".repeat((term_width as usize).saturating_sub(4))),
             style::SetForegroundColor(Color::White)
         )?;
 
-        for (i, message) in self.messages.iter().enumerate() {
+        if log.is_empty() {
+            execute!(
+                stdout(),
+                cursor::MoveTo(4, 4),
+                style::Print("No messages yet.")
+            )?;
+        }
+
+        for (i, entry) in log.window(offset, page_size).enumerate() {
+            let text: String = entry.text.chars().take(max_text_width).collect();
             execute!(
                 stdout(),
-                cursor::MoveTo(10, 17 + i as u16),
-                style::Print(message)
+                cursor::MoveTo(2, 3 + i as u16),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!("[T{:>5}] ", entry.turn)),
+                style::SetForegroundColor(log_color(entry.category)),
+                style::Print(text)
             )?;
         }
 
+        let shown_end = (offset + page_size).min(log.len());
+        execute!(
+            stdout(),
+            cursor::MoveTo(2, 4 + page_size as u16),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!(
+                "{}-{} of {} | PgUp/PgDn: page | Up/Down: line | Home/End: jump | Esc/L: close",
+                if log.is_empty() { 0 } else { offset + 1 },
+                shown_end,
+                log.len()
+            )),
+            style::SetForegroundColor(Color::White)
+        )?;
+
         Ok(())
     }
 
-    pub fn draw_ability_selection(&mut self, player: &Player) -> io::Result<usize> {
+    /// Like `wait_for_key`, but returns None when the terminal is resized so
+    /// the caller can redraw for the new size
+    pub fn wait_for_key_or_resize(&mut self) -> io::Result<Option<KeyEvent>> {
+        loop {
+            match event::read()? {
+                Event::Resize(_, _) => return Ok(None),
+                Event::Key(key_event) => {
+                    // On Windows, filter out key release events to prevent double input
+                    #[cfg(windows)]
+                    {
+                        if key_event.kind == KeyEventKind::Press {
+                            return Ok(Some(platform::normalize_key_event(key_event)));
+                        }
+                    }
+                    #[cfg(not(windows))]
+                    {
+                        return Ok(Some(platform::normalize_key_event(key_event)));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn wait_for_key(&mut self) -> io::Result<KeyEvent> {
+        loop {
+            if let Some(key_event) = self.wait_for_key_or_resize()? {
+                return Ok(key_event);
+            }
+        }
+    }
+
+    /// Waits up to `wait` for a key, returning None if none was pressed
+    pub fn poll_key(&mut self, wait: Duration) -> io::Result<Option<KeyEvent>> {
+        if event::poll(wait)? {
+            return self.wait_for_key_or_resize();
+        }
+        Ok(None)
+    }
+
+    /// Lists the best recorded runs until a key is pressed; `replay`
+    /// describes the last recorded run, which R offers to watch. Returns
+    /// whether it was asked for.
+    pub fn draw_hall_of_fame(
+        &mut self,
+        leaderboard: &Leaderboard,
+        replay: Option<&str>,
+    ) -> io::Result<bool> {
         self.clear_screen()?;
 
+        let (term_width, term_height) = terminal::size()?;
+
+        let rows = leaderboard.rows();
+        let daily_rows = leaderboard.daily_rows();
+        // The daily challenges get a heading and a gap below the main list
+        let daily_height = if daily_rows.is_empty() {
+            0
+        } else {
+            2 + daily_rows.len() as u16
+        };
+        let area = Layout::centered(
+            term_width,
+            term_height,
+            80,
+            8 + rows.len().max(1) as u16 + daily_height,
+        );
+        let (start_x, start_y) = (area.x, area.y);
+        let (border_width, border_height) = (area.width, area.height);
+
+        self.draw_game_border(
+            start_x as usize,
+            start_y as usize,
+            border_width as usize,
+            border_height as usize,
+        )?;
+
+        let title = "Hall of Fame";
+        let title_pos_x = area.center_x(title);
         execute!(
             stdout(),
-            cursor::MoveTo(30, 1),
-            style::SetForegroundColor(Color::Cyan),
-            style::Print("Select Ability"),
+            cursor::MoveTo(title_pos_x, start_y + 2),
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(title),
             style::SetForegroundColor(Color::White)
         )?;
 
-        if player.class.abilities.is_empty() {
+        if rows.is_empty() {
             execute!(
                 stdout(),
-                cursor::MoveTo(10, 5),
-                style::Print("You don't have any abilities yet!")
+                cursor::MoveTo(start_x + 3, start_y + 4),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("No finished runs yet."),
+                style::SetForegroundColor(Color::White)
             )?;
-
+        }
+        for (i, row) in rows.iter().enumerate() {
             execute!(
                 stdout(),
-                cursor::MoveTo(10, 7),
-                style::Print("Press any key to return to combat...")
+                cursor::MoveTo(start_x + 3, start_y + 4 + i as u16),
+                style::Print(row)
             )?;
-
-            event::read()?;
-            return Err(io::Error::other("No abilities available"));
         }
 
-        for (i, ability) in player.class.abilities.iter().enumerate() {
+        let daily_y = start_y + 5 + rows.len().max(1) as u16;
+        if !daily_rows.is_empty() {
             execute!(
                 stdout(),
-                cursor::MoveTo(10, 5 + i as u16),
-                style::Print(format!("{}. {}", i + 1, ability))
+                cursor::MoveTo(start_x + 3, daily_y),
+                style::SetForegroundColor(Color::Yellow),
+                style::Print("Daily Challenges"),
+                style::SetForegroundColor(Color::White)
+            )?;
+        }
+        for (i, row) in daily_rows.iter().enumerate() {
+            execute!(
+                stdout(),
+                cursor::MoveTo(start_x + 3, daily_y + 1 + i as u16),
+                style::Print(row)
             )?;
         }
 
+        let prompt = match replay {
+            Some(summary) => format!("R: watch the last run ({summary}) | any other key: return"),
+            None => "Press any key to return...".to_string(),
+        };
+        let prompt_pos_x = area.center_x(&prompt);
         execute!(
             stdout(),
-            cursor::MoveTo(10, 5 + player.class.abilities.len() as u16 + 2),
-            style::Print("Press the number key to select an ability, or ESC to cancel...")
+            cursor::MoveTo(prompt_pos_x, start_y + border_height.saturating_sub(2)),
+            style::Print(&prompt)
         )?;
 
-        loop {
-            if let Event::Key(key_event) = event::read()? {
-                // On Windows, only process key press events
-                #[cfg(windows)]
-                {
-                    if key_event.kind != KeyEventKind::Press {
-                        continue;
-                    }
-                }
-
-                match key_event.code {
-                    KeyCode::Char(c) if ('1'..='9').contains(&c) => {
-                        let index = c.to_digit(10).unwrap() as usize - 1;
-                        if index < player.class.abilities.len() {
-                            return Ok(index);
-                        }
-                    }
-                    KeyCode::Esc => {
-                        return Err(io::Error::other("Cancelled"));
-                    }
-                    _ => {}
-                }
-            }
-        }
+        let code = self.wait_for_key()?.code;
+        Ok(replay.is_some() && matches!(code, KeyCode::Char('r') | KeyCode::Char('R')))
     }
 
-    pub fn draw_item_selection(&mut self, player: &Player) -> io::Result<usize> {
+    /// Lists every achievement, unlocked ones with the day they were,
+    /// until a key is pressed
+    pub fn draw_achievements(&mut self, profile: &AchievementProfile) -> io::Result<()> {
         self.clear_screen()?;
 
+        let (term_width, term_height) = terminal::size()?;
+        let rows = profile.rows();
+        let area = Layout::centered(term_width, term_height, 90, 8 + rows.len() as u16);
+        let (start_x, start_y) = (area.x, area.y);
+        let (border_width, border_height) = (area.width, area.height);
+
+        self.draw_game_border(
+            start_x as usize,
+            start_y as usize,
+            border_width as usize,
+            border_height as usize,
+        )?;
+
+        let title = format!("Achievements - {}", profile.summary());
+        execute!(
+            stdout(),
+            cursor::MoveTo(area.center_x(&title), start_y + 2),
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(&title),
+            style::SetForegroundColor(Color::White)
+        )?;
+
+        for (i, (row, unlocked)) in rows.iter().enumerate() {
+            let color = if *unlocked {
+                Color::Magenta
+            } else {
+                Color::DarkGrey
+            };
+            execute!(
+                stdout(),
+                cursor::MoveTo(start_x + 3, start_y + 4 + i as u16),
+                style::SetForegroundColor(color),
+                style::Print(row),
+                style::SetForegroundColor(Color::White)
+            )?;
+        }
+
+        let prompt = "Press any key to return...";
         execute!(
             stdout(),
-            cursor::MoveTo(30, 1),
-            style::SetForegroundColor(Color::Cyan),
-            style::Print("Select Item"),
-            style::SetForegroundColor(Color::White)
+            cursor::MoveTo(
+                area.center_x(prompt),
+                start_y + border_height.saturating_sub(2)
+            ),
+            style::Print(prompt)
         )?;
 
-        let consumables: Vec<(usize, &Item)> = player
-            .inventory
-            .items
-            .iter()
-            .enumerate()
-            .filter(|(_, item)| matches!(item, Item::Consumable(_)))
-            .collect();
+        self.wait_for_key()?;
+        Ok(())
+    }
 
-        if consumables.is_empty() {
+    /// Lets the player change the settings: up and down pick a setting,
+    /// left and right change it. Every change is applied straight away;
+    /// Escape or Enter returns to the title screen.
+    pub fn options_screen(&mut self, settings: &mut Settings) -> io::Result<()> {
+        let mut selected = 0;
+
+        loop {
+            self.clear_screen()?;
+
+            let (term_width, term_height) = terminal::size()?;
+            let rows = settings.rows();
+            let area = Layout::centered(term_width, term_height, 60, 8 + rows.len() as u16);
+            let (start_x, start_y) = (area.x, area.y);
+            let (border_width, border_height) = (area.width, area.height);
+
+            self.draw_game_border(
+                start_x as usize,
+                start_y as usize,
+                border_width as usize,
+                border_height as usize,
+            )?;
+
+            let title = "Options";
             execute!(
                 stdout(),
-                cursor::MoveTo(10, 5),
-                style::Print("You don't have any usable items!")
+                cursor::MoveTo(area.center_x(title), start_y + 2),
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(title),
+                style::SetForegroundColor(Color::White)
             )?;
 
+            for (i, row) in rows.iter().enumerate() {
+                let (marker, color) = if i == selected {
+                    ("> ", Color::Yellow)
+                } else {
+                    ("  ", Color::White)
+                };
+                execute!(
+                    stdout(),
+                    cursor::MoveTo(start_x + 3, start_y + 4 + i as u16),
+                    style::SetForegroundColor(color),
+                    style::Print(format!("{marker}{row}")),
+                    style::SetForegroundColor(Color::White)
+                )?;
+            }
+
             execute!(
                 stdout(),
-                cursor::MoveTo(10, 7),
-                style::Print("Press any key to return to combat...")
+                cursor::MoveTo(start_x + 3, start_y + border_height.saturating_sub(2)),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("Up/Down: choose  Left/Right: change  Esc: back"),
+                style::SetForegroundColor(Color::White)
             )?;
 
-            event::read()?;
-            return Err(io::Error::other("No usable items available"));
+            let code = self.wait_for_key()?.code;
+            match code {
+                KeyCode::Up => selected = (selected + rows.len() - 1) % rows.len(),
+                KeyCode::Down => selected = (selected + 1) % rows.len(),
+                KeyCode::Left | KeyCode::Right => {
+                    settings.adjust(Setting::ALL[selected], code == KeyCode::Right);
+                    self.apply_settings(settings);
+                }
+                KeyCode::Esc | KeyCode::Enter => return Ok(()),
+                _ => {}
+            }
         }
+    }
+
+    /// Draws the pause menu with `selected` highlighted; while `question`
+    /// is set it is asked below the menu instead of the key hints
+    pub fn draw_pause_menu(
+        &mut self,
+        options: &[PauseOption],
+        selected: usize,
+        question: Option<&str>,
+    ) -> io::Result<()> {
+        self.clear_screen()?;
+
+        let (term_width, term_height) = terminal::size()?;
+        let area = Layout::centered(term_width, term_height, 76, 8 + options.len() as u16);
+        let (start_x, start_y) = (area.x, area.y);
+        let (border_width, border_height) = (area.width, area.height);
+
+        self.draw_game_border(
+            start_x as usize,
+            start_y as usize,
+            border_width as usize,
+            border_height as usize,
+        )?;
+
+        let title = "Paused";
+        execute!(
+            stdout(),
+            cursor::MoveTo(area.center_x(title), start_y + 2),
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(title)
+        )?;
 
-        for (i, (_item_index, item)) in consumables.iter().enumerate() {
+        for (i, option) in options.iter().enumerate() {
+            let (marker, color) = if i == selected {
+                ("> ", Color::Yellow)
+            } else {
+                ("  ", Color::White)
+            };
             execute!(
                 stdout(),
-                cursor::MoveTo(10, 5 + i as u16),
-                style::Print(format!("{}. {}", i + 1, item.name()))
+                cursor::MoveTo(start_x + 3, start_y + 4 + i as u16),
+                style::SetForegroundColor(color),
+                style::Print(format!("{marker}{}. {}", i + 1, option.label()))
             )?;
         }
 
+        let (line, color) = match question {
+            Some(question) => (format!("{question} (Y/N)"), Color::Red),
+            None => (
+                "Up/Down: choose  Enter: select  Esc: resume".to_string(),
+                Color::DarkGrey,
+            ),
+        };
         execute!(
             stdout(),
-            cursor::MoveTo(10, 5 + consumables.len() as u16 + 2),
-            style::Print("Press the number key to select an item, or ESC to cancel...")
+            cursor::MoveTo(start_x + 3, start_y + border_height.saturating_sub(2)),
+            style::SetForegroundColor(color),
+            style::Print(line),
+            style::SetForegroundColor(Color::White)
         )?;
-
-        loop {
-            if let Event::Key(key_event) = event::read()? {
-                // On Windows, only process key press events
-                #[cfg(windows)]
-                {
-                    if key_event.kind != KeyEventKind::Press {
-                        continue;
-                    }
-                }
-
-                match key_event.code {
-                    KeyCode::Char(c) if ('1'..='9').contains(&c) => {
-                        let index = c.to_digit(10).unwrap() as usize - 1;
-                        if index < consumables.len() {
-                            return Ok(consumables[index].0);
-                        }
-                    }
-                    KeyCode::Esc => {
-                        return Err(io::Error::other("Cancelled"));
-                    }
-                    _ => {}
-                }
-            }
-        }
+        Ok(())
     }
 
-    pub fn handle_combat_action(&mut self, player: &Player) -> io::Result<CombatAction> {
+    /// Shows the pause menu until an entry is picked with Enter or its
+    /// number; Esc picks Resume
+    pub fn pause_menu(&mut self, options: &[PauseOption]) -> io::Result<PauseOption> {
+        let mut selected = 0;
         loop {
-            if let Event::Key(key_event) = event::read()? {
-                // On Windows, only process key press events
-                #[cfg(windows)]
-                {
-                    if key_event.kind != KeyEventKind::Press {
-                        continue;
-                    }
-                }
-
-                match key_event.code {
-                    KeyCode::Char('1') => return Ok(CombatAction::Attack),
-                    KeyCode::Char('2') => {
-                        if let Ok(ability_index) = self.draw_ability_selection(player) {
-                            return Ok(CombatAction::UseAbility(ability_index));
-                        }
+            self.draw_pause_menu(options, selected, None)?;
+            match self.wait_for_key()?.code {
+                KeyCode::Up => selected = (selected + options.len() - 1) % options.len(),
+                KeyCode::Down => selected = (selected + 1) % options.len(),
+                KeyCode::Enter => return Ok(options[selected]),
+                KeyCode::Esc => return Ok(PauseOption::Resume),
+                KeyCode::Char(c) => {
+                    let picked = c
+                        .to_digit(10)
+                        .and_then(|n| (n as usize).checked_sub(1))
+                        .and_then(|i| options.get(i));
+                    if let Some(&option) = picked {
+                        return Ok(option);
                     }
-                    KeyCode::Char('3') => {
-                        if let Ok(item_index) = self.draw_item_selection(player) {
-                            return Ok(CombatAction::UseItem(item_index));
-                        }
-                    }
-                    KeyCode::Char('4') => return Ok(CombatAction::Flee),
-                    _ => {}
                 }
+                _ => {}
             }
         }
     }
 
-    pub fn wait_for_key(&mut self) -> io::Result<KeyEvent> {
+    /// Asks `question` on the pause menu; true if the player answers yes
+    pub fn confirm_quit(&mut self, options: &[PauseOption], question: &str) -> io::Result<bool> {
+        let selected = options
+            .iter()
+            .position(|&option| option == PauseOption::QuitToMenu)
+            .unwrap_or(0);
+        self.draw_pause_menu(options, selected, Some(question))?;
         loop {
-            if let Event::Key(key_event) = event::read()? {
-                // On Windows, filter out key release events to prevent double input
-                #[cfg(windows)]
-                {
-                    if key_event.kind == KeyEventKind::Press {
-                        return Ok(platform::normalize_key_event(key_event));
-                    }
-                }
-                #[cfg(not(windows))]
-                {
-                    // On other platforms, use the original behavior
-                    return Ok(platform::normalize_key_event(key_event));
-                }
+            match self.wait_for_key()?.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => return Ok(true),
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => return Ok(false),
+                _ => {}
             }
         }
     }
 
-    /// Command Prompt optimized rendering - renders line by line with minimal colors
-    #[cfg(windows)]
-    fn render_cmd_optimized(
-        &mut self,
-        level: &Level,
-        center_x: usize,
-        center_y: usize,
-        content_start_x: usize,
-        content_start_y: usize,
-    ) -> io::Result<()> {
-        use crossterm::style::Color;
-
-        // Build entire screen as strings to minimize terminal operations
-        let mut screen_lines = Vec::new();
+    /// Shows the help text a page at a time; PageUp and PageDown turn the
+    /// pages and any other key returns
+    pub fn show_help(&mut self) -> io::Result<()> {
+        let lines = help::help_lines(self.settings.glyph_set());
+        let mut page = 0;
 
-        for screen_y in 0..MAP_HEIGHT {
-            let mut line_chars = Vec::new();
-            let mut line_colors = Vec::new();
+        loop {
+            self.clear_screen()?;
 
-            for screen_x in 0..MAP_WIDTH {
-                let map_x = level.player_position.x - center_x as i32 + screen_x as i32;
-                let map_y = level.player_position.y - center_y as i32 + screen_y as i32;
+            let (term_width, term_height) = terminal::size()?;
+            let text_width = lines.iter().map(|line| line.chars().count()).max();
+            let area = Layout::centered(
+                term_width,
+                term_height,
+                text_width.unwrap_or(0) as u16 + 6,
+                term_height.saturating_sub(2).max(10),
+            );
+            let (start_x, start_y) = (area.x, area.y);
+            let (border_width, border_height) = (area.width, area.height);
+            let page_rows = border_height.saturating_sub(7).max(1) as usize;
+            let pages = lines.len().div_ceil(page_rows).max(1);
+            page = page.min(pages - 1);
 
-                if map_x < 0
-                    || map_x >= level.width as i32
-                    || map_y < 0
-                    || map_y >= level.height as i32
-                {
-                    line_chars.push(' ');
-                    line_colors.push(Color::Black);
-                    continue;
-                }
+            self.draw_game_border(
+                start_x as usize,
+                start_y as usize,
+                border_width as usize,
+                border_height as usize,
+            )?;
 
-                let pos = Position::new(map_x, map_y);
-                let tile = &level.tiles[map_y as usize][map_x as usize];
-
-                let (char_to_draw, color) = if pos == level.player_position {
-                    ('@', Color::Yellow)
-                } else if !tile.explored {
-                    (' ', Color::Black)
-                } else if tile.visible && level.enemies.contains_key(&pos) {
-                    ('E', Color::Red)
-                } else if tile.visible && level.items.contains_key(&pos) {
-                    ('!', Color::Green)
-                } else if !tile.visible {
-                    (' ', Color::Black) // Complete fog of war for Command Prompt
+            let title = format!("Help ({}/{pages})", page + 1);
+            execute!(
+                stdout(),
+                cursor::MoveTo(area.center_x(&title), start_y + 1),
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(&title),
+                style::SetForegroundColor(Color::White)
+            )?;
+            for (i, line) in lines
+                .iter()
+                .skip(page * page_rows)
+                .take(page_rows)
+                .enumerate()
+            {
+                let color = if line.starts_with("==") {
+                    Color::Cyan
                 } else {
-                    // Simplified tile rendering for Command Prompt
-                    match tile.tile_type {
-                        crate::world::TileType::Wall => ('#', Color::White),
-                        crate::world::TileType::Floor => ('.', Color::DarkGrey),
-                        crate::world::TileType::Door => ('+', Color::Cyan),
-                        crate::world::TileType::StairsDown => ('>', Color::Blue),
-                        crate::world::TileType::StairsUp => ('<', Color::Blue),
-                        crate::world::TileType::Chest => ('C', Color::Cyan),
-                        crate::world::TileType::Exit => ('E', Color::Green),
-                    }
+                    Color::White
                 };
-
-                line_chars.push(char_to_draw);
-                line_colors.push(color);
+                execute!(
+                    stdout(),
+                    cursor::MoveTo(start_x + 3, start_y + 3 + i as u16),
+                    style::SetForegroundColor(color),
+                    style::Print(line),
+                    style::SetForegroundColor(Color::White)
+                )?;
             }
-
-            screen_lines.push((line_chars, line_colors));
-        }
-
-        // Render line by line with color optimization for Command Prompt
-        for (y, (chars, colors)) in screen_lines.iter().enumerate() {
-            queue!(
+            execute!(
                 stdout(),
-                cursor::MoveTo(content_start_x as u16, (content_start_y + y) as u16)
+                cursor::MoveTo(start_x + 3, start_y + border_height.saturating_sub(2)),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("PageUp/PageDown: turn pages, any other key: return"),
+                style::SetForegroundColor(Color::White)
             )?;
 
-            let mut current_color = Color::White;
-            let mut line_buffer = String::new();
-            let mut buffer_color = Color::White;
-
-            for (i, (&ch, &color)) in chars.iter().zip(colors.iter()).enumerate() {
-                if color != buffer_color || i == chars.len() - 1 {
-                    // Flush current buffer if color changes or at end
-                    if !line_buffer.is_empty() {
-                        if buffer_color != current_color {
-                            queue!(stdout(), style::SetForegroundColor(buffer_color))?;
-                            current_color = buffer_color;
-                        }
-                        queue!(stdout(), style::Print(&line_buffer))?;
-                        line_buffer.clear();
-                    }
-
-                    if i == chars.len() - 1 {
-                        // Handle last character
-                        if color != current_color {
-                            queue!(stdout(), style::SetForegroundColor(color))?;
-                        }
-                        queue!(stdout(), style::Print(ch))?;
-                    } else {
-                        buffer_color = color;
-                        line_buffer.push(ch);
-                    }
-                } else {
-                    line_buffer.push(ch);
-                }
+            match self.wait_for_key_or_resize()? {
+                None => {}
+                Some(key_event) => match key_event.code {
+                    KeyCode::PageDown => page = (page + 1).min(pages - 1),
+                    KeyCode::PageUp => page = page.saturating_sub(1),
+                    _ => return Ok(()),
+                },
             }
         }
-
-        stdout().flush()?;
-        Ok(())
     }
 
-    pub fn draw_game_over(&mut self, player: &Player) -> io::Result<()> {
+    pub fn draw_game_over(
+        &mut self,
+        player: &Player,
+        stats: &GameStats,
+        placement: Option<&Placement>,
+        run_label: Option<&str>,
+    ) -> io::Result<()> {
         self.clear_screen()?;
 
         // Get actual terminal size
         let (term_width, term_height) = terminal::size()?;
 
-        // Create a centered box for game over screen
-        let border_width = 60;
-        let border_height = 10;
-        let start_x = ((term_width as i32 - border_width as i32) / 2).max(0) as u16;
-        let start_y = ((term_height as i32 - border_height) / 2).max(0) as u16;
+        // Create a centered box for game over screen, tall enough for the stats
+        let table_rows = stats.summary_rows().len() as u16;
+        let area = Layout::centered(term_width, term_height, 60, 12 + table_rows);
+        let (start_x, start_y) = (area.x, area.y);
+        let (border_width, border_height) = (area.width, area.height);
 
         self.draw_game_border(
             start_x as usize,
@@ -1707,26 +3385,39 @@ impl UI {
         )?;
 
         let title = "Game Over";
-        let title_pos_x = start_x + (border_width - title.len() as u16) / 2;
+        let title_pos_x = area.center_x(title);
 
         let message = format!(
             "{} died at level {} after a brave adventure.",
             player.name, player.level
         );
-        let message_pos_x = start_x + (border_width - message.len() as u16) / 2;
+        let message_pos_x = area.center_x(&message);
 
         let prompt = "Press any key to exit...";
-        let prompt_pos_x = start_x + (border_width - prompt.len() as u16) / 2;
+        let prompt_pos_x = area.center_x(prompt);
 
         execute!(
             stdout(),
             cursor::MoveTo(title_pos_x, start_y + 2),
             style::SetForegroundColor(Color::Red),
             style::Print(title),
-            cursor::MoveTo(message_pos_x, start_y + 5),
+            cursor::MoveTo(message_pos_x, start_y + 4),
             style::SetForegroundColor(Color::White),
-            style::Print(message),
-            cursor::MoveTo(prompt_pos_x, start_y + 8),
+            style::Print(message)
+        )?;
+        self.draw_run_label(start_x, border_width, start_y + 3, run_label)?;
+
+        self.draw_stats_table(start_x, border_width, start_y + 6, stats)?;
+        self.draw_placement(
+            start_x,
+            border_width,
+            start_y + border_height.saturating_sub(4),
+            placement,
+        )?;
+
+        execute!(
+            stdout(),
+            cursor::MoveTo(prompt_pos_x, start_y + border_height.saturating_sub(2)),
             style::Print(prompt)
         )?;
 
@@ -1734,17 +3425,31 @@ impl UI {
         Ok(())
     }
 
-    pub fn draw_victory_screen(&mut self, player: &Player) -> io::Result<()> {
+    pub fn draw_victory_screen(
+        &mut self,
+        player: &Player,
+        quests: &[Quest],
+        stats: &GameStats,
+        placement: Option<&Placement>,
+        run_label: Option<&str>,
+    ) -> io::Result<bool> {
         self.clear_screen()?;
 
         // Get actual terminal size
         let (term_width, term_height) = terminal::size()?;
 
-        // Create a centered box for victory screen
-        let border_width = 70;
-        let border_height = 10;
-        let start_x = ((term_width as i32 - border_width as i32) / 2).max(0) as u16;
-        let start_y = ((term_height as i32 - border_height) / 2).max(0) as u16;
+        let completed: Vec<&Quest> = quests.iter().filter(|q| q.is_complete()).collect();
+
+        // Create a centered box for victory screen, tall enough for quests and stats
+        let table_rows = stats.summary_rows().len() as u16;
+        let area = Layout::centered(
+            term_width,
+            term_height,
+            70,
+            12 + completed.len() as u16 + table_rows,
+        );
+        let (start_x, start_y) = (area.x, area.y);
+        let (border_width, border_height) = (area.width, area.height);
 
         self.draw_game_border(
             start_x as usize,
@@ -1754,30 +3459,299 @@ impl UI {
         )?;
 
         let title = "Congratulations! You've won!";
-        let title_pos_x = start_x + (border_width - title.len() as u16) / 2;
+        let title_pos_x = area.center_x(title);
 
         let message = format!(
             "{} completed the adventure at level {} and saved the realm!",
             player.name, player.level
         );
-        let message_pos_x = start_x + (border_width - message.len() as u16) / 2;
+        let message_pos_x = area.center_x(&message);
 
-        let prompt = "Press any key to exit...";
-        let prompt_pos_x = start_x + (border_width - prompt.len() as u16) / 2;
+        let prompt = "1. New Game+ (tougher dungeons)   2. Quit";
+        let prompt_pos_x = area.center_x(prompt);
 
         execute!(
             stdout(),
             cursor::MoveTo(title_pos_x, start_y + 2),
             style::SetForegroundColor(Color::Green),
             style::Print(title),
-            cursor::MoveTo(message_pos_x, start_y + 5),
+            cursor::MoveTo(message_pos_x, start_y + 4),
             style::SetForegroundColor(Color::White),
-            style::Print(message),
-            cursor::MoveTo(prompt_pos_x, start_y + 8),
+            style::Print(message)
+        )?;
+        self.draw_run_label(start_x, border_width, start_y + 3, run_label)?;
+
+        // List the quest bonuses earned on the way out
+        for (i, quest) in completed.iter().enumerate() {
+            let line = format!(
+                "Quest complete: {} (+{} XP, +{} gold)",
+                quest.item_name, quest.reward_experience, quest.reward_gold
+            );
+            let line_pos_x = area.center_x(&line);
+            execute!(
+                stdout(),
+                cursor::MoveTo(line_pos_x, start_y + 5 + i as u16),
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(line),
+                style::SetForegroundColor(Color::White)
+            )?;
+        }
+
+        self.draw_stats_table(
+            start_x,
+            border_width,
+            start_y + 6 + completed.len() as u16,
+            stats,
+        )?;
+        self.draw_placement(
+            start_x,
+            border_width,
+            start_y + border_height.saturating_sub(4),
+            placement,
+        )?;
+
+        execute!(
+            stdout(),
+            cursor::MoveTo(prompt_pos_x, start_y + border_height.saturating_sub(2)),
             style::Print(prompt)
         )?;
 
-        self.wait_for_key()?;
+        // Carry on with the same character, or leave
+        loop {
+            match self.wait_for_key()?.code {
+                KeyCode::Char('1') | KeyCode::Char('n') | KeyCode::Char('N') => return Ok(true),
+                KeyCode::Char('2') | KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => {
+                    return Ok(false)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Draws the name of a special run, such as a daily challenge, centred
+    /// in a box
+    fn draw_run_label(
+        &mut self,
+        box_x: u16,
+        box_width: u16,
+        y: u16,
+        run_label: Option<&str>,
+    ) -> io::Result<()> {
+        let Some(label) = run_label else {
+            return Ok(());
+        };
+
+        let x = box_x + box_width.saturating_sub(label.chars().count() as u16) / 2;
+        execute!(
+            stdout(),
+            cursor::MoveTo(x, y),
+            style::SetForegroundColor(Color::Magenta),
+            style::Print(label),
+            style::SetForegroundColor(Color::White)
+        )
+    }
+
+    /// Draws where the finished run landed in the hall of fame, centred in a
+    /// box
+    fn draw_placement(
+        &mut self,
+        box_x: u16,
+        box_width: u16,
+        y: u16,
+        placement: Option<&Placement>,
+    ) -> io::Result<()> {
+        let Some(placement) = placement else {
+            return Ok(());
+        };
+
+        let line = placement.to_string();
+        let x = box_x + box_width.saturating_sub(line.len() as u16) / 2;
+        execute!(
+            stdout(),
+            cursor::MoveTo(x, y),
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(line),
+            style::SetForegroundColor(Color::White)
+        )
+    }
+
+    /// Draws the run statistics as a two-column table centred in a box
+    fn draw_stats_table(
+        &mut self,
+        box_x: u16,
+        box_width: u16,
+        y: u16,
+        stats: &GameStats,
+    ) -> io::Result<()> {
+        const TABLE_WIDTH: u16 = 28;
+        let x = box_x + box_width.saturating_sub(TABLE_WIDTH) / 2;
+
+        execute!(
+            stdout(),
+            cursor::MoveTo(x, y),
+            style::SetForegroundColor(Color::Cyan),
+            style::Print("Run Summary"),
+            style::SetForegroundColor(Color::White)
+        )?;
+
+        for (i, (label, value)) in stats.summary_rows().iter().enumerate() {
+            execute!(
+                stdout(),
+                cursor::MoveTo(x, y + 1 + i as u16),
+                style::Print(format!("{label:<20}{value:>8}"))
+            )?;
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Game, MoveOutcome};
+    use crate::startup::StartupOptions;
+
+    const LEVEL: (usize, usize) = (80, 45);
+
+    #[test]
+    fn test_wrap_words_breaks_between_words() {
+        assert_eq!(
+            wrap_words("You've already taken today's challenge", 16),
+            ["You've already", "taken today's", "challenge"]
+        );
+        assert_eq!(wrap_words("Unbreakable", 4), ["Unbreakable"]);
+        assert!(wrap_words("", 10).is_empty());
+    }
+
+    #[test]
+    fn test_layout_rejects_small_terminals() {
+        let (min_width, min_height) = GameLayout::minimum_terminal_size();
+
+        assert_eq!(GameLayout::fit(80, 24, LEVEL.0, LEVEL.1), None);
+        assert_eq!(
+            GameLayout::fit(min_width - 1, min_height, LEVEL.0, LEVEL.1),
+            None
+        );
+
+        let layout = GameLayout::fit(min_width, min_height, LEVEL.0, LEVEL.1).unwrap();
+        assert_eq!(
+            (layout.map_width, layout.map_height),
+            (MIN_MAP_WIDTH, MIN_MAP_HEIGHT)
+        );
+        assert_eq!(
+            (layout.border_x, layout.border_y),
+            (SCREEN_MARGIN, SCREEN_MARGIN)
+        );
+    }
+
+    #[test]
+    fn test_layout_fits_recommended_terminal() {
+        let layout = GameLayout::fit(139, 41, LEVEL.0, LEVEL.1).unwrap();
+
+        assert_eq!((layout.map_width, layout.map_height), (70, 25));
+        assert_eq!(layout.content_origin(), (6, 6));
+        assert_eq!(layout.panel_x(), 76);
+        assert_eq!(layout.side_column_x(), 117);
+        assert_eq!(layout.log_y(), 36);
+        // The side column and message log stay on screen
+        assert!(layout.side_column_x() + SIDE_COLUMN_WIDTH <= 139);
+        assert!(layout.log_y() + MESSAGE_LOG_LINES <= 41);
+        assert_eq!(layout.message_rows(41), 2);
+        // Once the whole level fits, spare rows go to the log
+        let tall = GameLayout::fit(139, 100, LEVEL.0, LEVEL.1).unwrap();
+        assert!(tall.message_rows(100) > 2);
+        assert!(tall.log_y() + 1 + tall.message_rows(100) <= 100);
+    }
+
+    #[test]
+    fn test_layout_caps_map_at_level_size_and_centers() {
+        let layout = GameLayout::fit(300, 100, LEVEL.0, LEVEL.1).unwrap();
+
+        assert_eq!((layout.map_width, layout.map_height), LEVEL);
+        let (total_width, total_height) = layout.total_size();
+        assert_eq!(layout.border_x, (300 - total_width) / 2);
+        assert_eq!(layout.border_y, (100 - total_height) / 2);
+    }
+
+    #[test]
+    fn test_game_screen_fits_every_terminal_size() {
+        let ui = UI::new();
+        let mut game = Game::new(Player::new("Tester".to_string(), ClassType::Warrior));
+        game.update_visibility();
+        for term_width in (20..=300).step_by(7).chain([300]) {
+            for term_height in (10..=100).step_by(3).chain([100]) {
+                let mut frame = ScreenBuffer::new(term_width, term_height);
+                let layout = ui.compose_game_screen(
+                    &mut frame,
+                    &game.player,
+                    game.current_level(),
+                    game.current_dungeon(),
+                    &game.log,
+                    None,
+                );
+                let Some(layout) = layout else {
+                    continue;
+                };
+                let (total_width, total_height) = layout.total_size();
+                // The border's top edge is drawn on the row above border_y
+                assert!(layout.border_y >= 1);
+                assert!(layout.border_x + total_width <= term_width);
+                assert!(layout.border_y + total_height <= term_height);
+                assert!(layout.log_y() + layout.message_rows(term_height) < term_height);
+            }
+        }
+    }
+
+    /// Bytes written for the first frame and for the frame after one step,
+    /// on a terminal of the given size
+    fn frame_bytes(term_width: usize, term_height: usize) -> (usize, usize) {
+        let ui = UI::new();
+        // A fixed dungeon and no ambient events, so every run draws the same
+        let options = StartupOptions {
+            seed: Some(7),
+            ..StartupOptions::default()
+        };
+        let player = Player::new("Tester".to_string(), ClassType::Warrior);
+        let mut game = Game::with_options(player, &options);
+        game.ambient_events = false;
+        let mut screen = DoubleBuffer::new();
+        let mut present = |game: &mut Game| {
+            game.update_visibility();
+            let mut frame = ScreenBuffer::new(term_width, term_height);
+            ui.compose_game_screen(
+                &mut frame,
+                &game.player,
+                game.current_level(),
+                game.current_dungeon(),
+                &game.log,
+                None,
+            );
+            let mut out = Vec::new();
+            screen.present(frame, &mut out).unwrap();
+            out.len()
+        };
+
+        let full = present(&mut game);
+        let stepped = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+            .into_iter()
+            .any(|(dx, dy)| game.move_player(dx, dy) == MoveOutcome::Moved);
+        assert!(stepped, "the player should be able to step somewhere");
+        game.process_turn();
+        (full, present(&mut game))
+    }
+
+    #[test]
+    fn test_a_step_redraws_a_fraction_of_the_screen() {
+        // The map scrolls with the player, but the border, panel, controls
+        // and legend stay as they were, as do unexplored parts of the map
+        for (term_width, term_height) in [(139, 41), (160, 70)] {
+            let (full, step) = frame_bytes(term_width, term_height);
+            let saved = 1.0 - step as f64 / full as f64;
+            assert!(
+                saved > 0.5,
+                "{term_width}x{term_height}: one step sent {step} of the full frame's {full} bytes"
+            );
+        }
+    }
+}
@@ -0,0 +1,286 @@
+//! Double-buffered drawing for the game screen
+//!
+//! A frame is drawn into a `ScreenBuffer` first. `DoubleBuffer::present`
+//! compares it with the frame already on the terminal and writes only the
+//! cells that changed, each run of neighbouring changes with one cursor move
+//! and as few color changes and prints as its colors allow. The whole frame
+//! is written when there is nothing to compare with: on the first frame,
+//! after the terminal is resized and after another screen was drawn.
+
+use crossterm::{
+    cursor, queue,
+    style::{self, Attribute, Color, Colors},
+    terminal::{self, ClearType},
+};
+use std::io::{self, Write};
+
+/// One character cell of the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub symbol: char,
+    pub fg: Color,
+    pub bg: Color,
+    /// Drawn in reverse video, foreground and background swapped
+    pub reverse: bool,
+}
+
+impl Cell {
+    /// What a freshly cleared terminal shows
+    pub const BLANK: Cell = Cell {
+        symbol: ' ',
+        fg: Color::Reset,
+        bg: Color::Reset,
+        reverse: false,
+    };
+
+    pub fn new(symbol: char, fg: Color) -> Self {
+        Cell {
+            symbol,
+            fg,
+            bg: Color::Reset,
+            reverse: false,
+        }
+    }
+}
+
+/// A terminal's worth of cells, row by row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenBuffer {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+}
+
+impl ScreenBuffer {
+    /// A blank buffer of `width` x `height` cells
+    pub fn new(width: usize, height: usize) -> Self {
+        ScreenBuffer {
+            width,
+            height,
+            cells: vec![Cell::BLANK; width * height],
+        }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Sets one cell; anything outside the buffer is dropped
+    pub fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = cell;
+        }
+    }
+
+    /// Writes `text` from `(x, y)` in one color, cut off at the right edge
+    pub fn print(&mut self, x: usize, y: usize, text: &str, fg: Color) {
+        for (i, symbol) in text.chars().enumerate() {
+            self.set(x + i, y, Cell::new(symbol, fg));
+        }
+    }
+
+    /// Turns the `width` cells from `(x, y)` to reverse video
+    pub fn reverse(&mut self, x: usize, y: usize, width: usize) {
+        if y >= self.height {
+            return;
+        }
+        for x in x..(x + width).min(self.width) {
+            self.cells[y * self.width + x].reverse = true;
+        }
+    }
+
+    /// Blanks row `y`
+    pub fn clear_row(&mut self, y: usize) {
+        for x in 0..self.width {
+            self.set(x, y, Cell::BLANK);
+        }
+    }
+
+    /// Draws a white line box with `title` centered in its top edge; the box
+    /// covers `width` columns and `height` rows from `(x, y)`
+    pub fn draw_box(&mut self, x: usize, y: usize, width: usize, height: usize, title: &str) {
+        if width < 2 || height < 2 {
+            return;
+        }
+        let (right, bottom) = (x + width - 1, y + height - 1);
+        for column in x + 1..right {
+            self.set(column, y, Cell::new('─', Color::White));
+            self.set(column, bottom, Cell::new('─', Color::White));
+        }
+        for row in y + 1..bottom {
+            self.set(x, row, Cell::new('│', Color::White));
+            self.set(right, row, Cell::new('│', Color::White));
+        }
+        self.set(x, y, Cell::new('┌', Color::White));
+        self.set(right, y, Cell::new('┐', Color::White));
+        self.set(x, bottom, Cell::new('└', Color::White));
+        self.set(right, bottom, Cell::new('┘', Color::White));
+
+        let title_x = x + width.saturating_sub(title.chars().count()) / 2;
+        self.print(title_x, y, title, Color::Cyan);
+    }
+}
+
+/// Keeps the frame on the terminal so the next one can be written as the
+/// difference between the two
+#[derive(Debug, Default)]
+pub struct DoubleBuffer {
+    /// What the terminal shows, or None if that isn't known
+    front: Option<ScreenBuffer>,
+}
+
+impl DoubleBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets what the terminal shows, so the next frame is written in
+    /// full; called whenever something else draws on the terminal
+    pub fn invalidate(&mut self) {
+        self.front = None;
+    }
+
+    /// Writes what changed between the frame on the terminal and `frame`
+    /// to `out`, then keeps `frame` as the one on the terminal
+    pub fn present<W: Write>(&mut self, frame: ScreenBuffer, out: &mut W) -> io::Result<()> {
+        let blank;
+        let front = match &self.front {
+            Some(front) if front.size() == frame.size() => front,
+            _ => {
+                queue!(out, style::ResetColor, terminal::Clear(ClearType::All))?;
+                blank = ScreenBuffer::new(frame.width, frame.height);
+                &blank
+            }
+        };
+
+        let mut colors = None;
+        let mut reverse = false;
+        let mut text = String::new();
+        for y in 0..frame.height {
+            let row = y * frame.width..(y + 1) * frame.width;
+            let (old, new) = (&front.cells[row.clone()], &frame.cells[row]);
+            let mut x = 0;
+            while x < frame.width {
+                if old[x] == new[x] {
+                    x += 1;
+                    continue;
+                }
+
+                // A run of changed cells: one move, then print them in
+                // stretches of the same colors
+                queue!(out, cursor::MoveTo(x as u16, y as u16))?;
+                while x < frame.width && old[x] != new[x] {
+                    let cell = new[x];
+                    if colors != Some((cell.fg, cell.bg)) || reverse != cell.reverse {
+                        if !text.is_empty() {
+                            queue!(out, style::Print(&text))?;
+                            text.clear();
+                        }
+                        if reverse != cell.reverse {
+                            let attribute = if cell.reverse {
+                                Attribute::Reverse
+                            } else {
+                                Attribute::NoReverse
+                            };
+                            queue!(out, style::SetAttribute(attribute))?;
+                            reverse = cell.reverse;
+                        }
+                        queue!(out, style::SetColors(Colors::new(cell.fg, cell.bg)))?;
+                        colors = Some((cell.fg, cell.bg));
+                    }
+                    text.push(cell.symbol);
+                    x += 1;
+                }
+                queue!(out, style::Print(&text))?;
+                text.clear();
+            }
+        }
+
+        if reverse {
+            queue!(out, style::SetAttribute(Attribute::NoReverse))?;
+        }
+        queue!(out, style::ResetColor)?;
+        out.flush()?;
+        self.front = Some(frame);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_frame() -> ScreenBuffer {
+        let mut frame = ScreenBuffer::new(80, 24);
+        frame.draw_box(0, 0, 80, 24, "Title");
+        for y in 1..23 {
+            frame.print(1, y, &"#.".repeat(39), Color::DarkGrey);
+        }
+        frame
+    }
+
+    fn present(buffer: &mut DoubleBuffer, frame: ScreenBuffer) -> Vec<u8> {
+        let mut out = Vec::new();
+        buffer.present(frame, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_only_changed_cells_are_written() {
+        let mut buffer = DoubleBuffer::new();
+        let full = present(&mut buffer, sample_frame());
+        assert!(String::from_utf8_lossy(&full).contains("Title"));
+
+        // Nothing changed: just the color reset
+        let unchanged = present(&mut buffer, sample_frame());
+        assert!(!String::from_utf8_lossy(&unchanged).contains('#'));
+
+        let mut frame = sample_frame();
+        frame.print(10, 5, "@@", Color::Yellow);
+        let moved = present(&mut buffer, frame);
+        // A frame with a step taken costs a small part of a full redraw
+        assert!(
+            moved.len() * 10 < full.len(),
+            "{} of {}",
+            moved.len(),
+            full.len()
+        );
+        let written = String::from_utf8_lossy(&moved);
+        assert_eq!(written.matches('@').count(), 2);
+        // The two neighbouring cells go out as one run
+        assert_eq!(written.matches("\x1b[6;11H").count(), 1);
+        assert!(!written.contains('#'));
+    }
+
+    #[test]
+    fn test_reversed_cells_are_rewritten_and_switched_back() {
+        let mut buffer = DoubleBuffer::new();
+        present(&mut buffer, sample_frame());
+
+        let mut frame = sample_frame();
+        frame.reverse(1, 3, 4);
+        let flashed = String::from_utf8_lossy(&present(&mut buffer, frame)).to_string();
+        assert_eq!(flashed.matches("\x1b[7m").count(), 1);
+        assert_eq!(flashed.matches("\x1b[27m").count(), 1);
+        assert_eq!(flashed.matches('#').count(), 2);
+
+        // The flash over, the same cells go back to normal
+        let restored = String::from_utf8_lossy(&present(&mut buffer, sample_frame())).to_string();
+        assert_eq!(restored.matches('#').count(), 2);
+        assert!(!restored.contains("\x1b[7m"));
+    }
+
+    #[test]
+    fn test_resize_and_invalidate_force_a_full_redraw() {
+        let mut buffer = DoubleBuffer::new();
+        let full = present(&mut buffer, sample_frame()).len();
+
+        buffer.invalidate();
+        assert_eq!(present(&mut buffer, sample_frame()).len(), full);
+
+        let mut wider = ScreenBuffer::new(100, 24);
+        wider.draw_box(0, 0, 80, 24, "Title");
+        let resized = present(&mut buffer, wider);
+        assert!(String::from_utf8_lossy(&resized).contains("Title"));
+    }
+}
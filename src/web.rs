@@ -1,48 +1,267 @@
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use web_sys::{
     console, window, CanvasRenderingContext2d, Document, HtmlCanvasElement, HtmlDivElement,
-    HtmlElement, KeyboardEvent,
+    HtmlElement, KeyboardEvent, MouseEvent, TouchEvent,
 };
 
-use crate::character::{ClassType, Player};
-use crate::combat::CombatAction;
-use crate::game::{Game, GameState};
-use crate::inventory::InventoryManager;
-use crate::world::{Position, TileType};
-
-// Game display constants - responsive sizing
-const MAP_WIDTH: i32 = 50;
-const MAP_HEIGHT: i32 = 20;
-const CELL_SIZE: i32 = 10;
-const UI_PANEL_WIDTH: i32 = 250;
-const MESSAGE_HEIGHT: i32 = 100;
-
-// Colors for different elements
-const PLAYER_COLOR: &str = "#FFD700"; // Gold
-const WALL_COLOR: &str = "#808080"; // Gray
-const FLOOR_COLOR: &str = "#2F4F2F"; // Dark green
-const DOOR_COLOR: &str = "#8B4513"; // Brown
-const ENEMY_COLOR: &str = "#FF0000"; // Red
-const ITEM_COLOR: &str = "#00FFFF"; // Cyan
-const CHEST_COLOR: &str = "#DAA520"; // Goldenrod
-const EXIT_COLOR: &str = "#32CD32"; // Lime green
-const FOG_COLOR: &str = "#1a1a1a"; // Very dark gray
+use crate::achievements::AchievementProfile;
+use crate::bones::{BonesPool, BonesRecord};
+use crate::character::{player, Class, ClassType, Player, StatAllocation, StatType};
+use crate::combat::{self, CombatAction};
+use crate::daily::DailyChallenge;
+use crate::effects::{ActiveEffects, ScreenEffect};
+use crate::game::{
+    export_game, import_game, quit_question, EventWatch, ExplorationPass, Game, GameEvent,
+    GameLoop, GameSnapshot, GameState, Input, PauseOption, PendingGame,
+};
+use crate::hall_of_fame::{self, Leaderboard, Placement};
+use crate::help;
+use crate::inventory::{InventoryManager, HOTBAR_SLOTS, RECIPES};
+use crate::item::Rarity;
+use crate::log::LogCategory;
+use crate::map_export;
+use crate::render::{
+    legend, view_origin, Bar, CombatView, FrameStats, Glyph, MapCell, MapFrame, StatPanel,
+    TileBuffer, TileChange, ViewLayout,
+};
+use crate::settings::{Setting, Settings};
+use crate::startup::StartupOptions;
+use crate::world::fog_of_war::FogColor;
+use crate::world::Position;
+
+// On-screen controls for touch devices, as (key sent, label)
+const TOUCH_DPAD: [(&str, &str); 4] = [
+    ("ArrowUp", "▲"),
+    ("ArrowLeft", "◀"),
+    ("ArrowRight", "▶"),
+    ("ArrowDown", "▼"),
+];
+const TOUCH_ACTIONS: [(&str, &str); 7] = [
+    ("g", "Get"),
+    ("i", "Inv"),
+    ("c", "Char"),
+    ("j", "Quests"),
+    ("1", "Attack"),
+    ("4", "Flee"),
+    ("Escape", "Back"),
+];
+/// Shortest swipe on the map, in map cells, that counts as a move
+const SWIPE_CELLS: f64 = 2.0;
+
+/// Shortest time between two steps while an arrow key is held, in
+/// milliseconds
+const MOVE_INTERVAL: f64 = 150.0;
+
+/// Rows of the area around the player marked as explored each frame, so
+/// arriving somewhere new never holds up a frame for long
+const EXPLORATION_ROWS_PER_FRAME: usize = 4;
+
+/// Height of a line of the help overlay, in CSS pixels
+const HELP_LINE_HEIGHT: f64 = 11.0;
+
+/// localStorage key holding the hall of fame as JSON
+const HALL_OF_FAME_KEY: &str = "echoes_rpg_hall_of_fame";
+
+/// localStorage key holding the achievements unlocked as JSON
+const ACHIEVEMENTS_KEY: &str = "echoes_rpg_achievements";
+
+/// localStorage key holding the bones of dead characters as JSON
+const BONES_KEY: &str = "echoes_rpg_bones";
+
+/// localStorage key holding the settings as JSON
+const SETTINGS_KEY: &str = "echoes_rpg_settings";
+
+// Colors for the page; map glyph colors come from the render module
 const BACKGROUND_COLOR: &str = "#000000"; // Black
 const TEXT_COLOR: &str = "#00FF00"; // Green terminal text
 const BORDER_COLOR: &str = "#00FF00"; // Green border
 
+/// List opened from the combat actions, picked from with the number keys
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CombatMenu {
+    Abilities,
+    Items,
+}
+
+/// Steps of putting an item on the hotbar from the inventory
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum HotbarPick {
+    /// 'a' was pressed; the next number picks the item
+    Item,
+    /// The item at this index was picked; the next number picks its slot
+    Slot(usize),
+}
+
+/// Steps of enchanting an item at a shrine from the inventory
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EnchantPick {
+    /// 'n' was pressed; the next number picks the item
+    Item,
+    /// The offer for the item at this index is shown; Y or Enter takes it
+    Confirm(usize),
+}
+
+/// Steps of character creation after "Start New Game"
+#[derive(Debug, Clone, PartialEq)]
+enum CharacterCreation {
+    /// Typing the name; holds what has been typed so far
+    EnteringName(String),
+    /// Picking a class for the chosen name
+    SelectingClass(String),
+    /// Spending points on the chosen class's stats; `selected` indexes
+    /// `StatType::iter()`
+    CustomizingStats {
+        name: String,
+        allocation: StatAllocation,
+        selected: usize,
+    },
+}
+
+/// Something the player did, recorded by an event handler for the next
+/// frame to act on
+#[derive(Debug, Clone, PartialEq)]
+enum Intent {
+    Key(String),
+    /// Shift+number in the inventory, showing that item's details
+    Inspect(usize),
+    /// A click on the map, in CSS pixels
+    Look(i32, i32),
+}
+
+/// Input waiting for the next frame. The event handlers only fill this in;
+/// `tick` acts on it, so no handler does any game work itself.
+#[derive(Debug, Default)]
+struct InputState {
+    /// Everything done since the last frame, in order
+    intents: VecDeque<Intent>,
+    /// Arrow keys held down, the latest last; the latest keeps the player
+    /// walking
+    held: Vec<String>,
+}
+
+impl InputState {
+    fn press(&mut self, intent: Intent) {
+        self.intents.push_back(intent);
+    }
+
+    fn hold(&mut self, key: &str) {
+        self.release(key);
+        self.held.push(key.to_string());
+    }
+
+    fn release(&mut self, key: &str) {
+        self.held.retain(|held| held != key);
+    }
+}
+
+fn is_arrow(key: &str) -> bool {
+    matches!(key, "ArrowUp" | "ArrowDown" | "ArrowLeft" | "ArrowRight")
+}
+
+/// Handle exported to JavaScript; the mutable state is shared with the
+/// keyboard callbacks through `Rc<RefCell<_>>`
 #[wasm_bindgen]
 pub struct WebGame {
+    inner: Rc<RefCell<GameInner>>,
+}
+
+/// Game and DOM state mutated by the event handlers
+struct GameInner {
     game: Game,
+    /// Holds everything below; set to a portrait or landscape layout to
+    /// match the window
+    container: HtmlDivElement,
+    /// Space the canvas is fitted into
+    map_area: HtmlDivElement,
     canvas: HtmlCanvasElement,
     context: CanvasRenderingContext2d,
+    /// How many map cells the canvas shows and how big they are
+    view: ViewLayout,
+    /// Set when the window may have changed size, so the next frame fits
+    /// the canvas to it again
+    layout_pending: bool,
     ui_panel: HtmlDivElement,
     message_area: HtmlDivElement,
-    pressed_keys: HashMap<String, bool>,
-    last_key_time: f64,
-    key_repeat_delay: f64,
+    input: InputState,
+    /// When the player last took a step, in milliseconds
+    last_move_time: f64,
+    move_interval: f64,
+    /// Set when something changed, so the next frame draws the game
+    needs_render: bool,
+    /// The area around the player still being marked as explored
+    exploration: Option<ExplorationPass>,
+    showing_journal: bool,
+    showing_bestiary: bool,
+    /// First line shown while the help overlay is open
+    help_scroll: Option<usize>,
+    /// Set after 'd' in the inventory; the next number drops that item
+    pending_drop: bool,
+    /// How far through putting an item on the hotbar, after 'a' in the
+    /// inventory
+    pending_hotbar: Option<HotbarPick>,
+    /// How far through enchanting an item, after 'n' in the inventory at a
+    /// shrine
+    pending_enchant: Option<EnchantPick>,
+    /// Whether the map tints the tiles enemies in sight can reach; Tab
+    /// turns it on and off
+    show_threats: bool,
+    /// Flashes and the map's shake, timed by the page's clock
+    effects: ActiveEffects,
+    /// How far the canvas is pushed from its place by the shake, in CSS
+    /// pixels
+    shake: (f32, f32),
+    /// Set after 'r' in the inventory; the recipe list is shown and the
+    /// next number crafts that recipe
+    pending_craft: bool,
+    /// A new game whose world is being generated, a level a frame
+    generating: Option<PendingGame>,
+    /// Inventory index whose details are shown, chosen with Shift+number
+    inspected_item: Option<usize>,
+    /// Description of the last map tile clicked, shown in the UI panel
+    look_description: Option<String>,
+    /// On-screen D-pad and buttons, hidden until the device shows it has touch
+    touch_controls: HtmlDivElement,
+    /// Where the current swipe on the map started, in CSS pixels
+    swipe_start: Option<(i32, i32)>,
+    /// Whether the finished game has been added to the hall of fame
+    run_recorded: bool,
+    /// Where the finished game placed in the hall of fame
+    run_placement: Option<Placement>,
+    /// Ability or item list open during combat
+    combat_menu: Option<CombatMenu>,
+    /// What each key does in each state, and the enemy being attacked
+    game_loop: GameLoop,
+    settings: Settings,
+    /// Highlighted row while the options screen is shown
+    options_selected: Option<usize>,
+    /// Set while a new character is being made from the title screen
+    creation: Option<CharacterCreation>,
+    /// Today's challenge, while its character is being named
+    daily: Option<DailyChallenge>,
+    /// Highlighted entry of the pause menu
+    pause_selected: usize,
+    /// Whether the pause menu asks before quitting to the title screen
+    confirming_quit: bool,
+    /// What the canvas shows of the map, so a frame only redraws the tiles
+    /// that changed
+    tiles: TileBuffer,
+    /// Each glyph drawn once onto a cell-sized canvas, copied onto the map
+    /// wherever it appears
+    glyph_sprites: HashMap<Glyph, HtmlCanvasElement>,
+    /// HTML last put in the UI panel, so it's only replaced when it changes
+    panel_html: String,
+    /// Frame and tile counts shown by the F3 debug overlay
+    frame_stats: FrameStats,
+    /// Debug overlay over the top-left of the map, hidden until F3
+    stats_overlay: HtmlDivElement,
+    /// Watches for the level-ups, deaths and victories the page hears about
+    events: EventWatch,
+    /// Called with each of those events as JSON, once the page registers it
+    event_listener: Option<js_sys::Function>,
 }
 
 #[wasm_bindgen]
@@ -51,6 +270,326 @@ impl WebGame {
     pub fn new() -> Result<WebGame, JsValue> {
         console::log_1(&"Creating Echoes RPG Visual Dungeon Crawler...".into());
 
+        Ok(WebGame {
+            inner: Rc::new(RefCell::new(GameInner::new()?)),
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn start_game(&mut self) -> Result<(), JsValue> {
+        console::log_1(&"Starting visual dungeon crawler...".into());
+
+        self.setup_keyboard_handlers()?;
+        self.setup_mouse_handlers()?;
+        self.setup_touch_handlers()?;
+        self.inner.borrow_mut().show_title_screen()?;
+        self.start_animation_loop()?;
+
+        Ok(())
+    }
+
+    /// Where the game stands, as a `GameSnapshot` in JSON
+    #[wasm_bindgen]
+    pub fn get_state_json(&self) -> String {
+        match self.inner.try_borrow() {
+            Ok(inner) => GameSnapshot::of(&inner.game).to_json(),
+            Err(_) => String::new(),
+        }
+    }
+
+    /// Presses `key` as if on the keyboard, taking the names
+    /// `KeyboardEvent.key` uses: "ArrowUp", "g", "Enter", "Escape"
+    #[wasm_bindgen]
+    pub fn send_key(&self, key: &str) {
+        if let Ok(mut inner) = self.inner.try_borrow_mut() {
+            inner.input.press(Intent::Key(key.to_string()));
+        }
+    }
+
+    /// The whole game as a string, for the page to keep where it likes;
+    /// empty if it couldn't be written
+    #[wasm_bindgen]
+    pub fn save(&self) -> String {
+        match self.inner.try_borrow() {
+            Ok(inner) => export_game(&inner.game),
+            Err(_) => String::new(),
+        }
+    }
+
+    /// Picks up a game kept from `save`. Returns false, leaving the current
+    /// game be, if `data` isn't one.
+    #[wasm_bindgen]
+    pub fn load(&self, data: &str) -> bool {
+        let Some(game) = import_game(data) else {
+            return false;
+        };
+        match self.inner.try_borrow_mut() {
+            Ok(mut inner) => inner.resume_game(game).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    /// Calls `callback` with a `GameEvent` in JSON each time the player
+    /// levels up, dies or wins, replacing any callback registered before
+    #[wasm_bindgen]
+    pub fn on_event(&self, callback: js_sys::Function) {
+        if let Ok(mut inner) = self.inner.try_borrow_mut() {
+            inner.event_listener = Some(callback);
+        }
+    }
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const EMBEDDING_TYPES: &str = r#"
+/** What `WebGame.get_state_json()` returns, once parsed */
+export interface GameSnapshot {
+    player: {
+        name: string;
+        class: string;
+        level: number;
+        experience: number;
+        health: number;
+        max_health: number;
+        mana: number;
+        max_mana: number;
+        gold: number;
+    };
+    dungeon: string;
+    /** Counting from 1 */
+    level: number;
+    state: "main_menu" | "playing" | "combat" | "inventory" | "character"
+        | "paused" | "dialogue" | "game_over" | "victory";
+    /** The newest lines of the message log, oldest first */
+    messages: string[];
+}
+
+/** What the `WebGame.on_event()` callback is passed, once parsed */
+export type GameEvent =
+    | { type: "level_up"; level: number }
+    | { type: "death" }
+    | { type: "victory" };
+"#;
+
+impl WebGame {
+    /// Runs `tick` once per animation frame from now on
+    fn start_animation_loop(&self) -> Result<(), JsValue> {
+        // The callback asks for the frame after its own, so it needs a
+        // handle on itself
+        let callback: Rc<RefCell<Option<Closure<dyn FnMut(f64)>>>> = Rc::new(RefCell::new(None));
+        let next_frame = Rc::clone(&callback);
+        let inner = Rc::clone(&self.inner);
+        *callback.borrow_mut() = Some(Closure::wrap(Box::new(move |now: f64| {
+            run_frame(&inner, now);
+            if let Some(callback) = next_frame.borrow().as_ref() {
+                let _ = request_animation_frame(callback);
+            }
+        }) as Box<dyn FnMut(f64)>));
+
+        if let Some(callback) = callback.borrow().as_ref() {
+            request_animation_frame(callback)?;
+        }
+        Ok(())
+    }
+
+    fn setup_keyboard_handlers(&self) -> Result<(), JsValue> {
+        let window = window().unwrap();
+        let document = window.document().unwrap();
+
+        // Key presses are recorded for the next frame; the closures keep
+        // their own handle on the state
+        let inner = Rc::clone(&self.inner);
+        let keydown_closure = Closure::wrap(Box::new(move |event: KeyboardEvent| {
+            // Ctrl+U asks to be pulled free, and mustn't open the page source
+            let key = if event.ctrl_key() && event.key().eq_ignore_ascii_case("u") {
+                event.prevent_default();
+                "Ctrl+U".to_string()
+            } else {
+                event.key()
+            };
+
+            // Prevent browser shortcuts for game keys
+            match key.as_str() {
+                "ArrowUp" | "ArrowDown" | "ArrowLeft" | "ArrowRight" | "i" | "I" | "c" | "C"
+                | "g" | "G" | "q" | "Q" | " " | "Enter" | "Escape" | "F3" | "Tab" => {
+                    event.prevent_default();
+                    event.stop_propagation();
+                }
+                _ => {}
+            }
+
+            // Holding an arrow walks at the game's own pace, not the
+            // keyboard's repeat rate
+            if event.repeat() {
+                return;
+            }
+            // Shift+number inspects an item; the digit comes from the physical
+            // key since the shifted character depends on the keyboard layout
+            let inspect_digit = event
+                .code()
+                .strip_prefix("Digit")
+                .and_then(|digit| digit.parse::<usize>().ok())
+                .filter(|digit| event.shift_key() && *digit > 0);
+            // Skip the event rather than panic if the state is already borrowed
+            if let Ok(mut inner) = inner.try_borrow_mut() {
+                match inspect_digit {
+                    Some(digit) if matches!(inner.game.game_state, GameState::Inventory) => {
+                        inner.input.press(Intent::Inspect(digit - 1))
+                    }
+                    _ => inner.input.press(Intent::Key(key.clone())),
+                }
+                if is_arrow(&key) {
+                    inner.input.hold(&key);
+                }
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        document.add_event_listener_with_callback(
+            "keydown",
+            keydown_closure.as_ref().unchecked_ref(),
+        )?;
+        keydown_closure.forget();
+
+        let inner = Rc::clone(&self.inner);
+        let keyup_closure = Closure::wrap(Box::new(move |event: KeyboardEvent| {
+            if let Ok(mut inner) = inner.try_borrow_mut() {
+                inner.input.release(&event.key());
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        document
+            .add_event_listener_with_callback("keyup", keyup_closure.as_ref().unchecked_ref())?;
+        keyup_closure.forget();
+
+        // Keys let go of while the page is in the background never send a
+        // keyup, so stop walking when it loses focus
+        let inner = Rc::clone(&self.inner);
+        let blur_closure = Closure::wrap(Box::new(move || {
+            if let Ok(mut inner) = inner.try_borrow_mut() {
+                inner.input.held.clear();
+            }
+        }) as Box<dyn FnMut()>);
+        window.add_event_listener_with_callback("blur", blur_closure.as_ref().unchecked_ref())?;
+        blur_closure.forget();
+
+        Ok(())
+    }
+
+    /// Clicking a map tile describes it in the UI panel
+    fn setup_mouse_handlers(&self) -> Result<(), JsValue> {
+        let canvas = self.inner.borrow().canvas.clone();
+
+        let inner = Rc::clone(&self.inner);
+        let click_closure = Closure::wrap(Box::new(move |event: MouseEvent| {
+            if let Ok(mut inner) = inner.try_borrow_mut() {
+                inner
+                    .input
+                    .press(Intent::Look(event.offset_x(), event.offset_y()));
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        canvas.add_event_listener_with_callback("click", click_closure.as_ref().unchecked_ref())?;
+        click_closure.forget();
+
+        Ok(())
+    }
+
+    /// Touch buttons and map swipes feed the same path as key presses. The
+    /// buttons appear on coarse-pointer devices or after the first touch, so
+    /// desktop keyboard play is left alone.
+    fn setup_touch_handlers(&self) -> Result<(), JsValue> {
+        let window = window().unwrap();
+        let document = window.document().unwrap();
+        let (canvas, controls) = {
+            let inner = self.inner.borrow();
+            (inner.canvas.clone(), inner.touch_controls.clone())
+        };
+
+        let coarse_pointer = window
+            .match_media("(pointer: coarse)")?
+            .is_some_and(|query| query.matches());
+        if coarse_pointer {
+            self.inner.borrow_mut().show_touch_controls()?;
+        }
+
+        // The first touch anywhere reveals the controls
+        let inner = Rc::clone(&self.inner);
+        let reveal_closure = Closure::wrap(Box::new(move |_event: TouchEvent| {
+            if let Ok(mut inner) = inner.try_borrow_mut() {
+                let _ = inner.show_touch_controls();
+            }
+        }) as Box<dyn FnMut(_)>);
+        document.add_event_listener_with_callback(
+            "touchstart",
+            reveal_closure.as_ref().unchecked_ref(),
+        )?;
+        reveal_closure.forget();
+
+        // Buttons carry the key they stand for in a data-key attribute
+        let inner = Rc::clone(&self.inner);
+        let button_closure = Closure::wrap(Box::new(move |event: TouchEvent| {
+            let key = event
+                .target()
+                .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+                .and_then(|element| element.get_attribute("data-key"));
+            if let Some(key) = key {
+                // Stop the browser from zooming or sending a mouse click too
+                event.prevent_default();
+                if let Ok(mut inner) = inner.try_borrow_mut() {
+                    inner.input.press(Intent::Key(key));
+                }
+            }
+        }) as Box<dyn FnMut(_)>);
+        controls.add_event_listener_with_callback(
+            "touchstart",
+            button_closure.as_ref().unchecked_ref(),
+        )?;
+        button_closure.forget();
+
+        // Swiping across the map moves the player one step
+        let inner = Rc::clone(&self.inner);
+        let swipe_start_closure = Closure::wrap(Box::new(move |event: TouchEvent| {
+            if let (Ok(mut inner), Some(touch)) = (inner.try_borrow_mut(), event.touches().get(0)) {
+                inner.swipe_start = Some((touch.client_x(), touch.client_y()));
+            }
+        }) as Box<dyn FnMut(_)>);
+        canvas.add_event_listener_with_callback(
+            "touchstart",
+            swipe_start_closure.as_ref().unchecked_ref(),
+        )?;
+        swipe_start_closure.forget();
+
+        let inner = Rc::clone(&self.inner);
+        let swipe_end_closure = Closure::wrap(Box::new(move |event: TouchEvent| {
+            if let (Ok(mut inner), Some(touch)) =
+                (inner.try_borrow_mut(), event.changed_touches().get(0))
+            {
+                inner.finish_swipe(touch.client_x(), touch.client_y());
+            }
+        }) as Box<dyn FnMut(_)>);
+        canvas.add_event_listener_with_callback(
+            "touchend",
+            swipe_end_closure.as_ref().unchecked_ref(),
+        )?;
+        swipe_end_closure.forget();
+
+        // The canvas, the layout around it and the button sizes follow the
+        // page as it's resized, rotated or zoomed
+        let inner = Rc::clone(&self.inner);
+        let resize_closure = Closure::wrap(Box::new(move || {
+            if let Ok(mut inner) = inner.try_borrow_mut() {
+                inner.layout_pending = true;
+            }
+        }) as Box<dyn FnMut()>);
+        window
+            .add_event_listener_with_callback("resize", resize_closure.as_ref().unchecked_ref())?;
+        resize_closure.forget();
+
+        Ok(())
+    }
+}
+
+impl GameInner {
+    fn new() -> Result<GameInner, JsValue> {
         let window = window().unwrap();
         let document = window.document().unwrap();
 
@@ -67,13 +606,19 @@ impl WebGame {
         // Create message area
         let message_area = Self::create_message_area(&document)?;
 
+        // Create touch controls, hidden until needed
+        let touch_controls = Self::create_touch_controls(&document)?;
+
         // Add elements to container
         let map_area = Self::create_map_area(&document)?;
         map_area.append_child(&canvas)?;
+        let stats_overlay = Self::create_stats_overlay(&document)?;
+        map_area.append_child(&stats_overlay)?;
 
         container.append_child(&map_area)?;
         container.append_child(&ui_panel)?;
         container.append_child(&message_area)?;
+        container.append_child(&touch_controls)?;
 
         // Add container to main-content div instead of body
         let main_content = document
@@ -82,31 +627,62 @@ impl WebGame {
         main_content.append_child(&container)?;
 
         // Create game instance with default player for now
+        let settings = load_settings();
         let player = Player::new("WebHero".to_string(), ClassType::Warrior);
-        let game = Game::new(player);
+        let mut game = Game::new(player);
+        game.apply_settings(&settings);
 
-        let web_game = WebGame {
+        // The canvas is fitted on the first frame, once the page is laid out
+        let view = ViewLayout::fit(0.0, 0.0, 1.0);
+
+        Ok(GameInner {
             game,
+            container,
+            map_area,
             canvas,
             context,
+            view,
+            layout_pending: true,
             ui_panel,
             message_area,
-            pressed_keys: HashMap::new(),
-            last_key_time: 0.0,
-            key_repeat_delay: 150.0, // milliseconds
-        };
-
-        Ok(web_game)
-    }
-
-    #[wasm_bindgen]
-    pub fn start_game(&mut self) -> Result<(), JsValue> {
-        console::log_1(&"Starting visual dungeon crawler...".into());
-
-        self.setup_keyboard_handlers()?;
-        self.show_title_screen()?;
-
-        Ok(())
+            input: InputState::default(),
+            last_move_time: 0.0,
+            move_interval: MOVE_INTERVAL,
+            needs_render: false,
+            exploration: None,
+            showing_journal: false,
+            showing_bestiary: false,
+            help_scroll: None,
+            pending_drop: false,
+            pending_hotbar: None,
+            pending_enchant: None,
+            pending_craft: false,
+            generating: None,
+            show_threats: false,
+            effects: ActiveEffects::default(),
+            shake: (0.0, 0.0),
+            inspected_item: None,
+            look_description: None,
+            touch_controls,
+            swipe_start: None,
+            run_recorded: false,
+            run_placement: None,
+            combat_menu: None,
+            game_loop: GameLoop::new(),
+            settings,
+            options_selected: None,
+            creation: None,
+            daily: None,
+            pause_selected: 0,
+            confirming_quit: false,
+            tiles: TileBuffer::new(view.columns, view.rows),
+            glyph_sprites: HashMap::new(),
+            panel_html: String::new(),
+            frame_stats: FrameStats::default(),
+            stats_overlay,
+            events: EventWatch::default(),
+            event_listener: None,
+        })
     }
 
     fn create_game_container(document: &Document) -> Result<HtmlDivElement, JsValue> {
@@ -123,13 +699,19 @@ impl WebGame {
             .create_element("canvas")?
             .dyn_into::<HtmlCanvasElement>()?;
         canvas.set_id("game-canvas");
-        canvas.set_width((MAP_WIDTH * CELL_SIZE) as u32);
-        canvas.set_height((MAP_HEIGHT * CELL_SIZE) as u32);
 
+        // Centered in the map area without adding to its size, so the map
+        // area can be measured for the space the canvas has
         let style = canvas.style();
+        style.set_property("position", "absolute")?;
+        style.set_property("left", "50%")?;
+        style.set_property("top", "50%")?;
+        style.set_property("transform", "translate(-50%, -50%)")?;
+        // The border goes around the size set, so the backing store is
+        // shown pixel for pixel
+        style.set_property("box-sizing", "content-box")?;
         style.set_property("border", &format!("1px solid {}", BORDER_COLOR))?;
         style.set_property("background", BACKGROUND_COLOR)?;
-        style.set_property("image-rendering", "pixelated")?;
 
         Ok(canvas)
     }
@@ -147,15 +729,37 @@ impl WebGame {
             .dyn_into::<HtmlDivElement>()?;
         map_area.set_id("map-area");
 
+        // Takes whatever space the layout leaves it; the canvas and the
+        // debug overlay are placed against it
         let style = map_area.dyn_ref::<HtmlElement>().unwrap().style();
-        style.set_property("display", "flex")?;
-        style.set_property("gap", "5px")?;
-        style.set_property("flex-wrap", "wrap")?;
-        style.set_property("justify-content", "center")?;
+        style.set_property("position", "relative")?;
+        style.set_property("overflow", "hidden")?;
+        style.set_property("min-width", "0")?;
+        style.set_property("min-height", "0")?;
 
         Ok(map_area)
     }
 
+    fn create_stats_overlay(document: &Document) -> Result<HtmlDivElement, JsValue> {
+        let overlay = document
+            .create_element("div")?
+            .dyn_into::<HtmlDivElement>()?;
+        overlay.set_id("frame-stats");
+
+        let style = overlay.style();
+        style.set_property("display", "none")?;
+        style.set_property("position", "absolute")?;
+        style.set_property("top", "2px")?;
+        style.set_property("left", "2px")?;
+        style.set_property("padding", "2px 4px")?;
+        style.set_property("background", "rgba(0, 0, 0, 0.75)")?;
+        style.set_property("color", "#FFFF00")?;
+        style.set_property("font", "10px monospace")?;
+        style.set_property("pointer-events", "none")?;
+
+        Ok(overlay)
+    }
+
     fn create_ui_panel(document: &Document) -> Result<HtmlDivElement, JsValue> {
         let panel = document
             .create_element("div")?
@@ -163,9 +767,6 @@ impl WebGame {
         panel.set_id("ui-panel");
 
         let style = panel.dyn_ref::<HtmlElement>().unwrap().style();
-        style.set_property("min-width", "200px")?;
-        style.set_property("max-width", "300px")?;
-        style.set_property("flex", "1")?;
         style.set_property("background", "rgba(0, 20, 0, 0.8)")?;
         style.set_property("border", &format!("1px solid {}", BORDER_COLOR))?;
         style.set_property("padding", "8px")?;
@@ -185,9 +786,7 @@ impl WebGame {
         messages.set_id("message-area");
 
         let style = messages.dyn_ref::<HtmlElement>().unwrap().style();
-        style.set_property("width", "100%")?;
         style.set_property("height", "80px")?;
-        style.set_property("max-height", "120px")?;
         style.set_property("background", "rgba(0, 20, 0, 0.8)")?;
         style.set_property("border", &format!("1px solid {}", BORDER_COLOR))?;
         style.set_property("padding", "8px")?;
@@ -195,147 +794,730 @@ impl WebGame {
         style.set_property("font-size", "10px")?;
         style.set_property("font-family", "'Courier New', monospace")?;
         style.set_property("overflow-y", "auto")?;
-        style.set_property("margin-top", "5px")?;
 
         Ok(messages)
     }
 
-    fn setup_keyboard_handlers(&mut self) -> Result<(), JsValue> {
-        let window = window().unwrap();
-        let document = window.document().unwrap();
-
-        // Prevent default browser shortcuts
-        let keydown_closure = Closure::wrap(Box::new(move |event: KeyboardEvent| {
-            let key = event.key();
+    /// A D-pad and a row of action and number buttons, each tagged with
+    /// the key it sends
+    fn create_touch_controls(document: &Document) -> Result<HtmlDivElement, JsValue> {
+        let controls = document
+            .create_element("div")?
+            .dyn_into::<HtmlDivElement>()?;
+        controls.set_id("touch-controls");
 
-            // Prevent browser shortcuts for game keys
-            match key.as_str() {
-                "ArrowUp" | "ArrowDown" | "ArrowLeft" | "ArrowRight" | "i" | "I" | "c" | "C"
-                | "g" | "G" | "q" | "Q" | " " | "Enter" | "Escape" => {
-                    event.prevent_default();
-                    event.stop_propagation();
-                }
-                _ => {}
-            }
-        }) as Box<dyn FnMut(_)>);
+        let style = controls.style();
+        style.set_property("display", "none")?;
+        style.set_property("flex-wrap", "wrap")?;
+        style.set_property("gap", "10px")?;
+        style.set_property("justify-content", "center")?;
+        style.set_property("margin-top", "5px")?;
+        style.set_property("user-select", "none")?;
+        style.set_property("touch-action", "manipulation")?;
+
+        // Arrows sit around an empty middle cell of a 3x3 grid
+        let dpad = Self::create_button_group(document, "repeat(3, var(--touch-size))")?;
+        for (index, (key, label)) in TOUCH_DPAD.iter().enumerate() {
+            let button = Self::create_touch_button(document, key, label)?;
+            let (column, row) = [(2, 1), (1, 2), (3, 2), (2, 3)][index];
+            button
+                .style()
+                .set_property("grid-area", &format!("{row} / {column}"))?;
+            dpad.append_child(&button)?;
+        }
+        controls.append_child(&dpad)?;
 
-        document.add_event_listener_with_callback(
-            "keydown",
-            keydown_closure.as_ref().unchecked_ref(),
-        )?;
-        keydown_closure.forget();
+        let actions = Self::create_button_group(document, "repeat(4, auto)")?;
+        for (key, label) in TOUCH_ACTIONS {
+            let button = Self::create_touch_button(document, key, label)?;
+            actions.append_child(&button)?;
+        }
+        controls.append_child(&actions)?;
+
+        // Numbers pick menu entries, inventory items and abilities
+        let digits = Self::create_button_group(document, "repeat(9, auto)")?;
+        for digit in 1..=9 {
+            let key = digit.to_string();
+            let button = Self::create_touch_button(document, &key, &key)?;
+            digits.append_child(&button)?;
+        }
+        controls.append_child(&digits)?;
 
-        // Handle key processing
-        let game_ptr = self as *mut WebGame;
-        let keyup_closure = Closure::wrap(Box::new(move |event: KeyboardEvent| {
-            let key = event.key();
-            unsafe {
-                if let Some(game) = game_ptr.as_mut() {
-                    let _ = game.handle_key_input(&key);
-                }
-            }
-        }) as Box<dyn FnMut(_)>);
+        Ok(controls)
+    }
 
-        document
-            .add_event_listener_with_callback("keyup", keyup_closure.as_ref().unchecked_ref())?;
-        keyup_closure.forget();
+    fn create_button_group(document: &Document, columns: &str) -> Result<HtmlElement, JsValue> {
+        let group = document.create_element("div")?.dyn_into::<HtmlElement>()?;
+        let style = group.style();
+        style.set_property("display", "grid")?;
+        style.set_property("grid-template-columns", columns)?;
+        style.set_property("gap", "4px")?;
+        style.set_property("align-content", "center")?;
+        Ok(group)
+    }
 
-        Ok(())
+    fn create_touch_button(
+        document: &Document,
+        key: &str,
+        label: &str,
+    ) -> Result<HtmlElement, JsValue> {
+        let button = document
+            .create_element("button")?
+            .dyn_into::<HtmlElement>()?;
+        button.set_attribute("data-key", key)?;
+        button.set_inner_text(label);
+
+        let style = button.style();
+        style.set_property("min-width", "var(--touch-size)")?;
+        style.set_property("height", "var(--touch-size)")?;
+        style.set_property("background", "rgba(0, 40, 0, 0.9)")?;
+        style.set_property("border", &format!("1px solid {}", BORDER_COLOR))?;
+        style.set_property("color", TEXT_COLOR)?;
+        style.set_property("font-family", "'Courier New', monospace")?;
+        style.set_property("font-size", "14px")?;
+        Ok(button)
     }
 
-    fn handle_key_input(&mut self, key: &str) -> Result<(), JsValue> {
-        // Prevent key repeat spam
-        let now = js_sys::Date::now();
-        if now - self.last_key_time < self.key_repeat_delay {
+    /// Shows the touch controls; does nothing once they are visible
+    fn show_touch_controls(&mut self) -> Result<(), JsValue> {
+        let style = self.touch_controls.style();
+        if style.get_property_value("display")? != "none" {
             return Ok(());
         }
-        self.last_key_time = now;
+        style.set_property("display", "flex")?;
+        // Swipes on the map move the player instead of scrolling the page
+        self.canvas.style().set_property("touch-action", "none")?;
+        self.layout_touch_controls()
+    }
 
-        match self.game.game_state.clone() {
-            GameState::Playing => self.handle_gameplay_input(key),
-            GameState::MainMenu => self.handle_menu_input(key),
-            GameState::Inventory => self.handle_inventory_input(key),
-            GameState::Character => self.handle_character_input(key),
-            GameState::Combat(pos) => self.handle_combat_input(key, pos),
-            _ => Ok(()),
+    /// Sizes the buttons to about four map cells as displayed, so they
+    /// shrink with the canvas but stay large enough to hit
+    fn layout_touch_controls(&self) -> Result<(), JsValue> {
+        let size = (self.view.cell_size() * 4.0 / self.canvas_scale()).clamp(40.0, 64.0);
+        self.touch_controls
+            .style()
+            .set_property("--touch-size", &format!("{size:.0}px"))
+    }
+
+    /// Drawing units per CSS pixel the canvas is shown at; one, unless the
+    /// browser has squeezed the canvas
+    fn canvas_scale(&self) -> f64 {
+        self.view.width() / self.canvas.client_width().max(1) as f64
+    }
+
+    /// Lays the page out for the window's shape and fits the canvas to the
+    /// space left for the map: its backing store matches the size shown
+    /// times the device pixel ratio, so glyphs stay sharp on any screen
+    fn fit_view(&mut self) -> Result<(), JsValue> {
+        let window = window().unwrap();
+        let width = window.inner_width()?.as_f64().unwrap_or(0.0);
+        let height = window.inner_height()?.as_f64().unwrap_or(0.0);
+        let orientation = if width > height {
+            "landscape"
+        } else {
+            "portrait"
+        };
+        if self.container.class_name() != orientation {
+            self.container.set_class_name(orientation);
         }
+
+        // Room is left for the canvas's border
+        let view = ViewLayout::fit(
+            f64::from(self.map_area.client_width() - 2),
+            f64::from(self.map_area.client_height() - 2),
+            window.device_pixel_ratio(),
+        );
+        if view != self.view {
+            self.view = view;
+            let cell_pixels = view.cell_pixels;
+            self.canvas.set_width(view.columns as u32 * cell_pixels);
+            self.canvas.set_height(view.rows as u32 * cell_pixels);
+            let style = self.canvas.style();
+            style.set_property("width", &format!("{}px", view.width()))?;
+            style.set_property("height", &format!("{}px", view.height()))?;
+            // Resizing resets the context, so drawing is scaled back to CSS
+            // pixels here
+            self.context
+                .set_transform(view.pixel_ratio, 0.0, 0.0, view.pixel_ratio, 0.0, 0.0)?;
+            self.tiles = TileBuffer::new(view.columns, view.rows);
+            self.glyph_sprites.clear();
+            self.redraw()?;
+        }
+        self.layout_touch_controls()
     }
 
-    fn handle_gameplay_input(&mut self, key: &str) -> Result<(), JsValue> {
-        match key {
-            "ArrowUp" => {
-                if self.game.move_player(0, -1) {
-                    self.process_movement()?;
-                }
-            }
-            "ArrowDown" => {
-                if self.game.move_player(0, 1) {
-                    self.process_movement()?;
-                }
-            }
-            "ArrowLeft" => {
-                if self.game.move_player(-1, 0) {
-                    self.process_movement()?;
-                }
-            }
-            "ArrowRight" => {
-                if self.game.move_player(1, 0) {
-                    self.process_movement()?;
-                }
-            }
-            "i" | "I" => {
-                self.game.game_state = GameState::Inventory;
-                self.render_game()?;
-            }
-            "c" | "C" => {
-                self.game.game_state = GameState::Character;
-                self.render_game()?;
-            }
-            "g" | "G" => {
-                if let Some(message) = self.game.try_get_item() {
-                    self.add_message(&message);
-                    self.render_game()?;
-                }
-            }
-            "q" | "Q" => {
-                self.add_message("Thanks for playing!");
-                // Could add exit confirmation here
+    /// Draws whatever was on the canvas again after it was resized
+    fn redraw(&mut self) -> Result<(), JsValue> {
+        if matches!(self.game.game_state, GameState::MainMenu) && self.help_scroll.is_none() {
+            if self.creation.is_some() {
+                return self.show_character_creation();
             }
-            _ => {}
+            return self.show_title_screen();
         }
-        Ok(())
+        self.render_game()
     }
 
-    fn handle_menu_input(&mut self, key: &str) -> Result<(), JsValue> {
-        match key {
-            "1" => {
-                self.start_new_game()?;
-            }
-            "2" => {
-                self.add_message("Load game not implemented yet.");
+    /// Ends a swipe on the map, moving one step in its main direction.
+    /// Short touches are left to the click handler as a look.
+    fn finish_swipe(&mut self, end_x: i32, end_y: i32) {
+        let Some((start_x, start_y)) = self.swipe_start.take() else {
+            return;
+        };
+
+        let scale = self.canvas_scale();
+        let dx = f64::from(end_x - start_x) * scale;
+        let dy = f64::from(end_y - start_y) * scale;
+        if dx.abs().max(dy.abs()) < SWIPE_CELLS * self.view.cell_size() {
+            return;
+        }
+
+        let key = if dx.abs() > dy.abs() {
+            if dx > 0.0 {
+                "ArrowRight"
+            } else {
+                "ArrowLeft"
+            }
+        } else if dy > 0.0 {
+            "ArrowDown"
+        } else {
+            "ArrowUp"
+        };
+        self.input.press(Intent::Key(key.to_string()));
+    }
+
+    /// Describes the tile under a click on the canvas, given in CSS pixels
+    fn look_at_pixel(&mut self, offset_x: i32, offset_y: i32) -> Result<(), JsValue> {
+        if !matches!(self.game.game_state, GameState::Playing)
+            || self.showing_journal
+            || self.showing_bestiary
+        {
+            return Ok(());
+        }
+
+        // The canvas may be squeezed by the browser, so convert to drawing
+        // units first, then to a cell of the view centered on the player
+        let scale = self.canvas_scale();
+        let cell = self.view.cell_size();
+        let x = (offset_x as f64 * scale / cell) as i32;
+        let y = (offset_y as f64 * scale / cell) as i32;
+        let level = self.game.current_level();
+        let origin = view_origin(level.player_position, self.view.columns, self.view.rows);
+
+        let description = level
+            .describe_position(Position::new(origin.x + x, origin.y + y))
+            .unwrap_or_else(|| "You can't see that.".to_string());
+        self.look_description = Some(description);
+        self.render_game()
+    }
+
+    /// One animation frame: fits the canvas to a resized window, acts on
+    /// the input recorded since the last frame, taking at most one step,
+    /// marks a few more rows of the map as explored and draws the game if
+    /// anything changed
+    fn tick(&mut self, now: f64) -> Result<(), JsValue> {
+        if std::mem::take(&mut self.layout_pending) {
+            self.fit_view()?;
+        }
+
+        // Nothing to do but wait while a new world is generated
+        if self.generating.is_some() {
+            self.input.intents.clear();
+            self.continue_generation()?;
+            return self.end_frame();
+        }
+
+        let mut stepped = false;
+        while let Some(intent) = self.input.intents.pop_front() {
+            match intent {
+                Intent::Key(key) if is_arrow(&key) && self.walking() => {
+                    if stepped || now - self.last_move_time < self.move_interval {
+                        // Too soon after the last step; try again next frame
+                        self.input.intents.push_front(Intent::Key(key));
+                        break;
+                    }
+                    self.step(&key, now)?;
+                    stepped = true;
+                }
+                Intent::Key(key) => self.handle_key_input(&key)?,
+                Intent::Inspect(index) => self.inspect_item(index)?,
+                Intent::Look(x, y) => self.look_at_pixel(x, y)?,
+            }
+        }
+
+        // A held arrow keeps walking
+        if !stepped && self.walking() && now - self.last_move_time >= self.move_interval {
+            if let Some(key) = self.input.held.last().cloned() {
+                self.step(&key, now)?;
+            }
+        }
+
+        self.continue_exploration();
+        // Flashes and the shake play out over the next few frames
+        if self.effects.expire(js_sys::Date::now()) {
+            self.needs_render = true;
+        }
+        if self.needs_render {
+            self.needs_render = false;
+            self.draw_game()?;
+        }
+        self.end_frame()
+    }
+
+    /// Whether the arrow keys walk the player rather than move through a
+    /// menu
+    fn walking(&self) -> bool {
+        matches!(self.game.game_state, GameState::Playing)
+            && self.help_scroll.is_none()
+            && self.creation.is_none()
+    }
+
+    fn step(&mut self, key: &str, now: f64) -> Result<(), JsValue> {
+        self.last_move_time = now;
+        self.look_description = None;
+        self.handle_gameplay_input(key)
+    }
+
+    /// Marks a few more rows around the player as explored, finishing the
+    /// previous pass at once if the player has already moved on
+    fn continue_exploration(&mut self) {
+        if let Some(pass) = self.game.take_exploration() {
+            if let Some(previous) = self.exploration.replace(pass) {
+                self.game.explore(previous, usize::MAX);
+            }
+        }
+        if let Some(pass) = self.exploration.take() {
+            self.exploration = self.game.explore(pass, EXPLORATION_ROWS_PER_FRAME);
+            // The title screen isn't drawn over the map
+            if !matches!(self.game.game_state, GameState::MainMenu) {
+                self.needs_render = true;
+            }
+        }
+    }
+
+    fn handle_key_input(&mut self, key: &str) -> Result<(), JsValue> {
+        // The debug overlay can be toggled from any screen
+        if key == "F3" {
+            return self.toggle_frame_stats();
+        }
+
+        if self.creation.is_some() {
+            return self.handle_creation_input(key);
+        }
+        self.look_description = None;
+
+        // The help overlay takes every key until it's closed
+        if self.help_scroll.is_some() {
+            return self.handle_help_input(key);
+        }
+
+        match self.game.game_state.clone() {
+            GameState::Playing => self.handle_gameplay_input(key),
+            GameState::MainMenu => self.handle_menu_input(key),
+            GameState::Inventory => self.handle_inventory_input(key),
+            GameState::Character => self.handle_character_input(key),
+            GameState::Combat(_) => self.handle_combat_input(key),
+            GameState::Victory => self.handle_victory_input(key),
+            GameState::Paused => self.handle_pause_input(key),
+            GameState::Dialogue(_) => self.handle_dialogue_input(key),
+            _ => Ok(()),
+        }
+    }
+
+    fn handle_gameplay_input(&mut self, key: &str) -> Result<(), JsValue> {
+        match key {
+            "ArrowUp" => self.play(Input::Move { dx: 0, dy: -1 })?,
+            "ArrowDown" => self.play(Input::Move { dx: 0, dy: 1 })?,
+            "ArrowLeft" => self.play(Input::Move { dx: -1, dy: 0 })?,
+            "ArrowRight" => self.play(Input::Move { dx: 1, dy: 0 })?,
+            ">" | "<" => self.play(Input::Stairs { down: key == ">" })?,
+            "i" | "I" => self.play(Input::OpenInventory)?,
+            "c" | "C" => self.play(Input::OpenCharacter)?,
+            "g" | "G" => self.play(Input::GetItem)?,
+            "e" | "E" => self.play(Input::Sense)?,
+            "M" => {
+                self.download_map()?;
+                self.render_game()?;
+            }
+            #[cfg(debug_assertions)]
+            "`" => {
+                self.game.log_level_budget();
+                self.game.log_reachability();
+                self.render_game()?;
+            }
+            "Ctrl+U" => self.play(Input::Unstick)?,
+            "j" | "J" => {
+                self.showing_journal = !self.showing_journal;
+                self.showing_bestiary = false;
+                self.render_game()?;
+            }
+            "b" | "B" => {
+                self.showing_bestiary = !self.showing_bestiary;
+                self.showing_journal = false;
+                self.showing_bestiary = false;
+                self.render_game()?;
+            }
+            "1" | "2" | "3" | "4" | "5" => {
+                let slot = key.parse::<usize>().unwrap_or(1) - 1;
+                self.play(Input::Hotbar(slot))?;
+            }
+            "Tab" => {
+                self.show_threats = !self.show_threats;
+                let state = if self.show_threats { "on" } else { "off" };
+                self.add_message(&format!("Threat overlay {state}"));
+                self.render_game()?;
+            }
+            "?" => self.show_help()?,
+            "Escape" => self.pause(false)?,
+            "q" | "Q" => self.pause(true)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Opens the help overlay at its first line
+    fn show_help(&mut self) -> Result<(), JsValue> {
+        self.help_scroll = Some(0);
+        self.render_game()
+    }
+
+    /// Scrolls the help overlay with the arrows and PageUp/PageDown; any
+    /// other key closes it
+    fn handle_help_input(&mut self, key: &str) -> Result<(), JsValue> {
+        let rows = self.help_rows();
+        let last = help::help_lines(self.settings.glyph_set())
+            .len()
+            .saturating_sub(rows);
+        let scroll = self.help_scroll.unwrap_or(0);
+        self.help_scroll = match key {
+            "ArrowUp" => Some(scroll.saturating_sub(1)),
+            "ArrowDown" => Some((scroll + 1).min(last)),
+            "PageUp" => Some(scroll.saturating_sub(rows)),
+            "PageDown" => Some((scroll + rows).min(last)),
+            _ => None,
+        };
+        if self.help_scroll.is_none() && matches!(self.game.game_state, GameState::MainMenu) {
+            return self.show_title_screen();
+        }
+        self.render_game()
+    }
+
+    /// Lines of help that fit on the canvas below its title and above its
+    /// hint
+    fn help_rows(&self) -> usize {
+        ((self.view.height() - 40.0) / HELP_LINE_HEIGHT).max(1.0) as usize
+    }
+
+    /// Covers the canvas with the help text, scrolled to `help_scroll`
+    fn render_help(&mut self) -> Result<(), JsValue> {
+        let lines = help::help_lines(self.settings.glyph_set());
+        let scroll = self.help_scroll.unwrap_or(0);
+        let rows = self.help_rows();
+        self.clear_canvas()?;
+
+        self.context
+            .set_fill_style(&wasm_bindgen::JsValue::from_str(TEXT_COLOR));
+        self.context.set_font("12px 'Courier New'");
+        self.context.fill_text("HELP", 10.0, 16.0)?;
+        self.context.set_font("10px 'Courier New'");
+        for (i, line) in lines.iter().skip(scroll).take(rows).enumerate() {
+            self.context
+                .fill_text(line, 10.0, 32.0 + HELP_LINE_HEIGHT * i as f64)?;
+        }
+        let shown = (scroll + rows).min(lines.len());
+        self.context.fill_text(
+            &format!(
+                "Lines {}-{shown} of {}. Arrows/PageUp/PageDown scroll, any other key closes",
+                scroll + 1,
+                lines.len()
+            ),
+            10.0,
+            self.view.height() - 6.0,
+        )?;
+        Ok(())
+    }
+
+    /// Opens the pause menu over the map, optionally straight at the quit
+    /// question
+    fn pause(&mut self, confirm_quit: bool) -> Result<(), JsValue> {
+        if self.game.pause() {
+            self.showing_journal = false;
+            self.showing_bestiary = false;
+            self.pause_selected = 0;
+            self.confirming_quit = confirm_quit;
+        }
+        self.render_game()
+    }
+
+    /// Moves through the pause menu; the browser version can't save, so
+    /// the menu has no Save entry
+    fn handle_pause_input(&mut self, key: &str) -> Result<(), JsValue> {
+        if self.options_selected.is_some() {
+            return self.handle_options_input(key);
+        }
+        if self.confirming_quit {
+            match key {
+                "y" | "Y" | "Enter" => {
+                    self.confirming_quit = false;
+                    return self.show_title_screen();
+                }
+                "n" | "N" | "Escape" => self.confirming_quit = false,
+                _ => {}
+            }
+            return self.render_game();
+        }
+
+        let options = PauseOption::menu(false);
+        let count = options.len();
+        let chosen = match key {
+            "ArrowUp" => {
+                self.pause_selected = (self.pause_selected + count - 1) % count;
+                None
+            }
+            "ArrowDown" => {
+                self.pause_selected = (self.pause_selected + 1) % count;
+                None
+            }
+            "Enter" => options.get(self.pause_selected).copied(),
+            "Escape" => Some(PauseOption::Resume),
+            _ => key
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| n.checked_sub(1))
+                .and_then(|i| options.get(i).copied()),
+        };
+
+        match chosen {
+            Some(PauseOption::Resume) => self.game.resume(),
+            Some(PauseOption::Options) => self.options_selected = Some(0),
+            Some(PauseOption::Help) => self.help_scroll = Some(0),
+            Some(PauseOption::ResetHints) => {
+                self.game.reset_hints();
+                self.game.resume();
+            }
+            Some(PauseOption::ExportMap) => {
+                self.game.resume();
+                self.download_map()?;
+            }
+            Some(PauseOption::QuitToMenu) => self.confirming_quit = true,
+            Some(PauseOption::Save) | None => {}
+        }
+        self.render_game()
+    }
+
+    /// Dims the map and draws the pause menu, or its quit question, on it
+    fn render_pause_menu(&mut self) -> Result<(), JsValue> {
+        // The dimmed map is drawn afresh on the next frame
+        self.tiles.invalidate();
+        self.context
+            .set_fill_style(&wasm_bindgen::JsValue::from_str("rgba(0, 0, 0, 0.75)"));
+        self.context
+            .fill_rect(0.0, 0.0, self.view.width(), self.view.height());
+
+        self.context
+            .set_fill_style(&wasm_bindgen::JsValue::from_str(TEXT_COLOR));
+        self.context.set_font("16px 'Courier New'");
+        self.context.fill_text("PAUSED", 210.0, 40.0)?;
+        self.context.set_font("10px 'Courier New'");
+        let hint = if self.confirming_quit {
+            "Y: quit   N: stay"
+        } else {
+            "Up/Down and Enter to choose, Escape to resume"
+        };
+        self.context.fill_text(hint, 110.0, 185.0)?;
+
+        self.context.set_font("12px 'Courier New'");
+        if self.confirming_quit {
+            self.context
+                .set_fill_style(&wasm_bindgen::JsValue::from_str("#FF5555"));
+            self.context
+                .fill_text(quit_question(self.game.permadeath), 20.0, 100.0)?;
+            return Ok(());
+        }
+
+        for (i, option) in PauseOption::menu(false).iter().enumerate() {
+            let (marker, color) = if i == self.pause_selected {
+                ("> ", "#FFFF00")
+            } else {
+                ("  ", TEXT_COLOR)
+            };
+            self.context
+                .set_fill_style(&wasm_bindgen::JsValue::from_str(color));
+            self.context.fill_text(
+                &format!("{marker}{}. {}", i + 1, option.label()),
+                170.0,
+                75.0 + 20.0 * i as f64,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Carries the character on into New Game+ or goes back to the title
+    fn handle_victory_input(&mut self, key: &str) -> Result<(), JsValue> {
+        match key {
+            "1" | "n" | "N" => {
+                self.game.start_new_game_plus();
+                self.exploration = None;
+                self.run_recorded = false;
+                self.run_placement = None;
+                self.showing_journal = false;
+                self.render_game()
+            }
+            "2" | "Escape" => self.show_title_screen(),
+            _ => Ok(()),
+        }
+    }
+
+    fn handle_menu_input(&mut self, key: &str) -> Result<(), JsValue> {
+        if self.options_selected.is_some() {
+            return self.handle_options_input(key);
+        }
+        match key {
+            "1" => {
+                self.daily = None;
+                self.creation = Some(CharacterCreation::EnteringName(String::new()));
+                self.show_character_creation()?;
+            }
+            "2" => {
+                // Each day's challenge can be taken once
+                let challenge = DailyChallenge::today();
+                match load_leaderboard().daily_result(&challenge.date) {
+                    Some(result) => self.add_message(&challenge.already_taken(result)),
+                    None => {
+                        self.daily = Some(challenge);
+                        self.creation = Some(CharacterCreation::EnteringName(String::new()));
+                        self.show_character_creation()?;
+                    }
+                }
             }
             "3" => {
-                self.show_instructions()?;
+                self.add_message("Load game not implemented yet.");
+            }
+            "4" | "?" => {
+                self.show_help()?;
+            }
+            "5" => {
+                self.show_hall_of_fame()?;
+            }
+            "6" => {
+                self.show_achievements()?;
             }
-            "4" | "q" | "Q" => {
+            "7" => {
+                self.options_selected = Some(0);
+                self.show_options()?;
+            }
+            "8" | "q" | "Q" => {
                 self.add_message("Thanks for playing!");
             }
+            "Escape" => {
+                self.show_title_screen()?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Moves through and changes the settings, storing every change at once
+    fn handle_options_input(&mut self, key: &str) -> Result<(), JsValue> {
+        let Some(selected) = self.options_selected else {
+            return Ok(());
+        };
+        let count = Setting::ALL.len();
+        match key {
+            "ArrowUp" => self.options_selected = Some((selected + count - 1) % count),
+            "ArrowDown" => self.options_selected = Some((selected + 1) % count),
+            "ArrowLeft" | "ArrowRight" => {
+                self.settings
+                    .adjust(Setting::ALL[selected], key == "ArrowRight");
+                self.game.apply_settings(&self.settings);
+                if !store_settings(&self.settings) {
+                    self.add_message(
+                        "Could not save the settings: browser storage is unavailable.",
+                    );
+                }
+            }
+            "Escape" | "Enter" => {
+                self.options_selected = None;
+                // Opened from the pause menu, the options go back to it
+                if matches!(self.game.game_state, GameState::Paused) {
+                    return self.render_game();
+                }
+                return self.show_title_screen();
+            }
             _ => {}
         }
+        self.show_options()
+    }
+
+    fn inspect_item(&mut self, index: usize) -> Result<(), JsValue> {
+        if index < InventoryManager::get_item_count(&self.game.player) {
+            self.inspected_item = Some(index);
+            self.render_game()?;
+        }
         Ok(())
     }
 
     fn handle_inventory_input(&mut self, key: &str) -> Result<(), JsValue> {
+        let dropping = std::mem::take(&mut self.pending_drop);
+        let hotbar_pick = self.pending_hotbar.take();
+        let enchant_pick = self.pending_enchant.take();
+        let crafting = std::mem::take(&mut self.pending_craft);
+        self.inspected_item = None;
+
         match key {
+            "y" | "Y" | "Enter" if matches!(enchant_pick, Some(EnchantPick::Confirm(_))) => {
+                if let Some(EnchantPick::Confirm(index)) = enchant_pick {
+                    let result = self.game.enchant_item(index);
+                    self.add_message(&result.message);
+                    self.render_game()?;
+                }
+            }
             "Escape" | "i" | "I" => {
                 self.game.game_state = GameState::Playing;
                 self.render_game()?;
             }
+            "d" | "D" => {
+                self.pending_drop = true;
+                self.add_message("Drop which item? Press 1-9.");
+            }
+            "n" | "N" if self.game.can_enchant() => {
+                self.pending_enchant = Some(EnchantPick::Item);
+                self.add_message("Enchant which item? Press 1-9.");
+            }
+            "a" | "A" => {
+                self.pending_hotbar = Some(HotbarPick::Item);
+                self.add_message("Put which item on the hotbar? Press 1-9.");
+            }
+            "r" | "R" => {
+                self.pending_craft = !crafting;
+                self.render_game()?;
+            }
             key if key.len() == 1 && key.chars().next().unwrap().is_ascii_digit() && key != "0" => {
                 if let Ok(index) = key.parse::<usize>() {
                     let index = index - 1; // Convert to 0-based
-                    if index < InventoryManager::get_item_count(&self.game.player) {
-                        let result = InventoryManager::use_item(&mut self.game.player, index);
+                    if crafting {
+                        if let Some(recipe) = RECIPES.get(index) {
+                            let result = InventoryManager::craft(&mut self.game.player, recipe);
+                            self.add_message(&result.message);
+                        }
+                        self.render_game()?;
+                    } else if enchant_pick == Some(EnchantPick::Item) {
+                        self.offer_enchant(index);
+                    } else if let Some(HotbarPick::Slot(item)) = hotbar_pick {
+                        let result =
+                            InventoryManager::assign_hotbar(&mut self.game.player, item, index);
+                        self.add_message(&result.message);
+                        self.render_game()?;
+                    } else if index < InventoryManager::get_item_count(&self.game.player)
+                        && hotbar_pick == Some(HotbarPick::Item)
+                    {
+                        self.pending_hotbar = Some(HotbarPick::Slot(index));
+                        self.add_message(&format!("Which hotbar slot? Press 1-{HOTBAR_SLOTS}."));
+                    } else if index < InventoryManager::get_item_count(&self.game.player) {
+                        let result = if dropping {
+                            self.game.drop_item(index)
+                        } else {
+                            self.game.use_item(index)
+                        };
                         self.add_message(&result.message);
                         if result.success {
                             self.render_game()?;
@@ -348,101 +1530,538 @@ impl WebGame {
         Ok(())
     }
 
+    /// Shows what enchanting the item at `index` takes, waiting on Y or
+    /// Enter if the player can afford it
+    fn offer_enchant(&mut self, index: usize) {
+        let Some(offer) = self.game.enchant_offer(index) else {
+            self.add_message("Only identified equipment can be enchanted.");
+            return;
+        };
+        for line in offer.lines() {
+            self.add_message(&line);
+        }
+        if offer.affordable() {
+            self.pending_enchant = Some(EnchantPick::Confirm(index));
+            self.add_message("Offer it up? Press Y or Enter.");
+        }
+    }
+
+    /// Y or Enter takes up the NPC's offer; Escape or N leaves the talk
+    fn handle_dialogue_input(&mut self, key: &str) -> Result<(), JsValue> {
+        match key {
+            "y" | "Y" | "Enter" => self.play(Input::Accept),
+            "Escape" | "n" | "N" => self.play(Input::Back),
+            _ => Ok(()),
+        }
+    }
+
     fn handle_character_input(&mut self, key: &str) -> Result<(), JsValue> {
         match key {
             "Escape" | "c" | "C" => {
                 self.game.game_state = GameState::Playing;
                 self.render_game()?;
             }
+            "1" | "2" | "3" | "4" | "5" => {
+                // Spend an unspent stat point
+                let index = key.parse::<usize>().unwrap_or(1) - 1;
+                if let Some(stat) = StatType::iter().nth(index) {
+                    if self.game.player.allocate_stat_point(stat).is_ok() {
+                        self.add_message(&format!("{stat} increased by 1"));
+                        self.render_game()?;
+                    }
+                }
+            }
             _ => {}
         }
         Ok(())
     }
 
-    fn handle_combat_input(&mut self, key: &str, pos: Position) -> Result<(), JsValue> {
+    fn handle_combat_input(&mut self, key: &str) -> Result<(), JsValue> {
+        if let Some(menu) = self.combat_menu {
+            return self.handle_combat_menu_input(menu, key);
+        }
+
         match key {
             "1" | " " => {
                 // Attack
-                self.execute_combat_action(CombatAction::Attack, pos)?;
+                self.execute_combat_action(CombatAction::Attack)?;
             }
             "2" => {
-                // Use ability (if implemented)
-                self.execute_combat_action(CombatAction::UseAbility(0), pos)?;
+                if self.game.player.available_abilities().is_empty() {
+                    self.add_message("No abilities available!");
+                } else {
+                    self.combat_menu = Some(CombatMenu::Abilities);
+                    self.render_game()?;
+                }
             }
             "3" => {
-                // Use item (if implemented)
-                self.execute_combat_action(CombatAction::UseItem(0), pos)?;
+                if InventoryManager::get_consumables(&self.game.player).is_empty() {
+                    self.add_message("No consumables available!");
+                } else {
+                    self.combat_menu = Some(CombatMenu::Items);
+                    self.render_game()?;
+                }
             }
             "4" | "f" | "F" => {
                 // Flee
-                self.execute_combat_action(CombatAction::Flee, pos)?;
+                self.execute_combat_action(CombatAction::Flee)?;
+            }
+            "t" | "T" | "Tab" => {
+                // Aim at the next enemy still standing
+                let enemies = self.game.combat_enemies();
+                if enemies.len() > 1 {
+                    self.game_loop.combat_target =
+                        combat::next_target(&enemies, self.game_loop.combat_target);
+                    self.render_game()?;
+                }
             }
             _ => {}
         }
         Ok(())
     }
 
-    fn process_movement(&mut self) -> Result<(), JsValue> {
-        match self.game.game_state {
-            GameState::Combat(_) => {
-                // Combat will be handled in the next update
-                self.render_game()?;
+    /// Picks an entry from the open ability or item list; Escape goes back
+    /// to the combat actions
+    fn handle_combat_menu_input(&mut self, menu: CombatMenu, key: &str) -> Result<(), JsValue> {
+        if key == "Escape" {
+            self.combat_menu = None;
+            return self.render_game();
+        }
+        let Some(index) = key
+            .parse::<usize>()
+            .ok()
+            .filter(|n| (1..=9).contains(n))
+            .map(|n| n - 1)
+        else {
+            return Ok(());
+        };
+
+        let action = match menu {
+            CombatMenu::Abilities => {
+                let Some(ability) = self.game.player.available_abilities().get(index).copied()
+                else {
+                    return Ok(());
+                };
+                if self.game.player.mana < Class::mana_cost(ability) {
+                    self.add_message(&format!("Not enough mana to use {ability}!"));
+                    return Ok(());
+                }
+                if self.game.player.cooldown_left(index) > 0 {
+                    self.add_message(&format!(
+                        "{ability} isn't ready yet {}",
+                        self.game.player.cooldown_label(index)
+                    ));
+                    return Ok(());
+                }
+                CombatAction::UseAbility(index)
             }
-            _ => {
-                self.game.process_turn();
-                self.render_game()?;
+            CombatMenu::Items => {
+                let consumables = InventoryManager::get_consumables(&self.game.player);
+                let Some((item_index, _)) = consumables.get(index) else {
+                    return Ok(());
+                };
+                CombatAction::UseItem(*item_index)
             }
+        };
+
+        self.combat_menu = None;
+        self.execute_combat_action(action)
+    }
+
+    /// HTML for the open combat list, with unaffordable or cooling down
+    /// abilities greyed out
+    fn combat_menu_html(&self, menu: CombatMenu) -> String {
+        let player = &self.game.player;
+        let (title, rows): (&str, Vec<String>) = match menu {
+            CombatMenu::Abilities => (
+                "ABILITIES",
+                player
+                    .available_abilities()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, ability)| {
+                        let cost = Class::mana_cost(ability);
+                        let color = if player.mana < cost || player.cooldown_left(i) > 0 {
+                            "#666666"
+                        } else {
+                            "inherit"
+                        };
+                        format!(
+                            "<div style='color: {color};'>{}. {ability} ({cost} mana) {}</div>",
+                            i + 1,
+                            player.cooldown_label(i)
+                        )
+                    })
+                    .collect(),
+            ),
+            CombatMenu::Items => (
+                "ITEMS",
+                InventoryManager::get_consumables(player)
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (_, consumable))| {
+                        format!(
+                            "<div>{}. {} ({})</div>",
+                            i + 1,
+                            consumable.name,
+                            consumable.effect_text()
+                        )
+                    })
+                    .collect(),
+            ),
+        };
+
+        format!(
+            "<div style='margin-top: 5px;'>{title}</div>{}<div>Number to choose, Escape to go back</div>",
+            rows.concat()
+        )
+    }
+
+    /// Hands an input to the state machine and shows what it did
+    fn play(&mut self, input: Input) -> Result<(), JsValue> {
+        let transition = self.game_loop.handle(&mut self.game, input);
+        if matches!(transition.entered, Some(GameState::Combat(_))) {
+            // Combat is fought from the side panel, starting next key press;
+            // the fight's opening line is already in the log
+            self.game_loop.combat_round(&mut self.game);
         }
+        // A bump into a wall has a message to show too
+        self.render_game()
+    }
+
+    /// Has the browser download the explored map of the current level
+    fn download_map(&mut self) -> Result<(), JsValue> {
+        let text = map_export::map_text(&self.game, &hall_of_fame::today());
+        download_text(&map_export::file_name(&self.game, "txt"), &text)?;
+        self.game
+            .log
+            .push(LogCategory::System, "Map exported to your downloads.");
         Ok(())
     }
 
-    fn execute_combat_action(
-        &mut self,
-        action: CombatAction,
-        _pos: Position,
-    ) -> Result<(), JsValue> {
-        // This would integrate with the actual combat system
-        // For now, just add a placeholder message
-        match action {
-            CombatAction::Attack => {
-                self.add_message("You attack the enemy!");
+    /// Plays one combat turn against the current target and ends the fight
+    /// if it is over
+    fn execute_combat_action(&mut self, action: CombatAction) -> Result<(), JsValue> {
+        let turn = self.game_loop.handle(&mut self.game, Input::Combat(action));
+        // The turn's messages go into the game log, shown by render_game, so
+        // there's nothing to wait for before the fight ends
+        if !turn.effects.is_empty() {
+            let shown = self.game_loop.handle(&mut self.game, Input::CombatShown);
+            if shown.entered.is_some() {
+                self.combat_menu = None;
             }
-            CombatAction::Flee => {
-                self.add_message("You attempt to flee!");
-                self.game.game_state = GameState::Playing;
+        }
+        self.render_game()
+    }
+
+    /// Name entry, class selection, then the optional point-buy; Escape
+    /// backs out one step, or keeps the class's own stats
+    fn handle_creation_input(&mut self, key: &str) -> Result<(), JsValue> {
+        let Some(step) = self.creation.take() else {
+            return Ok(());
+        };
+        self.creation = Some(match step {
+            CharacterCreation::EnteringName(name) if key == "Enter" && self.daily.is_some() => {
+                // The date picks the class, so the name is all it needs
+                let name = match name.trim() {
+                    "" => player::DEFAULT_NAME.to_string(),
+                    name => name.to_string(),
+                };
+                return self.start_daily_challenge(name);
+            }
+            CharacterCreation::EnteringName(mut name) => match key {
+                "Enter" => {
+                    let name = name.trim();
+                    CharacterCreation::SelectingClass(if name.is_empty() {
+                        player::DEFAULT_NAME.to_string()
+                    } else {
+                        name.to_string()
+                    })
+                }
+                "Escape" => {
+                    self.daily = None;
+                    return self.show_title_screen();
+                }
+                "Backspace" => {
+                    name.pop();
+                    CharacterCreation::EnteringName(name)
+                }
+                _ => {
+                    // Named keys such as "Shift" are longer than one character
+                    let mut chars = key.chars();
+                    if let (Some(c), None) = (chars.next(), chars.next()) {
+                        if name.chars().count() < player::MAX_NAME_LENGTH
+                            && player::is_name_character(c)
+                        {
+                            name.push(c);
+                        }
+                    }
+                    CharacterCreation::EnteringName(name)
+                }
+            },
+            CharacterCreation::SelectingClass(name) => {
+                let choice = key
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| n.checked_sub(1))
+                    .and_then(|i| ClassType::ALL.get(i));
+                match (key, choice) {
+                    (_, Some(&class_type)) => CharacterCreation::CustomizingStats {
+                        name,
+                        allocation: StatAllocation::new(class_type),
+                        selected: 0,
+                    },
+                    ("Escape" | "Backspace", None) => CharacterCreation::EnteringName(name),
+                    _ => CharacterCreation::SelectingClass(name),
+                }
+            }
+            CharacterCreation::CustomizingStats {
+                name,
+                mut allocation,
+                mut selected,
+            } => {
+                let stats = StatType::iter().count();
+                let stat = StatType::iter().nth(selected).unwrap_or(StatType::Strength);
+                match key {
+                    "ArrowUp" => selected = (selected + stats - 1) % stats,
+                    "ArrowDown" => selected = (selected + 1) % stats,
+                    "ArrowLeft" => {
+                        allocation.lower(stat);
+                    }
+                    "ArrowRight" => {
+                        allocation.raise(stat);
+                    }
+                    "Enter" if allocation.is_complete() => {
+                        if let Ok(player) = allocation.finish(name.clone()) {
+                            return self
+                                .generate_world(Game::start(player, &StartupOptions::default()));
+                        }
+                    }
+                    "Escape" => {
+                        let player = Player::new(name, allocation.class_type());
+                        return self
+                            .generate_world(Game::start(player, &StartupOptions::default()));
+                    }
+                    "Backspace" => {
+                        self.creation = Some(CharacterCreation::SelectingClass(name));
+                        return self.show_character_creation();
+                    }
+                    _ => {}
+                }
+                CharacterCreation::CustomizingStats {
+                    name,
+                    allocation,
+                    selected,
+                }
+            }
+        });
+        self.show_character_creation()
+    }
+
+    /// Shows the name being typed on the canvas and the current prompt in
+    /// the UI panel
+    fn show_character_creation(&mut self) -> Result<(), JsValue> {
+        let Some(step) = self.creation.clone() else {
+            return Ok(());
+        };
+        self.needs_render = false;
+        self.clear_canvas()?;
+        self.context
+            .set_fill_style(&wasm_bindgen::JsValue::from_str(TEXT_COLOR));
+        self.context.set_font("20px 'Courier New'");
+        self.context.fill_text("CHARACTER CREATION", 140.0, 80.0)?;
+        self.context.set_font("14px 'Courier New'");
+        let daily = match &self.daily {
+            Some(challenge) => format!(
+                "<div style='color: #FF00FF; margin-bottom: 10px;'>{}: you play a {}</div>",
+                challenge.label(),
+                challenge.class()
+            ),
+            None => String::new(),
+        };
+
+        let prompt = match &step {
+            CharacterCreation::EnteringName(name) => {
+                self.context
+                    .fill_text(&format!("Name: {name}_"), 140.0, 120.0)?;
+                format!(
+                    "<div>Enter your character's name:</div>
+                    <div style='color: #FFFF00; margin: 10px 0;'>{name}_</div>
+                    <div style='font-size: 10px;'>Letters, digits and spaces, up to {} characters.</div>
+                    <div style='margin-top: 20px; font-size: 10px;'>Enter to confirm (empty for \"{}\"), Escape to return</div>",
+                    player::MAX_NAME_LENGTH,
+                    player::DEFAULT_NAME
+                )
+            }
+            CharacterCreation::SelectingClass(name) => {
+                self.context
+                    .fill_text(&format!("Name: {name}"), 140.0, 120.0)?;
+                let classes: String = ClassType::ALL
+                    .iter()
+                    .enumerate()
+                    .map(|(i, class_type)| {
+                        format!(
+                            "<div style='margin-bottom: 6px;'>{}. {}<div style='font-size: 10px;'>{}</div></div>",
+                            i + 1,
+                            class_type,
+                            class_type.description()
+                        )
+                    })
+                    .collect();
+                format!(
+                    "<div>Choose a class for {name}:</div>
+                    <div style='margin: 10px 0;'>{classes}</div>
+                    <div style='margin-top: 20px; font-size: 10px;'>Press a number key to select, Escape to rename</div>"
+                )
             }
-            _ => {
-                self.add_message("Combat action not yet implemented.");
+            CharacterCreation::CustomizingStats {
+                name,
+                allocation,
+                selected,
+            } => {
+                self.context
+                    .fill_text(&format!("Name: {name}"), 140.0, 120.0)?;
+                let stats: String = StatType::iter()
+                    .enumerate()
+                    .map(|(i, stat)| {
+                        let color = if i == *selected {
+                            "#FFFF00"
+                        } else {
+                            TEXT_COLOR
+                        };
+                        format!(
+                            "<div style='color: {color};'>{}{stat}: {} ({}-{})</div>",
+                            if i == *selected { "&gt; " } else { "" },
+                            allocation.stats().get(stat),
+                            allocation.floor(stat),
+                            allocation.cap(stat)
+                        )
+                    })
+                    .collect();
+                let preview = allocation.preview();
+                let confirm = if allocation.is_complete() {
+                    "Enter to confirm"
+                } else {
+                    "spend every point, then Enter to confirm"
+                };
+                format!(
+                    "<div>Customize your {}'s stats - points left: {} of {}</div>
+                    <div style='margin: 10px 0;'>{stats}</div>
+                    <div style='color: #00FF00;'>HP {}  MP {}  Attack {}  Defense {}</div>
+                    <div style='margin-top: 20px; font-size: 10px;'>Up/Down: stat, Left/Right: adjust, {confirm}, Escape for the class's own stats, Backspace to change class</div>",
+                    allocation.class_type(),
+                    allocation.points_left(),
+                    crate::character::allocation::STAT_POINT_POOL,
+                    preview.max_health,
+                    preview.max_mana,
+                    preview.attack,
+                    preview.defense
+                )
             }
+        };
+
+        self.set_panel_html(&format!(
+            "<div style='color: {}; font-family: monospace;'>
+                <div style='font-size: 14px; margin-bottom: 10px; text-align: center;'>CHARACTER CREATION</div>
+                {daily}
+                {}
+            </div>",
+            TEXT_COLOR, prompt
+        ));
+        Ok(())
+    }
+
+    /// Starts today's challenge for the named character
+    fn start_daily_challenge(&mut self, name: String) -> Result<(), JsValue> {
+        match self.daily.take() {
+            Some(challenge) => self.generate_world(challenge.begin(name)),
+            None => self.show_title_screen(),
+        }
+    }
+
+    /// Picks up a game the page kept, where it was left
+    fn resume_game(&mut self, game: Game) -> Result<(), JsValue> {
+        self.game = game;
+        self.game.defer_exploration = true;
+        self.exploration = None;
+        self.game.apply_settings(&self.settings);
+        self.game.track_achievements(load_achievements());
+        if self.game.game_state == GameState::MainMenu {
+            self.game.game_state = GameState::Playing;
         }
+        self.creation = None;
+        self.combat_menu = None;
+        self.run_recorded = false;
+        self.run_placement = None;
+        self.tiles.invalidate();
+        self.add_message("Game loaded.");
         self.render_game()
     }
 
-    fn start_new_game(&mut self) -> Result<(), JsValue> {
-        // For now, start with a simple character creation
-        // In full implementation, this would show character creation screen
-        let player = Player::new("Hero".to_string(), ClassType::Warrior);
-        self.game = Game::new(player);
+    /// The level-ups, deaths and victories since the last frame, with the
+    /// callback the page wants them given to; None until it registers one
+    fn take_events(&mut self) -> Option<(js_sys::Function, Vec<GameEvent>)> {
+        let events = self.events.poll(&self.game);
+        let listener = self.event_listener.clone()?;
+        (!events.is_empty()).then_some((listener, events))
+    }
+
+    /// Generates a new game's world a level per frame, showing how far
+    /// along it is, then drops into the game
+    fn generate_world(&mut self, pending: PendingGame) -> Result<(), JsValue> {
+        self.creation = None;
+        self.show_generation_progress(pending.levels_built(), pending.levels_total())?;
+        self.generating = Some(pending);
+        Ok(())
+    }
+
+    /// Generates another level of the new game's world, starting the game
+    /// once the last is done
+    fn continue_generation(&mut self) -> Result<(), JsValue> {
+        let Some(mut pending) = self.generating.take() else {
+            return Ok(());
+        };
+        if pending.step() {
+            return self.begin_game(pending.finish());
+        }
+        self.show_generation_progress(pending.levels_built(), pending.levels_total())?;
+        self.generating = Some(pending);
+        Ok(())
+    }
+
+    /// "Generating world... 2/5 levels", over a blank map
+    fn show_generation_progress(&mut self, built: usize, total: usize) -> Result<(), JsValue> {
+        self.needs_render = false;
+        self.clear_canvas()?;
+        self.set_panel_html(&format!(
+            "<div style='text-align: center; margin-top: 50px; color: #FFFF00;'>
+                Generating world... {built}/{total} levels
+            </div>"
+        ));
+        Ok(())
+    }
+
+    /// Drops into a freshly created game
+    fn begin_game(&mut self, game: Game) -> Result<(), JsValue> {
+        self.game = game;
+        self.game.defer_exploration = true;
+        self.exploration = None;
+        self.game.apply_settings(&self.settings);
+        self.game.haunt(load_bones());
+        self.game.track_achievements(load_achievements());
         self.game.game_state = GameState::Playing;
+        self.run_recorded = false;
+        self.run_placement = None;
 
         self.add_message("Welcome to the dungeon! Use arrow keys to move.");
         self.add_message("Press 'i' for inventory, 'c' for character, 'g' to get items.");
+        self.add_message("Press '?' for help.");
         self.render_game()
     }
 
-    fn show_instructions(&mut self) -> Result<(), JsValue> {
-        self.add_message("=== GAME INSTRUCTIONS ===");
-        self.add_message("Arrow Keys: Move your character");
-        self.add_message("I: Open inventory");
-        self.add_message("C: View character stats");
-        self.add_message("G: Pick up items");
-        self.add_message("Q: Quit game");
-        self.add_message("In combat: 1=Attack, 4=Flee");
-        self.add_message("Press any key to continue...");
-        Ok(())
-    }
-
     fn show_title_screen(&mut self) -> Result<(), JsValue> {
+        // Drawn straight onto the canvas, so nothing else is drawn over it
+        self.needs_render = false;
         self.clear_canvas()?;
         self.game.game_state = GameState::MainMenu;
 
@@ -457,28 +2076,201 @@ impl WebGame {
             .fill_text("Web Dungeon Crawler", 220.0, 130.0)?;
 
         // Update UI panel with menu
-        self.ui_panel.set_inner_html(&format!(
+        self.set_panel_html(&format!(
             "<div style='text-align: center; margin-top: 50px;'>
                 <div style='font-size: 16px; margin-bottom: 20px;'>MAIN MENU</div>
                 <div>1. Start New Game</div>
-                <div>2. Load Game</div>
-                <div>3. Instructions</div>
-                <div>4. Exit</div>
+                <div>2. Daily Challenge</div>
+                <div>3. Load Game</div>
+                <div>4. Help</div>
+                <div>5. Hall of Fame</div>
+                <div>6. Achievements</div>
+                <div>7. Options</div>
+                <div>8. Exit</div>
                 <div style='margin-top: 30px; font-size: 10px;'>Press number key to select</div>
             </div>"
         ));
 
-        self.add_message("Welcome to Echoes RPG!");
-        self.add_message("Use number keys to navigate the menu.");
+        self.add_message("Welcome to Echoes RPG!");
+        self.add_message("Use number keys to navigate the menu.");
+
+        Ok(())
+    }
+
+    /// Lists the best recorded runs in the UI panel
+    fn show_hall_of_fame(&mut self) -> Result<(), JsValue> {
+        let leaderboard = load_leaderboard();
+        let rows = leaderboard.rows();
+        let mut list = if rows.is_empty() {
+            "No finished runs yet.".to_string()
+        } else {
+            rows.join("\n")
+        };
+        let daily_rows = leaderboard.daily_rows();
+        if !daily_rows.is_empty() {
+            list.push_str("\n\nDaily Challenges\n");
+            list.push_str(&daily_rows.join("\n"));
+        }
+
+        self.set_panel_html(&format!(
+            "<div style='color: {}; font-family: monospace;'>
+                <div style='font-size: 14px; margin-bottom: 10px; text-align: center;'>HALL OF FAME</div>
+                <pre style='font-size: 10px; white-space: pre-wrap;'>{}</pre>
+                <div style='margin-top: 10px; font-size: 10px;'>Press Escape to return to the menu</div>
+            </div>",
+            TEXT_COLOR, list
+        ));
+        Ok(())
+    }
+
+    /// Lists every achievement in the UI panel, unlocked ones with the day
+    /// they were
+    fn show_achievements(&mut self) -> Result<(), JsValue> {
+        let profile = load_achievements();
+        let rows: String = profile
+            .rows()
+            .into_iter()
+            .map(|(row, unlocked)| {
+                let color = if unlocked { "#FF9CFF" } else { "#666666" };
+                format!("<div style='color: {color};'>{row}</div>")
+            })
+            .collect();
+
+        self.set_panel_html(&format!(
+            "<div style='color: {}; font-family: monospace;'>
+                <div style='font-size: 14px; margin-bottom: 10px; text-align: center;'>ACHIEVEMENTS - {}</div>
+                <div style='font-size: 10px; white-space: pre-wrap;'>{}</div>
+                <div style='margin-top: 10px; font-size: 10px;'>Press Escape to return to the menu</div>
+            </div>",
+            TEXT_COLOR,
+            profile.summary(),
+            rows
+        ));
+        Ok(())
+    }
+
+    /// Writes the achievements to localStorage if the game unlocked any
+    /// since the last call
+    fn store_achievements(&mut self) {
+        let Some(profile) = self.game.take_unlocked_achievements() else {
+            return;
+        };
+        let stored = window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .is_some_and(|storage| {
+                storage
+                    .set_item(ACHIEVEMENTS_KEY, &profile.to_json())
+                    .is_ok()
+            });
+        if !stored {
+            self.add_message("Achievements not saved: browser storage is unavailable.");
+        }
+    }
+
+    /// Lists the settings in the UI panel, the selected one highlighted
+    fn show_options(&mut self) -> Result<(), JsValue> {
+        let selected = self.options_selected.unwrap_or(0);
+        let rows: String = self
+            .settings
+            .rows()
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let (marker, color) = if i == selected {
+                    ("&gt; ", "#FFFF00")
+                } else {
+                    ("&nbsp;&nbsp;", TEXT_COLOR)
+                };
+                format!("<div style='color: {color};'>{marker}{row}</div>")
+            })
+            .collect();
+
+        self.set_panel_html(&format!(
+            "<div style='color: {}; font-family: monospace;'>
+                <div style='font-size: 14px; margin-bottom: 10px; text-align: center;'>OPTIONS</div>
+                <div style='font-size: 10px; white-space: pre;'>{}</div>
+                <div style='margin-top: 10px; font-size: 10px;'>Up/Down to choose, Left/Right to change, Escape to return</div>
+            </div>",
+            TEXT_COLOR, rows
+        ));
+        Ok(())
+    }
+
+    /// Adds the finished game to the hall of fame in localStorage, once
+    fn record_run(&mut self) {
+        if self.run_recorded {
+            return;
+        }
+        self.run_recorded = true;
+        // Winning or dying can be what earns an achievement
+        self.game.check_achievements();
+        self.store_achievements();
+
+        // Bones that can't be stored just never turn up
+        if matches!(self.game.game_state, GameState::GameOver) {
+            let mut bones = load_bones();
+            bones.add(BonesRecord::from_game(&self.game));
+            if let Some(storage) = window().and_then(|window| window.local_storage().ok().flatten())
+            {
+                let _ = storage.set_item(BONES_KEY, &bones.to_json());
+            }
+        }
 
-        Ok(())
+        let mut leaderboard = load_leaderboard();
+        let placement = leaderboard.record_game(&self.game);
+        let stored = window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .is_some_and(|storage| {
+                storage
+                    .set_item(HALL_OF_FAME_KEY, &leaderboard.to_json())
+                    .is_ok()
+            });
+        if stored {
+            self.run_placement = Some(placement);
+        } else {
+            self.add_message("Could not record the run: browser storage is unavailable.");
+        }
     }
 
+    /// Asks for the game to be drawn at the end of this frame
     fn render_game(&mut self) -> Result<(), JsValue> {
-        self.clear_canvas()?;
-        self.update_visibility();
+        self.needs_render = true;
+        Ok(())
+    }
+
+    fn draw_game(&mut self) -> Result<(), JsValue> {
+        // Show anything the last action reported
+        self.show_new_log_entries();
+        // The browser version has no sound; drop the queued cues
+        self.game.take_sounds();
+        let now = js_sys::Date::now();
+        let effects = self.game.take_effects();
+        if self.settings.screen_effects {
+            self.effects.start(effects, now);
+        }
+        self.shake_canvas(now)?;
+        // Nor a crash log; bug reports go to the console instead
+        for report in self.game.take_bug_reports() {
+            let text = format!("{}\n{}", report.heading, report.lines.join("\n"));
+            console::log_1(&text.into());
+        }
+        self.store_achievements();
+
+        // Only a full redraw starts from a blank canvas; otherwise the map
+        // repaints just the tiles that changed
+        if self.tiles.is_stale() {
+            self.clear_canvas()?;
+        }
 
         match self.game.game_state {
+            GameState::Playing if self.showing_journal => {
+                self.render_map()?;
+                self.render_journal_panel()?;
+            }
+            GameState::Playing if self.showing_bestiary => {
+                self.render_map()?;
+                self.render_bestiary_panel()?;
+            }
             GameState::Playing | GameState::Combat(_) => {
                 self.render_map()?;
                 self.render_ui_panel()?;
@@ -491,201 +2283,385 @@ impl WebGame {
                 self.render_map()?;
                 self.render_character_panel()?;
             }
-            _ => {}
+            GameState::Dialogue(_) => {
+                self.render_map()?;
+                self.render_dialogue_panel()?;
+            }
+            GameState::GameOver | GameState::Victory => {
+                self.record_run();
+                self.render_map()?;
+                self.render_summary_panel()?;
+            }
+            GameState::Paused => {
+                self.render_map()?;
+                self.render_pause_menu()?;
+                if self.options_selected.is_some() {
+                    self.show_options()?;
+                } else {
+                    self.render_ui_panel()?;
+                }
+            }
+            _ => self.clear_canvas()?,
+        }
+
+        if self.help_scroll.is_some() {
+            self.render_help()?;
         }
 
         Ok(())
     }
 
     fn clear_canvas(&mut self) -> Result<(), JsValue> {
+        self.tiles.invalidate();
         self.context
             .set_fill_style(&wasm_bindgen::JsValue::from_str(BACKGROUND_COLOR));
-        self.context.fill_rect(
-            0.0,
-            0.0,
-            (MAP_WIDTH * CELL_SIZE) as f64,
-            (MAP_HEIGHT * CELL_SIZE) as f64,
-        );
+        self.context
+            .fill_rect(0.0, 0.0, self.view.width(), self.view.height());
         Ok(())
     }
 
-    fn update_visibility(&mut self) {
-        self.game.update_visibility();
-    }
-
     fn render_map(&mut self) -> Result<(), JsValue> {
-        // Extract all needed data first to avoid borrowing issues
-        let level_width = self.game.current_level().width as i32;
-        let level_height = self.game.current_level().height as i32;
-        let player_pos = self.game.player_position();
+        // The camera follows the player, however many cells the canvas
+        // has room for
+        let mut frame = MapFrame::centered(
+            self.game.current_level(),
+            self.view.columns,
+            self.view.rows,
+            self.settings.glyph_set(),
+        );
+        if self.show_threats {
+            frame.show_threats(self.game.current_level());
+        }
 
-        // Collect tile data
-        let mut tile_data = Vec::new();
-        let mut enemy_positions = Vec::new();
-        let mut item_positions = Vec::new();
+        let scheme = self.settings.color_scheme();
+        let changes = self
+            .tiles
+            .update(frame.cells.into_iter().map(|cell| MapCell {
+                glyph: Glyph {
+                    color: scheme.apply(cell.glyph.color),
+                    background: cell.glyph.background.map(|bg| scheme.apply(bg)),
+                    ..cell.glyph
+                },
+                ..cell
+            }));
+        for change in &changes {
+            self.render_tile(change)?;
+        }
+        self.frame_stats.add_tiles(changes.len());
 
-        {
-            let level = self.game.current_level();
-            for y in 0..MAP_HEIGHT {
-                for x in 0..MAP_WIDTH {
-                    if x < level_width && y < level_height {
-                        let tile = &level.tiles[y as usize][x as usize];
-                        tile_data.push((x, y, tile.visible, tile.explored, tile.tile_type.clone()));
-                    }
-                }
-            }
+        Ok(())
+    }
 
-            // Collect entity positions
-            for (pos, _enemy) in &level.enemies {
-                enemy_positions.push((pos.x, pos.y));
+    /// Redraws one map cell by copying its glyph's sprite over it, or
+    /// blanks it
+    fn render_tile(&mut self, change: &TileChange) -> Result<(), JsValue> {
+        let cell = self.view.cell_size();
+        let x = change.x as f64 * cell;
+        let y = change.y as f64 * cell;
+        match change.glyph {
+            Some(glyph) => {
+                let sprite = self.glyph_sprite(glyph)?;
+                self.context
+                    .draw_image_with_html_canvas_element_and_dw_and_dh(&sprite, x, y, cell, cell)
             }
-            for (pos, _item) in &level.items {
-                item_positions.push((pos.x, pos.y));
+            None => {
+                self.context
+                    .set_fill_style(&wasm_bindgen::JsValue::from_str(BACKGROUND_COLOR));
+                self.context.fill_rect(x, y, cell, cell);
+                Ok(())
             }
         }
+    }
 
-        // Now render everything
-        for (x, y, visible, explored, tile_type) in tile_data {
-            if visible {
-                self.render_tile(x, y, &tile_type)?;
-
-                // Render entities at this position
-                if player_pos.x == x && player_pos.y == y {
-                    self.render_player(x, y)?;
-                } else if enemy_positions.contains(&(x, y)) {
-                    self.render_enemy(x, y)?;
-                } else if item_positions.contains(&(x, y)) {
-                    self.render_item(x, y)?;
-                }
-            } else if explored {
-                self.render_fog_tile(x, y)?;
-            }
+    /// The cell-sized canvas `glyph` is drawn on at full device resolution,
+    /// made the first time the glyph is needed at the current cell size
+    fn glyph_sprite(&mut self, glyph: Glyph) -> Result<HtmlCanvasElement, JsValue> {
+        if let Some(sprite) = self.glyph_sprites.get(&glyph) {
+            return Ok(sprite.clone());
         }
 
-        Ok(())
+        let document = window().unwrap().document().unwrap();
+        let sprite = document
+            .create_element("canvas")?
+            .dyn_into::<HtmlCanvasElement>()?;
+        let size = self.view.cell_pixels;
+        sprite.set_width(size);
+        sprite.set_height(size);
+
+        let size = f64::from(size);
+        let context = Self::get_canvas_context(&sprite)?;
+        let background = glyph
+            .background
+            .map_or_else(|| BACKGROUND_COLOR.to_string(), css_color);
+        context.set_fill_style(&wasm_bindgen::JsValue::from_str(&background));
+        context.fill_rect(0.0, 0.0, size, size);
+        context.set_fill_style(&wasm_bindgen::JsValue::from_str(&css_color(glyph.color)));
+        context.set_font(&format!("{size}px monospace"));
+        context.fill_text(&glyph.symbol.to_string(), size * 0.1, size * 0.9)?;
+
+        self.glyph_sprites.insert(glyph, sprite.clone());
+        Ok(sprite)
     }
 
-    fn render_tile(&mut self, x: i32, y: i32, tile_type: &TileType) -> Result<(), JsValue> {
-        let color = match tile_type {
-            TileType::Wall => WALL_COLOR,
-            TileType::Floor => FLOOR_COLOR,
-            TileType::Door => DOOR_COLOR,
-            TileType::Chest => CHEST_COLOR,
-            TileType::Exit => EXIT_COLOR,
-            TileType::StairsDown => EXIT_COLOR,
-            TileType::StairsUp => EXIT_COLOR,
-        };
-
-        self.context
-            .set_fill_style(&wasm_bindgen::JsValue::from_str(color));
-        self.context.fill_rect(
-            (x * CELL_SIZE) as f64,
-            (y * CELL_SIZE) as f64,
-            CELL_SIZE as f64,
-            CELL_SIZE as f64,
-        );
-
+    /// Pushes the canvas a pixel or two from its place while the player
+    /// being hurt shakes it, and back after
+    fn shake_canvas(&mut self, now: f64) -> Result<(), JsValue> {
+        let shake = self.effects.shake_offset(now);
+        if shake != self.shake {
+            self.shake = shake;
+            self.canvas.style().set_property(
+                "transform",
+                &format!(
+                    "translate(calc(-50% + {}px), calc(-50% + {}px))",
+                    shake.0, shake.1
+                ),
+            )?;
+        }
         Ok(())
     }
 
-    fn render_fog_tile(&mut self, x: i32, y: i32) -> Result<(), JsValue> {
-        self.context
-            .set_fill_style(&wasm_bindgen::JsValue::from_str(FOG_COLOR));
-        self.context.fill_rect(
-            (x * CELL_SIZE) as f64,
-            (y * CELL_SIZE) as f64,
-            CELL_SIZE as f64,
-            CELL_SIZE as f64,
-        );
-        Ok(())
+    /// Background for a line of the UI panel while `effect` flashes on it
+    fn flash_style(&self, effect: ScreenEffect) -> String {
+        if self.effects.is_showing(effect, js_sys::Date::now()) {
+            let color = self.settings.color_scheme().apply(effect.color());
+            format!("background: {};", css_color(color))
+        } else {
+            String::new()
+        }
     }
 
-    fn render_player(&mut self, x: i32, y: i32) -> Result<(), JsValue> {
-        self.context
-            .set_fill_style(&wasm_bindgen::JsValue::from_str(PLAYER_COLOR));
-        self.context.fill_rect(
-            (x * CELL_SIZE) as f64,
-            (y * CELL_SIZE) as f64,
-            CELL_SIZE as f64,
-            CELL_SIZE as f64,
-        );
-
-        // Add @ symbol for player
-        self.context
-            .set_fill_style(&wasm_bindgen::JsValue::from_str("#000000"));
-        self.context
-            .set_font(&format!("{}px monospace", CELL_SIZE - 2));
-        self.context.fill_text(
-            "@",
-            (x * CELL_SIZE + 2) as f64,
-            (y * CELL_SIZE + CELL_SIZE - 2) as f64,
-        )?;
+    /// Replaces the UI panel's contents, unless they're already `html`
+    fn set_panel_html(&mut self, html: &str) {
+        if self.panel_html != html {
+            self.ui_panel.set_inner_html(html);
+            self.panel_html = html.to_string();
+        }
+    }
 
+    /// Counts the frame just drawn and refreshes the debug overlay once a
+    /// second
+    fn end_frame(&mut self) -> Result<(), JsValue> {
+        if self.frame_stats.end_frame(js_sys::Date::now()) {
+            self.show_frame_stats();
+        }
         Ok(())
     }
 
-    fn render_enemy(&mut self, x: i32, y: i32) -> Result<(), JsValue> {
-        self.context
-            .set_fill_style(&wasm_bindgen::JsValue::from_str(ENEMY_COLOR));
-        self.context.fill_rect(
-            (x * CELL_SIZE) as f64,
-            (y * CELL_SIZE) as f64,
-            CELL_SIZE as f64,
-            CELL_SIZE as f64,
-        );
+    /// Shows or hides the debug overlay with frames drawn and tiles
+    /// redrawn per second
+    fn toggle_frame_stats(&mut self) -> Result<(), JsValue> {
+        let style = self.stats_overlay.style();
+        let shown = style.get_property_value("display")? != "none";
+        style.set_property("display", if shown { "none" } else { "block" })?;
+        self.show_frame_stats();
         Ok(())
     }
 
-    fn render_item(&mut self, x: i32, y: i32) -> Result<(), JsValue> {
-        self.context
-            .set_fill_style(&wasm_bindgen::JsValue::from_str(ITEM_COLOR));
-        self.context.fill_rect(
-            (x * CELL_SIZE) as f64,
-            (y * CELL_SIZE) as f64,
-            CELL_SIZE as f64,
-            CELL_SIZE as f64,
-        );
-        Ok(())
+    fn show_frame_stats(&self) {
+        self.stats_overlay.set_inner_html(&format!(
+            "{} frames/s<br>{} tiles/s<br>{} glyphs cached",
+            self.frame_stats.frames_per_second,
+            self.frame_stats.tiles_per_second,
+            self.glyph_sprites.len()
+        ));
     }
 
     fn render_ui_panel(&mut self) -> Result<(), JsValue> {
-        let player = &self.game.player;
-        let dungeon = self.game.current_dungeon();
+        let panel = StatPanel::new(&self.game.player, self.game.current_dungeon());
+        let level_up = self.flash_style(ScreenEffect::LevelUp);
+        let hurt = self.flash_style(ScreenEffect::Hurt);
+        let stat_rows: String = panel
+            .rows
+            .iter()
+            .map(|row| format!("<div style='{level_up}'>{row}</div>"))
+            .chain(panel.bars.iter().enumerate().map(|(i, stat)| {
+                let color = css_color(self.settings.color_scheme().apply(stat.color));
+                let flash = if i == 0 && !hurt.is_empty() {
+                    &hurt
+                } else {
+                    &level_up
+                };
+                format!(
+                    "<div style='{flash}'>{}</div>",
+                    bar_html(stat.label, &stat.bar, &color)
+                )
+            }))
+            .chain(
+                panel
+                    .light
+                    .iter()
+                    .map(|light| format!("<div style='color: #FFD700;'>{light}</div>")),
+            )
+            .chain(
+                panel
+                    .companion
+                    .iter()
+                    .map(|companion| format!("<div style='color: #00C800;'>{companion}</div>")),
+            )
+            .chain(
+                panel
+                    .ambient
+                    .iter()
+                    .map(|ambient| format!("<div style='color: #87CEEB;'>{ambient}</div>")),
+            )
+            .collect();
+
+        // Show who the player is fighting while in combat
+        let combat_info = match self.game.game_state {
+            GameState::Combat(_) => {
+                let enemies = self.game.combat_enemies();
+                if enemies.is_empty() {
+                    String::new()
+                } else {
+                    let view = CombatView::new(
+                        &self.game.player,
+                        &enemies,
+                        self.game_loop.combat_target,
+                        &self.game.combat_context(),
+                    );
+                    let roster: String = view
+                        .enemies
+                        .iter()
+                        .map(|line| format!("<div style='white-space: pre;'>{line}</div>"))
+                        .collect();
+                    let actions: String = match self.combat_menu {
+                        Some(menu) => self.combat_menu_html(menu),
+                        None => view
+                            .actions
+                            .iter()
+                            .map(|action| format!("<div>{action}</div>"))
+                            .collect(),
+                    };
+                    let buffs: String = view
+                        .buffs
+                        .iter()
+                        .map(|line| format!("<div style='color: #78DC78;'>{line}</div>"))
+                        .collect();
+                    // What attacking is likely to do, before the player commits
+                    let forecast: String = view
+                        .forecast
+                        .iter()
+                        .map(|line| format!("<div style='color: #DCC878;'>{line}</div>"))
+                        .collect();
+                    // Why the numbers are what they are this turn
+                    let modifiers: String = view
+                        .modifiers
+                        .iter()
+                        .map(|line| format!("<div style='color: #DC8CFF;'>{line}</div>"))
+                        .collect();
+                    let enemy_bar = format!(
+                        "<div style='{}'>{}</div>",
+                        self.flash_style(ScreenEffect::CriticalHit),
+                        bar_html(
+                            "HP",
+                            &view.enemy_bar,
+                            &css_color(
+                                self.settings
+                                    .color_scheme()
+                                    .apply(view.enemy_bar.level().color()),
+                            ),
+                        )
+                    );
+                    format!(
+                        "<div style='margin-top: 15px; color: #FF6666;'>
+                            <div style='font-size: 12px; margin-bottom: 5px;'>COMBAT</div>
+                            <div>{}</div>
+                            {}
+                            {}
+                            {}
+                            {}
+                            <div style='margin: 5px 0;'>{}</div>
+                            {}
+                        </div>",
+                        view.enemy, enemy_bar, roster, buffs, forecast, modifiers, actions
+                    )
+                }
+            }
+            _ => String::new(),
+        };
+
+        let legend_rows: String = legend(self.settings.glyph_set())
+            .iter()
+            .map(|entry| {
+                format!(
+                    "<div><span style='color: {};'>{}</span> - {}</div>",
+                    css_color(self.settings.color_scheme().apply(entry.glyph.color)),
+                    entry.glyph.symbol,
+                    entry.label
+                )
+            })
+            .collect();
+
+        // What G would do right now, while exploring
+        let prompt = match self.game.game_state {
+            GameState::Playing => self
+                .game
+                .current_interaction()
+                .map(|interaction| {
+                    format!(
+                        "<div style='margin-top: 10px; color: #FFFF00;'>{}</div>",
+                        interaction.prompt()
+                    )
+                })
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+
+        let look_info = self
+            .look_description
+            .as_ref()
+            .map(|description| {
+                format!(
+                    "<div style='margin-top: 15px; color: #FFD700;'>
+                        <div style='font-size: 12px; margin-bottom: 5px;'>LOOK</div>
+                        <div>{description}</div>
+                    </div>"
+                )
+            })
+            .unwrap_or_default();
 
         let ui_content = format!(
             "<div style='color: {}; font-family: monospace;'>
                 <div style='font-size: 14px; margin-bottom: 10px; text-align: center;'>HERO STATUS</div>
-                <div>Name: {}</div>
-                <div>Level: {}</div>
-                <div>Health: {}/{}</div>
-                <div>Experience: {}</div>
-                <div>Gold: {}</div>
+                <div>{}</div>
+                {}
                 <div style='margin-top: 15px;'>
-                    <div style='font-size: 12px; margin-bottom: 5px;'>DUNGEON INFO</div>
-                    <div>Floor: {}</div>
-                    <div>Type: {:?}</div>
+                    <div style='font-size: 12px; margin-bottom: 5px;'>LOCATION</div>
+                    <div>{}</div>
                 </div>
+                {}
+                {}
+                {}
+                {}
                 <div style='margin-top: 15px;'>
                     <div style='font-size: 12px; margin-bottom: 5px;'>CONTROLS</div>
                     <div>↑↓←→ Move</div>
+                    <div>1-5 - Hotbar</div>
                     <div>I - Inventory</div>
                     <div>C - Character</div>
-                    <div>G - Get Item</div>
-                    <div>Q - Quit</div>
+                    <div>G - Interact</div>
+                    <div>J - Journal</div>
+                    <div>B - Bestiary</div>
+                    <div>Click - Look at tile</div>
+                    <div>Esc/Q - Pause menu</div>
+                </div>
+                <div style='margin-top: 15px;'>
+                    <div style='font-size: 12px; margin-bottom: 5px;'>LEGEND</div>
+                    {}
                 </div>
             </div>",
             TEXT_COLOR,
-            player.name,
-            player.level,
-            player.health,
-            player.max_health,
-            player.experience,
-            player.gold,
-            self.game.current_dungeon_index + 1,
-            dungeon.dungeon_type
+            panel.name,
+            stat_rows,
+            panel.location,
+            hotbar_html(&self.game.player),
+            prompt,
+            combat_info,
+            look_info,
+            legend_rows
         );
 
-        self.ui_panel.set_inner_html(&ui_content);
+        self.set_panel_html(&ui_content);
         Ok(())
     }
 
@@ -695,45 +2671,161 @@ impl WebGame {
 
         let mut content = format!(
             "<div style='color: {}; font-family: monospace;'>
-                <div style='font-size: 14px; margin-bottom: 10px; text-align: center;'>INVENTORY</div>",
-            TEXT_COLOR
+                <div style='font-size: 14px; margin-bottom: 10px; text-align: center;'>INVENTORY</div>
+                <div style='margin-bottom: 5px;'>{}</div>",
+            TEXT_COLOR,
+            InventoryManager::weight_text(player)
         );
 
         if item_count == 0 {
             content.push_str("<div>Your inventory is empty.</div>");
         } else {
-            for i in 0..item_count {
-                if let Some(item) = InventoryManager::get_item(player, i) {
-                    content.push_str(&format!("<div>{}. {}</div>", i + 1, item.name()));
+            for (i, info) in InventoryManager::get_items(player).iter().enumerate() {
+                match info.rarity {
+                    Some(rarity) => content.push_str(&format!(
+                        "<div>{}. <span style='color: {};' title='{}'>{}</span> &lt;{}&gt;{}{}</div>",
+                        i + 1,
+                        rarity_css_color(rarity),
+                        rarity,
+                        info.name,
+                        rarity,
+                        info.durability_tag(),
+                        info.weight_tag()
+                    )),
+                    None => content.push_str(&format!(
+                        "<div>{}. {}{}</div>",
+                        i + 1,
+                        info.name,
+                        info.weight_tag()
+                    )),
                 }
             }
         }
 
+        if let Some(details) = self
+            .inspected_item
+            .and_then(|index| InventoryManager::get_item_details(player, index))
+        {
+            let name_color = details
+                .rarity
+                .map(rarity_css_color)
+                .unwrap_or_else(|| "#FFD700".to_string());
+            content.push_str(&format!(
+                "<div style='margin-top: 10px; border-top: 1px solid {}; padding-top: 5px;'>
+                    <div style='color: {};'>{}</div>
+                    <div style='color: #AAAAAA; margin-bottom: 5px;'>{}</div>",
+                BORDER_COLOR, name_color, details.name, details.description
+            ));
+            for line in details.lines() {
+                content.push_str(&format!("<div>{line}</div>"));
+            }
+            content.push_str("</div>");
+        }
+
+        if self.pending_craft {
+            content.push_str(&format!(
+                "<div style='margin-top: 10px; border-top: 1px solid {BORDER_COLOR}; padding-top: 5px;'>
+                    <div style='color: #FFD700;'>CRAFTING</div>"
+            ));
+            for (i, recipe) in RECIPES.iter().enumerate() {
+                let color = if InventoryManager::can_craft(player, recipe) {
+                    "#00FF00"
+                } else {
+                    "#808080"
+                };
+                content.push_str(&format!(
+                    "<div style='color: {color};'>{}. {}</div>",
+                    i + 1,
+                    recipe.label()
+                ));
+            }
+            content.push_str(&format!(
+                "<div>Press 1-{} to craft, R to put the recipes away</div></div>",
+                RECIPES.len()
+            ));
+        }
+
+        content.push_str(&hotbar_html(player));
         content.push_str(
             "
             <div style='margin-top: 15px;'>
                 <div>Press 1-9 to use item</div>
+                <div>Press D then 1-9 to drop item</div>
+                <div>Press A, the item, then 1-5 to put it on the hotbar</div>
+                <div>Press Shift+1-9 to inspect item</div>
+                <div>Press R to craft from materials</div>",
+        );
+        if self.game.can_enchant() {
+            content.push_str("<div>Press N then 1-9 to enchant gear at this shrine</div>");
+        }
+        content.push_str(
+            "
                 <div>Press I or ESC to close</div>
             </div>
         </div>",
         );
 
-        self.ui_panel.set_inner_html(&content);
+        self.set_panel_html(&content);
         Ok(())
     }
 
     fn render_character_panel(&mut self) -> Result<(), JsValue> {
         let player = &self.game.player;
 
+        let stat_points = if player.unspent_stat_points > 0 {
+            format!(
+                "<div style='margin-top: 10px; color: #FFD700;'>
+                    <div>Unspent stat points: {}</div>
+                    <div>Press 1-5 to raise Str/Int/Dex/Con/Wis</div>
+                </div>",
+                player.unspent_stat_points
+            )
+        } else {
+            String::new()
+        };
+
+        let companion = match &player.companion {
+            Some(companion) => format!(
+                "<div style='margin-top: 10px;'>
+                    <div style='font-size: 12px; margin-bottom: 5px;'>COMPANION</div>
+                    <div>{}</div>
+                    <div>{}, Attack: {}</div>
+                </div>",
+                companion.title(),
+                companion.status_text(),
+                companion.attack
+            ),
+            None => String::new(),
+        };
+
+        let mut ng_plus = if self.game.ng_plus_counter > 0 {
+            format!(
+                "<div style='color: #FF00FF;'>New Game+ {}</div>",
+                self.game.ng_plus_counter
+            )
+        } else {
+            String::new()
+        };
+        if let Some(label) = self.game.run_label() {
+            ng_plus.push_str(&format!("<div style='color: #FFFF00;'>{label}</div>"));
+        }
+        if let Some(note) = self.game.difficulty_note() {
+            ng_plus.push_str(&format!("<div style='color: #808080;'>{note}</div>"));
+        }
+
+        let (trait_name, trait_text) = player.class.class_type.passive();
         let content = format!(
             "<div style='color: {}; font-family: monospace;'>
                 <div style='font-size: 14px; margin-bottom: 10px; text-align: center;'>CHARACTER</div>
                 <div>Name: {}</div>
                 <div>Class: {:?}</div>
+                <div>Trait: {} - {}</div>
                 <div>Level: {}</div>
                 <div>Health: {}/{}</div>
-                <div>Experience: {}</div>
+                <div>Experience: {}/{}</div>
                 <div>Gold: {}</div>
+                <div>Turns: {}</div>
+                {}
                 <div style='margin-top: 10px;'>
                     <div style='font-size: 12px; margin-bottom: 5px;'>STATS</div>
                     <div>Strength: {}</div>
@@ -741,7 +2833,14 @@ impl WebGame {
                     <div>Dexterity: {}</div>
                     <div>Constitution: {}</div>
                     <div>Wisdom: {}</div>
+                    <div>Carry capacity: {}</div>
+                </div>
+                <div style='margin-top: 10px;'>
+                    <div style='font-size: 12px; margin-bottom: 5px;'>ABILITIES</div>
+                    <div>{}</div>
                 </div>
+                {}
+                {}
                 <div style='margin-top: 15px;'>
                     <div>Press C or ESC to close</div>
                 </div>
@@ -749,30 +2848,212 @@ impl WebGame {
             TEXT_COLOR,
             player.name,
             player.class.class_type,
+            trait_name,
+            trait_text,
             player.level,
             player.health,
             player.max_health,
             player.experience,
+            player.xp_for_next_level(),
             player.gold,
+            self.game.stats.turns,
+            ng_plus,
             player.stats.strength,
             player.stats.intelligence,
             player.stats.dexterity,
             player.stats.constitution,
-            player.stats.wisdom
+            player.stats.wisdom,
+            player.carry_capacity(),
+            player.available_abilities().join(", "),
+            companion,
+            stat_points
         );
 
-        self.ui_panel.set_inner_html(&content);
+        self.set_panel_html(&content);
         Ok(())
     }
 
-    fn add_message(&mut self, message: &str) {
-        let current_content = self.message_area.inner_html();
-        let new_content = if current_content.is_empty() {
-            message.to_string()
-        } else {
-            format!("{}<br>{}", current_content, message)
+    /// Shows the talk with an NPC in the side panel
+    fn render_dialogue_panel(&mut self) -> Result<(), JsValue> {
+        let Some(dialogue) = self.game.dialogue() else {
+            return Ok(());
+        };
+        let mut content = format!(
+            "<div style='color: {}; font-family: monospace;'>
+                <div style='font-size: 14px; margin-bottom: 10px; text-align: center;'>{}</div>",
+            TEXT_COLOR,
+            dialogue.speaker.to_uppercase()
+        );
+        for line in &dialogue.lines {
+            content.push_str(&format!("<div style='margin-bottom: 5px;'>{line}</div>"));
+        }
+        content.push_str("<div style='margin-top: 15px;'>");
+        if let Some(offer) = &dialogue.offer {
+            content.push_str(&format!(
+                "<div style='color: #FFFF00;'>Y or Enter: {offer}</div>"
+            ));
+        }
+        content.push_str("<div>N or ESC: leave</div></div></div>");
+
+        self.set_panel_html(&content);
+        Ok(())
+    }
+
+    fn render_journal_panel(&mut self) -> Result<(), JsValue> {
+        let mut content = format!(
+            "<div style='color: {}; font-family: monospace;'>
+                <div style='font-size: 14px; margin-bottom: 10px; text-align: center;'>QUEST JOURNAL</div>",
+            TEXT_COLOR
+        );
+
+        for (heading, completed) in [("ACTIVE", false), ("COMPLETED", true)] {
+            content.push_str(&format!(
+                "<div style='font-size: 12px; margin: 10px 0 5px;'>{heading}</div>"
+            ));
+            let mut any = false;
+            for quest in self
+                .game
+                .quests
+                .iter()
+                .filter(|q| q.is_complete() == completed)
+            {
+                any = true;
+                content.push_str(&format!(
+                    "<div>{} [{}]</div><div style='color: #888888;'>{}</div>",
+                    quest.title,
+                    quest.status_label(),
+                    quest.description
+                ));
+            }
+            if !any {
+                content.push_str("<div>None</div>");
+            }
+        }
+
+        content.push_str(
+            "
+            <div style='margin-top: 15px;'>
+                <div>Press J to close</div>
+            </div>
+        </div>",
+        );
+
+        self.set_panel_html(&content);
+        Ok(())
+    }
+
+    fn render_bestiary_panel(&mut self) -> Result<(), JsValue> {
+        let mut content = format!(
+            "<div style='color: {}; font-family: monospace;'>
+                <div style='font-size: 14px; margin-bottom: 10px; text-align: center;'>BESTIARY</div>",
+            TEXT_COLOR
+        );
+
+        if self.game.bestiary.is_empty() {
+            content.push_str("<div>You haven't fought anything yet.</div>");
+        }
+        for (name, entry) in self.game.bestiary.entries() {
+            content.push_str(&format!(
+                "<div style='margin-top: 5px;'>{name} [{}]</div>
+                <div style='color: #888888;'>Met {}, slain {}, worst hit taken {}</div>",
+                entry.stats_text(),
+                entry.encountered,
+                entry.killed,
+                entry.max_damage_taken
+            ));
+        }
+
+        content.push_str(
+            "
+            <div style='margin-top: 15px;'>
+                <div>Press B to close</div>
+            </div>
+        </div>",
+        );
+
+        self.set_panel_html(&content);
+        Ok(())
+    }
+
+    fn render_summary_panel(&mut self) -> Result<(), JsValue> {
+        let title = match self.game.game_state {
+            GameState::Victory => "VICTORY!",
+            _ => "GAME OVER",
         };
-        self.message_area.set_inner_html(&new_content);
+
+        let run_label = self
+            .game
+            .run_label()
+            .map(|label| format!("<div style='color: #FF00FF; text-align: center;'>{label}</div>"))
+            .unwrap_or_default();
+
+        let mut content = format!(
+            "<div style='color: {}; font-family: monospace;'>
+                <div style='font-size: 14px; margin-bottom: 10px; text-align: center;'>{}</div>
+                {run_label}
+                <div style='font-size: 12px; margin-bottom: 5px;'>RUN SUMMARY</div>
+                <table style='width: 100%; color: inherit;'>",
+            TEXT_COLOR, title
+        );
+
+        for (label, value) in self.game.stats.summary_rows() {
+            content.push_str(&format!(
+                "<tr><td>{label}</td><td style='text-align: right;'>{value}</td></tr>"
+            ));
+        }
+
+        content.push_str("</table>");
+        if let Some(placement) = self.run_placement {
+            content.push_str(&format!(
+                "<div style='margin-top: 10px; color: #FFFF00;'>{placement}</div>"
+            ));
+        }
+        if matches!(self.game.game_state, GameState::Victory) {
+            content.push_str(
+                "<div style='margin-top: 15px;'>
+                    <div>1. New Game+ (tougher dungeons)</div>
+                    <div>2. Return to main menu</div>
+                </div>",
+            );
+        }
+        content.push_str("</div>");
+
+        self.set_panel_html(&content);
+        Ok(())
+    }
+
+    fn add_message(&mut self, message: &str) {
+        self.game.log.push(LogCategory::System, message);
+        self.show_new_log_entries();
+    }
+
+    /// Appends the game log's entries that aren't on the page yet to the
+    /// message area
+    fn show_new_log_entries(&mut self) {
+        let entries = self.game.log.take_unread();
+        if entries.is_empty() {
+            return;
+        }
+
+        let mut content = self.message_area.inner_html();
+        for entry in entries {
+            let color = match entry.category {
+                LogCategory::Combat => "#ffc8c8",
+                LogCategory::Loot => "#c8ffc8",
+                LogCategory::Movement => "#c8e6ff",
+                LogCategory::System => "#ffffff",
+                LogCategory::Achievement => "#ff9cff",
+                LogCategory::Hint => "#ffff9c",
+            };
+            if !content.is_empty() {
+                content.push_str("<br>");
+            }
+            content.push_str(&format!(
+                "<span style='color: {color}'>{}</span>",
+                entry.text
+            ));
+        }
+        self.message_area.set_inner_html(&content);
 
         // Auto-scroll to bottom
         self.message_area
@@ -781,7 +3062,8 @@ impl WebGame {
 }
 
 // Initialize the game when the WASM module loads
-#[wasm_bindgen(start)]
+// The tests make their own games, on a page without the game's markup
+#[cfg_attr(not(test), wasm_bindgen(start))]
 pub fn main() -> Result<(), JsValue> {
     console::log_1(
         &"WASM module loaded - initializing Echoes RPG Visual Dungeon Crawler...".into(),
@@ -796,6 +3078,155 @@ pub fn main() -> Result<(), JsValue> {
     Ok(())
 }
 
+/// Plays one animation frame, then tells the page's callback about the
+/// level-ups, deaths and victories it brought
+fn run_frame(inner: &RefCell<GameInner>, now: f64) {
+    let events = match inner.try_borrow_mut() {
+        Ok(mut inner) => {
+            if let Err(error) = inner.tick(now) {
+                console::error_1(&error);
+            }
+            inner.take_events()
+        }
+        Err(_) => None,
+    };
+    // Told once the state is let go, so the page's callback can query the
+    // game
+    if let Some((listener, events)) = events {
+        for event in events {
+            let json = JsValue::from_str(&event.to_json());
+            if let Err(error) = listener.call1(&JsValue::NULL, &json) {
+                console::error_1(&error);
+            }
+        }
+    }
+}
+
+fn request_animation_frame(callback: &Closure<dyn FnMut(f64)>) -> Result<i32, JsValue> {
+    window()
+        .ok_or("No window to animate")?
+        .request_animation_frame(callback.as_ref().unchecked_ref())
+}
+
+/// Reads the hall of fame from localStorage; missing or corrupt data starts
+/// a fresh one
+fn load_leaderboard() -> Leaderboard {
+    window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .and_then(|storage| storage.get_item(HALL_OF_FAME_KEY).ok().flatten())
+        .map(|json| Leaderboard::from_json(&json))
+        .unwrap_or_default()
+}
+
+/// Reads the achievements unlocked from localStorage; missing or corrupt
+/// data means none
+fn load_achievements() -> AchievementProfile {
+    window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .and_then(|storage| storage.get_item(ACHIEVEMENTS_KEY).ok().flatten())
+        .map(|json| AchievementProfile::from_json(&json))
+        .unwrap_or_default()
+}
+
+/// Reads the bones of dead characters from localStorage; missing or
+/// corrupt data means none
+fn load_bones() -> BonesPool {
+    window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .and_then(|storage| storage.get_item(BONES_KEY).ok().flatten())
+        .map(|json| BonesPool::from_json(&json))
+        .unwrap_or_default()
+}
+
+/// Reads the settings from localStorage, warning in the console about any
+/// value that had to be clamped; missing settings are the defaults
+fn load_settings() -> Settings {
+    let Some(json) = window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .and_then(|storage| storage.get_item(SETTINGS_KEY).ok().flatten())
+    else {
+        return Settings::default();
+    };
+    let (settings, warnings) = Settings::from_json(&json);
+    for warning in warnings {
+        console::warn_1(&format!("Settings: {warning}").into());
+    }
+    settings
+}
+
+/// Writes the settings to localStorage, returning whether that worked
+fn store_settings(settings: &Settings) -> bool {
+    window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .is_some_and(|storage| storage.set_item(SETTINGS_KEY, &settings.to_json()).is_ok())
+}
+
+/// Has the browser download `text` as a file called `file_name`, by way of
+/// a link to a Blob of it that's clicked and thrown away
+fn download_text(file_name: &str, text: &str) -> Result<(), JsValue> {
+    let document = window()
+        .and_then(|window| window.document())
+        .ok_or("No document to download from")?;
+    let parts = js_sys::Array::of1(&JsValue::from_str(text));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type("text/plain");
+    let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+    let link: web_sys::HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    link.set_href(&url);
+    link.set_download(file_name);
+    link.click();
+    web_sys::Url::revoke_object_url(&url)
+}
+
+/// CSS color for a glyph color from the render module
+fn css_color(color: FogColor) -> String {
+    format!("#{:02X}{:02X}{:02X}", color.r, color.g, color.b)
+}
+
+/// A bar as a styled div, filled in `color` to `bar`'s fraction between its
+/// label and its numbers
+fn bar_html(label: &str, bar: &Bar, color: &str) -> String {
+    format!(
+        "<div style='display: flex; align-items: center; gap: 6px;'>
+            <span>{label}</span>
+            <div style='flex: 1; height: 10px; background: #333333; border: 1px solid #555555;'>
+                <div style='width: {:.0}%; height: 100%; background: {color};'></div>
+            </div>
+            <span>{}</span>
+        </div>",
+        bar.fraction() * 100.0,
+        bar.value()
+    )
+}
+
+/// The HOTBAR section of the side panels, empty slots dimmed
+fn hotbar_html(player: &Player) -> String {
+    let slots: String = InventoryManager::hotbar_slots(player)
+        .iter()
+        .map(|slot| {
+            let color = if slot.is_empty() {
+                "#666666"
+            } else {
+                TEXT_COLOR
+            };
+            format!("<div style='color: {color};'>{}</div>", slot.label())
+        })
+        .collect();
+    format!(
+        "<div style='margin-top: 15px;'>
+            <div style='font-size: 12px; margin-bottom: 5px;'>HOTBAR</div>
+            {slots}
+        </div>"
+    )
+}
+
+/// CSS hex color for an equipment rarity tier
+fn rarity_css_color(rarity: Rarity) -> String {
+    let (r, g, b) = rarity.rgb();
+    format!("#{r:02X}{g:02X}{b:02X}")
+}
+
 fn initialize_game() -> Result<(), JsValue> {
     // Wait a bit to ensure DOM is ready
     let window = window().unwrap();
@@ -813,8 +3244,125 @@ fn initialize_game() -> Result<(), JsValue> {
 
     console::log_1(&"Visual dungeon crawler initialized successfully!".into());
 
-    // Keep the game instance alive (in a real implementation, you'd want to store this properly)
-    std::mem::forget(game);
-
+    // The keyboard handlers hold their own reference to the game state,
+    // so it stays alive after this handle is dropped
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    /// A game set up on a page with the element it's put in
+    fn web_game() -> WebGame {
+        let document = window().unwrap().document().unwrap();
+        if document.get_element_by_id("main-content").is_none() {
+            let main_content = document.create_element("div").unwrap();
+            main_content.set_id("main-content");
+            document
+                .body()
+                .unwrap()
+                .append_child(&main_content)
+                .unwrap();
+        }
+        WebGame::new().unwrap()
+    }
+
+    /// A game under way, kept as the page would keep it
+    fn saved_game() -> String {
+        let mut game = Game::new(Player::new("Kept".to_string(), ClassType::Mage));
+        game.game_state = GameState::Playing;
+        game.ambient_events = false;
+        export_game(&game)
+    }
+
+    fn state(web: &WebGame) -> Value {
+        serde_json::from_str(&web.get_state_json()).unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_the_state_is_given_as_json() {
+        let web = web_game();
+        assert_eq!(state(&web)["state"], "main_menu");
+        assert_eq!(state(&web)["player"]["name"], "WebHero");
+
+        // Asked while the game is busy, the answer is empty
+        let _busy = web.inner.borrow_mut();
+        assert_eq!(web.get_state_json(), "");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_keys_sent_are_played_on_the_next_frame() {
+        let web = web_game();
+        assert!(web.load(&saved_game()));
+        web.send_key("i");
+        assert_eq!(state(&web)["state"], "playing");
+        run_frame(&web.inner, 1000.0);
+        assert_eq!(state(&web)["state"], "inventory");
+
+        // A key sent while the game is busy is dropped
+        {
+            let _busy = web.inner.borrow_mut();
+            web.send_key("Escape");
+        }
+        run_frame(&web.inner, 2000.0);
+        assert_eq!(state(&web)["state"], "inventory");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_a_saved_game_loads_back() {
+        let web = web_game();
+        assert!(!web.load("not a save"));
+        assert_eq!(state(&web)["player"]["name"], "WebHero");
+
+        assert!(web.load(&saved_game()));
+        assert_eq!(state(&web)["player"]["name"], "Kept");
+        assert_eq!(state(&web)["state"], "playing");
+        let saved = web.save();
+        assert_eq!(import_game(&saved).unwrap().player.name, "Kept");
+
+        // Neither works while the game is busy
+        let _busy = web.inner.borrow_mut();
+        assert_eq!(web.save(), "");
+        assert!(!web.load(&saved));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_the_page_hears_of_a_level_up_and_can_look_at_the_game() {
+        let web = web_game();
+        assert!(web.load(&saved_game()));
+        let heard = Rc::new(RefCell::new(Vec::new()));
+        let record = Rc::clone(&heard);
+        let page = WebGame {
+            inner: Rc::clone(&web.inner),
+        };
+        let callback = Closure::<dyn FnMut(String)>::new(move |event: String| {
+            record.borrow_mut().push((event, page.get_state_json()));
+        });
+        web.on_event(
+            callback
+                .as_ref()
+                .unchecked_ref::<js_sys::Function>()
+                .clone(),
+        );
+
+        run_frame(&web.inner, 1000.0);
+        assert!(heard.borrow().is_empty());
+
+        web.inner.borrow_mut().game.player.level += 1;
+        run_frame(&web.inner, 2000.0);
+        let heard = heard.borrow();
+        assert_eq!(heard.len(), 1);
+        let (event, snapshot) = &heard[0];
+        let event: Value = serde_json::from_str(event).unwrap();
+        assert_eq!(event["type"], "level_up");
+        assert_eq!(event["level"], 2);
+        // The callback was called with the game let go, so it could read it
+        let snapshot: Value = serde_json::from_str(snapshot).unwrap();
+        assert_eq!(snapshot["player"]["level"], 2);
+    }
+}
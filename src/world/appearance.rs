@@ -0,0 +1,307 @@
+//! How everything on the map looks
+//!
+//! One table gives every tile type and everything that stands on the map a
+//! glyph in each glyph set, a color and a legend label. The map, the fog of
+//! war and every legend read it, so no two of them can disagree. The plain
+//! ASCII set is the default because some terminals draw the block and arrow
+//! characters of the unicode set badly.
+
+use crate::world::fog_of_war::FogColor;
+use crate::world::{Alertness, TileType};
+
+/// Which characters the map is drawn with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GlyphSet {
+    /// Letters and punctuation only
+    #[default]
+    Ascii,
+    /// Solid walls, arrows for stairs and other box-drawing characters
+    Unicode,
+}
+
+impl GlyphSet {
+    pub const ALL: [GlyphSet; 2] = [GlyphSet::Ascii, GlyphSet::Unicode];
+
+    /// Name stored in the settings file
+    pub fn name(self) -> &'static str {
+        match self {
+            GlyphSet::Ascii => "ascii",
+            GlyphSet::Unicode => "unicode",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|set| set.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Anything that can fill a cell of the map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapFeature {
+    Player,
+    Companion,
+    /// Someone friendly met in the dungeon
+    Npc,
+    Enemy(Alertness),
+    /// An enemy leading a pack, whatever its alertness
+    Leader,
+    Item,
+    Gold,
+    Corpse,
+    Tile(TileType),
+}
+
+/// How one map feature is drawn and what the legend calls it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileAppearance {
+    pub feature: MapFeature,
+    pub ascii: char,
+    pub unicode: char,
+    pub color: FogColor,
+    pub label: &'static str,
+}
+
+impl TileAppearance {
+    /// The row of `TILE_APPEARANCES` for `feature`
+    pub fn of(feature: MapFeature) -> &'static TileAppearance {
+        TILE_APPEARANCES
+            .iter()
+            .find(|appearance| appearance.feature == feature)
+            .expect("every map feature has a row in TILE_APPEARANCES")
+    }
+
+    pub fn symbol(&self, set: GlyphSet) -> char {
+        match set {
+            GlyphSet::Ascii => self.ascii,
+            GlyphSet::Unicode => self.unicode,
+        }
+    }
+}
+
+const GREEN: FogColor = FogColor::rgb(0, 255, 0);
+
+/// Every map feature, in the order legends list them. Enemies share a
+/// letter and tell their alertness by color and case, and pack leaders
+/// stand out in violet.
+pub const TILE_APPEARANCES: [TileAppearance; 22] = [
+    TileAppearance {
+        feature: MapFeature::Player,
+        ascii: '@',
+        unicode: '@',
+        color: FogColor::rgb(255, 255, 0), // Yellow
+        label: "You",
+    },
+    TileAppearance {
+        feature: MapFeature::Companion,
+        ascii: 'd',
+        unicode: 'd',
+        color: FogColor::rgb(0, 200, 0), // Green
+        label: "Your companion",
+    },
+    TileAppearance {
+        feature: MapFeature::Npc,
+        ascii: 'N',
+        unicode: '☺',
+        color: FogColor::rgb(120, 190, 255), // Light blue
+        label: "Friendly NPC",
+    },
+    TileAppearance {
+        feature: MapFeature::Enemy(Alertness::Unaware),
+        ascii: 'e',
+        unicode: 'e',
+        color: FogColor::rgb(255, 0, 0), // Red
+        label: "Unaware enemy",
+    },
+    TileAppearance {
+        feature: MapFeature::Enemy(Alertness::Suspicious),
+        ascii: 'E',
+        unicode: 'E',
+        color: FogColor::rgb(255, 165, 0), // Orange
+        label: "Wary enemy",
+    },
+    TileAppearance {
+        feature: MapFeature::Enemy(Alertness::Alerted),
+        ascii: 'E',
+        unicode: 'E',
+        color: FogColor::rgb(255, 0, 0), // Red
+        label: "Hunting enemy",
+    },
+    TileAppearance {
+        feature: MapFeature::Leader,
+        ascii: 'E',
+        unicode: 'E',
+        color: FogColor::rgb(200, 80, 255), // Violet
+        label: "Pack leader",
+    },
+    TileAppearance {
+        feature: MapFeature::Item,
+        ascii: '!',
+        unicode: '!',
+        color: FogColor::rgb(0, 255, 255), // Cyan
+        label: "Item",
+    },
+    TileAppearance {
+        feature: MapFeature::Gold,
+        ascii: '$',
+        unicode: '$',
+        color: FogColor::rgb(255, 215, 0), // Gold
+        label: "Gold",
+    },
+    TileAppearance {
+        feature: MapFeature::Corpse,
+        ascii: '%',
+        unicode: '%',
+        color: FogColor::rgb(140, 40, 40), // Dark red
+        label: "Corpse",
+    },
+    TileAppearance {
+        feature: MapFeature::Tile(TileType::Wall),
+        ascii: '#',
+        unicode: '█',
+        color: FogColor::GREY,
+        label: "Wall",
+    },
+    TileAppearance {
+        feature: MapFeature::Tile(TileType::Floor),
+        ascii: '.',
+        unicode: '·',
+        color: FogColor::WHITE,
+        label: "Floor",
+    },
+    TileAppearance {
+        feature: MapFeature::Tile(TileType::Door),
+        ascii: '+',
+        unicode: '▒',
+        color: FogColor::rgb(139, 69, 19), // Brown
+        label: "Door",
+    },
+    TileAppearance {
+        feature: MapFeature::Tile(TileType::Chest),
+        ascii: 'C',
+        unicode: '▯',
+        color: FogColor::rgb(255, 215, 0), // Gold
+        label: "Chest",
+    },
+    TileAppearance {
+        feature: MapFeature::Tile(TileType::StairsDown),
+        ascii: '>',
+        unicode: '▼',
+        color: GREEN,
+        label: "Stairs down",
+    },
+    TileAppearance {
+        feature: MapFeature::Tile(TileType::StairsUp),
+        ascii: '<',
+        unicode: '▲',
+        color: GREEN,
+        label: "Stairs up",
+    },
+    TileAppearance {
+        feature: MapFeature::Tile(TileType::Exit),
+        ascii: 'X',
+        unicode: '≈',
+        color: FogColor::rgb(255, 0, 255), // Magenta, apart from the stairs
+        label: "Dungeon exit",
+    },
+    TileAppearance {
+        feature: MapFeature::Tile(TileType::Grave),
+        ascii: '&',
+        unicode: '†',
+        color: FogColor::rgb(200, 200, 220), // Pale grey
+        label: "Grave",
+    },
+    TileAppearance {
+        feature: MapFeature::Tile(TileType::ShallowWater),
+        ascii: '~',
+        unicode: '~',
+        color: FogColor::rgb(64, 128, 255), // Blue
+        label: "Shallow water",
+    },
+    TileAppearance {
+        feature: MapFeature::Tile(TileType::Lava),
+        ascii: '=',
+        unicode: '≋',
+        color: FogColor::rgb(255, 80, 0), // Molten orange
+        label: "Lava",
+    },
+    TileAppearance {
+        feature: MapFeature::Tile(TileType::Rubble),
+        ascii: ':',
+        unicode: '░',
+        color: FogColor::rgb(150, 130, 110), // Dusty brown
+        label: "Rubble",
+    },
+    TileAppearance {
+        feature: MapFeature::Tile(TileType::Altar),
+        ascii: '_',
+        unicode: '╥',
+        color: FogColor::rgb(190, 150, 255), // Pale violet
+        label: "Altar",
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Enemies of every alertness, and their leaders, count as one thing on
+    /// the map
+    fn same_thing(a: MapFeature, b: MapFeature) -> bool {
+        let enemy = |feature| matches!(feature, MapFeature::Enemy(_) | MapFeature::Leader);
+        (enemy(a) && enemy(b)) || a == b
+    }
+
+    #[test]
+    fn test_no_two_features_share_a_glyph_within_a_set() {
+        for set in GlyphSet::ALL {
+            for (i, a) in TILE_APPEARANCES.iter().enumerate() {
+                for b in &TILE_APPEARANCES[i + 1..] {
+                    assert_ne!(a.feature, b.feature);
+                    assert!(
+                        a.symbol(set) != b.symbol(set) || same_thing(a.feature, b.feature),
+                        "{} and {} are both drawn as {:?} in the {} set",
+                        a.label,
+                        b.label,
+                        a.symbol(set),
+                        set.name()
+                    );
+                    assert!((a.symbol(set), a.color) != (b.symbol(set), b.color));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_tile_type_and_alertness_has_an_appearance() {
+        for tile in [
+            TileType::Wall,
+            TileType::Floor,
+            TileType::Door,
+            TileType::StairsDown,
+            TileType::StairsUp,
+            TileType::Chest,
+            TileType::Exit,
+            TileType::Grave,
+            TileType::ShallowWater,
+            TileType::Lava,
+            TileType::Rubble,
+            TileType::Altar,
+        ] {
+            assert_eq!(
+                TileAppearance::of(MapFeature::Tile(tile)).label,
+                tile.name()
+            );
+        }
+        for alertness in [
+            Alertness::Unaware,
+            Alertness::Suspicious,
+            Alertness::Alerted,
+        ] {
+            TileAppearance::of(MapFeature::Enemy(alertness));
+        }
+        assert_eq!(GlyphSet::from_name("Unicode"), Some(GlyphSet::Unicode));
+        assert_eq!(GlyphSet::from_name("braille"), None);
+    }
+}
@@ -0,0 +1,80 @@
+//! How aware enemies are of the player, and the noise that wakes them
+
+use serde::{Deserialize, Serialize};
+
+/// Tiles a noise carries per point of loudness
+pub const TILES_PER_NOISE: i32 = 4;
+/// Points of dexterity that muffle a noise by one tile
+pub const DEXTERITY_PER_MUFFLED_TILE: i32 = 4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Alertness {
+    /// Wanders about; attacking it first lands a sneak attack
+    #[default]
+    Unaware,
+    /// Heard something and goes to look
+    Suspicious,
+    /// Has seen the player and chases them
+    Alerted,
+}
+
+impl Alertness {
+    /// How the look command describes an enemy in this state
+    pub fn describe(self) -> &'static str {
+        match self {
+            Alertness::Unaware => "hasn't noticed you",
+            Alertness::Suspicious => "is searching for something",
+            Alertness::Alerted => "is hunting you",
+        }
+    }
+}
+
+/// Something the player did that enemies may hear
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Noise {
+    Footsteps,
+    /// Stepping through a doorway
+    Door,
+    /// Prying open a chest
+    Chest,
+    /// Smashing a chest open
+    Smash,
+    Fight,
+}
+
+impl Noise {
+    pub fn loudness(self) -> i32 {
+        match self {
+            Noise::Footsteps => 1,
+            Noise::Door | Noise::Chest => 2,
+            Noise::Smash => 4,
+            Noise::Fight => 5,
+        }
+    }
+
+    /// How far, in tiles, the noise carries when made by a player with
+    /// `dexterity`; nimble players make less of it
+    pub fn radius(self, dexterity: i32) -> i32 {
+        let muffled = dexterity.max(0) / DEXTERITY_PER_MUFFLED_TILE;
+        (self.loudness() * TILES_PER_NOISE - muffled).max(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_louder_noises_carry_farther_and_dexterity_muffles_them() {
+        assert_eq!(Noise::Footsteps.radius(0), TILES_PER_NOISE);
+        assert!(Noise::Chest.radius(8) > Noise::Footsteps.radius(8));
+        assert!(Noise::Fight.radius(8) > Noise::Chest.radius(8));
+
+        assert_eq!(
+            Noise::Footsteps.radius(DEXTERITY_PER_MUFFLED_TILE * 2),
+            TILES_PER_NOISE - 2
+        );
+        // A nimble enough player walks in silence
+        assert_eq!(Noise::Footsteps.radius(100), 0);
+    }
+}
@@ -0,0 +1,118 @@
+//! How much danger and loot a level is given
+//!
+//! Each level gets a budget from its dungeon's difficulty plus how deep it
+//! lies. Enemy points are spent on enemies, stronger ones costing more, and
+//! the loot budget sets how many chests there are and how good their items
+//! are. The final level gets a bonus on both.
+
+/// Enemy points a level gets per point of depth
+pub const ENEMY_POINTS_PER_DEPTH: u32 = 3;
+/// Enemy levels per extra point an enemy costs
+pub const ENEMY_LEVELS_PER_POINT: u32 = 3;
+/// Extra enemy points on the final level, in percent
+pub const FINAL_LEVEL_BONUS_PERCENT: u32 = 50;
+/// Chests every level gets, before depth adds more
+pub const BASE_CHESTS: u32 = 2;
+/// Points of depth per extra chest
+pub const DEPTH_PER_CHEST: u32 = 3;
+
+/// What generating a level may spend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelBudget {
+    /// Difficulty plus level number; everything else follows from it
+    pub depth: u32,
+    /// Points to spend on enemies; see `enemy_cost`
+    pub enemy_points: u32,
+    pub chests: u32,
+    /// Level of the items in chests and on the floor
+    pub item_level: u32,
+    pub is_final: bool,
+}
+
+impl LevelBudget {
+    pub fn new(difficulty: u32, level_num: u32, is_final: bool) -> Self {
+        let depth = difficulty + level_num;
+        let mut enemy_points = depth * ENEMY_POINTS_PER_DEPTH;
+        let mut chests = BASE_CHESTS + depth / DEPTH_PER_CHEST;
+        // Harder dungeons hold loot from deeper down
+        let mut item_level = level_num + difficulty / 2;
+        if is_final {
+            enemy_points += enemy_points * FINAL_LEVEL_BONUS_PERCENT / 100;
+            chests += 1;
+            item_level += 1;
+        }
+
+        LevelBudget {
+            depth,
+            enemy_points,
+            chests,
+            item_level,
+            is_final,
+        }
+    }
+
+    /// The budget spelled out for tuning it, given the enemy points the
+    /// level's enemies are still worth
+    pub fn breakdown(&self, enemies_left: u32) -> Vec<String> {
+        let final_bonus = if self.is_final {
+            format!(" (final level +{FINAL_LEVEL_BONUS_PERCENT}%)")
+        } else {
+            String::new()
+        };
+        vec![
+            format!(
+                "Budget for depth {}: {} x {ENEMY_POINTS_PER_DEPTH} enemy points per depth{final_bonus}",
+                self.depth, self.depth
+            ),
+            format!(
+                "Enemies: {enemies_left} of {} points still on the level",
+                self.enemy_points
+            ),
+            format!(
+                "Loot: {} chests of item level {}",
+                self.chests, self.item_level
+            ),
+        ]
+    }
+}
+
+/// Enemy points an enemy of `level` costs
+pub fn enemy_cost(level: u32) -> u32 {
+    1 + level / ENEMY_LEVELS_PER_POINT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_grows_with_depth_and_difficulty() {
+        for difficulty in 1..8 {
+            for level_num in 1..8 {
+                let budget = LevelBudget::new(difficulty, level_num, false);
+                for deeper in [
+                    LevelBudget::new(difficulty, level_num + 1, false),
+                    LevelBudget::new(difficulty + 1, level_num, false),
+                ] {
+                    assert!(deeper.enemy_points > budget.enemy_points);
+                    assert!(deeper.chests >= budget.chests);
+                    assert!(deeper.item_level >= budget.item_level);
+                }
+
+                let last = LevelBudget::new(difficulty, level_num, true);
+                assert!(last.enemy_points > budget.enemy_points);
+                assert!(last.chests > budget.chests);
+                assert!(last.item_level > budget.item_level);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stronger_enemies_cost_more() {
+        assert_eq!(enemy_cost(1), 1);
+        assert!(enemy_cost(ENEMY_LEVELS_PER_POINT * 4) > enemy_cost(ENEMY_LEVELS_PER_POINT));
+        for level in 1..40 {
+            assert!(enemy_cost(level + 1) >= enemy_cost(level));
+        }
+    }
+}
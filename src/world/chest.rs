@@ -0,0 +1,247 @@
+//! Chests: a handful of items and some gold, found in one go
+//!
+//! Not every chest is what it seems. A few are trapped and spring on
+//! whoever opens them carelessly, and a few are mimics waiting for a hand
+//! on the lid. Which is which is kept with the chest and never shown until
+//! a careful look beside it turns it up.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::item::Item;
+
+/// Most items a freshly generated chest holds
+pub const MAX_CHEST_ITEMS: usize = 3;
+
+/// Chance a freshly generated chest is trapped
+pub const TRAPPED_CHANCE: f64 = 0.1;
+
+/// Chance a freshly generated chest is a mimic
+pub const MIMIC_CHANCE: f64 = 0.05;
+
+/// Turns a poison gas trap leaves the player poisoned
+pub const POISON_TURNS: u32 = 8;
+
+/// What springs on whoever opens a trapped chest without disarming it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChestTrap {
+    Needle,
+    /// Poisons the player as well as hurting them
+    Poison,
+    /// Hurts the most, and is heard a long way off
+    Explosion,
+}
+
+impl ChestTrap {
+    /// "needle trap", as the log names it
+    pub fn name(self) -> &'static str {
+        match self {
+            ChestTrap::Needle => "needle trap",
+            ChestTrap::Poison => "poison gas trap",
+            ChestTrap::Explosion => "explosive rune",
+        }
+    }
+
+    /// Damage it does on springing, on level `depth` of a dungeon
+    pub fn damage(self, depth: u32) -> i32 {
+        let depth = depth as i32;
+        match self {
+            ChestTrap::Needle => 3 + depth,
+            ChestTrap::Poison => 2 + depth / 2,
+            ChestTrap::Explosion => 6 + 2 * depth,
+        }
+    }
+}
+
+/// What a chest really is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChestKind {
+    #[default]
+    Plain,
+    Trapped(ChestTrap),
+    /// No chest at all, but something that wakes when touched
+    Mimic,
+}
+
+impl ChestKind {
+    /// What a freshly generated chest turns out to be: a mimic
+    /// `MIMIC_CHANCE` of the time, trapped `TRAPPED_CHANCE` of it
+    pub fn roll<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let roll: f64 = rng.gen();
+        if roll < MIMIC_CHANCE {
+            ChestKind::Mimic
+        } else if roll < MIMIC_CHANCE + TRAPPED_CHANCE {
+            let traps = [ChestTrap::Needle, ChestTrap::Poison, ChestTrap::Explosion];
+            ChestKind::Trapped(traps[rng.gen_range(0..traps.len())])
+        } else {
+            ChestKind::Plain
+        }
+    }
+}
+
+/// Chance someone with `dexterity` and `wisdom` spots what's wrong with a
+/// chest they look over
+pub fn detection_chance(dexterity: i32, wisdom: i32) -> f64 {
+    (0.25 + 0.04 * (dexterity + wisdom - 10) as f64).clamp(0.1, 0.9)
+}
+
+/// Chance someone with `dexterity` disarms a trap they've found
+pub fn disarm_chance(dexterity: i32) -> f64 {
+    (0.4 + 0.05 * (dexterity - 5) as f64).clamp(0.1, 0.9)
+}
+
+/// Chance a Warrior with `strength` smashes a chest open before the trap
+/// they've found on it springs
+pub fn smash_chance(strength: i32) -> f64 {
+    (0.3 + 0.05 * (strength - 5) as f64).clamp(0.1, 0.9)
+}
+
+/// What a chest holds until it's opened
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Chest {
+    pub items: Vec<Item>,
+    pub gold: u32,
+    /// Whether it's trapped or a mimic; never shown until `found` says so
+    #[serde(default)]
+    pub kind: ChestKind,
+    /// Whether the player has looked it over and found what's wrong with it
+    #[serde(default)]
+    pub found: bool,
+}
+
+impl Chest {
+    /// A chest of 1 to `MAX_CHEST_ITEMS` items for dungeon level
+    /// `loot_level`, with gold scaled by the dungeon's difficulty. The first
+    /// item gets the chest's better odds; the rest are ordinary finds.
+    pub fn generate<R: Rng + ?Sized>(loot_level: u32, difficulty: u32, rng: &mut R) -> Self {
+        let count = rng.gen_range(1..=MAX_CHEST_ITEMS);
+        let mut items = vec![Item::generate_for_chest(loot_level, rng)];
+        items.extend((1..count).map(|_| Item::generate_with_rng(loot_level, rng)));
+
+        let gold = rng.gen_range(5..=15) * difficulty.max(1) + loot_level * 3;
+        Chest {
+            items,
+            gold,
+            ..Chest::default()
+        }
+    }
+
+    /// Looks the chest over as someone with `dexterity` and `wisdom`.
+    /// Returns whether they found a trap or a mimic; a plain chest has
+    /// nothing to find.
+    pub fn inspect<R: Rng + ?Sized>(&mut self, dexterity: i32, wisdom: i32, rng: &mut R) -> bool {
+        if self.kind == ChestKind::Plain {
+            return false;
+        }
+        self.found = self.found || rng.gen_bool(detection_chance(dexterity, wisdom));
+        self.found
+    }
+
+    /// The trap found on the chest, if a look over it turned one up
+    pub fn found_trap(&self) -> Option<ChestTrap> {
+        match self.kind {
+            ChestKind::Trapped(trap) if self.found => Some(trap),
+            _ => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty() && self.gold == 0
+    }
+
+    /// Everything in the chest, with repeats counted:
+    /// "Steel Dagger (+5 ATK), 2x Health Potion (+20 HP), 34 gold"
+    pub fn summary(&self) -> String {
+        let mut counted: Vec<(String, usize)> = Vec::new();
+        for item in &self.items {
+            let name = item.name_with_stats();
+            match counted.iter_mut().find(|(seen, _)| *seen == name) {
+                Some((_, count)) => *count += 1,
+                None => counted.push((name, 1)),
+            }
+        }
+
+        let mut parts: Vec<String> = counted
+            .into_iter()
+            .map(|(name, count)| match count {
+                1 => name,
+                _ => format!("{count}x {name}"),
+            })
+            .collect();
+        if self.gold > 0 {
+            parts.push(format!("{} gold", self.gold));
+        }
+        parts.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::Consumable;
+    use rand::rngs::mock::StepRng;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_generated_chests_are_never_empty() {
+        for seed in 0..500 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let chest = Chest::generate(1 + seed as u32 % 5, 1 + seed as u32 % 3, &mut rng);
+            assert!((1..=MAX_CHEST_ITEMS).contains(&chest.items.len()));
+            assert!(chest.gold > 0);
+        }
+    }
+
+    #[test]
+    fn test_summary_counts_repeats() {
+        let potion = Item::Consumable(Consumable::health_potion(20));
+        let chest = Chest {
+            items: vec![
+                Item::Equipment(crate::item::Equipment::weapon("Steel Dagger", 5)),
+                potion.clone(),
+                potion,
+            ],
+            gold: 34,
+            ..Chest::default()
+        };
+        assert_eq!(
+            chest.summary(),
+            "Steel Dagger (+5 ATK), 2x Minor Health Potion (+20 HP), 34 gold"
+        );
+        assert!(Chest::default().is_empty());
+        assert_eq!(Chest::default().summary(), "");
+    }
+
+    #[test]
+    fn test_only_a_careful_look_finds_what_is_wrong_with_a_chest() {
+        let mut lucky = StepRng::new(0, 0);
+        let mut unlucky = StepRng::new(u64::MAX, 0);
+        assert_eq!(ChestKind::roll(&mut lucky), ChestKind::Mimic);
+        assert_eq!(ChestKind::roll(&mut unlucky), ChestKind::Plain);
+
+        let mut plain = Chest::default();
+        assert!(!plain.inspect(20, 20, &mut lucky));
+
+        let mut trapped = Chest {
+            kind: ChestKind::Trapped(ChestTrap::Needle),
+            ..Chest::default()
+        };
+        assert!(!trapped.inspect(5, 5, &mut unlucky));
+        assert_eq!(trapped.found_trap(), None);
+        assert!(trapped.inspect(5, 5, &mut lucky));
+        assert_eq!(trapped.found_trap(), Some(ChestTrap::Needle));
+        // Once found, it stays found
+        assert!(trapped.inspect(5, 5, &mut unlucky));
+
+        assert!(detection_chance(12, 12) > detection_chance(5, 5));
+        assert!(disarm_chance(12) > disarm_chance(5));
+        assert_eq!(detection_chance(100, 100), 0.9);
+        assert_eq!(disarm_chance(-100), 0.1);
+
+        // Saves from before traps and mimics load as plain chests
+        let old: Chest = serde_json::from_str(r#"{"items":[],"gold":5}"#).unwrap();
+        assert_eq!(old.kind, ChestKind::Plain);
+        assert!(!old.found);
+    }
+}
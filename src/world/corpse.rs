@@ -0,0 +1,165 @@
+//! Remains left where enemies fall
+//!
+//! A corpse does nothing but mark where something died until the player
+//! searches it, which now and then turns up gold or a potion the enemy
+//! didn't drop. Left alone it rots away after a while, and a dark mage
+//! nearby may raise it first, so it pays to search promptly.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::item::{Consumable, Item};
+use crate::world::enemy::EnemyType;
+use crate::world::Enemy;
+
+/// Turns a corpse lies before it rots away
+pub const CORPSE_DECAY_TURNS: u32 = 200;
+
+/// Chance of a search finding something, before wisdom
+pub const SEARCH_CHANCE: f64 = 0.15;
+
+/// Chance each point of wisdom adds to a search finding something
+pub const SEARCH_CHANCE_PER_WISDOM: f64 = 0.02;
+
+/// Best chance a search can have of finding something
+pub const MAX_SEARCH_CHANCE: f64 = 0.6;
+
+/// Distance (diagonals counting as one) within which a dark mage can raise
+/// a corpse it sees
+pub const RAISE_RADIUS: i32 = 5;
+
+/// Chance each turn of a dark mage raising a corpse within reach
+pub const RAISE_CHANCE: f64 = 0.1;
+
+/// What's left of an enemy where it fell
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Corpse {
+    /// The enemy's name: "Goblin"
+    pub name: String,
+    pub level: u32,
+    /// Turn of the run the enemy died on
+    pub died_on_turn: u32,
+    /// Whether the player has searched it already
+    #[serde(default)]
+    pub searched: bool,
+    /// Laid to rest by a Cleric, so nothing can raise it
+    #[serde(default)]
+    pub sanctified: bool,
+}
+
+/// What searching a corpse turned up
+#[derive(Debug, Clone)]
+pub enum SearchFind {
+    Gold(u32),
+    Item(Item),
+    Nothing,
+}
+
+impl Corpse {
+    pub fn new(enemy: &Enemy, turn: u32) -> Self {
+        Corpse {
+            name: enemy.name.clone(),
+            level: enemy.level,
+            died_on_turn: turn,
+            searched: false,
+            sanctified: false,
+        }
+    }
+
+    /// Whether the corpse has lain long enough by `turn` to rot away
+    pub fn has_decayed(&self, turn: u32) -> bool {
+        turn.saturating_sub(self.died_on_turn) >= CORPSE_DECAY_TURNS
+    }
+
+    /// Searches the corpse, which can only be done once. A searcher with
+    /// `wisdom` finds something with `search_chance(wisdom)`: gold or a
+    /// common consumable, as likely as each other.
+    pub fn search<R: Rng + ?Sized>(&mut self, wisdom: i32, rng: &mut R) -> SearchFind {
+        if self.searched {
+            return SearchFind::Nothing;
+        }
+        self.searched = true;
+        if !rng.gen_bool(search_chance(wisdom)) {
+            return SearchFind::Nothing;
+        }
+        if rng.gen_bool(0.5) {
+            SearchFind::Gold(rng.gen_range(2..=6) * self.level.max(1))
+        } else {
+            SearchFind::Item(Item::Consumable(Consumable::generate_with_rng(
+                self.level, rng,
+            )))
+        }
+    }
+
+    /// The weak undead a dark mage raises from the corpse
+    pub fn raise(&self) -> Enemy {
+        Enemy::new(
+            format!("Risen {}", self.name),
+            EnemyType::Skeleton,
+            (self.level / 2).max(1),
+        )
+    }
+}
+
+/// Chance of a search by someone with `wisdom` finding something
+pub fn search_chance(wisdom: i32) -> f64 {
+    (SEARCH_CHANCE + SEARCH_CHANCE_PER_WISDOM * f64::from(wisdom)).clamp(0.0, MAX_SEARCH_CHANCE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn goblin_corpse() -> Corpse {
+        let goblin = Enemy::new("Goblin".to_string(), EnemyType::Goblin, 3);
+        Corpse::new(&goblin, 10)
+    }
+
+    #[test]
+    fn test_search_odds_rise_with_wisdom() {
+        assert_eq!(search_chance(0), SEARCH_CHANCE);
+        assert!(search_chance(12) > search_chance(5));
+        assert_eq!(search_chance(100), MAX_SEARCH_CHANCE);
+        assert_eq!(search_chance(-20), 0.0);
+
+        // Over many corpses the finds come close to the odds
+        let mut rng = StdRng::seed_from_u64(7);
+        for wisdom in [0, 5, 15] {
+            let tries = 4000;
+            let finds = (0..tries)
+                .filter(|_| {
+                    !matches!(
+                        goblin_corpse().search(wisdom, &mut rng),
+                        SearchFind::Nothing
+                    )
+                })
+                .count();
+            let rate = finds as f64 / tries as f64;
+            assert!(
+                (rate - search_chance(wisdom)).abs() < 0.03,
+                "{wisdom}: {rate}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_a_corpse_is_searched_once_and_rots_away() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut corpse = goblin_corpse();
+        corpse.search(100, &mut rng);
+        assert!(corpse.searched);
+        for _ in 0..50 {
+            assert!(matches!(corpse.search(100, &mut rng), SearchFind::Nothing));
+        }
+
+        assert!(!corpse.has_decayed(10 + CORPSE_DECAY_TURNS - 1));
+        assert!(corpse.has_decayed(10 + CORPSE_DECAY_TURNS));
+
+        let risen = corpse.raise();
+        assert_eq!(risen.name, "Risen Goblin");
+        assert_eq!(risen.enemy_type, EnemyType::Skeleton);
+        assert_eq!(risen.level, 1);
+    }
+}
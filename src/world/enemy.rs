@@ -1,10 +1,168 @@
 use crate::character::Stats;
-use crate::item::Item;
+use crate::combat::{damage_after_defense, Affinity, Combatant, DamageType};
+use crate::game::energy;
+use crate::item::consumable::Material;
+use crate::item::{Consumable, Equipment, Item};
+use crate::world::{Alertness, Chest, DungeonType, Position};
+use rand::distributions::{Distribution, WeightedIndex};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::ops::Range;
 
+/// Chance that an enemy's wielded weapon is left behind when it dies, on
+/// top of its usual loot
+pub const WIELDED_DROP_CHANCE: f64 = 0.4;
+
+/// Chance a magical enemy leaves Arcane Dust behind when it dies
+pub const ARCANE_DUST_DROP_CHANCE: f64 = 0.5;
+
+/// Chance an enemy leaves a crafting material behind when it dies, if its
+/// type has one
+pub const MATERIAL_DROP_CHANCE: f64 = 0.3;
+
+/// Tiles a ranged enemy tries to keep between itself and the player
+pub const KITE_DISTANCE: i32 = 3;
+
+/// Share a pack leader's stats and rewards are raised by
+pub const LEADER_STAT_BONUS: f32 = 0.3;
+
+/// How far a pack leader's shouts carry to the enemies in its room
+pub const LEADER_AURA_RADIUS: i32 = 6;
+
+/// Attack a pack leader lends every enemy within its aura
+pub const LEADER_RALLY_BONUS: i32 = 1;
+
+/// Turns a pack spends fleeing the player once its leader falls
+pub const PANIC_TURNS: u32 = 4;
+
+/// How an enemy that fights from a distance shoots while the player explores
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangedProfile {
+    /// Farthest it shoots, in tiles
+    pub range: i32,
+    /// Damage a shot does before the player's defense
+    pub damage: i32,
+    /// Turns it spends readying another shot after one
+    pub cooldown: u32,
+}
+
+/// Broad family an enemy belongs to, each with its own mechanical quirks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum EnemyKind {
+    /// Fast creatures that move twice as often as most
+    Beast,
+    /// Immune to poison, weak to holy power
+    Undead,
+    /// Carry and drop equipment
+    Humanoid,
+    /// Resist their own element and are weak to its opposite
+    Elemental,
+    /// Magical constructs, immune to poison
+    Construct,
+}
+
+impl EnemyKind {
+    /// Relative spawn weight of this kind in the given dungeon type
+    pub fn spawn_weight(&self, dungeon_type: DungeonType) -> u32 {
+        match (dungeon_type, self) {
+            (DungeonType::Ruins, EnemyKind::Undead) => 6,
+            (DungeonType::Ruins, EnemyKind::Construct) => 3,
+            (DungeonType::Ruins, _) => 2,
+            (DungeonType::Forest, EnemyKind::Beast) => 5,
+            (DungeonType::Forest, EnemyKind::Humanoid) => 4,
+            (DungeonType::Forest, _) => 2,
+            (DungeonType::Mountain, EnemyKind::Humanoid) => 5,
+            (DungeonType::Mountain, EnemyKind::Beast) => 4,
+            (DungeonType::Mountain, _) => 2,
+            (DungeonType::Cavern, EnemyKind::Beast | EnemyKind::Elemental) => 6,
+            (DungeonType::Cavern, _) => 2,
+        }
+    }
+
+    /// How fast enemies of this kind act while the player explores
+    pub fn speed(&self) -> u32 {
+        match self {
+            EnemyKind::Beast => 2 * energy::NORMAL_SPEED,
+            _ => energy::NORMAL_SPEED,
+        }
+    }
+
+    /// Chance of an enemy of this kind spawning with a weapon in hand:
+    /// humanoids usually do and skeletons and ghosts sometimes, more often
+    /// the stronger they are. Beasts, elementals and constructs never do.
+    pub fn wield_chance(&self, level: u32) -> f64 {
+        let base = match self {
+            EnemyKind::Humanoid => 0.5,
+            EnemyKind::Undead => 0.25,
+            _ => return 0.0,
+        };
+        (base + level as f64 * 0.02).min(0.9)
+    }
+
+    /// How every enemy of this kind reacts to the given damage type
+    pub fn affinity(&self, damage_type: DamageType) -> Affinity {
+        match (self, damage_type) {
+            (EnemyKind::Undead | EnemyKind::Construct, DamageType::Poison) => Affinity::Immune,
+            (EnemyKind::Undead, DamageType::Holy) => Affinity::Weak,
+            _ => Affinity::Neutral,
+        }
+    }
+}
+
+impl fmt::Display for EnemyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnemyKind::Beast => write!(f, "Beast"),
+            EnemyKind::Undead => write!(f, "Undead"),
+            EnemyKind::Humanoid => write!(f, "Humanoid"),
+            EnemyKind::Elemental => write!(f, "Elemental"),
+            EnemyKind::Construct => write!(f, "Construct"),
+        }
+    }
+}
+
+/// Element of an elemental enemy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Element {
+    Fire,
+    Water,
+    Earth,
+    Air,
+}
+
+impl Element {
+    pub fn iter() -> impl Iterator<Item = Element> {
+        [Element::Fire, Element::Water, Element::Earth, Element::Air].into_iter()
+    }
+
+    /// How an elemental of this element reacts to the given damage type
+    pub fn affinity(&self, damage_type: DamageType) -> Affinity {
+        match (self, damage_type) {
+            (Element::Fire, DamageType::Fire)
+            | (Element::Water, DamageType::Ice)
+            | (Element::Earth, DamageType::Physical)
+            | (Element::Air, DamageType::Lightning) => Affinity::Resistant,
+            (Element::Fire, DamageType::Ice)
+            | (Element::Water, DamageType::Lightning)
+            | (Element::Earth, DamageType::Ice)
+            | (Element::Air, DamageType::Fire) => Affinity::Weak,
+            _ => Affinity::Neutral,
+        }
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Element::Fire => write!(f, "Fire"),
+            Element::Water => write!(f, "Water"),
+            Element::Earth => write!(f, "Earth"),
+            Element::Air => write!(f, "Air"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EnemyType {
     Goblin,
@@ -18,9 +176,88 @@ pub enum EnemyType {
     Golem,
     DarkMage,
     AncientGuardian,
+    GoblinArcher,
+    CaveShaman,
+    /// Lies in wait as a chest, and never wanders in on its own
+    Mimic,
+}
+
+impl fmt::Display for EnemyType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnemyType::Goblin => write!(f, "Goblin"),
+            EnemyType::Orc => write!(f, "Orc"),
+            EnemyType::Skeleton => write!(f, "Skeleton"),
+            EnemyType::Ghost => write!(f, "Ghost"),
+            EnemyType::Slime => write!(f, "Slime"),
+            EnemyType::Drake => write!(f, "Drake"),
+            EnemyType::Troll => write!(f, "Troll"),
+            EnemyType::Elemental => write!(f, "Elemental"),
+            EnemyType::Golem => write!(f, "Golem"),
+            EnemyType::DarkMage => write!(f, "Dark Mage"),
+            EnemyType::AncientGuardian => write!(f, "Ancient Guardian"),
+            EnemyType::GoblinArcher => write!(f, "Goblin Archer"),
+            EnemyType::CaveShaman => write!(f, "Cave Shaman"),
+            EnemyType::Mimic => write!(f, "Mimic"),
+        }
+    }
 }
 
 impl EnemyType {
+    pub fn all() -> [EnemyType; 14] {
+        [
+            EnemyType::Goblin,
+            EnemyType::Orc,
+            EnemyType::Skeleton,
+            EnemyType::Ghost,
+            EnemyType::Slime,
+            EnemyType::Drake,
+            EnemyType::Troll,
+            EnemyType::Elemental,
+            EnemyType::Golem,
+            EnemyType::DarkMage,
+            EnemyType::AncientGuardian,
+            EnemyType::GoblinArcher,
+            EnemyType::CaveShaman,
+            EnemyType::Mimic,
+        ]
+    }
+
+    pub fn kind(&self) -> EnemyKind {
+        match self {
+            EnemyType::Goblin
+            | EnemyType::Orc
+            | EnemyType::Troll
+            | EnemyType::DarkMage
+            | EnemyType::GoblinArcher
+            | EnemyType::CaveShaman => EnemyKind::Humanoid,
+            EnemyType::Skeleton | EnemyType::Ghost => EnemyKind::Undead,
+            EnemyType::Slime | EnemyType::Drake | EnemyType::Mimic => EnemyKind::Beast,
+            EnemyType::Elemental => EnemyKind::Elemental,
+            EnemyType::Golem | EnemyType::AncientGuardian => EnemyKind::Construct,
+        }
+    }
+
+    /// Picks a level-appropriate enemy type, weighted by how common its kind
+    /// is in the given dungeon type
+    pub fn choose<R: Rng + ?Sized>(
+        level: u32,
+        dungeon_type: DungeonType,
+        rng: &mut R,
+    ) -> Option<Self> {
+        let candidates: Vec<EnemyType> = EnemyType::all()
+            .into_iter()
+            .filter(|e_type| e_type.get_level_range().contains(&level))
+            .collect();
+
+        let weights = candidates
+            .iter()
+            .map(|e_type| e_type.kind().spawn_weight(dungeon_type));
+        let dist = WeightedIndex::new(weights).ok()?;
+
+        Some(candidates[dist.sample(rng)].clone())
+    }
+
     pub fn get_base_stats(&self) -> Stats {
         let mut stats = Stats::new();
 
@@ -102,6 +339,27 @@ impl EnemyType {
                 stats.set_constitution(12);
                 stats.set_wisdom(8);
             }
+            EnemyType::GoblinArcher => {
+                stats.set_strength(2);
+                stats.set_intelligence(2);
+                stats.set_dexterity(7);
+                stats.set_constitution(3);
+                stats.set_wisdom(3);
+            }
+            EnemyType::CaveShaman => {
+                stats.set_strength(2);
+                stats.set_intelligence(7);
+                stats.set_dexterity(4);
+                stats.set_constitution(4);
+                stats.set_wisdom(6);
+            }
+            EnemyType::Mimic => {
+                stats.set_strength(7);
+                stats.set_intelligence(1);
+                stats.set_dexterity(3);
+                stats.set_constitution(7);
+                stats.set_wisdom(2);
+            }
         }
 
         stats
@@ -120,9 +378,17 @@ impl EnemyType {
             EnemyType::Golem => "A massive construct of stone or metal, brought to life by magic.",
             EnemyType::DarkMage => "A corrupted spellcaster wielding forbidden magic.",
             EnemyType::AncientGuardian => "A powerful entity created to protect ancient treasures.",
+            EnemyType::GoblinArcher => "A goblin with a short bow, happiest at a distance.",
+            EnemyType::CaveShaman => "A hunched mystic who hurls lightning from the shadows.",
+            EnemyType::Mimic => "A chest that isn't, its lid lined with teeth.",
         }
     }
 
+    /// "goblins", "dark mages": a pack of this type, as the log names it
+    pub fn plural(&self) -> String {
+        format!("{}s", self.to_string().to_lowercase())
+    }
+
     pub fn get_level_range(&self) -> Range<u32> {
         match self {
             EnemyType::Goblin | EnemyType::Skeleton | EnemyType::Slime => 1..6,
@@ -131,6 +397,70 @@ impl EnemyType {
             EnemyType::Elemental | EnemyType::Golem => 10..17,
             EnemyType::DarkMage => 14..21,
             EnemyType::AncientGuardian => 18..31,
+            EnemyType::GoblinArcher => 2..8,
+            EnemyType::CaveShaman => 5..12,
+            // Only ever found among the chests
+            EnemyType::Mimic => 0..0,
+        }
+    }
+
+    /// How enemies of this type shoot at the given level, if they fight
+    /// from a distance at all
+    pub fn ranged_profile(&self, level: u32) -> Option<RangedProfile> {
+        let (range, damage, cooldown) = match self {
+            EnemyType::GoblinArcher => (6, 3, 2),
+            EnemyType::CaveShaman => (5, 5, 3),
+            _ => return None,
+        };
+        Some(RangedProfile {
+            range,
+            damage: damage + level as i32 / 2,
+            cooldown,
+        })
+    }
+
+    /// What a ranged enemy of this type does the turn before it shoots, so
+    /// the player can get out of sight: "draws back its bowstring"
+    pub fn takes_aim(&self) -> &'static str {
+        match self {
+            EnemyType::CaveShaman => "raises its staff and starts to chant",
+            _ => "draws back its bowstring",
+        }
+    }
+
+    /// The crafting material enemies of this type can leave behind: herbs
+    /// from foragers, iron from the armored and the built, crystal dust
+    /// from the spirits and spellcasters
+    pub fn material(&self) -> Option<Material> {
+        match self {
+            EnemyType::Goblin | EnemyType::GoblinArcher | EnemyType::Slime => Some(Material::Herbs),
+            EnemyType::Orc
+            | EnemyType::Skeleton
+            | EnemyType::Troll
+            | EnemyType::Golem
+            | EnemyType::AncientGuardian => Some(Material::IronShard),
+            EnemyType::Ghost
+            | EnemyType::Elemental
+            | EnemyType::DarkMage
+            | EnemyType::CaveShaman => Some(Material::CrystalDust),
+            EnemyType::Drake | EnemyType::Mimic => None,
+        }
+    }
+
+    /// Whether enemies of this type work magic, and so can leave Arcane
+    /// Dust behind
+    pub fn is_magical(&self) -> bool {
+        matches!(
+            self,
+            EnemyType::Elemental | EnemyType::DarkMage | EnemyType::CaveShaman
+        )
+    }
+
+    /// What a ranged enemy of this type shoots: "An arrow"
+    pub fn projectile(&self) -> &'static str {
+        match self {
+            EnemyType::CaveShaman => "A crackling bolt",
+            _ => "An arrow",
         }
     }
 }
@@ -139,6 +469,9 @@ impl EnemyType {
 pub struct Enemy {
     pub name: String,
     pub enemy_type: EnemyType,
+    pub kind: EnemyKind,
+    #[serde(default)]
+    pub element: Option<Element>,
     pub level: u32,
     pub stats: Stats,
     pub health: i32,
@@ -146,6 +479,57 @@ pub struct Enemy {
     pub experience_reward: u32,
     pub gold_reward: u32,
     pub item_drop_chance: f32,
+    /// Turns this enemy still loses to being stunned
+    #[serde(default)]
+    pub stunned_turns: u32,
+    /// Turns this enemy still spends catching its breath after the player
+    /// fled from it, neither chasing nor wandering
+    #[serde(default)]
+    pub winded_turns: u32,
+    #[serde(default)]
+    pub alertness: Alertness,
+    /// Where the enemy last heard or saw the player
+    #[serde(default)]
+    pub last_known: Option<Position>,
+    /// Energy stored towards the enemy's next action while the player
+    /// explores
+    #[serde(default)]
+    pub energy: u32,
+    /// Turns this enemy stays slowed
+    #[serde(default)]
+    pub slowed_turns: u32,
+    /// The weapon the enemy fights with, adding to its attacks and
+    /// sometimes dropped when it dies
+    #[serde(default)]
+    pub wielded: Option<Equipment>,
+    /// How it shoots from a distance, for archers and the like
+    #[serde(default)]
+    pub ranged: Option<RangedProfile>,
+    /// Turns until a ranged enemy can shoot again
+    #[serde(default)]
+    pub reload_turns: u32,
+    /// Whether a ranged enemy has taken aim and shoots on its next turn
+    #[serde(default)]
+    pub taking_aim: bool,
+    /// Whether the player has struck it from range, so that closing in on
+    /// it opens the fight with an opening strike
+    #[serde(default)]
+    pub struck_from_range: bool,
+    /// Whether it leads the pack it was placed with
+    #[serde(default)]
+    pub leader: bool,
+    /// How far a leader's aura reaches; 0 for the rest
+    #[serde(default)]
+    pub aura_radius: i32,
+    /// Whether a living leader nearby is lending it attack
+    #[serde(default)]
+    pub rallied: bool,
+    /// Turns this enemy still spends fleeing the player after its leader fell
+    #[serde(default)]
+    pub panicked_turns: u32,
+    /// Items it always drops when it dies, such as a mimic's hoard
+    #[serde(default)]
+    pub carried: Vec<Item>,
 }
 
 impl Enemy {
@@ -178,14 +562,14 @@ impl Enemy {
             }
         }
 
-        let max_health = 10 + stats.constitution * 5;
+        let max_health = stats.max_health();
 
         // Calculate rewards based on level and enemy type
         let experience_reward = level * 25
             + match enemy_type {
                 EnemyType::AncientGuardian | EnemyType::DarkMage => 100,
                 EnemyType::Golem | EnemyType::Elemental | EnemyType::Drake => 60,
-                EnemyType::Troll | EnemyType::Ghost => 40,
+                EnemyType::Troll | EnemyType::Ghost | EnemyType::Mimic => 40,
                 _ => 20,
             };
 
@@ -193,7 +577,7 @@ impl Enemy {
             + match enemy_type {
                 EnemyType::AncientGuardian | EnemyType::DarkMage => 50,
                 EnemyType::Golem | EnemyType::Elemental | EnemyType::Drake => 30,
-                EnemyType::Troll | EnemyType::Ghost => 20,
+                EnemyType::Troll | EnemyType::Ghost | EnemyType::Mimic => 20,
                 _ => 10,
             };
 
@@ -203,13 +587,16 @@ impl Enemy {
             + match enemy_type {
                 EnemyType::AncientGuardian | EnemyType::DarkMage => 0.4,
                 EnemyType::Golem | EnemyType::Elemental | EnemyType::Drake => 0.25,
-                EnemyType::Troll | EnemyType::Ghost => 0.15,
+                EnemyType::Troll | EnemyType::Ghost | EnemyType::Mimic => 0.15,
                 _ => 0.05,
             };
 
         Enemy {
             name,
+            kind: enemy_type.kind(),
+            ranged: enemy_type.ranged_profile(level),
             enemy_type,
+            element: None,
             level,
             stats,
             health: max_health,
@@ -217,39 +604,136 @@ impl Enemy {
             experience_reward,
             gold_reward,
             item_drop_chance,
+            stunned_turns: 0,
+            winded_turns: 0,
+            alertness: Alertness::Unaware,
+            last_known: None,
+            energy: 0,
+            slowed_turns: 0,
+            wielded: None,
+            reload_turns: 0,
+            taking_aim: false,
+            struck_from_range: false,
+            leader: false,
+            aura_radius: 0,
+            rallied: false,
+            panicked_turns: 0,
+            carried: Vec::new(),
         }
     }
 
-    pub fn generate_random(level: u32, difficulty: u32) -> Self {
-        let mut rng = rand::thread_rng();
+    /// A mimic of `level` that was passing as the chest `hoard`. It carries
+    /// everything the chest held, and always drops something more.
+    pub fn mimic(level: u32, hoard: Chest) -> Self {
+        let mut mimic = Enemy::new("Mimic".to_string(), EnemyType::Mimic, level);
+        mimic.gold_reward += hoard.gold;
+        mimic.carried = hoard.items;
+        mimic.item_drop_chance = 1.0;
+        mimic
+    }
 
-        // Determine what enemy types are appropriate for this level
-        let possible_types: Vec<EnemyType> = vec![
-            EnemyType::Goblin,
-            EnemyType::Orc,
-            EnemyType::Skeleton,
-            EnemyType::Ghost,
-            EnemyType::Slime,
-            EnemyType::Drake,
-            EnemyType::Troll,
-            EnemyType::Elemental,
-            EnemyType::Golem,
-            EnemyType::DarkMage,
-            EnemyType::AncientGuardian,
-        ]
-        .into_iter()
-        .filter(|e_type| {
-            let range = e_type.get_level_range();
-            range.contains(&level)
-        })
-        .collect();
+    /// Makes this enemy its pack's leader: "Pack Leader Sneaky Goblin",
+    /// with its stats and rewards raised and an aura that rallies the
+    /// enemies around it
+    pub fn promote_to_leader(&mut self) {
+        self.scale_strength(1.0 + LEADER_STAT_BONUS);
+        self.experience_reward =
+            (self.experience_reward as f32 * (1.0 + LEADER_STAT_BONUS)).round() as u32;
+        self.name = format!("Pack Leader {}", self.name);
+        self.leader = true;
+        self.aura_radius = LEADER_AURA_RADIUS;
+    }
 
-        if possible_types.is_empty() {
-            // Fallback to basic enemies if no appropriate types
-            return Enemy::new("Goblin".to_string(), EnemyType::Goblin, level);
+    /// Scales this enemy's stats, and the gold and chance of an item it
+    /// carries, by `factor`, leaving it at full health
+    pub fn scale_strength(&mut self, factor: f32) {
+        let scale = |value: i32| (value as f32 * factor).round() as i32;
+        let stats = &mut self.stats;
+        stats.strength = scale(stats.strength);
+        stats.intelligence = scale(stats.intelligence);
+        stats.dexterity = scale(stats.dexterity);
+        stats.constitution = scale(stats.constitution);
+        stats.wisdom = scale(stats.wisdom);
+        self.max_health = stats.max_health();
+        self.health = self.max_health;
+        self.gold_reward = scale(self.gold_reward as i32) as u32;
+        self.item_drop_chance *= factor;
+    }
+
+    /// Sends this enemy fleeing for at least `turns` turns, no longer
+    /// rallied by anyone
+    pub fn panic(&mut self, turns: u32) {
+        self.panicked_turns = self.panicked_turns.max(turns);
+        self.rallied = false;
+    }
+
+    /// Uses up one panicked turn. Returns whether the enemy was panicking
+    /// and so flees this turn.
+    pub fn flee_in_panic(&mut self) -> bool {
+        if self.panicked_turns == 0 {
+            return false;
         }
+        self.panicked_turns -= 1;
+        true
+    }
 
-        let enemy_type = possible_types[rng.gen_range(0..possible_types.len())].clone();
+    /// Hears a noise made at `origin`; an enemy that isn't already chasing
+    /// the player goes to look
+    pub fn hear_noise(&mut self, origin: Position) {
+        if self.alertness != Alertness::Alerted {
+            self.alertness = Alertness::Suspicious;
+            self.last_known = Some(origin);
+        }
+    }
+
+    /// Looks around for the player at `player`: a suspicious enemy that
+    /// spots them gives chase, and a chasing one that loses sight of them
+    /// searches where it last saw them
+    pub fn look_for_player(&mut self, sees_player: bool, player: Position) {
+        match (self.alertness, sees_player) {
+            (Alertness::Suspicious | Alertness::Alerted, true) => {
+                self.alertness = Alertness::Alerted;
+                self.last_known = Some(player);
+            }
+            (Alertness::Alerted, false) => self.alertness = Alertness::Suspicious,
+            _ => {}
+        }
+    }
+
+    /// Knows exactly where the player is, as once a fight has started
+    pub fn alert(&mut self, player: Position) {
+        self.alertness = Alertness::Alerted;
+        self.last_known = Some(player);
+    }
+
+    /// Gives up a search that found nothing
+    pub fn calm_down(&mut self) {
+        self.alertness = Alertness::Unaware;
+        self.last_known = None;
+    }
+
+    /// Where the enemy heads this turn: after the player while chasing,
+    /// to what it heard while suspicious, or wherever it wanders if None
+    pub fn destination(&self, player: Position) -> Option<Position> {
+        match self.alertness {
+            Alertness::Unaware => None,
+            Alertness::Suspicious => self.last_known,
+            Alertness::Alerted => Some(player),
+        }
+    }
+
+    pub fn generate_with_rng<R: Rng + ?Sized>(
+        level: u32,
+        difficulty: u32,
+        dungeon_type: DungeonType,
+        rng: &mut R,
+    ) -> Self {
+        // Determine what enemy types are appropriate for this level and dungeon
+        let Some(enemy_type) = EnemyType::choose(level, dungeon_type, rng) else {
+            // Fallback to basic enemies if no appropriate types
+            return Enemy::new("Goblin".to_string(), EnemyType::Goblin, level);
+        };
+        let mut element = None;
 
         // Generate name with some variety
         let name = match enemy_type {
@@ -282,8 +766,11 @@ impl Enemy {
                 format!("{} Troll", prefixes[rng.gen_range(0..prefixes.len())])
             }
             EnemyType::Elemental => {
-                let elements = ["Fire", "Water", "Earth", "Air"];
-                format!("{} Elemental", elements[rng.gen_range(0..elements.len())])
+                let chosen = Element::iter()
+                    .nth(rng.gen_range(0..4))
+                    .unwrap_or(Element::Fire);
+                element = Some(chosen);
+                format!("{chosen} Elemental")
             }
             EnemyType::Golem => {
                 let materials = ["Stone", "Iron", "Crystal", "Ancient"];
@@ -297,49 +784,136 @@ impl Enemy {
                 let prefixes = ["Eternal", "Forgotten", "Colossal", "Primordial"];
                 format!("{} Guardian", prefixes[rng.gen_range(0..prefixes.len())])
             }
+            EnemyType::GoblinArcher => {
+                let prefixes = ["Sharp-eyed", "Skulking", "Keen", "Twitchy"];
+                format!(
+                    "{} Goblin Archer",
+                    prefixes[rng.gen_range(0..prefixes.len())]
+                )
+            }
+            EnemyType::CaveShaman => {
+                let prefixes = ["Chanting", "Bone-rattling", "Mad", "Hooded"];
+                format!("{} Cave Shaman", prefixes[rng.gen_range(0..prefixes.len())])
+            }
+            EnemyType::Mimic => "Mimic".to_string(),
         };
 
-        // Adjust level based on difficulty
-        let adjusted_level = level + rng.gen_range(0..=difficulty.min(5));
+        // Adjust level based on difficulty; harder dungeons raise the floor
+        // as well as the spread
+        let adjusted_level = level + difficulty / 2 + rng.gen_range(0..=difficulty.min(5));
 
-        Enemy::new(name, enemy_type, adjusted_level)
+        let mut enemy = Enemy::new(name, enemy_type, adjusted_level);
+        enemy.element = element;
+        if rng.gen_bool(enemy.kind.wield_chance(adjusted_level)) {
+            enemy.wielded = Some(Equipment::generate_weapon_with_rng(adjusted_level, 0, rng));
+        }
+        enemy
     }
 
-    pub fn attack_damage(&self) -> i32 {
-        let base_damage = match self.enemy_type {
-            EnemyType::Goblin | EnemyType::Ghost => self.stats.dexterity,
-            EnemyType::DarkMage | EnemyType::Elemental => self.stats.intelligence,
-            _ => self.stats.strength,
-        };
+    /// How this enemy reacts to the given damage type. Elementals follow
+    /// their element, a few types have their own weaknesses and the rest
+    /// fall back to their kind.
+    pub fn affinity(&self, damage_type: DamageType) -> Affinity {
+        if let Some(element) = self.element {
+            return element.affinity(damage_type);
+        }
 
-        let level_bonus = self.level as i32 / 2;
+        match (&self.enemy_type, damage_type) {
+            (EnemyType::Drake, DamageType::Fire) => Affinity::Resistant,
+            (EnemyType::Drake, DamageType::Ice)
+            | (EnemyType::Troll, DamageType::Fire)
+            | (EnemyType::Slime, DamageType::Lightning)
+            | (EnemyType::Golem, DamageType::Lightning)
+            | (EnemyType::DarkMage, DamageType::Holy) => Affinity::Weak,
+            _ => self.kind.affinity(damage_type),
+        }
+    }
 
-        base_damage + level_bonus
+    /// Multiplier applied to damage of the given type
+    pub fn damage_multiplier(&self, damage_type: DamageType) -> f32 {
+        self.affinity(damage_type).multiplier()
     }
 
-    pub fn defense(&self) -> i32 {
-        let base_defense = self.stats.constitution / 2;
-        let level_bonus = self.level as i32 / 3;
+    /// Stuns this enemy for at least `turns` turns
+    pub fn stun(&mut self, turns: u32) {
+        self.stunned_turns = self.stunned_turns.max(turns);
+    }
 
-        base_defense + level_bonus
+    /// Uses up one stunned turn. Returns whether the enemy was stunned and so
+    /// loses this turn.
+    pub fn lose_turn_to_stun(&mut self) -> bool {
+        if self.stunned_turns == 0 {
+            return false;
+        }
+        self.stunned_turns -= 1;
+        true
+    }
+
+    /// Leaves this enemy slowed for at least `turns` turns
+    pub fn slow(&mut self, turns: u32) {
+        self.slowed_turns = self.slowed_turns.max(turns);
+    }
+
+    /// How fast the enemy acts while the player explores
+    pub fn speed(&self) -> u32 {
+        let speed = self.kind.speed();
+        if self.slowed_turns > 0 {
+            energy::slowed(speed)
+        } else {
+            speed
+        }
+    }
+
+    /// Leaves this enemy winded for at least `turns` turns
+    pub fn wind(&mut self, turns: u32) {
+        self.winded_turns = self.winded_turns.max(turns);
+    }
+
+    /// Uses up one winded turn. Returns whether the enemy was winded and so
+    /// stays put this turn.
+    pub fn catch_breath(&mut self) -> bool {
+        if self.winded_turns == 0 {
+            return false;
+        }
+        self.winded_turns -= 1;
+        true
     }
 
-    pub fn take_damage(&mut self, amount: i32) -> i32 {
-        let defense = self.defense();
-        let damage_taken = (amount - defense).max(1); // Always take at least 1 damage
+    /// Damage a hit of `amount` would do once defense and resistances are
+    /// applied, without landing it
+    pub fn damage_from(&self, amount: i32, damage_type: DamageType) -> i32 {
+        let damage = damage_after_defense(amount, self.defense()); // Resistances apply after the minimum
+        (damage as f32 * self.damage_multiplier(damage_type)).round() as i32
+    }
+
+    /// Applies damage after defense and this enemy's affinity to `damage_type`
+    pub fn take_damage_from(&mut self, amount: i32, damage_type: DamageType) -> i32 {
+        let damage_taken = self.damage_from(amount, damage_type);
 
         self.health -= damage_taken;
 
         damage_taken
     }
 
-    pub fn is_alive(&self) -> bool {
-        self.health > 0
+    /// "wielding Rusty Sword", for an enemy with a weapon in hand
+    pub fn wielding(&self) -> Option<String> {
+        self.wielded
+            .as_ref()
+            .map(|weapon| format!("wielding {}", weapon.display_name()))
     }
 
-    pub fn get_drops(&self) -> (u32, u32, Option<Item>) {
-        let mut rng = rand::thread_rng();
+    /// "Sneaky Goblin (wielding Rusty Sword)", or just the name of an enemy
+    /// without a weapon
+    pub fn described_name(&self) -> String {
+        match self.wielding() {
+            Some(wielding) => format!("{} ({wielding})", self.name),
+            None => self.name.clone(),
+        }
+    }
 
+    /// Experience, gold and items for defeating this enemy: its wielded
+    /// weapon, some of the time, and whatever loot it carried
+    pub fn get_drops_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> (u32, u32, Vec<Item>) {
         // Randomize gold and experience a bit
         let exp_variation = rng.gen_range(0.8..1.2);
         let gold_variation = rng.gen_range(0.8..1.2);
@@ -347,14 +921,34 @@ impl Enemy {
         let experience = (self.experience_reward as f32 * exp_variation) as u32;
         let gold = (self.gold_reward as f32 * gold_variation) as u32;
 
-        // Determine if an item drops
-        let item = if rng.gen_bool(self.item_drop_chance as f64) {
-            Some(Item::generate_random(self.level))
-        } else {
-            None
-        };
+        let mut items = Vec::new();
+        if let Some(weapon) = &self.wielded {
+            if rng.gen_bool(WIELDED_DROP_CHANCE) {
+                items.push(Item::Equipment(weapon.clone()));
+            }
+        }
 
-        (experience, gold, item)
+        // Determine if an item drops; humanoids carry gear, everything else
+        // only leaves consumables behind
+        if rng.gen_bool(self.item_drop_chance.clamp(0.0, 1.0) as f64) {
+            items.push(match self.kind {
+                EnemyKind::Humanoid => {
+                    Item::Equipment(Equipment::generate_with_rng(self.level, 0, rng))
+                }
+                _ => Item::Consumable(Consumable::generate_with_rng(self.level, rng)),
+            });
+        }
+        if self.enemy_type.is_magical() && rng.gen_bool(ARCANE_DUST_DROP_CHANCE) {
+            items.push(Item::Consumable(Consumable::arcane_dust()));
+        }
+        if let Some(material) = self.enemy_type.material() {
+            if rng.gen_bool(MATERIAL_DROP_CHANCE) {
+                items.push(Item::Consumable(Consumable::material(material)));
+            }
+        }
+        items.extend(self.carried.iter().cloned());
+
+        (experience, gold, items)
     }
 }
 
@@ -371,3 +965,312 @@ impl fmt::Display for Enemy {
         )
     }
 }
+
+impl Combatant for Enemy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    fn health(&self) -> i32 {
+        self.health
+    }
+
+    fn attack_damage(&self) -> i32 {
+        let base_damage = match self.enemy_type {
+            EnemyType::Goblin | EnemyType::Ghost | EnemyType::GoblinArcher => self.stats.dexterity,
+            EnemyType::DarkMage | EnemyType::Elemental | EnemyType::CaveShaman => {
+                self.stats.intelligence
+            }
+            _ => self.stats.strength,
+        };
+
+        let level_bonus = self.level as i32 / 2;
+        let weapon_damage = self
+            .wielded
+            .as_ref()
+            .map_or(0, |weapon| weapon.effective_power());
+
+        base_damage + level_bonus + weapon_damage + self.rally_bonus()
+    }
+
+    fn weapon_damage(&self) -> i32 {
+        self.wielded
+            .as_ref()
+            .map_or(0, |weapon| weapon.effective_power())
+    }
+
+    fn rally_bonus(&self) -> i32 {
+        if self.rallied {
+            LEADER_RALLY_BONUS
+        } else {
+            0
+        }
+    }
+
+    fn defense(&self) -> i32 {
+        let base_defense = self.stats.base_defense();
+        let level_bonus = self.level as i32 / 3;
+
+        base_defense + level_bonus
+    }
+
+    fn take_hit(&mut self, amount: i32, damage_type: DamageType) -> i32 {
+        self.take_damage_from(amount, damage_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashMap;
+
+    fn enemy_of(enemy_type: EnemyType) -> Enemy {
+        // Level 1 keeps defense low so the multiplier math is easy to follow
+        let mut enemy = Enemy::new("Test".to_string(), enemy_type, 1);
+        enemy.health = 1000;
+        enemy
+    }
+
+    #[test]
+    fn test_a_mimic_drops_its_hoard_and_more() {
+        let hoard = Chest {
+            items: vec![Item::Consumable(Consumable::torch())],
+            gold: 40,
+            ..Chest::default()
+        };
+        let mimic = Enemy::mimic(3, hoard);
+        assert_eq!(
+            mimic.gold_reward,
+            Enemy::new("Mimic".to_string(), EnemyType::Mimic, 3).gold_reward + 40
+        );
+        let mut rng = StdRng::seed_from_u64(1);
+        let (_, _, items) = mimic.get_drops_with_rng(&mut rng);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].name(), "Torch");
+
+        // It never turns up on its own
+        for level in 0..40 {
+            assert!(!EnemyType::Mimic.get_level_range().contains(&level));
+        }
+    }
+
+    #[test]
+    fn test_undead_poison_immunity_and_holy_weakness() {
+        let mut skeleton = enemy_of(EnemyType::Skeleton);
+        let raw = 20 - skeleton.defense();
+
+        assert_eq!(skeleton.take_damage_from(20, DamageType::Poison), 0);
+        assert_eq!(skeleton.take_damage_from(20, DamageType::Physical), raw);
+        assert_eq!(skeleton.take_damage_from(20, DamageType::Holy), raw * 2);
+    }
+
+    #[test]
+    fn test_elemental_resists_own_element() {
+        let mut elemental = enemy_of(EnemyType::Elemental);
+        elemental.element = Some(Element::Fire);
+        let raw = 30 - elemental.defense();
+
+        assert_eq!(
+            elemental.take_damage_from(30, DamageType::Fire),
+            (raw as f32 * 0.5).round() as i32
+        );
+        assert_eq!(elemental.take_damage_from(30, DamageType::Ice), raw * 2);
+        assert_eq!(elemental.take_damage_from(30, DamageType::Lightning), raw);
+    }
+
+    #[test]
+    fn test_themed_affinities() {
+        let drake = enemy_of(EnemyType::Drake);
+        assert_eq!(drake.affinity(DamageType::Fire), Affinity::Resistant);
+        assert_eq!(drake.affinity(DamageType::Ice), Affinity::Weak);
+
+        let golem = enemy_of(EnemyType::Golem);
+        assert_eq!(golem.affinity(DamageType::Lightning), Affinity::Weak);
+        // Falls back to the Construct poison immunity
+        assert_eq!(golem.affinity(DamageType::Poison), Affinity::Immune);
+    }
+
+    #[test]
+    fn test_kinds_without_resistances() {
+        let goblin = enemy_of(EnemyType::Goblin);
+        assert_eq!(goblin.kind, EnemyKind::Humanoid);
+        for damage_type in DamageType::ELEMENTS {
+            assert_eq!(goblin.damage_multiplier(damage_type), 1.0);
+        }
+        assert_eq!(goblin.damage_multiplier(DamageType::Poison), 1.0);
+    }
+
+    fn kind_counts(dungeon_type: DungeonType, level: u32) -> HashMap<EnemyKind, u32> {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut counts = HashMap::new();
+        for _ in 0..2000 {
+            let enemy_type = EnemyType::choose(level, dungeon_type, &mut rng).unwrap();
+            *counts.entry(enemy_type.kind()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn test_ruins_favor_undead() {
+        // Level 4 offers Goblin, Orc (Humanoid), Skeleton, Ghost (Undead), Slime (Beast)
+        let counts = kind_counts(DungeonType::Ruins, 4);
+        let undead = counts[&EnemyKind::Undead];
+        assert!(undead > counts[&EnemyKind::Humanoid] * 2);
+        assert!(undead > counts[&EnemyKind::Beast] * 3);
+    }
+
+    #[test]
+    fn test_caverns_favor_beasts_and_elementals() {
+        // Level 12 offers Drake (Beast), Troll (Humanoid), Elemental, Golem (Construct)
+        let counts = kind_counts(DungeonType::Cavern, 12);
+        assert!(counts[&EnemyKind::Beast] > counts[&EnemyKind::Humanoid] * 2);
+        assert!(counts[&EnemyKind::Elemental] > counts[&EnemyKind::Construct] * 2);
+    }
+
+    #[test]
+    fn test_choose_respects_level_range() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..200 {
+            let enemy_type = EnemyType::choose(1, DungeonType::Forest, &mut rng).unwrap();
+            assert!(enemy_type.get_level_range().contains(&1));
+        }
+        assert!(EnemyType::choose(100, DungeonType::Forest, &mut rng).is_none());
+    }
+
+    fn armed_goblin() -> Enemy {
+        let mut goblin = enemy_of(EnemyType::Goblin);
+        goblin.wielded = Some(Equipment::weapon("Rusty Scimitar", 5));
+        goblin
+    }
+
+    #[test]
+    fn test_wielded_weapon_adds_to_attacks_and_is_named() {
+        let unarmed = enemy_of(EnemyType::Goblin);
+        let armed = armed_goblin();
+
+        assert_eq!(armed.attack_damage(), unarmed.attack_damage() + 5);
+        assert_eq!(armed.described_name(), "Test (wielding Rusty Scimitar)");
+        assert_eq!(unarmed.described_name(), "Test");
+        assert_eq!(unarmed.wielding(), None);
+    }
+
+    #[test]
+    fn test_wielded_weapon_drops_some_of_the_time() {
+        let mut rng = StdRng::seed_from_u64(9);
+        let mut goblin = armed_goblin();
+        // Leave the usual loot out of it, crafting materials included
+        goblin.item_drop_chance = 0.0;
+        let drops = |enemy: &Enemy, rng: &mut StdRng| {
+            let (_, _, mut items) = enemy.get_drops_with_rng(rng);
+            items.retain(|item| !matches!(item, Item::Consumable(c) if c.is_material()));
+            items
+        };
+
+        let rolls = 2000;
+        let dropped = (0..rolls)
+            .filter(|_| match drops(&goblin, &mut rng).as_slice() {
+                [] => false,
+                [Item::Equipment(weapon)] => weapon.name == "Rusty Scimitar",
+                _ => panic!("only the wielded weapon can drop"),
+            })
+            .count();
+        let rate = dropped as f64 / rolls as f64;
+        assert!((rate - WIELDED_DROP_CHANCE).abs() < 0.05, "{rate}");
+
+        // On top of the usual loot, not instead of it
+        goblin.item_drop_chance = 1.0;
+        let most = (0..50).map(|_| drops(&goblin, &mut rng).len()).max();
+        assert_eq!(most, Some(2));
+        assert!(drops(&enemy_of(EnemyType::Slime), &mut rng).len() <= 1);
+    }
+
+    #[test]
+    fn test_enemies_leave_the_materials_of_their_kind() {
+        let mut rng = StdRng::seed_from_u64(12);
+        let mut golem = enemy_of(EnemyType::Golem);
+        golem.item_drop_chance = 0.0;
+        let materials: Vec<Item> = (0..200)
+            .flat_map(|_| golem.get_drops_with_rng(&mut rng).2)
+            .collect();
+        let rate = materials.len() as f64 / 200.0;
+        assert!((rate - MATERIAL_DROP_CHANCE).abs() < 0.1, "{rate}");
+        assert!(materials.iter().all(|item| item.name() == "Iron Shard"));
+        assert_eq!(EnemyType::Mimic.material(), None);
+    }
+
+    #[test]
+    fn test_only_some_kinds_spawn_armed() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let enemies: Vec<Enemy> = (0..300)
+            .map(|_| Enemy::generate_with_rng(3, 0, DungeonType::Mountain, &mut rng))
+            .collect();
+
+        assert!(enemies
+            .iter()
+            .any(|enemy| enemy.kind == EnemyKind::Humanoid && enemy.wielded.is_some()));
+        for enemy in &enemies {
+            if let Some(weapon) = &enemy.wielded {
+                assert_eq!(weapon.slot, crate::item::EquipmentSlot::Weapon);
+            }
+            if matches!(enemy.kind, EnemyKind::Beast | EnemyKind::Elemental) {
+                assert!(enemy.wielded.is_none());
+            }
+        }
+        assert!(EnemyKind::Humanoid.wield_chance(20) > EnemyKind::Humanoid.wield_chance(1));
+        assert_eq!(EnemyKind::Construct.wield_chance(20), 0.0);
+    }
+
+    #[test]
+    fn test_wielded_weapon_survives_a_save() {
+        let goblin = armed_goblin();
+        let json = serde_json::to_string(&goblin).unwrap();
+        let restored: Enemy = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.wielded.unwrap().name, "Rusty Scimitar");
+
+        // Enemies saved before they carried weapons load unarmed
+        let mut old = serde_json::to_value(enemy_of(EnemyType::Orc)).unwrap();
+        old.as_object_mut().unwrap().remove("wielded");
+        let restored: Enemy = serde_json::from_value(old).unwrap();
+        assert!(restored.wielded.is_none());
+    }
+
+    #[test]
+    fn test_noise_and_sight_raise_alertness_and_searches_end() {
+        let mut goblin = enemy_of(EnemyType::Goblin);
+        let (noise, player) = (Position::new(4, 4), Position::new(6, 4));
+        assert_eq!(goblin.alertness, Alertness::Unaware);
+        assert_eq!(goblin.destination(player), None);
+
+        // Seeing the player isn't enough for an enemy that wasn't listening
+        goblin.look_for_player(true, player);
+        assert_eq!(goblin.alertness, Alertness::Unaware);
+
+        goblin.hear_noise(noise);
+        assert_eq!(goblin.alertness, Alertness::Suspicious);
+        assert_eq!(goblin.destination(player), Some(noise));
+
+        goblin.look_for_player(true, player);
+        assert_eq!(goblin.alertness, Alertness::Alerted);
+        assert_eq!(goblin.destination(player), Some(player));
+
+        // More noise doesn't distract a chasing enemy
+        goblin.hear_noise(noise);
+        assert_eq!(goblin.destination(player), Some(player));
+
+        // Losing sight sends it to where the player was last seen
+        let moved_on = Position::new(9, 9);
+        goblin.look_for_player(false, moved_on);
+        assert_eq!(goblin.alertness, Alertness::Suspicious);
+        assert_eq!(goblin.destination(moved_on), Some(player));
+
+        goblin.calm_down();
+        assert_eq!(goblin.alertness, Alertness::Unaware);
+        assert_eq!(goblin.last_known, None);
+    }
+}
@@ -3,7 +3,8 @@
 //! This module provides a centralized way to handle fog of war rendering, ensuring consistent
 //! behavior between GUI and terminal versions of the game.
 
-use crate::world::{Level, Position, Tile};
+use crate::render::glyph;
+use crate::world::{GlyphSet, Level, MapFeature, Position, Tile, TileType};
 use serde::{Deserialize, Serialize};
 
 /// Represents the visibility state of a tile from the player's perspective
@@ -18,7 +19,7 @@ pub enum VisibilityState {
 }
 
 /// Color information for rendering fog of war
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct FogColor {
     pub r: u8,
     pub g: u8,
@@ -52,6 +53,11 @@ impl FogColor {
         a: 255,
     };
 
+    /// An opaque color from its red, green and blue parts
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 255 }
+    }
+
     /// Create a dimmed version of this color
     pub fn dimmed(&self, factor: f32) -> Self {
         Self {
@@ -129,8 +135,8 @@ impl FogOfWar {
     ) -> FogRenderResult {
         match self.get_visibility_state(tile) {
             VisibilityState::Unexplored => {
-                // Hide walls (#) and floors (.) completely in unexplored areas
-                if base_character == '#' || base_character == '.' {
+                // Hide walls and floors completely in unexplored areas
+                if is_plain_ground(tile) {
                     FogRenderResult {
                         character: ' ',
                         color: Some(FogColor::BLACK),
@@ -152,9 +158,9 @@ impl FogOfWar {
             }
             VisibilityState::ExploredHidden => {
                 if self.config.show_explored_dimmed {
-                    // Hide walls (#) and floors (.) in explored but not visible areas
+                    // Hide walls and floors in explored but not visible areas
                     // This only affects the in-game map rendering
-                    if base_character == '#' || base_character == '.' {
+                    if is_plain_ground(tile) {
                         FogRenderResult {
                             character: ' ',
                             color: Some(self.config.unexplored_color),
@@ -187,23 +193,21 @@ impl FogOfWar {
         }
     }
 
-    /// Process a position on the map, handling entities and tiles
+    /// Process a position on the map, handling entities and tiles, drawn
+    /// with the glyphs of `glyphs`
     pub fn process_position(
         &self,
         level: &Level,
         pos: Position,
         player_pos: Position,
+        glyphs: GlyphSet,
     ) -> FogRenderResult {
         // Player is always visible
         if pos == player_pos {
+            let player = glyph(MapFeature::Player, glyphs);
             return FogRenderResult {
-                character: '@',
-                color: Some(FogColor {
-                    r: 255,
-                    g: 255,
-                    b: 0,
-                    a: 255,
-                }), // Yellow
+                character: player.symbol,
+                color: Some(player.color),
                 should_render: true,
             };
         }
@@ -218,97 +222,68 @@ impl FogOfWar {
         }
 
         let tile = &level.tiles[pos.y as usize][pos.x as usize];
-        let visibility_state = self.get_visibility_state(tile);
 
-        // For unexplored tiles, return early
-        if visibility_state == VisibilityState::Unexplored {
-            return self.process_tile(tile, ' ', Some(self.config.unexplored_color));
-        }
+        // Check for entities: anything on a visible tile, and enemies and
+        // loot glimpsed just past sight
+        let glimpsed = level.glimpsed_positions.contains(&pos);
+        if tile.visible || glimpsed {
+            let entity = if let Some(enemy) = level.enemies.get(&pos) {
+                Some(if enemy.leader {
+                    MapFeature::Leader
+                } else {
+                    MapFeature::Enemy(enemy.alertness)
+                })
+            } else if tile.visible && Some(pos) == level.companion_position {
+                Some(MapFeature::Companion)
+            } else if tile.visible && level.npcs.contains_key(&pos) {
+                Some(MapFeature::Npc)
+            } else if level.items.contains_key(&pos) {
+                Some(MapFeature::Item)
+            } else if level.gold.contains_key(&pos) {
+                Some(MapFeature::Gold)
+            } else if tile.visible && level.corpses.contains_key(&pos) {
+                Some(MapFeature::Corpse)
+            } else {
+                None
+            };
 
-        // Check for entities (only visible if tile is visible)
-        if tile.visible {
-            if level.enemies.contains_key(&pos) {
+            if let Some(feature) = entity {
+                let entity = glyph(feature, glyphs);
                 return FogRenderResult {
-                    character: 'E',
-                    color: Some(FogColor {
-                        r: 255,
-                        g: 0,
-                        b: 0,
-                        a: 255,
-                    }), // Red
+                    character: entity.symbol,
+                    color: Some(entity.color),
                     should_render: true,
                 };
             }
+        }
 
-            if level.items.contains_key(&pos) {
-                return FogRenderResult {
-                    character: '!',
-                    color: Some(FogColor {
-                        r: 0,
-                        g: 255,
-                        b: 255,
-                        a: 255,
-                    }), // Cyan
-                    should_render: true,
-                };
-            }
+        // For unexplored tiles, return early
+        if self.get_visibility_state(tile) == VisibilityState::Unexplored {
+            return self.process_tile(tile, ' ', Some(self.config.unexplored_color));
         }
 
         // Get base tile rendering info
-        let base_character = tile.tile_type.symbol();
-        let base_color = self.get_tile_color(&tile.tile_type);
+        let base = glyph(MapFeature::Tile(tile.tile_type), glyphs);
 
-        self.process_tile(tile, base_character, Some(base_color))
+        self.process_tile(tile, base.symbol, Some(base.color))
     }
+}
 
-    /// Get the base color for a tile type
-    fn get_tile_color(&self, tile_type: &crate::world::TileType) -> FogColor {
-        match tile_type {
-            crate::world::TileType::Wall => FogColor::GREY,
-            crate::world::TileType::Floor => FogColor::WHITE,
-            crate::world::TileType::Door => FogColor {
-                r: 139,
-                g: 69,
-                b: 19,
-                a: 255,
-            }, // Brown
-            crate::world::TileType::Chest => FogColor {
-                r: 255,
-                g: 215,
-                b: 0,
-                a: 255,
-            }, // Gold
-            crate::world::TileType::StairsDown | crate::world::TileType::StairsUp => {
-                FogColor {
-                    r: 0,
-                    g: 255,
-                    b: 0,
-                    a: 255,
-                } // Green
-            }
-            crate::world::TileType::Exit => FogColor {
-                r: 0,
-                g: 255,
-                b: 0,
-                a: 255,
-            }, // Green
-        }
-    }
+/// Walls and floors, which fog hides entirely once out of sight
+fn is_plain_ground(tile: &Tile) -> bool {
+    matches!(tile.tile_type, TileType::Wall | TileType::Floor)
 }
 
 /// Utility functions for different rendering backends
 impl FogOfWar {
     /// Convert FogColor to egui Color32 for GUI rendering
-    #[cfg(all(feature = "gui", target_os = "windows"))]
+    #[cfg(feature = "gui")]
     pub fn to_egui_color(color: &FogColor) -> egui::Color32 {
         egui::Color32::from_rgba_unmultiplied(color.r, color.g, color.b, color.a)
     }
 
     /// Convert FogColor to crossterm Color for terminal rendering
-    #[cfg(all(
-        not(all(feature = "gui", target_os = "windows")),
-        not(target_arch = "wasm32")
-    ))]
+    #[cfg(not(target_arch = "wasm32"))]
     pub fn to_terminal_color(color: &FogColor) -> crossterm::style::Color {
         crossterm::style::Color::Rgb {
             r: color.r,
@@ -0,0 +1,239 @@
+//! Building a dungeon's levels, all at once or a few at a time
+//!
+//! Every level of a dungeon is generated when the dungeon is, and no level
+//! depends on another. The builder draws a seed for each level from the
+//! dungeon's RNG before any is built, so a level comes out the same
+//! whichever order the levels are finished in. Natively they're built on a
+//! thread each; the web build can't start threads, so it builds one level
+//! per animation frame with `step` instead.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::{generate_reachable, Dungeon, DungeonType, Level};
+
+/// Everything needed to build one level, on whatever thread
+#[derive(Debug, Clone, Copy)]
+struct LevelPlan {
+    difficulty: u32,
+    level_num: u32,
+    dungeon_type: DungeonType,
+    is_final: bool,
+    seed: u64,
+}
+
+impl LevelPlan {
+    /// The level, and a warning for each try thrown away as cut off
+    fn build(&self) -> (Level, Vec<String>) {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut warnings = Vec::new();
+        let level = generate_reachable(
+            self.level_num,
+            || {
+                Level::generate_with_rng(
+                    self.difficulty,
+                    self.level_num,
+                    self.dungeon_type,
+                    self.is_final,
+                    &mut rng,
+                )
+            },
+            &mut warnings,
+        );
+        (level, warnings)
+    }
+}
+
+/// A dungeon whose levels are still being generated
+#[derive(Debug)]
+pub struct DungeonBuilder {
+    name: String,
+    dungeon_type: DungeonType,
+    difficulty: u32,
+    plans: Vec<LevelPlan>,
+    /// The levels built so far, in order, with their warnings
+    built: Vec<(Level, Vec<String>)>,
+}
+
+impl DungeonBuilder {
+    /// Plans a dungeon of `num_levels` levels, drawing each level's seed
+    /// from `rng`
+    pub fn new<R: Rng + ?Sized>(
+        name: String,
+        dungeon_type: DungeonType,
+        difficulty: u32,
+        num_levels: usize,
+        rng: &mut R,
+    ) -> Self {
+        let plans = (0..num_levels)
+            .map(|i| LevelPlan {
+                difficulty,
+                level_num: i as u32 + 1,
+                dungeon_type,
+                is_final: i == num_levels - 1,
+                seed: rng.gen(),
+            })
+            .collect();
+        DungeonBuilder {
+            name,
+            dungeon_type,
+            difficulty,
+            plans,
+            built: Vec::new(),
+        }
+    }
+
+    /// Plans a dungeon of a random type and name, with more levels the
+    /// harder it is
+    pub fn random<R: Rng + ?Sized>(difficulty: u32, rng: &mut R) -> Self {
+        let dungeon_type = DungeonType::choose(rng);
+        let difficulty = difficulty.max(1);
+
+        // Generate a thematic name
+        let prefix = match rng.gen_range(0..4) {
+            0 => "Forgotten",
+            1 => "Ancient",
+            2 => "Mysterious",
+            _ => "Haunted",
+        };
+
+        let location = match dungeon_type {
+            DungeonType::Ruins => match rng.gen_range(0..3) {
+                0 => "Temple",
+                1 => "Citadel",
+                _ => "Palace",
+            },
+            DungeonType::Forest => match rng.gen_range(0..3) {
+                0 => "Grove",
+                1 => "Thicket",
+                _ => "Woods",
+            },
+            DungeonType::Mountain => match rng.gen_range(0..3) {
+                0 => "Peaks",
+                1 => "Summit",
+                _ => "Cliffs",
+            },
+            DungeonType::Cavern => match rng.gen_range(0..3) {
+                0 => "Caverns",
+                1 => "Depths",
+                _ => "Grotto",
+            },
+        };
+
+        let name = format!("{prefix} {location}");
+
+        // Number of levels increases with difficulty
+        let num_levels = 3 + (difficulty / 5).min(5) as usize;
+
+        Self::new(name, dungeon_type, difficulty, num_levels, rng)
+    }
+
+    /// Levels the dungeon will have
+    pub fn levels_total(&self) -> usize {
+        self.plans.len()
+    }
+
+    /// Levels built so far
+    pub fn levels_built(&self) -> usize {
+        self.built.len()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.levels_built() == self.levels_total()
+    }
+
+    /// Builds the next level on this thread, for a front-end that has to
+    /// keep drawing between levels
+    pub fn step(&mut self) {
+        if let Some(plan) = self.plans.get(self.built.len()) {
+            self.built.push(plan.build());
+        }
+    }
+
+    /// Builds whatever levels are left and returns the dungeon
+    pub fn build(self) -> Dungeon {
+        self.build_counting(&AtomicUsize::new(0))
+    }
+
+    /// Builds whatever levels are left, a thread each where threads can be
+    /// started, and returns the dungeon. `progress` counts the levels
+    /// built, so another thread can show how far along it is.
+    pub fn build_counting(mut self, progress: &AtomicUsize) -> Dungeon {
+        progress.store(self.built.len(), Ordering::Relaxed);
+        let remaining = &self.plans[self.built.len()..];
+        let build = |plan: &LevelPlan| {
+            let level = plan.build();
+            progress.fetch_add(1, Ordering::Relaxed);
+            level
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let rest: Vec<_> = std::thread::scope(|scope| {
+            let workers: Vec<_> = remaining
+                .iter()
+                .map(|plan| scope.spawn(move || build(plan)))
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect()
+        });
+        #[cfg(target_arch = "wasm32")]
+        let rest: Vec<_> = remaining.iter().map(build).collect();
+
+        self.built.extend(rest);
+        let (levels, warnings): (Vec<_>, Vec<_>) = self.built.into_iter().unzip();
+        Dungeon {
+            name: self.name,
+            dungeon_type: self.dungeon_type,
+            left_on_turn: vec![None; levels.len()],
+            levels,
+            current_level: 0,
+            difficulty: self.difficulty,
+            generation_warnings: warnings.into_iter().flatten().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Each level as JSON, to compare whole levels at once
+    fn levels_json(dungeon: &Dungeon) -> Vec<serde_json::Value> {
+        dungeon
+            .levels
+            .iter()
+            .map(|level| serde_json::to_value(level).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_threads_build_the_same_levels_as_one_at_a_time() {
+        let plan = || DungeonBuilder::random(12, &mut StdRng::seed_from_u64(1629));
+        let progress = AtomicUsize::new(0);
+        let parallel = plan().build_counting(&progress);
+        assert_eq!(progress.load(Ordering::Relaxed), parallel.levels.len());
+
+        let mut stepped = plan();
+        let total = stepped.levels_total();
+        assert_eq!(total, parallel.levels.len());
+        while !stepped.is_finished() {
+            stepped.step();
+        }
+        assert_eq!(stepped.levels_built(), total);
+        let sequential = stepped.build();
+
+        assert_eq!(parallel.name, sequential.name);
+        assert_eq!(levels_json(&parallel), levels_json(&sequential));
+        for (i, level) in parallel.levels.iter().enumerate() {
+            assert_eq!(level.level_num, i as u32 + 1);
+        }
+    }
+}
@@ -1,11 +1,37 @@
-use crate::item::Item;
-use crate::world::{DungeonType, Enemy, Tile, TileType};
+use crate::combat::Combatant;
+use crate::item::consumable::Material;
+use crate::item::{Consumable, Equipment, Item, CHEST_RARITY_BONUS};
+use crate::world::budget::{enemy_cost, LevelBudget};
+use crate::world::corpse::RAISE_RADIUS;
+use crate::world::npc::NPC_CHANCE;
+use crate::world::placement::{distance_band, path_distances, place_stairs, StairsPlacement};
+use crate::world::reachability::Landmark;
+use crate::world::special_room::MAX_SPECIAL_ROOMS;
+use crate::world::{
+    AmbientEffect, AmbientLight, Chest, ChestKind, Corpse, DungeonType, Enemy, Npc,
+    ReachabilityReport, RoomKind, SpecialRoom, Tile, TileType,
+};
+use rand::seq::SliceRandom;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 const MAP_WIDTH: usize = 80;
 const MAP_HEIGHT: usize = 45;
+/// Tries at finding a free spot for each enemy or chest before giving up
+const PLACEMENT_ATTEMPTS: u32 = 200;
+/// Enemies placed in one room that make a pack with a leader
+const PACK_SIZE: usize = 3;
+/// Chance of a room other than the first getting a patch of terrain
+const TERRAIN_CHANCE: f64 = 0.35;
+/// Crafting materials lying about on each level
+const MATERIALS_PER_LEVEL: std::ops::RangeInclusive<usize> = 1..=3;
+/// Steps of the random walk that lays down each terrain patch
+const TERRAIN_PATCH_STEPS: std::ops::RangeInclusive<u32> = 4..=14;
+
+/// Distance (in tiles, diagonals counting as one) within which a visible
+/// enemy can spot the player
+pub const NOTICE_RADIUS: i32 = 5;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Position {
@@ -45,12 +71,17 @@ impl Room {
         self.x1 <= other.x2 && self.x2 >= other.x1 && self.y1 <= other.y2 && self.y2 >= other.y1
     }
 
-    pub fn width(&self) -> i32 {
-        self.x2 - self.x1
+    /// Whether `pos` is on the room's floor, inside its walls
+    pub fn contains(&self, pos: Position) -> bool {
+        pos.x > self.x1 && pos.x < self.x2 && pos.y > self.y1 && pos.y < self.y2
     }
 
-    pub fn height(&self) -> i32 {
-        self.y2 - self.y1
+    /// A random spot on the room's floor
+    fn random_spot<R: Rng + ?Sized>(&self, rng: &mut R) -> Position {
+        Position::new(
+            rng.gen_range((self.x1 + 1)..self.x2),
+            rng.gen_range((self.y1 + 1)..self.y2),
+        )
     }
 }
 
@@ -58,17 +89,70 @@ impl Room {
 pub struct Level {
     pub tiles: Vec<Vec<Tile>>,
     pub rooms: Vec<Room>,
+    /// The rooms set aside as shrines, libraries and the like
+    #[serde(default)]
+    pub special_rooms: Vec<SpecialRoom>,
     pub width: usize,
     pub height: usize,
+    #[serde(with = "position_map")]
     pub enemies: HashMap<Position, Enemy>,
+    #[serde(with = "position_map")]
     pub items: HashMap<Position, Item>,
-    pub stairs_down: Option<Position>,
-    pub stairs_up: Option<Position>,
+    /// Piles of gold lying on the floor, keyed by position
+    #[serde(with = "position_map", default)]
+    pub gold: HashMap<Position, u32>,
+    /// What's left of enemies killed here, keyed by where they fell
+    #[serde(with = "position_map", default)]
+    pub corpses: HashMap<Position, Corpse>,
+    /// Friendly folk standing on the level, keyed by position
+    #[serde(with = "position_map", default)]
+    pub npcs: HashMap<Position, Npc>,
+    /// What each unopened chest holds, keyed by the chest's position
+    #[serde(with = "position_map", default)]
+    pub chest_contents: HashMap<Position, Chest>,
+    /// The epitaph on each grave, keyed by the grave's position
+    #[serde(with = "position_map", default)]
+    pub graves: HashMap<Position, String>,
+    /// Altars that have answered a prayer already
+    #[serde(with = "position_set", default)]
+    pub spent_altars: HashSet<Position>,
+    /// Floor items the player has already been told are next to them
+    #[serde(with = "position_set", default)]
+    pub announced_items: HashSet<Position>,
+    /// Where the stairs down are; arriving from the level below puts the
+    /// player here
+    #[serde(alias = "stairs_down")]
+    pub stairs_down_position: Option<Position>,
+    /// Where the stairs up are; arriving from the level above puts the
+    /// player here
+    #[serde(alias = "stairs_up")]
+    pub stairs_up_position: Option<Position>,
     pub level_num: u32,
     pub player_position: Position,
+    /// Where the player's companion stands, while it's up and on this level
+    #[serde(default)]
+    pub companion_position: Option<Position>,
     pub revealed_tiles: Vec<Vec<bool>>,
     pub visible_tiles: Vec<Vec<bool>>,
+    /// Every position marked in `visible_tiles`, so the next sight update
+    /// clears just those instead of the whole level
+    #[serde(with = "position_set", default)]
+    pub visible_positions: HashSet<Position>,
+    /// Positions just past sight where a Ranger still makes out enemies
+    /// and loot, though not the ground they're on
+    #[serde(skip)]
+    pub glimpsed_positions: HashSet<Position>,
     pub exit_position: Option<Position>,
+    /// How far the player sees here; caverns are dark
+    #[serde(default)]
+    pub ambient_light: AmbientLight,
+    /// Weather and the like still going on here
+    #[serde(default)]
+    pub ambient_effects: Vec<AmbientEffect>,
+    /// Whether a Mage has sensed the way down here yet; it works once a
+    /// level
+    #[serde(default)]
+    pub way_sensed: bool,
 }
 
 impl Level {
@@ -80,36 +164,65 @@ impl Level {
         Level {
             tiles,
             rooms: Vec::new(),
+            special_rooms: Vec::new(),
             width,
             height,
             enemies: HashMap::new(),
             items: HashMap::new(),
-            stairs_down: None,
-            stairs_up: None,
+            gold: HashMap::new(),
+            corpses: HashMap::new(),
+            npcs: HashMap::new(),
+            chest_contents: HashMap::new(),
+            graves: HashMap::new(),
+            spent_altars: HashSet::new(),
+            announced_items: HashSet::new(),
+            stairs_down_position: None,
+            stairs_up_position: None,
             level_num: 1,
             player_position: Position::new(0, 0),
+            companion_position: None,
             revealed_tiles,
             visible_tiles,
+            visible_positions: HashSet::new(),
+            glimpsed_positions: HashSet::new(),
             exit_position: None,
+            ambient_light: AmbientLight::Normal,
+            ambient_effects: Vec::new(),
+            way_sensed: false,
         }
     }
 
     pub fn generate(
         difficulty: u32,
         level_num: u32,
-        _dungeon_type: DungeonType,
+        dungeon_type: DungeonType,
         is_final: bool,
+    ) -> Self {
+        Self::generate_with_rng(
+            difficulty,
+            level_num,
+            dungeon_type,
+            is_final,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    pub fn generate_with_rng<R: Rng + ?Sized>(
+        difficulty: u32,
+        level_num: u32,
+        dungeon_type: DungeonType,
+        is_final: bool,
+        rng: &mut R,
     ) -> Self {
         let mut level = Level::new(MAP_WIDTH, MAP_HEIGHT);
         level.level_num = level_num;
+        level.ambient_light = AmbientLight::for_dungeon(dungeon_type);
 
         // Generate rooms
         let max_rooms = 10 + (difficulty / 2).min(15) as i32;
         let min_size = 5;
         let max_size = 12;
 
-        let mut rng = rand::thread_rng();
-
         for _ in 0..max_rooms {
             let w = rng.gen_range(min_size..=max_size);
             let h = rng.gen_range(min_size..=max_size);
@@ -153,7 +266,7 @@ impl Level {
                 }
 
                 // Place doors
-                level.place_doors(&new_room);
+                level.place_doors(&new_room, rng);
 
                 // Store the room
                 level.rooms.push(new_room);
@@ -164,31 +277,50 @@ impl Level {
         let player_pos = level.rooms[0].center();
         level.player_position = player_pos;
 
-        // Place stairs
+        // The way on goes a long walk from the start, the stairs up beside it
+        let placement = place_stairs(&level.tiles, &level.rooms, player_pos, level_num > 1, rng)
+            .unwrap_or(StairsPlacement {
+                way_on: level.rooms[level.rooms.len() - 1].center(),
+                stairs_up: None,
+                min_distance: 0,
+            });
+        let way_on = placement.way_on;
         if !is_final {
-            let stairs_pos = level.rooms[level.rooms.len() - 1].center();
-            level.tiles[stairs_pos.y as usize][stairs_pos.x as usize] = Tile::stairs_down();
-            level.stairs_down = Some(stairs_pos);
+            level.tiles[way_on.y as usize][way_on.x as usize] = Tile::stairs_down();
+            level.stairs_down_position = Some(way_on);
         } else {
             // Final level has an exit instead of stairs
-            let exit_pos = level.rooms[level.rooms.len() - 1].center();
-            level.tiles[exit_pos.y as usize][exit_pos.x as usize] = Tile::exit();
-            level.exit_position = Some(exit_pos);
+            level.tiles[way_on.y as usize][way_on.x as usize] = Tile::exit();
+            level.exit_position = Some(way_on);
         }
-
-        if level_num > 1 {
-            // Add stairs up in the first room, but not at the player's position
-            let mut stairs_up_pos = level.rooms[0].center();
-            stairs_up_pos.x += 1; // Place it next to the player
+        if let Some(stairs_up_pos) = placement.stairs_up {
             level.tiles[stairs_up_pos.y as usize][stairs_up_pos.x as usize] = Tile::stairs_up();
-            level.stairs_up = Some(stairs_up_pos);
+            level.stairs_up_position = Some(stairs_up_pos);
         }
 
-        // Place enemies
-        level.place_enemies(difficulty);
-
-        // Place items and chests
-        level.place_items(difficulty);
+        // Water, lava or rubble, depending on the dungeon
+        level.place_terrain(dungeon_type, rng);
+        level.place_special_rooms(dungeon_type, rng);
+
+        // Spend the level's budget on enemies, then on chests and items
+        let budget = LevelBudget::new(difficulty, level_num, is_final);
+        level.place_enemies(&budget, difficulty, dungeon_type, rng);
+        level.place_items(&budget, difficulty, rng);
+        level.place_npc(dungeon_type, rng);
+        level.place_materials(dungeon_type, rng);
+
+        // The final level's exit is the only way out of the dungeon; every
+        // other level leads on by its stairs
+        debug_assert_eq!(
+            level
+                .tiles
+                .iter()
+                .flatten()
+                .filter(|tile| tile.tile_type == TileType::Exit)
+                .count(),
+            usize::from(is_final),
+            "only the final level has an exit"
+        );
 
         level
     }
@@ -231,9 +363,7 @@ impl Level {
         }
     }
 
-    fn place_doors(&mut self, room: &Room) {
-        let mut rng = rand::thread_rng();
-
+    fn place_doors<R: Rng + ?Sized>(&mut self, room: &Room, rng: &mut R) {
         // Try to place a door on each side of the room with some randomness
         if rng.gen_bool(0.7) {
             let x = rng.gen_range((room.x1 + 1)..room.x2);
@@ -288,103 +418,602 @@ impl Level {
         has_floor_adjacent && has_wall_adjacent
     }
 
-    fn place_enemies(&mut self, difficulty: u32) {
-        let mut rng = rand::thread_rng();
+    /// Lays patches of the dungeon's terrain over the floor of rooms other
+    /// than the first, so the player never starts in any of it. Only floor
+    /// is covered, which keeps the stairs and the exit clear; nothing else
+    /// has been placed yet.
+    fn place_terrain<R: Rng + ?Sized>(&mut self, dungeon_type: DungeonType, rng: &mut R) {
+        let terrain: &[TileType] = match dungeon_type {
+            DungeonType::Ruins => &[TileType::Rubble],
+            DungeonType::Forest => &[TileType::ShallowWater],
+            DungeonType::Mountain => &[TileType::Lava],
+            DungeonType::Cavern => &[TileType::ShallowWater, TileType::Lava],
+        };
+
+        for room in self.rooms.iter().skip(1) {
+            if !rng.gen_bool(TERRAIN_CHANCE) {
+                continue;
+            }
+            let tile_type = terrain[rng.gen_range(0..terrain.len())];
+            let mut pos = Position::new(
+                rng.gen_range((room.x1 + 1)..room.x2),
+                rng.gen_range((room.y1 + 1)..room.y2),
+            );
+            for _ in 0..rng.gen_range(TERRAIN_PATCH_STEPS) {
+                let tile = &mut self.tiles[pos.y as usize][pos.x as usize];
+                if tile.tile_type == TileType::Floor {
+                    *tile = Tile::new(tile_type);
+                }
+                // Wander on, staying inside the room
+                pos.x = (pos.x + rng.gen_range(-1..=1)).clamp(room.x1 + 1, room.x2 - 1);
+                pos.y = (pos.y + rng.gen_range(-1..=1)).clamp(room.y1 + 1, room.y2 - 1);
+            }
+        }
+    }
 
-        // Skip the first room (player's starting position)
-        for i in 1..self.rooms.len() {
-            let room = &self.rooms[i];
+    /// Sets aside one or two rooms, never the first nor the one with the
+    /// way on, as special rooms of the kinds the dungeon has. A shrine gets
+    /// its altar and a flooded chamber its water now; the chest of an
+    /// armory and the scroll of a library come with the rest of the loot.
+    fn place_special_rooms<R: Rng + ?Sized>(&mut self, dungeon_type: DungeonType, rng: &mut R) {
+        let way_on_room = self.way_on_room();
+        let mut candidates: Vec<usize> = (1..self.rooms.len())
+            .filter(|&index| Some(index) != way_on_room)
+            .collect();
+        candidates.shuffle(rng);
+        let count = rng.gen_range(1..=MAX_SPECIAL_ROOMS).min(candidates.len());
+        let kinds = RoomKind::for_dungeon(dungeon_type);
+
+        for index in candidates.into_iter().take(count) {
+            let kind = kinds[rng.gen_range(0..kinds.len())];
+            let room = &self.rooms[index];
+            match kind {
+                RoomKind::Shrine => {
+                    let altar = room.center();
+                    self.tiles[altar.y as usize][altar.x as usize] = Tile::altar();
+                }
+                RoomKind::FloodedChamber => {
+                    for y in (room.y1 + 1)..room.y2 {
+                        for x in (room.x1 + 1)..room.x2 {
+                            let tile = &mut self.tiles[y as usize][x as usize];
+                            if tile.tile_type == TileType::Floor {
+                                *tile = Tile::shallow_water();
+                            }
+                        }
+                    }
+                }
+                RoomKind::Library | RoomKind::Armory => {}
+            }
+            self.special_rooms.push(SpecialRoom::new(kind, index));
+        }
+    }
 
-            // Number of enemies increases with difficulty and room size
-            let room_area = room.width() * room.height();
-            let num_enemies = ((room_area as f32 * 0.01 * difficulty as f32).round() as u32).min(5);
+    /// The room the stairs down or the exit stand in, by index
+    pub fn way_on_room(&self) -> Option<usize> {
+        let way_on = self.stairs_down_position.or(self.exit_position)?;
+        self.rooms.iter().position(|room| room.contains(way_on))
+    }
 
-            for _ in 0..num_enemies {
-                let x = rng.gen_range((room.x1 + 1)..room.x2);
-                let y = rng.gen_range((room.y1 + 1)..room.y2);
+    /// Rooms set aside as `kind`, by index
+    fn rooms_of_kind(&self, kind: RoomKind) -> Vec<usize> {
+        self.special_rooms
+            .iter()
+            .filter(|special| special.kind == kind)
+            .map(|special| special.room)
+            .collect()
+    }
 
-                let pos = Position::new(x, y);
+    /// What kind of special room `pos` lies in, if any
+    pub fn special_room_at(&self, pos: Position) -> Option<RoomKind> {
+        self.special_rooms
+            .iter()
+            .find(|special| self.rooms[special.room].contains(pos))
+            .map(|special| special.kind)
+    }
 
-                // Don't place enemies on stairs or other enemies
-                if (Some(pos) != self.stairs_down)
-                    && (Some(pos) != self.stairs_up)
-                    && (!self.enemies.contains_key(&pos))
-                {
-                    // Generate enemy based on difficulty and level number
-                    let enemy = Enemy::generate_random(self.level_num, difficulty);
+    /// The special room the player is in, if they're walking into it for
+    /// the first time; it counts as entered from then on
+    pub fn enter_special_room(&mut self) -> Option<RoomKind> {
+        let player = self.player_position;
+        let rooms = &self.rooms;
+        let special = self
+            .special_rooms
+            .iter_mut()
+            .find(|special| rooms[special.room].contains(player))?;
+        if special.entered {
+            return None;
+        }
+        special.entered = true;
+        Some(special.kind)
+    }
 
-                    self.enemies.insert(pos, enemy);
-                }
+    /// Spends the budget's enemy points on enemies spread over every room
+    /// but the first, skipping any the points left can't pay for
+    fn place_enemies<R: Rng + ?Sized>(
+        &mut self,
+        budget: &LevelBudget,
+        difficulty: u32,
+        dungeon_type: DungeonType,
+        rng: &mut R,
+    ) {
+        if self.rooms.len() < 2 {
+            return;
+        }
+        let mut remaining = budget.enemy_points;
+
+        for _ in 0..PLACEMENT_ATTEMPTS {
+            if remaining == 0 {
+                break;
+            }
+            let room = &self.rooms[rng.gen_range(1..self.rooms.len())];
+            let pos = Position::new(
+                rng.gen_range((room.x1 + 1)..room.x2),
+                rng.gen_range((room.y1 + 1)..room.y2),
+            );
+
+            // Don't place enemies on stairs, altars, in lava or on other
+            // enemies
+            let tile_type = self.tiles[pos.y as usize][pos.x as usize].tile_type;
+            if (Some(pos) == self.stairs_down_position)
+                || (Some(pos) == self.stairs_up_position)
+                || tile_type.is_hazardous()
+                || !tile_type.is_walkable()
+                || self.enemies.contains_key(&pos)
+            {
+                continue;
+            }
+
+            // Generate enemy based on difficulty and level number
+            let enemy = Enemy::generate_with_rng(self.level_num, difficulty, dungeon_type, rng);
+            let cost = enemy_cost(enemy.level);
+            if cost <= remaining {
+                remaining -= cost;
+                self.enemies.insert(pos, enemy);
             }
         }
+        self.promote_pack_leaders();
     }
 
-    fn place_items(&mut self, _difficulty: u32) {
-        let mut rng = rand::thread_rng();
+    /// Makes the strongest enemy of every room holding three or more of
+    /// them its pack's leader
+    fn promote_pack_leaders(&mut self) {
+        for room in &self.rooms {
+            let pack: Vec<Position> = self
+                .enemies
+                .keys()
+                .copied()
+                .filter(|&pos| room.contains(pos))
+                .collect();
+            if pack.len() < PACK_SIZE {
+                continue;
+            }
+            // Ties go to the top-left, so a seed always picks the same one
+            let strongest = pack
+                .into_iter()
+                .max_by_key(|pos| (self.enemies[pos].level, -pos.y, -pos.x));
+            if let Some(enemy) = strongest.and_then(|pos| self.enemies.get_mut(&pos)) {
+                enemy.promote_to_leader();
+            }
+        }
+    }
 
-        // Place chests and items in random rooms (but not the first)
-        for i in 1..self.rooms.len() {
-            let room = &self.rooms[i];
+    /// Where the living enemies rallied by a leader at `leader`, with an
+    /// aura reaching `radius` tiles, stand: the rest of those in its room
+    /// and within reach. A leader out in a corridor rallies no one.
+    pub fn pack_of(&self, leader: Position, radius: i32) -> Vec<Position> {
+        let Some(room) = self.rooms.iter().find(|room| room.contains(leader)) else {
+            return Vec::new();
+        };
+        let mut pack: Vec<Position> = self
+            .enemies
+            .iter()
+            .filter(|(&pos, enemy)| {
+                pos != leader
+                    && !enemy.leader
+                    && enemy.is_alive()
+                    && room.contains(pos)
+                    && (pos.x - leader.x).abs().max((pos.y - leader.y).abs()) <= radius
+            })
+            .map(|(&pos, _)| pos)
+            .collect();
+        pack.sort_by_key(|pos| (pos.y, pos.x));
+        pack
+    }
 
-            // 50% chance of chest (increased from 30% to ensure more chests spawn for testing)
-            // This makes it easier to verify the fix works
-            if rng.gen_bool(0.5) {
-                // Find a spot for the chest
-                let mut chest_x = rng.gen_range((room.x1 + 1)..room.x2);
-                let mut chest_y = rng.gen_range((room.y1 + 1)..room.y2);
-                let mut chest_pos = Position::new(chest_x, chest_y);
-
-                // Make sure we're not placing on top of stairs, enemies, or player
-                while (Some(chest_pos) == self.stairs_down)
-                    || (Some(chest_pos) == self.stairs_up)
-                    || (self.enemies.contains_key(&chest_pos))
-                    || (chest_pos == self.player_position)
-                {
-                    chest_x = rng.gen_range((room.x1 + 1)..room.x2);
-                    chest_y = rng.gen_range((room.y1 + 1)..room.y2);
-                    chest_pos = Position::new(chest_x, chest_y);
-                }
+    /// Works out afresh which enemies a living leader is rallying, as they
+    /// move about
+    pub fn rally_packs(&mut self) {
+        let leaders: Vec<(Position, i32)> = self
+            .enemies
+            .iter()
+            .filter(|(_, enemy)| enemy.leader && enemy.is_alive())
+            .map(|(&pos, enemy)| (pos, enemy.aura_radius))
+            .collect();
+        let rallied: HashSet<Position> = leaders
+            .into_iter()
+            .flat_map(|(pos, radius)| self.pack_of(pos, radius))
+            .collect();
+        for (pos, enemy) in self.enemies.iter_mut() {
+            enemy.rallied = rallied.contains(pos);
+        }
+    }
+
+    /// Enemy points the enemies on the level are worth
+    pub fn enemy_points(&self) -> u32 {
+        self.enemies
+            .values()
+            .map(|enemy| enemy_cost(enemy.level))
+            .sum()
+    }
+
+    /// Spawns up to `count` new enemies in the level's rooms, never on the
+    /// player, an item, a chest or the stairs, nor anywhere the player can
+    /// see or within `min_distance` tiles of them. Returns how many spawned.
+    pub fn spawn_wanderers<R: Rng + ?Sized>(
+        &mut self,
+        count: usize,
+        difficulty: u32,
+        dungeon_type: DungeonType,
+        min_distance: i32,
+        rng: &mut R,
+    ) -> usize {
+        if self.rooms.is_empty() {
+            return 0;
+        }
 
-                // Place chest
-                self.tiles[chest_y as usize][chest_x as usize] = Tile::chest();
+        let mut spawned = 0;
+        for _ in 0..count * 20 {
+            if spawned == count {
+                break;
+            }
+            let room = &self.rooms[rng.gen_range(0..self.rooms.len())];
+            let pos = Position::new(
+                rng.gen_range((room.x1 + 1)..room.x2),
+                rng.gen_range((room.y1 + 1)..room.y2),
+            );
+            if self.can_spawn_at(pos, min_distance) {
+                let enemy = Enemy::generate_with_rng(self.level_num, difficulty, dungeon_type, rng);
+                self.enemies.insert(pos, enemy);
+                spawned += 1;
+            }
+        }
+        spawned
+    }
 
-                // Generate a guaranteed quality item specifically for chests
-                // This ensures consistent chest contents across all platforms
-                let item = Item::generate_for_chest(self.level_num);
+    /// Whether a new enemy may appear at `pos` without the player seeing it
+    /// happen
+    fn can_spawn_at(&self, pos: Position, min_distance: i32) -> bool {
+        let player = self.player_position;
+        let distance = (pos.x - player.x).abs().max((pos.y - player.y).abs());
+        let visible = self
+            .visible_tiles
+            .get(pos.y as usize)
+            .and_then(|row| row.get(pos.x as usize))
+            .copied()
+            .unwrap_or(false);
+
+        self.get_tile(pos.x, pos.y)
+            .is_some_and(|tile| tile.tile_type == TileType::Floor)
+            && distance > min_distance
+            && !visible
+            && !self.enemies.contains_key(&pos)
+            && !self.items.contains_key(&pos)
+            && !self.npcs.contains_key(&pos)
+            && !self.chest_contents.contains_key(&pos)
+            && Some(pos) != self.stairs_down_position
+            && Some(pos) != self.stairs_up_position
+    }
 
-                // Explicitly insert the item at the chest position
-                // We force the item to exist by inserting before any potential platform-specific checks
-                self.items.insert(chest_pos, item);
+    /// Places the budget's chests in rooms other than the first, an
+    /// armory's chest among them and the rest spread from near to far, then
+    /// a scroll in each library and maybe a loose item in each of the rooms
+    fn place_items<R: Rng + ?Sized>(&mut self, budget: &LevelBudget, difficulty: u32, rng: &mut R) {
+        if self.rooms.len() < 2 {
+            return;
+        }
+        let loot_level = budget.item_level;
 
-                // Debug validation - confirm item was added at this position
-                if cfg!(debug_assertions) {
-                    assert!(
-                        self.items.contains_key(&chest_pos),
-                        "Failed to insert item at chest position: {chest_pos:?}"
-                    );
-                }
+        // An armory's chest always has equipment in it
+        let mut chests = 0;
+        for room in self.rooms_of_kind(RoomKind::Armory) {
+            if chests == budget.chests {
+                break;
+            }
+            let Some(pos) = self.free_spot_in(room, rng) else {
+                continue;
+            };
+            let mut chest = self.fill_chest(loot_level, difficulty, rng);
+            if !chest
+                .items
+                .iter()
+                .any(|item| matches!(item, Item::Equipment(_)))
+            {
+                chest.items[0] = Item::Equipment(Equipment::generate_with_rng(
+                    loot_level + 1,
+                    CHEST_RARITY_BONUS,
+                    rng,
+                ));
+            }
+            self.tiles[pos.y as usize][pos.x as usize] = Tile::chest();
+            self.chest_contents.insert(pos, chest);
+            chests += 1;
+        }
+
+        // The rest go in whichever band of distance from the start has the
+        // fewest chests yet, the nearest first, so they aren't all found in
+        // the first few rooms
+        let bands = budget.chests.max(1) as usize;
+        let distances = path_distances(&self.tiles, self.player_position);
+        let farthest = distances.values().copied().max().unwrap_or(0);
+        let mut filled = vec![0; bands];
+        for pos in self.chest_contents.keys() {
+            if let Some(&distance) = distances.get(pos) {
+                filled[distance_band(distance, farthest, bands)] += 1;
             }
+        }
+        let mut spots = self.chest_spots_by_band(&distances, bands);
+        while chests < budget.chests {
+            let Some(band) = (0..bands)
+                .filter(|&band| !spots[band].is_empty())
+                .min_by_key(|&band| (filled[band], band))
+            else {
+                break;
+            };
+            filled[band] += 1;
+            let spot = rng.gen_range(0..spots[band].len());
+            let chest_pos = spots[band].swap_remove(spot);
+
+            // Place chest
+            self.tiles[chest_pos.y as usize][chest_pos.x as usize] = Tile::chest();
+            let chest = self.fill_chest(loot_level, difficulty, rng);
+            self.chest_contents.insert(chest_pos, chest);
+            chests += 1;
+        }
 
-            // Maybe place some loose items too (20% chance)
+        for room in self.rooms_of_kind(RoomKind::Library) {
+            if let Some(pos) = self.free_spot_in(room, rng) {
+                let scroll = Consumable::scroll_with_rng(loot_level, rng);
+                self.items.insert(pos, Item::Consumable(scroll));
+            }
+        }
+
+        // Maybe place some loose items too (20% chance per room)
+        for i in 1..self.rooms.len() {
+            let room = &self.rooms[i];
             if rng.gen_bool(0.2) {
                 let x = rng.gen_range((room.x1 + 1)..room.x2);
                 let y = rng.gen_range((room.y1 + 1)..room.y2);
                 let pos = Position::new(x, y);
 
-                // Don't place on stairs, enemies, chests, or player
-                if (Some(pos) != self.stairs_down)
-                    && (Some(pos) != self.stairs_up)
+                // Don't place on stairs, enemies, chests, altars, lava,
+                // other items or player
+                let tile_type = self.tiles[y as usize][x as usize].tile_type;
+                if (Some(pos) != self.stairs_down_position)
+                    && (Some(pos) != self.stairs_up_position)
                     && (!self.enemies.contains_key(&pos))
-                    && (self.tiles[y as usize][x as usize].tile_type != TileType::Chest)
+                    && (tile_type != TileType::Chest)
+                    && !tile_type.is_hazardous()
+                    && tile_type.is_walkable()
+                    && !self.items.contains_key(&pos)
                     && (pos != self.player_position)
                 {
-                    let item = Item::generate_random(self.level_num);
+                    let item = Item::generate_with_rng(loot_level, rng);
                     self.items.insert(pos, item);
                 }
             }
         }
     }
 
+    /// Where a chest could go in rooms other than the first, sorted into
+    /// `bands` bands of the walking `distances` from the player, nearest
+    /// first. Only floor the player can walk to counts, clear of the
+    /// stairs, the exit, enemies and other chests.
+    fn chest_spots_by_band(
+        &self,
+        distances: &HashMap<Position, u32>,
+        bands: usize,
+    ) -> Vec<Vec<Position>> {
+        let farthest = distances.values().copied().max().unwrap_or(0);
+        let mut spots = vec![Vec::new(); bands];
+        for room in self.rooms.iter().skip(1) {
+            for y in (room.y1 + 1)..room.y2 {
+                for x in (room.x1 + 1)..room.x2 {
+                    let pos = Position::new(x, y);
+                    let Some(&distance) = distances.get(&pos) else {
+                        continue;
+                    };
+                    if self.tiles[y as usize][x as usize].tile_type == TileType::Floor
+                        && Some(pos) != self.stairs_down_position
+                        && Some(pos) != self.stairs_up_position
+                        && Some(pos) != self.exit_position
+                        && !self.enemies.contains_key(&pos)
+                        && !self.chest_contents.contains_key(&pos)
+                        && pos != self.player_position
+                    {
+                        spots[distance_band(distance, farthest, bands)].push(pos);
+                    }
+                }
+            }
+        }
+        spots
+    }
+
+    /// Now and then puts someone friendly on a free spot in a room other
+    /// than the first
+    fn place_npc<R: Rng + ?Sized>(&mut self, dungeon_type: DungeonType, rng: &mut R) {
+        if self.rooms.len() < 2 || !rng.gen_bool(NPC_CHANCE) {
+            return;
+        }
+        let room = rng.gen_range(1..self.rooms.len());
+        if let Some(pos) = self.free_spot_in(room, rng) {
+            let npc = Npc::generate(dungeon_type, self.level_num, rng);
+            self.npcs.insert(pos, npc);
+        }
+    }
+
+    /// Leaves a few crafting materials on the floor, of the kind the
+    /// dungeon's ground gives: herbs in forests, iron in the mountains,
+    /// crystal dust in caverns and a bit of everything in ruins
+    fn place_materials<R: Rng + ?Sized>(&mut self, dungeon_type: DungeonType, rng: &mut R) {
+        if self.rooms.is_empty() {
+            return;
+        }
+        for _ in 0..rng.gen_range(MATERIALS_PER_LEVEL) {
+            let material = match dungeon_type {
+                DungeonType::Forest => Material::Herbs,
+                DungeonType::Mountain => Material::IronShard,
+                DungeonType::Cavern => Material::CrystalDust,
+                DungeonType::Ruins => Material::ALL[rng.gen_range(0..Material::ALL.len())],
+            };
+            let room = rng.gen_range(0..self.rooms.len());
+            if let Some(pos) = self.free_spot_in(room, rng) {
+                self.items
+                    .insert(pos, Item::Consumable(Consumable::material(material)));
+            }
+        }
+    }
+
+    /// A freshly generated chest's contents. A chest never turns up empty;
+    /// in the dark, many hold a torch. Now and then it's trapped, or a
+    /// mimic.
+    fn fill_chest<R: Rng + ?Sized>(&self, loot_level: u32, difficulty: u32, rng: &mut R) -> Chest {
+        let mut chest = Chest::generate(loot_level, difficulty, rng);
+        chest.kind = ChestKind::roll(rng);
+        if self.ambient_light == AmbientLight::Dark && rng.gen_bool(0.5) {
+            chest.items.push(Item::Consumable(Consumable::torch()));
+        }
+        chest
+    }
+
+    /// A free spot on the floor of room `index`, if one turns up
+    fn free_spot_in<R: Rng + ?Sized>(&self, index: usize, rng: &mut R) -> Option<Position> {
+        let room = &self.rooms[index];
+        (0..PLACEMENT_ATTEMPTS)
+            .map(|_| room.random_spot(rng))
+            .find(|&pos| self.is_free_floor(pos))
+    }
+
+    /// Puts `item` into a chest on this level, adding it to an existing chest
+    /// if there is one, otherwise placing a new chest in a room other than
+    /// the starting one. Returns the chest position.
+    pub fn place_in_chest<R: Rng + ?Sized>(&mut self, item: Item, rng: &mut R) -> Option<Position> {
+        let mut chests: Vec<Position> = self
+            .chest_contents
+            .keys()
+            .copied()
+            .filter(|pos| self.tiles[pos.y as usize][pos.x as usize].tile_type == TileType::Chest)
+            .collect();
+        // HashMap order isn't stable, so sort before picking with the rng
+        chests.sort_by_key(|pos| (pos.y, pos.x));
+
+        if !chests.is_empty() {
+            let pos = chests[rng.gen_range(0..chests.len())];
+            self.chest_contents.entry(pos).or_default().items.push(item);
+            return Some(pos);
+        }
+
+        for _ in 0..100 {
+            let room_index = rng.gen_range(1.min(self.rooms.len() - 1)..self.rooms.len());
+            let room = &self.rooms[room_index];
+            let pos = Position::new(
+                rng.gen_range((room.x1 + 1)..room.x2),
+                rng.gen_range((room.y1 + 1)..room.y2),
+            );
+
+            if self.tiles[pos.y as usize][pos.x as usize].tile_type == TileType::Floor
+                && !self.enemies.contains_key(&pos)
+                && !self.items.contains_key(&pos)
+                && !self.npcs.contains_key(&pos)
+                && pos != self.player_position
+            {
+                self.tiles[pos.y as usize][pos.x as usize] = Tile::chest();
+                self.chest_contents.insert(
+                    pos,
+                    Chest {
+                        items: vec![item],
+                        ..Chest::default()
+                    },
+                );
+                return Some(pos);
+            }
+        }
+
+        None
+    }
+
+    /// Digs a grave with `epitaph` in a room other than the starting one,
+    /// with `ghost` standing beside it and a chest of `items` on another
+    /// side. Returns the grave's position, or None if no room had space.
+    pub fn place_grave<R: Rng + ?Sized>(
+        &mut self,
+        epitaph: String,
+        ghost: Enemy,
+        items: Vec<Item>,
+        rng: &mut R,
+    ) -> Option<Position> {
+        if self.rooms.len() < 2 {
+            return None;
+        }
+
+        for _ in 0..PLACEMENT_ATTEMPTS {
+            let room = &self.rooms[rng.gen_range(1..self.rooms.len())];
+            let pos = Position::new(
+                rng.gen_range((room.x1 + 1)..room.x2),
+                rng.gen_range((room.y1 + 1)..room.y2),
+            );
+            if !self.is_free_floor(pos) {
+                continue;
+            }
+            let beside: Vec<Position> = [(0, -1), (0, 1), (-1, 0), (1, 0)]
+                .into_iter()
+                .map(|(dx, dy)| Position::new(pos.x + dx, pos.y + dy))
+                .filter(|&spot| self.is_free_floor(spot))
+                .collect();
+            let [ghost_pos, chest_pos, ..] = beside[..] else {
+                continue;
+            };
+
+            self.tiles[pos.y as usize][pos.x as usize] = Tile::grave();
+            self.graves.insert(pos, epitaph);
+            self.enemies.insert(ghost_pos, ghost);
+            self.tiles[chest_pos.y as usize][chest_pos.x as usize] = Tile::chest();
+            self.chest_contents.insert(
+                chest_pos,
+                Chest {
+                    items,
+                    ..Chest::default()
+                },
+            );
+            return Some(pos);
+        }
+        None
+    }
+
+    /// Whether `pos` is floor with nothing on it
+    fn is_free_floor(&self, pos: Position) -> bool {
+        self.get_tile(pos.x, pos.y)
+            .is_some_and(|tile| tile.tile_type == TileType::Floor)
+            && !self.enemies.contains_key(&pos)
+            && !self.items.contains_key(&pos)
+            && !self.npcs.contains_key(&pos)
+            && pos != self.player_position
+            && Some(pos) != self.companion_position
+    }
+
+    /// Marks `pos` and the tiles around it explored, as if the player had
+    /// seen them
+    pub fn reveal_around(&mut self, pos: Position) {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let (x, y) = (pos.x + dx, pos.y + dy);
+                if let Some(tile) = self.get_tile_mut(x, y) {
+                    tile.explored = true;
+                    self.revealed_tiles[y as usize][x as usize] = true;
+                }
+            }
+        }
+    }
+
     pub fn is_position_valid(&self, x: i32, y: i32) -> bool {
         x >= 0 && x < self.width as i32 && y >= 0 && y < self.height as i32
     }
@@ -425,25 +1054,860 @@ impl Level {
         self.enemies.remove(pos)
     }
 
-    pub fn get_item_at(&self, pos: &Position) -> Option<&Item> {
-        let item = self.items.get(pos);
+    pub fn remove_item_at(&mut self, pos: &Position) -> Option<Item> {
+        // Whatever is dropped here next gets pointed out again
+        self.announced_items.remove(pos);
+        self.items.remove(pos)
+    }
 
-        // Additional debug validation for chest items
-        if cfg!(debug_assertions) {
-            if let Some(tile) = self.get_tile(pos.x, pos.y) {
-                if tile.tile_type == TileType::Chest && item.is_none() {
-                    // This would indicate a bug - chest exists but has no item
-                    eprintln!("WARNING: Found chest at {pos:?} but no item associated with it");
+    /// Items in view right next to the player that haven't been pointed out
+    /// yet, marked as pointed out. Chests keep their contents hidden.
+    pub fn announce_adjacent_items(&mut self) -> Vec<&Item> {
+        let player = self.player_position;
+        let mut found = Vec::new();
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let pos = Position::new(player.x + dx, player.y + dy);
+                let in_view = self
+                    .get_tile(pos.x, pos.y)
+                    .is_some_and(|tile| tile.visible && tile.tile_type != TileType::Chest);
+                if pos != player
+                    && in_view
+                    && self.items.contains_key(&pos)
+                    && self.announced_items.insert(pos)
+                {
+                    found.push(pos);
                 }
             }
         }
+        found.iter().map(|pos| &self.items[pos]).collect()
+    }
+
+    /// Empties the chest at `pos`, turning its tile back into floor. Saves
+    /// from before chests held several things kept a single item on the
+    /// chest's tile, so that is picked up too.
+    pub fn take_chest(&mut self, pos: Position) -> Option<Chest> {
+        let tile = self.get_tile_mut(pos.x, pos.y)?;
+        if tile.tile_type != TileType::Chest {
+            return None;
+        }
+        *tile = Tile::floor();
 
-        item
+        let mut chest = self.chest_contents.remove(&pos).unwrap_or_default();
+        chest.items.extend(self.items.remove(&pos));
+        Some(chest)
     }
 
-    pub fn remove_item_at(&mut self, pos: &Position) -> Option<Item> {
-        self.items.remove(pos)
+    /// Leaves `gold` in a pile at `pos` and each of `items` on the nearest
+    /// free floor around it, as slain enemies and opened chests do. Returns
+    /// the items that found nowhere to land.
+    pub fn drop_loot(&mut self, pos: Position, gold: u32, items: Vec<Item>) -> Vec<Item> {
+        if gold > 0 {
+            *self.gold.entry(pos).or_insert(0) += gold;
+        }
+        items
+            .into_iter()
+            .filter_map(|item| match self.drop_near(pos, item.clone()) {
+                Some(_) => None,
+                None => Some(item),
+            })
+            .collect()
+    }
+
+    /// Picks up the gold lying at `pos`, if there is any
+    pub fn take_gold_at(&mut self, pos: Position) -> Option<u32> {
+        self.gold.remove(&pos)
+    }
+
+    /// Clears away the corpses that have rotted by `turn`
+    pub fn rot_corpses(&mut self, turn: u32) {
+        self.corpses.retain(|_, corpse| !corpse.has_decayed(turn));
+    }
+
+    /// The nearest unsearched corpse a dark mage at `pos` could raise:
+    /// within `RAISE_RADIUS`, in plain sight of it and with no one
+    /// standing on it
+    pub fn corpse_to_raise(&self, pos: Position) -> Option<Position> {
+        self.corpses
+            .iter()
+            .filter(|(&at, corpse)| {
+                let distance = (at.x - pos.x).abs().max((at.y - pos.y).abs());
+                !corpse.searched
+                    && !corpse.sanctified
+                    && distance <= RAISE_RADIUS
+                    && !self.enemies.contains_key(&at)
+                    && at != self.player_position
+                    && Some(at) != self.companion_position
+                    && self.clear_line(pos, at)
+            })
+            .map(|(&at, _)| at)
+            .min_by_key(|at| ((at.x - pos.x).abs().max((at.y - pos.y).abs()), at.y, at.x))
+    }
+
+    /// Drops `item` on the nearest walkable tile to `pos` that has nothing
+    /// lying on it, returning where it landed
+    pub fn drop_near(&mut self, pos: Position, item: Item) -> Option<Position> {
+        let max_radius = self.width.max(self.height) as i32;
+        for radius in 0..=max_radius {
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    if dx.abs().max(dy.abs()) != radius {
+                        continue;
+                    }
+                    let spot = Position::new(pos.x + dx, pos.y + dy);
+                    if self
+                        .get_tile(spot.x, spot.y)
+                        .is_some_and(|tile| tile.tile_type == TileType::Floor)
+                        && !self.items.contains_key(&spot)
+                        && !self.npcs.contains_key(&spot)
+                    {
+                        self.items.insert(spot, item);
+                        return Some(spot);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Every walkable tile that can be walked to from `start`, moving a
+    /// step up, down, left or right at a time
+    pub fn reachable_from(&self, start: Position) -> HashSet<Position> {
+        let mut reached = HashSet::new();
+        if !self.is_tile_walkable(start) {
+            return reached;
+        }
+        reached.insert(start);
+        let mut frontier = VecDeque::from([start]);
+        while let Some(pos) = frontier.pop_front() {
+            for (dx, dy) in [(0, -1), (0, 1), (-1, 0), (1, 0)] {
+                let next = Position::new(pos.x + dx, pos.y + dy);
+                if self.is_tile_walkable(next) && reached.insert(next) {
+                    frontier.push_back(next);
+                }
+            }
+        }
+        reached
+    }
+
+    /// Walks the level from `from` and reports whether a step can be taken
+    /// and the stairs and exit got to
+    pub fn validate_reachability(&self, from: Position) -> ReachabilityReport {
+        let reached = self.reachable_from(from);
+        let landmarks = [
+            (Landmark::StairsDown, self.stairs_down_position),
+            (Landmark::StairsUp, self.stairs_up_position),
+            (Landmark::Exit, self.exit_position),
+        ]
+        .into_iter()
+        .filter_map(|(landmark, pos)| pos.map(|pos| (landmark, pos, reached.contains(&pos))))
+        .collect();
+        ReachabilityReport {
+            from,
+            can_move: [(0, -1), (0, 1), (-1, 0), (1, 0)]
+                .iter()
+                .any(|(dx, dy)| self.is_tile_walkable(Position::new(from.x + dx, from.y + dy))),
+            landmarks,
+            reached_tiles: reached.len(),
+            walkable_tiles: self
+                .tiles
+                .iter()
+                .flatten()
+                .filter(|tile| tile.tile_type.is_walkable())
+                .count(),
+        }
+    }
+
+    /// The open tile nearest `from` that the way on can be walked to from,
+    /// with no enemy on it: where a stuck player is pulled free to
+    pub fn nearest_unstuck_position(&self, from: Position) -> Option<Position> {
+        let way_on = self
+            .stairs_down_position
+            .or(self.exit_position)
+            .or(self.stairs_up_position)?;
+        self.reachable_from(way_on)
+            .into_iter()
+            .filter(|pos| {
+                pos != &from
+                    && !self.enemies.contains_key(pos)
+                    && matches!(
+                        self.tiles[pos.y as usize][pos.x as usize].tile_type,
+                        TileType::Floor
+                            | TileType::Door
+                            | TileType::ShallowWater
+                            | TileType::Rubble
+                    )
+            })
+            .min_by_key(|pos| {
+                let (dx, dy) = (pos.x - from.x, pos.y - from.y);
+                (dx * dx + dy * dy, pos.y, pos.x)
+            })
+    }
+
+    /// Whether the enemy at `pos` is close enough to the player, and in
+    /// view, to spot them
+    pub fn enemy_sees_player(&self, pos: Position) -> bool {
+        let in_view = self.get_tile(pos.x, pos.y).is_some_and(|tile| tile.visible);
+        let distance = (pos.x - self.player_position.x)
+            .abs()
+            .max((pos.y - self.player_position.y).abs());
+        in_view && distance <= NOTICE_RADIUS
+    }
+
+    /// Whether an enemy at `pos` has a clear shot at the player no more than
+    /// `range` tiles away: the player can see it and no wall stands on the
+    /// straight line between them
+    pub fn in_line_of_fire(&self, pos: Position, range: i32) -> bool {
+        let in_view = self.get_tile(pos.x, pos.y).is_some_and(|tile| tile.visible);
+        let distance = (pos.x - self.player_position.x)
+            .abs()
+            .max((pos.y - self.player_position.y).abs());
+        in_view && distance <= range && self.clear_line(pos, self.player_position)
+    }
+
+    /// Walkable tiles an enemy could hit the player on: those next to
+    /// each enemy and, for one that shoots, those within its range that it
+    /// has a clear line to. With `visible_only`, only enemies the map
+    /// shows count, so the set never gives away one the player can't see.
+    pub fn threatened_tiles(&self, visible_only: bool) -> HashSet<Position> {
+        let mut threatened = HashSet::new();
+        for (&pos, enemy) in &self.enemies {
+            let shown = self.get_tile(pos.x, pos.y).is_some_and(|tile| tile.visible)
+                || self.glimpsed_positions.contains(&pos);
+            if visible_only && !shown {
+                continue;
+            }
+            let reach = enemy.ranged.map_or(1, |ranged| ranged.range.max(1));
+            for dy in -reach..=reach {
+                for dx in -reach..=reach {
+                    let target = Position::new(pos.x + dx, pos.y + dy);
+                    if target == pos || !self.is_tile_walkable(target) {
+                        continue;
+                    }
+                    let adjacent = dx.abs() <= 1 && dy.abs() <= 1;
+                    if adjacent || self.clear_line(pos, target) {
+                        threatened.insert(target);
+                    }
+                }
+            }
+        }
+        threatened
+    }
+
+    /// Whether nothing that blocks sight stands on the straight line
+    /// between `from` and `to`
+    fn clear_line(&self, from: Position, to: Position) -> bool {
+        line_between(from, to).all(|step| {
+            self.get_tile(step.x, step.y)
+                .is_some_and(|tile| !tile.tile_type.blocks_sight())
+        })
+    }
+
+    /// Describes what the player knows about a position for the look
+    /// command, or None if it has never been seen
+    pub fn describe_position(&self, pos: Position) -> Option<String> {
+        let tile = self.get_tile(pos.x, pos.y)?;
+        if !tile.explored {
+            return None;
+        }
+
+        if pos == self.player_position {
+            return Some("That's you.".to_string());
+        }
+        if Some(pos) == self.companion_position {
+            return Some("Your companion.".to_string());
+        }
+        if let Some(npc) = self.npcs.get(&pos).filter(|_| tile.visible) {
+            return Some(format!(
+                "{} - friendly; walk into them to talk",
+                npc.kind.name()
+            ));
+        }
+
+        if tile.visible {
+            if let Some(enemy) = self.enemies.get(&pos) {
+                let wielding = enemy
+                    .wielding()
+                    .map(|wielding| format!(", {wielding}"))
+                    .unwrap_or_default();
+                let shoots = enemy
+                    .ranged
+                    .map(|ranged| format!(", shoots up to {} tiles", ranged.range))
+                    .unwrap_or_default();
+                return Some(format!(
+                    "{} (Level {}{wielding}) - HP {}/{}{shoots}, {}",
+                    enemy.name,
+                    enemy.level,
+                    enemy.health,
+                    enemy.max_health,
+                    enemy.alertness.describe()
+                ));
+            }
+
+            // Chest contents stay hidden until opened, and a trap until
+            // it's found
+            if let Some(trap) = self.chest_contents.get(&pos).and_then(Chest::found_trap) {
+                return Some(format!("Chest, with a {} found on it", trap.name()));
+            }
+            if tile.tile_type != TileType::Chest {
+                if let Some(item) = self.items.get(&pos) {
+                    return Some(format!("{} (on the floor)", item.name_with_stats()));
+                }
+            }
+            if let Some(gold) = self.gold.get(&pos) {
+                return Some(format!("{gold} gold (on the floor)"));
+            }
+            if let Some(corpse) = self.corpses.get(&pos) {
+                let searched = if corpse.searched { ", searched" } else { "" };
+                return Some(format!("{} corpse{searched}", corpse.name));
+            }
+        }
+
+        if self.spent_altars.contains(&pos) {
+            return Some("Altar, silent now".to_string());
+        }
+        Some(tile.tile_type.name().to_string())
     }
 
     // More methods for field of view calculations would be added here
 }
+
+/// The tiles on the straight line from `from` to `to`, leaving out both
+/// ends (Bresenham's line)
+fn line_between(from: Position, to: Position) -> impl Iterator<Item = Position> {
+    let (dx, dy) = ((to.x - from.x).abs(), -(to.y - from.y).abs());
+    let (step_x, step_y) = ((to.x - from.x).signum(), (to.y - from.y).signum());
+    let mut error = dx + dy;
+    let mut at = from;
+    std::iter::from_fn(move || {
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            at.x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            at.y += step_y;
+        }
+        Some(at)
+    })
+    .take_while(move |&step| step != to)
+}
+
+/// Serializes position-keyed maps as lists of pairs, since JSON object keys
+/// must be strings. The pairs go row by row, so the same level always
+/// writes the same JSON.
+mod position_map {
+    use super::Position;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    pub fn serialize<V, S>(map: &HashMap<Position, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        V: Serialize,
+        S: Serializer,
+    {
+        let mut pairs: Vec<_> = map.iter().collect();
+        pairs.sort_by_key(|(pos, _)| (pos.y, pos.x));
+        serializer.collect_seq(pairs)
+    }
+
+    pub fn deserialize<'de, V, D>(deserializer: D) -> Result<HashMap<Position, V>, D::Error>
+    where
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        let pairs = Vec::<(Position, V)>::deserialize(deserializer)?;
+        Ok(pairs.into_iter().collect())
+    }
+}
+
+/// Serializes sets of positions row by row, for the same reason
+mod position_set {
+    use super::Position;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::collections::HashSet;
+
+    pub fn serialize<S: Serializer>(
+        set: &HashSet<Position>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut positions: Vec<_> = set.iter().collect();
+        positions.sort_by_key(|pos| (pos.y, pos.x));
+        serializer.collect_seq(positions)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<HashSet<Position>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        HashSet::deserialize(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::Consumable;
+    use crate::world::enemy::{EnemyType, LEADER_RALLY_BONUS};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// A small lit room with the player in the middle
+    fn lit_level() -> Level {
+        let mut level = Level::new(20, 10);
+        for row in level.tiles.iter_mut() {
+            for tile in row.iter_mut() {
+                *tile = Tile::floor();
+                tile.explored = true;
+                tile.visible = true;
+            }
+        }
+        level.player_position = Position::new(2, 2);
+        level
+    }
+
+    #[test]
+    fn test_a_room_of_three_gets_a_leader_whose_aura_lasts_while_it_lives() {
+        let mut level = lit_level();
+        // Room floors run from 1 to 8 across and 1 to 6 down
+        level.rooms = vec![Room::new(0, 0, 9, 7), Room::new(10, 0, 9, 7)];
+        let goblin = |level| Enemy::new("Goblin".to_string(), EnemyType::Goblin, level);
+        let (strongest, near, far) = (
+            Position::new(4, 3),
+            Position::new(2, 2),
+            Position::new(8, 6),
+        );
+        level.enemies.insert(strongest, goblin(3));
+        level.enemies.insert(near, goblin(1));
+        level.enemies.insert(far, goblin(1));
+        // Two to a room aren't a pack
+        level.enemies.insert(Position::new(12, 2), goblin(4));
+        level.enemies.insert(Position::new(14, 2), goblin(4));
+
+        level.promote_pack_leaders();
+        let leader = &level.enemies[&strongest];
+        let plain = goblin(3);
+        assert!(leader.leader);
+        assert_eq!(leader.name, "Pack Leader Goblin");
+        assert_eq!(
+            leader.stats.dexterity,
+            (plain.stats.dexterity as f32 * 1.3).round() as i32
+        );
+        assert!(leader.max_health > plain.max_health);
+        assert_eq!(
+            level.enemies.values().filter(|enemy| enemy.leader).count(),
+            1
+        );
+
+        // Its room-mates within reach hit 1 harder, and stop once it's dead
+        let attack = level.enemies[&near].attack_damage();
+        level.rally_packs();
+        assert!(level.enemies[&near].rallied);
+        assert!(level.enemies[&far].rallied);
+        assert!(!level.enemies[&Position::new(12, 2)].rallied);
+        assert_eq!(
+            level.enemies[&near].attack_damage(),
+            attack + LEADER_RALLY_BONUS
+        );
+        assert_eq!(level.pack_of(strongest, 2), [near]);
+
+        level.get_enemy_at_mut(&strongest).unwrap().health = 0;
+        level.rally_packs();
+        assert!(!level.enemies[&near].rallied);
+        assert_eq!(level.enemies[&near].attack_damage(), attack);
+    }
+
+    #[test]
+    fn test_describe_enemy_shows_health_and_awareness() {
+        let mut level = lit_level();
+        let mut goblin = Enemy::new("Goblin".to_string(), EnemyType::Goblin, 2);
+        goblin.health = goblin.max_health / 2;
+        let (health, max_health) = (goblin.health, goblin.max_health);
+        level.enemies.insert(Position::new(15, 2), goblin.clone());
+        goblin.alert(level.player_position);
+        level.enemies.insert(Position::new(4, 2), goblin);
+
+        assert_eq!(
+            level.describe_position(Position::new(4, 2)),
+            Some(format!(
+                "Goblin (Level 2) - HP {health}/{max_health}, is hunting you"
+            ))
+        );
+        assert!(level
+            .describe_position(Position::new(15, 2))
+            .unwrap()
+            .ends_with("hasn't noticed you"));
+        assert!(level.enemy_sees_player(Position::new(4, 2)));
+        assert!(!level.enemy_sees_player(Position::new(15, 2)));
+    }
+
+    #[test]
+    fn test_describe_items_tiles_and_unseen_positions() {
+        let mut level = lit_level();
+        let potion = Item::Consumable(Consumable::generate_with_rng(
+            1,
+            &mut StdRng::seed_from_u64(1),
+        ));
+        let name = potion.name_with_stats();
+        level.items.insert(Position::new(3, 3), potion.clone());
+        level.tiles[4][4] = Tile::chest();
+        level.tiles[4][4].explored = true;
+        level.tiles[4][4].visible = true;
+        level.items.insert(Position::new(4, 4), potion);
+        level.tiles[5][5].explored = false;
+
+        assert_eq!(
+            level.describe_position(Position::new(3, 3)),
+            Some(format!("{name} (on the floor)"))
+        );
+        assert_eq!(
+            level.describe_position(Position::new(4, 4)),
+            Some("Chest".to_string())
+        );
+        assert_eq!(
+            level.describe_position(Position::new(6, 6)),
+            Some("Floor".to_string())
+        );
+        assert_eq!(
+            level.describe_position(Position::new(2, 2)),
+            Some("That's you.".to_string())
+        );
+        assert_eq!(level.describe_position(Position::new(5, 5)), None);
+        assert_eq!(level.describe_position(Position::new(-1, 0)), None);
+    }
+
+    #[test]
+    fn test_adjacent_items_are_announced_once_and_remembered() {
+        let mut level = lit_level();
+        let potion = Item::Consumable(Consumable::health_potion(20));
+        level.items.insert(Position::new(3, 3), potion.clone());
+        level.items.insert(Position::new(5, 2), potion);
+        assert_eq!(level.announce_adjacent_items().len(), 1);
+        assert!(level.announce_adjacent_items().is_empty());
+
+        // The announced spots are saved with the level
+        let json = serde_json::to_string(&level).unwrap();
+        let mut loaded: Level = serde_json::from_str(&json).unwrap();
+        assert!(loaded.announce_adjacent_items().is_empty());
+
+        // Something dropped on the same spot later is pointed out again
+        let spot = Position::new(3, 3);
+        let item = loaded.remove_item_at(&spot).unwrap();
+        loaded.items.insert(spot, item);
+        assert_eq!(loaded.announce_adjacent_items().len(), 1);
+    }
+
+    #[test]
+    fn test_generated_chests_always_hold_something() {
+        for level_num in 1..=40 {
+            let level = Level::generate(1 + level_num % 4, level_num, DungeonType::Ruins, false);
+            for (y, row) in level.tiles.iter().enumerate() {
+                for (x, tile) in row.iter().enumerate() {
+                    if tile.tile_type == TileType::Chest {
+                        let chest = &level.chest_contents[&Position::new(x as i32, y as i32)];
+                        assert!(!chest.items.is_empty());
+                        assert!(chest.gold > 0);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_generated_levels_keep_to_their_budget() {
+        for (difficulty, level_num, is_final) in [(1, 1, false), (3, 4, false), (6, 8, true)] {
+            let budget = LevelBudget::new(difficulty, level_num, is_final);
+            let level = Level::generate(difficulty, level_num, DungeonType::Mountain, is_final);
+            assert!(level.enemy_points() <= budget.enemy_points);
+            assert!(!level.enemies.is_empty());
+            assert_eq!(level.chest_contents.len(), budget.chests as usize);
+            assert!(level.exit_position.is_some() == is_final);
+        }
+    }
+
+    #[test]
+    fn test_only_the_final_level_has_an_exit() {
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let is_final = seed % 2 == 0;
+            let level = Level::generate_with_rng(
+                1 + seed as u32 % 6,
+                3,
+                DungeonType::Ruins,
+                is_final,
+                &mut rng,
+            );
+            let exits: Vec<Position> = level
+                .tiles
+                .iter()
+                .enumerate()
+                .flat_map(|(y, row)| {
+                    row.iter()
+                        .enumerate()
+                        .filter(|(_, tile)| tile.tile_type == TileType::Exit)
+                        .map(move |(x, _)| Position::new(x as i32, y as i32))
+                })
+                .collect();
+            if is_final {
+                assert_eq!(exits, [level.exit_position.unwrap()], "seed {seed}");
+                assert_eq!(level.stairs_down_position, None);
+            } else {
+                assert!(exits.is_empty(), "seed {seed}");
+                assert!(level.stairs_down_position.is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn test_chests_are_spread_from_near_the_start_to_far_from_it() {
+        for seed in 0..100 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let level = Level::generate_with_rng(4, 3, DungeonType::Ruins, false, &mut rng);
+            let distances = path_distances(&level.tiles, level.player_position);
+            let farthest = distances.values().copied().max().unwrap();
+            // Every chest can be walked to, and each half of the walk with
+            // room for one holds one
+            let bands: Vec<usize> = level
+                .chest_contents
+                .keys()
+                .map(|pos| distance_band(distances[pos], farthest, 2))
+                .collect();
+            let spots = level.chest_spots_by_band(&distances, 2);
+            for (band, spots) in spots.iter().enumerate() {
+                assert!(spots.is_empty() || bands.contains(&band), "seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_terrain_follows_the_dungeon_and_lava_stays_clear_of_the_way() {
+        let dungeon_types = [
+            DungeonType::Ruins,
+            DungeonType::Forest,
+            DungeonType::Mountain,
+            DungeonType::Cavern,
+        ];
+        let mut seen: Vec<(DungeonType, TileType)> = Vec::new();
+        for seed in 0..200 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let dungeon_type = dungeon_types[seed as usize % dungeon_types.len()];
+            let is_final = seed % 5 == 0;
+            let level = Level::generate_with_rng(3, 2, dungeon_type, is_final, &mut rng);
+
+            let tile_at = |pos: Position| level.tiles[pos.y as usize][pos.x as usize].tile_type;
+            assert_eq!(tile_at(level.player_position), TileType::Floor);
+            for pos in [
+                level.stairs_down_position,
+                level.stairs_up_position,
+                level.exit_position,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                assert!(!tile_at(pos).is_hazardous() && tile_at(pos) != TileType::Floor);
+            }
+            for pos in level.chest_contents.keys() {
+                assert_eq!(tile_at(*pos), TileType::Chest, "seed {seed}");
+            }
+            for pos in level.enemies.keys() {
+                assert!(!tile_at(*pos).is_hazardous(), "seed {seed}");
+            }
+            for row in &level.tiles {
+                for tile in row {
+                    if !seen.contains(&(dungeon_type, tile.tile_type)) {
+                        seen.push((dungeon_type, tile.tile_type));
+                    }
+                }
+            }
+        }
+
+        assert!(seen.contains(&(DungeonType::Ruins, TileType::Rubble)));
+        assert!(seen.contains(&(DungeonType::Forest, TileType::ShallowWater)));
+        assert!(seen.contains(&(DungeonType::Mountain, TileType::Lava)));
+        assert!(seen.contains(&(DungeonType::Cavern, TileType::Lava)));
+        assert!(!seen.contains(&(DungeonType::Forest, TileType::Lava)));
+        assert!(!seen.contains(&(DungeonType::Ruins, TileType::ShallowWater)));
+    }
+
+    #[test]
+    fn test_npcs_stand_on_open_floor_and_never_in_walls() {
+        let mut met = 0;
+        for seed in 0..200 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut level = Level::generate_with_rng(3, 2, DungeonType::Cavern, false, &mut rng);
+            // Chests hidden later, as quests do, go elsewhere
+            for _ in 0..5 {
+                let item = Item::Consumable(Consumable::health_potion(20));
+                level.place_in_chest(item, &mut rng);
+            }
+            for pos in level.npcs.keys() {
+                met += 1;
+                let tile = level.tiles[pos.y as usize][pos.x as usize].tile_type;
+                assert_eq!(tile, TileType::Floor, "seed {seed}");
+                assert!(!level.enemies.contains_key(pos), "seed {seed}");
+                assert!(!level.items.contains_key(pos), "seed {seed}");
+                assert_ne!(*pos, level.player_position, "seed {seed}");
+            }
+        }
+        assert!(met > 20, "only {met} NPCs in 200 levels");
+    }
+
+    #[test]
+    fn test_special_rooms_hold_what_their_kind_promises() {
+        let dungeon_types = [
+            DungeonType::Ruins,
+            DungeonType::Forest,
+            DungeonType::Mountain,
+            DungeonType::Cavern,
+        ];
+        let mut seen = Vec::new();
+        for seed in 0..120 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let dungeon_type = dungeon_types[seed as usize % dungeon_types.len()];
+            let level = Level::generate_with_rng(3, 2, dungeon_type, false, &mut rng);
+
+            assert!(!level.special_rooms.is_empty(), "seed {seed}");
+            assert!(level.special_rooms.len() <= MAX_SPECIAL_ROOMS);
+            for special in &level.special_rooms {
+                assert!(special.room > 0 && Some(special.room) != level.way_on_room());
+                assert!(RoomKind::for_dungeon(dungeon_type).contains(&special.kind));
+                let room = &level.rooms[special.room];
+                match special.kind {
+                    RoomKind::Shrine => {
+                        let altar = room.center();
+                        let tile = level.tiles[altar.y as usize][altar.x as usize].tile_type;
+                        assert_eq!(tile, TileType::Altar, "seed {seed}");
+                    }
+                    RoomKind::Armory => assert!(
+                        level.chest_contents.iter().any(|(pos, chest)| {
+                            room.contains(*pos)
+                                && chest
+                                    .items
+                                    .iter()
+                                    .any(|item| matches!(item, Item::Equipment(_)))
+                        }),
+                        "seed {seed}"
+                    ),
+                    RoomKind::Library => assert!(
+                        level.items.iter().any(|(pos, item)| {
+                            room.contains(*pos) && item.name().contains("Scroll")
+                        }),
+                        "seed {seed}"
+                    ),
+                    RoomKind::FloodedChamber => {
+                        assert!(level.tiles[(room.y1 + 1) as usize..room.y2 as usize]
+                            .iter()
+                            .flat_map(|row| &row[(room.x1 + 1) as usize..room.x2 as usize])
+                            .all(|tile| tile.tile_type != TileType::Floor));
+                    }
+                }
+                if !seen.contains(&special.kind) {
+                    seen.push(special.kind);
+                }
+            }
+        }
+        assert_eq!(seen.len(), 4);
+
+        // A saved level remembers its special rooms
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut level = Level::generate_with_rng(3, 2, DungeonType::Forest, false, &mut rng);
+        level.player_position = level.rooms[level.special_rooms[0].room].center();
+        let kind = level.enter_special_room();
+        assert_eq!(kind, Some(level.special_rooms[0].kind));
+        assert_eq!(level.enter_special_room(), None);
+        let saved = serde_json::to_string(&level).unwrap();
+        let loaded: Level = serde_json::from_str(&saved).unwrap();
+        assert_eq!(loaded.special_rooms, level.special_rooms);
+    }
+
+    #[test]
+    fn test_wanderers_spawn_out_of_sight() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..20 {
+            let mut level = Level::generate(2, 2, DungeonType::Cavern, false);
+            level.enemies.clear();
+            let player = level.player_position;
+            // Everything near the player is in view
+            for (y, row) in level.visible_tiles.iter_mut().enumerate() {
+                for (x, visible) in row.iter_mut().enumerate() {
+                    *visible = (x as i32 - player.x).abs() <= 6 && (y as i32 - player.y).abs() <= 6;
+                }
+            }
+
+            let spawned = level.spawn_wanderers(6, 2, DungeonType::Cavern, 8, &mut rng);
+            assert_eq!(level.enemies.len(), spawned);
+            for pos in level.enemies.keys() {
+                let distance = (pos.x - player.x).abs().max((pos.y - player.y).abs());
+                assert!(distance > 8);
+                assert!(!level.visible_tiles[pos.y as usize][pos.x as usize]);
+                assert_eq!(
+                    level.tiles[pos.y as usize][pos.x as usize].tile_type,
+                    TileType::Floor
+                );
+                assert!(!level.items.contains_key(pos));
+                assert!(!level.chest_contents.contains_key(pos));
+                assert_ne!(Some(*pos), level.stairs_down_position);
+                assert_ne!(Some(*pos), level.stairs_up_position);
+            }
+        }
+    }
+
+    #[test]
+    fn test_remembered_tiles_hide_enemies() {
+        let mut level = lit_level();
+        let goblin = Enemy::new("Goblin".to_string(), EnemyType::Goblin, 1);
+        level.enemies.insert(Position::new(3, 2), goblin);
+        level.tiles[2][3].visible = false;
+
+        assert_eq!(
+            level.describe_position(Position::new(3, 2)),
+            Some("Floor".to_string())
+        );
+        assert!(!level.enemy_sees_player(Position::new(3, 2)));
+    }
+
+    #[test]
+    fn test_a_brawler_threatens_the_tiles_around_it() {
+        let mut level = lit_level();
+        let orc = Enemy::new("Orc".to_string(), EnemyType::Orc, 1);
+        level.enemies.insert(Position::new(5, 5), orc);
+        // A wall beside it is nowhere to stand
+        level.tiles[4][6] = Tile::wall();
+
+        let threatened = level.threatened_tiles(true);
+        let mut expected = HashSet::new();
+        for y in 4..=6 {
+            for x in 4..=6 {
+                expected.insert(Position::new(x, y));
+            }
+        }
+        expected.remove(&Position::new(5, 5));
+        expected.remove(&Position::new(6, 4));
+        assert_eq!(threatened, expected);
+
+        // Out of sight, it gives nothing away
+        level.tiles[5][5].visible = false;
+        assert!(level.threatened_tiles(true).is_empty());
+        assert_eq!(level.threatened_tiles(false), expected);
+    }
+
+    #[test]
+    fn test_an_archer_threatens_what_it_can_see_within_range() {
+        let mut level = lit_level();
+        let archer = Enemy::new("Archer".to_string(), EnemyType::GoblinArcher, 1);
+        let range = archer.ranged.unwrap().range;
+        let at = Position::new(10, 5);
+        level.enemies.insert(at, archer);
+        // A pillar two tiles to its left shades the row behind it
+        level.tiles[5][8] = Tile::wall();
+
+        let threatened = level.threatened_tiles(true);
+        assert!(threatened.contains(&Position::new(9, 5)));
+        assert!(threatened.contains(&Position::new(10 + range, 5)));
+        assert!(!threatened.contains(&Position::new(11 + range, 5)));
+        assert!(!threatened.contains(&Position::new(8, 5)));
+        assert!(!threatened.contains(&Position::new(7, 5)));
+        assert!(!threatened.contains(&at));
+    }
+}
@@ -0,0 +1,87 @@
+//! How much light a level has, and so how far the player sees on it
+
+use serde::{Deserialize, Serialize};
+
+use crate::world::DungeonType;
+
+/// Farthest the player sees on a dark level without a torch
+pub const DARK_VIEW_RADIUS: i32 = 3;
+/// Extra tiles of sight on brightly lit levels
+pub const BRIGHT_VIEW_BONUS: i32 = 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AmbientLight {
+    /// Only a torch lets the player see the usual distance
+    Dark,
+    #[default]
+    Normal,
+    /// Open sky lets the player see a little farther
+    Bright,
+}
+
+impl AmbientLight {
+    /// The light every level of a dungeon of `dungeon_type` has: caverns
+    /// are dark and forests bright
+    pub fn for_dungeon(dungeon_type: DungeonType) -> Self {
+        match dungeon_type {
+            DungeonType::Cavern => AmbientLight::Dark,
+            DungeonType::Forest => AmbientLight::Bright,
+            DungeonType::Ruins | DungeonType::Mountain => AmbientLight::Normal,
+        }
+    }
+
+    /// How far the player sees in this light, given the radius from the
+    /// settings and whether they carry a burning torch
+    pub fn view_radius(self, base_radius: i32, torch_lit: bool) -> i32 {
+        match self {
+            AmbientLight::Dark if !torch_lit => base_radius.min(DARK_VIEW_RADIUS),
+            AmbientLight::Dark | AmbientLight::Normal => base_radius,
+            AmbientLight::Bright => base_radius + BRIGHT_VIEW_BONUS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view_radius_for_each_light_with_and_without_a_torch() {
+        let base = 8;
+        for torch_lit in [false, true] {
+            assert_eq!(AmbientLight::Normal.view_radius(base, torch_lit), base);
+            assert_eq!(
+                AmbientLight::Bright.view_radius(base, torch_lit),
+                base + BRIGHT_VIEW_BONUS
+            );
+        }
+        assert_eq!(
+            AmbientLight::Dark.view_radius(base, false),
+            DARK_VIEW_RADIUS
+        );
+        assert_eq!(AmbientLight::Dark.view_radius(base, true), base);
+
+        // Darkness never lets the player see farther than the settings allow
+        assert_eq!(AmbientLight::Dark.view_radius(2, false), 2);
+    }
+
+    #[test]
+    fn test_caverns_are_dark_and_forests_bright() {
+        assert_eq!(
+            AmbientLight::for_dungeon(DungeonType::Cavern),
+            AmbientLight::Dark
+        );
+        assert_eq!(
+            AmbientLight::for_dungeon(DungeonType::Forest),
+            AmbientLight::Bright
+        );
+        assert_eq!(
+            AmbientLight::for_dungeon(DungeonType::Ruins),
+            AmbientLight::Normal
+        );
+        assert_eq!(
+            AmbientLight::for_dungeon(DungeonType::Mountain),
+            AmbientLight::Normal
+        );
+    }
+}
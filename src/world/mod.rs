@@ -1,17 +1,38 @@
+pub mod appearance;
+pub mod awareness;
+pub mod budget;
+pub mod chest;
+pub mod corpse;
 pub mod enemy;
 pub mod fog_factory;
 pub mod fog_of_war;
+pub mod generation;
 pub mod level;
+pub mod light;
+pub mod npc;
+pub mod placement;
+pub mod reachability;
+pub mod special_room;
 pub mod tile;
+pub mod weather;
 
 // Re-exports
-pub use enemy::Enemy;
-#[cfg(not(target_arch = "wasm32"))]
-pub use fog_factory::create_standard_fog_of_war;
+pub use appearance::{GlyphSet, MapFeature, TileAppearance};
+pub use awareness::{Alertness, Noise};
+pub use budget::LevelBudget;
+pub use chest::{Chest, ChestKind, ChestTrap};
+pub use corpse::Corpse;
+pub use enemy::{Enemy, EnemyKind};
 #[cfg(not(target_arch = "wasm32"))]
 pub use fog_of_war::FogOfWar;
+pub use generation::DungeonBuilder;
 pub use level::{Level, Position};
+pub use light::AmbientLight;
+pub use npc::{Dialogue, Npc, NpcOffer};
+pub use reachability::ReachabilityReport;
+pub use special_room::{RoomKind, SpecialRoom};
 pub use tile::{Tile, TileType};
+pub use weather::{AmbientEffect, AmbientEvent};
 
 use rand::Rng;
 use serde::{Deserialize, Serialize};
@@ -43,8 +64,7 @@ impl DungeonType {
         }
     }
 
-    pub fn random() -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn choose<R: Rng + ?Sized>(rng: &mut R) -> Self {
         match rng.gen_range(0..4) {
             0 => DungeonType::Ruins,
             1 => DungeonType::Forest,
@@ -54,6 +74,17 @@ impl DungeonType {
     }
 }
 
+/// Turns a level in a difficulty 1 dungeon has to be left alone before
+/// enemies move back in; harder dungeons refill sooner
+const BASE_REPOPULATION_TURNS: u32 = 300;
+/// The soonest any level refills, in turns
+const MIN_REPOPULATION_TURNS: u32 = 60;
+/// Most enemies that move back into a level at once
+const MAX_REPOPULATION_ENEMIES: usize = 4;
+/// Most times a level is generated before one the player can get around
+/// in is given up on
+const MAX_GENERATION_ATTEMPTS: u32 = 5;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Dungeon {
     pub name: String,
@@ -61,6 +92,13 @@ pub struct Dungeon {
     pub levels: Vec<Level>,
     pub current_level: usize,
     pub difficulty: u32,
+    /// Turn the player last left each level on, by level index
+    #[serde(default)]
+    pub left_on_turn: Vec<Option<u32>>,
+    /// Levels that came out cut off and were generated again, for the game
+    /// to log once it has the dungeon
+    #[serde(skip)]
+    pub generation_warnings: Vec<String>,
 }
 
 impl Dungeon {
@@ -70,70 +108,29 @@ impl Dungeon {
         difficulty: u32,
         num_levels: usize,
     ) -> Self {
-        let mut levels = Vec::new();
-
-        for i in 0..num_levels {
-            let is_final = i == num_levels - 1;
-            levels.push(Level::generate(
-                difficulty,
-                i as u32 + 1,
-                dungeon_type,
-                is_final,
-            ));
-        }
-
-        Dungeon {
+        Self::new_with_rng(
             name,
             dungeon_type,
-            levels,
-            current_level: 0,
             difficulty,
-        }
+            num_levels,
+            &mut rand::thread_rng(),
+        )
     }
 
-    pub fn generate_random(player_level: u32) -> Self {
-        let mut rng = rand::thread_rng();
-
-        let dungeon_type = DungeonType::random();
-        let difficulty = player_level.max(1);
-
-        // Generate a thematic name
-        let prefix = match rng.gen_range(0..4) {
-            0 => "Forgotten",
-            1 => "Ancient",
-            2 => "Mysterious",
-            _ => "Haunted",
-        };
-
-        let location = match dungeon_type {
-            DungeonType::Ruins => match rng.gen_range(0..3) {
-                0 => "Temple",
-                1 => "Citadel",
-                _ => "Palace",
-            },
-            DungeonType::Forest => match rng.gen_range(0..3) {
-                0 => "Grove",
-                1 => "Thicket",
-                _ => "Woods",
-            },
-            DungeonType::Mountain => match rng.gen_range(0..3) {
-                0 => "Peaks",
-                1 => "Summit",
-                _ => "Cliffs",
-            },
-            DungeonType::Cavern => match rng.gen_range(0..3) {
-                0 => "Caverns",
-                1 => "Depths",
-                _ => "Grotto",
-            },
-        };
-
-        let name = format!("{prefix} {location}");
-
-        // Number of levels increases with difficulty
-        let num_levels = 3 + (difficulty / 5).min(5) as usize;
+    pub fn new_with_rng<R: Rng + ?Sized>(
+        name: String,
+        dungeon_type: DungeonType,
+        difficulty: u32,
+        num_levels: usize,
+        rng: &mut R,
+    ) -> Self {
+        DungeonBuilder::new(name, dungeon_type, difficulty, num_levels, rng).build()
+    }
 
-        Dungeon::new(name, dungeon_type, difficulty, num_levels)
+    /// A dungeon of a random type and name; the same seeded `rng` always
+    /// builds the same dungeon
+    pub fn generate_with_rng<R: Rng + ?Sized>(difficulty: u32, rng: &mut R) -> Self {
+        DungeonBuilder::random(difficulty, rng).build()
     }
 
     pub fn current_level(&self) -> &Level {
@@ -165,4 +162,163 @@ impl Dungeon {
     pub fn is_final_level(&self) -> bool {
         self.current_level == self.levels.len() - 1
     }
+
+    /// The budget the current level was generated with
+    pub fn current_budget(&self) -> LevelBudget {
+        LevelBudget::new(
+            self.difficulty,
+            self.current_level().level_num,
+            self.is_final_level(),
+        )
+    }
+
+    /// Notes that the player is leaving the current level on `turn`
+    pub fn leave_level(&mut self, turn: u32) {
+        if self.left_on_turn.len() < self.levels.len() {
+            self.left_on_turn.resize(self.levels.len(), None);
+        }
+        self.left_on_turn[self.current_level] = Some(turn);
+        // The companion comes along
+        self.levels[self.current_level].companion_position = None;
+    }
+
+    /// Turns a level has to be left alone before enemies move back in
+    pub fn repopulation_delay(&self) -> u32 {
+        (BASE_REPOPULATION_TURNS / self.difficulty.max(1)).max(MIN_REPOPULATION_TURNS)
+    }
+
+    /// Spawns a few fresh enemies out of the player's sight if they have
+    /// been away from the current level for long enough by `turn`.
+    /// Returns how many moved in.
+    pub fn repopulate_current_level<R: Rng + ?Sized>(
+        &mut self,
+        turn: u32,
+        min_distance: i32,
+        rng: &mut R,
+    ) -> usize {
+        let Some(Some(left)) = self.left_on_turn.get(self.current_level).copied() else {
+            return 0;
+        };
+        if turn.saturating_sub(left) < self.repopulation_delay() {
+            return 0;
+        }
+
+        let count = (1 + self.difficulty as usize / 2).min(MAX_REPOPULATION_ENEMIES);
+        let (difficulty, dungeon_type) = (self.difficulty, self.dungeon_type);
+        self.current_level_mut()
+            .spawn_wanderers(count, difficulty, dungeon_type, min_distance, rng)
+    }
+}
+
+/// Generates level `level_num` with `generate` until the player can get
+/// from where they start to the stairs and exit, noting each level thrown
+/// away in `warnings`
+fn generate_reachable(
+    level_num: u32,
+    mut generate: impl FnMut() -> Level,
+    warnings: &mut Vec<String>,
+) -> Level {
+    let mut level = generate();
+    for _ in 1..MAX_GENERATION_ATTEMPTS {
+        let report = level.validate_reachability(level.player_position);
+        if report.is_ok() {
+            break;
+        }
+        warnings.push(format!(
+            "Level {level_num} came out cut off and was generated again. {}",
+            report.lines().join(". ")
+        ));
+        level = generate();
+    }
+    level
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_the_same_seed_builds_the_same_dungeon() {
+        let build = |seed| Dungeon::generate_with_rng(4, &mut StdRng::seed_from_u64(seed));
+        let (first, second) = (build(1580), build(1580));
+        assert_eq!(first.name, second.name);
+        assert_eq!(first.levels.len(), second.levels.len());
+        for (a, b) in first.levels.iter().zip(&second.levels) {
+            let tiles = |level: &Level| {
+                level
+                    .tiles
+                    .iter()
+                    .flatten()
+                    .map(|tile| tile.tile_type)
+                    .collect::<Vec<_>>()
+            };
+            let enemies = |level: &Level| {
+                let mut enemies: Vec<(i32, i32, String)> = level
+                    .enemies
+                    .iter()
+                    .map(|(pos, enemy)| (pos.x, pos.y, enemy.name.clone()))
+                    .collect();
+                enemies.sort();
+                enemies
+            };
+            assert_eq!(tiles(a), tiles(b));
+            assert_eq!(enemies(a), enemies(b));
+        }
+    }
+
+    #[test]
+    fn test_cut_off_levels_are_generated_again() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let good = Level::generate_with_rng(2, 1, DungeonType::Ruins, false, &mut rng);
+        assert!(good.validate_reachability(good.player_position).is_ok());
+        let mut walled_in = good.clone();
+        let stairs = walled_in.stairs_down_position.unwrap();
+        for (dx, dy) in [(0, -1), (0, 1), (-1, 0), (1, 0)] {
+            walled_in.tiles[(stairs.y + dy) as usize][(stairs.x + dx) as usize] = Tile::wall();
+        }
+        let report = walled_in.validate_reachability(walled_in.player_position);
+        assert!(report.can_move && !report.is_ok());
+        assert!(report.lines().contains(&format!(
+            "Stairs down at ({}, {}): unreachable",
+            stairs.x, stairs.y
+        )));
+
+        // The first try comes out walled in, the second is fine
+        let mut tries = vec![good.clone(), walled_in.clone()];
+        let mut warnings = Vec::new();
+        let level = generate_reachable(2, || tries.pop().unwrap(), &mut warnings);
+        assert!(level.validate_reachability(level.player_position).is_ok());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("Level 2 came out cut off"));
+
+        // Generation gives up rather than looping forever
+        let mut warnings = Vec::new();
+        generate_reachable(2, || walled_in.clone(), &mut warnings);
+        assert_eq!(warnings.len(), MAX_GENERATION_ATTEMPTS as usize - 1);
+    }
+
+    #[test]
+    fn test_levels_refill_only_after_the_delay() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut dungeon = Dungeon::new("Test Caverns".to_string(), DungeonType::Cavern, 2, 3);
+        assert_eq!(dungeon.repopulation_delay(), 150);
+        for level in &mut dungeon.levels {
+            level.enemies.clear();
+        }
+
+        // Never left, so nothing to refill
+        assert_eq!(dungeon.repopulate_current_level(1000, 5, &mut rng), 0);
+
+        dungeon.leave_level(10);
+        dungeon.go_to_next_level().unwrap();
+        dungeon.go_to_previous_level().unwrap();
+        assert_eq!(dungeon.repopulate_current_level(100, 5, &mut rng), 0);
+        assert!(dungeon.current_level().enemies.is_empty());
+
+        let spawned = dungeon.repopulate_current_level(160, 5, &mut rng);
+        assert!((1..=2).contains(&spawned));
+        assert_eq!(dungeon.current_level().enemies.len(), spawned);
+    }
 }
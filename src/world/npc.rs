@@ -0,0 +1,302 @@
+//! Friendly folk met in the dungeons
+//!
+//! Now and then a level has someone on it who isn't out to kill the player:
+//! a lost adventurer, a hermit or the ghost of a scholar. Enemies leave them
+//! be and the player can't attack them. Walking into one, or pressing G
+//! beside them, starts a talk: a few lines that suit who they are and where
+//! they are, then the one thing they can do for the player, once. A hermit
+//! marks the way down or a chest on the map, an adventurer sells something
+//! for gold and a scholar sends the player after a lost folio.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::item::Item;
+use crate::world::DungeonType;
+
+/// Chance of a level having someone friendly on it
+pub const NPC_CHANCE: f64 = 0.3;
+
+/// Gold a lost adventurer asks for what they sell, on level 1
+const TRADE_BASE_PRICE: u32 = 20;
+/// Gold added to the price for each level deeper
+const TRADE_PRICE_PER_LEVEL: u32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NpcKind {
+    /// Sells something from their pack
+    LostAdventurer,
+    /// Knows the level and marks it on the player's map
+    Hermit,
+    /// Sends the player after a lost folio
+    GhostlyScholar,
+}
+
+impl NpcKind {
+    pub const ALL: [NpcKind; 3] = [
+        NpcKind::LostAdventurer,
+        NpcKind::Hermit,
+        NpcKind::GhostlyScholar,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NpcKind::LostAdventurer => "Lost Adventurer",
+            NpcKind::Hermit => "Hermit",
+            NpcKind::GhostlyScholar => "Ghostly Scholar",
+        }
+    }
+}
+
+/// What each kind of NPC says in each kind of dungeon, before getting to
+/// what they can do for the player
+const DIALOGUE: [(NpcKind, DungeonType, &[&str]); 12] = [
+    (
+        NpcKind::LostAdventurer,
+        DungeonType::Ruins,
+        &[
+            "\"Another living face! I'd started talking to the statues.\"",
+            "\"Every corridor in these ruins looks the same. I've been round this one twice.\"",
+        ],
+    ),
+    (
+        NpcKind::LostAdventurer,
+        DungeonType::Forest,
+        &[
+            "\"Careful where you step. The roots here move when you're not looking.\"",
+            "\"I came in after a friend. I've not found them, and now I can't find the way out.\"",
+        ],
+    ),
+    (
+        NpcKind::LostAdventurer,
+        DungeonType::Mountain,
+        &[
+            "\"The air's thin this high. Sit a moment, if you like.\"",
+            "\"My rope snapped on the last ledge. I'm not going any higher.\"",
+            "\"I'm not going any lower either, mind.\"",
+        ],
+    ),
+    (
+        NpcKind::LostAdventurer,
+        DungeonType::Cavern,
+        &[
+            "\"Is that a light? Keep it close - the dark here has teeth.\"",
+            "\"My torch gave out a day ago. Or a week. It's hard to tell down here.\"",
+        ],
+    ),
+    (
+        NpcKind::Hermit,
+        DungeonType::Ruins,
+        &[
+            "An old man sits cross-legged among fallen columns.",
+            "\"I knew these halls when they had roofs. I know them still.\"",
+        ],
+    ),
+    (
+        NpcKind::Hermit,
+        DungeonType::Forest,
+        &[
+            "A woman in a cloak of moss looks up from her herbs.",
+            "\"The trees tell me where things are, if you ask them politely.\"",
+        ],
+    ),
+    (
+        NpcKind::Hermit,
+        DungeonType::Mountain,
+        &[
+            "A wiry hermit pours you a cup of something steaming.",
+            "\"Forty winters on this mountain. Every stone has a name.\"",
+            "\"Most of them are rude names.\"",
+        ],
+    ),
+    (
+        NpcKind::Hermit,
+        DungeonType::Cavern,
+        &[
+            "A pale hermit blinks at your light as though it hurts.",
+            "\"I listen to the water. It runs everywhere down here, and it talks.\"",
+        ],
+    ),
+    (
+        NpcKind::GhostlyScholar,
+        DungeonType::Ruins,
+        &[
+            "A translucent figure in scholar's robes drifts between the pillars.",
+            "\"I catalogued these ruins once. Death has rather slowed the work.\"",
+        ],
+    ),
+    (
+        NpcKind::GhostlyScholar,
+        DungeonType::Forest,
+        &[
+            "A ghost hovers over a rotted lectern, turning pages that aren't there.",
+            "\"I came to study the old groves. The groves, it seems, studied me.\"",
+        ],
+    ),
+    (
+        NpcKind::GhostlyScholar,
+        DungeonType::Mountain,
+        &[
+            "A faint shape sits by a cold brazier, scribbling at nothing.",
+            "\"The temples up here kept the finest libraries. I meant to read them all.\"",
+        ],
+    ),
+    (
+        NpcKind::GhostlyScholar,
+        DungeonType::Cavern,
+        &[
+            "A ghost glows softly beside the crystals, its face buried in a book.",
+            "\"The crystals remember everything. I wrote down what they told me.\"",
+            "\"Then I dropped it.\"",
+        ],
+    ),
+];
+
+/// What someone met in the dungeon can do for the player, once
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum NpcOffer {
+    /// Mark the way down, or failing that an unseen chest, on the map
+    Hint,
+    /// Sell `item` for `price` gold
+    Trade { item: Item, price: u32 },
+    /// Send the player after a folio lost deeper down, through the quest
+    /// journal
+    Errand,
+}
+
+/// Someone friendly standing on the level
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Npc {
+    pub kind: NpcKind,
+    /// The dungeon they were met in, which picks what they say
+    pub dungeon_type: DungeonType,
+    pub offer: NpcOffer,
+    /// Whether they've done what they can for the player
+    #[serde(default)]
+    pub spent: bool,
+}
+
+/// A talk with an NPC, ready for a front-end to show
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dialogue {
+    /// "Hermit"
+    pub speaker: &'static str,
+    /// What they say, a line at a time
+    pub lines: Vec<String>,
+    /// What taking them up on their offer does, or None once it's spent
+    pub offer: Option<String>,
+}
+
+impl Npc {
+    pub fn new(kind: NpcKind, dungeon_type: DungeonType, offer: NpcOffer) -> Self {
+        Npc {
+            kind,
+            dungeon_type,
+            offer,
+            spent: false,
+        }
+    }
+
+    /// Someone of a random kind for level `level_num` of a `dungeon_type`
+    /// dungeon, with the offer that suits them
+    pub fn generate<R: Rng + ?Sized>(
+        dungeon_type: DungeonType,
+        level_num: u32,
+        rng: &mut R,
+    ) -> Self {
+        let kind = NpcKind::ALL[rng.gen_range(0..NpcKind::ALL.len())];
+        let offer = match kind {
+            NpcKind::LostAdventurer => NpcOffer::Trade {
+                item: Item::generate_with_rng(level_num, rng),
+                price: TRADE_BASE_PRICE + TRADE_PRICE_PER_LEVEL * level_num,
+            },
+            NpcKind::Hermit => NpcOffer::Hint,
+            NpcKind::GhostlyScholar => NpcOffer::Errand,
+        };
+        Npc::new(kind, dungeon_type, offer)
+    }
+
+    /// The flavor lines from `DIALOGUE` for who they are and where
+    pub fn flavor(&self) -> &'static [&'static str] {
+        DIALOGUE
+            .iter()
+            .find(|(kind, dungeon_type, _)| {
+                *kind == self.kind && *dungeon_type == self.dungeon_type
+            })
+            .map(|(_, _, lines)| *lines)
+            .expect("every NPC kind has lines for every dungeon type")
+    }
+
+    /// What they say about their offer, and what taking it does
+    fn pitch(&self) -> (String, String) {
+        match &self.offer {
+            NpcOffer::Hint => (
+                "\"Lost? I can mark what I know on your map.\"".to_string(),
+                "Ask what they know".to_string(),
+            ),
+            NpcOffer::Trade { item, price } => (
+                format!(
+                    "\"I'd part with my {} for {price} gold. I need it more than I need the weight.\"",
+                    item.labelled_name()
+                ),
+                format!("Buy the {} for {price} gold", item.labelled_name()),
+            ),
+            NpcOffer::Errand => (
+                "\"My folio fell somewhere deeper down. Fetch it out for me, would you?\"".to_string(),
+                "Agree to find the folio".to_string(),
+            ),
+        }
+    }
+
+    pub fn dialogue(&self) -> Dialogue {
+        let mut lines: Vec<String> = self.flavor().iter().map(|line| line.to_string()).collect();
+        let offer = if self.spent {
+            lines.push("\"Good luck down there.\"".to_string());
+            None
+        } else {
+            let (pitch, offer) = self.pitch();
+            lines.push(pitch);
+            Some(offer)
+        };
+        Dialogue {
+            speaker: self.kind.name(),
+            lines,
+            offer,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_every_kind_has_a_few_lines_in_every_dungeon() {
+        let dungeons = [
+            DungeonType::Ruins,
+            DungeonType::Forest,
+            DungeonType::Mountain,
+            DungeonType::Cavern,
+        ];
+        for kind in NpcKind::ALL {
+            for dungeon_type in dungeons {
+                let npc = Npc::new(kind, dungeon_type, NpcOffer::Hint);
+                assert!((2..=4).contains(&npc.flavor().len()));
+                assert!(npc.dialogue().lines.len() <= 4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_the_offer_goes_once_spent() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut npc = Npc::generate(DungeonType::Cavern, 2, &mut rng);
+        assert!(npc.dialogue().offer.is_some());
+        npc.spent = true;
+        let dialogue = npc.dialogue();
+        assert_eq!(dialogue.offer, None);
+        assert_eq!(dialogue.speaker, npc.kind.name());
+    }
+}
@@ -0,0 +1,237 @@
+//! Where the ways in and out of a level go
+//!
+//! Rooms are joined in a chain, so the last room dug can still end up a
+//! few steps from the first. The stairs down, or the final level's exit,
+//! go in a room a good walk from where the player starts instead: at least
+//! `MIN_STAIRS_DISTANCE_SHARE` of the way to the farthest tile, counting
+//! steps along the floor rather than as the crow flies. The stairs up go
+//! beside the start, so climbing back up lands the player where they came
+//! in.
+
+use std::collections::{HashMap, VecDeque};
+
+use rand::Rng;
+
+use crate::world::level::Room;
+use crate::world::{Position, Tile, TileType};
+
+/// How far the way on must be from the start, as a share of the steps to
+/// the tile farthest from it
+pub const MIN_STAIRS_DISTANCE_SHARE: f64 = 0.6;
+
+/// Where a level's stairs go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StairsPlacement {
+    /// The stairs down, or the final level's exit
+    pub way_on: Position,
+    /// The stairs up, beside the start, when the level has them
+    pub stairs_up: Option<Position>,
+    /// Fewest steps the way on had to be from the start
+    pub min_distance: u32,
+}
+
+/// Steps from `start` to every tile that can be walked to from it, moving
+/// up, down, left or right
+pub fn path_distances(tiles: &[Vec<Tile>], start: Position) -> HashMap<Position, u32> {
+    let walkable = |pos: Position| {
+        pos.x >= 0
+            && pos.y >= 0
+            && tiles
+                .get(pos.y as usize)
+                .and_then(|row| row.get(pos.x as usize))
+                .is_some_and(|tile| tile.tile_type.is_walkable())
+    };
+
+    let mut distances = HashMap::new();
+    if !walkable(start) {
+        return distances;
+    }
+    distances.insert(start, 0);
+    let mut frontier = VecDeque::from([start]);
+    while let Some(pos) = frontier.pop_front() {
+        let steps = distances[&pos] + 1;
+        for (dx, dy) in [(0, -1), (0, 1), (-1, 0), (1, 0)] {
+            let next = Position::new(pos.x + dx, pos.y + dy);
+            if walkable(next) && !distances.contains_key(&next) {
+                distances.insert(next, steps);
+                frontier.push_back(next);
+            }
+        }
+    }
+    distances
+}
+
+/// Which of `bands` equal slices of the steps out to `farthest` a tile
+/// `distance` steps away falls in, nearest first
+pub fn distance_band(distance: u32, farthest: u32, bands: usize) -> usize {
+    let band = u64::from(distance) * bands as u64 / (u64::from(farthest) + 1);
+    (band as usize).min(bands.saturating_sub(1))
+}
+
+/// Places the way on in the center of a room far enough from `start`, or
+/// failing any, on the floor tile farthest from it, and the stairs up, if
+/// `with_stairs_up`, on the floor beside `start`. Only floor that can be
+/// walked to from `start` is considered. None if there's no such floor
+/// but `start` itself.
+pub fn place_stairs<R: Rng + ?Sized>(
+    tiles: &[Vec<Tile>],
+    rooms: &[Room],
+    start: Position,
+    with_stairs_up: bool,
+    rng: &mut R,
+) -> Option<StairsPlacement> {
+    let distances = path_distances(tiles, start);
+    let is_floor =
+        |pos: &Position| tiles[pos.y as usize][pos.x as usize].tile_type == TileType::Floor;
+    let farthest = distances.values().copied().max().unwrap_or(0);
+    let min_distance = ((f64::from(farthest) * MIN_STAIRS_DISTANCE_SHARE).ceil() as u32).max(1);
+
+    let far_rooms: Vec<Position> = rooms
+        .iter()
+        .map(Room::center)
+        .filter(|center| is_floor(center))
+        .filter(|center| distances.get(center).is_some_and(|&d| d >= min_distance))
+        .collect();
+    let way_on = if far_rooms.is_empty() {
+        // Ties go to the top-left, so a seed always picks the same one
+        distances
+            .iter()
+            .filter(|(pos, _)| is_floor(pos))
+            .max_by_key(|(pos, &d)| (d, -pos.y, -pos.x))
+            .map(|(&pos, _)| pos)
+            .filter(|&pos| pos != start)?
+    } else {
+        far_rooms[rng.gen_range(0..far_rooms.len())]
+    };
+
+    let stairs_up = with_stairs_up
+        .then(|| {
+            [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                .into_iter()
+                .map(|(dx, dy)| Position::new(start.x + dx, start.y + dy))
+                .find(|pos| *pos != way_on && distances.contains_key(pos) && is_floor(pos))
+        })
+        .flatten();
+
+    Some(StairsPlacement {
+        way_on,
+        stairs_up,
+        min_distance,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{DungeonType, Level};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// A level of walls with `rooms` dug out, joined by nothing
+    fn dug(width: usize, height: usize, rooms: &[Room]) -> Vec<Vec<Tile>> {
+        let mut tiles = vec![vec![Tile::wall(); width]; height];
+        for room in rooms {
+            for y in (room.y1 + 1)..room.y2 {
+                for x in (room.x1 + 1)..room.x2 {
+                    tiles[y as usize][x as usize] = Tile::floor();
+                }
+            }
+        }
+        tiles
+    }
+
+    #[test]
+    fn test_the_way_on_is_a_long_walk_from_the_start_on_every_seed() {
+        let dungeon_types = [
+            DungeonType::Ruins,
+            DungeonType::Forest,
+            DungeonType::Mountain,
+            DungeonType::Cavern,
+        ];
+        for seed in 0..150 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let is_final = seed % 4 == 0;
+            let level = Level::generate_with_rng(
+                1 + seed as u32 % 8,
+                2,
+                dungeon_types[seed as usize % dungeon_types.len()],
+                is_final,
+                &mut rng,
+            );
+            let start = level.player_position;
+            let way_on = level.stairs_down_position.or(level.exit_position).unwrap();
+
+            // The level as dug, before anything was put on it
+            let tiles: Vec<Vec<Tile>> = level
+                .tiles
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|tile| match tile.tile_type {
+                            TileType::Wall | TileType::Door => tile.clone(),
+                            _ => Tile::floor(),
+                        })
+                        .collect()
+                })
+                .collect();
+            let placement = place_stairs(&tiles, &level.rooms, start, true, &mut rng).unwrap();
+            let distances = path_distances(&tiles, start);
+            for pos in [placement.way_on, way_on] {
+                assert!(distances[&pos] >= placement.min_distance, "seed {seed}");
+            }
+
+            let walked = path_distances(&level.tiles, start);
+            assert!(walked.contains_key(&way_on), "seed {seed}");
+            let stairs_up = level.stairs_up_position.unwrap();
+            assert_eq!(walked[&stairs_up], 1, "seed {seed}");
+            assert_ne!(
+                level.tiles[way_on.y as usize][way_on.x as usize].tile_type,
+                TileType::Wall
+            );
+        }
+    }
+
+    #[test]
+    fn test_the_way_on_never_lands_in_a_walled_off_pocket() {
+        // The start room, a corridor's worth of floor east of it, and a
+        // far room with no way in
+        let rooms = [
+            Room::new(1, 1, 6, 6),
+            Room::new(6, 2, 14, 4),
+            Room::new(30, 10, 8, 8),
+        ];
+        let tiles = dug(40, 20, &rooms);
+        let start = rooms[0].center();
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let placement = place_stairs(&tiles, &rooms, start, true, &mut rng).unwrap();
+            assert!(!rooms[2].contains(placement.way_on));
+            assert_eq!(
+                tiles[placement.way_on.y as usize][placement.way_on.x as usize].tile_type,
+                TileType::Floor
+            );
+            let distances = path_distances(&tiles, start);
+            assert!(distances[&placement.way_on] >= placement.min_distance);
+            let up = placement.stairs_up.unwrap();
+            assert_eq!(distances[&up], 1);
+        }
+
+        // Nowhere to go but the start
+        let mut walled = vec![vec![Tile::wall(); 3]; 3];
+        walled[1][1] = Tile::floor();
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(
+            place_stairs(&walled, &[], Position::new(1, 1), false, &mut rng),
+            None
+        );
+    }
+
+    #[test]
+    fn test_distance_bands_split_the_walk_evenly() {
+        assert_eq!(distance_band(0, 29, 3), 0);
+        assert_eq!(distance_band(9, 29, 3), 0);
+        assert_eq!(distance_band(10, 29, 3), 1);
+        assert_eq!(distance_band(29, 29, 3), 2);
+        assert_eq!(distance_band(5, 0, 1), 0);
+    }
+}
@@ -0,0 +1,97 @@
+//! Checking that a level can be got around
+//!
+//! A bad seed, or a bug in whatever changes a level as it's played, could
+//! leave the player walled in or the way on cut off. The audit walks the
+//! level from the player's tile and reports what it can't reach. Levels
+//! are audited as they're generated, and the player can ask to be pulled
+//! free when they're stuck.
+
+use std::fmt;
+
+use crate::world::Position;
+
+/// Share of the player's current health that pulling them free costs
+pub const UNSTICK_HEALTH_COST: f64 = 0.2;
+
+/// A way into or out of a level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Landmark {
+    StairsDown,
+    StairsUp,
+    Exit,
+}
+
+impl fmt::Display for Landmark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Landmark::StairsDown => write!(f, "Stairs down"),
+            Landmark::StairsUp => write!(f, "Stairs up"),
+            Landmark::Exit => write!(f, "Dungeon exit"),
+        }
+    }
+}
+
+/// What walking a level from one tile found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReachabilityReport {
+    /// Where the walk started
+    pub from: Position,
+    /// Whether any tile next to `from` can be stepped onto
+    pub can_move: bool,
+    /// Each way in or out of the level, where it is and whether the walk
+    /// reached it
+    pub landmarks: Vec<(Landmark, Position, bool)>,
+    /// Walkable tiles the walk reached
+    pub reached_tiles: usize,
+    /// Walkable tiles on the whole level
+    pub walkable_tiles: usize,
+}
+
+impl ReachabilityReport {
+    /// Whether the player can move and get to every way in and out
+    pub fn is_ok(&self) -> bool {
+        self.can_move && self.landmarks.iter().all(|&(_, _, reached)| reached)
+    }
+
+    /// The report, a line at a time, for the log: "Stairs down at (40, 18):
+    /// unreachable"
+    pub fn lines(&self) -> Vec<String> {
+        let verdict = if self.is_ok() { "ok" } else { "STUCK" };
+        let mut lines = vec![format!(
+            "Reachability from ({}, {}): {verdict}",
+            self.from.x, self.from.y
+        )];
+        if !self.can_move {
+            lines.push("No walkable tile next to the start".to_string());
+        }
+        for (landmark, pos, reached) in &self.landmarks {
+            let state = if *reached { "reachable" } else { "unreachable" };
+            lines.push(format!("{landmark} at ({}, {}): {state}", pos.x, pos.y));
+        }
+        lines.push(format!(
+            "Walkable tiles reached: {} of {}",
+            self.reached_tiles, self.walkable_tiles
+        ));
+        lines
+    }
+}
+
+/// Health pulling a player with `health` free costs: a fifth of it, at
+/// least 1, but never the last point
+pub fn unstick_cost(health: i32) -> i32 {
+    let cost = (f64::from(health) * UNSTICK_HEALTH_COST).round() as i32;
+    cost.max(1).min(health - 1).max(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pulling_free_costs_a_fifth_but_never_kills() {
+        assert_eq!(unstick_cost(100), 20);
+        assert_eq!(unstick_cost(12), 2);
+        assert_eq!(unstick_cost(3), 1);
+        assert_eq!(unstick_cost(1), 0);
+    }
+}
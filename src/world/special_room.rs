@@ -0,0 +1,135 @@
+//! Rooms with a character of their own
+//!
+//! Each level sets aside a room or two as a shrine, a library, an armory or
+//! a flooded chamber. The player is told what they've found the first time
+//! they walk in, and each kind holds something: a shrine has an altar that
+//! answers one prayer, a library a scroll, an armory a chest with equipment
+//! in it, and a flooded chamber stands under water.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::character::StatType;
+use crate::world::DungeonType;
+
+/// Most rooms a level sets aside
+pub const MAX_SPECIAL_ROOMS: usize = 2;
+
+/// Chance of a prayer at an altar bringing a curse instead of a blessing
+pub const CURSE_CHANCE: f64 = 0.2;
+
+/// Chance of a blessing being a point in a stat rather than healing
+pub const BOON_CHANCE: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoomKind {
+    /// Has an altar to pray at
+    Shrine,
+    /// Has a scroll lying on the floor
+    Library,
+    /// Has a chest with equipment in it
+    Armory,
+    /// Its floor is under shallow water
+    FloodedChamber,
+}
+
+impl RoomKind {
+    /// What a room of this kind can be in `dungeon_type`: only dungeons with
+    /// water in them have flooded chambers
+    pub fn for_dungeon(dungeon_type: DungeonType) -> &'static [RoomKind] {
+        match dungeon_type {
+            DungeonType::Forest | DungeonType::Cavern => &[
+                RoomKind::Shrine,
+                RoomKind::Library,
+                RoomKind::Armory,
+                RoomKind::FloodedChamber,
+            ],
+            DungeonType::Ruins | DungeonType::Mountain => {
+                &[RoomKind::Shrine, RoomKind::Library, RoomKind::Armory]
+            }
+        }
+    }
+
+    /// Said the first time the player walks in
+    pub fn flavor(self) -> &'static str {
+        match self {
+            RoomKind::Shrine => {
+                "Candles gutter around an old altar. This shrine still feels watched over."
+            }
+            RoomKind::Library => "Dusty bookshelves line the walls of this ancient library.",
+            RoomKind::Armory => "Racks of rusted weapons stand along the walls of an old armory.",
+            RoomKind::FloodedChamber => {
+                "Cold water laps at your ankles. This chamber has long since flooded."
+            }
+        }
+    }
+}
+
+/// A room of the level set aside as something special
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecialRoom {
+    pub kind: RoomKind,
+    /// Index of the room in `Level::rooms`
+    pub room: usize,
+    /// Whether the player has walked in yet
+    #[serde(default)]
+    pub entered: bool,
+}
+
+impl SpecialRoom {
+    pub fn new(kind: RoomKind, room: usize) -> Self {
+        SpecialRoom {
+            kind,
+            room,
+            entered: false,
+        }
+    }
+}
+
+/// How an altar answered a prayer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prayer {
+    /// One more point in the stat, for good
+    Boon(StatType),
+    /// Health and mana made whole
+    Healed,
+    /// One point less in the stat, for good
+    Curse(StatType),
+}
+
+/// Rolls the answer to a prayer at an altar
+pub fn pray<R: Rng + ?Sized>(rng: &mut R) -> Prayer {
+    let stats: Vec<StatType> = StatType::iter().collect();
+    let stat = stats[rng.gen_range(0..stats.len())];
+    if rng.gen_bool(CURSE_CHANCE) {
+        Prayer::Curse(stat)
+    } else if rng.gen_bool(BOON_CHANCE) {
+        Prayer::Boon(stat)
+    } else {
+        Prayer::Healed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_prayers_mostly_bless_and_sometimes_curse() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let tries = 4000;
+        let answers: Vec<Prayer> = (0..tries).map(|_| pray(&mut rng)).collect();
+        let curses = answers
+            .iter()
+            .filter(|answer| matches!(answer, Prayer::Curse(_)))
+            .count();
+        let rate = curses as f64 / tries as f64;
+        assert!((rate - CURSE_CHANCE).abs() < 0.03, "{rate}");
+        assert!(answers.contains(&Prayer::Healed));
+        for stat in StatType::iter() {
+            assert!(answers.contains(&Prayer::Boon(stat)), "{stat}");
+        }
+    }
+}
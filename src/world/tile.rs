@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+use crate::world::{MapFeature, TileAppearance};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TileType {
     Wall,
@@ -10,6 +12,16 @@ pub enum TileType {
     StairsUp,
     Chest,
     Exit,
+    /// Where a character from an earlier run lies buried
+    Grave,
+    /// Muffles footsteps, but wading costs the player their footing
+    ShallowWater,
+    /// Burns whoever stands in it; enemies keep out
+    Lava,
+    /// Takes an extra turn to clamber over
+    Rubble,
+    /// Stands in a shrine; praying at it answers once
+    Altar,
 }
 
 impl TileType {
@@ -20,20 +32,47 @@ impl TileType {
             | TileType::StairsDown
             | TileType::StairsUp
             | TileType::Chest
-            | TileType::Exit => true,
-            TileType::Wall => false,
+            | TileType::Exit
+            | TileType::ShallowWater
+            | TileType::Lava
+            | TileType::Rubble => true,
+            TileType::Wall | TileType::Grave | TileType::Altar => false,
         }
     }
 
-    pub fn symbol(&self) -> char {
+    /// Human-readable name used by the look command
+    pub fn name(&self) -> &'static str {
         match self {
-            TileType::Wall => '#',
-            TileType::Floor => '.',
-            TileType::Door => '+',
-            TileType::StairsDown => '>',
-            TileType::StairsUp => '<',
-            TileType::Chest => 'C',
-            TileType::Exit => 'E',
+            TileType::Wall => "Wall",
+            TileType::Floor => "Floor",
+            TileType::Door => "Door",
+            TileType::StairsDown => "Stairs down",
+            TileType::StairsUp => "Stairs up",
+            TileType::Chest => "Chest",
+            TileType::Exit => "Dungeon exit",
+            TileType::Grave => "Grave",
+            TileType::ShallowWater => "Shallow water",
+            TileType::Lava => "Lava",
+            TileType::Rubble => "Rubble",
+            TileType::Altar => "Altar",
+        }
+    }
+
+    /// Whether standing here hurts; enemies never step in
+    pub fn is_hazardous(&self) -> bool {
+        *self == TileType::Lava
+    }
+
+    /// Whether nothing can be seen or shot through it
+    pub fn blocks_sight(&self) -> bool {
+        *self == TileType::Wall
+    }
+
+    /// Turns it takes to step onto the tile
+    pub fn move_cost(&self) -> u32 {
+        match self {
+            TileType::Rubble => 2,
+            _ => 1,
         }
     }
 }
@@ -82,24 +121,32 @@ impl Tile {
         Tile::new(TileType::Exit)
     }
 
+    pub fn grave() -> Self {
+        Tile::new(TileType::Grave)
+    }
+
+    pub fn shallow_water() -> Self {
+        Tile::new(TileType::ShallowWater)
+    }
+
+    pub fn lava() -> Self {
+        Tile::new(TileType::Lava)
+    }
+
+    pub fn rubble() -> Self {
+        Tile::new(TileType::Rubble)
+    }
+
+    pub fn altar() -> Self {
+        Tile::new(TileType::Altar)
+    }
+
+    /// The tile's ASCII glyph, blank until it has been explored
     pub fn render(&self) -> char {
         if !self.explored {
             return ' ';
         }
-
-        if self.visible {
-            self.tile_type.symbol()
-        } else {
-            match self.tile_type {
-                TileType::Wall => '#',
-                TileType::Floor => '.',
-                TileType::Door => '+',
-                TileType::StairsDown => '>',
-                TileType::StairsUp => '<',
-                TileType::Chest => 'C',
-                TileType::Exit => 'E',
-            }
-        }
+        TileAppearance::of(MapFeature::Tile(self.tile_type)).ascii
     }
 }
 
@@ -0,0 +1,288 @@
+//! Weather and other happenings that come and go on a level
+//!
+//! Every turn there is a small chance of something happening, and what it
+//! is depends on the kind of dungeon: rain in forests, rockslides in the
+//! mountains, tremors in caverns and whispers in the ruins. Rain and
+//! tremors last a while and are kept with the level; the others are over
+//! at once. Players who would rather do without them can turn them off in
+//! the options.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::world::{DungeonType, Level, Position, TileType};
+
+/// Chance each turn that something happens on the player's level
+pub const AMBIENT_EVENT_CHANCE: f64 = 0.01;
+/// Turns a shower of rain lasts
+pub const RAIN_TURNS: u32 = 30;
+/// Tiles of sight the rain takes away
+pub const RAIN_SIGHT_PENALTY: i32 = 2;
+/// Turns the ground shakes for after a tremor
+pub const TREMOR_TURNS: u32 = 3;
+/// How far from the player a tremor wakes enemies, in tiles
+pub const TREMOR_WAKE_RADIUS: i32 = 10;
+/// Closest to the player a rockslide comes down, in tiles
+pub const ROCKSLIDE_MIN_DISTANCE: i32 = 8;
+
+/// What the ruins whisper to the player
+pub const WHISPERS: [&str; 5] = [
+    "A voice whispers a name you almost recognize.",
+    "Faint chanting drifts through the stones, then stops.",
+    "Someone breathes \"turn back\" right behind you. No one is there.",
+    "The dust stirs, as if someone had just walked past.",
+    "Distant laughter echoes from somewhere below.",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AmbientEvent {
+    /// Shortens sight and muffles the player's noise
+    Rain,
+    /// Blocks a corridor away from the player
+    Rockslide,
+    /// Shakes the screen and wakes nearby enemies
+    Tremor,
+    /// Only a message
+    Whispers,
+}
+
+impl AmbientEvent {
+    /// What happens in a dungeon of `dungeon_type`
+    pub fn for_dungeon(dungeon_type: DungeonType) -> Self {
+        match dungeon_type {
+            DungeonType::Forest => AmbientEvent::Rain,
+            DungeonType::Mountain => AmbientEvent::Rockslide,
+            DungeonType::Cavern => AmbientEvent::Tremor,
+            DungeonType::Ruins => AmbientEvent::Whispers,
+        }
+    }
+
+    /// Turns the event goes on for; 0 for one that's over at once
+    pub fn duration(self) -> u32 {
+        match self {
+            AmbientEvent::Rain => RAIN_TURNS,
+            AmbientEvent::Tremor => TREMOR_TURNS,
+            AmbientEvent::Rockslide | AmbientEvent::Whispers => 0,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AmbientEvent::Rain => "Rain",
+            AmbientEvent::Rockslide => "Rockslide",
+            AmbientEvent::Tremor => "Tremors",
+            AmbientEvent::Whispers => "Whispers",
+        }
+    }
+
+    /// Logged when the event is over
+    fn ending_message(self) -> &'static str {
+        match self {
+            AmbientEvent::Rain => "The rain stops.",
+            AmbientEvent::Tremor => "The ground settles.",
+            AmbientEvent::Rockslide | AmbientEvent::Whispers => "",
+        }
+    }
+}
+
+/// An event still going on on a level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AmbientEffect {
+    pub event: AmbientEvent,
+    /// Turns left, counting the current one
+    pub turns: u32,
+}
+
+impl AmbientEffect {
+    /// "Rain (12 turns)", shown in the side panel
+    pub fn label(&self) -> String {
+        match self.turns {
+            1 => format!("{} (1 turn)", self.event.name()),
+            turns => format!("{} ({turns} turns)", self.event.name()),
+        }
+    }
+}
+
+/// Starts `event` on `level`, or starts it over if it's already going on.
+/// Events that are over at once leave nothing behind.
+pub fn start_effect(level: &mut Level, event: AmbientEvent) {
+    let turns = event.duration();
+    if turns == 0 {
+        return;
+    }
+    match level.ambient_effects.iter_mut().find(|e| e.event == event) {
+        Some(effect) => effect.turns = effect.turns.max(turns),
+        None => level.ambient_effects.push(AmbientEffect { event, turns }),
+    }
+}
+
+/// Counts the level's effects down a turn, returning a message for each
+/// one that ends
+pub fn tick_effects(level: &mut Level) -> Vec<String> {
+    let mut ended = Vec::new();
+    level.ambient_effects.retain_mut(|effect| {
+        effect.turns = effect.turns.saturating_sub(1);
+        if effect.turns == 0 {
+            ended.push(effect.event.ending_message().to_string());
+        }
+        effect.turns > 0
+    });
+    ended
+}
+
+pub fn has_effect(level: &Level, event: AmbientEvent) -> bool {
+    level
+        .ambient_effects
+        .iter()
+        .any(|effect| effect.event == event)
+}
+
+/// Tiles of sight the level's weather takes away
+pub fn sight_penalty(level: &Level) -> i32 {
+    if has_effect(level, AmbientEvent::Rain) {
+        RAIN_SIGHT_PENALTY
+    } else {
+        0
+    }
+}
+
+/// Columns the map is pushed right by while the ground shakes; it jumps
+/// back and forth from one turn to the next
+pub fn shake_offset(level: &Level) -> usize {
+    level
+        .ambient_effects
+        .iter()
+        .find(|effect| effect.event == AmbientEvent::Tremor)
+        .map_or(0, |effect| (effect.turns % 2) as usize)
+}
+
+/// Brings down rocks on a random corridor tile well away from the player.
+/// The tile becomes a wall unless that would cut the player off from
+/// somewhere they could reach, in which case it becomes rubble they can
+/// climb over. Returns where the rocks fell, if anywhere could take them.
+pub fn rockslide<R: Rng + ?Sized>(level: &mut Level, rng: &mut R) -> Option<Position> {
+    let player = level.player_position;
+    let mut corridors = Vec::new();
+    for (y, row) in level.tiles.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            let pos = Position::new(x as i32, y as i32);
+            let far =
+                (pos.x - player.x).abs().max((pos.y - player.y).abs()) >= ROCKSLIDE_MIN_DISTANCE;
+            let in_room = level.rooms.iter().any(|room| {
+                (room.x1..=room.x2).contains(&pos.x) && (room.y1..=room.y2).contains(&pos.y)
+            });
+            let occupied = level.enemies.contains_key(&pos)
+                || level.items.contains_key(&pos)
+                || level.gold.contains_key(&pos)
+                || level.companion_position == Some(pos);
+            if tile.tile_type == TileType::Floor && far && !in_room && !occupied {
+                corridors.push(pos);
+            }
+        }
+    }
+    let pos = *corridors.choose(rng)?;
+
+    let reachable = level.reachable_from(player).len();
+    let (x, y) = (pos.x as usize, pos.y as usize);
+    level.tiles[y][x].tile_type = TileType::Wall;
+    // Only the tile itself may drop out of reach
+    if level.reachable_from(player).len() + 1 < reachable {
+        level.tiles[y][x].tile_type = TileType::Rubble;
+    }
+    Some(pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::level::Room;
+    use crate::world::Tile;
+    use rand::rngs::mock::StepRng;
+
+    /// Two rooms joined by a corridor along row 5, and by a second one
+    /// along row 15 if `looped`
+    fn corridor_level(looped: bool) -> Level {
+        let mut level = Level::new(40, 20);
+        let mut carve = |x: i32, y: i32| level.tiles[y as usize][x as usize] = Tile::floor();
+        for y in 2..=17 {
+            for x in 2..=5 {
+                carve(x, y);
+                carve(x + 32, y);
+            }
+        }
+        for x in 6..=33 {
+            carve(x, 5);
+            if looped {
+                carve(x, 15);
+            }
+        }
+        level.rooms = vec![Room::new(1, 1, 5, 17), Room::new(33, 1, 5, 17)];
+        level.player_position = Position::new(3, 3);
+        level
+    }
+
+    #[test]
+    fn test_effects_last_their_turns_and_say_when_they_end() {
+        let mut level = Level::new(10, 10);
+        start_effect(&mut level, AmbientEvent::Whispers);
+        assert!(level.ambient_effects.is_empty());
+
+        start_effect(&mut level, AmbientEvent::Tremor);
+        assert_eq!(level.ambient_effects[0].label(), "Tremors (3 turns)");
+        assert_eq!(shake_offset(&level), 1);
+        assert!(tick_effects(&mut level).is_empty());
+        assert_eq!(shake_offset(&level), 0);
+        tick_effects(&mut level);
+        assert_eq!(tick_effects(&mut level), ["The ground settles."]);
+        assert_eq!(shake_offset(&level), 0);
+        assert!(level.ambient_effects.is_empty());
+
+        // Rain starting over while it falls keeps one shower going
+        start_effect(&mut level, AmbientEvent::Rain);
+        tick_effects(&mut level);
+        start_effect(&mut level, AmbientEvent::Rain);
+        assert_eq!(
+            level.ambient_effects,
+            [AmbientEffect {
+                event: AmbientEvent::Rain,
+                turns: RAIN_TURNS
+            }]
+        );
+        assert_eq!(sight_penalty(&level), RAIN_SIGHT_PENALTY);
+    }
+
+    #[test]
+    fn test_rockslides_never_seal_the_only_way_through() {
+        // The corridor is the only way to the far room, so the rocks leave
+        // rubble to climb over
+        let mut level = corridor_level(false);
+        let reachable = level.reachable_from(level.player_position).len();
+        let pos = rockslide(&mut level, &mut StepRng::new(0, 0)).unwrap();
+        assert_eq!(pos.y, 5);
+        assert!(pos.x - level.player_position.x >= ROCKSLIDE_MIN_DISTANCE);
+        assert_eq!(level.tiles[5][pos.x as usize].tile_type, TileType::Rubble);
+        assert_eq!(level.reachable_from(level.player_position).len(), reachable);
+
+        // With a second way round, the corridor is walled off
+        let mut level = corridor_level(true);
+        let pos = rockslide(&mut level, &mut StepRng::new(0, 0)).unwrap();
+        assert_eq!(
+            level.tiles[pos.y as usize][pos.x as usize].tile_type,
+            TileType::Wall
+        );
+
+        // Nowhere far enough from the player, nothing falls
+        let mut level = corridor_level(false);
+        level.player_position = Position::new(20, 5);
+        level.rooms.clear();
+        for x in 0..40 {
+            for y in 0..20 {
+                if (x - 20i32).abs() >= ROCKSLIDE_MIN_DISTANCE {
+                    level.tiles[y][x as usize] = Tile::wall();
+                }
+            }
+        }
+        assert!(rockslide(&mut level, &mut StepRng::new(0, 0)).is_none());
+    }
+}
@@ -53,7 +53,7 @@ def main():
         print(f"📁 Serving from dist directory")
     else:
         print("❌ Error: No built files found!")
-        print("   Please run 'wasm-pack build --target web --out-dir pkg --no-typescript' first")
+        print("   Please run 'wasm-pack build --target web --out-dir pkg' first")
         print("   Then create a deployment directory with index.html and pkg/")
         sys.exit(1)
 